@@ -1,8 +1,30 @@
+pub mod account_deletion_service;
+pub mod data_export_service;
+pub mod login_attempt_service;
+pub mod mfa_service;
+pub mod password_service;
+pub mod personal_access_token_service;
+pub mod profile_icon_service;
+pub mod refresh_token_service;
+pub mod security_event_service;
 pub mod user_service;
 
 pub trait AuthService {}
 
 pub trait UserService: Send + Sync {
+    fn find_by_id(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::user::User>> + Send + '_>>;
+
+    fn find_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::user::User>> + Send + '_>>;
+
+    /// 認証基盤に問い合わせる前に、登録済みのメールアドレスかを確認します
+    fn exists_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<bool>> + Send + '_>>;
+
     fn create_user(
         &self, user: domain::user::User,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
@@ -10,4 +32,355 @@ pub trait UserService: Send + Sync {
     fn update(
         &self, user: domain::user::User,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// 登録されないまま一定期間が過ぎると削除されるゲストユーザーを、保存せずに作成します
+    ///
+    /// 認証基盤に登録してから`create_user`で保存します
+    fn new_guest(&self, name: domain::user::name::Name) -> domain::user::User;
+
+    /// ユーザーのロールを変更し、変更後のユーザーを返します
+    fn change_role(
+        &self, user_id: &domain::user::user_id::UserId, role: domain::user::role::Role,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::user::User>> + Send + '_>>;
+}
+
+/// ローテーションする前に確認したリフレッシュトークンです
+#[derive(Debug, Clone)]
+pub struct VerifiedRefreshToken {
+    pub user_id: domain::user::user_id::UserId,
+    /// リフレッシュトークンの系列のIDで、`revoke_session`に渡すセッションのIDです
+    pub session_id: String,
+    /// 認証基盤のリフレッシュトークンです
+    pub upstream_refresh_token: String,
+}
+
+/// リフレッシュトークンのローテーション結果です
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub user_id: domain::user::user_id::UserId,
+    /// 認証基盤のリフレッシュトークンです
+    pub upstream_refresh_token: String,
+    /// クライアントに返す新しいリフレッシュトークンです
+    pub refresh_token: String,
+}
+
+pub trait RefreshTokenService: Send + Sync {
+    /// 新しいトークン系列を作成し、クライアントに返すリフレッシュトークンを返します
+    ///
+    /// 系列はログイン中のセッションとして`device`とともに記録します
+    fn issue(
+        &self, user_id: &domain::user::user_id::UserId, upstream_refresh_token: &str,
+        device: domain::refresh_token::Device,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<String>> + Send + '_>>;
+
+    /// トークンを使用済みにせずに確認し、ユーザーと認証基盤のリフレッシュトークンを返します
+    ///
+    /// 使用済みのトークンが提示された場合は系列全体を無効化します
+    fn verify(
+        &self, refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<VerifiedRefreshToken>> + Send + '_>>;
+
+    /// トークンを使用済みにして新しいトークンを発行します
+    ///
+    /// 認証基盤での更新が済んでから呼び出し、更新後の`upstream_refresh_token`を保存します
+    /// 使用済みのトークンが提示された場合は系列全体を無効化します
+    /// セッションの最終利用日時と接続元の`ip`を更新します
+    fn rotate(
+        &self, refresh_token: &str, ip: &str, upstream_refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Rotation>> + Send + '_>>;
+
+    /// ログイン中のセッションを最終利用日時の新しい順に返します
+    fn list_sessions(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = anyhow::Result<Vec<domain::refresh_token::RefreshTokenFamily>>>
+                + Send
+                + '_,
+        >,
+    >;
+
+    /// ユーザーのセッションを無効化します
+    ///
+    /// 他のユーザーのセッションや無効化済みのセッションは`RefreshTokenError::SessionNotFound`を返します
+    fn revoke_session(
+        &self, user_id: &domain::user::user_id::UserId, session_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// ユーザーのすべてのセッションを無効化し、無効化した数を返します
+    fn revoke_all_sessions(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>>;
+}
+
+pub trait LoginAttemptService: Send + Sync {
+    /// メールアドレスまたは接続元がロック中の場合、`AuthUserError::AccountLocked`を返します
+    fn check(
+        &self, email: &domain::user::email::Email, ip: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// 認証の失敗をメールアドレスと接続元ごとに記録します
+    fn record_failure(
+        &self, email: &domain::user::email::Email, ip: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// 認証に成功したメールアドレスの失敗の記録を消去します
+    fn clear(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+}
+
+/// 多要素認証の登録内容です
+#[derive(Debug, Clone)]
+pub struct MfaEnrollment {
+    /// 認証アプリに手入力するためのBase32のシークレットです
+    pub secret: String,
+    /// 認証アプリがQRコードから読み込む`otpauth://`形式のURIです
+    pub otpauth_uri: String,
+}
+
+/// 多要素認証の確認を待っているログインです
+#[derive(Debug)]
+pub struct MfaChallenge {
+    /// チャレンジトークンごとに異なる値です。使用済みのチャレンジトークンの記録に使います
+    pub nonce: String,
+    pub user_id: domain::user::user_id::UserId,
+    /// チャレンジトークンの有効期限です
+    pub expires_at: i64,
+    /// 確認後にクライアントへ返す認証基盤のトークンです
+    pub token: domain::token::Token,
+}
+
+pub trait MfaService: Send + Sync {
+    /// シークレットを生成し、有効化前の設定として保存します
+    ///
+    /// 有効化前に再度呼び出した場合はシークレットを作り直します
+    fn enroll(
+        &self, user_id: &domain::user::user_id::UserId, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<MfaEnrollment>> + Send + '_>>;
+
+    /// 認証アプリのコードを確認して有効にし、リカバリーコードを返します
+    ///
+    /// リカバリーコードはハッシュのみを保存するため、平文を取得できるのはこの時だけです
+    fn activate(
+        &self, user_id: &domain::user::user_id::UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<String>>> + Send + '_>>;
+
+    fn is_enabled(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<bool>> + Send + '_>>;
+
+    /// 認証アプリのコードまたはリカバリーコードを確認し、使用済みにします
+    fn verify(
+        &self, user_id: &domain::user::user_id::UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// コードを確認して多要素認証を無効にします
+    fn disable(
+        &self, user_id: &domain::user::user_id::UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// パスワード認証に成功したログインを暗号化し、クライアントに渡すチャレンジトークンを返します
+    fn issue_challenge(
+        &self, user_id: &domain::user::user_id::UserId, token: &domain::token::Token,
+    ) -> anyhow::Result<String>;
+
+    /// チャレンジトークンを復号します。改ざんされている場合や有効期限を過ぎている場合はエラーを返します
+    fn open_challenge(&self, challenge_token: &str) -> anyhow::Result<MfaChallenge>;
+}
+
+/// 発行した個人用アクセストークンです
+#[derive(Debug, Clone)]
+pub struct IssuedPersonalAccessToken {
+    pub token: domain::personal_access_token::PersonalAccessToken,
+    /// クライアントに渡すトークンです。ハッシュのみを保存するため、取得できるのは発行時だけです
+    pub value: String,
+}
+
+pub trait PersonalAccessTokenService: Send + Sync {
+    /// `scopes`の操作を`ttl`の間だけ許可するトークンを発行します
+    fn issue(
+        &self, user_id: &domain::user::user_id::UserId, name: &str, scopes: Vec<domain::personal_access_token::Scope>,
+        ttl: std::time::Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<IssuedPersonalAccessToken>> + Send + '_>>;
+
+    fn list(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = anyhow::Result<Vec<domain::personal_access_token::PersonalAccessToken>>>
+                + Send
+                + '_,
+        >,
+    >;
+
+    fn revoke(
+        &self, user_id: &domain::user::user_id::UserId, token_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// トークンを検証し、保存されている内容を返します
+    ///
+    /// 存在しない、失効した、または有効期限を過ぎたトークンはエラーを返します
+    fn authenticate(
+        &self, value: &str,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = anyhow::Result<domain::personal_access_token::PersonalAccessToken>>
+                + Send
+                + '_,
+        >,
+    >;
+}
+
+/// 個人用アクセストークンの接頭辞です
+///
+/// 認証ヘッダーのトークンがJWTか個人用アクセストークンかを見分けるために使います
+pub const PERSONAL_ACCESS_TOKEN_PREFIX: &str = "pat_";
+
+pub trait PasswordService: Send + Sync {
+    /// パスワードポリシーと漏洩パスワードのリストで検証し、満たしていない規則をすべて
+    /// `PasswordError::PolicyViolation`で返します
+    fn validate(
+        &self, password: &str, email: &domain::user::email::Email, name: Option<&domain::user::name::Name>,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<domain::user::password::Password>> + Send + '_>,
+    >;
+}
+
+pub trait AccountDeletionService: Send + Sync {
+    /// 猶予期間が過ぎた後にアカウントを削除する依頼を作成します
+    ///
+    /// 完了もキャンセルもしていない依頼がある場合は`AccountDeletionError::AlreadyRequested`を返します
+    fn request(
+        &self, user_id: &domain::user::user_id::UserId, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<domain::account_deletion::AccountDeletion>> + Send + '_>,
+    >;
+
+    /// 完了もキャンセルもしていない依頼を返します
+    fn find_active(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = anyhow::Result<Option<domain::account_deletion::AccountDeletion>>>
+                + Send
+                + '_,
+        >,
+    >;
+
+    /// 猶予期間中の依頼をキャンセルします
+    fn cancel(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<domain::account_deletion::AccountDeletion>> + Send + '_>,
+    >;
+
+    /// 完了した依頼を削除証明として返します。完了していない場合は`AccountDeletionError::NotFound`を返します
+    fn find_receipt(
+        &self, deletion_id: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<domain::account_deletion::AccountDeletion>> + Send + '_>,
+    >;
+
+    /// 猶予期間が過ぎた依頼の削除を開始または再開し、完了した数を返します
+    ///
+    /// 1件の削除に失敗しても残りの依頼を続けます。失敗した依頼は次回の実行で再開します
+    fn run_due(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>>;
+}
+
+/// データのエクスポートのダウンロード用トークンです
+#[derive(Debug, Clone)]
+pub struct IssuedDownloadToken {
+    /// クライアントに渡すトークンです。ハッシュのみを保存するため、取得できるのは発行時だけです
+    pub value: domain::secret::SecretString,
+    pub expires_at: i64,
+}
+
+/// エクスポートしたZIPファイルです
+pub struct ExportFile {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+impl std::fmt::Debug for ExportFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExportFile").field("file_name", &self.file_name).field("size", &self.content.len()).finish()
+    }
+}
+
+pub trait DataExportService: Send + Sync {
+    /// データのエクスポートを依頼します。ファイルはバックグラウンドのジョブで作成します
+    ///
+    /// 完了も失敗もしていない依頼がある場合は`DataExportError::AlreadyRequested`を返します
+    fn request(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::data_export::DataExport>> + Send + '_>>;
+
+    /// ユーザーのエクスポートを依頼日時の新しい順に返します
+    fn list(
+        &self, user_id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<Vec<domain::data_export::DataExport>>> + Send + '_>,
+    >;
+
+    /// 他のユーザーのエクスポートは`DataExportError::NotFound`を返します
+    fn find(
+        &self, user_id: &domain::user::user_id::UserId, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::data_export::DataExport>> + Send + '_>>;
+
+    /// 完了したエクスポートのダウンロード用トークンを発行します。発行済みのトークンは使えなくなります
+    fn issue_download_token(
+        &self, user_id: &domain::user::user_id::UserId, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<IssuedDownloadToken>> + Send + '_>>;
+
+    /// ダウンロード用トークンを検証し、ファイルを返します
+    fn download(
+        &self, token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<ExportFile>> + Send + '_>>;
+
+    /// 完了も失敗もしていないエクスポートのファイルを作成し、完了した数を返します
+    ///
+    /// 失敗したエクスポートは`failed`として記録し、残りの依頼を続けます
+    fn run_pending(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>>;
+}
+
+/// 監査ログの検索条件です
+///
+/// `user_id`と`event_type`の少なくとも一方が必要です
+#[derive(Debug, Clone, Default)]
+pub struct SecurityEventQuery {
+    pub user_id: Option<domain::user::user_id::UserId>,
+    pub event_type: Option<domain::security_event::SecurityEventType>,
+    /// 指定しない場合は期間の下限を設けません
+    pub from: Option<i64>,
+    /// 指定しない場合は現在までのイベントを返します
+    pub to: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+pub trait SecurityEventService: Send + Sync {
+    /// イベントを監査ログに追記します
+    fn record(
+        &self, event: domain::security_event::SecurityEvent,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>>;
+
+    /// 条件に合うイベントを発生日時の新しい順に返します
+    ///
+    /// 期間が不正な場合は`SecurityEventError::InvalidTimeRange`を、
+    /// `user_id`と`event_type`がどちらもない場合は`SecurityEventError::FilterRequired`を返します
+    fn search(
+        &self, query: SecurityEventQuery,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = anyhow::Result<Vec<domain::security_event::SecurityEvent>>> + Send + '_>,
+    >;
+}
+
+pub trait ProfileIconService: Send + Sync {
+    /// アップロードされた画像からサムネイルを作成して保存し、`profile_icon_path`を更新したユーザーを返します
+    ///
+    /// 対応していない形式の場合は`ProfileIconError::UnsupportedFormat`を返します
+    /// 以前のアイコンは更新後に削除します
+    fn upload(
+        &self, user_id: &domain::user::user_id::UserId, content: Vec<u8>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<domain::user::User>> + Send + '_>>;
 }
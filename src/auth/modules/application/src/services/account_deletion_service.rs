@@ -0,0 +1,623 @@
+use std::sync::Arc;
+
+use domain::{
+    account_deletion::{AccountDeletion, AccountDeletionError, DeletionStatus},
+    clock::{Clock, SystemClock},
+    data_export::DataExport,
+    profile_icon::ProfileIcon,
+    repository::{
+        account_deletion_repository::AccountDeletionRepository, auth_user_repository::AuthUserRepository,
+        data_export_repository::DataExportRepository, external_identity_repository::ExternalIdentityRepository,
+        mfa_repository::MfaRepository, object_storage::ObjectStorage,
+        personal_access_token_repository::PersonalAccessTokenRepository,
+        refresh_token_repository::RefreshTokenRepository, user_data_repository::UserDataRepository,
+        user_repository::UserRepository,
+    },
+    user::{email::Email, user_error::UserError, user_id::UserId},
+};
+
+use super::AccountDeletionService;
+
+/// 削除証明に記録する、認証基盤の削除の対象の名前です
+pub const AUTH_TARGET: &str = "auth";
+/// 削除証明に記録する、リフレッシュトークンの系列の削除の対象の名前です
+pub const SESSION_TARGET: &str = "session";
+/// 削除証明に記録する、個人用アクセストークンの削除の対象の名前です
+pub const PERSONAL_ACCESS_TOKEN_TARGET: &str = "personal_access_token";
+/// 削除証明に記録する、2段階認証の設定の削除の対象の名前です
+pub const MFA_TARGET: &str = "mfa";
+/// 削除証明に記録する、IDプロバイダーのアカウントとの紐付けの削除の対象の名前です
+pub const EXTERNAL_IDENTITY_TARGET: &str = "external_identity";
+/// 削除証明に記録する、プロフィールアイコンのファイルの削除の対象の名前です
+pub const PROFILE_ICON_TARGET: &str = "profile_icon";
+/// 削除証明に記録する、エクスポートしたファイルの削除の対象の名前です
+pub const EXPORT_FILE_TARGET: &str = "export_file";
+/// 削除証明に記録する、エクスポートの依頼の削除の対象の名前です
+pub const DATA_EXPORT_TARGET: &str = "data_export";
+/// 削除証明に記録する、ユーザー情報の削除の対象の名前です
+pub const USER_TARGET: &str = "user";
+
+/// 1件の削除を実行する間、他のプロセスに同じ依頼を実行させない秒数です
+///
+/// 実行中のプロセスが停止した場合は、この時間が過ぎてから他のプロセスが再開します
+const LEASE_SECONDS: i64 = 60 * 60;
+
+pub struct AccountDeletionServiceImpl {
+    repository: Arc<dyn AccountDeletionRepository>,
+    auth_user_repository: Arc<dyn AuthUserRepository>,
+    refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+    personal_access_token_repository: Arc<dyn PersonalAccessTokenRepository>,
+    mfa_repository: Arc<dyn MfaRepository>,
+    external_identity_repository: Arc<dyn ExternalIdentityRepository>,
+    data_export_repository: Arc<dyn DataExportRepository>,
+    user_data_repositories: Vec<Arc<dyn UserDataRepository>>,
+    object_storage: Arc<dyn ObjectStorage>,
+    user_repository: Arc<dyn UserRepository>,
+    grace_period: std::time::Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl AccountDeletionServiceImpl {
+    /// `grace_period`は依頼してから削除を開始するまでの、キャンセルできる期間です
+    ///
+    /// 削除は引数の順に、認証基盤、トークンや紐付け、エクスポートの依頼、`user_data_repositories`、`object_storage`のファイル、ユーザー情報の順に行います
+    /// ユーザー情報を最後に削除するため、途中で失敗しても依頼したユーザーを特定できます
+    ///
+    /// 監査ログは不正なアクセスの調査に使うため削除しません。追記のみの記録で、ユーザー情報を削除した後は利用者に表示しません
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        repository: Arc<dyn AccountDeletionRepository>, auth_user_repository: Arc<dyn AuthUserRepository>,
+        refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+        personal_access_token_repository: Arc<dyn PersonalAccessTokenRepository>,
+        mfa_repository: Arc<dyn MfaRepository>, external_identity_repository: Arc<dyn ExternalIdentityRepository>,
+        data_export_repository: Arc<dyn DataExportRepository>,
+        user_data_repositories: Vec<Arc<dyn UserDataRepository>>, object_storage: Arc<dyn ObjectStorage>,
+        user_repository: Arc<dyn UserRepository>, grace_period: std::time::Duration,
+    ) -> Self {
+        Self {
+            repository,
+            auth_user_repository,
+            refresh_token_repository,
+            personal_access_token_repository,
+            mfa_repository,
+            external_identity_repository,
+            data_export_repository,
+            user_data_repositories,
+            object_storage,
+            user_repository,
+            grace_period,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 削除の予定日時と実行の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn find_active_deletion(&self, user_id: &UserId) -> anyhow::Result<Option<AccountDeletion>> {
+        let deletions = self.repository.find_by_user_id(user_id).await?;
+        Ok(deletions.into_iter().find(AccountDeletion::is_active))
+    }
+
+    /// ユーザー情報の現在のメールアドレスを返します。ユーザー情報がない場合は依頼時のメールアドレスを返します
+    async fn current_email(&self, deletion: &AccountDeletion) -> anyhow::Result<Email> {
+        match self.user_repository.find_by_id(deletion.user_id()).await {
+            Ok(user) => Ok(user.email().clone()),
+            Err(UserError::NotFound(_)) => Ok(deletion.email().cloned().ok_or_else(|| {
+                AccountDeletionError::DeleteError(format!("email is missing: {}", deletion.deletion_id()))
+            })?),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 削除済みでない場合は`delete`で削除し、削除した数を記録して進捗を保存します
+    async fn delete_target(
+        &self, deletion: AccountDeletion, target: &str,
+        delete: impl std::future::Future<Output = anyhow::Result<usize>>,
+    ) -> anyhow::Result<AccountDeletion> {
+        if deletion.is_deleted(target) {
+            return Ok(deletion);
+        }
+        let deletion = deletion.record(target, delete.await?);
+        self.repository.save(deletion.clone()).await?;
+        Ok(deletion)
+    }
+
+    async fn release(&self, deletion_id: &str) -> anyhow::Result<()> {
+        let deletion = self.repository.find(deletion_id).await?;
+        self.repository.save(deletion.release()).await?;
+        Ok(())
+    }
+
+    /// 削除済みの対象を飛ばし、対象を1つ削除するごとに進捗を保存します
+    async fn execute(&self, deletion: AccountDeletion) -> anyhow::Result<()> {
+        let mut deletion = deletion.start();
+        self.repository.save(deletion.clone()).await?;
+        let user_id = deletion.user_id().clone();
+
+        if !deletion.is_deleted(AUTH_TARGET) {
+            self.auth_user_repository.delete_user(self.current_email(&deletion).await?).await?;
+            deletion = deletion.record(AUTH_TARGET, 1);
+            self.repository.save(deletion.clone()).await?;
+        }
+
+        let sessions = async { Ok(self.refresh_token_repository.delete_by_user_id(&user_id).await?) };
+        deletion = self.delete_target(deletion, SESSION_TARGET, sessions).await?;
+        let tokens = async { Ok(self.personal_access_token_repository.delete_by_user_id(&user_id).await?) };
+        deletion = self.delete_target(deletion, PERSONAL_ACCESS_TOKEN_TARGET, tokens).await?;
+        let mfa = async {
+            let found = self.mfa_repository.find(&user_id).await?;
+            self.mfa_repository.delete(&user_id).await?;
+            Ok(usize::from(found.is_some()))
+        };
+        deletion = self.delete_target(deletion, MFA_TARGET, mfa).await?;
+        let identities = async { Ok(self.external_identity_repository.delete_by_user_id(&user_id).await?) };
+        deletion = self.delete_target(deletion, EXTERNAL_IDENTITY_TARGET, identities).await?;
+        let data_exports = async { Ok(self.data_export_repository.delete_by_user_id(&user_id).await?) };
+        deletion = self.delete_target(deletion, DATA_EXPORT_TARGET, data_exports).await?;
+
+        for repository in &self.user_data_repositories {
+            let items = async { Ok(repository.delete_by_user_id(&user_id).await?) };
+            deletion = self.delete_target(deletion, repository.target(), items).await?;
+        }
+
+        let icons = async { Ok(self.object_storage.delete_by_prefix(&ProfileIcon::prefix(&user_id)).await?) };
+        deletion = self.delete_target(deletion, PROFILE_ICON_TARGET, icons).await?;
+        let exports = async { Ok(self.object_storage.delete_by_prefix(&DataExport::prefix(&user_id)).await?) };
+        deletion = self.delete_target(deletion, EXPORT_FILE_TARGET, exports).await?;
+
+        if !deletion.is_deleted(USER_TARGET) {
+            self.user_repository.delete(deletion.user_id()).await?;
+            deletion = deletion.record(USER_TARGET, 1);
+        }
+        self.repository.save(deletion.complete(self.clock.now())).await?;
+        tracing::info!("account deleted. user_id: {}, deletion_id: {}", deletion.user_id(), deletion.deletion_id());
+        Ok(())
+    }
+}
+
+impl AccountDeletionService for AccountDeletionServiceImpl {
+    fn request(
+        &self, user_id: &UserId, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<AccountDeletion>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let email = email.clone();
+
+        Box::pin(async move {
+            if let Some(v) = self.find_active_deletion(&user_id).await? {
+                return Err(AccountDeletionError::AlreadyRequested(v.deletion_id().to_string()).into());
+            }
+
+            let deletion = AccountDeletion::request(
+                uuid::Uuid::new_v4().to_string(),
+                user_id.clone(),
+                email,
+                self.clock.now(),
+                self.grace_period.as_secs() as i64,
+            );
+            self.repository.save(deletion.clone()).await?;
+            tracing::info!("account deletion requested. user_id: {}, deletion_id: {}", user_id, deletion.deletion_id());
+            Ok(deletion)
+        })
+    }
+
+    fn find_active(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Option<AccountDeletion>>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move { self.find_active_deletion(&user_id).await })
+    }
+
+    fn cancel(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<AccountDeletion>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let deletion = self
+                .find_active_deletion(&user_id)
+                .await?
+                .ok_or_else(|| AccountDeletionError::NotFound(user_id.to_string()))?;
+            let cancelled = deletion.cancel(self.clock.now())?;
+            self.repository.save(cancelled.clone()).await?;
+            tracing::info!(
+                "account deletion cancelled. user_id: {}, deletion_id: {}",
+                user_id,
+                cancelled.deletion_id()
+            );
+            Ok(cancelled)
+        })
+    }
+
+    fn find_receipt(
+        &self, deletion_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<AccountDeletion>> + Send + '_>> {
+        let deletion_id = deletion_id.to_string();
+
+        Box::pin(async move {
+            let deletion = self.repository.find(&deletion_id).await?;
+            if deletion.status() != DeletionStatus::Completed {
+                return Err(AccountDeletionError::NotFound(deletion_id).into());
+            }
+            Ok(deletion)
+        })
+    }
+
+    fn run_due(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>> {
+        Box::pin(async move {
+            let mut completed = 0;
+            for deletion in self.repository.find_due(self.clock.now()).await? {
+                let deletion_id = deletion.deletion_id().to_string();
+                let now = self.clock.now();
+                let deletion = match self.repository.claim(&deletion_id, now, now + LEASE_SECONDS).await {
+                    Ok(v) => v,
+                    // 他のプロセスが実行中か、既に完了した依頼です
+                    Err(AccountDeletionError::NotClaimable(_)) => {
+                        tracing::info!("account deletion skipped. deletion_id: {}", deletion_id);
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::error!("failed to claim account deletion. deletion_id: {}, {:?}", deletion_id, e);
+                        continue;
+                    }
+                };
+                match self.execute(deletion).await {
+                    Ok(_) => completed += 1,
+                    Err(e) => {
+                        tracing::error!("failed to delete account. deletion_id: {}, {:?}", deletion_id, e);
+                        // 次の実行で再開できるよう、保存済みの進捗のまま期限を破棄します
+                        if let Err(e) = self.release(&deletion_id).await {
+                            tracing::error!(
+                                "failed to release account deletion. deletion_id: {}, {:?}",
+                                deletion_id,
+                                e
+                            );
+                        }
+                    }
+                }
+            }
+            Ok(completed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, time::Duration};
+
+    use domain::{
+        auth_user::{AuthUser, AuthUserError},
+        clock::FixedClock,
+        data_export::DataExport,
+        external_identity::ExternalIdentity,
+        mfa::Mfa,
+        personal_access_token::PersonalAccessToken,
+        refresh_token::{Device, RefreshTokenFamily},
+        repository::credential_repository::CredentialRepository,
+        user::User,
+        user_data::{UserDataError, UserDataItem},
+    };
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_user_data_repository::InMemoryUserDataRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+
+    use super::*;
+
+    const PRIVATE_KEY: &[u8] = include_bytes!("../../../infrastructure/testdata/jwt/private_key.pem");
+    const PUBLIC_KEY: &[u8] = include_bytes!("../../../infrastructure/testdata/jwt/public_key.pem");
+    const USER_ID: &str = "usr_123e4567-e89b-12d3-a456-426614174000";
+    const EMAIL: &str = "hoge@email.com";
+    const NOW: i64 = 1_700_000_000;
+
+    /// 1回目の削除だけ失敗します
+    #[derive(Default)]
+    struct FlakyUserDataRepository {
+        failed: std::sync::atomic::AtomicBool,
+        inner: InMemoryUserDataRepository,
+    }
+
+    impl UserDataRepository for FlakyUserDataRepository {
+        fn target(&self) -> &str {
+            "category"
+        }
+
+        fn find_by_user_id(
+            &self, user_id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<UserDataItem>, UserDataError>> + Send + '_>>
+        {
+            self.inner.find_by_user_id(user_id)
+        }
+
+        fn delete_by_user_id(
+            &self, user_id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, UserDataError>> + Send + '_>> {
+            if !self.failed.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                return Box::pin(async { Err(UserDataError::DeleteError("unavailable".to_string())) });
+            }
+            self.inner.delete_by_user_id(user_id)
+        }
+    }
+
+    struct Fixture {
+        service: AccountDeletionServiceImpl,
+        repository: Arc<InMemoryAccountDeletionRepository>,
+        credentials: Arc<InMemoryCredentialRepository>,
+        auth_user_repository: Arc<LocalAuthUserRepository>,
+        refresh_token_repository: Arc<InMemoryRefreshTokenRepository>,
+        personal_access_token_repository: Arc<InMemoryPersonalAccessTokenRepository>,
+        mfa_repository: Arc<InMemoryMfaRepository>,
+        external_identity_repository: Arc<InMemoryExternalIdentityRepository>,
+        data_export_repository: Arc<InMemoryDataExportRepository>,
+        subscribe: Arc<InMemoryUserDataRepository>,
+        category: Arc<FlakyUserDataRepository>,
+        object_storage: Arc<LocalObjectStorage>,
+        user_repository: Arc<InMemoryUserRepository>,
+        root: std::path::PathBuf,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    async fn fixture(grace_period: Duration) -> Fixture {
+        let credentials = Arc::new(InMemoryCredentialRepository::new());
+        let tokens = LocalTokenIssuer::from_pem(
+            PRIVATE_KEY,
+            PUBLIC_KEY,
+            "key_id".to_string(),
+            "issuer".to_string(),
+            "audience".to_string(),
+        )
+        .unwrap();
+        let auth_user_repository =
+            Arc::new(LocalAuthUserRepository::new(credentials.clone(), Arc::new(LogCodeSender), tokens));
+        sign_up(&credentials, &auth_user_repository, EMAIL).await;
+
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        user_repository.create(User::build(USER_ID, EMAIL, "hoge", 1, None).unwrap()).await.unwrap();
+        let refresh_token_repository = Arc::new(InMemoryRefreshTokenRepository::new());
+        for i in 1..=2 {
+            let family = RefreshTokenFamily::new(
+                format!("family_{}", i),
+                user_id(),
+                "hash".to_string(),
+                vec![],
+                "upstream".to_string(),
+                false,
+                Device::default(),
+                0,
+                0,
+                i64::MAX,
+            );
+            refresh_token_repository.create(family).await.unwrap();
+        }
+        let personal_access_token_repository = Arc::new(InMemoryPersonalAccessTokenRepository::new());
+        let token = PersonalAccessToken::new(
+            "token_1".to_string(),
+            user_id(),
+            "import".to_string(),
+            vec![],
+            "hash".to_string(),
+            0,
+            i64::MAX,
+        );
+        personal_access_token_repository.save(token).await.unwrap();
+        let mfa_repository = Arc::new(InMemoryMfaRepository::new());
+        mfa_repository.save(Mfa::enroll(user_id(), "secret".to_string())).await.unwrap();
+        let external_identity_repository = Arc::new(InMemoryExternalIdentityRepository::new());
+        let identity = ExternalIdentity::new("google".to_string(), "1234567890".to_string(), user_id(), email(), 0);
+        external_identity_repository.save(identity).await.unwrap();
+        let data_export_repository = Arc::new(InMemoryDataExportRepository::new());
+        let export = DataExport::request("export_1".to_string(), user_id(), 0).start(1).complete("key".to_string(), 0);
+        data_export_repository.save(export).await.unwrap();
+        let subscribe = Arc::new(InMemoryUserDataRepository::new("subscribe"));
+        subscribe.insert(&user_id(), vec![UserDataItem::new(); 3]);
+        let category = Arc::new(FlakyUserDataRepository::default());
+        category.inner.insert(&user_id(), vec![UserDataItem::new(); 2]);
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let object_storage = Arc::new(LocalObjectStorage::new(&root));
+        let keys = vec![
+            ProfileIcon::key(&user_id(), "icon_1", 256),
+            ProfileIcon::key(&user_id(), "icon_1", 64),
+            format!("{}/export_1.zip", DataExport::prefix(&user_id())),
+        ];
+        for key in keys {
+            object_storage.put(&key, b"content".to_vec(), "application/octet-stream").await.unwrap();
+        }
+
+        let repository = Arc::new(InMemoryAccountDeletionRepository::new());
+        let service = AccountDeletionServiceImpl::new(
+            repository.clone(),
+            auth_user_repository.clone(),
+            refresh_token_repository.clone(),
+            personal_access_token_repository.clone(),
+            mfa_repository.clone(),
+            external_identity_repository.clone(),
+            data_export_repository.clone(),
+            vec![
+                subscribe.clone(),
+                category.clone(),
+            ],
+            object_storage.clone(),
+            user_repository.clone(),
+            grace_period,
+        )
+        .with_clock(Arc::new(FixedClock(NOW)));
+        Fixture {
+            service,
+            repository,
+            credentials,
+            auth_user_repository,
+            refresh_token_repository,
+            personal_access_token_repository,
+            mfa_repository,
+            external_identity_repository,
+            data_export_repository,
+            subscribe,
+            category,
+            object_storage,
+            user_repository,
+            root,
+        }
+    }
+
+    async fn sign_up(
+        credentials: &InMemoryCredentialRepository, auth_user_repository: &LocalAuthUserRepository, email: &str,
+    ) {
+        auth_user_repository.sign_up(AuthUser::build(email, "Password123").unwrap()).await.unwrap();
+        let credential = credentials.find_by_email(&Email::from_str(email).unwrap()).await.unwrap();
+        credentials.update(credential.confirm()).await.unwrap();
+    }
+
+    fn user_id() -> UserId {
+        UserId::from_str(USER_ID).unwrap()
+    }
+
+    fn email() -> Email {
+        Email::from_str(EMAIL).unwrap()
+    }
+
+    fn error(e: anyhow::Error) -> AccountDeletionError {
+        e.downcast::<AccountDeletionError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_and_cancel() {
+        let fixture = fixture(Duration::from_secs(60)).await;
+        let service = &fixture.service;
+        assert!(service.find_active(&user_id()).await.unwrap().is_none());
+        assert!(matches!(service.cancel(&user_id()).await.map_err(error), Err(AccountDeletionError::NotFound(_))));
+
+        let deletion = service.request(&user_id(), &email()).await.unwrap();
+        assert_eq!(DeletionStatus::Scheduled, deletion.status());
+        assert_eq!(NOW, deletion.requested_at());
+        assert_eq!(NOW + 60, deletion.scheduled_at());
+        assert_eq!(Some(deletion.clone()), service.find_active(&user_id()).await.unwrap());
+
+        let result = service.request(&user_id(), &email()).await.map_err(error);
+        assert!(matches!(result, Err(AccountDeletionError::AlreadyRequested(_))));
+
+        // 猶予期間中は削除しません
+        assert_eq!(0, service.run_due().await.unwrap());
+
+        let cancelled = service.cancel(&user_id()).await.unwrap();
+        assert_eq!(DeletionStatus::Cancelled, cancelled.status());
+        assert!(service.find_active(&user_id()).await.unwrap().is_none());
+        let result = service.find_receipt(deletion.deletion_id()).await.map_err(error);
+        assert!(matches!(result, Err(AccountDeletionError::NotFound(_))));
+
+        // キャンセルした後は再度依頼できます
+        assert!(service.request(&user_id(), &email()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_due_resumes_and_completes() {
+        let fixture = fixture(Duration::ZERO).await;
+        let service = &fixture.service;
+        let deletion = service.request(&user_id(), &email()).await.unwrap();
+        let result = service.cancel(&user_id()).await.map_err(error);
+        assert!(matches!(result, Err(AccountDeletionError::NotCancellable(_))));
+
+        // カテゴリーの削除に失敗した時点で中断し、それまでの進捗を保存します
+        assert_eq!(0, service.run_due().await.unwrap());
+        let interrupted = fixture.repository.find(deletion.deletion_id()).await.unwrap();
+        assert_eq!(DeletionStatus::InProgress, interrupted.status());
+        assert!(interrupted.is_deleted(AUTH_TARGET));
+        assert!(interrupted.is_deleted("subscribe"));
+        assert!(!interrupted.is_deleted("category"));
+        let result = fixture.auth_user_repository.authenticate(AuthUser::build(EMAIL, "Password123").unwrap()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(fixture.refresh_token_repository.find_by_user_id(&user_id()).await.unwrap().is_empty());
+        assert!(fixture.personal_access_token_repository.find_by_user_id(&user_id()).await.unwrap().is_empty());
+        assert!(fixture.mfa_repository.find(&user_id()).await.unwrap().is_none());
+        assert!(fixture.external_identity_repository.find("google", "1234567890").await.unwrap().is_none());
+        assert!(fixture.data_export_repository.find_by_user_id(&user_id()).await.unwrap().is_empty());
+        assert!(fixture.user_repository.find_by_id(&user_id()).await.is_ok());
+
+        // 再開した場合は削除済みの対象を飛ばします
+        fixture.subscribe.insert(&user_id(), vec![UserDataItem::new(); 1]);
+        assert_eq!(1, service.run_due().await.unwrap());
+        assert_eq!(1, fixture.subscribe.count(&user_id()));
+        assert_eq!(0, fixture.category.inner.count(&user_id()));
+        let result = fixture.object_storage.get(&ProfileIcon::key(&user_id(), "icon_1", 256)).await;
+        assert!(matches!(result, Err(domain::file::FileError::NotFound(_))));
+        assert!(!fixture.root.join(DataExport::prefix(&user_id())).exists());
+        assert!(fixture.user_repository.find_by_id(&user_id()).await.is_err());
+
+        let receipt = service.find_receipt(deletion.deletion_id()).await.unwrap();
+        assert_eq!(DeletionStatus::Completed, receipt.status());
+        assert!(receipt.email().is_none());
+        assert!(receipt.completed_at().is_some());
+        let deleted: Vec<_> = receipt.deleted().iter().map(|v| (v.target(), v.count())).collect();
+        let expected = vec![
+            (AUTH_TARGET, 1),
+            (SESSION_TARGET, 2),
+            (PERSONAL_ACCESS_TOKEN_TARGET, 1),
+            (MFA_TARGET, 1),
+            (EXTERNAL_IDENTITY_TARGET, 1),
+            (DATA_EXPORT_TARGET, 1),
+            ("subscribe", 3),
+            ("category", 2),
+            (PROFILE_ICON_TARGET, 2),
+            (EXPORT_FILE_TARGET, 1),
+            (USER_TARGET, 1),
+        ];
+        assert_eq!(expected, deleted);
+
+        assert_eq!(0, service.run_due().await.unwrap());
+        assert!(service.find_active(&user_id()).await.unwrap().is_none());
+    }
+
+    /// 他のプロセスが実行中の依頼は実行しません
+    #[tokio::test]
+    async fn test_run_due_skips_claimed() {
+        let fixture = fixture(Duration::ZERO).await;
+        let service = &fixture.service;
+        let deletion = service.request(&user_id(), &email()).await.unwrap();
+        fixture.repository.claim(deletion.deletion_id(), NOW, NOW + 60).await.unwrap();
+
+        assert_eq!(0, service.run_due().await.unwrap());
+        let result = fixture.repository.find(deletion.deletion_id()).await.unwrap();
+        assert_eq!(DeletionStatus::Scheduled, result.status());
+        assert!(result.deleted().is_empty());
+        assert!(fixture.user_repository.find_by_id(&user_id()).await.is_ok());
+    }
+
+    /// 猶予期間中にメールアドレスを変更した場合も、変更後のメールアドレスで認証基盤から削除します
+    #[tokio::test]
+    async fn test_run_due_after_email_changed() {
+        let fixture = fixture(Duration::ZERO).await;
+        let service = &fixture.service;
+        let deletion = service.request(&user_id(), &email()).await.unwrap();
+
+        // 変更後のメールアドレスで認証基盤に登録し、ユーザー情報のメールアドレスを変更します
+        sign_up(&fixture.credentials, &fixture.auth_user_repository, "fuga@email.com").await;
+        fixture.credentials.delete(&email()).await.unwrap();
+        let user = fixture.user_repository.find_by_id(&user_id()).await.unwrap();
+        fixture.user_repository.update(user.change_email(Email::from_str("fuga@email.com").unwrap())).await.unwrap();
+
+        service.run_due().await.unwrap();
+        service.run_due().await.unwrap();
+        let result = fixture.credentials.find_by_email(&Email::from_str("fuga@email.com").unwrap()).await;
+        assert!(result.is_err());
+        let receipt = service.find_receipt(deletion.deletion_id()).await.unwrap();
+        assert!(receipt.is_deleted(AUTH_TARGET));
+    }
+}
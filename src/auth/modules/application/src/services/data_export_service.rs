@@ -0,0 +1,533 @@
+use std::{collections::BTreeSet, io::Write, sync::Arc, time::Duration};
+
+use base64::Engine;
+use domain::{
+    clock::{Clock, SystemClock},
+    data_export::{DataExport, DataExportError},
+    file::FileError,
+    repository::{
+        data_export_repository::DataExportRepository, object_storage::ObjectStorage,
+        user_data_repository::UserDataRepository, user_repository::UserRepository,
+    },
+    secret::SecretString,
+    user::{user_id::UserId, User},
+    user_data::{FieldValue, UserDataItem},
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::{DataExportService, ExportFile, IssuedDownloadToken};
+
+/// 進捗に記録する、ユーザー情報の対象の名前です
+pub const PROFILE_TARGET: &str = "profile";
+
+const SECRET_LENGTH: usize = 32;
+
+pub struct DataExportServiceImpl {
+    repository: Arc<dyn DataExportRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    user_data_repositories: Vec<Arc<dyn UserDataRepository>>,
+    object_storage: Arc<dyn ObjectStorage>,
+    download_ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl DataExportServiceImpl {
+    /// `object_storage`にはZIPファイルを保存し、プロフィールアイコンを読み込みます
+    ///
+    /// `download_ttl`はダウンロード用トークンの有効期間です
+    pub fn new(
+        repository: Arc<dyn DataExportRepository>, user_repository: Arc<dyn UserRepository>,
+        user_data_repositories: Vec<Arc<dyn UserDataRepository>>, object_storage: Arc<dyn ObjectStorage>,
+        download_ttl: Duration,
+    ) -> Self {
+        Self {
+            repository,
+            user_repository,
+            user_data_repositories,
+            object_storage,
+            download_ttl,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 依頼と完了の日時、ダウンロード用トークンの有効期限に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn find_own(&self, user_id: &UserId, export_id: &str) -> anyhow::Result<DataExport> {
+        let export = self.repository.find(export_id).await?;
+        if export.user_id() != user_id {
+            return Err(DataExportError::NotFound(export_id.to_string()).into());
+        }
+        Ok(export)
+    }
+
+    /// データの種類を1つ書き出すごとに進捗を保存し、最後にZIPファイルを保存します
+    async fn execute(&self, export: DataExport) -> anyhow::Result<()> {
+        let mut export = export.start(self.user_data_repositories.len() + 1);
+        self.repository.save(export.clone()).await?;
+
+        let mut archive = Archive::new();
+        let user = self.user_repository.find_by_id(export.user_id()).await?;
+        archive.add_profile(&profile(&user))?;
+        if let Some(icon) = self.find_profile_icon(&user).await? {
+            archive.add_file(&icon.file_name, &icon.content)?;
+        }
+        export = export.record(PROFILE_TARGET, 1);
+        self.repository.save(export.clone()).await?;
+
+        for repository in &self.user_data_repositories {
+            let items = repository.find_by_user_id(export.user_id()).await?;
+            archive.add_items(repository.target(), &items)?;
+            export = export.record(repository.target(), items.len());
+            self.repository.save(export.clone()).await?;
+        }
+
+        let file_key = export.archive_key();
+        self.object_storage.put(&file_key, archive.finish()?, "application/zip").await?;
+        self.repository.save(export.complete(file_key, self.clock.now())).await?;
+        tracing::info!("data exported. user_id: {}, export_id: {}", export.user_id(), export.export_id());
+        Ok(())
+    }
+
+    /// 保存先にアイコンがない場合は、エクスポートを止めずにアイコンを含めません
+    async fn find_profile_icon(&self, user: &User) -> anyhow::Result<Option<ExportFile>> {
+        let Some(path) = user.profile_icon_path().as_deref().filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        match self.object_storage.get(path).await {
+            Ok(content) => {
+                let extension = path.rsplit_once('.').map(|(_, v)| v).filter(|v| !v.contains('/'));
+                let file_name = match extension {
+                    Some(v) => format!("profile_icon.{}", v),
+                    None => "profile_icon".to_string(),
+                };
+                Ok(Some(ExportFile { file_name, content }))
+            }
+            Err(FileError::NotFound(_)) | Err(FileError::InvalidKey(_)) => {
+                tracing::warn!("profile icon is not found. user_id: {}, path: {}", user.user_id(), path);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl DataExportService for DataExportServiceImpl {
+    fn request(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<DataExport>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let exports = self.repository.find_by_user_id(&user_id).await?;
+            if let Some(active) = exports.iter().find(|v| v.is_active()) {
+                return Err(DataExportError::AlreadyRequested(active.export_id().to_string()).into());
+            }
+
+            let export = DataExport::request(uuid::Uuid::new_v4().to_string(), user_id.clone(), self.clock.now());
+            self.repository.save(export.clone()).await?;
+            tracing::info!("data export requested. user_id: {}, export_id: {}", user_id, export.export_id());
+            Ok(export)
+        })
+    }
+
+    fn list(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<DataExport>>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let mut exports = self.repository.find_by_user_id(&user_id).await?;
+            exports.reverse();
+            Ok(exports)
+        })
+    }
+
+    fn find(
+        &self, user_id: &UserId, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<DataExport>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let export_id = export_id.to_string();
+
+        Box::pin(async move { self.find_own(&user_id, &export_id).await })
+    }
+
+    fn issue_download_token(
+        &self, user_id: &UserId, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<IssuedDownloadToken>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let export_id = export_id.to_string();
+
+        Box::pin(async move {
+            let export = self.find_own(&user_id, &export_id).await?;
+            let secret = generate_secret();
+            let expires_at = self.clock.now() + self.download_ttl.as_secs() as i64;
+            self.repository.save(export.issue_download_token(hash(&secret), expires_at)?).await?;
+
+            Ok(IssuedDownloadToken { value: SecretString::new(format_token(&export_id, &secret)), expires_at })
+        })
+    }
+
+    fn download(
+        &self, token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<ExportFile>> + Send + '_>> {
+        let token = token.to_string();
+
+        Box::pin(async move {
+            let (export_id, secret) = parse_token(&token)?;
+            let export = self.repository.find(&export_id).await.map_err(|e| match e {
+                DataExportError::NotFound(_) => DataExportError::InvalidToken,
+                e => e,
+            })?;
+            let file_key = export.verify_download_token(&hash(&secret), self.clock.now())?;
+            let content = self.object_storage.get(file_key).await?;
+            tracing::info!("data export downloaded. user_id: {}, export_id: {}", export.user_id(), export_id);
+
+            Ok(ExportFile { file_name: format!("subtrack-export-{}.zip", export_id), content })
+        })
+    }
+
+    fn run_pending(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>> {
+        Box::pin(async move {
+            let exports = self.repository.find_active().await?;
+            let mut completed = 0;
+            for export in exports {
+                let export_id = export.export_id().to_string();
+                match self.execute(export.clone()).await {
+                    Ok(()) => completed += 1,
+                    Err(e) => {
+                        tracing::error!("failed to export data. export_id: {}, error: {:?}", export_id, e);
+                        // 書き出した進捗を残すため、最後に保存した値を失敗として記録します
+                        let failed = self.repository.find(&export_id).await.unwrap_or(export).fail(self.clock.now());
+                        if let Err(e) = self.repository.save(failed).await {
+                            tracing::error!("failed to save data export. export_id: {}, error: {:?}", export_id, e);
+                        }
+                    }
+                }
+            }
+            Ok(completed)
+        })
+    }
+}
+
+/// ZIPファイルに、データの種類ごとのJSONとCSVを追加します
+struct Archive {
+    writer: zip::ZipWriter<std::io::Cursor<Vec<u8>>>,
+}
+
+impl Archive {
+    fn new() -> Self {
+        Self { writer: zip::ZipWriter::new(std::io::Cursor::new(vec![])) }
+    }
+
+    fn add_file(&mut self, file_name: &str, content: &[u8]) -> Result<(), DataExportError> {
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        self.writer.start_file(file_name, options).map_err(|e| DataExportError::ArchiveError(e.to_string()))?;
+        self.writer.write_all(content).map_err(|e| DataExportError::ArchiveError(e.to_string()))
+    }
+
+    /// プロフィールは1件のため、JSONは配列ではなくオブジェクトとして書き出します
+    fn add_profile(&mut self, profile: &UserDataItem) -> Result<(), DataExportError> {
+        let json = to_json(&FieldValue::Map(profile.clone()));
+        self.add_file(&format!("{}.json", PROFILE_TARGET), &to_json_bytes(&json)?)?;
+        self.add_file(&format!("{}.csv", PROFILE_TARGET), &to_csv(std::slice::from_ref(profile))?)
+    }
+
+    fn add_items(&mut self, target: &str, items: &[UserDataItem]) -> Result<(), DataExportError> {
+        let json = serde_json::Value::Array(items.iter().map(|v| to_json(&FieldValue::Map(v.clone()))).collect());
+        self.add_file(&format!("{}.json", target), &to_json_bytes(&json)?)?;
+        self.add_file(&format!("{}.csv", target), &to_csv(items)?)
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, DataExportError> {
+        self.writer.finish().map(|v| v.into_inner()).map_err(|e| DataExportError::ArchiveError(e.to_string()))
+    }
+}
+
+fn profile(user: &User) -> UserDataItem {
+    let string = |v: &str| FieldValue::String(v.to_string());
+    // ゲストユーザーのメールアドレスは自動で割り当てた値のため、書き出しません
+    let email = match user.email().is_guest() {
+        true => FieldValue::Null,
+        false => string(user.email().value()),
+    };
+    let profile_icon_path =
+        user.profile_icon_path().as_deref().filter(|v| !v.is_empty()).map_or(FieldValue::Null, string);
+    UserDataItem::from([
+        ("user_id".to_string(), string(&user.user_id().to_string())),
+        ("email".to_string(), email),
+        ("name".to_string(), string(&user.name().to_string())),
+        ("user_type".to_string(), string(&user.user_type().to_string())),
+        ("role".to_string(), string(&user.role().to_string())),
+        ("profile_icon_path".to_string(), profile_icon_path),
+    ])
+}
+
+/// 数値として読めない値は文字列のまま書き出します
+fn to_json(value: &FieldValue) -> serde_json::Value {
+    match value {
+        FieldValue::Null => serde_json::Value::Null,
+        FieldValue::Bool(v) => serde_json::Value::Bool(*v),
+        FieldValue::Number(v) => serde_json::from_str::<serde_json::Number>(v)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|_| serde_json::Value::String(v.clone())),
+        FieldValue::String(v) => serde_json::Value::String(v.clone()),
+        FieldValue::List(v) => serde_json::Value::Array(v.iter().map(to_json).collect()),
+        FieldValue::Map(v) => serde_json::Value::Object(v.iter().map(|(k, v)| (k.clone(), to_json(v))).collect()),
+    }
+}
+
+fn to_json_bytes(value: &serde_json::Value) -> Result<Vec<u8>, DataExportError> {
+    serde_json::to_vec_pretty(value).map_err(|e| DataExportError::ArchiveError(e.to_string()))
+}
+
+/// すべての項目の属性名を列とし、項目にない属性は空欄にします。リストとマップはJSONの文字列として書き出します
+fn to_csv(items: &[UserDataItem]) -> Result<Vec<u8>, DataExportError> {
+    let columns: BTreeSet<&String> = items.iter().flat_map(|v| v.keys()).collect();
+    let mut writer = csv::Writer::from_writer(vec![]);
+    if !columns.is_empty() {
+        writer.write_record(&columns).map_err(|e| DataExportError::ArchiveError(e.to_string()))?;
+    }
+    for item in items {
+        let record = columns.iter().map(|column| match item.get(*column) {
+            None | Some(FieldValue::Null) => String::new(),
+            Some(FieldValue::Bool(v)) => v.to_string(),
+            Some(FieldValue::Number(v)) | Some(FieldValue::String(v)) => v.clone(),
+            Some(v) => to_json(v).to_string(),
+        });
+        writer.write_record(record).map_err(|e| DataExportError::ArchiveError(e.to_string()))?;
+    }
+    writer.into_inner().map_err(|e| DataExportError::ArchiveError(e.to_string()))
+}
+
+fn generate_secret() -> [u8; SECRET_LENGTH] {
+    let mut secret = [0u8; SECRET_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// ダウンロード用トークンは`{export_id}.{secret}`の形式です
+fn format_token(export_id: &str, secret: &[u8]) -> String {
+    format!("{}.{}", export_id, base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret))
+}
+
+fn parse_token(token: &str) -> Result<(String, Vec<u8>), DataExportError> {
+    let (export_id, secret) = token.split_once('.').ok_or(DataExportError::InvalidToken)?;
+    uuid::Uuid::parse_str(export_id).map_err(|_| DataExportError::InvalidToken)?;
+    let secret =
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(secret).map_err(|_| DataExportError::InvalidToken)?;
+    if secret.len() != SECRET_LENGTH {
+        return Err(DataExportError::InvalidToken);
+    }
+    Ok((export_id.to_string(), secret))
+}
+
+fn hash(secret: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, str::FromStr};
+
+    use domain::data_export::ExportStatus;
+    use infrastructure::repository_impl::{
+        in_memory_data_export_repository::InMemoryDataExportRepository,
+        in_memory_user_data_repository::InMemoryUserDataRepository, in_memory_user_repository::InMemoryUserRepository,
+        local_object_storage::LocalObjectStorage,
+    };
+
+    use super::*;
+
+    const USER_ID: &str = "usr_123e4567-e89b-12d3-a456-426614174000";
+
+    struct Fixture {
+        service: DataExportServiceImpl,
+        repository: Arc<InMemoryDataExportRepository>,
+        object_storage: Arc<LocalObjectStorage>,
+        root: std::path::PathBuf,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn user_id() -> UserId {
+        UserId::from_str(USER_ID).unwrap()
+    }
+
+    fn subscribe(name: &str, amount: &str) -> UserDataItem {
+        UserDataItem::from([
+            ("user_id".to_string(), FieldValue::String(USER_ID.to_string())),
+            ("subscribe_name".to_string(), FieldValue::String(name.to_string())),
+            ("amount".to_string(), FieldValue::Number(amount.to_string())),
+        ])
+    }
+
+    async fn fixture(profile_icon_path: Option<&str>, download_ttl: Duration) -> Fixture {
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, profile_icon_path.map(ToString::to_string));
+        user_repository.create(user.unwrap()).await.unwrap();
+        let subscribe_repository = Arc::new(InMemoryUserDataRepository::new("subscribe"));
+        subscribe_repository.insert(
+            &user_id(),
+            vec![
+                subscribe("Netflix", "1490"),
+                subscribe("Spotify, Premium", "980"),
+            ],
+        );
+        subscribe_repository.insert(&UserId::new(), vec![subscribe("Other", "100")]);
+
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let object_storage = Arc::new(LocalObjectStorage::new(&root));
+        let repository = Arc::new(InMemoryDataExportRepository::new());
+        let service = DataExportServiceImpl::new(
+            repository.clone(),
+            user_repository,
+            vec![
+                subscribe_repository,
+                Arc::new(InMemoryUserDataRepository::new("category")),
+            ],
+            object_storage.clone(),
+            download_ttl,
+        );
+        Fixture { service, repository, object_storage, root }
+    }
+
+    fn unzip(content: Vec<u8>) -> std::collections::BTreeMap<String, Vec<u8>> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(content)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut content = vec![];
+                file.read_to_end(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+
+    fn error(e: anyhow::Error) -> DataExportError {
+        e.downcast::<DataExportError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_and_download() {
+        let fixture = fixture(Some("icons/usr_1.png"), Duration::from_secs(60)).await;
+        fixture.object_storage.put("icons/usr_1.png", b"png".to_vec(), "image/png").await.unwrap();
+
+        let export = fixture.service.request(&user_id()).await.unwrap();
+        assert_eq!(ExportStatus::Pending, export.status());
+        let result = fixture.service.request(&user_id()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::AlreadyRequested(_)));
+        let result = fixture.service.issue_download_token(&user_id(), export.export_id()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::NotReady(_)));
+
+        assert_eq!(1, fixture.service.run_pending().await.unwrap());
+        assert_eq!(0, fixture.service.run_pending().await.unwrap());
+        let completed = fixture.service.find(&user_id(), export.export_id()).await.unwrap();
+        assert_eq!(ExportStatus::Completed, completed.status());
+        assert_eq!(3, completed.total());
+        let progress: Vec<_> = completed.exported().iter().map(|v| (v.target(), v.count())).collect();
+        let expected = vec![
+            ("profile", 1),
+            ("subscribe", 2),
+            ("category", 0),
+        ];
+        assert_eq!(expected, progress);
+
+        let token = fixture.service.issue_download_token(&user_id(), export.export_id()).await.unwrap();
+        let file = fixture.service.download(token.value.expose_secret()).await.unwrap();
+        assert_eq!(format!("subtrack-export-{}.zip", export.export_id()), file.file_name);
+
+        let files = unzip(file.content);
+        let names: Vec<_> = files.keys().map(String::as_str).collect();
+        let expected = vec![
+            "category.csv",
+            "category.json",
+            "profile.csv",
+            "profile.json",
+            "profile_icon.png",
+            "subscribe.csv",
+            "subscribe.json",
+        ];
+        assert_eq!(expected, names);
+        assert_eq!(b"png".to_vec(), files["profile_icon.png"]);
+
+        let profile: serde_json::Value = serde_json::from_slice(&files["profile.json"]).unwrap();
+        assert_eq!(USER_ID, profile["user_id"]);
+        assert_eq!("hoge@email.com", profile["email"]);
+        let subscribe: serde_json::Value = serde_json::from_slice(&files["subscribe.json"]).unwrap();
+        assert_eq!(1490, subscribe[0]["amount"]);
+        assert_eq!("Spotify, Premium", subscribe[1]["subscribe_name"]);
+        let expected = "amount,subscribe_name,user_id\n1490,Netflix,usr_123e4567-e89b-12d3-a456-426614174000\n980,\"Spotify, Premium\",usr_123e4567-e89b-12d3-a456-426614174000\n";
+        assert_eq!(expected, String::from_utf8(files["subscribe.csv"].clone()).unwrap());
+        assert_eq!(b"[]".to_vec(), files["category.json"]);
+        assert!(files["category.csv"].is_empty());
+
+        // 再発行すると以前のトークンは使えません
+        fixture.service.issue_download_token(&user_id(), export.export_id()).await.unwrap();
+        let result = fixture.service.download(token.value.expose_secret()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::InvalidToken));
+
+        assert!(fixture.service.request(&user_id()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_download_invalid_token() {
+        let fixture = fixture(None, Duration::ZERO).await;
+        let export = fixture.service.request(&user_id()).await.unwrap();
+        fixture.service.run_pending().await.unwrap();
+
+        let token = fixture.service.issue_download_token(&user_id(), export.export_id()).await.unwrap();
+        let result = fixture.service.download(token.value.expose_secret()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::Expired));
+
+        let (_, secret) = token.value.expose_secret().split_once('.').unwrap();
+        let test_cases = vec![
+            "".to_string(),
+            "invalid".to_string(),
+            format!("{}.{}", uuid::Uuid::new_v4(), secret),
+            format!("{}.invalid", export.export_id()),
+        ];
+        for token in test_cases {
+            let result = fixture.service.download(&token).await;
+            assert!(matches!(error(result.unwrap_err()), DataExportError::InvalidToken), "{}", token);
+        }
+
+        let result = fixture.service.find(&UserId::new(), export.export_id()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::NotFound(_)));
+        let result = fixture.service.issue_download_token(&UserId::new(), export.export_id()).await;
+        assert!(matches!(error(result.unwrap_err()), DataExportError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_export_without_icon_file() {
+        let fixture = fixture(Some("icons/missing.png"), Duration::from_secs(60)).await;
+        let export = fixture.service.request(&user_id()).await.unwrap();
+        fixture.service.run_pending().await.unwrap();
+
+        let token = fixture.service.issue_download_token(&user_id(), export.export_id()).await.unwrap();
+        let files = unzip(fixture.service.download(token.value.expose_secret()).await.unwrap().content);
+        assert!(!files.contains_key("profile_icon.png"));
+        let profile: serde_json::Value = serde_json::from_slice(&files["profile.json"]).unwrap();
+        assert_eq!("icons/missing.png", profile["profile_icon_path"]);
+    }
+
+    #[tokio::test]
+    async fn test_export_failed() {
+        let fixture = fixture(None, Duration::from_secs(60)).await;
+        let export = DataExport::request(uuid::Uuid::new_v4().to_string(), UserId::new(), 100);
+        fixture.repository.save(export.clone()).await.unwrap();
+
+        assert_eq!(0, fixture.service.run_pending().await.unwrap());
+        let failed = fixture.repository.find(export.export_id()).await.unwrap();
+        assert_eq!(ExportStatus::Failed, failed.status());
+        assert!(failed.file_key().is_none());
+        assert!(fixture.service.request(failed.user_id()).await.is_ok());
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use domain::{
+    auth_user::AuthUserError,
+    clock::{Clock, SystemClock},
+    login_attempt::{LockoutPolicy, LoginAttempt},
+    repository::login_attempt_repository::LoginAttemptRepository,
+    user::email::Email,
+};
+
+use super::LoginAttemptService;
+
+const EMAIL_PREFIX: &str = "email#";
+const IP_PREFIX: &str = "ip#";
+
+pub struct LoginAttemptServiceImpl {
+    repository: Arc<dyn LoginAttemptRepository>,
+    email_policy: LockoutPolicy,
+    ip_policy: LockoutPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl LoginAttemptServiceImpl {
+    /// 同じ接続元から複数のユーザーがログインする場合を考慮し、`ip_policy`は`email_policy`より緩く設定します
+    pub fn new(
+        repository: Arc<dyn LoginAttemptRepository>, email_policy: LockoutPolicy, ip_policy: LockoutPolicy,
+    ) -> Self {
+        Self { repository, email_policy, ip_policy, clock: Arc::new(SystemClock) }
+    }
+
+    /// 認証の失敗の記録と集計に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    fn keys(&self, email: &Email, ip: &str) -> [(String, LockoutPolicy); 2] {
+        [
+            (email_key(email), self.email_policy),
+            (format!("{}{}", IP_PREFIX, ip), self.ip_policy),
+        ]
+    }
+}
+
+fn email_key(email: &Email) -> String {
+    format!("{}{}", EMAIL_PREFIX, email.value())
+}
+
+impl LoginAttemptService for LoginAttemptServiceImpl {
+    fn check(
+        &self, email: &Email, ip: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let keys = self.keys(email, ip);
+
+        Box::pin(async move {
+            let now = self.clock.now();
+            let mut retry_after = None;
+            for (key, _) in keys {
+                if let Some(v) = self.repository.find(&key).await?.and_then(|v| v.retry_after(now)) {
+                    retry_after = retry_after.max(Some(v));
+                }
+            }
+
+            match retry_after {
+                Some(v) => Err(AuthUserError::AccountLocked(v).into()),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn record_failure(
+        &self, email: &Email, ip: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let keys = self.keys(email, ip);
+
+        Box::pin(async move {
+            let now = self.clock.now();
+            for (key, policy) in keys {
+                let previous = self.repository.find(&key).await?.unwrap_or_else(|| LoginAttempt::empty(key.clone()));
+                let attempt = previous.record_failure(now, &policy);
+                if attempt.lockouts() > previous.lockouts() {
+                    tracing::warn!(
+                        "login locked. key: {}, lockouts: {}, locked_until: {}",
+                        key,
+                        attempt.lockouts(),
+                        attempt.locked_until()
+                    );
+                }
+                self.repository.save(attempt).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn clear(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let key = email_key(email);
+
+        // 接続元の記録は消去しません。1つの正規アカウントで接続元のロックを解除できないようにするためです
+        Box::pin(async move {
+            self.repository.delete(&key).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::repository::login_attempt_repository::LoginAttemptRepository;
+    use infrastructure::repository_impl::in_memory_login_attempt_repository::InMemoryLoginAttemptRepository;
+
+    use super::*;
+
+    const EMAIL_POLICY: LockoutPolicy = LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+    const IP_POLICY: LockoutPolicy = LockoutPolicy { threshold: 5, window: 60, base_lockout: 30, max_lockout: 300 };
+    const IP: &str = "192.0.2.1";
+
+    fn service() -> (LoginAttemptServiceImpl, Arc<InMemoryLoginAttemptRepository>) {
+        let repository = Arc::new(InMemoryLoginAttemptRepository::new());
+        (LoginAttemptServiceImpl::new(repository.clone(), EMAIL_POLICY, IP_POLICY), repository)
+    }
+
+    fn email(value: &str) -> Email {
+        Email::from_str(value).unwrap()
+    }
+
+    fn retry_after(result: anyhow::Result<()>) -> Option<u64> {
+        match result.map_err(|e| e.downcast::<AuthUserError>()) {
+            Err(Ok(AuthUserError::AccountLocked(v))) => Some(v),
+            Ok(_) => None,
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_email_locked_after_threshold() {
+        let (service, _) = service();
+        let hoge = email("hoge@email.com");
+
+        for _ in 0..EMAIL_POLICY.threshold - 1 {
+            service.record_failure(&hoge, IP).await.unwrap();
+            assert_eq!(None, retry_after(service.check(&hoge, IP).await));
+        }
+        service.record_failure(&hoge, IP).await.unwrap();
+
+        let result = retry_after(service.check(&hoge, "198.51.100.1").await);
+        assert!(result.is_some_and(|v| 0 < v && v <= 30));
+        assert_eq!(None, retry_after(service.check(&email("fuga@email.com"), IP).await));
+    }
+
+    #[tokio::test]
+    async fn test_ip_locked_after_threshold() {
+        let (service, _) = service();
+
+        for i in 0..IP_POLICY.threshold {
+            service.record_failure(&email(&format!("user{}@email.com", i)), IP).await.unwrap();
+        }
+
+        assert!(retry_after(service.check(&email("fuga@email.com"), IP).await).is_some());
+        assert_eq!(None, retry_after(service.check(&email("fuga@email.com"), "198.51.100.1").await));
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let (service, repository) = service();
+        let hoge = email("hoge@email.com");
+
+        for _ in 0..EMAIL_POLICY.threshold - 1 {
+            service.record_failure(&hoge, IP).await.unwrap();
+        }
+        service.clear(&hoge).await.unwrap();
+
+        assert_eq!(None, repository.find(&email_key(&hoge)).await.unwrap());
+        assert!(repository.find("ip#192.0.2.1").await.unwrap().is_some());
+
+        service.record_failure(&hoge, IP).await.unwrap();
+        assert_eq!(None, retry_after(service.check(&hoge, IP).await));
+    }
+}
@@ -0,0 +1,629 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, KeyInit, Nonce,
+};
+use base64::Engine;
+use domain::{
+    clock::{Clock, SystemClock},
+    mfa::{Mfa, MfaError},
+    repository::mfa_repository::MfaRepository,
+    token::Token,
+    user::{email::Email, user_id::UserId},
+};
+use hmac::Mac;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+use super::{MfaChallenge, MfaEnrollment, MfaService};
+
+/// RFC 6238の既定値です。多くの認証アプリはこれ以外の値に対応していません
+const DIGITS: u32 = 6;
+pub const PERIOD: i64 = 30;
+/// 端末の時刻のずれを考慮し、前後のステップのコードも受け付けます
+const DRIFT_STEPS: i64 = 1;
+/// RFC 4226が推奨する160ビットです
+const SECRET_LENGTH: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+/// 読み間違えやすい`0`、`1`、`O`、`I`を除いた文字です
+const RECOVERY_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const NONCE_LENGTH: usize = 12;
+/// 同じユーザーの設定が同時に更新された場合に、読み直して確認する回数です
+const MAX_UPDATE_ATTEMPTS: usize = 3;
+const SECRET_KEY_CONTEXT: &[u8] = b"subtrack.mfa.secret";
+const CHALLENGE_KEY_CONTEXT: &[u8] = b"subtrack.mfa.challenge";
+
+pub struct MfaServiceImpl {
+    repository: Arc<dyn MfaRepository>,
+    issuer: String,
+    secret_cipher: Aes256Gcm,
+    challenge_cipher: Aes256Gcm,
+    challenge_ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl MfaServiceImpl {
+    /// `encryption_key`からシークレットとチャレンジトークンそれぞれの暗号鍵を導出します
+    ///
+    /// 鍵を変更すると保存済みのシークレットを復号できなくなるため、運用中は同じ鍵を使い続けてください
+    pub fn new(
+        repository: Arc<dyn MfaRepository>, issuer: String, encryption_key: &[u8], challenge_ttl: Duration,
+    ) -> Self {
+        Self {
+            repository,
+            issuer,
+            secret_cipher: cipher(SECRET_KEY_CONTEXT, encryption_key),
+            challenge_cipher: cipher(CHALLENGE_KEY_CONTEXT, encryption_key),
+            challenge_ttl,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 認証コードとチャレンジトークンの検証に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn find_enabled(&self, user_id: &UserId) -> Result<Mfa, MfaError> {
+        match self.repository.find(user_id).await? {
+            Some(mfa) if mfa.enabled() => Ok(mfa),
+            _ => Err(MfaError::NotEnrolled(user_id.to_string())),
+        }
+    }
+
+    /// コードを確認し、使用済みにした設定を返します
+    fn consume(&self, mfa: &Mfa, code: &str) -> Result<Mfa, MfaError> {
+        let code: String = code.chars().filter(|v| !v.is_whitespace() && *v != '-').collect();
+
+        let used = match code.len() == DIGITS as usize {
+            true => {
+                let secret = self.decrypt_secret(mfa)?;
+                matching_step(&secret, &code, self.clock.now()).and_then(|step| mfa.use_step(step))
+            }
+            false => mfa.use_recovery_code(&hash(&code.to_ascii_uppercase())).inspect(|v| {
+                tracing::warn!(
+                    "recovery code used. user_id: {}, remaining: {}",
+                    mfa.user_id(),
+                    v.recovery_code_hashes().len()
+                )
+            }),
+        };
+        used.ok_or(MfaError::InvalidCode)
+    }
+
+    fn encrypt_secret(&self, user_id: &UserId, secret: &[u8]) -> Result<String, MfaError> {
+        // 別のユーザーの設定に暗号文を移し替えても復号できないよう、ユーザーIDを関連データにします
+        let aad = user_id.to_string();
+        let encrypted = encrypt(&self.secret_cipher, Payload { msg: secret, aad: aad.as_bytes() })?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(encrypted))
+    }
+
+    fn decrypt_secret(&self, mfa: &Mfa) -> Result<Vec<u8>, MfaError> {
+        let aad = mfa.user_id().to_string();
+        let encrypted = base64::engine::general_purpose::STANDARD
+            .decode(mfa.secret())
+            .map_err(|e| MfaError::CryptoError(e.to_string()))?;
+        decrypt(&self.secret_cipher, &encrypted, aad.as_bytes())
+    }
+
+    fn otpauth_uri(&self, email: &Email, secret: &str) -> String {
+        let issuer = utf8_percent_encode(&self.issuer, NON_ALPHANUMERIC).to_string();
+        let account = utf8_percent_encode(email.value(), NON_ALPHANUMERIC);
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            issuer, account, secret, issuer, DIGITS, PERIOD
+        )
+    }
+}
+
+impl MfaService for MfaServiceImpl {
+    fn enroll(
+        &self, user_id: &UserId, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<MfaEnrollment>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let email = email.clone();
+
+        Box::pin(async move {
+            if self.repository.find(&user_id).await?.is_some_and(|v| v.enabled()) {
+                return Err(MfaError::AlreadyEnabled(user_id.to_string()).into());
+            }
+
+            let mut secret = [0u8; SECRET_LENGTH];
+            rand::rngs::OsRng.fill_bytes(&mut secret);
+            self.repository.save(Mfa::enroll(user_id.clone(), self.encrypt_secret(&user_id, &secret)?)).await?;
+
+            let secret = data_encoding::BASE32_NOPAD.encode(&secret);
+            let otpauth_uri = self.otpauth_uri(&email, &secret);
+            Ok(MfaEnrollment { secret, otpauth_uri })
+        })
+    }
+
+    fn activate(
+        &self, user_id: &UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<String>>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let code = code.trim().to_string();
+
+        Box::pin(async move {
+            let mfa = match self.repository.find(&user_id).await? {
+                Some(mfa) if mfa.enabled() => return Err(MfaError::AlreadyEnabled(user_id.to_string()).into()),
+                Some(mfa) => mfa,
+                None => return Err(MfaError::NotEnrolled(user_id.to_string()).into()),
+            };
+
+            let secret = self.decrypt_secret(&mfa)?;
+            let step = matching_step(&secret, &code, self.clock.now()).ok_or(MfaError::InvalidCode)?;
+            let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+            let hashes = recovery_codes.iter().map(|v| hash(&v.replace('-', ""))).collect();
+
+            self.repository.save(mfa.activate(hashes, step)).await?;
+            tracing::info!("mfa enabled. user_id: {}", user_id);
+            Ok(recovery_codes)
+        })
+    }
+
+    fn is_enabled(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<bool>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move { Ok(self.repository.find(&user_id).await?.is_some_and(|v| v.enabled())) })
+    }
+
+    fn verify(
+        &self, user_id: &UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let code = code.to_string();
+
+        Box::pin(async move {
+            for _ in 0..MAX_UPDATE_ATTEMPTS {
+                let mfa = self.find_enabled(&user_id).await?;
+                match self.repository.update(self.consume(&mfa, &code)?, &mfa).await {
+                    // 同じコードが同時に使われた場合は、読み直した設定ではコードが使用済みになります
+                    Err(MfaError::Conflict(_)) => continue,
+                    result => return Ok(result?),
+                }
+            }
+            Err(MfaError::Conflict(user_id.to_string()).into())
+        })
+    }
+
+    fn disable(
+        &self, user_id: &UserId, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let mfa = self.find_enabled(&user_id).await?;
+            self.consume(&mfa, &code)?;
+            self.repository.delete(&user_id).await?;
+            tracing::info!("mfa disabled. user_id: {}", user_id);
+            Ok(())
+        })
+    }
+
+    fn issue_challenge(&self, user_id: &UserId, token: &Token) -> anyhow::Result<String> {
+        let expires_at = self.clock.now() + self.challenge_ttl.as_secs() as i64;
+        let plaintext = [
+            uuid::Uuid::new_v4().to_string(),
+            user_id.to_string(),
+            expires_at.to_string(),
+            token.access_token().expose_secret().to_string(),
+            token.id_token().map(|v| v.expose_secret().to_string()).unwrap_or_default(),
+            token.refresh_token().expose_secret().to_string(),
+            token.issued_at().to_string(),
+            token.expires_in().to_string(),
+        ]
+        .join("\n");
+
+        let encrypted = encrypt(&self.challenge_cipher, Payload { msg: plaintext.as_bytes(), aad: &[] })?;
+        Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(encrypted))
+    }
+
+    fn open_challenge(&self, challenge_token: &str) -> anyhow::Result<MfaChallenge> {
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(challenge_token)
+            .map_err(|_| MfaError::InvalidChallenge)?;
+        let plaintext = decrypt(&self.challenge_cipher, &bytes, &[]).map_err(|_| MfaError::InvalidChallenge)?;
+        let plaintext = String::from_utf8(plaintext).map_err(|_| MfaError::InvalidChallenge)?;
+
+        let fields: Vec<&str> = plaintext.split('\n').collect();
+        let [nonce, user_id, expires_at, access_token, id_token, refresh_token, issued_at, expires_in] = fields[..]
+        else {
+            return Err(MfaError::InvalidChallenge.into());
+        };
+        let user_id = UserId::from_str(user_id).map_err(|_| MfaError::InvalidChallenge)?;
+        let expires_at: i64 = expires_at.parse().map_err(|_| MfaError::InvalidChallenge)?;
+        if expires_at <= self.clock.now() {
+            return Err(MfaError::ChallengeExpired.into());
+        }
+
+        let issued_at = issued_at.parse().map_err(|_| MfaError::InvalidChallenge)?;
+        let expires_in = expires_in.parse().map_err(|_| MfaError::InvalidChallenge)?;
+        let id_token = (!id_token.is_empty()).then(|| id_token.to_string().into());
+        let token =
+            Token::new(access_token.to_string().into(), refresh_token.to_string().into(), issued_at, expires_in)
+                .with_id_token(id_token);
+        Ok(MfaChallenge { nonce: nonce.to_string(), user_id, expires_at, token })
+    }
+}
+
+/// RFC 6238のHMAC-SHA1によるコードです
+///
+/// `step`はUNIX時刻を`PERIOD`で割った値です
+pub fn totp(secret: &[u8], step: i64) -> String {
+    let mut mac = <hmac::Hmac<sha1::Sha1> as Mac>::new_from_slice(secret).expect("HMAC can take key of any size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!("{:0width$}", value % 10_u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+/// `code`が一致するステップを返します
+fn matching_step(secret: &[u8], code: &str, now: i64) -> Option<i64> {
+    let current = now / PERIOD;
+    (current - DRIFT_STEPS..=current + DRIFT_STEPS).find(|step| constant_time_eq(&totp(secret, *step), code))
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `XXXXX-XXXXX`の形式のリカバリーコードです
+fn generate_recovery_code() -> String {
+    let mut rng = rand::rngs::OsRng;
+    let code: String = (0..RECOVERY_CODE_LENGTH)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+    format!("{}-{}", first, second)
+}
+
+/// リカバリーコードは十分な長さの乱数のため、低速なハッシュ関数は使用しません
+fn hash(code: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code.as_bytes()))
+}
+
+fn cipher(context: &[u8], key: &[u8]) -> Aes256Gcm {
+    let key = Sha256::new().chain_update(context).chain_update(key).finalize();
+    Aes256Gcm::new(&key)
+}
+
+/// 先頭にnonceを付けた暗号文を返します
+fn encrypt(cipher: &Aes256Gcm, payload: Payload) -> Result<Vec<u8>, MfaError> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext =
+        cipher.encrypt(Nonce::from_slice(&nonce), payload).map_err(|e| MfaError::CryptoError(e.to_string()))?;
+    let mut bytes = nonce.to_vec();
+    bytes.extend(ciphertext);
+    Ok(bytes)
+}
+
+fn decrypt(cipher: &Aes256Gcm, bytes: &[u8], aad: &[u8]) -> Result<Vec<u8>, MfaError> {
+    if bytes.len() < NONCE_LENGTH {
+        return Err(MfaError::CryptoError("ciphertext too short".to_string()));
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|e| MfaError::CryptoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::clock::FixedClock;
+    use infrastructure::repository_impl::in_memory_mfa_repository::InMemoryMfaRepository;
+
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+    const NOW: i64 = 1_700_000_000;
+
+    fn service() -> (MfaServiceImpl, Arc<InMemoryMfaRepository>) {
+        let repository = Arc::new(InMemoryMfaRepository::new());
+        let service = MfaServiceImpl::new(repository.clone(), "SubTrack".to_string(), KEY, Duration::from_secs(300))
+            .with_clock(Arc::new(FixedClock(NOW)));
+        (service, repository)
+    }
+
+    fn email() -> Email {
+        Email::from_str("hoge@email.com").unwrap()
+    }
+
+    fn code(enrollment: &MfaEnrollment, offset: i64) -> String {
+        let secret = data_encoding::BASE32_NOPAD.decode(enrollment.secret.as_bytes()).unwrap();
+        totp(&secret, NOW / PERIOD + offset)
+    }
+
+    fn mfa_error<T: std::fmt::Debug>(result: anyhow::Result<T>) -> MfaError {
+        result.unwrap_err().downcast::<MfaError>().unwrap()
+    }
+
+    /// RFC 6238 Appendix BのSHA-1のテストベクターの下6桁です
+    #[test]
+    fn test_totp() {
+        let secret = b"12345678901234567890";
+        let test_cases = vec![
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (time, expected) in test_cases {
+            assert_eq!(expected, totp(secret, time / PERIOD));
+        }
+    }
+
+    #[test]
+    fn test_matching_step_with_drift() {
+        let secret = b"12345678901234567890";
+        let now = 1111111111;
+        let step = now / PERIOD;
+
+        let test_cases = vec![
+            (totp(secret, step - 2), None),
+            (totp(secret, step - 1), Some(step - 1)),
+            (totp(secret, step), Some(step)),
+            (totp(secret, step + 1), Some(step + 1)),
+            (totp(secret, step + 2), None),
+            ("12345".to_string(), None),
+        ];
+        for (code, expected) in test_cases {
+            assert_eq!(expected, matching_step(secret, &code, now));
+        }
+    }
+
+    #[test]
+    fn test_generate_recovery_code() {
+        let code = generate_recovery_code();
+        assert_eq!(RECOVERY_CODE_LENGTH + 1, code.len());
+        assert_eq!(Some(RECOVERY_CODE_LENGTH / 2), code.find('-'));
+        assert!(code.replace('-', "").bytes().all(|v| RECOVERY_CODE_ALPHABET.contains(&v)));
+        assert_ne!(code, generate_recovery_code());
+    }
+
+    #[tokio::test]
+    async fn test_enroll_and_activate() {
+        let (service, repository) = service();
+        let user_id = UserId::new();
+
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+        assert_eq!(32, enrollment.secret.len());
+        assert_eq!(
+            format!(
+                "otpauth://totp/SubTrack:hoge%40email%2Ecom?secret={}&issuer=SubTrack&algorithm=SHA1&digits=6&period=30",
+                enrollment.secret
+            ),
+            enrollment.otpauth_uri
+        );
+        assert!(!service.is_enabled(&user_id).await.unwrap());
+
+        // シークレットは平文で保存しません
+        let saved = repository.find(&user_id).await.unwrap().unwrap();
+        assert!(!saved.secret().contains(&enrollment.secret));
+
+        assert!(matches!(mfa_error(service.activate(&user_id, "000000").await), MfaError::InvalidCode));
+
+        let recovery_codes = service.activate(&user_id, &code(&enrollment, 0)).await.unwrap();
+        assert_eq!(RECOVERY_CODE_COUNT, recovery_codes.len());
+        assert!(service.is_enabled(&user_id).await.unwrap());
+        assert!(!repository.find(&user_id).await.unwrap().unwrap().recovery_code_hashes().contains(&recovery_codes[0]));
+
+        assert!(matches!(mfa_error(service.enroll(&user_id, &email()).await), MfaError::AlreadyEnabled(_)));
+        assert!(matches!(
+            mfa_error(service.activate(&user_id, &code(&enrollment, 1)).await),
+            MfaError::AlreadyEnabled(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_activate_not_enrolled() {
+        let (service, _) = service();
+        assert!(matches!(mfa_error(service.activate(&UserId::new(), "000000").await), MfaError::NotEnrolled(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_replay() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+        let activated = code(&enrollment, 0);
+        service.activate(&user_id, &activated).await.unwrap();
+
+        assert!(matches!(mfa_error(service.verify(&user_id, &activated).await), MfaError::InvalidCode));
+
+        let next = code(&enrollment, 1);
+        service.verify(&user_id, &next).await.unwrap();
+        assert!(matches!(mfa_error(service.verify(&user_id, &next).await), MfaError::InvalidCode));
+    }
+
+    /// 読み込みのたびに他のタスクに切り替わり、同時のリクエストを再現するリポジトリです
+    struct YieldingMfaRepository(InMemoryMfaRepository);
+
+    impl MfaRepository for YieldingMfaRepository {
+        fn find(
+            &self, user_id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<Mfa>, MfaError>> + Send + '_>> {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                self.0.find(&user_id).await
+            })
+        }
+
+        fn save(
+            &self, mfa: Mfa,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+            self.0.save(mfa)
+        }
+
+        fn update(
+            &self, mfa: Mfa, expected: &Mfa,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+            self.0.update(mfa, expected)
+        }
+
+        fn delete(
+            &self, user_id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+            self.0.delete(user_id)
+        }
+    }
+
+    /// 同じコードを同時に使用したリクエストは1つだけ受け付けます
+    #[tokio::test]
+    async fn test_verify_concurrently() {
+        let repository = Arc::new(YieldingMfaRepository(InMemoryMfaRepository::new()));
+        let service = MfaServiceImpl::new(repository, "SubTrack".to_string(), KEY, Duration::from_secs(300))
+            .with_clock(Arc::new(FixedClock(NOW)));
+        let user_id = UserId::new();
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+        let recovery_codes = service.activate(&user_id, &code(&enrollment, 0)).await.unwrap();
+
+        let test_cases = vec![
+            code(&enrollment, 1),
+            recovery_codes[0].clone(),
+        ];
+        for code in test_cases {
+            let (first, second) = tokio::join!(service.verify(&user_id, &code), service.verify(&user_id, &code));
+            assert_eq!(
+                1,
+                [
+                    first.is_ok(),
+                    second.is_ok()
+                ]
+                .iter()
+                .filter(|v| **v)
+                .count(),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_recovery_code() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+        let recovery_codes = service.activate(&user_id, &code(&enrollment, 0)).await.unwrap();
+
+        service.verify(&user_id, &recovery_codes[0]).await.unwrap();
+        assert!(matches!(mfa_error(service.verify(&user_id, &recovery_codes[0]).await), MfaError::InvalidCode));
+
+        // 区切り文字と大文字小文字は区別しません
+        service.verify(&user_id, &recovery_codes[1].replace('-', "").to_lowercase()).await.unwrap();
+        assert!(matches!(mfa_error(service.verify(&user_id, "AAAAA-AAAAA").await), MfaError::InvalidCode));
+    }
+
+    #[tokio::test]
+    async fn test_verify_not_enabled() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+
+        let result = service.verify(&user_id, &code(&enrollment, 0)).await;
+        assert!(matches!(mfa_error(result), MfaError::NotEnrolled(_)));
+    }
+
+    #[tokio::test]
+    async fn test_disable() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let enrollment = service.enroll(&user_id, &email()).await.unwrap();
+        service.activate(&user_id, &code(&enrollment, 0)).await.unwrap();
+
+        assert!(matches!(mfa_error(service.disable(&user_id, "000000").await), MfaError::InvalidCode));
+        assert!(service.is_enabled(&user_id).await.unwrap());
+
+        service.disable(&user_id, &code(&enrollment, 1)).await.unwrap();
+        assert!(!service.is_enabled(&user_id).await.unwrap());
+    }
+
+    #[test]
+    fn test_challenge() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+
+        let test_cases = vec![
+            None,
+            Some("id".to_string()),
+        ];
+        for id_token in test_cases {
+            let token = Token::new("jwt".to_string().into(), "refresh".to_string().into(), 100, 3600)
+                .with_id_token(id_token.clone().map(Into::into));
+            let challenge_token = service.issue_challenge(&user_id, &token);
+            let challenge = service.open_challenge(&challenge_token.unwrap()).unwrap();
+            assert_eq!(user_id, challenge.user_id);
+            assert_eq!(NOW + 300, challenge.expires_at);
+            assert_eq!("jwt", challenge.token.access_token().expose_secret());
+            assert_eq!(id_token.as_deref(), challenge.token.id_token().map(|v| v.expose_secret()));
+            assert_eq!("refresh", challenge.token.refresh_token().expose_secret());
+            assert_eq!(100, challenge.token.issued_at());
+            assert_eq!(3600, challenge.token.expires_in());
+        }
+    }
+
+    #[test]
+    fn test_challenge_nonce() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let token = Token::new("jwt".to_string().into(), "refresh".to_string().into(), 100, 3600);
+
+        let first = service.open_challenge(&service.issue_challenge(&user_id, &token).unwrap()).unwrap();
+        let second = service.open_challenge(&service.issue_challenge(&user_id, &token).unwrap()).unwrap();
+        assert_ne!(first.nonce, second.nonce);
+    }
+
+    #[test]
+    fn test_challenge_failed() {
+        let (service, _) = service();
+        let token = Token::new("jwt".to_string().into(), "refresh".to_string().into(), 100, 3600);
+        let challenge_token = service.issue_challenge(&UserId::new(), &token).unwrap();
+
+        let other = MfaServiceImpl::new(
+            Arc::new(InMemoryMfaRepository::new()),
+            "SubTrack".to_string(),
+            b"other",
+            Duration::from_secs(300),
+        );
+        // 有効期間が過ぎた時刻に発行したチャレンジトークンです
+        let expired = MfaServiceImpl::new(
+            Arc::new(InMemoryMfaRepository::new()),
+            "SubTrack".to_string(),
+            KEY,
+            Duration::from_secs(300),
+        )
+        .with_clock(Arc::new(FixedClock(NOW - 300)));
+
+        let test_cases = vec![
+            (other.open_challenge(&challenge_token), "Invalid MFA challenge"),
+            (service.open_challenge("invalid"), "Invalid MFA challenge"),
+            (service.open_challenge(&format!("{}A", challenge_token)), "Invalid MFA challenge"),
+            (
+                service.open_challenge(&expired.issue_challenge(&UserId::new(), &token).unwrap()),
+                "MFA challenge expired",
+            ),
+        ];
+        for (result, expected) in test_cases {
+            assert_eq!(expected, mfa_error(result).to_string());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use domain::{
+    repository::breached_password_repository::BreachedPasswordRepository,
+    user::{
+        email::Email,
+        name::Name,
+        password::{Password, PasswordError, PasswordPolicy, PasswordViolation},
+    },
+};
+use sha1::{Digest, Sha1};
+
+use super::PasswordService;
+
+/// k-anonymityで照合するハッシュの先頭の文字数です
+const PREFIX_LENGTH: usize = 5;
+
+pub struct PasswordServiceImpl {
+    policy: PasswordPolicy,
+    breached_password_repository: Arc<dyn BreachedPasswordRepository>,
+}
+
+impl PasswordServiceImpl {
+    pub fn new(policy: PasswordPolicy, breached_password_repository: Arc<dyn BreachedPasswordRepository>) -> Self {
+        Self { policy, breached_password_repository }
+    }
+
+    /// ハッシュの先頭5文字でリストを検索し、残りの文字をこのサービス内で比較します
+    async fn is_breached(&self, password: &str) -> Result<bool, PasswordError> {
+        let hash = data_encoding::HEXUPPER.encode(&Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+        let range = self.breached_password_repository.find_range(prefix).await?;
+
+        Ok(range.iter().any(|v| v.eq_ignore_ascii_case(suffix)))
+    }
+}
+
+impl PasswordService for PasswordServiceImpl {
+    fn validate(
+        &self, password: &str, email: &Email, name: Option<&Name>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Password>> + Send + '_>> {
+        let password = password.to_string();
+        let mut violations = self.policy.violations(&password, Some(email), name);
+
+        Box::pin(async move {
+            if self.is_breached(&password).await? {
+                violations.push(PasswordViolation::Breached);
+            }
+
+            match violations.is_empty() {
+                true => Ok(self.policy.validate(&password, None, None)?),
+                false => Err(PasswordError::PolicyViolation(violations).into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::user::password::CharacterClass;
+    use infrastructure::repository_impl::local_breached_password_repository::LocalBreachedPasswordRepository;
+
+    use super::*;
+
+    /// `Password123`と`P@ssw0rd!`のSHA-1です
+    const BREACHED: &str = "B2E98AD6F6EB8508DD6A14CFA704BAD7F05F6FB1:2000
+076D3E6C4B9F654B5B220B9045B7458AB6B4CBC6";
+
+    fn service(policy: PasswordPolicy) -> PasswordServiceImpl {
+        let repository = LocalBreachedPasswordRepository::parse(BREACHED).unwrap();
+        PasswordServiceImpl::new(policy, Arc::new(repository))
+    }
+
+    fn violations(result: anyhow::Result<Password>) -> Vec<PasswordViolation> {
+        match result.map_err(|e| e.downcast::<PasswordError>()) {
+            Ok(_) => vec![],
+            Err(Ok(PasswordError::PolicyViolation(v))) => v,
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let policy = PasswordPolicy {
+            required_classes: vec![
+                CharacterClass::Uppercase,
+                CharacterClass::Digit,
+            ],
+            ..PasswordPolicy::default()
+        };
+        let service = service(policy);
+        let email = Email::from_str("hanako@email.com").unwrap();
+        let name = Name::from_str("Yamada").unwrap();
+
+        let test_cases = vec![
+            ("Correct7Horse", vec![]),
+            ("Password123", vec![PasswordViolation::Breached]),
+            (
+                "hanako",
+                vec![
+                    PasswordViolation::TooShort(8),
+                    PasswordViolation::MissingCharacterClass(CharacterClass::Uppercase),
+                    PasswordViolation::MissingCharacterClass(CharacterClass::Digit),
+                    PasswordViolation::ContainsEmail,
+                ],
+            ),
+            ("Yamada2024", vec![PasswordViolation::ContainsName]),
+        ];
+        for (password, expected) in test_cases {
+            let result = service.validate(password, &email, Some(&name)).await;
+            assert_eq!(expected, violations(result), "{}", password);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_returns_password() {
+        let service = service(PasswordPolicy::default());
+        let email = Email::from_str("hanako@email.com").unwrap();
+
+        let password = service.validate("パスワード2024", &email, None).await.unwrap();
+        assert_eq!("パスワード2024", password.expose_secret());
+    }
+}
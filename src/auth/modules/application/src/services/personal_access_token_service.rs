@@ -0,0 +1,307 @@
+use std::{sync::Arc, time::Duration};
+
+use base64::Engine;
+use domain::{
+    clock::{Clock, SystemClock},
+    personal_access_token::{PersonalAccessToken, PersonalAccessTokenError, Scope},
+    repository::personal_access_token_repository::PersonalAccessTokenRepository,
+    user::user_id::UserId,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::{IssuedPersonalAccessToken, PersonalAccessTokenService, PERSONAL_ACCESS_TOKEN_PREFIX};
+
+const SECRET_LENGTH: usize = 32;
+
+pub struct PersonalAccessTokenServiceImpl {
+    repository: Arc<dyn PersonalAccessTokenRepository>,
+    max_ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl PersonalAccessTokenServiceImpl {
+    /// `max_ttl`は発行できるトークンの最長の有効期間です
+    pub fn new(repository: Arc<dyn PersonalAccessTokenRepository>, max_ttl: Duration) -> Self {
+        Self { repository, max_ttl, clock: Arc::new(SystemClock) }
+    }
+
+    /// 発行日時と有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl PersonalAccessTokenService for PersonalAccessTokenServiceImpl {
+    fn issue(
+        &self, user_id: &UserId, name: &str, scopes: Vec<Scope>, ttl: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<IssuedPersonalAccessToken>> + Send + '_>>
+    {
+        let user_id = user_id.clone();
+        let name = name.to_string();
+
+        Box::pin(async move {
+            let name = PersonalAccessToken::validate_name(&name)?;
+            if scopes.is_empty() {
+                return Err(PersonalAccessTokenError::InvalidScope("".to_string()).into());
+            }
+            if ttl.is_zero() || ttl > self.max_ttl {
+                return Err(PersonalAccessTokenError::InvalidExpiry(ttl.as_secs()).into());
+            }
+            let mut scopes = scopes;
+            scopes.sort();
+            scopes.dedup();
+
+            let token_id = uuid::Uuid::new_v4().to_string();
+            let secret = generate_secret();
+            let now = self.clock.now();
+            let token = PersonalAccessToken::new(
+                token_id.clone(),
+                user_id.clone(),
+                name,
+                scopes,
+                hash(&secret),
+                now,
+                now + ttl.as_secs() as i64,
+            );
+            self.repository.save(token.clone()).await?;
+            tracing::info!("personal access token issued. user_id: {}, token_id: {}", user_id, token_id);
+
+            Ok(IssuedPersonalAccessToken { token, value: format_token(&user_id, &token_id, &secret) })
+        })
+    }
+
+    fn list(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<PersonalAccessToken>>> + Send + '_>>
+    {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let now = self.clock.now();
+            // TTLによる削除は遅延するため、有効期限を過ぎたトークンを除きます
+            let tokens = self.repository.find_by_user_id(&user_id).await?;
+            Ok(tokens.into_iter().filter(|v| !v.is_expired(now)).collect())
+        })
+    }
+
+    fn revoke(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let token_id = token_id.to_string();
+
+        Box::pin(async move {
+            self.repository.delete(&user_id, &token_id).await?;
+            tracing::info!("personal access token revoked. user_id: {}, token_id: {}", user_id, token_id);
+            Ok(())
+        })
+    }
+
+    fn authenticate(
+        &self, value: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<PersonalAccessToken>> + Send + '_>> {
+        let value = value.to_string();
+
+        Box::pin(async move {
+            let (user_id, token_id, secret) = parse_token(&value)?;
+            let token =
+                self.repository.find(&user_id, &token_id).await?.ok_or(PersonalAccessTokenError::InvalidToken)?;
+            if token.token_hash() != hash(&secret) {
+                return Err(PersonalAccessTokenError::InvalidToken.into());
+            }
+            if token.is_expired(self.clock.now()) {
+                return Err(PersonalAccessTokenError::Expired.into());
+            }
+            Ok(token)
+        })
+    }
+}
+
+fn generate_secret() -> [u8; SECRET_LENGTH] {
+    let mut secret = [0u8; SECRET_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// クライアントに渡すトークンは`pat_{user_id}.{token_id}.{secret}`の形式です
+///
+/// ユーザーIDを含めることで、ユーザーごとに保存しているトークンを1回の読み込みで取得できます
+fn format_token(user_id: &UserId, token_id: &str, secret: &[u8]) -> String {
+    format!(
+        "{}{}.{}.{}",
+        PERSONAL_ACCESS_TOKEN_PREFIX,
+        user_id,
+        token_id,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret)
+    )
+}
+
+fn parse_token(token: &str) -> Result<(UserId, String, Vec<u8>), PersonalAccessTokenError> {
+    use std::str::FromStr;
+
+    let token = token.strip_prefix(PERSONAL_ACCESS_TOKEN_PREFIX).ok_or(PersonalAccessTokenError::InvalidToken)?;
+    let mut parts = token.splitn(3, '.');
+    let (user_id, token_id, secret) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(user_id), Some(token_id), Some(secret)) => (user_id, token_id, secret),
+        _ => return Err(PersonalAccessTokenError::InvalidToken),
+    };
+    let user_id = UserId::from_str(user_id).map_err(|_| PersonalAccessTokenError::InvalidToken)?;
+    uuid::Uuid::parse_str(token_id).map_err(|_| PersonalAccessTokenError::InvalidToken)?;
+    let secret = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(secret)
+        .map_err(|_| PersonalAccessTokenError::InvalidToken)?;
+    if secret.len() != SECRET_LENGTH {
+        return Err(PersonalAccessTokenError::InvalidToken);
+    }
+    Ok((user_id, token_id.to_string(), secret))
+}
+
+fn hash(secret: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use infrastructure::repository_impl::in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository;
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60 * 60);
+
+    fn service() -> (PersonalAccessTokenServiceImpl, Arc<InMemoryPersonalAccessTokenRepository>) {
+        let repository = Arc::new(InMemoryPersonalAccessTokenRepository::new());
+        (PersonalAccessTokenServiceImpl::new(repository.clone(), Duration::from_secs(24 * 60 * 60)), repository)
+    }
+
+    fn error(e: anyhow::Error) -> PersonalAccessTokenError {
+        e.downcast::<PersonalAccessTokenError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_authenticate_success() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let scopes = vec![
+            Scope::SubscriptionsWrite,
+            Scope::SubscriptionsRead,
+            Scope::SubscriptionsWrite,
+        ];
+
+        let issued = service.issue(&user_id, " import ", scopes, TTL).await.unwrap();
+        assert!(issued.value.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX));
+        assert_eq!("import", issued.token.name());
+        let expected = vec![
+            Scope::SubscriptionsRead,
+            Scope::SubscriptionsWrite,
+        ];
+        assert_eq!(expected, issued.token.scopes());
+
+        let result = service.authenticate(&issued.value).await.unwrap();
+        assert_eq!(issued.token, result);
+        assert_eq!(&user_id, result.user_id());
+    }
+
+    #[tokio::test]
+    async fn test_issue_stores_only_hash() {
+        let (service, repository) = service();
+        let user_id = UserId::new();
+
+        let issued = service.issue(&user_id, "import", vec![Scope::SubscriptionsRead], TTL).await.unwrap();
+        let saved = repository.find(&user_id, issued.token.token_id()).await.unwrap().unwrap();
+
+        let (_, _, secret) = parse_token(&issued.value).unwrap();
+        let encoded_secret = issued.value.rsplit_once('.').unwrap().1;
+        assert_eq!(hash(&secret), saved.token_hash());
+        assert!(!saved.token_hash().contains(encoded_secret));
+    }
+
+    #[tokio::test]
+    async fn test_issue_failed() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+
+        let result = service.issue(&user_id, " ", vec![Scope::SubscriptionsRead], TTL).await;
+        assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::InvalidName(_)));
+
+        let result = service.issue(&user_id, "import", vec![], TTL).await;
+        assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::InvalidScope(_)));
+
+        let test_cases = vec![
+            Duration::ZERO,
+            Duration::from_secs(24 * 60 * 60 + 1),
+        ];
+        for ttl in test_cases {
+            let result = service.issue(&user_id, "import", vec![Scope::SubscriptionsRead], ttl).await;
+            assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::InvalidExpiry(_)), "{:?}", ttl);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_revoke() {
+        let (service, repository) = service();
+        let user_id = UserId::new();
+        let first = service.issue(&user_id, "first", vec![Scope::SubscriptionsRead], TTL).await.unwrap();
+        service.issue(&UserId::new(), "other", vec![Scope::SubscriptionsRead], TTL).await.unwrap();
+        let expired = PersonalAccessToken::new(
+            uuid::Uuid::new_v4().to_string(),
+            user_id.clone(),
+            "expired".to_string(),
+            vec![Scope::SubscriptionsRead],
+            "hash".to_string(),
+            0,
+            1,
+        );
+        repository.save(expired).await.unwrap();
+
+        let result = service.list(&user_id).await.unwrap();
+        assert_eq!(vec![first.token.clone()], result);
+
+        service.revoke(&user_id, first.token.token_id()).await.unwrap();
+        assert!(service.list(&user_id).await.unwrap().is_empty());
+        let result = service.authenticate(&first.value).await;
+        assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::InvalidToken));
+
+        let result = service.revoke(&user_id, first.token.token_id()).await;
+        assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_failed() {
+        let (service, repository) = service();
+        let user_id = UserId::new();
+        let issued = service.issue(&user_id, "import", vec![Scope::SubscriptionsRead], TTL).await.unwrap();
+        let token_id = issued.token.token_id();
+
+        let test_cases = vec![
+            "".to_string(),
+            "pat_".to_string(),
+            issued.value.trim_start_matches(PERSONAL_ACCESS_TOKEN_PREFIX).to_string(),
+            format!("{}{}.{}", PERSONAL_ACCESS_TOKEN_PREFIX, user_id, token_id),
+            format!("{}{}.{}.invalid", PERSONAL_ACCESS_TOKEN_PREFIX, user_id, token_id),
+            format_token(&user_id, token_id, &generate_secret()),
+            format_token(&UserId::new(), token_id, &generate_secret()),
+            format_token(&user_id, &uuid::Uuid::new_v4().to_string(), &generate_secret()),
+        ];
+        for value in test_cases {
+            let result = service.authenticate(&value).await;
+            assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::InvalidToken), "{}", value);
+        }
+
+        let secret = generate_secret();
+        let expired = PersonalAccessToken::new(
+            uuid::Uuid::new_v4().to_string(),
+            user_id.clone(),
+            "expired".to_string(),
+            vec![Scope::SubscriptionsRead],
+            hash(&secret),
+            0,
+            1,
+        );
+        repository.save(expired.clone()).await.unwrap();
+        let result = service.authenticate(&format_token(&user_id, expired.token_id(), &secret)).await;
+        assert!(matches!(error(result.unwrap_err()), PersonalAccessTokenError::Expired));
+
+        assert!(service.authenticate(&issued.value).await.is_ok());
+    }
+}
@@ -0,0 +1,273 @@
+use std::{io::Cursor, sync::Arc};
+
+use domain::{
+    profile_icon::{IconFormat, ProfileIcon, ProfileIconError},
+    repository::{object_storage::ObjectStorage, user_repository::UserRepository},
+    user::{user_id::UserId, User},
+};
+use image::{imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+
+use super::ProfileIconService;
+
+pub struct ProfileIconServiceImpl {
+    user_repository: Arc<dyn UserRepository>,
+    object_storage: Arc<dyn ObjectStorage>,
+}
+
+impl ProfileIconServiceImpl {
+    pub fn new(user_repository: Arc<dyn UserRepository>, object_storage: Arc<dyn ObjectStorage>) -> Self {
+        Self { user_repository, object_storage }
+    }
+
+    /// 保存に失敗したアイコンや置き換えたアイコンを削除します
+    ///
+    /// 残ったファイルは参照されないだけのため、削除に失敗しても処理を続けます
+    async fn delete_keys(&self, keys: &[String]) {
+        for key in keys {
+            if let Err(e) = self.object_storage.delete(key).await {
+                tracing::warn!("failed to delete profile icon. key: {}, error: {}", key, e);
+            }
+        }
+    }
+}
+
+/// `ProfileIcon::THUMBNAIL_SIZES`の順にサムネイルを作成します
+///
+/// EXIFの向きを反映してから正方形に切り抜き、メタデータを含まないPNGに書き出します
+fn thumbnails(icon: &ProfileIcon) -> anyhow::Result<Vec<Vec<u8>>> {
+    let format = match icon.format() {
+        IconFormat::Png => ImageFormat::Png,
+        IconFormat::Jpeg => ImageFormat::Jpeg,
+        IconFormat::Webp => ImageFormat::WebP,
+    };
+    let invalid = |e: image::ImageError| ProfileIconError::InvalidImage(e.to_string());
+
+    let mut reader = ImageReader::with_format(Cursor::new(icon.content()), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(ProfileIcon::MAX_DIMENSION);
+    limits.max_image_height = Some(ProfileIcon::MAX_DIMENSION);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(invalid)?;
+    let orientation = decoder.orientation().map_err(invalid)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+    image.apply_orientation(orientation);
+
+    let mut result = vec![];
+    for size in ProfileIcon::THUMBNAIL_SIZES {
+        let thumbnail = image.resize_to_fill(size, size, FilterType::Lanczos3).to_rgba8();
+        let mut content = Cursor::new(vec![]);
+        thumbnail.write_to(&mut content, ImageFormat::Png)?;
+        result.push(content.into_inner());
+    }
+    Ok(result)
+}
+
+impl ProfileIconService for ProfileIconServiceImpl {
+    fn upload(
+        &self, user_id: &UserId, content: Vec<u8>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let icon = ProfileIcon::new(content)?;
+            let user = self.user_repository.find_by_id(&user_id).await?;
+
+            // 画像の展開と縮小は時間がかかるため、非同期のタスクを止めないよう別のスレッドで実行します
+            let thumbnails = tokio::task::spawn_blocking(move || thumbnails(&icon)).await??;
+
+            let icon_id = uuid::Uuid::new_v4().to_string();
+            let mut keys = vec![];
+            for (size, content) in ProfileIcon::THUMBNAIL_SIZES.into_iter().zip(thumbnails) {
+                let key = ProfileIcon::key(&user_id, &icon_id, size);
+                keys.push(key.clone());
+                if let Err(e) = self.object_storage.put(&key, content, ProfileIcon::CONTENT_TYPE).await {
+                    self.delete_keys(&keys).await;
+                    return Err(e.into());
+                }
+            }
+
+            let updated = user.change_profile_icon(keys.first().cloned());
+            if let Err(e) = self.user_repository.update(updated.clone()).await {
+                self.delete_keys(&keys).await;
+                return Err(e.into());
+            }
+            if let Some(path) = user.profile_icon_path() {
+                self.delete_keys(&ProfileIcon::keys(&user_id, path)).await;
+            }
+
+            tracing::info!("profile icon uploaded. user_id: {}, icon_id: {}", user_id, icon_id);
+            Ok(updated)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::file::FileError;
+    use image::{GenericImageView, Rgb, RgbImage};
+    use infrastructure::repository_impl::{
+        in_memory_user_repository::InMemoryUserRepository, local_object_storage::LocalObjectStorage,
+    };
+
+    use super::*;
+
+    const USER_ID: &str = "usr_123e4567-e89b-12d3-a456-426614174000";
+
+    struct Fixture {
+        service: ProfileIconServiceImpl,
+        user_repository: Arc<InMemoryUserRepository>,
+        object_storage: Arc<LocalObjectStorage>,
+        root: std::path::PathBuf,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn user_id() -> UserId {
+        UserId::from_str(USER_ID).unwrap()
+    }
+
+    async fn fixture(profile_icon_path: Option<&str>) -> Fixture {
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, profile_icon_path.map(ToString::to_string));
+        user_repository.create(user.unwrap()).await.unwrap();
+
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let object_storage = Arc::new(LocalObjectStorage::new(&root));
+        let service = ProfileIconServiceImpl::new(user_repository.clone(), object_storage.clone());
+        Fixture { service, user_repository, object_storage, root }
+    }
+
+    /// 左半分が赤、右半分が青の画像です
+    fn image(width: u32, height: u32) -> DynamicImage {
+        let image = RgbImage::from_fn(width, height, |x, _| match x < width / 2 {
+            true => Rgb([
+                255, 0, 0,
+            ]),
+            false => Rgb([
+                0, 0, 255,
+            ]),
+        });
+        DynamicImage::ImageRgb8(image)
+    }
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut content = Cursor::new(vec![]);
+        image.write_to(&mut content, format).unwrap();
+        content.into_inner()
+    }
+
+    /// SOIの直後に、向きが`orientation`のEXIFを挿入します
+    fn with_exif(jpeg: Vec<u8>, orientation: u8) -> Vec<u8> {
+        let mut exif = b"\xff\xe1\x00\x22Exif\0\0MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0".to_vec();
+        exif.push(orientation);
+        exif.extend_from_slice(&[0; 6]);
+        [
+            &jpeg[..2],
+            &exif,
+            &jpeg[2..],
+        ]
+        .concat()
+    }
+
+    fn error(e: anyhow::Error) -> ProfileIconError {
+        e.downcast::<ProfileIconError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload() {
+        let test_cases = vec![
+            encode(&image(300, 200), ImageFormat::Png),
+            encode(&image(300, 200), ImageFormat::Jpeg),
+            encode(&image(300, 200), ImageFormat::WebP),
+        ];
+        for content in test_cases {
+            let fixture = fixture(None).await;
+            let user = fixture.service.upload(&user_id(), content).await.unwrap();
+
+            let path = user.profile_icon_path().clone().unwrap();
+            assert_eq!(
+                user.profile_icon_path(),
+                fixture.user_repository.find_by_id(&user_id()).await.unwrap().profile_icon_path()
+            );
+            let keys = ProfileIcon::keys(&user_id(), &path);
+            assert_eq!(path, keys[0]);
+            for (key, size) in keys.iter().zip(ProfileIcon::THUMBNAIL_SIZES) {
+                let content = fixture.object_storage.get(key).await.unwrap();
+                assert_eq!(Ok(IconFormat::Png), IconFormat::detect(&content));
+                let thumbnail = image::load_from_memory(&content).unwrap();
+                assert_eq!((size, size), thumbnail.dimensions());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_strip_exif() {
+        let fixture = fixture(None).await;
+        let jpeg = with_exif(encode(&image(300, 100), ImageFormat::Jpeg), 6);
+        assert!(jpeg.windows(4).any(|v| v == b"Exif"));
+
+        let user = fixture.service.upload(&user_id(), jpeg).await.unwrap();
+        let content = fixture.object_storage.get(user.profile_icon_path().as_ref().unwrap()).await.unwrap();
+        assert!(!content.windows(4).any(|v| v == b"Exif" || v == b"eXIf"));
+
+        // 時計回りに90度回転し、左半分の赤が上になります
+        let thumbnail = image::load_from_memory(&content).unwrap().to_rgb8();
+        let top = thumbnail.get_pixel(128, 10);
+        let bottom = thumbnail.get_pixel(128, 245);
+        assert!(top[0] > 200 && top[2] < 50, "{:?}", top);
+        assert!(bottom[2] > 200 && bottom[0] < 50, "{:?}", bottom);
+    }
+
+    #[tokio::test]
+    async fn test_upload_replace() {
+        let old = ProfileIcon::key(&user_id(), "old", 256);
+        let fixture = fixture(Some(&old)).await;
+        for key in ProfileIcon::keys(&user_id(), &old) {
+            fixture.object_storage.put(&key, b"png".to_vec(), ProfileIcon::CONTENT_TYPE).await.unwrap();
+        }
+
+        let user = fixture.service.upload(&user_id(), encode(&image(10, 10), ImageFormat::Png)).await.unwrap();
+        assert_ne!(Some(old.clone()), user.profile_icon_path().clone());
+        for key in ProfileIcon::keys(&user_id(), &old) {
+            assert!(matches!(fixture.object_storage.get(&key).await, Err(FileError::NotFound(_))), "{}", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_failed() {
+        let mut truncated = encode(&image(300, 200), ImageFormat::Png);
+        truncated.truncate(64);
+        let test_cases = vec![
+            (b"GIF89a".to_vec(), ProfileIconError::UnsupportedFormat),
+            (
+                vec![
+                    0xff, 0xd8, 0xff,
+                ],
+                ProfileIconError::InvalidImage(String::new()),
+            ),
+            (truncated, ProfileIconError::InvalidImage(String::new())),
+            (
+                encode(&image(ProfileIcon::MAX_DIMENSION + 1, 1), ImageFormat::Png),
+                ProfileIconError::InvalidImage(String::new()),
+            ),
+            (vec![0; ProfileIcon::MAX_BYTES + 1], ProfileIconError::TooLarge(ProfileIcon::MAX_BYTES + 1)),
+        ];
+        for (content, expected) in test_cases {
+            let fixture = fixture(None).await;
+            let result = error(fixture.service.upload(&user_id(), content).await.unwrap_err());
+            match expected {
+                ProfileIconError::InvalidImage(_) => assert!(matches!(result, ProfileIconError::InvalidImage(_))),
+                _ => assert_eq!(expected, result),
+            }
+            let user = fixture.user_repository.find_by_id(&user_id()).await.unwrap();
+            assert!(user.profile_icon_path().is_none());
+            assert!(!fixture.root.exists());
+        }
+    }
+}
@@ -0,0 +1,511 @@
+use std::{sync::Arc, time::Duration};
+
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use base64::Engine;
+use domain::{
+    clock::{Clock, SystemClock},
+    refresh_token::{Device, RefreshTokenError, RefreshTokenFamily},
+    repository::refresh_token_repository::RefreshTokenRepository,
+    user::user_id::UserId,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::{RefreshTokenService, Rotation, VerifiedRefreshToken};
+
+const SECRET_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const ENCRYPTION_KEY_CONTEXT: &[u8] = b"subtrack.refresh_token.upstream";
+
+pub struct RefreshTokenServiceImpl {
+    repository: Arc<dyn RefreshTokenRepository>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl RefreshTokenServiceImpl {
+    pub fn new(repository: Arc<dyn RefreshTokenRepository>, ttl: Duration) -> Self {
+        Self { repository, ttl, clock: Arc::new(SystemClock) }
+    }
+
+    /// 有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn find(&self, family_id: &str) -> Result<RefreshTokenFamily, RefreshTokenError> {
+        self.repository.find_by_family_id(family_id).await.map_err(|e| match e {
+            RefreshTokenError::NotFound(_) => RefreshTokenError::InvalidToken,
+            e => e,
+        })
+    }
+
+    /// 提示されたトークンが系列の最新のトークンであることを確認し、系列とシークレットを返します
+    ///
+    /// 使用済みのトークンが提示された場合は系列全体を無効化します
+    async fn check(&self, refresh_token: &str) -> Result<(RefreshTokenFamily, Vec<u8>), RefreshTokenError> {
+        let (family_id, secret) = parse_token(refresh_token)?;
+        let family = self.find(&family_id).await?;
+        let token_hash = hash(&secret);
+
+        if family.revoked() {
+            return Err(RefreshTokenError::Revoked(family_id));
+        }
+        if family.is_used(&token_hash) {
+            return Err(self.revoke_on_reuse(&family).await);
+        }
+        if family.token_hash() != token_hash {
+            return Err(RefreshTokenError::InvalidToken);
+        }
+        if family.is_expired(self.clock.now()) {
+            return Err(RefreshTokenError::Expired);
+        }
+        Ok((family, secret))
+    }
+
+    /// 使用済みトークンの再利用を検知した場合、系列全体を無効化します
+    async fn revoke_on_reuse(&self, family: &RefreshTokenFamily) -> RefreshTokenError {
+        tracing::error!(
+            "refresh token reuse detected. family_id: {}, user_id: {}",
+            family.family_id(),
+            family.user_id()
+        );
+        if let Err(e) = self.repository.revoke(family.family_id()).await {
+            return e;
+        }
+        RefreshTokenError::ReuseDetected(family.family_id().to_string())
+    }
+}
+
+impl RefreshTokenService for RefreshTokenServiceImpl {
+    fn issue(
+        &self, user_id: &UserId, upstream_refresh_token: &str, device: Device,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<String>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let upstream_refresh_token = upstream_refresh_token.to_string();
+
+        Box::pin(async move {
+            let family_id = uuid::Uuid::new_v4().to_string();
+            let secret = generate_secret();
+            let now = self.clock.now();
+            let family = RefreshTokenFamily::new(
+                family_id.clone(),
+                user_id,
+                hash(&secret),
+                vec![],
+                encrypt(&secret, &upstream_refresh_token)?,
+                false,
+                device,
+                now,
+                now,
+                now + self.ttl.as_secs() as i64,
+            );
+            self.repository.create(family).await?;
+
+            Ok(format_token(&family_id, &secret))
+        })
+    }
+
+    fn verify(
+        &self, refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<VerifiedRefreshToken>> + Send + '_>> {
+        let refresh_token = refresh_token.to_string();
+
+        Box::pin(async move {
+            let (family, secret) = self.check(&refresh_token).await?;
+            Ok(VerifiedRefreshToken {
+                user_id: family.user_id().clone(),
+                session_id: family.family_id().to_string(),
+                upstream_refresh_token: decrypt(&secret, family.upstream())?,
+            })
+        })
+    }
+
+    fn rotate(
+        &self, refresh_token: &str, ip: &str, upstream_refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Rotation>> + Send + '_>> {
+        let refresh_token = refresh_token.to_string();
+        let ip = ip.to_string();
+        let upstream_refresh_token = upstream_refresh_token.to_string();
+
+        Box::pin(async move {
+            let (family, _) = self.check(&refresh_token).await?;
+            let new_secret = generate_secret();
+            let rotated = family
+                .rotate(hash(&new_secret), encrypt(&new_secret, &upstream_refresh_token)?)
+                .seen(&ip, self.clock.now());
+
+            match self.repository.rotate(rotated, family.token_hash()).await {
+                Ok(_) => Ok(Rotation {
+                    user_id: family.user_id().clone(),
+                    upstream_refresh_token,
+                    refresh_token: format_token(family.family_id(), &new_secret),
+                }),
+                // 同じトークンが同時に使用された場合も再利用とみなします
+                Err(RefreshTokenError::Conflict(_)) => Err(self.revoke_on_reuse(&family).await.into()),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn list_sessions(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<RefreshTokenFamily>>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let now = self.clock.now();
+            let mut sessions: Vec<_> =
+                self.repository.find_by_user_id(&user_id).await?.into_iter().filter(|v| v.is_active(now)).collect();
+            sessions.sort_by_key(|v| std::cmp::Reverse(v.last_seen_at()));
+            Ok(sessions)
+        })
+    }
+
+    fn revoke_session(
+        &self, user_id: &UserId, session_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        let user_id = user_id.clone();
+        let session_id = session_id.to_string();
+
+        Box::pin(async move {
+            // 他のユーザーのセッションの有無を推測されないよう、存在しない場合と同じエラーを返します
+            let family = match self.repository.find_by_family_id(&session_id).await {
+                Ok(family) if family.user_id() == &user_id && family.is_active(self.clock.now()) => family,
+                Ok(_) | Err(RefreshTokenError::NotFound(_)) => {
+                    return Err(RefreshTokenError::SessionNotFound(session_id).into());
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            self.repository.revoke(family.family_id()).await?;
+            tracing::info!("session revoked. user_id: {}, family_id: {}", user_id, session_id);
+            Ok(())
+        })
+    }
+
+    fn revoke_all_sessions(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<usize>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let now = self.clock.now();
+            let mut count = 0;
+            for family in self.repository.find_by_user_id(&user_id).await? {
+                if family.is_active(now) {
+                    self.repository.revoke(family.family_id()).await?;
+                    count += 1;
+                }
+            }
+            tracing::info!("all sessions revoked. user_id: {}, count: {}", user_id, count);
+            Ok(count)
+        })
+    }
+}
+
+fn generate_secret() -> [u8; SECRET_LENGTH] {
+    let mut secret = [0u8; SECRET_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// クライアントに渡すトークンは`{family_id}.{secret}`の形式です
+fn format_token(family_id: &str, secret: &[u8]) -> String {
+    format!("{}.{}", family_id, base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret))
+}
+
+fn parse_token(token: &str) -> Result<(String, Vec<u8>), RefreshTokenError> {
+    let (family_id, secret) = token.split_once('.').ok_or(RefreshTokenError::InvalidToken)?;
+    uuid::Uuid::parse_str(family_id).map_err(|_| RefreshTokenError::InvalidToken)?;
+    let secret =
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(secret).map_err(|_| RefreshTokenError::InvalidToken)?;
+    if secret.len() != SECRET_LENGTH {
+        return Err(RefreshTokenError::InvalidToken);
+    }
+    Ok((family_id.to_string(), secret))
+}
+
+fn hash(secret: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(secret))
+}
+
+/// 認証基盤のリフレッシュトークンは、クライアントが持つシークレットから導出した鍵で暗号化して保存します
+fn cipher(secret: &[u8]) -> Aes256Gcm {
+    let key = Sha256::new().chain_update(ENCRYPTION_KEY_CONTEXT).chain_update(secret).finalize();
+    Aes256Gcm::new(&key)
+}
+
+fn encrypt(secret: &[u8], plaintext: &str) -> Result<String, RefreshTokenError> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = cipher(secret)
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|e| RefreshTokenError::CryptoError(e.to_string()))?;
+    let mut bytes = nonce.to_vec();
+    bytes.extend(ciphertext);
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+fn decrypt(secret: &[u8], encoded: &str) -> Result<String, RefreshTokenError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| RefreshTokenError::CryptoError(e.to_string()))?;
+    if bytes.len() < NONCE_LENGTH {
+        return Err(RefreshTokenError::CryptoError("ciphertext too short".to_string()));
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+
+    let plaintext = cipher(secret)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| RefreshTokenError::CryptoError(e.to_string()))?;
+    String::from_utf8(plaintext).map_err(|e| RefreshTokenError::CryptoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use infrastructure::repository_impl::in_memory_refresh_token_repository::InMemoryRefreshTokenRepository;
+
+    use super::*;
+
+    const UPSTREAM: &str = "cognito_refresh_token";
+    const TTL: Duration = Duration::from_secs(60 * 60);
+    const IP: &str = "198.51.100.1";
+
+    fn device() -> Device {
+        Device::new("Chrome on macOS".to_string(), "Mozilla/5.0".to_string(), "192.0.2.1".to_string())
+    }
+
+    fn service() -> (RefreshTokenServiceImpl, Arc<InMemoryRefreshTokenRepository>) {
+        let repository = Arc::new(InMemoryRefreshTokenRepository::new());
+        (RefreshTokenServiceImpl::new(repository.clone(), TTL), repository)
+    }
+
+    fn error(e: anyhow::Error) -> RefreshTokenError {
+        e.downcast::<RefreshTokenError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_rotate_success() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+
+        let token = service.issue(&user_id, UPSTREAM, device()).await.unwrap();
+        let result = service.rotate(&token, IP, UPSTREAM).await.unwrap();
+        assert_eq!(user_id, result.user_id);
+        assert_eq!(UPSTREAM, result.upstream_refresh_token);
+        assert_ne!(token, result.refresh_token);
+
+        let result = service.rotate(&result.refresh_token, IP, UPSTREAM).await.unwrap();
+        assert_eq!(UPSTREAM, result.upstream_refresh_token);
+    }
+
+    #[tokio::test]
+    async fn test_issue_stores_only_hash() {
+        let (service, repository) = service();
+
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+        let (family_id, secret) = parse_token(&token).unwrap();
+        let family = repository.find_by_family_id(&family_id).await.unwrap();
+
+        let encoded_secret = token.split_once('.').unwrap().1;
+        assert_eq!(hash(&secret), family.token_hash());
+        assert!(!family.token_hash().contains(encoded_secret));
+        assert!(!family.upstream().contains(UPSTREAM));
+    }
+
+    /// 攻撃者が盗んだトークンを先に使用し、その後正規ユーザーが同じトークンを使用するケースです
+    #[tokio::test]
+    async fn test_rotate_theft_revokes_family() {
+        let (service, repository) = service();
+        let stolen = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+
+        let attacker = service.rotate(&stolen, IP, UPSTREAM).await.unwrap();
+
+        let result = service.rotate(&stolen, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::ReuseDetected(_)));
+
+        let (family_id, _) = parse_token(&stolen).unwrap();
+        assert!(repository.find_by_family_id(&family_id).await.unwrap().revoked());
+
+        let result = service.rotate(&attacker.refresh_token, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::Revoked(_)));
+    }
+
+    /// 正規ユーザーが先にローテーションし、その後攻撃者が古いトークンを使用するケースです
+    #[tokio::test]
+    async fn test_rotate_theft_after_legitimate_rotation() {
+        let (service, _) = service();
+        let stolen = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+
+        let legitimate = service.rotate(&stolen, IP, UPSTREAM).await.unwrap();
+        let legitimate = service.rotate(&legitimate.refresh_token, IP, UPSTREAM).await.unwrap();
+
+        let result = service.rotate(&stolen, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::ReuseDetected(_)));
+
+        let result = service.rotate(&legitimate.refresh_token, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::Revoked(_)));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_concurrent_use_revokes_family() {
+        let (service, _) = service();
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+
+        let (first, second) = tokio::join!(service.rotate(&token, IP, UPSTREAM), service.rotate(&token, IP, UPSTREAM));
+        let winner = match (first, second) {
+            (Ok(v), Err(_)) | (Err(_), Ok(v)) => v,
+            _ => panic!("exactly one rotation must succeed"),
+        };
+        let result = service.rotate(&winner.refresh_token, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::Revoked(_)));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_expired() {
+        let repository = Arc::new(InMemoryRefreshTokenRepository::new());
+        let service = RefreshTokenServiceImpl::new(repository, Duration::ZERO);
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+
+        let result = service.rotate(&token, IP, UPSTREAM).await;
+        assert!(matches!(error(result.unwrap_err()), RefreshTokenError::Expired));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_invalid_token() {
+        let (service, _) = service();
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+        let (family_id, _) = parse_token(&token).unwrap();
+
+        let test_cases = vec![
+            "".to_string(),
+            "invalid".to_string(),
+            format!("{}.invalid", family_id),
+            format_token(&family_id, &generate_secret()),
+            format_token(&uuid::Uuid::new_v4().to_string(), &generate_secret()),
+        ];
+        for value in test_cases {
+            let result = service.rotate(&value, IP, UPSTREAM).await;
+            assert!(matches!(error(result.unwrap_err()), RefreshTokenError::InvalidToken), "{}", value);
+        }
+
+        assert!(service.rotate(&token, IP, UPSTREAM).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_rotate_upstream() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let token = service.issue(&user_id, UPSTREAM, device()).await.unwrap();
+
+        let verified = service.verify(&token).await.unwrap();
+        assert_eq!(user_id, verified.user_id);
+        assert_eq!(parse_token(&token).unwrap().0, verified.session_id);
+        assert_eq!(UPSTREAM, verified.upstream_refresh_token);
+
+        let rotation = service.rotate(&token, IP, "rotated_upstream").await.unwrap();
+        assert_eq!("rotated_upstream", rotation.upstream_refresh_token);
+
+        let verified = service.verify(&rotation.refresh_token).await.unwrap();
+        assert_eq!("rotated_upstream", verified.upstream_refresh_token);
+        assert!(matches!(error(service.verify(&token).await.unwrap_err()), RefreshTokenError::ReuseDetected(_)));
+    }
+
+    /// 認証基盤での更新に失敗した後の再試行を再利用とみなさないことを確認します
+    #[tokio::test]
+    async fn test_verify_does_not_consume_token() {
+        let (service, repository) = service();
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+        let (family_id, _) = parse_token(&token).unwrap();
+
+        service.verify(&token).await.unwrap();
+        service.verify(&token).await.unwrap();
+        assert!(!repository.find_by_family_id(&family_id).await.unwrap().revoked());
+
+        assert!(service.rotate(&token, IP, UPSTREAM).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_rotate_records_session() {
+        let (service, repository) = service();
+        let token = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+        let (family_id, _) = parse_token(&token).unwrap();
+
+        let family = repository.find_by_family_id(&family_id).await.unwrap();
+        assert_eq!(&device(), family.device());
+        assert_eq!(family.created_at(), family.last_seen_at());
+
+        service.rotate(&token, IP, UPSTREAM).await.unwrap();
+        let family = repository.find_by_family_id(&family_id).await.unwrap();
+        assert_eq!(IP, family.device().ip());
+        assert_eq!(device().label(), family.device().label());
+        assert!(family.last_seen_at() >= family.created_at());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_revoke_sessions() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let first = service.issue(&user_id, UPSTREAM, device()).await.unwrap();
+        let second = service.issue(&user_id, UPSTREAM, device()).await.unwrap();
+        let other = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+        let (first_id, _) = parse_token(&first).unwrap();
+        let (other_id, _) = parse_token(&other).unwrap();
+
+        assert_eq!(2, service.list_sessions(&user_id).await.unwrap().len());
+
+        service.revoke_session(&user_id, &first_id).await.unwrap();
+        let sessions = service.list_sessions(&user_id).await.unwrap();
+        assert_eq!(1, sessions.len());
+        assert_eq!(parse_token(&second).unwrap().0, sessions[0].family_id());
+        assert!(matches!(
+            error(service.rotate(&first, IP, UPSTREAM).await.unwrap_err()),
+            RefreshTokenError::Revoked(_)
+        ));
+
+        let test_cases = vec![
+            first_id,
+            other_id,
+            uuid::Uuid::new_v4().to_string(),
+        ];
+        for session_id in test_cases {
+            let result = service.revoke_session(&user_id, &session_id).await;
+            assert!(matches!(error(result.unwrap_err()), RefreshTokenError::SessionNotFound(_)), "{}", session_id);
+        }
+        assert!(service.rotate(&other, IP, UPSTREAM).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let (service, _) = service();
+        let user_id = UserId::new();
+        let tokens = vec![
+            service.issue(&user_id, UPSTREAM, device()).await.unwrap(),
+            service.issue(&user_id, UPSTREAM, device()).await.unwrap(),
+        ];
+        let other = service.issue(&UserId::new(), UPSTREAM, device()).await.unwrap();
+
+        assert_eq!(2, service.revoke_all_sessions(&user_id).await.unwrap());
+        assert!(service.list_sessions(&user_id).await.unwrap().is_empty());
+        for token in tokens {
+            assert!(matches!(
+                error(service.rotate(&token, IP, UPSTREAM).await.unwrap_err()),
+                RefreshTokenError::Revoked(_)
+            ));
+        }
+        assert!(service.rotate(&other, IP, UPSTREAM).await.is_ok());
+        assert_eq!(0, service.revoke_all_sessions(&user_id).await.unwrap());
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let secret = generate_secret();
+        let encrypted = encrypt(&secret, UPSTREAM).unwrap();
+
+        assert_eq!(UPSTREAM, decrypt(&secret, &encrypted).unwrap());
+        assert!(decrypt(&generate_secret(), &encrypted).is_err());
+        assert!(decrypt(&secret, "").is_err());
+    }
+}
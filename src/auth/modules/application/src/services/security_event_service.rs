@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use domain::{
+    clock::{Clock, SystemClock},
+    repository::security_event_repository::SecurityEventRepository,
+    security_event::{SecurityEvent, SecurityEventError},
+};
+
+use super::{SecurityEventQuery, SecurityEventService};
+
+/// 件数を指定しない場合に返すイベントの数です
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 100;
+
+pub struct SecurityEventServiceImpl {
+    repository: Arc<dyn SecurityEventRepository>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SecurityEventServiceImpl {
+    pub fn new(repository: Arc<dyn SecurityEventRepository>) -> Self {
+        Self { repository, clock: Arc::new(SystemClock) }
+    }
+
+    /// 検索期間の既定値に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl SecurityEventService for SecurityEventServiceImpl {
+    fn record(
+        &self, event: SecurityEvent,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            self.repository.save(event).await?;
+            Ok(())
+        })
+    }
+
+    fn search(
+        &self, query: SecurityEventQuery,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<Vec<SecurityEvent>>> + Send + '_>> {
+        Box::pin(async move {
+            let from = query.from.unwrap_or(0);
+            // 現在の秒に発生したイベントも含めます
+            let to = query.to.unwrap_or_else(|| self.clock.now() + 1);
+            if from < 0 || from >= to {
+                return Err(SecurityEventError::InvalidTimeRange(format!("from: {}, to: {}", from, to)).into());
+            }
+            let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+            let events = match (query.user_id, query.event_type) {
+                (Some(user_id), event_type) => {
+                    self.repository.find_by_user_id(&user_id, event_type, from, to, limit).await?
+                }
+                (None, Some(event_type)) => self.repository.find_by_event_type(event_type, from, to, limit).await?,
+                (None, None) => return Err(SecurityEventError::FilterRequired.into()),
+            };
+            Ok(events)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{clock::FixedClock, security_event::SecurityEventType, user::user_id::UserId};
+    use infrastructure::repository_impl::in_memory_security_event_repository::InMemorySecurityEventRepository;
+
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn event(event_id: &str, event_type: SecurityEventType, occurred_at: i64) -> SecurityEvent {
+        SecurityEvent::occurred(event_id.to_string(), event_type, occurred_at, "192.0.2.1".to_string(), "".to_string())
+    }
+
+    fn ids(events: &[SecurityEvent]) -> String {
+        events.iter().map(|v| v.event_id()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let service = SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))
+            .with_clock(Arc::new(FixedClock(NOW)));
+        let user_id = UserId::new();
+        let events = vec![
+            event("1", SecurityEventType::SignUp, NOW - 300).with_user_id(user_id.clone()),
+            event("2", SecurityEventType::LoginFailed, NOW - 200).with_email("hoge@email.com".to_string()),
+            event("3", SecurityEventType::LoginSucceeded, NOW - 100).with_user_id(user_id.clone()),
+            event("4", SecurityEventType::LoginFailed, NOW).with_user_id(user_id.clone()),
+        ];
+        for event in events {
+            service.record(event).await.unwrap();
+        }
+
+        let test_cases = vec![
+            (Some(user_id.clone()), None, None, None, None, "4 3 1"),
+            (Some(user_id.clone()), Some(SecurityEventType::LoginFailed), None, None, None, "4"),
+            (Some(user_id.clone()), None, Some(NOW - 300), Some(NOW), None, "3 1"),
+            (Some(user_id.clone()), None, None, None, Some(2), "4 3"),
+            // 件数は1件以上に補正します
+            (Some(user_id.clone()), None, None, None, Some(0), "4"),
+            (None, Some(SecurityEventType::LoginFailed), None, None, None, "4 2"),
+            (Some(UserId::new()), None, None, None, None, ""),
+        ];
+        for (user_id, event_type, from, to, limit, expected) in test_cases {
+            let query = SecurityEventQuery { user_id, event_type, from, to, limit };
+            let result = service.search(query.clone()).await.unwrap();
+            assert_eq!(expected, ids(&result), "{:?}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_invalid() {
+        let service = SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()));
+
+        let test_cases = vec![
+            (Some(UserId::new()), Some(-1), None),
+            (Some(UserId::new()), Some(100), Some(100)),
+            (Some(UserId::new()), Some(200), Some(100)),
+            (None, None, None),
+        ];
+        for (user_id, from, to) in test_cases {
+            let query = SecurityEventQuery { user_id: user_id.clone(), event_type: None, from, to, limit: None };
+            let error = service.search(query).await.unwrap_err();
+            let error = error.downcast_ref::<SecurityEventError>().unwrap();
+            if user_id.is_some() {
+                assert!(matches!(error, SecurityEventError::InvalidTimeRange(_)), "{:?} {:?}", from, to);
+            } else {
+                assert!(matches!(error, SecurityEventError::FilterRequired));
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use domain::{
+    clock::{Clock, SystemClock},
+    repository::user_repository::UserRepository,
+    user::{email::Email, name::Name, role::Role, user_error::UserError, user_id::UserId, User},
+};
+
+use super::UserService;
+
+pub struct UserServiceImpl {
+    user_repository: Arc<dyn UserRepository>,
+    guest_ttl: std::time::Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl UserServiceImpl {
+    /// `guest_ttl`は登録されなかったゲストユーザーを削除するまでの期間です
+    pub fn new(user_repository: Arc<dyn UserRepository>, guest_ttl: std::time::Duration) -> Self {
+        Self { user_repository, guest_ttl, clock: Arc::new(SystemClock) }
+    }
+
+    /// ゲストユーザーの有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl UserService for UserServiceImpl {
+    fn find_by_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let user = self.user_repository.find_by_id(&user_id).await?;
+            // TTLによる削除は遅延するため、期限切れのゲストユーザーが残っている場合があります
+            if user.is_expired(self.clock.now()) {
+                return Err(UserError::NotFound(user_id.to_string()).into());
+            }
+            Ok(user)
+        })
+    }
+
+    fn find_by_email(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+        let email = email.clone();
+
+        Box::pin(async move {
+            let user = self.user_repository.find_by_email(&email).await?;
+            if user.is_expired(self.clock.now()) {
+                return Err(UserError::NotFound(email.to_string()).into());
+            }
+            Ok(user)
+        })
+    }
+
+    fn exists_by_email(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<bool>> + Send + '_>> {
+        let email = email.clone();
+
+        Box::pin(async move { Ok(self.user_repository.exists_by_email(&email).await?) })
+    }
+
+    fn create_user(
+        &self, user: User,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            self.user_repository.create(user).await?;
+            Ok(())
+        })
+    }
+
+    fn update(
+        &self, user: User,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            self.user_repository.update(user).await?;
+            Ok(())
+        })
+    }
+
+    fn new_guest(&self, name: Name) -> User {
+        User::guest(UserId::new(), name, self.clock.now() + self.guest_ttl.as_secs() as i64)
+    }
+
+    fn change_role(
+        &self, user_id: &UserId, role: Role,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let user = self.find_by_id(&user_id).await?.with_role(role);
+            self.user_repository.update(user.clone()).await?;
+            Ok(user)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use domain::clock::FixedClock;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InMemoryUserRepository {
+        users: Mutex<HashMap<String, User>>,
+    }
+
+    impl UserRepository for InMemoryUserRepository {
+        fn find_by_id(
+            &self, id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>> {
+            let result = self.users.lock().unwrap().get(&id.to_string()).cloned();
+            let id = id.to_string();
+            Box::pin(async move { result.ok_or(UserError::NotFound(id)) })
+        }
+
+        fn find_by_email(
+            &self, email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>> {
+            let result = self.users.lock().unwrap().values().find(|v| v.email() == email).cloned();
+            let email = email.to_string();
+            Box::pin(async move { result.ok_or(UserError::NotFound(email)) })
+        }
+
+        fn exists_by_email(
+            &self, email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, UserError>> + Send + '_>> {
+            let result = self.users.lock().unwrap().values().any(|v| v.email() == email);
+            Box::pin(async move { Ok(result) })
+        }
+
+        fn create(
+            &self, user: User,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+            self.users.lock().unwrap().insert(user.user_id().to_string(), user);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn update(
+            &self, user: User,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+            let result = match self.users.lock().unwrap().get_mut(&user.user_id().to_string()) {
+                Some(v) => {
+                    *v = user;
+                    Ok(())
+                }
+                None => Err(UserError::UpdateUserError(user.user_id().to_string())),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn delete(
+            &self, id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+            self.users.lock().unwrap().remove(&id.to_string());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    const USER_ID: &str = "usr_123e4567-e89b-12d3-a456-426614174000";
+    const NOW: i64 = 1_700_000_000;
+
+    fn service() -> UserServiceImpl {
+        UserServiceImpl::new(Arc::new(InMemoryUserRepository::default()), std::time::Duration::from_secs(60))
+            .with_clock(Arc::new(FixedClock(NOW)))
+    }
+
+    fn user_id() -> UserId {
+        use std::str::FromStr;
+        UserId::from_str(USER_ID).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_user_and_find_by_id_success() {
+        let service = service();
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap();
+
+        assert!(service.create_user(user).await.is_ok());
+
+        let result = service.find_by_id(&user_id()).await;
+        assert!(result.is_ok());
+        assert_eq!("hoge@email.com", result.unwrap().email().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id_failed() {
+        let result = service().find_by_id(&user_id()).await;
+        assert!(result.is_err());
+        assert!(result.unwrap_err().downcast_ref::<UserError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email() {
+        use std::str::FromStr;
+
+        let service = service();
+        let email = Email::from_str("hoge@email.com").unwrap();
+        assert!(!service.exists_by_email(&email).await.unwrap());
+        let result = service.find_by_email(&email).await;
+        assert!(matches!(result.unwrap_err().downcast_ref::<UserError>(), Some(UserError::NotFound(_))));
+
+        service.create_user(User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap()).await.unwrap();
+        assert!(service.exists_by_email(&email).await.unwrap());
+        assert_eq!(USER_ID, service.find_by_email(&email).await.unwrap().user_id().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_expired_guest() {
+        use std::str::FromStr;
+
+        let service = UserServiceImpl::new(Arc::new(InMemoryUserRepository::default()), std::time::Duration::ZERO);
+        let guest = service.new_guest(Name::from_str("Guest").unwrap());
+        service.create_user(guest.clone()).await.unwrap();
+
+        let result = service.find_by_email(guest.email()).await;
+        assert!(matches!(result.unwrap_err().downcast_ref::<UserError>(), Some(UserError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_success() {
+        let service = service();
+        service.create_user(User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap()).await.unwrap();
+
+        let updated = User::build(USER_ID, "hoge@email.com", "fuga", 1, None).unwrap();
+        assert!(service.update(updated).await.is_ok());
+        assert_eq!("fuga", service.find_by_id(&user_id()).await.unwrap().name().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_new_guest_and_register_success() {
+        use std::str::FromStr;
+
+        let service = service();
+        let guest = service.new_guest(Name::from_str("Guest").unwrap());
+        service.create_user(guest.clone()).await.unwrap();
+        assert!(guest.is_guest());
+        assert_eq!(Some(NOW + 60), guest.expires_at());
+
+        let found = service.find_by_id(guest.user_id()).await.unwrap();
+        assert!(found.is_guest());
+
+        let email = domain::user::email::Email::from_str("hoge@email.com").unwrap();
+        service.update(found.register(email, Name::from_str("hoge").unwrap())).await.unwrap();
+        let pending = service.find_by_id(guest.user_id()).await.unwrap();
+        assert!(pending.is_guest());
+
+        service.update(pending.complete_registration()).await.unwrap();
+        let result = service.find_by_id(guest.user_id()).await.unwrap();
+        assert!(!result.is_guest());
+        assert_eq!(None, result.expires_at());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id_expired_guest() {
+        use std::str::FromStr;
+
+        let service = UserServiceImpl::new(Arc::new(InMemoryUserRepository::default()), std::time::Duration::ZERO);
+        let guest = service.new_guest(Name::from_str("Guest").unwrap());
+        service.create_user(guest.clone()).await.unwrap();
+
+        let result = service.find_by_id(guest.user_id()).await;
+        assert!(matches!(result.unwrap_err().downcast_ref::<UserError>(), Some(UserError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_change_role() {
+        let service = service();
+        service.create_user(User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap()).await.unwrap();
+
+        let result = service.change_role(&user_id(), Role::Support).await.unwrap();
+        assert_eq!(Role::Support, result.role());
+        assert_eq!(Role::Support, service.find_by_id(&user_id()).await.unwrap().role());
+
+        let result = service.change_role(&UserId::new(), Role::Admin).await;
+        assert!(matches!(result.unwrap_err().downcast_ref::<UserError>(), Some(UserError::NotFound(_))));
+    }
+}
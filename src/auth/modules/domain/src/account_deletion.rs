@@ -0,0 +1,322 @@
+use crate::user::{email::Email, user_id::UserId};
+
+/// アカウントの削除の依頼と、その進捗です
+///
+/// 削除を開始する日時までは猶予期間としてキャンセルできます
+/// 削除した対象を1つずつ記録するため、中断しても完了した対象を飛ばして再開できます
+/// 完了した後は、個人情報を含まない削除証明として残します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDeletion {
+    deletion_id: String,
+    user_id: UserId,
+    /// 認証基盤からユーザーを削除するために使います。完了またはキャンセルした時点で破棄します
+    email: Option<Email>,
+    status: DeletionStatus,
+    requested_at: i64,
+    /// 削除を開始する日時のUNIX秒です
+    scheduled_at: i64,
+    deleted: Vec<DeletedItems>,
+    completed_at: Option<i64>,
+    /// 削除を実行している処理が、他の処理に実行させない期限のUNIX秒です
+    leased_until: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionStatus {
+    /// 猶予期間中です。キャンセルできます
+    Scheduled,
+    InProgress,
+    Completed,
+    Cancelled,
+}
+
+/// 削除した対象と件数です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedItems {
+    target: String,
+    count: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountDeletionError {
+    #[error("Account deletion not found: {0}")]
+    NotFound(String),
+
+    #[error("Account deletion already requested: {0}")]
+    AlreadyRequested(String),
+
+    /// 猶予期間が過ぎ、削除を開始した依頼はキャンセルできません
+    #[error("Account deletion cannot be cancelled: {0}")]
+    NotCancellable(String),
+
+    #[error("Invalid deletion status: {0}")]
+    InvalidStatus(String),
+
+    /// 他の処理が削除を実行中か、完了やキャンセルにより削除を実行しない依頼のエラーです
+    #[error("Account deletion cannot be claimed: {0}")]
+    NotClaimable(String),
+
+    #[error("Failed to find account deletion: {0}")]
+    FindError(String),
+
+    #[error("Failed to save account deletion: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete user data: {0}")]
+    DeleteError(String),
+}
+
+impl AccountDeletion {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        deletion_id: String, user_id: UserId, email: Option<Email>, status: DeletionStatus, requested_at: i64,
+        scheduled_at: i64, deleted: Vec<DeletedItems>, completed_at: Option<i64>, leased_until: Option<i64>,
+    ) -> Self {
+        Self { deletion_id, user_id, email, status, requested_at, scheduled_at, deleted, completed_at, leased_until }
+    }
+
+    /// `now`から`grace_period`秒が過ぎた後に削除を開始する依頼を作成します
+    pub fn request(deletion_id: String, user_id: UserId, email: Email, now: i64, grace_period: i64) -> Self {
+        Self::new(
+            deletion_id,
+            user_id,
+            Some(email),
+            DeletionStatus::Scheduled,
+            now,
+            now + grace_period,
+            vec![],
+            None,
+            None,
+        )
+    }
+
+    /// 完了もキャンセルもしていない場合に`true`を返します
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, DeletionStatus::Scheduled | DeletionStatus::InProgress)
+    }
+
+    /// `now`の時点で削除を開始または再開する場合に`true`を返します
+    pub fn is_due(&self, now: i64) -> bool {
+        self.is_active() && self.scheduled_at <= now
+    }
+
+    /// `now`の時点で他の処理が削除を実行中の場合に`true`を返します
+    pub fn is_leased(&self, now: i64) -> bool {
+        self.leased_until.is_some_and(|v| now < v)
+    }
+
+    /// `leased_until`まで他の処理に削除を実行させない値を返します
+    pub fn lease(&self, leased_until: i64) -> Self {
+        Self { leased_until: Some(leased_until), ..self.clone() }
+    }
+
+    /// 他の処理がすぐに削除を再開できるよう、期限を破棄した値を返します
+    pub fn release(&self) -> Self {
+        Self { leased_until: None, ..self.clone() }
+    }
+
+    /// 猶予期間中の依頼をキャンセルした値を返します
+    pub fn cancel(&self, now: i64) -> Result<Self, AccountDeletionError> {
+        if self.status != DeletionStatus::Scheduled || self.is_due(now) {
+            return Err(AccountDeletionError::NotCancellable(self.deletion_id.clone()));
+        }
+        Ok(Self { status: DeletionStatus::Cancelled, email: None, ..self.clone() })
+    }
+
+    /// 削除を開始した値を返します
+    pub fn start(&self) -> Self {
+        Self { status: DeletionStatus::InProgress, ..self.clone() }
+    }
+
+    /// `target`を削除済みの場合に`true`を返します
+    pub fn is_deleted(&self, target: &str) -> bool {
+        self.deleted.iter().any(|v| v.target == target)
+    }
+
+    /// `target`から`count`件を削除したことを記録した値を返します
+    pub fn record(&self, target: &str, count: usize) -> Self {
+        let mut deleted = self.deleted.clone();
+        deleted.push(DeletedItems::new(target.to_string(), count));
+        Self { deleted, ..self.clone() }
+    }
+
+    /// 削除を完了し、メールアドレスを破棄した値を返します
+    pub fn complete(&self, now: i64) -> Self {
+        Self { status: DeletionStatus::Completed, email: None, completed_at: Some(now), ..self.clone() }
+    }
+
+    pub fn deletion_id(&self) -> &str {
+        &self.deletion_id
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn email(&self) -> Option<&Email> {
+        self.email.as_ref()
+    }
+
+    pub fn status(&self) -> DeletionStatus {
+        self.status
+    }
+
+    pub fn requested_at(&self) -> i64 {
+        self.requested_at
+    }
+
+    pub fn scheduled_at(&self) -> i64 {
+        self.scheduled_at
+    }
+
+    pub fn deleted(&self) -> &[DeletedItems] {
+        &self.deleted
+    }
+
+    pub fn completed_at(&self) -> Option<i64> {
+        self.completed_at
+    }
+
+    pub fn leased_until(&self) -> Option<i64> {
+        self.leased_until
+    }
+}
+
+impl DeletedItems {
+    pub fn new(target: String, count: usize) -> Self {
+        Self { target, count }
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl std::str::FromStr for DeletionStatus {
+    type Err = AccountDeletionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scheduled" => Ok(DeletionStatus::Scheduled),
+            "in_progress" => Ok(DeletionStatus::InProgress),
+            "completed" => Ok(DeletionStatus::Completed),
+            "cancelled" => Ok(DeletionStatus::Cancelled),
+            _ => Err(AccountDeletionError::InvalidStatus(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for DeletionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeletionStatus::Scheduled => write!(f, "scheduled"),
+            DeletionStatus::InProgress => write!(f, "in_progress"),
+            DeletionStatus::Completed => write!(f, "completed"),
+            DeletionStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn deletion() -> AccountDeletion {
+        AccountDeletion::request(
+            "deletion_id".to_string(),
+            UserId::new(),
+            Email::from_str("hoge@email.com").unwrap(),
+            100,
+            50,
+        )
+    }
+
+    #[test]
+    fn test_request_and_cancel() {
+        let deletion = deletion();
+        assert_eq!(DeletionStatus::Scheduled, deletion.status());
+        assert_eq!(150, deletion.scheduled_at());
+
+        let test_cases = vec![
+            (149, false),
+            (150, true),
+        ];
+        for (now, expected) in test_cases {
+            assert_eq!(expected, deletion.is_due(now), "{}", now);
+        }
+
+        let cancelled = deletion.cancel(149).unwrap();
+        assert_eq!(DeletionStatus::Cancelled, cancelled.status());
+        assert!(cancelled.email().is_none());
+        assert!(!cancelled.is_active());
+        assert!(!cancelled.is_due(150));
+
+        let test_cases = vec![
+            (deletion.clone(), 150),
+            (deletion.start(), 149),
+            (cancelled, 149),
+        ];
+        for (deletion, now) in test_cases {
+            assert!(matches!(deletion.cancel(now), Err(AccountDeletionError::NotCancellable(_))));
+        }
+    }
+
+    #[test]
+    fn test_lease() {
+        let deletion = deletion().start();
+        assert!(!deletion.is_leased(150));
+
+        let leased = deletion.lease(200);
+        assert_eq!(Some(200), leased.leased_until());
+        let test_cases = vec![
+            (199, true),
+            (200, false),
+        ];
+        for (now, expected) in test_cases {
+            assert_eq!(expected, leased.is_leased(now), "{}", now);
+        }
+        assert!(!leased.release().is_leased(150));
+    }
+
+    #[test]
+    fn test_record_and_complete() {
+        let deletion = deletion().start().record("auth", 1).record("subscribe", 3);
+        assert_eq!(DeletionStatus::InProgress, deletion.status());
+        assert!(deletion.is_due(150));
+        assert!(deletion.is_deleted("subscribe"));
+        assert!(!deletion.is_deleted("category"));
+
+        let completed = deletion.complete(200);
+        assert_eq!(DeletionStatus::Completed, completed.status());
+        assert_eq!(Some(200), completed.completed_at());
+        assert!(completed.email().is_none());
+        assert!(!completed.is_due(200));
+        assert_eq!(
+            vec![
+                DeletedItems::new("auth".to_string(), 1),
+                DeletedItems::new("subscribe".to_string(), 3),
+            ],
+            completed.deleted()
+        );
+    }
+
+    #[test]
+    fn test_status_from_str() {
+        let test_cases = vec![
+            DeletionStatus::Scheduled,
+            DeletionStatus::InProgress,
+            DeletionStatus::Completed,
+            DeletionStatus::Cancelled,
+        ];
+        for status in test_cases {
+            assert_eq!(status, DeletionStatus::from_str(&status.to_string()).unwrap());
+        }
+        assert!(DeletionStatus::from_str("deleted").is_err());
+    }
+}
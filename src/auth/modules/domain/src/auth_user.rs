@@ -1,8 +1,12 @@
 use std::str::FromStr;
 
-use crate::user::{
-    email::{Email, EmailError},
-    password::{Password, PasswordError},
+use crate::{
+    token::Token,
+    user::{
+        email::{Email, EmailError},
+        password::{Password, PasswordError},
+        user_id::UserId,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -11,6 +15,25 @@ pub struct AuthUser {
     password: Password,
 }
 
+/// 外部のIDプロバイダーで確認済みのメールアドレスによるサインインの結果です
+///
+/// `created`は認証基盤にユーザーを新しく作成した場合に`true`になります
+#[derive(Debug)]
+pub struct FederatedSignIn {
+    user_id: UserId,
+    token: Token,
+    created: bool,
+}
+
+/// ゲストユーザーの登録の結果です
+///
+/// `user_id`は認証基盤が割り当てた`UserId`です
+#[derive(Debug)]
+pub struct GuestSignUp {
+    user_id: UserId,
+    token: Token,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AuthUserError {
     #[error("{0}")]
@@ -33,10 +56,58 @@ pub enum AuthUserError {
 
     #[error("Invalid password: Password does not meet the required criteria")]
     InvalidPassword,
+
+    #[error("Account locked: retry after {0} seconds")]
+    AccountLocked(u64),
+
+    /// 認証基盤が対応していない操作です
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+}
+
+impl FederatedSignIn {
+    pub fn new(user_id: UserId, token: Token, created: bool) -> Self {
+        Self { user_id, token, created }
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn into_token(self) -> Token {
+        self.token
+    }
+
+    pub fn created(&self) -> bool {
+        self.created
+    }
+}
+
+impl GuestSignUp {
+    pub fn new(user_id: UserId, token: Token) -> Self {
+        Self { user_id, token }
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn into_token(self) -> Token {
+        self.token
+    }
 }
 
 impl AuthUser {
-    fn new(email: Email, password: Password) -> Self {
+    /// パスワードポリシーで検証済みのパスワードから作成します
+    pub fn new(email: Email, password: Password) -> Self {
         Self { email, password }
     }
 
@@ -46,6 +117,24 @@ impl AuthUser {
 
         Ok(Self::new(email, password))
     }
+
+    /// ログイン時に入力されたメールアドレスとパスワードから作成します
+    ///
+    /// パスワードはポリシーで検証しません。ポリシーを厳しくしても既存のユーザーがログインできるようにするためです
+    pub fn credentials(email: &str, password: &str) -> Result<Self, AuthUserError> {
+        let email = Email::from_str(email)?;
+        let password = Password::for_authentication(password)?;
+
+        Ok(Self::new(email, password))
+    }
+
+    pub fn email(&self) -> &Email {
+        &self.email
+    }
+
+    pub fn password(&self) -> &Password {
+        &self.password
+    }
 }
 
 #[cfg(test)]
@@ -62,7 +151,7 @@ mod tests {
 
         let result = result.unwrap();
         assert_eq!(email, result.email.to_string());
-        assert_eq!(password, result.password.to_string())
+        assert_eq!(password, result.password.expose_secret())
     }
 
     #[test]
@@ -73,4 +162,17 @@ mod tests {
         let result = AuthUser::build(email, password);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_credentials() {
+        let test_cases = vec![
+            ("test@email.com", "weak", true),
+            ("test@email.com", "", false),
+            ("testemail.com", "Password123", false),
+        ];
+        for (email, password, expected) in test_cases {
+            let result = AuthUser::credentials(email, password);
+            assert_eq!(expected, result.is_ok(), "{} {}", email, password);
+        }
+    }
 }
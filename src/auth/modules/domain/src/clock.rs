@@ -0,0 +1,31 @@
+/// 現在時刻をUNIX時間の秒で返します
+///
+/// 有効期限の判定で時刻を差し替えられるよう、`now()`を直接呼ばずにこのトレイトを受け取ります
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> i64;
+}
+
+/// システムの時刻を返す`Clock`です
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|v| v.as_secs() as i64)
+            .unwrap_or_default()
+    }
+}
+
+/// 常に同じ時刻を返す`Clock`です
+///
+/// テストで有効期限の前後を確認するために使います
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.0
+    }
+}
@@ -0,0 +1,373 @@
+use crate::user::{email::Email, user_id::UserId};
+
+/// 認証基盤を使わずにパスワード認証するためのユーザーの認証情報です
+///
+/// パスワードと確認コードはハッシュ化された値のみを保持します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credential {
+    user_id: UserId,
+    email: Email,
+    password_hash: String,
+    confirmed: bool,
+    verification_code: Option<VerificationCode>,
+    reset_code: Option<VerificationCode>,
+    signed_out_at: i64,
+    /// ゲストユーザーの認証情報を削除する日時のUNIX秒です
+    expires_at: Option<i64>,
+    email_change: Option<EmailChange>,
+}
+
+/// 確認コードを破棄するまでに許容する誤ったコードの入力回数です
+pub const MAX_CODE_ATTEMPTS: u32 = 5;
+
+/// メールアドレスの確認やパスワードの再設定に使うコードです
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationCode {
+    code_hash: String,
+    expires_at: i64,
+    failed_attempts: u32,
+}
+
+/// 確認コードによる確認を待っているメールアドレスの変更です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailChange {
+    new_email: Email,
+    code: VerificationCode,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialError {
+    #[error("Credential not found: {0}")]
+    NotFound(String),
+
+    #[error("Credential already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Failed to find credential: {0}")]
+    FindError(String),
+
+    #[error("Failed to save credential: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete credential: {0}")]
+    DeleteError(String),
+}
+
+impl Credential {
+    pub fn new(
+        user_id: UserId, email: Email, password_hash: String, confirmed: bool,
+        verification_code: Option<VerificationCode>, reset_code: Option<VerificationCode>, signed_out_at: i64,
+    ) -> Self {
+        Self {
+            user_id,
+            email,
+            password_hash,
+            confirmed,
+            verification_code,
+            reset_code,
+            signed_out_at,
+            expires_at: None,
+            email_change: None,
+        }
+    }
+
+    /// `expires_at`に削除されるゲストユーザーの認証情報を作成します
+    ///
+    /// メールアドレスの確認は行わないため、確認済みとして作成します
+    pub fn guest(user_id: UserId, email: Email, password_hash: String, expires_at: i64) -> Self {
+        Self { expires_at: Some(expires_at), ..Self::new(user_id, email, password_hash, true, None, None, 0) }
+    }
+
+    /// 削除する日時を設定した値を返します
+    pub fn with_expires_at(self, expires_at: Option<i64>) -> Self {
+        Self { expires_at, ..self }
+    }
+
+    /// 確認を待っているメールアドレスの変更を設定した値を返します
+    pub fn with_email_change(self, email_change: Option<EmailChange>) -> Self {
+        Self { email_change, ..self }
+    }
+
+    /// `now`の時点で削除する日時を過ぎている場合に`true`を返します
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|v| v <= now)
+    }
+
+    /// メールアドレスを確認済みにし、確認コードを破棄した値を返します
+    pub fn confirm(&self) -> Self {
+        Self { confirmed: true, verification_code: None, ..self.clone() }
+    }
+
+    /// パスワード再設定用のコードを設定した値を返します
+    ///
+    /// 以前に発行したコードは使用できなくなります
+    pub fn request_password_reset(&self, reset_code: VerificationCode) -> Self {
+        Self { reset_code: Some(reset_code), ..self.clone() }
+    }
+
+    /// パスワードを置き換え、再設定用のコードと`now`より前に発行されたリフレッシュトークンを無効にした値を返します
+    ///
+    /// 乗っ取られたセッションから要求された可能性があるため、確認を待っているメールアドレスの変更も破棄します
+    pub fn reset_password(&self, password_hash: String, now: i64) -> Self {
+        Self { password_hash, reset_code: None, signed_out_at: now, email_change: None, ..self.clone() }
+    }
+
+    /// メールアドレスの確認コードの入力を誤ったことを記録した値を返します
+    ///
+    /// 誤った回数が`MAX_CODE_ATTEMPTS`に達した場合はコードを破棄します
+    pub fn fail_verification(&self) -> Self {
+        Self { verification_code: self.verification_code.as_ref().and_then(VerificationCode::fail), ..self.clone() }
+    }
+
+    /// パスワード再設定用のコードの入力を誤ったことを記録した値を返します
+    ///
+    /// 誤った回数が`MAX_CODE_ATTEMPTS`に達した場合はコードを破棄します
+    pub fn fail_password_reset(&self) -> Self {
+        Self { reset_code: self.reset_code.as_ref().and_then(VerificationCode::fail), ..self.clone() }
+    }
+
+    /// メールアドレスの変更の確認コードの入力を誤ったことを記録した値を返します
+    ///
+    /// 誤った回数が`MAX_CODE_ATTEMPTS`に達した場合は変更を破棄します
+    pub fn fail_email_change(&self) -> Self {
+        let email_change =
+            self.email_change.as_ref().and_then(|v| v.code.fail().map(|code| EmailChange { code, ..v.clone() }));
+        Self { email_change, ..self.clone() }
+    }
+
+    /// メールアドレスの変更を要求した値を返します
+    ///
+    /// 以前に要求した変更は破棄します
+    pub fn request_email_change(&self, email_change: EmailChange) -> Self {
+        Self { email_change: Some(email_change), ..self.clone() }
+    }
+
+    /// メールアドレスを`email`に置き換え、`now`より前に発行されたリフレッシュトークンを無効にした値を返します
+    ///
+    /// 変更後のメールアドレスは確認コードで確認済みのため、確認済みとして扱います
+    pub fn change_email(&self, email: Email, now: i64) -> Self {
+        Self { email, confirmed: true, verification_code: None, signed_out_at: now, email_change: None, ..self.clone() }
+    }
+
+    /// `now`より前に発行されたリフレッシュトークンを無効にした値を返します
+    pub fn sign_out(&self, now: i64) -> Self {
+        Self { signed_out_at: now, ..self.clone() }
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn email(&self) -> &Email {
+        &self.email
+    }
+
+    pub fn password_hash(&self) -> &str {
+        &self.password_hash
+    }
+
+    pub fn confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub fn verification_code(&self) -> Option<&VerificationCode> {
+        self.verification_code.as_ref()
+    }
+
+    pub fn reset_code(&self) -> Option<&VerificationCode> {
+        self.reset_code.as_ref()
+    }
+
+    pub fn signed_out_at(&self) -> i64 {
+        self.signed_out_at
+    }
+
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires_at
+    }
+
+    pub fn email_change(&self) -> Option<&EmailChange> {
+        self.email_change.as_ref()
+    }
+}
+
+impl EmailChange {
+    pub fn new(new_email: Email, code: VerificationCode) -> Self {
+        Self { new_email, code }
+    }
+
+    pub fn new_email(&self) -> &Email {
+        &self.new_email
+    }
+
+    pub fn code(&self) -> &VerificationCode {
+        &self.code
+    }
+}
+
+impl VerificationCode {
+    pub fn new(code_hash: String, expires_at: i64) -> Self {
+        Self { code_hash, expires_at, failed_attempts: 0 }
+    }
+
+    /// 誤ったコードの入力回数を設定した値を返します
+    pub fn with_failed_attempts(self, failed_attempts: u32) -> Self {
+        Self { failed_attempts, ..self }
+    }
+
+    /// 誤ったコードの入力を記録した値を返します
+    ///
+    /// 誤った回数が`MAX_CODE_ATTEMPTS`に達した場合は、コードを破棄するため`None`を返します
+    pub fn fail(&self) -> Option<Self> {
+        let failed_attempts = self.failed_attempts + 1;
+        (failed_attempts < MAX_CODE_ATTEMPTS).then(|| Self { failed_attempts, ..self.clone() })
+    }
+
+    pub fn code_hash(&self) -> &str {
+        &self.code_hash
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn credential() -> Credential {
+        Credential::new(
+            UserId::new(),
+            Email::from_str("hoge@email.com").unwrap(),
+            "hash".to_string(),
+            false,
+            Some(VerificationCode::new("code_hash".to_string(), 100)),
+            None,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_confirm_success() {
+        let credential = credential();
+        let result = credential.confirm();
+
+        assert!(result.confirmed());
+        assert!(result.verification_code().is_none());
+        assert_eq!(credential.user_id(), result.user_id());
+        assert_eq!(credential.password_hash(), result.password_hash());
+    }
+
+    #[test]
+    fn test_reset_password_success() {
+        let credential = credential().request_password_reset(VerificationCode::new("reset_hash".to_string(), 100));
+        assert_eq!("reset_hash", credential.reset_code().unwrap().code_hash());
+
+        let result = credential.reset_password("new_hash".to_string(), 200);
+        assert_eq!("new_hash", result.password_hash());
+        assert!(result.reset_code().is_none());
+        assert_eq!(200, result.signed_out_at());
+        assert_eq!(credential.verification_code(), result.verification_code());
+    }
+
+    #[test]
+    fn test_change_email_success() {
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+        let email_change = EmailChange::new(new_email.clone(), VerificationCode::new("change_hash".to_string(), 100));
+        let credential = credential().request_email_change(email_change.clone());
+        assert_eq!(Some(&email_change), credential.email_change());
+
+        let result = credential.change_email(new_email.clone(), 200);
+        assert_eq!(&new_email, result.email());
+        assert_eq!(credential.user_id(), result.user_id());
+        assert_eq!(credential.password_hash(), result.password_hash());
+        assert!(result.confirmed());
+        assert!(result.email_change().is_none());
+        assert_eq!(200, result.signed_out_at());
+
+        // パスワードを再設定すると、確認を待っている変更は破棄されます
+        let result = credential.reset_password("new_hash".to_string(), 200);
+        assert!(result.email_change().is_none());
+    }
+
+    #[test]
+    fn test_fail_verification_discards_code() {
+        let mut credential = credential();
+        for i in 1..MAX_CODE_ATTEMPTS {
+            credential = credential.fail_verification();
+            assert_eq!(i, credential.verification_code().unwrap().failed_attempts());
+        }
+
+        let result = credential.fail_verification();
+        assert!(result.verification_code().is_none());
+        assert!(!result.confirmed());
+    }
+
+    #[test]
+    fn test_fail_password_reset_discards_code() {
+        let mut credential = credential().request_password_reset(VerificationCode::new("reset_hash".to_string(), 100));
+        for i in 1..MAX_CODE_ATTEMPTS {
+            credential = credential.fail_password_reset();
+            assert_eq!(i, credential.reset_code().unwrap().failed_attempts());
+        }
+
+        let result = credential.fail_password_reset();
+        assert!(result.reset_code().is_none());
+        assert_eq!(credential.verification_code(), result.verification_code());
+
+        // 新しいコードを発行すると、誤った回数は数え直します
+        let result = result.request_password_reset(VerificationCode::new("reset_hash".to_string(), 100));
+        assert_eq!(0, result.reset_code().unwrap().failed_attempts());
+    }
+
+    #[test]
+    fn test_fail_email_change_discards_change() {
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+        let email_change = EmailChange::new(new_email.clone(), VerificationCode::new("change_hash".to_string(), 100));
+        let mut credential = credential().request_email_change(email_change);
+        for i in 1..MAX_CODE_ATTEMPTS {
+            credential = credential.fail_email_change();
+            let email_change = credential.email_change().unwrap();
+            assert_eq!(&new_email, email_change.new_email());
+            assert_eq!(i, email_change.code().failed_attempts());
+        }
+
+        let result = credential.fail_email_change();
+        assert!(result.email_change().is_none());
+        assert_eq!(credential.email(), result.email());
+    }
+
+    #[test]
+    fn test_sign_out_success() {
+        let result = credential().sign_out(200);
+        assert_eq!(200, result.signed_out_at());
+    }
+
+    #[test]
+    fn test_guest_is_expired() {
+        let credential =
+            Credential::guest(UserId::new(), Email::from_str("hoge@email.com").unwrap(), "hash".to_string(), 100);
+        assert!(credential.confirmed());
+        assert!(!credential.is_expired(99));
+        assert!(credential.is_expired(100));
+        assert!(!credential.with_expires_at(None).is_expired(100));
+    }
+
+    #[test]
+    fn test_verification_code_is_expired() {
+        let code = VerificationCode::new("code_hash".to_string(), 100);
+        assert!(!code.is_expired(99));
+        assert!(code.is_expired(100));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::user::user_id::UserId;
+
+/// ユーザーのデータのエクスポートの依頼と、その進捗です
+///
+/// データの種類ごとに書き出した件数を記録し、進捗として返します
+/// 完了したファイルは、期限付きのダウンロード用トークンを発行してから取得します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataExport {
+    export_id: String,
+    user_id: UserId,
+    status: ExportStatus,
+    requested_at: i64,
+    /// 書き出すデータの種類の数です。開始するまでは0です
+    total: usize,
+    exported: Vec<ExportedItems>,
+    /// 完了したファイルを保存したキーです
+    file_key: Option<String>,
+    completed_at: Option<i64>,
+    /// ダウンロード用トークンのシークレットのSHA-256です。トークン本体は保存しません
+    download_token_hash: Option<String>,
+    download_expires_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// 書き出したデータの種類と件数です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedItems {
+    target: String,
+    count: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DataExportError {
+    #[error("Data export not found: {0}")]
+    NotFound(String),
+
+    #[error("Data export already requested: {0}")]
+    AlreadyRequested(String),
+
+    /// 完了していないエクスポートはダウンロードできません
+    #[error("Data export is not ready: {0}")]
+    NotReady(String),
+
+    #[error("Invalid download token")]
+    InvalidToken,
+
+    #[error("Download token has expired")]
+    Expired,
+
+    #[error("Invalid export status: {0}")]
+    InvalidStatus(String),
+
+    #[error("Failed to find data export: {0}")]
+    FindError(String),
+
+    #[error("Failed to save data export: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete data export: {0}")]
+    DeleteError(String),
+
+    #[error("Failed to create export file: {0}")]
+    ArchiveError(String),
+}
+
+impl DataExport {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        export_id: String, user_id: UserId, status: ExportStatus, requested_at: i64, total: usize,
+        exported: Vec<ExportedItems>, file_key: Option<String>, completed_at: Option<i64>,
+        download_token_hash: Option<String>, download_expires_at: Option<i64>,
+    ) -> Self {
+        Self {
+            export_id,
+            user_id,
+            status,
+            requested_at,
+            total,
+            exported,
+            file_key,
+            completed_at,
+            download_token_hash,
+            download_expires_at,
+        }
+    }
+
+    pub fn request(export_id: String, user_id: UserId, now: i64) -> Self {
+        Self::new(export_id, user_id, ExportStatus::Pending, now, 0, vec![], None, None, None, None)
+    }
+
+    /// ユーザーのZIPファイルを保存するディレクトリのキーです
+    pub fn prefix(user_id: &UserId) -> String {
+        format!("exports/{}", user_id)
+    }
+
+    /// ZIPファイルを保存するキーです
+    pub fn archive_key(&self) -> String {
+        format!("{}/{}.zip", Self::prefix(&self.user_id), self.export_id)
+    }
+
+    /// 完了も失敗もしていない場合に`true`を返します
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, ExportStatus::Pending | ExportStatus::InProgress)
+    }
+
+    /// `total`種類のデータの書き出しを開始した値を返します
+    ///
+    /// ファイルは最後にまとめて作成するため、中断した場合は最初からやり直します
+    pub fn start(&self, total: usize) -> Self {
+        Self { status: ExportStatus::InProgress, total, exported: vec![], ..self.clone() }
+    }
+
+    /// `target`を`count`件書き出したことを記録した値を返します
+    pub fn record(&self, target: &str, count: usize) -> Self {
+        let mut exported = self.exported.clone();
+        exported.push(ExportedItems::new(target.to_string(), count));
+        Self { exported, ..self.clone() }
+    }
+
+    /// `file_key`に保存したファイルで完了した値を返します
+    pub fn complete(&self, file_key: String, now: i64) -> Self {
+        Self { status: ExportStatus::Completed, file_key: Some(file_key), completed_at: Some(now), ..self.clone() }
+    }
+
+    pub fn fail(&self, now: i64) -> Self {
+        Self { status: ExportStatus::Failed, completed_at: Some(now), ..self.clone() }
+    }
+
+    /// ダウンロード用トークンを発行した値を返します。発行済みのトークンは使えなくなります
+    pub fn issue_download_token(&self, token_hash: String, expires_at: i64) -> Result<Self, DataExportError> {
+        if self.status != ExportStatus::Completed {
+            return Err(DataExportError::NotReady(self.export_id.clone()));
+        }
+        Ok(Self { download_token_hash: Some(token_hash), download_expires_at: Some(expires_at), ..self.clone() })
+    }
+
+    /// ダウンロード用トークンを検証し、ファイルのキーを返します
+    pub fn verify_download_token(&self, token_hash: &str, now: i64) -> Result<&str, DataExportError> {
+        let (Some(hash), Some(expires_at), Some(file_key)) =
+            (&self.download_token_hash, self.download_expires_at, &self.file_key)
+        else {
+            return Err(DataExportError::InvalidToken);
+        };
+        if hash != token_hash {
+            return Err(DataExportError::InvalidToken);
+        }
+        if expires_at <= now {
+            return Err(DataExportError::Expired);
+        }
+        Ok(file_key)
+    }
+
+    pub fn export_id(&self) -> &str {
+        &self.export_id
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn status(&self) -> ExportStatus {
+        self.status
+    }
+
+    pub fn requested_at(&self) -> i64 {
+        self.requested_at
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn exported(&self) -> &[ExportedItems] {
+        &self.exported
+    }
+
+    pub fn file_key(&self) -> Option<&str> {
+        self.file_key.as_deref()
+    }
+
+    pub fn completed_at(&self) -> Option<i64> {
+        self.completed_at
+    }
+
+    pub fn download_token_hash(&self) -> Option<&str> {
+        self.download_token_hash.as_deref()
+    }
+
+    pub fn download_expires_at(&self) -> Option<i64> {
+        self.download_expires_at
+    }
+}
+
+impl ExportedItems {
+    pub fn new(target: String, count: usize) -> Self {
+        Self { target, count }
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl std::str::FromStr for ExportStatus {
+    type Err = DataExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ExportStatus::Pending),
+            "in_progress" => Ok(ExportStatus::InProgress),
+            "completed" => Ok(ExportStatus::Completed),
+            "failed" => Ok(ExportStatus::Failed),
+            _ => Err(DataExportError::InvalidStatus(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ExportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportStatus::Pending => write!(f, "pending"),
+            ExportStatus::InProgress => write!(f, "in_progress"),
+            ExportStatus::Completed => write!(f, "completed"),
+            ExportStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn export() -> DataExport {
+        DataExport::request("export_id".to_string(), UserId::new(), 100)
+    }
+
+    #[test]
+    fn test_start_and_complete() {
+        let export = export();
+        assert_eq!(ExportStatus::Pending, export.status());
+        assert!(export.is_active());
+
+        let export = export.start(2).record("profile", 1).record("subscribe", 3);
+        assert_eq!(ExportStatus::InProgress, export.status());
+        assert_eq!(2, export.total());
+        assert_eq!(2, export.exported().len());
+        assert!(export.start(2).exported().is_empty());
+
+        let completed = export.complete("exports/export_id.zip".to_string(), 200);
+        assert_eq!(ExportStatus::Completed, completed.status());
+        assert_eq!(Some("exports/export_id.zip"), completed.file_key());
+        assert_eq!(Some(200), completed.completed_at());
+        assert!(!completed.is_active());
+
+        let failed = export.fail(200);
+        assert_eq!(ExportStatus::Failed, failed.status());
+        assert!(failed.file_key().is_none());
+    }
+
+    #[test]
+    fn test_download_token() {
+        let export = export();
+        let test_cases = vec![
+            export.clone(),
+            export.start(1),
+            export.start(1).fail(200),
+        ];
+        for export in test_cases {
+            assert!(matches!(export.issue_download_token("hash".to_string(), 300), Err(DataExportError::NotReady(_))));
+        }
+
+        let completed = export.start(1).complete("key".to_string(), 200);
+        assert!(matches!(completed.verify_download_token("hash", 200), Err(DataExportError::InvalidToken)));
+
+        let issued = completed.issue_download_token("hash".to_string(), 300).unwrap();
+        assert_eq!("key", issued.verify_download_token("hash", 299).unwrap());
+        let test_cases = vec![
+            ("other", 299, DataExportError::InvalidToken),
+            ("hash", 300, DataExportError::Expired),
+        ];
+        for (hash, now, expected) in test_cases {
+            let result = issued.verify_download_token(hash, now).unwrap_err();
+            assert_eq!(expected.to_string(), result.to_string(), "{}", hash);
+        }
+
+        let reissued = issued.issue_download_token("new".to_string(), 400).unwrap();
+        assert!(matches!(reissued.verify_download_token("hash", 299), Err(DataExportError::InvalidToken)));
+    }
+
+    #[test]
+    fn test_status_from_str() {
+        let test_cases = vec![
+            ExportStatus::Pending,
+            ExportStatus::InProgress,
+            ExportStatus::Completed,
+            ExportStatus::Failed,
+        ];
+        for status in test_cases {
+            assert_eq!(status, ExportStatus::from_str(&status.to_string()).unwrap());
+        }
+        assert!(ExportStatus::from_str("cancelled").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::user::{email::Email, user_id::UserId};
+
+/// 外部のIDプロバイダーのアカウントとユーザーの紐付けです
+///
+/// プロバイダー内で一意な`subject`(IDトークンの`sub`)でユーザーを特定します
+/// メールアドレスは紐付けた時点の値で、以降のログインでは参照しません
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalIdentity {
+    provider: String,
+    subject: String,
+    user_id: UserId,
+    email: Email,
+    linked_at: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalIdentityError {
+    #[error("Failed to find external identity: {0}")]
+    FindError(String),
+
+    #[error("Failed to save external identity: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete external identity: {0}")]
+    DeleteError(String),
+}
+
+impl ExternalIdentity {
+    pub fn new(provider: String, subject: String, user_id: UserId, email: Email, linked_at: i64) -> Self {
+        Self { provider, subject, user_id, email, linked_at }
+    }
+
+    /// プロバイダーと`subject`を組み合わせた、紐付けを一意に識別するキーです
+    pub fn key(&self) -> String {
+        Self::key_of(&self.provider, &self.subject)
+    }
+
+    pub fn key_of(provider: &str, subject: &str) -> String {
+        format!("{}#{}", provider, subject)
+    }
+
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn email(&self) -> &Email {
+        &self.email
+    }
+
+    pub fn linked_at(&self) -> i64 {
+        self.linked_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        let identity = ExternalIdentity::new(
+            "google".to_string(),
+            "1234567890".to_string(),
+            UserId::new(),
+            "hoge@email.com".parse().unwrap(),
+            0,
+        );
+
+        assert_eq!("google#1234567890", identity.key());
+        assert_eq!(identity.key(), ExternalIdentity::key_of("google", "1234567890"));
+    }
+}
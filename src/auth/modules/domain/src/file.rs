@@ -0,0 +1,28 @@
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("File not found: {0}")]
+    NotFound(String),
+
+    /// 空のキーや`..`を含むキーなど、保存先として使えないキーです
+    #[error("Invalid file key: {0}")]
+    InvalidKey(String),
+
+    #[error("Failed to read file: {0}")]
+    ReadError(String),
+
+    #[error("Failed to write file: {0}")]
+    WriteError(String),
+
+    #[error("Failed to delete file: {0}")]
+    DeleteError(String),
+}
+
+/// 保存先によって扱いが変わらないよう、空の区切りや`.`、`..`、`\`を含むキーを拒否します
+pub fn validate_key(key: &str) -> Result<(), FileError> {
+    let valid =
+        !key.is_empty() && !key.contains('\\') && key.split('/').all(|v| !v.is_empty() && v != "." && v != "..");
+    match valid {
+        true => Ok(()),
+        false => Err(FileError::InvalidKey(key.to_string())),
+    }
+}
@@ -1,7 +1,22 @@
+pub mod account_deletion;
 pub mod auth_user;
+pub mod clock;
+pub mod credential;
+pub mod data_export;
+pub mod external_identity;
+pub mod file;
+pub mod login_attempt;
+pub mod mfa;
+pub mod personal_access_token;
+pub mod profile_icon;
+pub mod refresh_token;
 pub mod repository;
+pub mod revoked_token;
+pub mod secret;
+pub mod security_event;
 pub mod token;
 pub mod user;
+pub mod user_data;
 
 use thiserror::Error;
 
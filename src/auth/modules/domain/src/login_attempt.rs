@@ -0,0 +1,169 @@
+/// ログイン失敗の記録です
+///
+/// メールアドレスや接続元IPアドレスごとに、ウィンドウ内の失敗時刻とロック状態を持ちます
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginAttempt {
+    key: String,
+    failures: Vec<i64>,
+    lockouts: u32,
+    locked_until: i64,
+    expires_at: i64,
+}
+
+/// アカウントをロックする条件とロック時間です
+///
+/// ロックされるたびにロック時間を`base_lockout`から倍にし、`max_lockout`を上限とします
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    pub threshold: usize,
+    pub window: i64,
+    pub base_lockout: i64,
+    pub max_lockout: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoginAttemptError {
+    #[error("Failed to find login attempt: {0}")]
+    FindError(String),
+
+    #[error("Failed to save login attempt: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete login attempt: {0}")]
+    DeleteError(String),
+}
+
+impl LoginAttempt {
+    pub fn new(key: String, failures: Vec<i64>, lockouts: u32, locked_until: i64, expires_at: i64) -> Self {
+        Self { key, failures, lockouts, locked_until, expires_at }
+    }
+
+    /// 失敗のない記録を作成します
+    pub fn empty(key: String) -> Self {
+        Self::new(key, vec![], 0, 0, 0)
+    }
+
+    /// ウィンドウ外の失敗を除いて失敗を追加した値を返します
+    ///
+    /// ウィンドウ内の失敗が`threshold`に達した場合はロックし、失敗の記録をリセットします
+    pub fn record_failure(&self, now: i64, policy: &LockoutPolicy) -> Self {
+        let mut failures: Vec<i64> = self.failures.iter().copied().filter(|v| *v > now - policy.window).collect();
+        failures.push(now);
+
+        let (failures, lockouts, locked_until) = match failures.len() >= policy.threshold {
+            true => {
+                let lockouts = self.lockouts.saturating_add(1);
+                (vec![], lockouts, now + policy.lockout(lockouts))
+            }
+            false => (failures, self.lockouts, self.locked_until),
+        };
+
+        // ロック回数はロック解除後もウィンドウの間は保持し、続けて失敗した場合のロック時間を延ばします
+        let expires_at = locked_until.max(now) + policy.window;
+        Self { key: self.key.clone(), failures, lockouts, locked_until, expires_at }
+    }
+
+    /// ロック中の場合、解除までの秒数を返します
+    pub fn retry_after(&self, now: i64) -> Option<u64> {
+        match self.locked_until > now {
+            true => Some((self.locked_until - now) as u64),
+            false => None,
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn failures(&self) -> &[i64] {
+        &self.failures
+    }
+
+    pub fn lockouts(&self) -> u32 {
+        self.lockouts
+    }
+
+    pub fn locked_until(&self) -> i64 {
+        self.locked_until
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+}
+
+impl LockoutPolicy {
+    /// `lockouts`回目のロック時間です
+    pub fn lockout(&self, lockouts: u32) -> i64 {
+        let factor = 2_i64.checked_pow(lockouts.saturating_sub(1)).unwrap_or(i64::MAX);
+        self.base_lockout.saturating_mul(factor).min(self.max_lockout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: LockoutPolicy = LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 100 };
+
+    #[test]
+    fn test_record_failure_locks_after_threshold() {
+        let attempt = LoginAttempt::empty("email#hoge@email.com".to_string());
+
+        let attempt = attempt.record_failure(0, &POLICY).record_failure(10, &POLICY);
+        assert_eq!(vec![0, 10], attempt.failures());
+        assert_eq!(None, attempt.retry_after(10));
+
+        let attempt = attempt.record_failure(20, &POLICY);
+        assert!(attempt.failures().is_empty());
+        assert_eq!(1, attempt.lockouts());
+        assert_eq!(Some(30), attempt.retry_after(20));
+        assert_eq!(Some(1), attempt.retry_after(49));
+        assert_eq!(None, attempt.retry_after(50));
+        assert_eq!(110, attempt.expires_at());
+    }
+
+    #[test]
+    fn test_record_failure_sliding_window() {
+        let attempt = LoginAttempt::empty("key".to_string()).record_failure(0, &POLICY).record_failure(30, &POLICY);
+
+        let attempt = attempt.record_failure(61, &POLICY);
+        assert_eq!(vec![30, 61], attempt.failures());
+        assert_eq!(None, attempt.retry_after(61));
+    }
+
+    #[test]
+    fn test_record_failure_exponential_backoff() {
+        let mut attempt = LoginAttempt::empty("key".to_string());
+        let mut now = 0;
+        let mut lockouts = vec![];
+
+        for _ in 0..4 {
+            for _ in 0..POLICY.threshold {
+                attempt = attempt.record_failure(now, &POLICY);
+            }
+            lockouts.push(attempt.retry_after(now).unwrap());
+            now = attempt.locked_until();
+        }
+        assert_eq!(vec![30, 60, 100, 100], lockouts);
+    }
+
+    #[test]
+    fn test_lockout() {
+        let test_cases = vec![
+            (0, 30),
+            (1, 30),
+            (2, 60),
+            (3, 100),
+            (64, 100),
+            (u32::MAX, 100),
+        ];
+        for (lockouts, expected) in test_cases {
+            assert_eq!(expected, POLICY.lockout(lockouts));
+        }
+    }
+}
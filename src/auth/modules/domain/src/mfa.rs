@@ -0,0 +1,161 @@
+use crate::user::user_id::UserId;
+
+/// ユーザーのTOTPによる多要素認証の設定です
+///
+/// シークレットは暗号化した値、リカバリーコードはハッシュのみを持ちます
+/// 登録直後は無効で、認証アプリのコードを確認してから有効にします
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mfa {
+    user_id: UserId,
+    secret: String,
+    enabled: bool,
+    recovery_code_hashes: Vec<String>,
+    last_used_step: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MfaError {
+    #[error("MFA is not enrolled: {0}")]
+    NotEnrolled(String),
+
+    #[error("MFA is already enabled: {0}")]
+    AlreadyEnabled(String),
+
+    #[error("Invalid MFA code")]
+    InvalidCode,
+
+    #[error("Invalid MFA challenge")]
+    InvalidChallenge,
+
+    #[error("MFA challenge expired")]
+    ChallengeExpired,
+
+    /// 確認中に別のリクエストが設定を更新した場合のエラーです
+    #[error("MFA was updated concurrently: {0}")]
+    Conflict(String),
+
+    #[error("Failed to find MFA: {0}")]
+    FindError(String),
+
+    #[error("Failed to save MFA: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete MFA: {0}")]
+    DeleteError(String),
+
+    #[error("Failed to encrypt MFA secret: {0}")]
+    CryptoError(String),
+}
+
+impl Mfa {
+    pub fn new(
+        user_id: UserId, secret: String, enabled: bool, recovery_code_hashes: Vec<String>, last_used_step: i64,
+    ) -> Self {
+        Self { user_id, secret, enabled, recovery_code_hashes, last_used_step }
+    }
+
+    /// 有効化前の設定を作成します
+    pub fn enroll(user_id: UserId, secret: String) -> Self {
+        Self::new(user_id, secret, false, vec![], 0)
+    }
+
+    /// `step`のコードで確認した設定を有効にした値を返します
+    pub fn activate(&self, recovery_code_hashes: Vec<String>, step: i64) -> Self {
+        Self { enabled: true, recovery_code_hashes, last_used_step: step, ..self.clone() }
+    }
+
+    /// `step`のコードを使用済みにした値を返します
+    ///
+    /// 同じコードを再利用できないよう、最後に使用したステップ以前のコードは`None`を返します
+    pub fn use_step(&self, step: i64) -> Option<Self> {
+        match step > self.last_used_step {
+            true => Some(Self { last_used_step: step, ..self.clone() }),
+            false => None,
+        }
+    }
+
+    /// リカバリーコードを消費した値を返します。該当するコードがない場合は`None`を返します
+    pub fn use_recovery_code(&self, code_hash: &str) -> Option<Self> {
+        let index = self.recovery_code_hashes.iter().position(|v| v == code_hash)?;
+        let mut recovery_code_hashes = self.recovery_code_hashes.clone();
+        recovery_code_hashes.remove(index);
+        Some(Self { recovery_code_hashes, ..self.clone() })
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn recovery_code_hashes(&self) -> &[String] {
+        &self.recovery_code_hashes
+    }
+
+    pub fn last_used_step(&self) -> i64 {
+        self.last_used_step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enrolled() -> Mfa {
+        Mfa::enroll(UserId::new(), "secret".to_string())
+    }
+
+    #[test]
+    fn test_enroll_and_activate() {
+        let mfa = enrolled();
+        assert!(!mfa.enabled());
+        assert!(mfa.recovery_code_hashes().is_empty());
+
+        let mfa = mfa.activate(
+            vec![
+                "a".to_string(),
+                "b".to_string(),
+            ],
+            100,
+        );
+        assert!(mfa.enabled());
+        assert_eq!(vec!["a", "b"], mfa.recovery_code_hashes());
+        assert_eq!(100, mfa.last_used_step());
+    }
+
+    #[test]
+    fn test_use_step() {
+        let mfa = enrolled().activate(vec![], 100);
+
+        let test_cases = vec![
+            (99, None),
+            (100, None),
+            (101, Some(101)),
+        ];
+        for (step, expected) in test_cases {
+            assert_eq!(expected, mfa.use_step(step).map(|v| v.last_used_step()));
+        }
+    }
+
+    #[test]
+    fn test_use_recovery_code() {
+        let mfa = enrolled().activate(
+            vec![
+                "a".to_string(),
+                "b".to_string(),
+            ],
+            100,
+        );
+
+        let used = mfa.use_recovery_code("a").unwrap();
+        assert_eq!(vec!["b"], used.recovery_code_hashes());
+        assert!(used.use_recovery_code("a").is_none());
+        assert!(mfa.use_recovery_code("c").is_none());
+    }
+}
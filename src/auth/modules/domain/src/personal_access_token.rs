@@ -0,0 +1,216 @@
+use crate::user::user_id::UserId;
+
+/// スクリプトや外部サービスとの連携に使う個人用アクセストークンです
+///
+/// トークン本体は保持せず、ハッシュのみを持ちます
+/// 許可したスコープの操作のみ行えます
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersonalAccessToken {
+    token_id: String,
+    user_id: UserId,
+    name: String,
+    scopes: Vec<Scope>,
+    token_hash: String,
+    created_at: i64,
+    expires_at: i64,
+}
+
+/// 個人用アクセストークンに許可する操作の範囲です
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    SubscriptionsRead,
+    SubscriptionsWrite,
+    CategoriesRead,
+    CategoriesWrite,
+    PaymentMethodsRead,
+    PaymentMethodsWrite,
+    UsersRead,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersonalAccessTokenError {
+    #[error("Invalid personal access token")]
+    InvalidToken,
+
+    #[error("Personal access token expired")]
+    Expired,
+
+    #[error("Personal access token not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid personal access token name: {0}")]
+    InvalidName(String),
+
+    #[error("Invalid scope: {0}")]
+    InvalidScope(String),
+
+    /// 指定された有効期間の秒数を持ちます
+    #[error("Invalid expiry: {0} seconds")]
+    InvalidExpiry(u64),
+
+    #[error("Failed to find personal access token: {0}")]
+    FindError(String),
+
+    #[error("Failed to save personal access token: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete personal access token: {0}")]
+    DeleteError(String),
+}
+
+/// トークン名の最大文字数です
+pub const NAME_MAX_CHARS: usize = 50;
+
+impl PersonalAccessToken {
+    pub fn new(
+        token_id: String, user_id: UserId, name: String, scopes: Vec<Scope>, token_hash: String, created_at: i64,
+        expires_at: i64,
+    ) -> Self {
+        Self { token_id, user_id, name, scopes, token_hash, created_at, expires_at }
+    }
+
+    /// トークン名は前後の空白を除いて1文字以上、`NAME_MAX_CHARS`文字以下です
+    pub fn validate_name(name: &str) -> Result<String, PersonalAccessTokenError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > NAME_MAX_CHARS {
+            return Err(PersonalAccessTokenError::InvalidName(name.to_string()));
+        }
+        Ok(name.to_string())
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn token_id(&self) -> &str {
+        &self.token_id
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn token_hash(&self) -> &str {
+        &self.token_hash
+    }
+
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = PersonalAccessTokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "subscriptions:read" => Ok(Scope::SubscriptionsRead),
+            "subscriptions:write" => Ok(Scope::SubscriptionsWrite),
+            "categories:read" => Ok(Scope::CategoriesRead),
+            "categories:write" => Ok(Scope::CategoriesWrite),
+            "payment_methods:read" => Ok(Scope::PaymentMethodsRead),
+            "payment_methods:write" => Ok(Scope::PaymentMethodsWrite),
+            "users:read" => Ok(Scope::UsersRead),
+            _ => Err(PersonalAccessTokenError::InvalidScope(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::SubscriptionsRead => write!(f, "subscriptions:read"),
+            Scope::SubscriptionsWrite => write!(f, "subscriptions:write"),
+            Scope::CategoriesRead => write!(f, "categories:read"),
+            Scope::CategoriesWrite => write!(f, "categories:write"),
+            Scope::PaymentMethodsRead => write!(f, "payment_methods:read"),
+            Scope::PaymentMethodsWrite => write!(f, "payment_methods:write"),
+            Scope::UsersRead => write!(f, "users:read"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_scope_from_str_and_display() {
+        let test_cases = vec![
+            ("subscriptions:read", Scope::SubscriptionsRead),
+            ("subscriptions:write", Scope::SubscriptionsWrite),
+            ("categories:read", Scope::CategoriesRead),
+            ("categories:write", Scope::CategoriesWrite),
+            ("payment_methods:read", Scope::PaymentMethodsRead),
+            ("payment_methods:write", Scope::PaymentMethodsWrite),
+            ("users:read", Scope::UsersRead),
+        ];
+        for (value, expected) in test_cases {
+            let result = Scope::from_str(value).unwrap();
+            assert_eq!(expected, result);
+            assert_eq!(value, result.to_string());
+        }
+
+        let test_cases = vec![
+            "",
+            "subscriptions",
+            "subscriptions:delete",
+            "*",
+        ];
+        for value in test_cases {
+            assert!(matches!(Scope::from_str(value), Err(PersonalAccessTokenError::InvalidScope(_))), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_validate_name() {
+        let max = "あ".repeat(NAME_MAX_CHARS);
+        let test_cases = vec![
+            ("import".to_string(), Some("import")),
+            ("  monthly report  ".to_string(), Some("monthly report")),
+            ("".to_string(), None),
+            ("   ".to_string(), None),
+            (max.clone(), Some(max.as_str())),
+            ("a".repeat(NAME_MAX_CHARS + 1), None),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected.map(ToString::to_string), PersonalAccessToken::validate_name(&value).ok());
+        }
+    }
+
+    #[test]
+    fn test_has_scope_and_is_expired() {
+        let token = PersonalAccessToken::new(
+            "token".to_string(),
+            UserId::new(),
+            "import".to_string(),
+            vec![Scope::SubscriptionsRead],
+            "hash".to_string(),
+            0,
+            100,
+        );
+
+        assert!(token.has_scope(Scope::SubscriptionsRead));
+        assert!(!token.has_scope(Scope::SubscriptionsWrite));
+        assert!(!token.is_expired(99));
+        assert!(token.is_expired(100));
+    }
+}
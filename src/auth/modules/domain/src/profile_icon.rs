@@ -0,0 +1,192 @@
+use thiserror::Error;
+
+use crate::user::user_id::UserId;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ProfileIconError {
+    #[error("Profile icon is too large: {0} bytes")]
+    TooLarge(usize),
+
+    /// PNG、JPEG、WebP以外の形式です
+    #[error("Unsupported profile icon format")]
+    UnsupportedFormat,
+
+    /// 形式は対応していますが、画像として読み込めないデータです
+    #[error("Invalid profile icon: {0}")]
+    InvalidImage(String),
+}
+
+/// アップロードできる画像の形式です
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl IconFormat {
+    /// 拡張子や`Content-Type`は信用せず、先頭のマジックバイトから形式を判定します
+    pub fn detect(content: &[u8]) -> Result<Self, ProfileIconError> {
+        match content {
+            [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Ok(Self::Png),
+            [0xff, 0xd8, 0xff, ..] => Ok(Self::Jpeg),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Ok(Self::Webp),
+            _ => Err(ProfileIconError::UnsupportedFormat),
+        }
+    }
+}
+
+/// アップロードされたプロフィールアイコンです
+///
+/// サイズと形式のみ検証します。画像として読み込めるかはサムネイルの生成時に確認します
+#[derive(Debug, Clone)]
+pub struct ProfileIcon {
+    content: Vec<u8>,
+    format: IconFormat,
+}
+
+impl ProfileIcon {
+    /// アップロードできる最大のバイト数です
+    pub const MAX_BYTES: usize = 5 * 1024 * 1024;
+
+    /// 展開後のメモリを抑えるため、縦横それぞれこのピクセル数を超える画像は拒否します
+    pub const MAX_DIMENSION: u32 = 4096;
+
+    /// 生成するサムネイルの一辺のピクセル数です
+    ///
+    /// 先頭のサイズのキーを`profile_icon_path`に保存します
+    pub const THUMBNAIL_SIZES: [u32; 2] = [
+        256, 64,
+    ];
+
+    /// サムネイルの`Content-Type`です。EXIFなどのメタデータを含めないよう、PNGで書き出し直します
+    pub const CONTENT_TYPE: &'static str = "image/png";
+
+    pub fn new(content: Vec<u8>) -> Result<Self, ProfileIconError> {
+        if content.len() > Self::MAX_BYTES {
+            return Err(ProfileIconError::TooLarge(content.len()));
+        }
+        let format = IconFormat::detect(&content)?;
+        Ok(Self { content, format })
+    }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
+    pub fn format(&self) -> IconFormat {
+        self.format
+    }
+
+    /// サムネイルを保存するキーです
+    ///
+    /// アップロードごとに`icon_id`を変え、配信先のキャッシュに古い画像が残らないようにします
+    pub fn key(user_id: &UserId, icon_id: &str, size: u32) -> String {
+        format!("{}/{}/{}.png", Self::prefix(user_id), icon_id, size)
+    }
+
+    /// ユーザーのアイコンを保存するディレクトリのキーです
+    pub fn prefix(user_id: &UserId) -> String {
+        format!("icons/{}", user_id)
+    }
+
+    /// `profile_icon_path`から、同じアップロードで保存したすべてのサイズのキーを返します
+    ///
+    /// 他のユーザーのファイルを消さないよう、`user_id`のアイコンのキーでない場合は空を返します
+    pub fn keys(user_id: &UserId, profile_icon_path: &str) -> Vec<String> {
+        let prefix = format!("{}/", Self::prefix(user_id));
+        let icon_id = profile_icon_path
+            .strip_prefix(&prefix)
+            .and_then(|v| v.split_once('/'))
+            .filter(|(icon_id, size)| !icon_id.is_empty() && !size.contains('/'))
+            .map(|(icon_id, _)| icon_id);
+        match icon_id {
+            Some(icon_id) => Self::THUMBNAIL_SIZES.iter().map(|size| Self::key(user_id, icon_id, *size)).collect(),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const PNG: &[u8] = &[
+        0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00,
+    ];
+    const JPEG: &[u8] = &[
+        0xff, 0xd8, 0xff, 0xe0, 0x00,
+    ];
+    const WEBP: &[u8] = b"RIFF\x24\x00\x00\x00WEBPVP8 ";
+
+    #[test]
+    fn test_detect() {
+        let test_cases = vec![
+            (PNG, IconFormat::Png),
+            (JPEG, IconFormat::Jpeg),
+            (WEBP, IconFormat::Webp),
+        ];
+        for (content, expected) in test_cases {
+            assert_eq!(Ok(expected), IconFormat::detect(content));
+        }
+    }
+
+    #[test]
+    fn test_detect_failed() {
+        let test_cases: Vec<&[u8]> = vec![
+            b"",
+            b"GIF89a",
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>",
+            &PNG[..7],
+            &[
+                0xff, 0xd8,
+            ],
+            b"RIFF\x24\x00\x00\x00WAVEfmt ",
+        ];
+        for content in test_cases {
+            assert_eq!(Err(ProfileIconError::UnsupportedFormat), IconFormat::detect(content), "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let icon = ProfileIcon::new(PNG.to_vec()).unwrap();
+        assert_eq!(IconFormat::Png, icon.format());
+        assert_eq!(PNG, icon.content());
+
+        let mut content = JPEG.to_vec();
+        content.resize(ProfileIcon::MAX_BYTES, 0);
+        assert!(ProfileIcon::new(content.clone()).is_ok());
+
+        content.push(0);
+        let result = ProfileIcon::new(content);
+        assert_eq!(Some(ProfileIconError::TooLarge(ProfileIcon::MAX_BYTES + 1)), result.err());
+    }
+
+    #[test]
+    fn test_keys() {
+        let user_id = UserId::from_str("usr_550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let path = ProfileIcon::key(&user_id, "icon_1", 256);
+        assert_eq!("icons/usr_550e8400-e29b-41d4-a716-446655440000/icon_1/256.png", path);
+        assert_eq!(
+            vec![
+                "icons/usr_550e8400-e29b-41d4-a716-446655440000/icon_1/256.png",
+                "icons/usr_550e8400-e29b-41d4-a716-446655440000/icon_1/64.png",
+            ],
+            ProfileIcon::keys(&user_id, &path)
+        );
+
+        let test_cases = vec![
+            "avatar.jpg",
+            "icons/usr_550e8400-e29b-41d4-a716-446655440000/256.png",
+            "icons/usr_550e8400-e29b-41d4-a716-446655440000//256.png",
+            "icons/usr_650e8400-e29b-41d4-a716-446655440000/icon_1/256.png",
+            "icons/usr_550e8400-e29b-41d4-a716-446655440000/icon_1/64/256.png",
+        ];
+        for path in test_cases {
+            assert!(ProfileIcon::keys(&user_id, path).is_empty(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::user::user_id::UserId;
+
+/// ローテーションされるリフレッシュトークンの系列です
+///
+/// トークン本体は保持せず、現在のトークンと使用済みトークンのハッシュのみを持ちます
+/// 使用済みトークンが再び提示された場合は盗用とみなし、系列全体を無効化します
+/// ログインごとに作成するため、ユーザーにはログイン中のセッションとして表示します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshTokenFamily {
+    family_id: String,
+    user_id: UserId,
+    token_hash: String,
+    used_hashes: Vec<String>,
+    upstream: String,
+    revoked: bool,
+    device: Device,
+    created_at: i64,
+    last_seen_at: i64,
+    expires_at: i64,
+}
+
+/// セッションを作成した端末の情報です
+///
+/// `ip`はリフレッシュトークンを最後に使用した接続元で更新します
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Device {
+    label: String,
+    user_agent: String,
+    ip: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshTokenError {
+    #[error("Invalid refresh token")]
+    InvalidToken,
+
+    #[error("Refresh token expired")]
+    Expired,
+
+    #[error("Refresh token family revoked: {0}")]
+    Revoked(String),
+
+    #[error("Refresh token reuse detected: {0}")]
+    ReuseDetected(String),
+
+    #[error("Refresh token was rotated concurrently: {0}")]
+    Conflict(String),
+
+    #[error("Refresh token family not found: {0}")]
+    NotFound(String),
+
+    /// ユーザーのセッション一覧にない系列を指定された場合のエラーです
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("Failed to find refresh token family: {0}")]
+    FindError(String),
+
+    #[error("Failed to save refresh token family: {0}")]
+    SaveError(String),
+
+    #[error("Failed to delete refresh token family: {0}")]
+    DeleteError(String),
+
+    #[error("Failed to encrypt refresh token: {0}")]
+    CryptoError(String),
+}
+
+impl RefreshTokenFamily {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        family_id: String, user_id: UserId, token_hash: String, used_hashes: Vec<String>, upstream: String,
+        revoked: bool, device: Device, created_at: i64, last_seen_at: i64, expires_at: i64,
+    ) -> Self {
+        Self {
+            family_id,
+            user_id,
+            token_hash,
+            used_hashes,
+            upstream,
+            revoked,
+            device,
+            created_at,
+            last_seen_at,
+            expires_at,
+        }
+    }
+
+    /// 現在のトークンを使用済みにし、新しいトークンのハッシュに置き換えた系列を返します
+    pub fn rotate(&self, token_hash: String, upstream: String) -> Self {
+        let mut used_hashes = self.used_hashes.clone();
+        used_hashes.push(self.token_hash.clone());
+        Self { token_hash, used_hashes, upstream, ..self.clone() }
+    }
+
+    /// `now`に`ip`からリフレッシュトークンが使用されたことを記録した値を返します
+    pub fn seen(&self, ip: &str, now: i64) -> Self {
+        let device = Device { ip: ip.to_string(), ..self.device.clone() };
+        Self { device, last_seen_at: now, ..self.clone() }
+    }
+
+    /// 系列全体を無効化した値を返します
+    pub fn revoke(&self) -> Self {
+        Self { revoked: true, ..self.clone() }
+    }
+
+    pub fn is_used(&self, token_hash: &str) -> bool {
+        self.used_hashes.iter().any(|v| v == token_hash)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn family_id(&self) -> &str {
+        &self.family_id
+    }
+
+    pub fn user_id(&self) -> &UserId {
+        &self.user_id
+    }
+
+    pub fn token_hash(&self) -> &str {
+        &self.token_hash
+    }
+
+    pub fn used_hashes(&self) -> &[String] {
+        &self.used_hashes
+    }
+
+    /// 認証基盤のリフレッシュトークンを暗号化した値です
+    pub fn upstream(&self) -> &str {
+        &self.upstream
+    }
+
+    pub fn revoked(&self) -> bool {
+        self.revoked
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    pub fn last_seen_at(&self) -> i64 {
+        self.last_seen_at
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+
+    /// 無効化されておらず、有効期限内の系列をログイン中のセッションとして扱います
+    pub fn is_active(&self, now: i64) -> bool {
+        !self.revoked && !self.is_expired(now)
+    }
+}
+
+impl Device {
+    pub fn new(label: String, user_agent: String, ip: String) -> Self {
+        Self { label, user_agent, ip }
+    }
+
+    /// 利用者が端末を見分けるための名前です
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family() -> RefreshTokenFamily {
+        RefreshTokenFamily::new(
+            "family".to_string(),
+            UserId::new(),
+            "hash1".to_string(),
+            vec![],
+            "upstream1".to_string(),
+            false,
+            Device::new("Chrome on macOS".to_string(), "Mozilla/5.0".to_string(), "192.0.2.1".to_string()),
+            10,
+            10,
+            100,
+        )
+    }
+
+    #[test]
+    fn test_rotate_success() {
+        let family = family();
+        let result = family.rotate("hash2".to_string(), "upstream2".to_string());
+
+        assert_eq!("hash2", result.token_hash());
+        assert_eq!("upstream2", result.upstream());
+        assert!(result.is_used("hash1"));
+        assert!(!result.is_used("hash2"));
+        assert_eq!(family.family_id(), result.family_id());
+        assert_eq!(family.user_id(), result.user_id());
+        assert_eq!(family.expires_at(), result.expires_at());
+        assert_eq!(family.device(), result.device());
+    }
+
+    #[test]
+    fn test_seen_success() {
+        let family = family();
+        let result = family.seen("198.51.100.1", 50);
+
+        assert_eq!("198.51.100.1", result.device().ip());
+        assert_eq!(family.device().label(), result.device().label());
+        assert_eq!(family.device().user_agent(), result.device().user_agent());
+        assert_eq!(10, result.created_at());
+        assert_eq!(50, result.last_seen_at());
+    }
+
+    #[test]
+    fn test_revoke_success() {
+        let family = family();
+        let result = family.revoke();
+
+        assert!(result.revoked());
+        assert_eq!(family.token_hash(), result.token_hash());
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let family = family();
+        assert!(!family.is_expired(99));
+        assert!(family.is_expired(100));
+        assert!(family.is_expired(101));
+    }
+
+    #[test]
+    fn test_is_active() {
+        let family = family();
+        assert!(family.is_active(99));
+        assert!(!family.is_active(100));
+        assert!(!family.revoke().is_active(99));
+    }
+}
@@ -1,2 +1,15 @@
+pub mod account_deletion_repository;
 pub mod auth_user_repository;
+pub mod breached_password_repository;
+pub mod credential_repository;
+pub mod data_export_repository;
+pub mod external_identity_repository;
+pub mod login_attempt_repository;
+pub mod mfa_repository;
+pub mod object_storage;
+pub mod personal_access_token_repository;
+pub mod refresh_token_repository;
+pub mod revoked_token_repository;
+pub mod security_event_repository;
+pub mod user_data_repository;
 pub mod user_repository;
@@ -0,0 +1,33 @@
+use crate::{
+    account_deletion::{AccountDeletion, AccountDeletionError},
+    user::user_id::UserId,
+};
+
+pub trait AccountDeletionRepository: Send + Sync {
+    /// 存在しない場合は`AccountDeletionError::NotFound`を返します
+    fn find(
+        &self, deletion_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>;
+    /// ユーザーの削除の依頼を依頼日時の昇順で返します
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    >;
+    /// `now`の時点で削除を開始または再開する依頼を返します
+    fn find_due(
+        &self, now: i64,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    >;
+    /// `now`の時点で削除を開始または再開する依頼を、`leased_until`まで他の処理に実行させないようにします
+    ///
+    /// 複数のプロセスが同じ依頼を同時に処理しないよう、処理する前に呼び出します
+    /// 保存済みの最新の依頼を返します。他の処理が実行中の場合などは`AccountDeletionError::NotClaimable`を返します
+    fn claim(
+        &self, deletion_id: &str, now: i64, leased_until: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>;
+    fn save(
+        &self, deletion: AccountDeletion,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AccountDeletionError>> + Send + '_>>;
+}
@@ -1,7 +1,7 @@
 use crate::{
-    auth_user::{AuthUser, AuthUserError},
+    auth_user::{AuthUser, AuthUserError, FederatedSignIn, GuestSignUp},
     token::Token,
-    user::email::Email,
+    user::{email::Email, password::Password, user_id::UserId},
 };
 
 pub trait AuthUserRepository: Send + Sync + 'static {
@@ -14,10 +14,107 @@ pub trait AuthUserRepository: Send + Sync + 'static {
     fn sign_up(
         &self,
         auth: AuthUser,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<UserId, AuthUserError>> + Send + '_>>;
     fn verify_code(
         &self,
         email: Email,
         code: &str,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    fn refresh(
+        &self,
+        email: Email,
+        refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Token, AuthUserError>> + Send + '_>>;
+    /// パスワード再設定用のコードをメールアドレスに送信します
+    fn forgot_password(
+        &self,
+        email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// 再設定用のコードを確認し、パスワードを`new_password`に置き換えます
+    fn confirm_forgot_password(
+        &self,
+        email: Email,
+        code: &str,
+        new_password: Password,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// アクセストークンの持ち主の認証基盤上のセッションをすべて無効化します
+    fn sign_out(
+        &self,
+        access_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// 外部のIDプロバイダーで確認済みのメールアドレスでサインインします
+    ///
+    /// 未登録の場合はパスワードを持たないユーザーを作成します
+    fn federated_sign_in(
+        &self,
+        email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<FederatedSignIn, AuthUserError>> + Send + '_>>;
+    /// 期限付きのゲストユーザーを登録し、トークンを発行します
+    ///
+    /// `email`には`Email::guest`で割り当てたメールアドレスを渡します
+    /// `UserId`を割り当てる認証基盤では`user_id`を使わず、割り当てた`UserId`を返します
+    fn sign_up_guest(
+        &self,
+        user_id: UserId,
+        email: Email,
+        expires_at: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<GuestSignUp, AuthUserError>> + Send + '_>>;
+    /// ゲストユーザーに、同じ`UserId`のままメールアドレスとパスワードを登録します
+    ///
+    /// サインアップと同様にメールアドレスの確認コードを送信し、ゲストユーザーとしてはログインできなくなります
+    fn upgrade_guest(
+        &self,
+        guest_email: Email,
+        auth: AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// `upgrade_guest`で送信した確認コードで、`user_id`のゲストユーザーのメールアドレスを確認します
+    ///
+    /// 確認されるまで`email`でユーザーを特定できない認証基盤では`user_id`を使います
+    fn verify_guest_upgrade(
+        &self,
+        user_id: UserId,
+        email: Email,
+        code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// `upgrade_guest`で登録した`email`を取り消し、`expires_at`に削除される`guest_email`のゲストユーザーに戻します
+    ///
+    /// ユーザーテーブルの更新に失敗した場合に、認証基盤の変更を取り消すために使います
+    fn revert_guest_upgrade(
+        &self,
+        guest_email: Email,
+        email: Email,
+        expires_at: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// `email`のユーザーのメールアドレスを`new_email`に変更するための確認コードを`new_email`に送信します
+    ///
+    /// 変更前のメールアドレスには変更が要求されたことを通知します。確認されるまでメールアドレスは変更しません
+    fn request_email_change(
+        &self,
+        email: Email,
+        new_email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// 確認コードを確認してメールアドレスを変更し、変更後のメールアドレスを返します
+    ///
+    /// `access_token`は認証基盤が本人のトークンで確認する場合に使います
+    fn confirm_email_change(
+        &self,
+        email: Email,
+        code: &str,
+        access_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Email, AuthUserError>> + Send + '_>>;
+    /// 変更したメールアドレスを`email`に戻します
+    ///
+    /// ユーザーテーブルの更新に失敗した場合に、認証基盤の変更を取り消すために使います
+    fn revert_email_change(
+        &self,
+        new_email: Email,
+        email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+    /// 認証基盤からユーザーを削除します
+    ///
+    /// 削除を再開した場合に備え、存在しない場合も成功として扱います
+    fn delete_user(
+        &self,
+        email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
 }
@@ -0,0 +1,11 @@
+use crate::user::password::PasswordError;
+
+/// 漏洩したパスワードのSHA-1ハッシュを、先頭5文字ごとに検索するリポジトリです
+///
+/// k-anonymityの形式で、パスワードのハッシュ全体を渡さずに照合できます
+pub trait BreachedPasswordRepository: Send + Sync {
+    /// 大文字16進数のハッシュの先頭が`prefix`のものについて、残りの35文字を返します
+    fn find_range(
+        &self, prefix: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<String>, PasswordError>> + Send + '_>>;
+}
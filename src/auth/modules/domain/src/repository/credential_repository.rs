@@ -0,0 +1,20 @@
+use crate::{
+    credential::{Credential, CredentialError},
+    user::email::Email,
+};
+
+pub trait CredentialRepository: Send + Sync {
+    fn find_by_email(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Credential, CredentialError>> + Send + '_>>;
+    /// 同じメールアドレスが登録済みの場合は`CredentialError::AlreadyExists`を返します
+    fn create(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>>;
+    fn update(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>>;
+    fn delete(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>>;
+}
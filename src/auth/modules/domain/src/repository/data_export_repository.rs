@@ -0,0 +1,26 @@
+use crate::{
+    data_export::{DataExport, DataExportError},
+    user::user_id::UserId,
+};
+
+pub trait DataExportRepository: Send + Sync {
+    /// 存在しない場合は`DataExportError::NotFound`を返します
+    fn find(
+        &self, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<DataExport, DataExportError>> + Send + '_>>;
+    /// ユーザーのエクスポートを依頼日時の昇順で返します
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>;
+    /// 完了も失敗もしていないエクスポートを返します
+    fn find_active(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>;
+    fn save(
+        &self, export: DataExport,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), DataExportError>> + Send + '_>>;
+    /// ユーザーのすべてのエクスポートを削除し、削除した数を返します
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, DataExportError>> + Send + '_>>;
+}
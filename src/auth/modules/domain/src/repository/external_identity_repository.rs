@@ -0,0 +1,20 @@
+use crate::{
+    external_identity::{ExternalIdentity, ExternalIdentityError},
+    user::user_id::UserId,
+};
+
+pub trait ExternalIdentityRepository: Send + Sync {
+    /// 紐付けられていない場合は`None`を返します
+    fn find(
+        &self, provider: &str, subject: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Option<ExternalIdentity>, ExternalIdentityError>> + Send + '_>,
+    >;
+    fn save(
+        &self, identity: ExternalIdentity,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExternalIdentityError>> + Send + '_>>;
+    /// ユーザーのすべての紐付けを削除し、削除した数を返します
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, ExternalIdentityError>> + Send + '_>>;
+}
@@ -0,0 +1,14 @@
+use crate::login_attempt::{LoginAttempt, LoginAttemptError};
+
+pub trait LoginAttemptRepository: Send + Sync {
+    /// 記録がない場合、または有効期限を過ぎている場合は`None`を返します
+    fn find(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<LoginAttempt>, LoginAttemptError>> + Send + '_>>;
+    fn save(
+        &self, attempt: LoginAttempt,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>>;
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>>;
+}
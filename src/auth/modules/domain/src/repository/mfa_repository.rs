@@ -0,0 +1,22 @@
+use crate::{
+    mfa::{Mfa, MfaError},
+    user::user_id::UserId,
+};
+
+pub trait MfaRepository: Send + Sync {
+    /// 登録されていない場合は`None`を返します
+    fn find(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<Mfa>, MfaError>> + Send + '_>>;
+    fn save(&self, mfa: Mfa) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>>;
+    /// 保存済みの設定が`expected`から変わっていない場合のみ`mfa`で更新します
+    ///
+    /// 同じコードを同時に使用したリクエストを1つだけ受け付けるために使います
+    /// 変わっている場合は`MfaError::Conflict`を返します
+    fn update(
+        &self, mfa: Mfa, expected: &Mfa,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>>;
+    fn delete(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>>;
+}
@@ -0,0 +1,25 @@
+use crate::file::FileError;
+
+/// プロフィールアイコンやエクスポートしたファイルなど、バイナリのファイルを保存します
+///
+/// キーは`/`で区切ったパスです
+pub trait ObjectStorage: Send + Sync {
+    /// 同じキーのファイルは上書きします
+    ///
+    /// `content_type`は配信する際の`Content-Type`です。ローカルのファイルシステムでは使用しません
+    fn put(
+        &self, key: &str, content: Vec<u8>, content_type: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>>;
+    /// 存在しない場合は`FileError::NotFound`を返します
+    fn get(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, FileError>> + Send + '_>>;
+    /// 存在しない場合も成功として扱います
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>>;
+    /// `prefix`を`/`で区切った下位のすべてのファイルを削除し、削除した数を返します
+    fn delete_by_prefix(
+        &self, prefix: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, FileError>> + Send + '_>>;
+}
@@ -0,0 +1,32 @@
+use crate::{
+    personal_access_token::{PersonalAccessToken, PersonalAccessTokenError},
+    user::user_id::UserId,
+};
+
+pub trait PersonalAccessTokenRepository: Send + Sync {
+    /// 存在しない場合は`None`を返します
+    fn find(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = Result<Option<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_,
+        >,
+    >;
+    /// ユーザーのトークンを作成日時の昇順で返します
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_>,
+    >;
+    fn save(
+        &self, token: PersonalAccessToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>>;
+    /// 存在しない場合は`PersonalAccessTokenError::NotFound`を返します
+    fn delete(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>>;
+    /// ユーザーのすべてのトークンを削除し、削除した数を返します
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, PersonalAccessTokenError>> + Send + '_>>;
+}
@@ -0,0 +1,32 @@
+use crate::{
+    refresh_token::{RefreshTokenError, RefreshTokenFamily},
+    user::user_id::UserId,
+};
+
+pub trait RefreshTokenRepository: Send + Sync {
+    fn find_by_family_id(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<RefreshTokenFamily, RefreshTokenError>> + Send + '_>>;
+    /// 無効化済みや有効期限切れを含む、ユーザーのすべての系列を返します
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<RefreshTokenFamily>, RefreshTokenError>> + Send + '_>,
+    >;
+    fn create(
+        &self, family: RefreshTokenFamily,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>>;
+    /// 保存されている`token_hash`が`expected_hash`と一致し、無効化されていない場合のみ更新します
+    ///
+    /// 一致しない場合は`RefreshTokenError::Conflict`を返します
+    fn rotate(
+        &self, family: RefreshTokenFamily, expected_hash: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>>;
+    fn revoke(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>>;
+    /// 無効化済みや有効期限切れを含む、ユーザーのすべての系列を削除し、削除した数を返します
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, RefreshTokenError>> + Send + '_>>;
+}
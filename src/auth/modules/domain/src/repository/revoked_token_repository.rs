@@ -0,0 +1,17 @@
+use crate::revoked_token::{RevokedToken, RevokedTokenError};
+
+pub trait RevokedTokenRepository: Send + Sync {
+    fn save(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RevokedTokenError>> + Send + '_>>;
+    /// `token`がまだ無効化されていない場合のみ保存し、保存した場合に`true`を返します
+    ///
+    /// 一度しか使えないトークンを同時に使用したリクエストを、1つだけ受け付けるために使います
+    fn revoke_once(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>>;
+    /// `jti`のトークンが無効化されていて、まだ有効期限内の場合に`true`を返します
+    fn is_revoked(
+        &self, jti: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>>;
+}
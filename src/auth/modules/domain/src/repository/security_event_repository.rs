@@ -0,0 +1,21 @@
+use crate::{
+    security_event::{SecurityEvent, SecurityEventError, SecurityEventType},
+    user::user_id::UserId,
+};
+
+/// 監査ログは追記のみで、変更と削除のメソッドは持ちません
+///
+/// 期間は`from`以上`to`未満の発生日時で、結果は発生日時の新しい順に最大`limit`件です
+pub trait SecurityEventRepository: Send + Sync {
+    fn save(
+        &self, event: SecurityEvent,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), SecurityEventError>> + Send + '_>>;
+    /// `event_type`を指定した場合、その種類のイベントのみ返します
+    fn find_by_user_id(
+        &self, user_id: &UserId, event_type: Option<SecurityEventType>, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>;
+    /// ユーザーを特定できないイベントも含めて返します
+    fn find_by_event_type(
+        &self, event_type: SecurityEventType, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>;
+}
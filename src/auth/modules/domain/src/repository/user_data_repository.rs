@@ -0,0 +1,18 @@
+use crate::{
+    user::user_id::UserId,
+    user_data::{UserDataError, UserDataItem},
+};
+
+/// サブスクリプションやカテゴリーなど、ユーザーが登録したデータを取得、削除します
+pub trait UserDataRepository: Send + Sync {
+    /// 削除証明やエクスポートのファイル名に使う、データの種類の名前です
+    fn target(&self) -> &str;
+    /// ユーザーのデータをすべて返します
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<UserDataItem>, UserDataError>> + Send + '_>>;
+    /// ユーザーのデータをすべて削除し、削除した件数を返します
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, UserDataError>> + Send + '_>>;
+}
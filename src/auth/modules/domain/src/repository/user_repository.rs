@@ -1,10 +1,19 @@
-use crate::user::{user_error::UserError, User};
+use crate::user::{email::Email, user_error::UserError, user_id::UserId, User};
 
 pub trait UserRepository: Send + Sync {
     fn find_by_id(
         &self,
-        id: i32,
+        id: &UserId,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>>;
+    /// メールアドレスのGSIで検索します
+    fn find_by_email(
+        &self,
+        email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>>;
+    fn exists_by_email(
+        &self,
+        email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, UserError>> + Send + '_>>;
     fn create(
         &self,
         user: User,
@@ -13,4 +22,9 @@ pub trait UserRepository: Send + Sync {
         &self,
         user: User,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>>;
+    /// 存在しない場合も成功として扱います
+    fn delete(
+        &self,
+        id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>>;
 }
@@ -0,0 +1,49 @@
+/// 有効期限前に無効化されたアクセストークンです
+///
+/// JWTの`jti`で識別し、トークンの有効期限を過ぎたら破棄できます
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevokedToken {
+    jti: String,
+    expires_at: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevokedTokenError {
+    #[error("Failed to find revoked token: {0}")]
+    FindError(String),
+
+    #[error("Failed to save revoked token: {0}")]
+    SaveError(String),
+}
+
+impl RevokedToken {
+    pub fn new(jti: String, expires_at: i64) -> Self {
+        Self { jti, expires_at }
+    }
+
+    pub fn jti(&self) -> &str {
+        &self.jti
+    }
+
+    /// 元のトークンの有効期限です
+    pub fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_expired() {
+        let token = RevokedToken::new("jti".to_string(), 100);
+        assert!(!token.is_expired(99));
+        assert!(token.is_expired(100));
+        assert!(token.is_expired(101));
+    }
+}
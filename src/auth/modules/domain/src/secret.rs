@@ -0,0 +1,88 @@
+use std::fmt::{Debug, Display};
+
+use zeroize::Zeroize;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// パスワードやトークンなど、ログに出力してはいけない文字列です
+///
+/// `Debug`と`Display`は値を伏せて出力し、値は`expose_secret`でのみ取り出せます
+/// 破棄する際にメモリ上の値を0で上書きします
+#[derive(Clone, Default)]
+pub struct SecretString {
+    value: String,
+}
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self { value }
+    }
+
+    /// 秘密の値を取り出します。ログやレスポンスに含める場合は、必要な箇所でのみ呼び出します
+    pub fn expose_secret(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretString({})", REDACTED)
+    }
+}
+
+impl Display for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        let secret = SecretString::from("Password123");
+
+        assert_eq!("SecretString([REDACTED])", format!("{:?}", secret));
+        assert_eq!("[REDACTED]", secret.to_string());
+        assert_eq!("Password123", secret.expose_secret());
+    }
+
+    /// `Drop`で呼び出す`zeroize`が、確保済みの領域を0で上書きすることを確認します
+    #[test]
+    fn test_zeroize() {
+        let mut value = String::with_capacity(32);
+        value.push_str("Password123");
+        let ptr = value.as_ptr();
+        let capacity = value.capacity();
+
+        // `zeroize`は長さを0にしますが、領域は解放しないため読み出せます
+        value.zeroize();
+        let buffer = unsafe { std::slice::from_raw_parts(ptr, capacity) };
+        assert!(buffer.iter().all(|v| *v == 0));
+        assert!(value.is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::user::user_id::UserId;
+
+/// 認証とアカウントに関わる操作の監査ログです
+///
+/// 記録したイベントは変更も削除もしません
+/// ログインの失敗など、ユーザーを特定できないイベントは`user_id`を持たず、入力されたメールアドレスを`email`に記録します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityEvent {
+    event_id: String,
+    user_id: Option<UserId>,
+    email: Option<String>,
+    event_type: SecurityEventType,
+    occurred_at: i64,
+    ip: String,
+    user_agent: String,
+    /// ログインの方法や無効化したトークンの種類などの補足です
+    detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecurityEventType {
+    SignUp,
+    /// 登録済みのメールアドレスでのサインアップです
+    SignUpRejected,
+    EmailVerified,
+    LoginSucceeded,
+    LoginFailed,
+    PasswordReset,
+    EmailChanged,
+    MfaEnabled,
+    MfaDisabled,
+    TokenRevoked,
+    /// 無効または使用済みのリフレッシュトークンが提示されました
+    InvalidToken,
+    AccountDeletionRequested,
+    AccountDeletionCancelled,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecurityEventError {
+    #[error("Invalid security event type: {0}")]
+    InvalidEventType(String),
+
+    /// 期間の指定が不正です
+    #[error("Invalid time range: {0}")]
+    InvalidTimeRange(String),
+
+    /// 検索には`user_id`と`event_type`の少なくとも一方が必要です
+    #[error("Security event filter required")]
+    FilterRequired,
+
+    #[error("Failed to find security events: {0}")]
+    FindError(String),
+
+    #[error("Failed to save security event: {0}")]
+    SaveError(String),
+}
+
+impl SecurityEvent {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        event_id: String, user_id: Option<UserId>, email: Option<String>, event_type: SecurityEventType,
+        occurred_at: i64, ip: String, user_agent: String, detail: Option<String>,
+    ) -> Self {
+        Self { event_id, user_id, email, event_type, occurred_at, ip, user_agent, detail }
+    }
+
+    /// ユーザーを指定せずにイベントを作成します
+    ///
+    /// `with_user_id`と`with_email`で対象を指定します
+    pub fn occurred(
+        event_id: String, event_type: SecurityEventType, occurred_at: i64, ip: String, user_agent: String,
+    ) -> Self {
+        Self::new(event_id, None, None, event_type, occurred_at, ip, user_agent, None)
+    }
+
+    pub fn with_user_id(self, user_id: UserId) -> Self {
+        Self { user_id: Some(user_id), ..self }
+    }
+
+    pub fn with_email(self, email: String) -> Self {
+        Self { email: Some(email), ..self }
+    }
+
+    pub fn with_detail(self, detail: &str) -> Self {
+        Self { detail: Some(detail.to_string()), ..self }
+    }
+
+    pub fn event_id(&self) -> &str {
+        &self.event_id
+    }
+
+    pub fn user_id(&self) -> Option<&UserId> {
+        self.user_id.as_ref()
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    pub fn event_type(&self) -> SecurityEventType {
+        self.event_type
+    }
+
+    pub fn occurred_at(&self) -> i64 {
+        self.occurred_at
+    }
+
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+}
+
+impl std::str::FromStr for SecurityEventType {
+    type Err = SecurityEventError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sign_up" => Ok(SecurityEventType::SignUp),
+            "sign_up_rejected" => Ok(SecurityEventType::SignUpRejected),
+            "email_verified" => Ok(SecurityEventType::EmailVerified),
+            "login_succeeded" => Ok(SecurityEventType::LoginSucceeded),
+            "login_failed" => Ok(SecurityEventType::LoginFailed),
+            "password_reset" => Ok(SecurityEventType::PasswordReset),
+            "email_changed" => Ok(SecurityEventType::EmailChanged),
+            "mfa_enabled" => Ok(SecurityEventType::MfaEnabled),
+            "mfa_disabled" => Ok(SecurityEventType::MfaDisabled),
+            "token_revoked" => Ok(SecurityEventType::TokenRevoked),
+            "invalid_token" => Ok(SecurityEventType::InvalidToken),
+            "account_deletion_requested" => Ok(SecurityEventType::AccountDeletionRequested),
+            "account_deletion_cancelled" => Ok(SecurityEventType::AccountDeletionCancelled),
+            _ => Err(SecurityEventError::InvalidEventType(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for SecurityEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityEventType::SignUp => write!(f, "sign_up"),
+            SecurityEventType::SignUpRejected => write!(f, "sign_up_rejected"),
+            SecurityEventType::EmailVerified => write!(f, "email_verified"),
+            SecurityEventType::LoginSucceeded => write!(f, "login_succeeded"),
+            SecurityEventType::LoginFailed => write!(f, "login_failed"),
+            SecurityEventType::PasswordReset => write!(f, "password_reset"),
+            SecurityEventType::EmailChanged => write!(f, "email_changed"),
+            SecurityEventType::MfaEnabled => write!(f, "mfa_enabled"),
+            SecurityEventType::MfaDisabled => write!(f, "mfa_disabled"),
+            SecurityEventType::TokenRevoked => write!(f, "token_revoked"),
+            SecurityEventType::InvalidToken => write!(f, "invalid_token"),
+            SecurityEventType::AccountDeletionRequested => write!(f, "account_deletion_requested"),
+            SecurityEventType::AccountDeletionCancelled => write!(f, "account_deletion_cancelled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_event_type_from_str_and_display() {
+        let test_cases = vec![
+            ("sign_up", SecurityEventType::SignUp),
+            ("sign_up_rejected", SecurityEventType::SignUpRejected),
+            ("email_verified", SecurityEventType::EmailVerified),
+            ("login_succeeded", SecurityEventType::LoginSucceeded),
+            ("login_failed", SecurityEventType::LoginFailed),
+            ("password_reset", SecurityEventType::PasswordReset),
+            ("email_changed", SecurityEventType::EmailChanged),
+            ("mfa_enabled", SecurityEventType::MfaEnabled),
+            ("mfa_disabled", SecurityEventType::MfaDisabled),
+            ("token_revoked", SecurityEventType::TokenRevoked),
+            ("invalid_token", SecurityEventType::InvalidToken),
+            ("account_deletion_requested", SecurityEventType::AccountDeletionRequested),
+            ("account_deletion_cancelled", SecurityEventType::AccountDeletionCancelled),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, SecurityEventType::from_str(value).unwrap());
+            assert_eq!(value, expected.to_string());
+        }
+        assert!(matches!(SecurityEventType::from_str("login"), Err(SecurityEventError::InvalidEventType(_))));
+    }
+
+    #[test]
+    fn test_occurred() {
+        let user_id = UserId::new();
+        let event = SecurityEvent::occurred(
+            "evt_1".to_string(),
+            SecurityEventType::LoginFailed,
+            100,
+            "192.0.2.1".to_string(),
+            "Firefox/121.0".to_string(),
+        );
+        assert_eq!(None, event.user_id());
+        assert_eq!(None, event.detail());
+
+        let event = event.with_user_id(user_id.clone()).with_email("hoge@email.com".to_string()).with_detail("mfa");
+        assert_eq!(Some(&user_id), event.user_id());
+        assert_eq!(Some("hoge@email.com"), event.email());
+        assert_eq!(Some("mfa"), event.detail());
+        assert_eq!(SecurityEventType::LoginFailed, event.event_type());
+    }
+}
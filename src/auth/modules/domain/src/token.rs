@@ -1,24 +1,313 @@
-#[derive(Debug)]
+use base64::Engine;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{clock::Clock, secret::SecretString};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TokenError {
+    #[error("Invalid token claims")]
+    InvalidClaims,
+}
+
+/// 認証基盤が発行したアクセストークン、IDトークン、リフレッシュトークンです
+///
+/// ログに出力しないよう、値は`expose_secret`でのみ取り出せます
+/// シリアライズではクライアントに返すアクセストークンとIDトークン、有効期間のみ出力し、
+/// 認証基盤のリフレッシュトークンは出力しません
+#[derive(Debug, Clone)]
 pub struct Token {
-    jwt: String,
-    refresh: String,
+    access_token: AccessToken,
+    id_token: Option<IdToken>,
+    refresh_token: RefreshToken,
+    issued_at: i64,
+    expires_in: u64,
+}
+
+/// APIの認可に使うアクセストークンです
+#[derive(Debug, Clone)]
+pub struct AccessToken(SecretString);
+
+/// ユーザーの属性を含むIDトークンです
+#[derive(Debug, Clone)]
+pub struct IdToken(SecretString);
+
+/// 認証基盤でアクセストークンを再発行するためのリフレッシュトークンです
+#[derive(Debug, Clone)]
+pub struct RefreshToken(SecretString);
+
+/// JWTのペイロードから取り出したクレームです
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenClaims {
+    sub: String,
+    email: Option<String>,
+    scopes: Vec<String>,
+    token_use: TokenUse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenUse {
+    Access,
+    Id,
+}
+
+#[derive(Deserialize)]
+struct RawClaims {
+    sub: String,
+    email: Option<String>,
+    #[serde(default)]
+    scope: String,
+    token_use: TokenUse,
 }
 
 impl Token {
-    pub fn new(jwt: String, refresh: String) -> Self {
-        Self { jwt, refresh }
+    /// `issued_at`は発行日時のUNIX秒、`expires_in`はアクセストークンの有効期間の秒数です
+    pub fn new(access_token: AccessToken, refresh_token: RefreshToken, issued_at: i64, expires_in: u64) -> Self {
+        Self { access_token, id_token: None, refresh_token, issued_at, expires_in }
+    }
+
+    /// IDトークンを設定した値を返します
+    pub fn with_id_token(self, id_token: Option<IdToken>) -> Self {
+        Self { id_token, ..self }
+    }
+
+    pub fn access_token(&self) -> &AccessToken {
+        &self.access_token
+    }
+
+    pub fn id_token(&self) -> Option<&IdToken> {
+        self.id_token.as_ref()
+    }
+
+    pub fn refresh_token(&self) -> &RefreshToken {
+        &self.refresh_token
+    }
+
+    pub fn issued_at(&self) -> i64 {
+        self.issued_at
+    }
+
+    pub fn expires_in(&self) -> u64 {
+        self.expires_in
+    }
+
+    /// アクセストークンの有効期限のUNIX秒です
+    pub fn expires_at(&self) -> i64 {
+        self.issued_at.saturating_add(self.expires_in as i64)
+    }
+
+    /// `clock`の時刻でアクセストークンの有効期限が切れているかを返します
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expires_at() <= clock.now()
+    }
+
+    /// アクセストークンのクレームです
+    pub fn claims(&self) -> Result<TokenClaims, TokenError> {
+        self.access_token.claims()
+    }
+}
+
+impl Serialize for Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Token", 4)?;
+        state.serialize_field("access_token", self.access_token.expose_secret())?;
+        match &self.id_token {
+            Some(id_token) => state.serialize_field("id_token", id_token.expose_secret())?,
+            None => state.skip_field("id_token")?,
+        }
+        state.serialize_field("token_type", "Bearer")?;
+        state.serialize_field("expires_in", &self.expires_in)?;
+        state.end()
+    }
+}
+
+impl AccessToken {
+    pub fn expose_secret(&self) -> &str {
+        self.0.expose_secret()
+    }
+
+    /// 署名は検証しないため、認証基盤から直接受け取ったトークンにのみ使用してください
+    pub fn claims(&self) -> Result<TokenClaims, TokenError> {
+        decode_claims(self.expose_secret())
+    }
+}
+
+impl From<String> for AccessToken {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+impl IdToken {
+    pub fn expose_secret(&self) -> &str {
+        self.0.expose_secret()
     }
+
+    /// 署名は検証しないため、認証基盤から直接受け取ったトークンにのみ使用してください
+    pub fn claims(&self) -> Result<TokenClaims, TokenError> {
+        decode_claims(self.expose_secret())
+    }
+}
+
+impl From<String> for IdToken {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+impl RefreshToken {
+    pub fn expose_secret(&self) -> &str {
+        self.0.expose_secret()
+    }
+}
+
+impl From<String> for RefreshToken {
+    fn from(value: String) -> Self {
+        Self(value.into())
+    }
+}
+
+impl TokenClaims {
+    pub fn sub(&self) -> &str {
+        &self.sub
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn token_use(&self) -> TokenUse {
+        self.token_use
+    }
+}
+
+/// JWTのペイロードをデコードします
+///
+/// `scope`は空白区切りの文字列のため、スコープごとに分割します
+fn decode_claims(jwt: &str) -> Result<TokenClaims, TokenError> {
+    let payload = jwt.split('.').nth(1).ok_or(TokenError::InvalidClaims)?;
+    let payload =
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(payload).map_err(|_| TokenError::InvalidClaims)?;
+    let claims: RawClaims = serde_json::from_slice(&payload).map_err(|_| TokenError::InvalidClaims)?;
+
+    Ok(TokenClaims {
+        sub: claims.sub,
+        email: claims.email,
+        scopes: claims.scope.split_whitespace().map(str::to_string).collect(),
+        token_use: claims.token_use,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::token::Token;
+    use base64::Engine;
+    use serde_json::json;
+
+    use crate::{
+        clock::FixedClock,
+        token::{Token, TokenError, TokenUse},
+    };
+
+    fn jwt(payload: serde_json::Value) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        format!(
+            "{}.{}.signature",
+            engine.encode(json!({ "alg": "none" }).to_string()),
+            engine.encode(payload.to_string())
+        )
+    }
+
+    fn token(access_token: &str) -> Token {
+        Token::new(access_token.to_string().into(), "refresh_token".to_string().into(), 100, 3600)
+    }
 
     #[test]
     fn test_token_create_success() {
-        let result = Token::new("jwt_token".to_string(), "refresh_token".to_string());
+        let result = token("jwt_token").with_id_token(Some("id_token".to_string().into()));
+
+        assert_eq!(result.access_token().expose_secret(), "jwt_token");
+        assert_eq!(result.id_token().unwrap().expose_secret(), "id_token");
+        assert_eq!(result.refresh_token().expose_secret(), "refresh_token");
+        assert_eq!(result.issued_at(), 100);
+        assert_eq!(result.expires_in(), 3600);
+        assert_eq!(result.expires_at(), 3700);
+    }
+
+    #[test]
+    fn test_token_debug_redacted() {
+        let token = Token::new("access_secret".to_string().into(), "refresh_secret".to_string().into(), 100, 3600)
+            .with_id_token(Some("id_secret".to_string().into()));
+        let result = format!("{:?}", token);
+
+        assert!(!result.contains("access_secret"));
+        assert!(!result.contains("id_secret"));
+        assert!(!result.contains("refresh_secret"));
+    }
 
-        assert_eq!(result.jwt, "jwt_token".to_string());
-        assert_eq!(result.refresh, "refresh_token".to_string())
+    #[test]
+    fn test_is_expired() {
+        let test_cases = vec![
+            (100, false),
+            (3699, false),
+            (3700, true),
+            (3701, true),
+        ];
+        for (now, expected) in test_cases {
+            assert_eq!(expected, token("jwt_token").is_expired(&FixedClock(now)), "now: {}", now);
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let test_cases = vec![
+            (token("jwt_token"), json!({ "access_token": "jwt_token", "token_type": "Bearer", "expires_in": 3600 })),
+            (
+                token("jwt_token").with_id_token(Some("id_token".to_string().into())),
+                json!({ "access_token": "jwt_token", "id_token": "id_token", "token_type": "Bearer", "expires_in": 3600 }),
+            ),
+        ];
+        for (token, expected) in test_cases {
+            assert_eq!(expected, serde_json::to_value(&token).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_claims() {
+        let access_token = jwt(json!({
+            "sub": "sub",
+            "token_use": "access",
+            "scope": "openid  profile email",
+        }));
+        let claims = token(&access_token).claims().unwrap();
+        assert_eq!("sub", claims.sub());
+        assert_eq!(None, claims.email());
+        assert_eq!(vec!["openid", "profile", "email"], claims.scopes());
+        assert_eq!(TokenUse::Access, claims.token_use());
+
+        let id_token = jwt(json!({ "sub": "sub", "token_use": "id", "email": "hoge@email.com" }));
+        let claims = token("jwt_token").with_id_token(Some(id_token.into())).id_token().unwrap().claims().unwrap();
+        assert_eq!(Some("hoge@email.com"), claims.email());
+        assert!(claims.scopes().is_empty());
+        assert_eq!(TokenUse::Id, claims.token_use());
+    }
+
+    #[test]
+    fn test_claims_failed() {
+        let test_cases = vec![
+            "".to_string(),
+            "invalid".to_string(),
+            "a.!!!.c".to_string(),
+            jwt(json!({ "token_use": "access" })),
+            jwt(json!({ "sub": "sub" })),
+            jwt(json!({ "sub": "sub", "token_use": "refresh" })),
+        ];
+        for value in test_cases {
+            assert_eq!(Err(TokenError::InvalidClaims), token(&value).claims(), "value: {}", value);
+        }
     }
 }
@@ -1,6 +1,7 @@
 pub mod email;
 pub mod name;
 pub mod password;
+pub mod role;
 pub mod user_error;
 pub mod user_id;
 pub mod user_type;
@@ -9,6 +10,7 @@ use std::str::FromStr;
 
 use email::Email;
 use name::Name;
+use role::Role;
 use user_error::UserError;
 use user_id::UserId;
 use user_type::UserType;
@@ -20,11 +22,22 @@ pub struct User {
     name: Name,
     user_type: UserType,
     profile_icon_path: Option<String>,
+    role: Role,
+    /// ゲストユーザーを削除する日時のUNIX秒です。登録済みのユーザーは`None`です
+    expires_at: Option<i64>,
 }
 
 impl User {
     fn new(user_id: UserId, email: Email, name: Name, user_type: UserType, profile_icon_path: Option<String>) -> Self {
-        Self { user_id, email, name, user_type, profile_icon_path }
+        Self { user_id, email, name, user_type, profile_icon_path, role: Role::default(), expires_at: None }
+    }
+
+    /// `expires_at`に削除されるゲストユーザーを作成します
+    ///
+    /// メールアドレスには`Email::guest`で割り当てた値を使います
+    pub fn guest(user_id: UserId, name: Name, expires_at: i64) -> Self {
+        let email = Email::guest(&user_id);
+        Self { expires_at: Some(expires_at), ..Self::new(user_id, email, name, UserType::Guest, None) }
     }
 
     pub fn build(
@@ -35,7 +48,56 @@ impl User {
         let name = Name::from_str(name)?;
         let user_type = UserType::from_usize(user_type)?;
 
-        Ok(Self { user_id, email, name, user_type, profile_icon_path })
+        Ok(Self::new(user_id, email, name, user_type, profile_icon_path))
+    }
+
+    /// ロールを設定した値を返します
+    pub fn with_role(self, role: Role) -> Self {
+        Self { role, ..self }
+    }
+
+    /// 削除する日時を設定した値を返します
+    pub fn with_expires_at(self, expires_at: Option<i64>) -> Self {
+        Self { expires_at, ..self }
+    }
+
+    /// 認証基盤が割り当てた`UserId`に置き換えた値を返します。メールアドレスは変更しません
+    pub fn with_user_id(self, user_id: UserId) -> Self {
+        Self { user_id, ..self }
+    }
+
+    /// ゲストユーザーにメールアドレスと名前を登録した値を返します
+    ///
+    /// メールアドレスが確認されるまではゲストユーザーのままです
+    /// 確認を待つ間に削除されないよう、削除する日時を取り除きます
+    pub fn register(&self, email: Email, name: Name) -> Self {
+        Self { email, name, expires_at: None, ..self.clone() }
+    }
+
+    /// メールアドレスを確認したゲストユーザーを、同じ`UserId`のまま登録済みのユーザーにした値を返します
+    pub fn complete_registration(&self) -> Self {
+        Self { user_type: UserType::Registerd, expires_at: None, ..self.clone() }
+    }
+
+    /// メールアドレスを置き換えた値を返します
+    ///
+    /// 認証基盤で変更後のメールアドレスを確認してから使用してください
+    pub fn change_email(&self, email: Email) -> Self {
+        Self { email, ..self.clone() }
+    }
+
+    /// プロフィールアイコンのキーを置き換えた値を返します。`None`の場合はアイコンを取り除きます
+    pub fn change_profile_icon(&self, profile_icon_path: Option<String>) -> Self {
+        Self { profile_icon_path, ..self.clone() }
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.user_type == UserType::Guest
+    }
+
+    /// `now`の時点で削除する日時を過ぎている場合に`true`を返します
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|v| v <= now)
     }
 
     pub fn user_id(&self) -> &UserId {
@@ -57,12 +119,21 @@ impl User {
     pub fn profile_icon_path(&self) -> &Option<String> {
         &self.profile_icon_path
     }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires_at
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
     fn test_user_build_success() {
         let user_id = "usr_123e4567-e89b-12d3-a456-426614174000";
         let email = "test@example.com";
@@ -75,8 +146,12 @@ mod tests {
         assert_eq!(user.user_id.to_string(), user_id);
         assert_eq!(user.email.to_string(), email);
         assert_eq!(user.name.to_string(), name);
+        assert_eq!(Role::User, user.role());
         assert_eq!(user.user_type as usize, user_type);
         assert_eq!(user.profile_icon_path, profile_icon);
+
+        let admin = User::build(user_id, email, name, user_type, None).unwrap().with_role(Role::Admin);
+        assert_eq!(Role::Admin, admin.role());
     }
 
     #[test]
@@ -99,6 +174,56 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_guest_and_register() {
+        let guest = User::guest(UserId::new(), Name::from_str("Guest").unwrap(), 100);
+        assert!(guest.is_guest());
+        assert!(guest.email().is_guest());
+        assert_eq!(Some(100), guest.expires_at());
+
+        let test_cases = vec![
+            (99, false),
+            (100, true),
+            (101, true),
+        ];
+        for (now, expected) in test_cases {
+            assert_eq!(expected, guest.is_expired(now));
+        }
+
+        let pending = guest.register(Email::from_str("test@example.com").unwrap(), Name::from_str("Test User").unwrap());
+        assert!(pending.is_guest());
+        assert_eq!(None, pending.expires_at());
+
+        let user = pending.complete_registration();
+        assert_eq!(guest.user_id(), user.user_id());
+        assert_eq!(UserType::Registerd, *user.user_type());
+        assert_eq!("test@example.com", user.email().to_string());
+        assert_eq!(None, user.expires_at());
+        assert!(!user.is_expired(101));
+    }
+
+    #[test]
+    fn test_change_email() {
+        let user =
+            User::build("usr_123e4567-e89b-12d3-a456-426614174000", "test@example.com", "Test User", 1, None).unwrap();
+
+        let result = user.change_email(Email::from_str("new@example.com").unwrap());
+        assert_eq!("new@example.com", result.email().to_string());
+        assert_eq!(user.user_id(), result.user_id());
+        assert_eq!(user.name().to_string(), result.name().to_string());
+    }
+
+    #[test]
+    fn test_change_profile_icon() {
+        let user =
+            User::build("usr_123e4567-e89b-12d3-a456-426614174000", "test@example.com", "Test User", 1, None).unwrap();
+
+        let result = user.change_profile_icon(Some("icons/usr_1/icon_1/256.png".to_string()));
+        assert_eq!(&Some("icons/usr_1/icon_1/256.png".to_string()), result.profile_icon_path());
+        assert_eq!(user.user_id(), result.user_id());
+        assert!(result.change_profile_icon(None).profile_icon_path().is_none());
+    }
+
     #[test]
     fn test_user_build_zero_user_type() {
         let result = User::build("usr_123e4567-e89b-12d3-a456-426614174000", "test@example.com", "Test User", 0, None);
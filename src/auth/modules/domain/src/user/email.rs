@@ -3,11 +3,25 @@ use std::{fmt::Display, str::FromStr};
 use regex::Regex;
 use thiserror::Error;
 
+use super::user_id::UserId;
+
+/// ゲストユーザーに割り当てるメールアドレスのドメインです
+///
+/// 実在しないよう、RFC 2606で予約された`.test`を使います
+const GUEST_EMAIL_DOMAIN: &str = "guest.subtrack.test";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Email {
     value: String,
 }
 
+/// RFC 5321のローカル部の最大オクテット数です
+const LOCAL_PART_MAX_LENGTH: usize = 64;
+/// RFC 5321のドメインの最大オクテット数です
+const DOMAIN_MAX_LENGTH: usize = 255;
+/// RFC 5321のパスの上限256オクテットから、前後の`<`と`>`を除いたメールアドレスの最大オクテット数です
+const EMAIL_MAX_LENGTH: usize = 254;
+
 #[derive(Debug, Error)]
 pub enum EmailError {
     #[error("Regex compile failed {0}")]
@@ -18,12 +32,45 @@ pub enum EmailError {
 }
 
 impl Email {
+    /// メールアドレスを小文字に変換し、ドメインはIDNAでASCIIに変換します
+    ///
+    /// 国際化ドメイン名はPunycodeに変換し、同じドメインの表記の違いで別のユーザーにならないようにします
+    /// ローカル部の大文字と小文字を区別するメールサーバーはほとんどないため、ローカル部も小文字にそろえます
+    fn normalize(email: &str) -> Result<String, EmailError> {
+        let (local_part, domain) = email.trim().rsplit_once('@').ok_or(EmailError::ValidateFailed)?;
+        let domain = idna::domain_to_ascii(domain).map_err(|_| EmailError::ValidateFailed)?;
+        Ok(format!("{}@{}", local_part.to_ascii_lowercase(), domain))
+    }
+
     fn validate_email(email: &str) -> Result<bool, EmailError> {
-        let regex = Regex::new(r"^[a-z0-9]([a-z0-9._%+-]{0,61}[a-z0-9])?@[a-z0-9-]{1,63}(\.[a-z0-9-]{1,63})*\.[a-z]{2,6}$")
-            .map_err(|e| EmailError::RegexCompilationFailed(e.to_string()))?;
+        let Some((local_part, domain)) = email.rsplit_once('@') else {
+            return Ok(false);
+        };
+        if email.len() > EMAIL_MAX_LENGTH
+            || local_part.len() > LOCAL_PART_MAX_LENGTH
+            || domain.len() > DOMAIN_MAX_LENGTH
+            || local_part.contains("..")
+        {
+            return Ok(false);
+        }
+
+        let regex = Regex::new(
+            r"^[A-Za-z0-9]([A-Za-z0-9._%+-]*[A-Za-z0-9])?@([a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?\.)+([a-z]{2,63}|xn--[a-z0-9-]{1,59})$",
+        )
+        .map_err(|e| EmailError::RegexCompilationFailed(e.to_string()))?;
         Ok(regex.is_match(email))
     }
 
+    /// メールアドレスを持たないゲストユーザーに、`UserId`から決まるメールアドレスを割り当てます
+    pub fn guest(user_id: &UserId) -> Self {
+        Self { value: format!("{}@{}", user_id.to_string().replace('_', "."), GUEST_EMAIL_DOMAIN) }
+    }
+
+    /// ゲストユーザーに割り当てたメールアドレスの場合に`true`を返します
+    pub fn is_guest(&self) -> bool {
+        self.value.ends_with(&format!("@{}", GUEST_EMAIL_DOMAIN))
+    }
+
     pub fn value(&self) -> &str {
         &self.value
     }
@@ -33,10 +80,9 @@ impl FromStr for Email {
     type Err = EmailError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match Self::validate_email(s)? {
-            true => Ok(Self {
-                value: s.to_string(),
-            }),
+        let value = Self::normalize(s)?;
+        match Self::validate_email(&value)? {
+            true => Ok(Self { value }),
             false => Err(EmailError::ValidateFailed),
         }
     }
@@ -109,6 +155,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_str_normalize() {
+        let test_cases = vec![
+            ("Hoge@Email.COM", "hoge@email.com"),
+            (" hoge@email.com ", "hoge@email.com"),
+            ("hoge@例え.jp", "hoge@xn--r8jz45g.jp"),
+            ("hoge@xn--r8jz45g.jp", "hoge@xn--r8jz45g.jp"),
+            ("hoge@ＥＸＡＭＰＬＥ.com", "hoge@example.com"),
+            ("hoge@example.みんな", "hoge@example.xn--q9jyb4c"),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, Email::from_str(value).unwrap().value(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_from_str_length() {
+        let domain = |length: usize| {
+            format!(
+                "{}.com",
+                [
+                    "a".repeat(63),
+                    "b".repeat(63),
+                    "c".repeat(length)
+                ]
+                .join(".")
+            )
+        };
+        let test_cases = vec![
+            (format!("{}@example.com", "a".repeat(64)), true),
+            (format!("{}@example.com", "a".repeat(65)), false),
+            (format!("{}@{}.com", "a", "b".repeat(63)), true),
+            (format!("{}@{}.com", "a", "b".repeat(64)), false),
+            // メールアドレス全体で254オクテットまでです
+            (format!("{}@{}", "a".repeat(64), domain(57)), true),
+            (format!("{}@{}", "a".repeat(64), domain(58)), false),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, Email::from_str(&value).is_ok(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_from_str_failed() {
+        let test_cases = vec![
+            "",
+            "hoge",
+            "@email.com",
+            "hoge@",
+            "hoge..fuga@email.com",
+            ".hoge@email.com",
+            "ほげ@email.com",
+            "hoge@-email.com",
+            "hoge@email..com",
+            "hoge@xn--zz.com",
+            "hoge@email.c",
+        ];
+        for value in test_cases {
+            assert!(Email::from_str(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_guest_success() {
+        let user_id = UserId::from_str("usr_550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let result = Email::guest(&user_id);
+
+        assert_eq!("usr.550e8400-e29b-41d4-a716-446655440000@guest.subtrack.test", result.value());
+        assert!(result.is_guest());
+        assert!(Email::validate_email(result.value()).unwrap());
+        assert!(!Email::from_str("hoge@email.com").unwrap().is_guest());
+    }
+
     #[test]
     fn test_display_success() {
         let email = "fuga@email.com";
@@ -1,6 +1,8 @@
 use std::{fmt::Display, str::FromStr};
 
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Name {
@@ -14,12 +16,31 @@ pub enum NameError {
 }
 
 impl Name {
+    /// ユーザー名の最大文字数です
+    ///
+    /// 結合文字や絵文字の組み合わせも見た目どおり1文字として数えます
+    pub const MAX_LENGTH: usize = 20;
+
+    /// 前後の空白を取り除き、NFCで正規化します
+    fn normalize(name: &str) -> String {
+        name.trim().nfc().collect()
+    }
+
     fn validate_name(name: &str) -> bool {
-        println!("name.len() = {}", name.len());
-        if name.len() > 20 || name.len() < 1 {
+        let length = name.graphemes(true).count();
+        if length > Self::MAX_LENGTH || length == 0 {
             return false;
         }
-        true
+        !name.chars().any(char::is_control)
+    }
+
+    /// `MAX_LENGTH`に収まるよう文字の区切りで切り詰めてから変換します
+    ///
+    /// 外部のIDプロバイダーから受け取った名前など、長さを利用者が直せない値に使います
+    pub fn truncate(name: &str) -> Result<Self, NameError> {
+        let name = Self::normalize(name);
+        let truncated: String = name.graphemes(true).take(Self::MAX_LENGTH).collect();
+        Self::from_str(&truncated)
     }
 }
 
@@ -27,10 +48,9 @@ impl FromStr for Name {
     type Err = NameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match Self::validate_name(s) {
-            true => Ok(Self {
-                value: s.to_string(),
-            }),
+        let value = Self::normalize(s);
+        match Self::validate_name(&value) {
+            true => Ok(Self { value }),
             false => Err(NameError::InvalidValidateName(s.to_string())),
         }
     }
@@ -53,18 +73,81 @@ mod tests {
         assert_eq!(String::from("hoge"), result.unwrap().value)
     }
 
+    #[test]
+    fn test_from_str_normalize() {
+        let test_cases = vec![
+            ("  hoge  ", "hoge"),
+            ("\u{3000}山田 太郎\u{3000}", "山田 太郎"),
+            // 濁点の結合文字はNFCで1文字にまとめます
+            ("か\u{3099}", "が"),
+            ("e\u{301}", "é"),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, Name::from_str(value).unwrap().value);
+        }
+    }
+
     #[test]
     fn test_display_success() {
         let result = Name::from_str("hoge").unwrap();
         assert_eq!("hoge".to_string(), result.to_string())
     }
 
+    #[test]
+    fn test_validate_name_success() {
+        let test_cases = vec![
+            "h",
+            "01234567890123456789",
+            "山田太郎",
+            "あいうえおかきくけこさしすせそたちつてと",
+            "👨‍👩‍👧‍👦👍🏽",
+            "🇯🇵",
+        ];
+        for test_case in test_cases {
+            assert!(Name::validate_name(test_case), "{}", test_case);
+        }
+    }
+
     #[test]
     fn test_validate_name_failed() {
-        let test_cases = vec!["", "012345678901234567891"];
+        let test_cases = vec![
+            "",
+            "012345678901234567891",
+            "あいうえおかきくけこさしすせそたちつてとな",
+            "hoge\u{0}",
+            "ho\nge",
+            "\u{7f}",
+        ];
         for test_case in test_cases {
             let result = Name::validate_name(test_case);
             assert!(!result);
         }
     }
+
+    #[test]
+    fn test_from_str_failed() {
+        let test_cases = vec![
+            " ", "\u{3000}", "\t\n",
+        ];
+        for test_case in test_cases {
+            assert!(Name::from_str(test_case).is_err());
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        let family = "👨\u{200d}👩\u{200d}👧\u{200d}👦";
+        let test_cases = vec![
+            ("hoge".to_string(), "hoge".to_string()),
+            ("abcdefghijklmnopqrstuvwxyz".to_string(), "abcdefghijklmnopqrst".to_string()),
+            ("あ".repeat(25), "あ".repeat(20)),
+            // 文字の途中で切り詰めません
+            (family.repeat(21), family.repeat(20)),
+            (" か\u{3099}".repeat(11), " が".repeat(10).trim().to_string()),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, Name::truncate(&value).unwrap().to_string());
+        }
+        assert!(Name::truncate("  ").is_err());
+    }
 }
@@ -2,51 +2,231 @@ use std::{fmt::Display, str::FromStr};
 
 use thiserror::Error;
 
+use super::{email::Email, name::Name};
+use crate::secret::SecretString;
+
+/// 個人情報の部分一致を判定する最小の文字数です
+///
+/// 短すぎる名前やメールアドレスのローカル部で、無関係なパスワードを拒否しないようにします
+const PERSONAL_INFO_MIN_CHARS: usize = 3;
+
+/// ログに出力しないよう、`Debug`と`Display`は値を伏せて出力します
 #[derive(Debug, Clone)]
 pub struct Password {
-    value: String,
+    value: SecretString,
 }
 
 #[derive(Debug, Error, PartialEq)]
 pub enum PasswordError {
-    #[error("Validate Error")]
-    ValidateFailed,
+    #[error("Password violates the policy: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    PolicyViolation(Vec<PasswordViolation>),
+
+    #[error("Password is empty")]
+    Empty,
+
+    #[error("Invalid character class: {0}")]
+    InvalidCharacterClass(String),
+
+    #[error("Failed to find breached password: {0}")]
+    BreachLookupError(String),
+}
+
+/// パスワードに含める文字の種類です
+///
+/// 英字と数字はASCIIに限らず、Unicodeの文字として判定します
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Letter,
+    Digit,
+    Symbol,
+}
+
+/// パスワードが満たしていないポリシーの規則です
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort(usize),
+    TooLong(usize),
+    MissingCharacterClass(CharacterClass),
+    TooManyRepeated(usize),
+    ContainsEmail,
+    ContainsName,
+    ControlCharacter,
+    Breached,
+}
+
+/// 起動時に設定するパスワードの規則です
+///
+/// 文字数は`char`の数で数えます。`max_repeated`は同じ文字が連続してよい最大の数です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub required_classes: Vec<CharacterClass>,
+    pub max_repeated: Option<usize>,
+    pub reject_personal_info: bool,
 }
 
 impl Password {
-    fn validate_password(password: &str) -> bool {
-        if password.len() < 8 {
-            return false;
+    /// 登録済みのパスワードと照合するための値を作成します
+    ///
+    /// ポリシーの変更前に登録したパスワードでもログインできるよう、空でないことのみ確認します
+    pub fn for_authentication(s: &str) -> Result<Self, PasswordError> {
+        match s.is_empty() {
+            true => Err(PasswordError::Empty),
+            false => Ok(Self { value: s.into() }),
         }
+    }
 
-        let has_lowercase = password.chars().any(|c| c.is_ascii_lowercase());
-        let has_uppercase = password.chars().any(|c| c.is_ascii_uppercase());
-        let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    pub fn expose_secret(&self) -> &str {
+        self.value.expose_secret()
+    }
+}
 
-        match (has_lowercase || has_uppercase) && has_digit {
-            true => true,
-            false => false,
+impl CharacterClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharacterClass::Lowercase => c.is_lowercase(),
+            CharacterClass::Uppercase => c.is_uppercase(),
+            CharacterClass::Letter => c.is_alphabetic(),
+            CharacterClass::Digit => c.is_numeric(),
+            CharacterClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace() && !c.is_control(),
         }
     }
 }
 
-impl FromStr for Password {
+impl FromStr for CharacterClass {
     type Err = PasswordError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match Self::validate_password(s) {
-            true => {
-                let password = { Self { value: s.into() } };
-                Ok(password)
+        match s {
+            "lowercase" => Ok(CharacterClass::Lowercase),
+            "uppercase" => Ok(CharacterClass::Uppercase),
+            "letter" => Ok(CharacterClass::Letter),
+            "digit" => Ok(CharacterClass::Digit),
+            "symbol" => Ok(CharacterClass::Symbol),
+            _ => Err(PasswordError::InvalidCharacterClass(s.to_string())),
+        }
+    }
+}
+
+impl Display for CharacterClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            CharacterClass::Lowercase => "lowercase",
+            CharacterClass::Uppercase => "uppercase",
+            CharacterClass::Letter => "letter",
+            CharacterClass::Digit => "digit",
+            CharacterClass::Symbol => "symbol",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// APIのエラーの詳細として返す識別子です
+impl Display for PasswordViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordViolation::TooShort(_) => write!(f, "too_short"),
+            PasswordViolation::TooLong(_) => write!(f, "too_long"),
+            PasswordViolation::MissingCharacterClass(v) => write!(f, "missing_{}", v),
+            PasswordViolation::TooManyRepeated(_) => write!(f, "too_many_repeated"),
+            PasswordViolation::ContainsEmail => write!(f, "contains_email"),
+            PasswordViolation::ContainsName => write!(f, "contains_name"),
+            PasswordViolation::ControlCharacter => write!(f, "control_character"),
+            PasswordViolation::Breached => write!(f, "breached"),
+        }
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            required_classes: vec![
+                CharacterClass::Letter,
+                CharacterClass::Digit,
+            ],
+            max_repeated: None,
+            reject_personal_info: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// 満たしていない規則をすべて返します
+    ///
+    /// `email`と`name`を渡した場合は、パスワードに含まれていないかを大文字と小文字を区別せずに確認します
+    pub fn violations(&self, password: &str, email: Option<&Email>, name: Option<&Name>) -> Vec<PasswordViolation> {
+        let mut violations = vec![];
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort(self.min_length));
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong(self.max_length));
+        }
+        for class in &self.required_classes {
+            if !password.chars().any(|c| class.matches(c)) {
+                violations.push(PasswordViolation::MissingCharacterClass(*class));
+            }
+        }
+        if let Some(max) = self.max_repeated {
+            if longest_run(password) > max {
+                violations.push(PasswordViolation::TooManyRepeated(max));
+            }
+        }
+        if self.reject_personal_info {
+            let lowercase = password.to_lowercase();
+            let local_part = email.and_then(|v| v.value().split('@').next());
+            if local_part.is_some_and(|v| contains_personal_info(&lowercase, v)) {
+                violations.push(PasswordViolation::ContainsEmail);
+            }
+            if name.is_some_and(|v| contains_personal_info(&lowercase, &v.to_string())) {
+                violations.push(PasswordViolation::ContainsName);
             }
-            false => Err(PasswordError::ValidateFailed),
+        }
+        if password.chars().any(char::is_control) {
+            violations.push(PasswordViolation::ControlCharacter);
+        }
+        violations
+    }
+
+    pub fn validate(
+        &self, password: &str, email: Option<&Email>, name: Option<&Name>,
+    ) -> Result<Password, PasswordError> {
+        let violations = self.violations(password, email, name);
+        match violations.is_empty() {
+            true => Ok(Password { value: password.into() }),
+            false => Err(PasswordError::PolicyViolation(violations)),
         }
     }
 }
 
+fn longest_run(password: &str) -> usize {
+    let chars: Vec<char> = password.chars().collect();
+    chars.chunk_by(|a, b| a == b).map(<[char]>::len).max().unwrap_or_default()
+}
+
+fn contains_personal_info(lowercase_password: &str, value: &str) -> bool {
+    let value = value.trim().to_lowercase();
+    value.chars().count() >= PERSONAL_INFO_MIN_CHARS && lowercase_password.contains(&value)
+}
+
+/// 既定のポリシーで検証します
+impl FromStr for Password {
+    type Err = PasswordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PasswordPolicy::default().validate(s, None, None)
+    }
+}
+
 impl Display for Password {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self.value)
+        write!(f, "{}", self.value)
     }
 }
 
@@ -65,10 +245,11 @@ mod tests {
             "ABCD1234",
             "abc123DEF",
             "123456aB",
+            "パスワード123",
         ];
         for value in test_cases {
-            let result = Password::validate_password(value);
-            assert!(result)
+            let result = PasswordPolicy::default().violations(value, None, None);
+            assert!(result.is_empty(), "{}", value)
         }
     }
 
@@ -84,13 +265,95 @@ mod tests {
             "!@#$%^&",
             "12345678",
             "abcdefghijk",
-            "パスワード123",
             "pass\nword",
             "pass\tword",
         ];
         for value in test_cases {
-            let result = Password::validate_password(value);
-            assert!(!result)
+            let result = PasswordPolicy::default().violations(value, None, None);
+            assert!(!result.is_empty(), "{}", value)
+        }
+    }
+
+    #[test]
+    fn test_violations() {
+        let policy = PasswordPolicy {
+            min_length: 10,
+            max_length: 16,
+            required_classes: vec![
+                CharacterClass::Lowercase,
+                CharacterClass::Uppercase,
+                CharacterClass::Digit,
+                CharacterClass::Symbol,
+            ],
+            max_repeated: Some(2),
+            reject_personal_info: true,
+        };
+        let email = Email::from_str("hanako@email.com").unwrap();
+        let name = Name::from_str("Yamada").unwrap();
+
+        let test_cases = vec![
+            ("Str0ng!Passw0rd", vec![]),
+            ("Ünïcödé!Pässw0rd", vec![]),
+            ("Sh0rt!", vec![PasswordViolation::TooShort(10)]),
+            ("Much-T00-Long-Password", vec![PasswordViolation::TooLong(16)]),
+            ("lowercase0nly!", vec![PasswordViolation::MissingCharacterClass(CharacterClass::Uppercase)]),
+            (
+                "NoDigitsOrSymbols",
+                vec![
+                    PasswordViolation::TooLong(16),
+                    PasswordViolation::MissingCharacterClass(CharacterClass::Digit),
+                    PasswordViolation::MissingCharacterClass(CharacterClass::Symbol),
+                ],
+            ),
+            ("Paaass!w0rd", vec![PasswordViolation::TooManyRepeated(2)]),
+            ("HANAKO!2024x", vec![PasswordViolation::ContainsEmail]),
+            ("yamada!2024X", vec![PasswordViolation::ContainsName]),
+            ("Tab\tPassw0rd!", vec![PasswordViolation::ControlCharacter]),
+        ];
+        for (password, expected) in test_cases {
+            assert_eq!(expected, policy.violations(password, Some(&email), Some(&name)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn test_violations_ignore_short_personal_info() {
+        let policy = PasswordPolicy::default();
+        let email = Email::from_str("ab@email.com").unwrap();
+        let name = Name::from_str("Al").unwrap();
+
+        assert!(policy.violations("ab12alpine", Some(&email), Some(&name)).is_empty());
+
+        let policy = PasswordPolicy { reject_personal_info: false, ..PasswordPolicy::default() };
+        let email = Email::from_str("hanako@email.com").unwrap();
+        assert!(policy.violations("hanako2024", Some(&email), None).is_empty());
+    }
+
+    #[test]
+    fn test_character_class_from_str() {
+        let test_cases = vec![
+            ("lowercase", CharacterClass::Lowercase),
+            ("uppercase", CharacterClass::Uppercase),
+            ("letter", CharacterClass::Letter),
+            ("digit", CharacterClass::Digit),
+            ("symbol", CharacterClass::Symbol),
+        ];
+        for (value, expected) in test_cases {
+            let class = CharacterClass::from_str(value).unwrap();
+            assert_eq!(expected, class);
+            assert_eq!(value, class.to_string());
+        }
+        assert!(CharacterClass::from_str("emoji").is_err());
+    }
+
+    #[test]
+    fn test_violation_to_string() {
+        let test_cases = vec![
+            (PasswordViolation::TooShort(8), "too_short"),
+            (PasswordViolation::MissingCharacterClass(CharacterClass::Digit), "missing_digit"),
+            (PasswordViolation::Breached, "breached"),
+        ];
+        for (violation, expected) in test_cases {
+            assert_eq!(expected, violation.to_string());
         }
     }
 
@@ -99,14 +362,28 @@ mod tests {
         let password = "Abcdefg123";
         let result = Password::from_str(password);
         assert!(result.is_ok());
-        assert_eq!(password.to_owned(), result.unwrap().value)
+        assert_eq!(password, result.unwrap().expose_secret())
     }
 
     #[test]
     fn test_from_str_failed() {
-        let error_message = "Validate Error";
+        let error_message = "Password violates the policy: too_short, missing_digit";
         let result = Password::from_str("hoge");
         assert!(result.is_err());
         assert_eq!(error_message.to_owned(), result.unwrap_err().to_string())
     }
+
+    #[test]
+    fn test_redacted() {
+        let password = Password::from_str("Abcdefg123").unwrap();
+
+        assert!(!format!("{:?}", password).contains("Abcdefg123"));
+        assert_eq!("[REDACTED]", password.to_string());
+    }
+
+    #[test]
+    fn test_for_authentication() {
+        assert_eq!("a", Password::for_authentication("a").unwrap().expose_secret());
+        assert_eq!(PasswordError::Empty, Password::for_authentication("").unwrap_err());
+    }
 }
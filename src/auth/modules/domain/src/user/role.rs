@@ -0,0 +1,175 @@
+use super::user_id::UserId;
+
+/// ユーザーのロールです
+///
+/// ロールごとに許可する操作を`Permission`で定義します
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Role {
+    #[default]
+    User,
+    /// 問い合わせ対応のため、他のユーザーのデータを参照できます
+    Support,
+    /// 他のユーザーのデータの変更とロールの変更ができます
+    Admin,
+}
+
+/// ロールに許可する操作です
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// 自分のサブスクリプション、カテゴリー、支払い方法を参照します
+    ReadOwnResources,
+    /// 自分のサブスクリプション、カテゴリー、支払い方法を変更します
+    WriteOwnResources,
+    /// 他のユーザーのデータを参照します
+    ReadAnyResources,
+    /// 他のユーザーのデータを変更します
+    WriteAnyResources,
+    /// ユーザーのロールを変更します
+    ManageRoles,
+    /// すべてのユーザーの監査ログを参照します
+    ReadSecurityEvents,
+}
+
+/// リソースに対する操作の種類です
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoleError {
+    #[error("Invalid role value: {0}. Expected values are user, support or admin")]
+    InvalidValue(String),
+}
+
+const USER_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnResources,
+    Permission::WriteOwnResources,
+];
+const SUPPORT_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnResources,
+    Permission::WriteOwnResources,
+    Permission::ReadAnyResources,
+];
+const ADMIN_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnResources,
+    Permission::WriteOwnResources,
+    Permission::ReadAnyResources,
+    Permission::WriteAnyResources,
+    Permission::ManageRoles,
+    Permission::ReadSecurityEvents,
+];
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::User => USER_PERMISSIONS,
+            Role::Support => SUPPORT_PERMISSIONS,
+            Role::Admin => ADMIN_PERMISSIONS,
+        }
+    }
+
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    /// `actor`が`owner`のリソースに`access`できる場合に`true`を返します
+    ///
+    /// 自分のリソースは`*OwnResources`、他のユーザーのリソースは`*AnyResources`の権限を求めます
+    pub fn can_access(&self, actor: &UserId, owner: &UserId, access: Access) -> bool {
+        let permission = match (actor == owner, access) {
+            (true, Access::Read) => Permission::ReadOwnResources,
+            (true, Access::Write) => Permission::WriteOwnResources,
+            (false, Access::Read) => Permission::ReadAnyResources,
+            (false, Access::Write) => Permission::WriteAnyResources,
+        };
+        self.has(permission)
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = RoleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "support" => Ok(Role::Support),
+            "admin" => Ok(Role::Admin),
+            _ => Err(RoleError::InvalidValue(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::User => write!(f, "user"),
+            Role::Support => write!(f, "support"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_from_str_and_display() {
+        let test_cases = vec![
+            ("user", Role::User),
+            ("support", Role::Support),
+            ("admin", Role::Admin),
+        ];
+        for (value, expected) in test_cases {
+            let result = Role::from_str(value).unwrap();
+            assert_eq!(expected, result);
+            assert_eq!(value, result.to_string());
+        }
+
+        let test_cases = vec![
+            "", "Admin", "root",
+        ];
+        for value in test_cases {
+            assert!(Role::from_str(value).is_err());
+        }
+    }
+
+    #[test]
+    fn test_has() {
+        let test_cases = vec![
+            (Role::User, Permission::WriteOwnResources, true),
+            (Role::User, Permission::ReadAnyResources, false),
+            (Role::Support, Permission::ReadAnyResources, true),
+            (Role::Support, Permission::WriteAnyResources, false),
+            (Role::Support, Permission::ManageRoles, false),
+            (Role::Admin, Permission::WriteAnyResources, true),
+            (Role::Admin, Permission::ManageRoles, true),
+            (Role::Support, Permission::ReadSecurityEvents, false),
+            (Role::Admin, Permission::ReadSecurityEvents, true),
+        ];
+        for (role, permission, expected) in test_cases {
+            assert_eq!(expected, role.has(permission));
+        }
+    }
+
+    #[test]
+    fn test_can_access() {
+        let actor = UserId::new();
+        let other = UserId::new();
+
+        let test_cases = vec![
+            (Role::User, &actor, Access::Write, true),
+            (Role::User, &other, Access::Read, false),
+            (Role::Support, &other, Access::Read, true),
+            (Role::Support, &other, Access::Write, false),
+            (Role::Admin, &other, Access::Write, true),
+        ];
+        for (role, owner, access, expected) in test_cases {
+            assert_eq!(expected, role.can_access(&actor, owner, access));
+        }
+    }
+}
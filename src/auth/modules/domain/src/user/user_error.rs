@@ -2,7 +2,7 @@ use thiserror::Error;
 
 use crate::EntityIdError;
 
-use super::{email::EmailError, name::NameError, user_type::UserTypeError};
+use super::{email::EmailError, name::NameError, role::RoleError, user_type::UserTypeError};
 
 #[derive(Debug, Error)]
 pub enum UserError {
@@ -18,12 +18,27 @@ pub enum UserError {
     #[error("{0}")]
     UserTypeError(#[from] UserTypeError),
 
+    #[error("{0}")]
+    RoleError(#[from] RoleError),
+
+    #[error("User not found: {0}")]
+    NotFound(String),
+
     #[error("Failed to find by id user: {0}")]
     FindByIdError(String),
 
+    #[error("Failed to find by email user: {0}")]
+    FindByEmailError(String),
+
     #[error("Failed to create user: {0}")]
     CreateUserError(String),
 
     #[error("Failed to update user: {0}")]
     UpdateUserError(String),
+
+    #[error("Failed to delete user: {0}")]
+    DeleteUserError(String),
+
+    #[error("User is not a guest: {0}")]
+    NotGuest(String),
 }
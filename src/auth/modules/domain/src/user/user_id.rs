@@ -22,6 +22,12 @@ impl UserId {
     }
 }
 
+impl Default for UserId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EntityId for UserId {
     fn generate_id(p: &str, u: Option<uuid::Uuid>) -> String {
         match u {
@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+/// サブスクリプションやカテゴリーなど、ユーザーが登録したデータの1件です
+///
+/// テーブルごとに属性が異なるため、属性名と値の組として扱います
+pub type UserDataItem = BTreeMap<String, FieldValue>;
+
+/// 属性の値です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    Null,
+    Bool(bool),
+    /// 精度を落とさないよう、数値は文字列のまま保持します
+    Number(String),
+    String(String),
+    List(Vec<FieldValue>),
+    Map(BTreeMap<String, FieldValue>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UserDataError {
+    #[error("Failed to find user data: {0}")]
+    FindError(String),
+
+    #[error("Failed to delete user data: {0}")]
+    DeleteError(String),
+}
@@ -0,0 +1,47 @@
+use domain::{auth_user::AuthUserError, user::email::Email};
+
+/// 自前の認証で発行した確認コードをユーザーに届けます
+pub trait CodeSender: Send + Sync {
+    fn send_verification_code(
+        &self, email: &Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+
+    fn send_password_reset_code(
+        &self, email: &Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+
+    /// 変更前のメールアドレスに、`new_email`への変更が要求されたことを通知します
+    fn send_email_change_notice(
+        &self, email: &Email, new_email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>>;
+}
+
+/// 確認コードをログに出力します
+///
+/// メールを送信できないローカル環境とCI用です。本番環境では使用しないでください
+/// 確認コードはログから伏せられないため、`LOCAL_AUTH_LOG_CODES=true`を設定した場合のみ使用します
+#[derive(Debug, Default)]
+pub struct LogCodeSender;
+
+impl CodeSender for LogCodeSender {
+    fn send_verification_code(
+        &self, email: &Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        tracing::info!("verification code for {}: {}", email, code);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn send_password_reset_code(
+        &self, email: &Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        tracing::info!("password reset code for {}: {}", email, code);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn send_email_change_notice(
+        &self, email: &Email, new_email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        tracing::info!("email change requested for {}: {}", email, new_email);
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -0,0 +1,86 @@
+//! テスト用のCognitoスタブサーバーです
+//!
+//! `X-Amz-Target`ヘッダーのオペレーション名ごとに、あらかじめ登録したレスポンスを返します
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use aws_sdk_cognitoidentityprovider::config::{BehaviorVersion, Credentials, Region};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+
+const TARGET_PREFIX: &str = "AWSCognitoIdentityProviderService.";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+type Responses = HashMap<String, (u16, serde_json::Value)>;
+type Requests = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+#[derive(Clone)]
+struct StubState {
+    responses: Arc<Responses>,
+    requests: Requests,
+}
+
+pub(crate) struct CognitoStub {
+    addr: SocketAddr,
+    requests: Requests,
+}
+
+impl CognitoStub {
+    /// (オペレーション名, ステータスコード, レスポンスボディ)の組を登録してスタブを起動します
+    pub(crate) async fn start(responses: Vec<(&str, u16, serde_json::Value)>) -> Self {
+        let responses =
+            responses.into_iter().map(|(target, status, body)| (target.to_string(), (status, body))).collect();
+        let requests = Requests::default();
+        let state = StubState { responses: Arc::new(responses), requests: requests.clone() };
+
+        let app = axum::Router::new().fallback(handle).with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self { addr, requests }
+    }
+
+    pub(crate) fn client(&self) -> aws_sdk_cognitoidentityprovider::Client {
+        let config = aws_sdk_cognitoidentityprovider::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("ap-northeast-1"))
+            .credentials_provider(Credentials::new("access_key", "secret_key", None, None, "stub"))
+            .endpoint_url(format!("http://{}", self.addr))
+            .build();
+        aws_sdk_cognitoidentityprovider::Client::from_conf(config)
+    }
+
+    /// 受信したリクエストを(オペレーション名, リクエストボディ)の組で返します
+    pub(crate) fn requests(&self) -> Vec<(String, serde_json::Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle(State(state): State<StubState>, headers: HeaderMap, body: String) -> impl IntoResponse {
+    let target = headers
+        .get("x-amz-target")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim_start_matches(TARGET_PREFIX).to_string())
+        .unwrap_or_default();
+    let body = serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+    state.requests.lock().unwrap().push((target.clone(), body));
+
+    match state.responses.get(&target) {
+        Some((status, body)) => {
+            (StatusCode::from_u16(*status).unwrap(), [("content-type", CONTENT_TYPE)], body.to_string())
+        }
+        None => (
+            StatusCode::BAD_REQUEST,
+            [("content-type", CONTENT_TYPE)],
+            serde_json::json!({ "__type": "UnknownOperationException", "message": target }).to_string(),
+        ),
+    }
+}
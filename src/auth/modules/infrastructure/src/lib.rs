@@ -1,17 +1,38 @@
+pub mod code_sender;
 pub mod mapper;
 pub mod repository_impl;
 
+#[cfg(test)]
+mod cognito_stub;
+
 use base64::Engine;
 use hmac::Mac;
 
-pub fn client_secret_hash(email: &domain::user::email::Email, client_id: &str, client_secret: &str) -> String {
+/// Cognitoのアプリクライアントのシークレットから、`username`の`SECRET_HASH`を計算します
+///
+/// `username`にはメールアドレスのほか、Cognitoがユーザーに割り当てたユーザー名も使えます
+pub fn client_secret_hash(username: &str, client_id: &str, client_secret: &str) -> String {
     let mut mac =
         hmac::Hmac::<sha2::Sha256>::new_from_slice(client_secret.as_bytes()).expect("HMAC can take key of any size");
 
-    mac.update(email.value().as_bytes());
+    mac.update(username.as_bytes());
     mac.update(client_id.as_bytes());
 
     let result = mac.finalize();
 
     base64::engine::general_purpose::STANDARD.encode(result.into_bytes())
 }
+
+/// DynamoDBのエラーからメッセージを取り出します
+pub(crate) fn dynamodb_error_message<E, R>(e: &aws_sdk_dynamodb::error::SdkError<E, R>) -> String
+where
+    E: aws_sdk_dynamodb::error::ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    use aws_sdk_dynamodb::error::ProvideErrorMetadata;
+
+    match e.message() {
+        Some(s) => s.to_string(),
+        None => e.to_string(),
+    }
+}
@@ -3,7 +3,7 @@
 /// Tはドメインモデルの型、Eはエラー型を表します
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// impl Mapper<PaymentMethod, PaymentError> for PaymentRepositoryImpl {
 ///     fn to_domain_model(v: HashMap<String, AttributeValue>) -> Result<PaymentMethod, PaymentError> {
 ///         // 実装
@@ -28,6 +28,65 @@ pub fn as_string(val: Option<&aws_sdk_dynamodb::types::AttributeValue>, default:
     val.and_then(|v| v.as_s().ok()).map(ToString::to_string).unwrap_or_else(|| default.to_string())
 }
 
+/// DynamoDBのAttributeValueから真偽値を取得します
+///
+/// # Arguments
+/// * `val` - DynamoDBのAttributeValue
+/// * `default` - 値が取得できない場合のデフォルト値
+pub fn as_bool(val: Option<&aws_sdk_dynamodb::types::AttributeValue>, default: bool) -> bool {
+    val.and_then(|v| v.as_bool().ok()).copied().unwrap_or(default)
+}
+
+/// DynamoDBのAttributeValueから数値を取得します
+///
+/// # Arguments
+/// * `val` - DynamoDBのAttributeValue
+/// * `default` - 値が取得できない、または数値に変換できない場合のデフォルト値
+pub fn as_i64(val: Option<&aws_sdk_dynamodb::types::AttributeValue>, default: i64) -> i64 {
+    val.and_then(|v| v.as_n().ok()).and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// DynamoDBのAttributeValueから文字列セットを取得します
+///
+/// DynamoDBは空のセットを保存できないため、属性がない場合は空のVecを返します
+pub fn as_string_set(val: Option<&aws_sdk_dynamodb::types::AttributeValue>) -> Vec<String> {
+    val.and_then(|v| v.as_ss().ok()).cloned().unwrap_or_default()
+}
+
+/// DynamoDBのAttributeValueから数値のリストを取得します
+///
+/// 数値に変換できない要素は除きます。属性がない場合は空のVecを返します
+pub fn as_i64_list(val: Option<&aws_sdk_dynamodb::types::AttributeValue>) -> Vec<i64> {
+    val.and_then(|v| v.as_l().ok())
+        .map(|v| v.iter().filter_map(|v| v.as_n().ok()).filter_map(|v| v.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// DynamoDBのAttributeValueを、テーブルの定義によらない値に変換します
+///
+/// 数値は文字列のまま、セットはリストとして、バイナリはBase64の文字列として扱います
+pub fn as_field_value(val: &aws_sdk_dynamodb::types::AttributeValue) -> domain::user_data::FieldValue {
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use base64::Engine;
+    use domain::user_data::FieldValue;
+
+    let binary = |v: &aws_sdk_dynamodb::primitives::Blob| {
+        FieldValue::String(base64::engine::general_purpose::STANDARD.encode(v.as_ref()))
+    };
+    match val {
+        AttributeValue::S(v) => FieldValue::String(v.clone()),
+        AttributeValue::N(v) => FieldValue::Number(v.clone()),
+        AttributeValue::Bool(v) => FieldValue::Bool(*v),
+        AttributeValue::B(v) => binary(v),
+        AttributeValue::Ss(v) => FieldValue::List(v.iter().cloned().map(FieldValue::String).collect()),
+        AttributeValue::Ns(v) => FieldValue::List(v.iter().cloned().map(FieldValue::Number).collect()),
+        AttributeValue::Bs(v) => FieldValue::List(v.iter().map(binary).collect()),
+        AttributeValue::L(v) => FieldValue::List(v.iter().map(as_field_value).collect()),
+        AttributeValue::M(v) => FieldValue::Map(v.iter().map(|(k, v)| (k.clone(), as_field_value(v))).collect()),
+        _ => FieldValue::Null,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,4 +104,88 @@ mod tests {
             assert_eq!(result, expected);
         }
     }
+
+    #[test]
+    fn test_as_bool_success() {
+        let binding = &aws_sdk_dynamodb::types::AttributeValue::Bool(true);
+        let invalid = &aws_sdk_dynamodb::types::AttributeValue::S("true".to_string());
+        let test_cases = vec![
+            (Some(binding), true),
+            (Some(invalid), false),
+            (None, false),
+        ];
+        for (found, expected) in test_cases {
+            assert_eq!(as_bool(found, false), expected);
+        }
+    }
+
+    #[test]
+    fn test_as_i64_success() {
+        let binding = &aws_sdk_dynamodb::types::AttributeValue::N("1700000000".to_string());
+        let invalid = &aws_sdk_dynamodb::types::AttributeValue::N("abc".to_string());
+        let test_cases = vec![
+            (Some(binding), 1700000000),
+            (Some(invalid), 0),
+            (None, 0),
+        ];
+        for (found, expected) in test_cases {
+            assert_eq!(as_i64(found, 0), expected);
+        }
+    }
+
+    #[test]
+    fn test_as_string_set_success() {
+        let binding = &aws_sdk_dynamodb::types::AttributeValue::Ss(vec![
+            "a".to_string(),
+            "b".to_string(),
+        ]);
+        assert_eq!(
+            as_string_set(Some(binding)),
+            vec![
+                "a".to_string(),
+                "b".to_string()
+            ]
+        );
+        assert!(as_string_set(None).is_empty());
+    }
+
+    #[test]
+    fn test_as_i64_list_success() {
+        let binding = &aws_sdk_dynamodb::types::AttributeValue::L(vec![
+            aws_sdk_dynamodb::types::AttributeValue::N("1".to_string()),
+            aws_sdk_dynamodb::types::AttributeValue::N("abc".to_string()),
+            aws_sdk_dynamodb::types::AttributeValue::N("1".to_string()),
+        ]);
+        assert_eq!(as_i64_list(Some(binding)), vec![1, 1]);
+        assert!(as_i64_list(None).is_empty());
+    }
+
+    #[test]
+    fn test_as_field_value_success() {
+        use aws_sdk_dynamodb::types::AttributeValue;
+        use domain::user_data::FieldValue;
+
+        let test_cases = vec![
+            (AttributeValue::S("a".to_string()), FieldValue::String("a".to_string())),
+            (AttributeValue::N("1.50".to_string()), FieldValue::Number("1.50".to_string())),
+            (AttributeValue::Bool(true), FieldValue::Bool(true)),
+            (AttributeValue::Null(true), FieldValue::Null),
+            (AttributeValue::B(aws_sdk_dynamodb::primitives::Blob::new("ab")), FieldValue::String("YWI=".to_string())),
+            (
+                AttributeValue::Ss(vec!["a".to_string()]),
+                FieldValue::List(vec![FieldValue::String("a".to_string())]),
+            ),
+            (
+                AttributeValue::L(vec![AttributeValue::N("1".to_string())]),
+                FieldValue::List(vec![FieldValue::Number("1".to_string())]),
+            ),
+            (
+                AttributeValue::M([("a".to_string(), AttributeValue::Bool(false))].into()),
+                FieldValue::Map([("a".to_string(), FieldValue::Bool(false))].into()),
+            ),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(expected, as_field_value(&value), "{:?}", value);
+        }
+    }
 }
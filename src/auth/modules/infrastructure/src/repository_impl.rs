@@ -1 +1,29 @@
+pub mod account_deletion_repository_impl;
+pub mod cognito_auth_user_repository;
+pub mod credential_repository_impl;
+pub mod data_export_repository_impl;
+pub mod external_identity_repository_impl;
+pub mod in_memory_account_deletion_repository;
+pub mod in_memory_credential_repository;
+pub mod in_memory_data_export_repository;
+pub mod in_memory_external_identity_repository;
+pub mod in_memory_login_attempt_repository;
+pub mod in_memory_mfa_repository;
+pub mod in_memory_personal_access_token_repository;
+pub mod in_memory_refresh_token_repository;
+pub mod in_memory_revoked_token_repository;
+pub mod in_memory_security_event_repository;
+pub mod in_memory_user_data_repository;
+pub mod in_memory_user_repository;
+pub mod local_auth_user_repository;
+pub mod local_breached_password_repository;
+pub mod local_object_storage;
+pub mod login_attempt_repository_impl;
+pub mod mfa_repository_impl;
+pub mod personal_access_token_repository_impl;
+pub mod refresh_token_repository_impl;
+pub mod revoked_token_repository_impl;
+pub mod s3_object_storage;
+pub mod security_event_repository_impl;
+pub mod user_data_repository_impl;
 pub mod user_repository_impl;
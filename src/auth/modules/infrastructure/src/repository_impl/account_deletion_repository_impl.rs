@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use domain::{
+    account_deletion::{AccountDeletion, AccountDeletionError, DeletedItems, DeletionStatus},
+    user::{email::Email, user_id::UserId},
+};
+
+/// アカウントの削除の依頼と削除証明を保存します
+///
+/// パーティションキーは`deletion_id`です
+#[derive(Debug)]
+pub struct AccountDeletionRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl AccountDeletionRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        AccountDeletionRepositoryImpl { client, table_name }
+    }
+}
+
+const DELETION_ID: &str = "deletion_id";
+const USER_ID: &str = "user_id";
+const EMAIL: &str = "email";
+const STATUS: &str = "status";
+const REQUESTED_AT: &str = "requested_at";
+const SCHEDULED_AT: &str = "scheduled_at";
+/// 削除した対象と件数の`{ target, count }`のリストです
+const DELETED: &str = "deleted";
+const TARGET: &str = "target";
+const COUNT: &str = "count";
+const COMPLETED_AT: &str = "completed_at";
+const LEASED_UNTIL: &str = "leased_until";
+
+/// `user_id`をパーティションキーとするGSIです。すべての属性を射影します
+const USER_ID_INDEX: &str = "user_id-index";
+
+const USER_ID_ATTR: &str = "#user_id";
+const USER_ID_VALUE: &str = ":user_id";
+const STATUS_ATTR: &str = "#status";
+const SCHEDULED_AT_ATTR: &str = "#scheduled_at";
+const SCHEDULED_VALUE: &str = ":scheduled";
+const IN_PROGRESS_VALUE: &str = ":in_progress";
+const NOW_VALUE: &str = ":now";
+const LEASED_UNTIL_ATTR: &str = "#leased_until";
+const LEASED_UNTIL_VALUE: &str = ":leased_until";
+
+impl domain::repository::account_deletion_repository::AccountDeletionRepository for AccountDeletionRepositoryImpl {
+    fn find(
+        &self, deletion_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+
+        let deletion_id = deletion_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(DELETION_ID, AttributeValue::S(deletion_id.clone()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = AccountDeletionError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => AccountDeletionRepositoryImpl::map_to_domain_model(item),
+                None => Err(AccountDeletionError::NotFound(deletion_id)),
+            }
+        })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    > {
+        use crate::mapper::Mapper;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let mut deletions = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .index_name(USER_ID_INDEX)
+                    .key_condition_expression("#user_id = :user_id")
+                    .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                    .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.clone()))
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = AccountDeletionError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    deletions.push(AccountDeletionRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            deletions.sort_by_key(|v| v.requested_at());
+            Ok(deletions)
+        })
+    }
+
+    /// 依頼の件数は少ないため、GSIを作らずにテーブル全体をスキャンします
+    fn find_due(
+        &self, now: i64,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    > {
+        use crate::mapper::Mapper;
+
+        Box::pin(async move {
+            let mut deletions = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .scan()
+                    .table_name(&self.table_name)
+                    .filter_expression("#status IN (:scheduled, :in_progress) AND #scheduled_at <= :now")
+                    .expression_attribute_names(STATUS_ATTR, STATUS)
+                    .expression_attribute_names(SCHEDULED_AT_ATTR, SCHEDULED_AT)
+                    .expression_attribute_values(
+                        SCHEDULED_VALUE,
+                        AttributeValue::S(DeletionStatus::Scheduled.to_string()),
+                    )
+                    .expression_attribute_values(
+                        IN_PROGRESS_VALUE,
+                        AttributeValue::S(DeletionStatus::InProgress.to_string()),
+                    )
+                    .expression_attribute_values(NOW_VALUE, AttributeValue::N(now.to_string()))
+                    .consistent_read(true)
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = AccountDeletionError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    deletions.push(AccountDeletionRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            deletions.sort_by_key(|v| v.scheduled_at());
+            Ok(deletions)
+        })
+    }
+
+    fn claim(
+        &self, deletion_id: &str, now: i64, leased_until: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+
+        let deletion_id = deletion_id.to_string();
+
+        Box::pin(async move {
+            // 進捗を上書きしないよう、期限のみを更新して保存済みの最新の依頼を受け取ります
+            let result = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .key(DELETION_ID, AttributeValue::S(deletion_id.clone()))
+                .update_expression("SET #leased_until = :leased_until")
+                .condition_expression(
+                    "#status IN (:scheduled, :in_progress) AND #scheduled_at <= :now \
+                     AND (attribute_not_exists(#leased_until) OR #leased_until <= :now)",
+                )
+                .expression_attribute_names(STATUS_ATTR, STATUS)
+                .expression_attribute_names(SCHEDULED_AT_ATTR, SCHEDULED_AT)
+                .expression_attribute_names(LEASED_UNTIL_ATTR, LEASED_UNTIL)
+                .expression_attribute_values(SCHEDULED_VALUE, AttributeValue::S(DeletionStatus::Scheduled.to_string()))
+                .expression_attribute_values(
+                    IN_PROGRESS_VALUE,
+                    AttributeValue::S(DeletionStatus::InProgress.to_string()),
+                )
+                .expression_attribute_values(NOW_VALUE, AttributeValue::N(now.to_string()))
+                .expression_attribute_values(LEASED_UNTIL_VALUE, AttributeValue::N(leased_until.to_string()))
+                .return_values(aws_sdk_dynamodb::types::ReturnValue::AllNew)
+                .send()
+                .await;
+
+            match result {
+                Ok(v) => AccountDeletionRepositoryImpl::map_to_domain_model(v.attributes.unwrap_or_default()),
+                Err(e) => {
+                    let error = match e.as_service_error().map(|v| v.is_conditional_check_failed_exception()) {
+                        Some(true) => AccountDeletionError::NotClaimable(deletion_id),
+                        _ => AccountDeletionError::SaveError(crate::dynamodb_error_message(&e)),
+                    };
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn save(
+        &self, deletion: AccountDeletion,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AccountDeletionError>> + Send + '_>> {
+        Box::pin(async move {
+            let deleted = deletion
+                .deleted()
+                .iter()
+                .map(|v| {
+                    AttributeValue::M(HashMap::from([
+                        (TARGET.to_string(), AttributeValue::S(v.target().to_string())),
+                        (COUNT.to_string(), AttributeValue::N(v.count().to_string())),
+                    ]))
+                })
+                .collect();
+            let mut request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(DELETION_ID, AttributeValue::S(deletion.deletion_id().to_string()))
+                .item(USER_ID, AttributeValue::S(deletion.user_id().to_string()))
+                .item(STATUS, AttributeValue::S(deletion.status().to_string()))
+                .item(REQUESTED_AT, AttributeValue::N(deletion.requested_at().to_string()))
+                .item(SCHEDULED_AT, AttributeValue::N(deletion.scheduled_at().to_string()))
+                .item(DELETED, AttributeValue::L(deleted));
+            // 完了またはキャンセルした依頼は、メールアドレスを保存しません
+            if let Some(v) = deletion.email() {
+                request = request.item(EMAIL, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = deletion.completed_at() {
+                request = request.item(COMPLETED_AT, AttributeValue::N(v.to_string()));
+            }
+            if let Some(v) = deletion.leased_until() {
+                request = request.item(LEASED_UNTIL, AttributeValue::N(v.to_string()));
+            }
+
+            request.send().await.map_err(|e| {
+                let error = AccountDeletionError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<AccountDeletion, AccountDeletionError> for AccountDeletionRepositoryImpl {
+    fn map_to_domain_model(v: HashMap<String, AttributeValue>) -> Result<AccountDeletion, AccountDeletionError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id =
+            UserId::from_str(&user_id).map_err(|e| AccountDeletionError::FindError(format!("{}: {}", e, user_id)))?;
+        let email = match v.get(EMAIL) {
+            Some(_) => Some(
+                Email::from_str(&as_string(v.get(EMAIL), ""))
+                    .map_err(|e| AccountDeletionError::FindError(e.to_string()))?,
+            ),
+            None => None,
+        };
+        let status = DeletionStatus::from_str(&as_string(v.get(STATUS), ""))
+            .map_err(|e| AccountDeletionError::FindError(e.to_string()))?;
+        let deleted = v
+            .get(DELETED)
+            .and_then(|v| v.as_l().ok())
+            .map(|v| {
+                v.iter()
+                    .filter_map(|v| v.as_m().ok())
+                    .map(|v| DeletedItems::new(as_string(v.get(TARGET), ""), as_i64(v.get(COUNT), 0) as usize))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(AccountDeletion::new(
+            as_string(v.get(DELETION_ID), ""),
+            user_id,
+            email,
+            status,
+            as_i64(v.get(REQUESTED_AT), 0),
+            as_i64(v.get(SCHEDULED_AT), 0),
+            deleted,
+            v.get(COMPLETED_AT).map(|_| as_i64(v.get(COMPLETED_AT), 0)),
+            v.get(LEASED_UNTIL).map(|_| as_i64(v.get(LEASED_UNTIL), 0)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (DELETION_ID.to_string(), AttributeValue::S("123e4567-e89b-12d3-a456-426614174000".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (EMAIL.to_string(), AttributeValue::S("hoge@email.com".to_string())),
+            (STATUS.to_string(), AttributeValue::S("in_progress".to_string())),
+            (REQUESTED_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+            (SCHEDULED_AT.to_string(), AttributeValue::N("1702592000".to_string())),
+            (
+                DELETED.to_string(),
+                AttributeValue::L(vec![
+                    AttributeValue::M(HashMap::from([
+                        (TARGET.to_string(), AttributeValue::S("auth".to_string())),
+                        (COUNT.to_string(), AttributeValue::N("1".to_string())),
+                    ])),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = AccountDeletionRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("123e4567-e89b-12d3-a456-426614174000", result.deletion_id());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!(Some("hoge@email.com".to_string()), result.email().map(ToString::to_string));
+        assert_eq!(DeletionStatus::InProgress, result.status());
+        assert_eq!(1700000000, result.requested_at());
+        assert_eq!(1702592000, result.scheduled_at());
+        assert_eq!(vec![DeletedItems::new("auth".to_string(), 1)], result.deleted());
+        assert_eq!(None, result.completed_at());
+        assert_eq!(None, result.leased_until());
+
+        let mut item = item();
+        item.remove(EMAIL);
+        item.remove(DELETED);
+        item.insert(STATUS.to_string(), AttributeValue::S("completed".to_string()));
+        item.insert(COMPLETED_AT.to_string(), AttributeValue::N("1702600000".to_string()));
+        item.insert(LEASED_UNTIL.to_string(), AttributeValue::N("1702596000".to_string()));
+        let result = AccountDeletionRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert!(result.email().is_none());
+        assert!(result.deleted().is_empty());
+        assert_eq!(DeletionStatus::Completed, result.status());
+        assert_eq!(Some(1702600000), result.completed_at());
+        assert_eq!(Some(1702596000), result.leased_until());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid() {
+        let test_cases = vec![
+            (USER_ID.to_string(), AttributeValue::S("invalid".to_string())),
+            (EMAIL.to_string(), AttributeValue::S("invalid".to_string())),
+            (STATUS.to_string(), AttributeValue::S("deleted".to_string())),
+        ];
+
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.clone(), value);
+            let result = AccountDeletionRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(AccountDeletionError::FindError(_))), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,1460 @@
+use aws_sdk_cognitoidentityprovider::error::ProvideErrorMetadata;
+use domain::auth_user::AuthUserError;
+
+#[derive(Debug)]
+pub struct CognitoAuthUserRepository {
+    client: aws_sdk_cognitoidentityprovider::Client,
+    client_id: String,
+    client_secret: String,
+    /// `AdminDeleteUser`などの管理者APIで使います。未設定の場合はユーザーの削除とメールアドレスの変更ができません
+    user_pool_id: Option<String>,
+    /// パスワードを使わずにトークンを発行するカスタム認証フローで、チャレンジに回答する値です
+    ///
+    /// ユーザープールの`VerifyAuthChallengeResponse`トリガーと同じ値を設定します。未設定の場合はソーシャルログインができません
+    custom_auth_secret: Option<String>,
+}
+
+impl CognitoAuthUserRepository {
+    pub fn new(client: aws_sdk_cognitoidentityprovider::Client, client_id: String, client_secret: String) -> Self {
+        CognitoAuthUserRepository { client, client_id, client_secret, user_pool_id: None, custom_auth_secret: None }
+    }
+
+    /// 管理者APIで使うユーザープールのIDを設定した値を返します
+    pub fn with_user_pool_id(self, user_pool_id: Option<String>) -> Self {
+        Self { user_pool_id, ..self }
+    }
+
+    /// カスタム認証フローのチャレンジに回答する値を設定した値を返します
+    pub fn with_custom_auth_secret(self, custom_auth_secret: Option<String>) -> Self {
+        Self { custom_auth_secret, ..self }
+    }
+
+    fn custom_auth_secret(&self) -> Result<&str, AuthUserError> {
+        self.custom_auth_secret.as_deref().ok_or_else(|| {
+            AuthUserError::Unsupported("Custom auth secret is required to sign in without a password".to_string())
+        })
+    }
+
+    /// 管理者APIの`AdminCreateUser`で、確認済みのメールアドレスを持つユーザーを作成します
+    ///
+    /// Cognitoからの招待メールは送信せず、パスワードでログインできないよう推測できないパスワードを設定します
+    async fn create_user(
+        &self, email: &domain::user::email::Email,
+    ) -> Result<domain::user::user_id::UserId, AuthUserError> {
+        use aws_sdk_cognitoidentityprovider::types::MessageActionType;
+
+        let result = self
+            .client
+            .admin_create_user()
+            .user_pool_id(self.user_pool_id()?)
+            .username(email.value())
+            .message_action(MessageActionType::Suppress)
+            .user_attributes(attribute(EMAIL_ATTRIBUTE, email.value())?)
+            .user_attributes(attribute(EMAIL_VERIFIED_ATTRIBUTE, "true")?)
+            .send()
+            .await
+            .map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+        let user_id = user_id(result.user().map(|v| v.attributes()).unwrap_or_default())?;
+
+        self.set_unusable_password(email).await?;
+        Ok(user_id)
+    }
+
+    /// 管理者APIの`AdminSetUserPassword`で、誰も知らないパスワードを恒久的なパスワードとして設定します
+    ///
+    /// パスワードを設定する場合はパスワードの再設定を使います
+    async fn set_unusable_password(&self, email: &domain::user::email::Email) -> Result<(), AuthUserError> {
+        self.set_password(email, &unusable_password()).await
+    }
+
+    /// 管理者APIの`AdminSetUserPassword`で、`password`を恒久的なパスワードとして設定します
+    async fn set_password(&self, email: &domain::user::email::Email, password: &str) -> Result<(), AuthUserError> {
+        self.client
+            .admin_set_user_password()
+            .user_pool_id(self.user_pool_id()?)
+            .username(email.value())
+            .password(password)
+            .permanent(true)
+            .send()
+            .await
+            .map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+        Ok(())
+    }
+
+    /// カスタム認証フローで、パスワードを使わずに`username`のユーザーのトークンを発行します
+    ///
+    /// `username`にはメールアドレスか、Cognitoが割り当てたユーザー名を渡します
+    /// `AdminInitiateAuth`で受け取ったチャレンジに、`AdminRespondToAuthChallenge`で`custom_auth_secret`を回答します
+    /// 管理者APIはサーバーの認証情報でのみ呼び出せるため、クライアントがこのフローを使うことはできません
+    async fn issue_without_password(&self, username: &str) -> Result<domain::token::Token, AuthUserError> {
+        use aws_sdk_cognitoidentityprovider::types::{AuthFlowType, ChallengeNameType};
+
+        let user_pool_id = self.user_pool_id()?;
+        let custom_auth_secret = self.custom_auth_secret()?;
+        let challenge = self
+            .client
+            .admin_initiate_auth()
+            .user_pool_id(user_pool_id)
+            .client_id(&self.client_id)
+            .auth_flow(AuthFlowType::CustomAuth)
+            .auth_parameters(USERNAME, username)
+            .auth_parameters(SECRET_HASH, crate::client_secret_hash(username, &self.client_id, &self.client_secret))
+            .send()
+            .await
+            .map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+        let session = match (challenge.challenge_name(), challenge.session()) {
+            (Some(ChallengeNameType::CustomChallenge), Some(session)) => session,
+            _ => {
+                tracing::error!("custom challenge is missing. challenge: {:?}", challenge.challenge_name());
+                return Err(AuthUserError::TokenMissing);
+            }
+        };
+
+        let result = self
+            .client
+            .admin_respond_to_auth_challenge()
+            .user_pool_id(user_pool_id)
+            .client_id(&self.client_id)
+            .challenge_name(ChallengeNameType::CustomChallenge)
+            .session(session)
+            .challenge_responses(USERNAME, username)
+            .challenge_responses(ANSWER, custom_auth_secret)
+            .challenge_responses(SECRET_HASH, crate::client_secret_hash(username, &self.client_id, &self.client_secret))
+            .send()
+            .await
+            .map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+        let authentication_result = result.authentication_result().ok_or_else(|| {
+            tracing::error!("authentication result is missing. challenge: {:?}", result.challenge_name());
+            AuthUserError::TokenMissing
+        })?;
+
+        token(authentication_result, None)
+    }
+
+    fn user_pool_id(&self) -> Result<&str, AuthUserError> {
+        self.user_pool_id.as_deref().ok_or_else(|| {
+            let error =
+                AuthUserError::InternalServerError("User pool id is required for Cognito admin APIs".to_string());
+            tracing::error!("{:?}", error);
+            error
+        })
+    }
+
+    /// 管理者APIで`email`のユーザーのメールアドレスを`new_email`に変更します
+    ///
+    /// `verified`が`false`の場合、Cognitoは`new_email`に確認コードを送信し、確認されるまで変更前のメールアドレスを使います
+    async fn update_email(
+        &self, email: &domain::user::email::Email, new_email: &domain::user::email::Email, verified: bool,
+    ) -> Result<(), AuthUserError> {
+        let mut attributes = vec![attribute(EMAIL_ATTRIBUTE, new_email.value())?];
+        if verified {
+            attributes.push(attribute(EMAIL_VERIFIED_ATTRIBUTE, "true")?);
+        }
+
+        self.client
+            .admin_update_user_attributes()
+            .user_pool_id(self.user_pool_id()?)
+            .username(email.value())
+            .set_user_attributes(Some(attributes))
+            .send()
+            .await
+            .map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+        Ok(())
+    }
+
+    fn secret_hash(&self, email: &domain::user::email::Email) -> String {
+        crate::client_secret_hash(email.value(), &self.client_id, &self.client_secret)
+    }
+}
+
+const USERNAME: &str = "USERNAME";
+const PASSWORD: &str = "PASSWORD";
+const REFRESH_TOKEN: &str = "REFRESH_TOKEN";
+const SECRET_HASH: &str = "SECRET_HASH";
+const ANSWER: &str = "ANSWER";
+const EMAIL_ATTRIBUTE: &str = "email";
+const EMAIL_VERIFIED_ATTRIBUTE: &str = "email_verified";
+const SUB_ATTRIBUTE: &str = "sub";
+
+const USERNAME_EXISTS: &str = "UsernameExistsException";
+const INVALID_PASSWORD: &str = "InvalidPasswordException";
+const NOT_AUTHORIZED: &str = "NotAuthorizedException";
+const USER_NOT_FOUND: &str = "UserNotFoundException";
+const USER_NOT_CONFIRMED: &str = "UserNotConfirmedException";
+const CODE_MISMATCH: &str = "CodeMismatchException";
+const EXPIRED_CODE: &str = "ExpiredCodeException";
+const ALIAS_EXISTS: &str = "AliasExistsException";
+
+/// Cognitoのエラーを`AuthUserError`に変換します
+///
+/// エラーコードで判別できないものは`InternalServerError`として扱います
+fn map_cognito_error<E>(e: E) -> AuthUserError
+where
+    E: ProvideErrorMetadata + std::fmt::Display,
+{
+    let msg = match e.message() {
+        Some(s) => s.to_string(),
+        None => e.to_string(),
+    };
+
+    match e.code() {
+        Some(USERNAME_EXISTS | ALIAS_EXISTS) => AuthUserError::UserAlreadyExists,
+        Some(INVALID_PASSWORD) => AuthUserError::InvalidPassword,
+        Some(NOT_AUTHORIZED | USER_NOT_FOUND | USER_NOT_CONFIRMED | CODE_MISMATCH | EXPIRED_CODE) => {
+            AuthUserError::AuthenticationFailed(msg)
+        }
+        _ => AuthUserError::InternalServerError(msg),
+    }
+}
+
+fn attribute(name: &str, value: &str) -> Result<aws_sdk_cognitoidentityprovider::types::AttributeType, AuthUserError> {
+    aws_sdk_cognitoidentityprovider::types::AttributeType::builder()
+        .name(name)
+        .value(value)
+        .build()
+        .map_err(|e| AuthUserError::InternalServerError(e.to_string()))
+}
+
+/// サインアップと同様に、Cognitoの`sub`から`UserId`を作成します
+fn user_id(
+    attributes: &[aws_sdk_cognitoidentityprovider::types::AttributeType],
+) -> Result<domain::user::user_id::UserId, AuthUserError> {
+    let sub = attributes.iter().find(|v| v.name() == SUB_ATTRIBUTE).and_then(|v| v.value()).unwrap_or_default();
+    let sub = uuid::Uuid::parse_str(sub).map_err(|e| AuthUserError::InternalServerError(format!("{}: {}", e, sub)))?;
+    Ok(domain::user::user_id::UserId::from(sub))
+}
+
+/// メールアドレスをユーザー名の属性とするユーザープールで、Cognitoが`sub`と同じ値で割り当てたユーザー名を返します
+///
+/// メールアドレスの変更が確認されるまでは、変更後のメールアドレスでユーザーを特定できないため使います
+fn username(user_id: &domain::user::user_id::UserId) -> String {
+    let user_id = user_id.to_string();
+    match user_id.split_once('_') {
+        Some((_, sub)) => sub.to_string(),
+        None => user_id,
+    }
+}
+
+/// パスワードでログインできないよう、誰も知らないパスワードを作成します
+///
+/// ユーザープールのパスワードポリシーを満たすよう、英大文字、英小文字、数字、記号を含めます
+fn unusable_password() -> String {
+    format!("{}Aa1!", uuid::Uuid::new_v4())
+}
+
+/// Cognitoの認証結果から`Token`を作成します
+///
+/// 認証結果にリフレッシュトークンがない場合は`refresh_token`を使います
+fn token(
+    result: &aws_sdk_cognitoidentityprovider::types::AuthenticationResultType, refresh_token: Option<&str>,
+) -> Result<domain::token::Token, AuthUserError> {
+    use domain::clock::Clock;
+
+    match (result.access_token(), result.refresh_token().or(refresh_token)) {
+        (Some(access_token), Some(refresh)) => Ok(domain::token::Token::new(
+            access_token.to_string().into(),
+            refresh.to_string().into(),
+            domain::clock::SystemClock.now(),
+            result.expires_in().max(0) as u64,
+        )
+        .with_id_token(result.id_token().map(|v| v.to_string().into()))),
+        _ => {
+            let error = AuthUserError::TokenMissing;
+            tracing::error!("{:?}", error);
+            Err(error)
+        }
+    }
+}
+
+impl domain::repository::auth_user_repository::AuthUserRepository for CognitoAuthUserRepository {
+    fn authenticate(
+        &self, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<domain::token::Token, AuthUserError>> + Send + '_>>
+    {
+        use aws_sdk_cognitoidentityprovider::types::AuthFlowType;
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .initiate_auth()
+                .client_id(&self.client_id)
+                .auth_flow(AuthFlowType::UserPasswordAuth)
+                .auth_parameters(USERNAME, auth.email().value())
+                .auth_parameters(PASSWORD, auth.password().expose_secret())
+                .auth_parameters(SECRET_HASH, self.secret_hash(auth.email()))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            let authentication_result = result.authentication_result().ok_or_else(|| {
+                tracing::error!("authentication result is missing. challenge: {:?}", result.challenge_name());
+                AuthUserError::TokenMissing
+            })?;
+
+            token(authentication_result, None)
+        })
+    }
+
+    fn sign_up(
+        &self, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<domain::user::user_id::UserId, AuthUserError>> + Send + '_>,
+    > {
+        use aws_sdk_cognitoidentityprovider::types::AttributeType;
+
+        Box::pin(async move {
+            let email = AttributeType::builder()
+                .name(EMAIL_ATTRIBUTE)
+                .value(auth.email().value())
+                .build()
+                .map_err(|e| AuthUserError::InternalServerError(e.to_string()))?;
+
+            let result = self
+                .client
+                .sign_up()
+                .client_id(&self.client_id)
+                .secret_hash(self.secret_hash(auth.email()))
+                .username(auth.email().value())
+                .password(auth.password().expose_secret())
+                .user_attributes(email)
+                .send()
+                .await;
+
+            match result {
+                Ok(v) => {
+                    tracing::info!("sign up user_sub: {}, confirmed: {}", v.user_sub(), v.user_confirmed());
+                    let user_sub = uuid::Uuid::parse_str(v.user_sub())
+                        .map_err(|e| AuthUserError::InternalServerError(e.to_string()))?;
+                    Ok(domain::user::user_id::UserId::from(user_sub))
+                }
+                Err(e) => {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn verify_code(
+        &self, email: domain::user::email::Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .confirm_sign_up()
+                .client_id(&self.client_id)
+                .secret_hash(self.secret_hash(&email))
+                .username(email.value())
+                .confirmation_code(code)
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn refresh(
+        &self, email: domain::user::email::Email, refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<domain::token::Token, AuthUserError>> + Send + '_>>
+    {
+        use aws_sdk_cognitoidentityprovider::types::AuthFlowType;
+
+        let refresh_token = refresh_token.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .initiate_auth()
+                .client_id(&self.client_id)
+                .auth_flow(AuthFlowType::RefreshTokenAuth)
+                .auth_parameters(REFRESH_TOKEN, &refresh_token)
+                .auth_parameters(SECRET_HASH, self.secret_hash(&email))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            let authentication_result = result.authentication_result().ok_or_else(|| {
+                let error = AuthUserError::TokenMissing;
+                tracing::error!("{:?}", error);
+                error
+            })?;
+
+            // Cognitoはリフレッシュトークンのローテーションが無効な場合、新しいリフレッシュトークンを返しません
+            token(authentication_result, Some(&refresh_token))
+        })
+    }
+
+    fn forgot_password(
+        &self, email: domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            self.client
+                .forgot_password()
+                .client_id(&self.client_id)
+                .secret_hash(self.secret_hash(&email))
+                .username(email.value())
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn confirm_forgot_password(
+        &self, email: domain::user::email::Email, code: &str, new_password: domain::user::password::Password,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            self.client
+                .confirm_forgot_password()
+                .client_id(&self.client_id)
+                .secret_hash(self.secret_hash(&email))
+                .username(email.value())
+                .confirmation_code(code)
+                .password(new_password.expose_secret())
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn sign_out(
+        &self, access_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let access_token = access_token.to_string();
+
+        Box::pin(async move {
+            self.client.global_sign_out().access_token(access_token).send().await.map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    /// 管理者APIの`AdminGetUser`でユーザーを探し、いない場合は`AdminCreateUser`で作成します
+    ///
+    /// トークンはカスタム認証フローで発行するため、ユーザープールにカスタム認証のトリガーが必要です
+    fn federated_sign_in(
+        &self, email: domain::user::email::Email,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<domain::auth_user::FederatedSignIn, AuthUserError>> + Send + '_>,
+    > {
+        use aws_sdk_cognitoidentityprovider::types::UserStatusType;
+
+        Box::pin(async move {
+            // 設定がない場合は、ユーザーを作成する前に失敗させます
+            let user_pool_id = self.user_pool_id()?;
+            self.custom_auth_secret()?;
+
+            let result = self.client.admin_get_user().user_pool_id(user_pool_id).username(email.value()).send().await;
+            let (user_id, created) = match result {
+                Ok(user) => {
+                    let user_id = user_id(user.user_attributes())?;
+                    // 確認前のパスワードは第三者が登録した可能性があるため、推測できないパスワードに置き換えます
+                    if user.user_status() == Some(&UserStatusType::Unconfirmed) {
+                        self.client
+                            .admin_confirm_sign_up()
+                            .user_pool_id(user_pool_id)
+                            .username(email.value())
+                            .send()
+                            .await
+                            .map_err(|e| {
+                                let error = map_cognito_error(e);
+                                tracing::error!("{:?}", error);
+                                error
+                            })?;
+                        self.set_unusable_password(&email).await?;
+                        self.update_email(&email, &email, true).await?;
+                    }
+                    (user_id, false)
+                }
+                Err(e) if e.code() == Some(USER_NOT_FOUND) => (self.create_user(&email).await?, true),
+                Err(e) => {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    return Err(error);
+                }
+            };
+
+            let token = self.issue_without_password(email.value()).await?;
+            Ok(domain::auth_user::FederatedSignIn::new(user_id, token, created))
+        })
+    }
+
+    /// 管理者APIの`AdminCreateUser`でパスワードを持たないユーザーを作成し、カスタム認証フローでトークンを発行します
+    ///
+    /// Cognitoは`sub`から`UserId`を割り当てるため、`user_id`は使いません
+    /// 有効期限はユーザーテーブルで管理するため、期限を過ぎたゲストユーザーもCognitoには残ります
+    fn sign_up_guest(
+        &self, _user_id: domain::user::user_id::UserId, email: domain::user::email::Email, _expires_at: i64,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<domain::auth_user::GuestSignUp, AuthUserError>> + Send + '_>,
+    > {
+        Box::pin(async move {
+            // 設定がない場合は、ユーザーを作成する前に失敗させます
+            self.custom_auth_secret()?;
+
+            let user_id = self.create_user(&email).await?;
+            let token = self.issue_without_password(email.value()).await?;
+            Ok(domain::auth_user::GuestSignUp::new(user_id, token))
+        })
+    }
+
+    /// 管理者APIでパスワードを設定してから、メールアドレスの変更と同様に`email`の変更を要求します
+    ///
+    /// Cognitoが確認コードを送信し、確認されるまではゲストユーザーのメールアドレスを使います
+    /// ゲストユーザーとして発行したリフレッシュトークンは、`AdminUserGlobalSignOut`で無効化します
+    fn upgrade_guest(
+        &self, guest_email: domain::user::email::Email, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            self.set_password(&guest_email, auth.password().expose_secret()).await?;
+            self.update_email(&guest_email, auth.email(), false).await?;
+            self.client
+                .admin_user_global_sign_out()
+                .user_pool_id(self.user_pool_id()?)
+                .username(guest_email.value())
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    /// カスタム認証フローで`user_id`のユーザーのトークンを発行し、`VerifyUserAttribute`で確認コードを確認します
+    fn verify_guest_upgrade(
+        &self, user_id: domain::user::user_id::UserId, _email: domain::user::email::Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let token = self.issue_without_password(&username(&user_id)).await?;
+            self.client
+                .verify_user_attribute()
+                .access_token(token.access_token().expose_secret())
+                .attribute_name(EMAIL_ATTRIBUTE)
+                .code(code)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    /// 確認を待っている変更をゲストユーザーの確認済みのメールアドレスで上書きし、パスワードを使えなくします
+    fn revert_guest_upgrade(
+        &self, guest_email: domain::user::email::Email, _email: domain::user::email::Email, _expires_at: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            self.update_email(&guest_email, &guest_email, true).await?;
+            self.set_unusable_password(&guest_email).await
+        })
+    }
+
+    /// 管理者APIの`AdminUpdateUserAttributes`で変更を要求し、Cognitoが`new_email`に確認コードを送信します
+    ///
+    /// ユーザープールはメールアドレスをユーザー名の属性とし、確認されるまで変更前のメールアドレスを使う設定が必要です
+    /// Cognitoは変更前のメールアドレスには通知しません
+    fn request_email_change(
+        &self, email: domain::user::email::Email, new_email: domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move { self.update_email(&email, &new_email, false).await })
+    }
+
+    /// `VerifyUserAttribute`で確認コードを確認し、`GetUser`で変更後のメールアドレスを取得します
+    fn confirm_email_change(
+        &self, _email: domain::user::email::Email, code: &str, access_token: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<domain::user::email::Email, AuthUserError>> + Send + '_>,
+    > {
+        use std::str::FromStr;
+
+        let code = code.to_string();
+        let access_token = access_token.to_string();
+
+        Box::pin(async move {
+            self.client
+                .verify_user_attribute()
+                .access_token(&access_token)
+                .attribute_name(EMAIL_ATTRIBUTE)
+                .code(code)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            let user = self.client.get_user().access_token(&access_token).send().await.map_err(|e| {
+                let error = map_cognito_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+
+            match user.user_attributes().iter().find(|v| v.name() == EMAIL_ATTRIBUTE).and_then(|v| v.value()) {
+                Some(v) => Ok(domain::user::email::Email::from_str(v)?),
+                None => {
+                    let error = AuthUserError::InternalServerError("Email attribute is missing".to_string());
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    /// 確認済みのメールアドレスに戻すため、確認コードは送信しません
+    fn revert_email_change(
+        &self, new_email: domain::user::email::Email, email: domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move { self.update_email(&new_email, &email, true).await })
+    }
+
+    /// 管理者APIの`AdminDeleteUser`で削除します。削除済みのユーザーは成功として扱います
+    fn delete_user(
+        &self, email: domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let result =
+                self.client.admin_delete_user().user_pool_id(self.user_pool_id()?).username(email.value()).send().await;
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) if e.code() == Some(USER_NOT_FOUND) => Ok(()),
+                Err(e) => {
+                    let error = map_cognito_error(e);
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::{auth_user::AuthUser, repository::auth_user_repository::AuthUserRepository, user::email::Email};
+    use serde_json::json;
+
+    use crate::cognito_stub::CognitoStub;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "client_id";
+    const CLIENT_SECRET: &str = "client_secret";
+
+    fn repository(stub: &CognitoStub) -> CognitoAuthUserRepository {
+        CognitoAuthUserRepository::new(stub.client(), CLIENT_ID.to_string(), CLIENT_SECRET.to_string())
+    }
+
+    fn auth_user() -> AuthUser {
+        AuthUser::build("hoge@email.com", "Password123").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_success() {
+        let stub = CognitoStub::start(vec![(
+            "InitiateAuth",
+            200,
+            json!({
+                "AuthenticationResult": {
+                    "AccessToken": "access_token",
+                    "RefreshToken": "refresh_token",
+                    "IdToken": "id_token",
+                    "ExpiresIn": 3600,
+                    "TokenType": "Bearer"
+                }
+            }),
+        )])
+        .await;
+
+        let result = repository(&stub).authenticate(auth_user()).await;
+        assert!(result.is_ok());
+
+        let result = result.unwrap();
+        assert_eq!("access_token", result.access_token().expose_secret());
+        assert_eq!("id_token", result.id_token().unwrap().expose_secret());
+        assert_eq!("refresh_token", result.refresh_token().expose_secret());
+        assert_eq!(3600, result.expires_in());
+
+        let requests = stub.requests();
+        assert_eq!(1, requests.len());
+        let (target, body) = &requests[0];
+        assert_eq!("InitiateAuth", target);
+        assert_eq!("USER_PASSWORD_AUTH", body["AuthFlow"]);
+        assert_eq!(CLIENT_ID, body["ClientId"]);
+        assert_eq!("hoge@email.com", body["AuthParameters"][USERNAME]);
+        assert_eq!("Password123", body["AuthParameters"][PASSWORD]);
+        assert_eq!(
+            crate::client_secret_hash("hoge@email.com", CLIENT_ID, CLIENT_SECRET),
+            body["AuthParameters"][SECRET_HASH]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_token_missing() {
+        let test_cases = vec![
+            json!({ "ChallengeName": "NEW_PASSWORD_REQUIRED", "Session": "session" }),
+            json!({ "AuthenticationResult": { "AccessToken": "access_token" } }),
+        ];
+
+        for body in test_cases {
+            let stub = CognitoStub::start(vec![("InitiateAuth", 200, body)]).await;
+            let result = repository(&stub).authenticate(auth_user()).await;
+            assert!(matches!(result, Err(AuthUserError::TokenMissing)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_failed() {
+        let stub = CognitoStub::start(vec![(
+            "InitiateAuth",
+            400,
+            json!({ "__type": NOT_AUTHORIZED, "message": "Incorrect username or password." }),
+        )])
+        .await;
+
+        let result = repository(&stub).authenticate(auth_user()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_success() {
+        let stub = CognitoStub::start(vec![(
+            "SignUp",
+            200,
+            json!({ "UserConfirmed": false, "UserSub": "550e8400-e29b-41d4-a716-446655440000" }),
+        )])
+        .await;
+
+        let result = repository(&stub).sign_up(auth_user()).await;
+        assert!(result.is_ok());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.unwrap().to_string());
+
+        let requests = stub.requests();
+        let (target, body) = &requests[0];
+        assert_eq!("SignUp", target);
+        assert_eq!("hoge@email.com", body["Username"]);
+        assert_eq!("Password123", body["Password"]);
+        assert_eq!(EMAIL_ATTRIBUTE, body["UserAttributes"][0]["Name"]);
+        assert_eq!("hoge@email.com", body["UserAttributes"][0]["Value"]);
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_failed() {
+        let test_cases = vec![
+            (USERNAME_EXISTS, AuthUserError::UserAlreadyExists),
+            (INVALID_PASSWORD, AuthUserError::InvalidPassword),
+            ("InternalErrorException", AuthUserError::InternalServerError("".to_string())),
+        ];
+
+        for (code, expected) in test_cases {
+            let stub = CognitoStub::start(vec![("SignUp", 400, json!({ "__type": code, "message": "error" }))]).await;
+            let result = repository(&stub).sign_up(auth_user()).await;
+            assert!(result.is_err());
+            assert_eq!(std::mem::discriminant(&expected), std::mem::discriminant(&result.unwrap_err()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_success() {
+        let stub = CognitoStub::start(vec![("ConfirmSignUp", 200, json!({}))]).await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).verify_code(email, "123456").await;
+        assert!(result.is_ok());
+
+        let requests = stub.requests();
+        let (target, body) = &requests[0];
+        assert_eq!("ConfirmSignUp", target);
+        assert_eq!("hoge@email.com", body["Username"]);
+        assert_eq!("123456", body["ConfirmationCode"]);
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_invalid_user_sub() {
+        let stub =
+            CognitoStub::start(vec![("SignUp", 200, json!({ "UserConfirmed": false, "UserSub": "invalid" }))]).await;
+
+        let result = repository(&stub).sign_up(auth_user()).await;
+        assert!(matches!(result, Err(AuthUserError::InternalServerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_failed() {
+        let stub = CognitoStub::start(vec![(
+            "ConfirmSignUp",
+            400,
+            json!({ "__type": CODE_MISMATCH, "message": "Invalid verification code provided." }),
+        )])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).verify_code(email, "000000").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_success() {
+        let test_cases = vec![
+            (json!({ "AuthenticationResult": { "AccessToken": "new_access_token" } }), "refresh_token"),
+            (
+                json!({ "AuthenticationResult": { "AccessToken": "new_access_token", "RefreshToken": "rotated" } }),
+                "rotated",
+            ),
+        ];
+
+        for (body, expected) in test_cases {
+            let stub = CognitoStub::start(vec![("InitiateAuth", 200, body)]).await;
+            let email = Email::from_str("hoge@email.com").unwrap();
+
+            let result = repository(&stub).refresh(email, "refresh_token").await;
+            assert!(result.is_ok());
+
+            let result = result.unwrap();
+            assert_eq!("new_access_token", result.access_token().expose_secret());
+            assert_eq!(expected, result.refresh_token().expose_secret());
+
+            let requests = stub.requests();
+            let (_, body) = &requests[0];
+            assert_eq!("REFRESH_TOKEN_AUTH", body["AuthFlow"]);
+            assert_eq!("refresh_token", body["AuthParameters"][REFRESH_TOKEN]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_failed() {
+        let stub = CognitoStub::start(vec![(
+            "InitiateAuth",
+            400,
+            json!({ "__type": NOT_AUTHORIZED, "message": "Invalid Refresh Token" }),
+        )])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).refresh(email, "refresh_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_forgot_password_success() {
+        let stub = CognitoStub::start(vec![(
+            "ForgotPassword",
+            200,
+            json!({ "CodeDeliveryDetails": { "DeliveryMedium": "EMAIL", "Destination": "h***@e***" } }),
+        )])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).forgot_password(email.clone()).await;
+        assert!(result.is_ok());
+
+        let requests = stub.requests();
+        let (target, body) = &requests[0];
+        assert_eq!("ForgotPassword", target);
+        assert_eq!(CLIENT_ID, body["ClientId"]);
+        assert_eq!("hoge@email.com", body["Username"]);
+        assert_eq!(crate::client_secret_hash(email.value(), CLIENT_ID, CLIENT_SECRET), body["SecretHash"]);
+    }
+
+    #[tokio::test]
+    async fn test_forgot_password_failed() {
+        let stub = CognitoStub::start(vec![(
+            "ForgotPassword",
+            400,
+            json!({ "__type": USER_NOT_FOUND, "message": "Username/client id combination not found." }),
+        )])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).forgot_password(email).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_forgot_password_success() {
+        let stub = CognitoStub::start(vec![("ConfirmForgotPassword", 200, json!({}))]).await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let password = domain::user::password::Password::from_str("NewPassword456").unwrap();
+
+        let result = repository(&stub).confirm_forgot_password(email, "123456", password).await;
+        assert!(result.is_ok());
+
+        let requests = stub.requests();
+        let (target, body) = &requests[0];
+        assert_eq!("ConfirmForgotPassword", target);
+        assert_eq!("hoge@email.com", body["Username"]);
+        assert_eq!("123456", body["ConfirmationCode"]);
+        assert_eq!("NewPassword456", body["Password"]);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_forgot_password_failed() {
+        let test_cases = vec![
+            (CODE_MISMATCH, AuthUserError::AuthenticationFailed("".to_string())),
+            (EXPIRED_CODE, AuthUserError::AuthenticationFailed("".to_string())),
+            (USER_NOT_FOUND, AuthUserError::AuthenticationFailed("".to_string())),
+            (INVALID_PASSWORD, AuthUserError::InvalidPassword),
+        ];
+
+        for (code, expected) in test_cases {
+            let stub =
+                CognitoStub::start(vec![("ConfirmForgotPassword", 400, json!({ "__type": code, "message": "error" }))])
+                    .await;
+            let email = Email::from_str("hoge@email.com").unwrap();
+            let password = domain::user::password::Password::from_str("NewPassword456").unwrap();
+
+            let result = repository(&stub).confirm_forgot_password(email, "123456", password).await;
+            assert!(result.is_err());
+            assert_eq!(std::mem::discriminant(&expected), std::mem::discriminant(&result.unwrap_err()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_out_success() {
+        let stub = CognitoStub::start(vec![("GlobalSignOut", 200, json!({}))]).await;
+
+        let result = repository(&stub).sign_out("access_token").await;
+        assert!(result.is_ok());
+
+        let requests = stub.requests();
+        let (_, body) = &requests[0];
+        assert_eq!("access_token", body["AccessToken"]);
+    }
+
+    const SUB: &str = "550e8400-e29b-41d4-a716-446655440000";
+
+    /// カスタム認証フローでトークンを発行するレスポンスです
+    fn custom_auth_responses() -> Vec<(&'static str, u16, serde_json::Value)> {
+        vec![
+            ("AdminInitiateAuth", 200, json!({ "ChallengeName": "CUSTOM_CHALLENGE", "Session": "session" })),
+            (
+                "AdminRespondToAuthChallenge",
+                200,
+                json!({
+                    "AuthenticationResult": {
+                        "AccessToken": "access_token",
+                        "RefreshToken": "refresh_token",
+                        "ExpiresIn": 3600,
+                        "TokenType": "Bearer"
+                    }
+                }),
+            ),
+        ]
+    }
+
+    fn federated_repository(stub: &CognitoStub) -> CognitoAuthUserRepository {
+        repository(stub)
+            .with_user_pool_id(Some("user_pool_id".to_string()))
+            .with_custom_auth_secret(Some("custom_auth_secret".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_creates_user() {
+        let mut responses = vec![
+            ("AdminGetUser", 400, json!({ "__type": USER_NOT_FOUND, "message": "User does not exist." })),
+            (
+                "AdminCreateUser",
+                200,
+                json!({ "User": { "Username": "hoge@email.com", "Attributes": [{ "Name": "sub", "Value": SUB }] } }),
+            ),
+            ("AdminSetUserPassword", 200, json!({})),
+        ];
+        responses.extend(custom_auth_responses());
+        let stub = CognitoStub::start(responses).await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = federated_repository(&stub).federated_sign_in(email.clone()).await.unwrap();
+        assert!(result.created());
+        assert_eq!(format!("usr_{}", SUB), result.user_id().to_string());
+        assert_eq!("access_token", result.token().access_token().expose_secret());
+        assert_eq!("refresh_token", result.token().refresh_token().expose_secret());
+
+        let requests = stub.requests();
+        let targets: Vec<_> = requests.iter().map(|(target, _)| target.as_str()).collect();
+        assert_eq!(
+            vec![
+                "AdminGetUser",
+                "AdminCreateUser",
+                "AdminSetUserPassword",
+                "AdminInitiateAuth",
+                "AdminRespondToAuthChallenge",
+            ],
+            targets
+        );
+        let (_, body) = &requests[1];
+        assert_eq!("SUPPRESS", body["MessageAction"]);
+        assert_eq!(
+            json!([{ "Name": "email", "Value": "hoge@email.com" }, { "Name": "email_verified", "Value": "true" }]),
+            body["UserAttributes"]
+        );
+        let (_, body) = &requests[2];
+        assert_eq!(true, body["Permanent"]);
+        let (_, body) = &requests[3];
+        assert_eq!("CUSTOM_AUTH", body["AuthFlow"]);
+        assert_eq!("hoge@email.com", body["AuthParameters"][USERNAME]);
+        let (_, body) = &requests[4];
+        assert_eq!("CUSTOM_CHALLENGE", body["ChallengeName"]);
+        assert_eq!("session", body["Session"]);
+        assert_eq!("custom_auth_secret", body["ChallengeResponses"][ANSWER]);
+        assert_eq!(
+            crate::client_secret_hash(email.value(), CLIENT_ID, CLIENT_SECRET),
+            body["ChallengeResponses"][SECRET_HASH]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_existing_user() {
+        let test_cases = vec![
+            (
+                "CONFIRMED",
+                vec![
+                    "AdminGetUser",
+                    "AdminInitiateAuth",
+                    "AdminRespondToAuthChallenge",
+                ],
+            ),
+            // 確認前のパスワードは推測できないパスワードに置き換え、メールアドレスを確認済みにします
+            (
+                "UNCONFIRMED",
+                vec![
+                    "AdminGetUser",
+                    "AdminConfirmSignUp",
+                    "AdminSetUserPassword",
+                    "AdminUpdateUserAttributes",
+                    "AdminInitiateAuth",
+                    "AdminRespondToAuthChallenge",
+                ],
+            ),
+        ];
+        for (status, expected) in test_cases {
+            let mut responses = vec![
+                (
+                    "AdminGetUser",
+                    200,
+                    json!({
+                        "Username": "hoge@email.com",
+                        "UserAttributes": [{ "Name": "sub", "Value": SUB }],
+                        "UserStatus": status,
+                    }),
+                ),
+                ("AdminConfirmSignUp", 200, json!({})),
+                ("AdminSetUserPassword", 200, json!({})),
+                ("AdminUpdateUserAttributes", 200, json!({})),
+            ];
+            responses.extend(custom_auth_responses());
+            let stub = CognitoStub::start(responses).await;
+
+            let email = Email::from_str("hoge@email.com").unwrap();
+            let result = federated_repository(&stub).federated_sign_in(email).await.unwrap();
+            assert!(!result.created());
+            assert_eq!(format!("usr_{}", SUB), result.user_id().to_string());
+
+            let requests = stub.requests();
+            let targets: Vec<_> = requests.iter().map(|(target, _)| target.as_str()).collect();
+            assert_eq!(expected, targets, "{}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_failed() {
+        // チャレンジを受け取れない場合は、カスタム認証のトリガーが設定されていません
+        let stub = CognitoStub::start(vec![
+            (
+                "AdminGetUser",
+                200,
+                json!({ "Username": "hoge@email.com", "UserAttributes": [{ "Name": "sub", "Value": SUB }] }),
+            ),
+            ("AdminInitiateAuth", 200, json!({ "ChallengeName": "PASSWORD_VERIFIER", "Session": "session" })),
+        ])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let result = federated_repository(&stub).federated_sign_in(email.clone()).await;
+        assert!(matches!(result, Err(AuthUserError::TokenMissing)));
+
+        let stub = CognitoStub::start(vec![]).await;
+        let result =
+            repository(&stub).with_user_pool_id(Some("user_pool_id".to_string())).federated_sign_in(email).await;
+        assert!(matches!(result, Err(AuthUserError::Unsupported(_))));
+        assert!(stub.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_guest() {
+        let mut responses = vec![
+            (
+                "AdminCreateUser",
+                200,
+                json!({ "User": { "Username": SUB, "Attributes": [{ "Name": "sub", "Value": SUB }] } }),
+            ),
+            ("AdminSetUserPassword", 200, json!({})),
+        ];
+        responses.extend(custom_auth_responses());
+        let stub = CognitoStub::start(responses).await;
+        let guest_email = Email::guest(&domain::user::user_id::UserId::new());
+
+        // Cognitoが割り当てた`UserId`を返します
+        let result = federated_repository(&stub)
+            .sign_up_guest(domain::user::user_id::UserId::new(), guest_email.clone(), 0)
+            .await
+            .unwrap();
+        assert_eq!(format!("usr_{}", SUB), result.user_id().to_string());
+        assert_eq!("access_token", result.token().access_token().expose_secret());
+
+        let requests = stub.requests();
+        let targets: Vec<_> = requests.iter().map(|(target, _)| target.as_str()).collect();
+        assert_eq!(
+            vec![
+                "AdminCreateUser",
+                "AdminSetUserPassword",
+                "AdminInitiateAuth",
+                "AdminRespondToAuthChallenge"
+            ],
+            targets
+        );
+        let (_, body) = &requests[0];
+        assert_eq!(guest_email.value(), body["Username"]);
+        let (_, body) = &requests[2];
+        assert_eq!(guest_email.value(), body["AuthParameters"][USERNAME]);
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_guest_without_custom_auth_secret() {
+        let stub = CognitoStub::start(vec![]).await;
+        let user_id = domain::user::user_id::UserId::new();
+        let guest_email = Email::guest(&user_id);
+
+        let result = repository(&stub)
+            .with_user_pool_id(Some("user_pool_id".to_string()))
+            .sign_up_guest(user_id, guest_email, 0)
+            .await;
+        assert!(matches!(result, Err(AuthUserError::Unsupported(_))));
+        assert!(stub.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_guest() {
+        let stub = CognitoStub::start(vec![
+            ("AdminSetUserPassword", 200, json!({})),
+            ("AdminUpdateUserAttributes", 200, json!({})),
+            ("AdminUserGlobalSignOut", 200, json!({})),
+        ])
+        .await;
+        let guest_email = Email::guest(&domain::user::user_id::UserId::new());
+
+        federated_repository(&stub).upgrade_guest(guest_email.clone(), auth_user()).await.unwrap();
+
+        let requests = stub.requests();
+        let targets: Vec<_> = requests.iter().map(|(target, _)| target.as_str()).collect();
+        assert_eq!(
+            vec![
+                "AdminSetUserPassword",
+                "AdminUpdateUserAttributes",
+                "AdminUserGlobalSignOut"
+            ],
+            targets
+        );
+        for (_, body) in &requests {
+            assert_eq!(guest_email.value(), body["Username"]);
+        }
+        let (_, body) = &requests[0];
+        assert_eq!(auth_user().password().expose_secret(), body["Password"]);
+        assert_eq!(true, body["Permanent"]);
+        // 確認コードを送信させるため、確認済みにはしません
+        let (_, body) = &requests[1];
+        assert_eq!(json!([{ "Name": "email", "Value": "hoge@email.com" }]), body["UserAttributes"]);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_guest_already_exists() {
+        let stub = CognitoStub::start(vec![
+            ("AdminSetUserPassword", 200, json!({})),
+            (
+                "AdminUpdateUserAttributes",
+                400,
+                json!({ "__type": ALIAS_EXISTS, "message": "An account with the email already exists." }),
+            ),
+        ])
+        .await;
+        let guest_email = Email::guest(&domain::user::user_id::UserId::new());
+
+        let result = federated_repository(&stub).upgrade_guest(guest_email, auth_user()).await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+        assert_eq!(2, stub.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_verify_guest_upgrade() {
+        let mut responses = custom_auth_responses();
+        responses.push(("VerifyUserAttribute", 200, json!({})));
+        let stub = CognitoStub::start(responses).await;
+        let user_id = domain::user::user_id::UserId::from(uuid::Uuid::parse_str(SUB).unwrap());
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        federated_repository(&stub).verify_guest_upgrade(user_id, email, "123456").await.unwrap();
+
+        // 確認されるまでは変更後のメールアドレスを使えないため、Cognitoのユーザー名でトークンを発行します
+        let requests = stub.requests();
+        let (_, body) = &requests[0];
+        assert_eq!(SUB, body["AuthParameters"][USERNAME]);
+        assert_eq!(crate::client_secret_hash(SUB, CLIENT_ID, CLIENT_SECRET), body["AuthParameters"][SECRET_HASH]);
+        let (target, body) = &requests[2];
+        assert_eq!("VerifyUserAttribute", target);
+        assert_eq!("access_token", body["AccessToken"]);
+        assert_eq!("email", body["AttributeName"]);
+        assert_eq!("123456", body["Code"]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_guest_upgrade_failed() {
+        let mut responses = custom_auth_responses();
+        responses.push((
+            "VerifyUserAttribute",
+            400,
+            json!({ "__type": CODE_MISMATCH, "message": "Invalid verification code provided." }),
+        ));
+        let stub = CognitoStub::start(responses).await;
+        let user_id = domain::user::user_id::UserId::from(uuid::Uuid::parse_str(SUB).unwrap());
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = federated_repository(&stub).verify_guest_upgrade(user_id, email, "000000").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_revert_guest_upgrade() {
+        let stub = CognitoStub::start(vec![
+            ("AdminUpdateUserAttributes", 200, json!({})),
+            ("AdminSetUserPassword", 200, json!({})),
+        ])
+        .await;
+        let guest_email = Email::guest(&domain::user::user_id::UserId::new());
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        federated_repository(&stub).revert_guest_upgrade(guest_email.clone(), email, 0).await.unwrap();
+
+        let requests = stub.requests();
+        let (_, body) = &requests[0];
+        assert_eq!(guest_email.value(), body["Username"]);
+        assert_eq!(
+            json!([{ "Name": "email", "Value": guest_email.value() }, { "Name": "email_verified", "Value": "true" }]),
+            body["UserAttributes"]
+        );
+        let (target, body) = &requests[1];
+        assert_eq!("AdminSetUserPassword", target);
+        assert_ne!(auth_user().password().expose_secret(), body["Password"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_email_change() {
+        let stub = CognitoStub::start(vec![("AdminUpdateUserAttributes", 200, json!({}))]).await;
+        let repository = repository(&stub).with_user_pool_id(Some("user_pool_id".to_string()));
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+
+        repository.request_email_change(email, new_email).await.unwrap();
+
+        let requests = stub.requests();
+        let (_, body) = &requests[0];
+        assert_eq!("user_pool_id", body["UserPoolId"]);
+        assert_eq!("hoge@email.com", body["Username"]);
+        assert_eq!(json!([{ "Name": "email", "Value": "fuga@email.com" }]), body["UserAttributes"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_email_change_already_exists() {
+        let stub = CognitoStub::start(vec![(
+            "AdminUpdateUserAttributes",
+            400,
+            json!({ "__type": ALIAS_EXISTS, "message": "An account with the email already exists." }),
+        )])
+        .await;
+        let repository = repository(&stub).with_user_pool_id(Some("user_pool_id".to_string()));
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+
+        let result = repository.request_email_change(email, new_email).await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+    }
+
+    #[tokio::test]
+    async fn test_request_email_change_without_user_pool_id() {
+        let stub = CognitoStub::start(vec![]).await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+
+        let result = repository(&stub).request_email_change(email, new_email).await;
+        assert!(matches!(result, Err(AuthUserError::InternalServerError(_))));
+        assert!(stub.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_email_change() {
+        let stub = CognitoStub::start(vec![
+            ("VerifyUserAttribute", 200, json!({})),
+            (
+                "GetUser",
+                200,
+                json!({
+                    "Username": "fuga@email.com",
+                    "UserAttributes": [
+                        { "Name": "sub", "Value": "sub" },
+                        { "Name": "email", "Value": "fuga@email.com" },
+                    ],
+                }),
+            ),
+        ])
+        .await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).confirm_email_change(email, "123456", "access_token").await.unwrap();
+        assert_eq!("fuga@email.com", result.value());
+
+        let requests = stub.requests();
+        let (target, body) = &requests[0];
+        assert_eq!("VerifyUserAttribute", target);
+        assert_eq!("access_token", body["AccessToken"]);
+        assert_eq!("email", body["AttributeName"]);
+        assert_eq!("123456", body["Code"]);
+        let (target, body) = &requests[1];
+        assert_eq!("GetUser", target);
+        assert_eq!("access_token", body["AccessToken"]);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_email_change_failed() {
+        let test_cases = vec![
+            json!({ "__type": CODE_MISMATCH, "message": "Invalid verification code provided." }),
+            json!({ "__type": EXPIRED_CODE, "message": "Invalid code provided, please request a code again." }),
+        ];
+        for body in test_cases {
+            let stub = CognitoStub::start(vec![("VerifyUserAttribute", 400, body.clone())]).await;
+            let email = Email::from_str("hoge@email.com").unwrap();
+
+            let result = repository(&stub).confirm_email_change(email, "000000", "access_token").await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))), "{}", body);
+            assert_eq!(1, stub.requests().len());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revert_email_change() {
+        let stub = CognitoStub::start(vec![("AdminUpdateUserAttributes", 200, json!({}))]).await;
+        let repository = repository(&stub).with_user_pool_id(Some("user_pool_id".to_string()));
+        let new_email = Email::from_str("fuga@email.com").unwrap();
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        repository.revert_email_change(new_email, email).await.unwrap();
+
+        let requests = stub.requests();
+        let (_, body) = &requests[0];
+        assert_eq!("fuga@email.com", body["Username"]);
+        assert_eq!(
+            json!([{ "Name": "email", "Value": "hoge@email.com" }, { "Name": "email_verified", "Value": "true" }]),
+            body["UserAttributes"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let test_cases = vec![
+            (200, json!({}), true),
+            (400, json!({ "__type": USER_NOT_FOUND, "message": "User does not exist." }), true),
+            (400, json!({ "__type": NOT_AUTHORIZED, "message": "Not authorized" }), false),
+        ];
+        for (status, body, expected) in test_cases {
+            let stub = CognitoStub::start(vec![("AdminDeleteUser", status, body.clone())]).await;
+            let repository = repository(&stub).with_user_pool_id(Some("user_pool_id".to_string()));
+
+            let result = repository.delete_user(Email::from_str("hoge@email.com").unwrap()).await;
+            assert_eq!(expected, result.is_ok(), "{}", body);
+
+            let requests = stub.requests();
+            let (_, body) = &requests[0];
+            assert_eq!("user_pool_id", body["UserPoolId"]);
+            assert_eq!("hoge@email.com", body["Username"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_without_user_pool_id() {
+        let stub = CognitoStub::start(vec![]).await;
+        let email = Email::from_str("hoge@email.com").unwrap();
+
+        let result = repository(&stub).delete_user(email).await;
+        assert!(matches!(result, Err(AuthUserError::InternalServerError(_))));
+        assert!(stub.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sign_out_failed() {
+        let stub = CognitoStub::start(vec![(
+            "GlobalSignOut",
+            400,
+            json!({ "__type": NOT_AUTHORIZED, "message": "Access Token has been revoked" }),
+        )])
+        .await;
+
+        let result = repository(&stub).sign_out("access_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+}
@@ -0,0 +1,316 @@
+use domain::credential::{Credential, CredentialError, EmailChange, VerificationCode};
+
+#[derive(Debug)]
+pub struct CredentialRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl CredentialRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        CredentialRepositoryImpl { client, table_name }
+    }
+
+    fn put_item(
+        &self, credential: &Credential,
+    ) -> aws_sdk_dynamodb::operation::put_item::builders::PutItemFluentBuilder {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let mut builder = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(EMAIL, AttributeValue::S(credential.email().to_string()))
+            .item(USER_ID, AttributeValue::S(credential.user_id().to_string()))
+            .item(PASSWORD_HASH, AttributeValue::S(credential.password_hash().to_string()))
+            .item(CONFIRMED, AttributeValue::Bool(credential.confirmed()))
+            .item(SIGNED_OUT_AT, AttributeValue::N(credential.signed_out_at().to_string()));
+
+        if let Some(code) = credential.verification_code() {
+            builder = builder
+                .item(CODE_HASH, AttributeValue::S(code.code_hash().to_string()))
+                .item(CODE_EXPIRES_AT, AttributeValue::N(code.expires_at().to_string()))
+                .item(CODE_FAILED_ATTEMPTS, AttributeValue::N(code.failed_attempts().to_string()));
+        }
+        if let Some(expires_at) = credential.expires_at() {
+            builder = builder.item(EXPIRES_AT, AttributeValue::N(expires_at.to_string()));
+        }
+        if let Some(code) = credential.reset_code() {
+            builder = builder
+                .item(RESET_CODE_HASH, AttributeValue::S(code.code_hash().to_string()))
+                .item(RESET_CODE_EXPIRES_AT, AttributeValue::N(code.expires_at().to_string()))
+                .item(RESET_CODE_FAILED_ATTEMPTS, AttributeValue::N(code.failed_attempts().to_string()));
+        }
+        if let Some(email_change) = credential.email_change() {
+            builder = builder
+                .item(NEW_EMAIL, AttributeValue::S(email_change.new_email().to_string()))
+                .item(EMAIL_CHANGE_CODE_HASH, AttributeValue::S(email_change.code().code_hash().to_string()))
+                .item(EMAIL_CHANGE_CODE_EXPIRES_AT, AttributeValue::N(email_change.code().expires_at().to_string()))
+                .item(
+                    EMAIL_CHANGE_CODE_FAILED_ATTEMPTS,
+                    AttributeValue::N(email_change.code().failed_attempts().to_string()),
+                );
+        }
+        builder
+    }
+}
+
+const EMAIL: &str = "email";
+const USER_ID: &str = "user_id";
+const PASSWORD_HASH: &str = "password_hash";
+const CONFIRMED: &str = "confirmed";
+const CODE_HASH: &str = "code_hash";
+const CODE_EXPIRES_AT: &str = "code_expires_at";
+const CODE_FAILED_ATTEMPTS: &str = "code_failed_attempts";
+const RESET_CODE_HASH: &str = "reset_code_hash";
+const RESET_CODE_EXPIRES_AT: &str = "reset_code_expires_at";
+const RESET_CODE_FAILED_ATTEMPTS: &str = "reset_code_failed_attempts";
+const SIGNED_OUT_AT: &str = "signed_out_at";
+const NEW_EMAIL: &str = "new_email";
+const EMAIL_CHANGE_CODE_HASH: &str = "email_change_code_hash";
+const EMAIL_CHANGE_CODE_EXPIRES_AT: &str = "email_change_code_expires_at";
+const EMAIL_CHANGE_CODE_FAILED_ATTEMPTS: &str = "email_change_code_failed_attempts";
+/// ゲストユーザーの認証情報を削除するDynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+
+impl domain::repository::credential_repository::CredentialRepository for CredentialRepositoryImpl {
+    fn find_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Credential, CredentialError>> + Send + '_>> {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let email = email.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(EMAIL, AttributeValue::S(email.clone()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = CredentialError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => CredentialRepositoryImpl::map_to_domain_model(item),
+                None => Err(CredentialError::NotFound(email)),
+            }
+        })
+    }
+
+    fn create(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        Box::pin(async move {
+            let result = self.put_item(&credential).condition_expression("attribute_not_exists(email)").send().await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = match e.as_service_error().map(|v| v.is_conditional_check_failed_exception()) {
+                        Some(true) => CredentialError::AlreadyExists(credential.email().to_string()),
+                        _ => CredentialError::SaveError(crate::dynamodb_error_message(&e)),
+                    };
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn update(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        Box::pin(async move {
+            self.put_item(&credential).condition_expression("attribute_exists(email)").send().await.map_err(|e| {
+                let error = CredentialError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    fn delete(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let email = email.to_string();
+
+        Box::pin(async move {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(EMAIL, AttributeValue::S(email))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = CredentialError::DeleteError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<Credential, CredentialError> for CredentialRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<Credential, CredentialError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_bool, as_i64, as_string};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id = domain::user::user_id::UserId::from_str(&user_id)
+            .map_err(|e| CredentialError::FindError(format!("{}: {}", e, user_id)))?;
+        let email = as_string(v.get(EMAIL), "");
+        let email = domain::user::email::Email::from_str(&email)
+            .map_err(|e| CredentialError::FindError(format!("{}: {}", e, email)))?;
+        let verification_code = v.get(CODE_HASH).map(|_| {
+            VerificationCode::new(as_string(v.get(CODE_HASH), ""), as_i64(v.get(CODE_EXPIRES_AT), 0))
+                .with_failed_attempts(as_i64(v.get(CODE_FAILED_ATTEMPTS), 0) as u32)
+        });
+        let reset_code = v.get(RESET_CODE_HASH).map(|_| {
+            VerificationCode::new(as_string(v.get(RESET_CODE_HASH), ""), as_i64(v.get(RESET_CODE_EXPIRES_AT), 0))
+                .with_failed_attempts(as_i64(v.get(RESET_CODE_FAILED_ATTEMPTS), 0) as u32)
+        });
+        let email_change = match v.get(NEW_EMAIL) {
+            Some(_) => {
+                let new_email = as_string(v.get(NEW_EMAIL), "");
+                let new_email = domain::user::email::Email::from_str(&new_email)
+                    .map_err(|e| CredentialError::FindError(format!("{}: {}", e, new_email)))?;
+                let code = VerificationCode::new(
+                    as_string(v.get(EMAIL_CHANGE_CODE_HASH), ""),
+                    as_i64(v.get(EMAIL_CHANGE_CODE_EXPIRES_AT), 0),
+                )
+                .with_failed_attempts(as_i64(v.get(EMAIL_CHANGE_CODE_FAILED_ATTEMPTS), 0) as u32);
+                Some(EmailChange::new(new_email, code))
+            }
+            None => None,
+        };
+
+        Ok(Credential::new(
+            user_id,
+            email,
+            as_string(v.get(PASSWORD_HASH), ""),
+            as_bool(v.get(CONFIRMED), false),
+            verification_code,
+            reset_code,
+            as_i64(v.get(SIGNED_OUT_AT), 0),
+        )
+        .with_expires_at(v.get(EXPIRES_AT).map(|_| as_i64(v.get(EXPIRES_AT), 0)))
+        .with_email_change(email_change))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (EMAIL.to_string(), AttributeValue::S("hoge@email.com".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (PASSWORD_HASH.to_string(), AttributeValue::S("hash".to_string())),
+            (CONFIRMED.to_string(), AttributeValue::Bool(true)),
+            (SIGNED_OUT_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = CredentialRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("hoge@email.com", result.email().value());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!("hash", result.password_hash());
+        assert!(result.confirmed());
+        assert!(result.verification_code().is_none());
+        assert!(result.reset_code().is_none());
+        assert_eq!(1700000000, result.signed_out_at());
+        assert_eq!(None, result.expires_at());
+        assert!(result.email_change().is_none());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_with_expires_at() {
+        let mut item = item();
+        item.insert(EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string()));
+
+        let result = CredentialRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!(Some(1700000000), result.expires_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_with_verification_code() {
+        let mut item = item();
+        item.insert(CODE_HASH.to_string(), AttributeValue::S("code_hash".to_string()));
+        item.insert(CODE_EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string()));
+        item.insert(CODE_FAILED_ATTEMPTS.to_string(), AttributeValue::N("3".to_string()));
+
+        let result = CredentialRepositoryImpl::map_to_domain_model(item).unwrap();
+        let code = result.verification_code().unwrap();
+        assert_eq!("code_hash", code.code_hash());
+        assert_eq!(1700000000, code.expires_at());
+        assert_eq!(3, code.failed_attempts());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_with_reset_code() {
+        let mut item = item();
+        item.insert(RESET_CODE_HASH.to_string(), AttributeValue::S("reset_hash".to_string()));
+        item.insert(RESET_CODE_EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string()));
+        item.insert(RESET_CODE_FAILED_ATTEMPTS.to_string(), AttributeValue::N("2".to_string()));
+
+        let result = CredentialRepositoryImpl::map_to_domain_model(item).unwrap();
+        let code = result.reset_code().unwrap();
+        assert_eq!("reset_hash", code.code_hash());
+        assert_eq!(1700000000, code.expires_at());
+        assert_eq!(2, code.failed_attempts());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_with_email_change() {
+        let mut item = item();
+        item.insert(NEW_EMAIL.to_string(), AttributeValue::S("fuga@email.com".to_string()));
+        item.insert(EMAIL_CHANGE_CODE_HASH.to_string(), AttributeValue::S("change_hash".to_string()));
+        item.insert(EMAIL_CHANGE_CODE_EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string()));
+        item.insert(EMAIL_CHANGE_CODE_FAILED_ATTEMPTS.to_string(), AttributeValue::N("4".to_string()));
+
+        let result = CredentialRepositoryImpl::map_to_domain_model(item).unwrap();
+        let email_change = result.email_change().unwrap();
+        assert_eq!("fuga@email.com", email_change.new_email().value());
+        assert_eq!("change_hash", email_change.code().code_hash());
+        assert_eq!(1700000000, email_change.code().expires_at());
+        assert_eq!(4, email_change.code().failed_attempts());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_failed() {
+        let test_cases = vec![
+            (USER_ID, "invalid"),
+            (EMAIL, "invalid"),
+            (NEW_EMAIL, "invalid"),
+        ];
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.to_string(), AttributeValue::S(value.to_string()));
+            let result = CredentialRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(CredentialError::FindError(_))), "{}", key);
+        }
+    }
+}
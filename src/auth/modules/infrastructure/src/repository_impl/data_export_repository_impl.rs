@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use domain::{
+    data_export::{DataExport, DataExportError, ExportStatus, ExportedItems},
+    user::user_id::UserId,
+};
+
+/// データのエクスポートの依頼と進捗を保存します
+///
+/// パーティションキーは`export_id`です
+#[derive(Debug)]
+pub struct DataExportRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl DataExportRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        DataExportRepositoryImpl { client, table_name }
+    }
+}
+
+const EXPORT_ID: &str = "export_id";
+const USER_ID: &str = "user_id";
+const STATUS: &str = "status";
+const REQUESTED_AT: &str = "requested_at";
+const TOTAL: &str = "total";
+/// 書き出したデータの種類と件数の`{ target, count }`のリストです
+const EXPORTED: &str = "exported";
+const TARGET: &str = "target";
+const COUNT: &str = "count";
+const FILE_KEY: &str = "file_key";
+const COMPLETED_AT: &str = "completed_at";
+const DOWNLOAD_TOKEN_HASH: &str = "download_token_hash";
+const DOWNLOAD_EXPIRES_AT: &str = "download_expires_at";
+
+/// `user_id`をパーティションキーとするGSIです。すべての属性を射影します
+const USER_ID_INDEX: &str = "user_id-index";
+
+const USER_ID_ATTR: &str = "#user_id";
+const USER_ID_VALUE: &str = ":user_id";
+const STATUS_ATTR: &str = "#status";
+const PENDING_VALUE: &str = ":pending";
+const IN_PROGRESS_VALUE: &str = ":in_progress";
+
+impl domain::repository::data_export_repository::DataExportRepository for DataExportRepositoryImpl {
+    fn find(
+        &self, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<DataExport, DataExportError>> + Send + '_>> {
+        use crate::mapper::Mapper;
+
+        let export_id = export_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(EXPORT_ID, AttributeValue::S(export_id.clone()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = DataExportError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => DataExportRepositoryImpl::map_to_domain_model(item),
+                None => Err(DataExportError::NotFound(export_id)),
+            }
+        })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let mut exports = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .index_name(USER_ID_INDEX)
+                    .key_condition_expression("#user_id = :user_id")
+                    .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                    .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.clone()))
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = DataExportError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    exports.push(DataExportRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            exports.sort_by_key(|v| v.requested_at());
+            Ok(exports)
+        })
+    }
+
+    /// 依頼の件数は少ないため、GSIを作らずにテーブル全体をスキャンします
+    fn find_active(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+
+        Box::pin(async move {
+            let mut exports = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .scan()
+                    .table_name(&self.table_name)
+                    .filter_expression("#status IN (:pending, :in_progress)")
+                    .expression_attribute_names(STATUS_ATTR, STATUS)
+                    .expression_attribute_values(PENDING_VALUE, AttributeValue::S(ExportStatus::Pending.to_string()))
+                    .expression_attribute_values(
+                        IN_PROGRESS_VALUE,
+                        AttributeValue::S(ExportStatus::InProgress.to_string()),
+                    )
+                    .consistent_read(true)
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = DataExportError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    exports.push(DataExportRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            exports.sort_by_key(|v| v.requested_at());
+            Ok(exports)
+        })
+    }
+
+    fn save(
+        &self, export: DataExport,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), DataExportError>> + Send + '_>> {
+        Box::pin(async move {
+            let exported = export
+                .exported()
+                .iter()
+                .map(|v| {
+                    AttributeValue::M(HashMap::from([
+                        (TARGET.to_string(), AttributeValue::S(v.target().to_string())),
+                        (COUNT.to_string(), AttributeValue::N(v.count().to_string())),
+                    ]))
+                })
+                .collect();
+            let mut request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(EXPORT_ID, AttributeValue::S(export.export_id().to_string()))
+                .item(USER_ID, AttributeValue::S(export.user_id().to_string()))
+                .item(STATUS, AttributeValue::S(export.status().to_string()))
+                .item(REQUESTED_AT, AttributeValue::N(export.requested_at().to_string()))
+                .item(TOTAL, AttributeValue::N(export.total().to_string()))
+                .item(EXPORTED, AttributeValue::L(exported));
+            if let Some(v) = export.file_key() {
+                request = request.item(FILE_KEY, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = export.completed_at() {
+                request = request.item(COMPLETED_AT, AttributeValue::N(v.to_string()));
+            }
+            if let Some(v) = export.download_token_hash() {
+                request = request.item(DOWNLOAD_TOKEN_HASH, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = export.download_expires_at() {
+                request = request.item(DOWNLOAD_EXPIRES_AT, AttributeValue::N(v.to_string()));
+            }
+
+            request.send().await.map_err(|e| {
+                let error = DataExportError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, DataExportError>> + Send + '_>> {
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let exports = self.find_by_user_id(&user_id).await?;
+            for export in &exports {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key(EXPORT_ID, AttributeValue::S(export.export_id().to_string()))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = DataExportError::DeleteError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+            }
+            Ok(exports.len())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<DataExport, DataExportError> for DataExportRepositoryImpl {
+    fn map_to_domain_model(v: HashMap<String, AttributeValue>) -> Result<DataExport, DataExportError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id =
+            UserId::from_str(&user_id).map_err(|e| DataExportError::FindError(format!("{}: {}", e, user_id)))?;
+        let status = ExportStatus::from_str(&as_string(v.get(STATUS), ""))
+            .map_err(|e| DataExportError::FindError(e.to_string()))?;
+        let exported = v
+            .get(EXPORTED)
+            .and_then(|v| v.as_l().ok())
+            .map(|v| {
+                v.iter()
+                    .filter_map(|v| v.as_m().ok())
+                    .map(|v| ExportedItems::new(as_string(v.get(TARGET), ""), as_i64(v.get(COUNT), 0) as usize))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(DataExport::new(
+            as_string(v.get(EXPORT_ID), ""),
+            user_id,
+            status,
+            as_i64(v.get(REQUESTED_AT), 0),
+            as_i64(v.get(TOTAL), 0) as usize,
+            exported,
+            v.get(FILE_KEY).map(|_| as_string(v.get(FILE_KEY), "")),
+            v.get(COMPLETED_AT).map(|_| as_i64(v.get(COMPLETED_AT), 0)),
+            v.get(DOWNLOAD_TOKEN_HASH).map(|_| as_string(v.get(DOWNLOAD_TOKEN_HASH), "")),
+            v.get(DOWNLOAD_EXPIRES_AT).map(|_| as_i64(v.get(DOWNLOAD_EXPIRES_AT), 0)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (EXPORT_ID.to_string(), AttributeValue::S("123e4567-e89b-12d3-a456-426614174000".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (STATUS.to_string(), AttributeValue::S("in_progress".to_string())),
+            (REQUESTED_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+            (TOTAL.to_string(), AttributeValue::N("6".to_string())),
+            (
+                EXPORTED.to_string(),
+                AttributeValue::L(vec![
+                    AttributeValue::M(HashMap::from([
+                        (TARGET.to_string(), AttributeValue::S("profile".to_string())),
+                        (COUNT.to_string(), AttributeValue::N("1".to_string())),
+                    ])),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = DataExportRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("123e4567-e89b-12d3-a456-426614174000", result.export_id());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!(ExportStatus::InProgress, result.status());
+        assert_eq!(1700000000, result.requested_at());
+        assert_eq!(6, result.total());
+        assert_eq!(vec![ExportedItems::new("profile".to_string(), 1)], result.exported());
+        assert_eq!(None, result.file_key());
+        assert_eq!(None, result.completed_at());
+        assert_eq!(None, result.download_token_hash());
+        assert_eq!(None, result.download_expires_at());
+
+        let mut item = item();
+        item.insert(STATUS.to_string(), AttributeValue::S("completed".to_string()));
+        item.insert(FILE_KEY.to_string(), AttributeValue::S("exports/usr_1/export.zip".to_string()));
+        item.insert(COMPLETED_AT.to_string(), AttributeValue::N("1700000100".to_string()));
+        item.insert(DOWNLOAD_TOKEN_HASH.to_string(), AttributeValue::S("hash".to_string()));
+        item.insert(DOWNLOAD_EXPIRES_AT.to_string(), AttributeValue::N("1700000900".to_string()));
+        let result = DataExportRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!(ExportStatus::Completed, result.status());
+        assert_eq!(Some("exports/usr_1/export.zip"), result.file_key());
+        assert_eq!(Some(1700000100), result.completed_at());
+        assert_eq!(Some("hash"), result.download_token_hash());
+        assert_eq!(Some(1700000900), result.download_expires_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid() {
+        let test_cases = vec![
+            (USER_ID.to_string(), AttributeValue::S("invalid".to_string())),
+            (STATUS.to_string(), AttributeValue::S("cancelled".to_string())),
+        ];
+
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.clone(), value);
+            let result = DataExportRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(DataExportError::FindError(_))), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use domain::{
+    external_identity::{ExternalIdentity, ExternalIdentityError},
+    user::{email::Email, user_id::UserId},
+};
+
+#[derive(Debug)]
+pub struct ExternalIdentityRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl ExternalIdentityRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        ExternalIdentityRepositoryImpl { client, table_name }
+    }
+}
+
+const IDENTITY_KEY: &str = "identity_key";
+const PROVIDER: &str = "provider";
+const SUBJECT: &str = "subject";
+const USER_ID: &str = "user_id";
+const EMAIL: &str = "email";
+const LINKED_AT: &str = "linked_at";
+
+/// `user_id`をパーティションキーとするGSIです。テーブルのキーのみを射影します
+const USER_ID_INDEX: &str = "user_id-index";
+
+const USER_ID_ATTR: &str = "#user_id";
+const USER_ID_VALUE: &str = ":user_id";
+
+impl domain::repository::external_identity_repository::ExternalIdentityRepository for ExternalIdentityRepositoryImpl {
+    fn find(
+        &self, provider: &str, subject: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Option<ExternalIdentity>, ExternalIdentityError>> + Send + '_>,
+    > {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let key = ExternalIdentity::key_of(provider, subject);
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(IDENTITY_KEY, AttributeValue::S(key))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = ExternalIdentityError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => Ok(Some(ExternalIdentityRepositoryImpl::map_to_domain_model(item)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn save(
+        &self, identity: ExternalIdentity,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExternalIdentityError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        Box::pin(async move {
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(IDENTITY_KEY, AttributeValue::S(identity.key()))
+                .item(PROVIDER, AttributeValue::S(identity.provider().to_string()))
+                .item(SUBJECT, AttributeValue::S(identity.subject().to_string()))
+                .item(USER_ID, AttributeValue::S(identity.user_id().to_string()))
+                .item(EMAIL, AttributeValue::S(identity.email().to_string()))
+                .item(LINKED_AT, AttributeValue::N(identity.linked_at().to_string()))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = ExternalIdentityError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, ExternalIdentityError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let mut keys = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .index_name(USER_ID_INDEX)
+                    .key_condition_expression("#user_id = :user_id")
+                    .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                    .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.clone()))
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = ExternalIdentityError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    keys.extend(item.get(IDENTITY_KEY).cloned());
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            for key in &keys {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key(IDENTITY_KEY, key.clone())
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = ExternalIdentityError::DeleteError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+            }
+            Ok(keys.len())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<ExternalIdentity, ExternalIdentityError> for ExternalIdentityRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<ExternalIdentity, ExternalIdentityError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id =
+            UserId::from_str(&user_id).map_err(|e| ExternalIdentityError::FindError(format!("{}: {}", e, user_id)))?;
+        let email = as_string(v.get(EMAIL), "");
+        let email =
+            Email::from_str(&email).map_err(|e| ExternalIdentityError::FindError(format!("{}: {}", e, email)))?;
+
+        Ok(ExternalIdentity::new(
+            as_string(v.get(PROVIDER), ""),
+            as_string(v.get(SUBJECT), ""),
+            user_id,
+            email,
+            as_i64(v.get(LINKED_AT), 0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (IDENTITY_KEY.to_string(), AttributeValue::S("google#1234567890".to_string())),
+            (PROVIDER.to_string(), AttributeValue::S("google".to_string())),
+            (SUBJECT.to_string(), AttributeValue::S("1234567890".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (EMAIL.to_string(), AttributeValue::S("hoge@email.com".to_string())),
+            (LINKED_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = ExternalIdentityRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("google", result.provider());
+        assert_eq!("1234567890", result.subject());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!("hoge@email.com", result.email().to_string());
+        assert_eq!(1700000000, result.linked_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid() {
+        let test_cases = vec![
+            (USER_ID, "invalid"),
+            (EMAIL, "invalid"),
+        ];
+
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.to_string(), AttributeValue::S(value.to_string()));
+            let result = ExternalIdentityRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(ExternalIdentityError::FindError(_))), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    account_deletion::{AccountDeletion, AccountDeletionError},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryAccountDeletionRepository {
+    deletions: Mutex<HashMap<String, AccountDeletion>>,
+}
+
+impl InMemoryAccountDeletionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::account_deletion_repository::AccountDeletionRepository for InMemoryAccountDeletionRepository {
+    fn find(
+        &self, deletion_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>
+    {
+        let result = self.deletions.lock().map_err(|e| AccountDeletionError::FindError(e.to_string())).and_then(|v| {
+            v.get(deletion_id).cloned().ok_or_else(|| AccountDeletionError::NotFound(deletion_id.to_string()))
+        });
+        Box::pin(async move { result })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    > {
+        let result = self.deletions.lock().map_err(|e| AccountDeletionError::FindError(e.to_string())).map(|v| {
+            let mut deletions: Vec<_> = v.values().filter(|v| v.user_id() == user_id).cloned().collect();
+            deletions.sort_by_key(|v| v.requested_at());
+            deletions
+        });
+        Box::pin(async move { result })
+    }
+
+    fn find_due(
+        &self, now: i64,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<AccountDeletion>, AccountDeletionError>> + Send + '_>,
+    > {
+        let result = self.deletions.lock().map_err(|e| AccountDeletionError::FindError(e.to_string())).map(|v| {
+            let mut deletions: Vec<_> = v.values().filter(|v| v.is_due(now)).cloned().collect();
+            deletions.sort_by_key(|v| v.scheduled_at());
+            deletions
+        });
+        Box::pin(async move { result })
+    }
+
+    fn claim(
+        &self, deletion_id: &str, now: i64, leased_until: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AccountDeletion, AccountDeletionError>> + Send + '_>>
+    {
+        let result =
+            self.deletions.lock().map_err(|e| AccountDeletionError::SaveError(e.to_string())).and_then(|mut v| match v
+                .get(deletion_id)
+            {
+                Some(current) if current.is_due(now) && !current.is_leased(now) => {
+                    let leased = current.lease(leased_until);
+                    v.insert(deletion_id.to_string(), leased.clone());
+                    Ok(leased)
+                }
+                _ => Err(AccountDeletionError::NotClaimable(deletion_id.to_string())),
+            });
+        Box::pin(async move { result })
+    }
+
+    fn save(
+        &self, deletion: AccountDeletion,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AccountDeletionError>> + Send + '_>> {
+        let result = self.deletions.lock().map_err(|e| AccountDeletionError::SaveError(e.to_string())).map(|mut v| {
+            v.insert(deletion.deletion_id().to_string(), deletion);
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::{
+        account_deletion::DeletionStatus, repository::account_deletion_repository::AccountDeletionRepository,
+        user::email::Email,
+    };
+
+    use super::*;
+
+    fn deletion(deletion_id: &str, user_id: &UserId, now: i64) -> AccountDeletion {
+        AccountDeletion::request(
+            deletion_id.to_string(),
+            user_id.clone(),
+            Email::from_str("hoge@email.com").unwrap(),
+            now,
+            10,
+        )
+    }
+
+    fn ids(deletions: &[AccountDeletion]) -> String {
+        deletions.iter().map(|v| v.deletion_id()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[tokio::test]
+    async fn test_save_and_find() {
+        let repository = InMemoryAccountDeletionRepository::new();
+        let user_id = UserId::new();
+        assert!(matches!(repository.find("first").await, Err(AccountDeletionError::NotFound(_))));
+
+        let first = deletion("first", &user_id, 100).cancel(100).unwrap();
+        let second = deletion("second", &user_id, 200);
+        repository.save(second.clone()).await.unwrap();
+        repository.save(first).await.unwrap();
+        repository.save(deletion("other", &UserId::new(), 300)).await.unwrap();
+
+        assert_eq!(DeletionStatus::Cancelled, repository.find("first").await.unwrap().status());
+        let result = repository.find_by_user_id(&user_id).await.unwrap();
+        assert_eq!("first second", ids(&result));
+
+        let test_cases = vec![
+            (209, ""),
+            (210, "second"),
+            (310, "second other"),
+        ];
+        for (now, expected) in test_cases {
+            assert_eq!(expected, ids(&repository.find_due(now).await.unwrap()), "{}", now);
+        }
+
+        repository.save(second.start().complete(220)).await.unwrap();
+        assert_eq!("other", ids(&repository.find_due(310).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_claim() {
+        let repository = InMemoryAccountDeletionRepository::new();
+        repository.save(deletion("first", &UserId::new(), 100)).await.unwrap();
+
+        let test_cases = vec![
+            (109, false),
+            (110, true),
+            // 期限までは他の処理に実行させません
+            (169, false),
+            (170, true),
+        ];
+        for (now, expected) in test_cases {
+            let result = repository.claim("first", now, now + 60).await;
+            assert_eq!(expected, result.is_ok(), "{}", now);
+        }
+        assert_eq!(Some(230), repository.find("first").await.unwrap().leased_until());
+        assert!(matches!(repository.claim("other", 200, 260).await, Err(AccountDeletionError::NotClaimable(_))));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::credential::{Credential, CredentialError};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryCredentialRepository {
+    credentials: Mutex<HashMap<String, Credential>>,
+}
+
+impl InMemoryCredentialRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::credential_repository::CredentialRepository for InMemoryCredentialRepository {
+    fn find_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Credential, CredentialError>> + Send + '_>> {
+        let result =
+            self.credentials.lock().map_err(|e| CredentialError::FindError(e.to_string())).and_then(|v| {
+                v.get(email.value()).cloned().ok_or_else(|| CredentialError::NotFound(email.to_string()))
+            });
+        Box::pin(async move { result })
+    }
+
+    fn create(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        let result =
+            self.credentials.lock().map_err(|e| CredentialError::SaveError(e.to_string())).and_then(|mut v| {
+                match v.contains_key(credential.email().value()) {
+                    true => Err(CredentialError::AlreadyExists(credential.email().to_string())),
+                    false => {
+                        v.insert(credential.email().to_string(), credential);
+                        Ok(())
+                    }
+                }
+            });
+        Box::pin(async move { result })
+    }
+
+    fn update(
+        &self, credential: Credential,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        let result =
+            self.credentials.lock().map_err(|e| CredentialError::SaveError(e.to_string())).and_then(|mut v| {
+                match v.contains_key(credential.email().value()) {
+                    true => {
+                        v.insert(credential.email().to_string(), credential);
+                        Ok(())
+                    }
+                    false => Err(CredentialError::SaveError(format!("not found: {}", credential.email()))),
+                }
+            });
+        Box::pin(async move { result })
+    }
+
+    fn delete(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), CredentialError>> + Send + '_>> {
+        let result = self.credentials.lock().map_err(|e| CredentialError::DeleteError(e.to_string())).map(|mut v| {
+            v.remove(email.value());
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use domain::{
+        repository::credential_repository::CredentialRepository,
+        user::{email::Email, user_id::UserId},
+    };
+
+    use super::*;
+
+    fn credential() -> Credential {
+        Credential::new(
+            UserId::new(),
+            Email::from_str("hoge@email.com").unwrap(),
+            "hash".to_string(),
+            false,
+            None,
+            None,
+            0,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_success() {
+        let repository = InMemoryCredentialRepository::new();
+        let credential = credential();
+        repository.create(credential.clone()).await.unwrap();
+
+        let result = repository.find_by_email(credential.email()).await.unwrap();
+        assert_eq!(credential, result);
+
+        let result = repository.create(credential).await;
+        assert!(matches!(result, Err(CredentialError::AlreadyExists(_))));
+    }
+
+    #[tokio::test]
+    async fn test_update_success() {
+        let repository = InMemoryCredentialRepository::new();
+        let credential = credential();
+        assert!(repository.update(credential.clone()).await.is_err());
+
+        repository.create(credential.clone()).await.unwrap();
+        repository.update(credential.confirm()).await.unwrap();
+        assert!(repository.find_by_email(credential.email()).await.unwrap().confirmed());
+    }
+
+    #[tokio::test]
+    async fn test_delete_success() {
+        let repository = InMemoryCredentialRepository::new();
+        let credential = credential();
+        repository.create(credential.clone()).await.unwrap();
+
+        repository.delete(credential.email()).await.unwrap();
+        let result = repository.find_by_email(credential.email()).await;
+        assert!(matches!(result, Err(CredentialError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_find_not_found() {
+        let repository = InMemoryCredentialRepository::new();
+        let result = repository.find_by_email(&Email::from_str("hoge@email.com").unwrap()).await;
+        assert!(matches!(result, Err(CredentialError::NotFound(_))));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    data_export::{DataExport, DataExportError},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryDataExportRepository {
+    exports: Mutex<HashMap<String, DataExport>>,
+}
+
+impl InMemoryDataExportRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::data_export_repository::DataExportRepository for InMemoryDataExportRepository {
+    fn find(
+        &self, export_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<DataExport, DataExportError>> + Send + '_>> {
+        let result =
+            self.exports.lock().map_err(|e| DataExportError::FindError(e.to_string())).and_then(|v| {
+                v.get(export_id).cloned().ok_or_else(|| DataExportError::NotFound(export_id.to_string()))
+            });
+        Box::pin(async move { result })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>
+    {
+        let result = self.exports.lock().map_err(|e| DataExportError::FindError(e.to_string())).map(|v| {
+            let mut exports: Vec<_> = v.values().filter(|v| v.user_id() == user_id).cloned().collect();
+            exports.sort_by_key(|v| v.requested_at());
+            exports
+        });
+        Box::pin(async move { result })
+    }
+
+    fn find_active(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<DataExport>, DataExportError>> + Send + '_>>
+    {
+        let result = self.exports.lock().map_err(|e| DataExportError::FindError(e.to_string())).map(|v| {
+            let mut exports: Vec<_> = v.values().filter(|v| v.is_active()).cloned().collect();
+            exports.sort_by_key(|v| v.requested_at());
+            exports
+        });
+        Box::pin(async move { result })
+    }
+
+    fn save(
+        &self, export: DataExport,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), DataExportError>> + Send + '_>> {
+        let result = self.exports.lock().map_err(|e| DataExportError::SaveError(e.to_string())).map(|mut v| {
+            v.insert(export.export_id().to_string(), export);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, DataExportError>> + Send + '_>> {
+        let result = self.exports.lock().map_err(|e| DataExportError::DeleteError(e.to_string())).map(|mut v| {
+            let count = v.len();
+            v.retain(|_, v| v.user_id() != user_id);
+            count - v.len()
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::repository::data_export_repository::DataExportRepository;
+
+    use super::*;
+
+    fn ids(exports: &[DataExport]) -> String {
+        exports.iter().map(|v| v.export_id()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[tokio::test]
+    async fn test_save_and_find() {
+        let repository = InMemoryDataExportRepository::new();
+        let user_id = UserId::new();
+        assert!(matches!(repository.find("first").await, Err(DataExportError::NotFound(_))));
+
+        let first = DataExport::request("first".to_string(), user_id.clone(), 100);
+        let second = DataExport::request("second".to_string(), user_id.clone(), 200);
+        repository.save(second.clone()).await.unwrap();
+        repository.save(first.start(1).complete("key".to_string(), 150)).await.unwrap();
+        repository.save(DataExport::request("other".to_string(), UserId::new(), 300)).await.unwrap();
+
+        assert_eq!(Some("key"), repository.find("first").await.unwrap().file_key());
+        assert_eq!("first second", ids(&repository.find_by_user_id(&user_id).await.unwrap()));
+        assert_eq!("second other", ids(&repository.find_active().await.unwrap()));
+
+        repository.save(second.fail(250)).await.unwrap();
+        assert_eq!("other", ids(&repository.find_active().await.unwrap()));
+
+        assert_eq!(2, repository.delete_by_user_id(&user_id).await.unwrap());
+        assert!(repository.find_by_user_id(&user_id).await.unwrap().is_empty());
+        assert_eq!("other", ids(&repository.find_active().await.unwrap()));
+    }
+}
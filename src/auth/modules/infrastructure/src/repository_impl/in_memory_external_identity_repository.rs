@@ -0,0 +1,87 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    external_identity::{ExternalIdentity, ExternalIdentityError},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryExternalIdentityRepository {
+    identities: Mutex<HashMap<String, ExternalIdentity>>,
+}
+
+impl InMemoryExternalIdentityRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::external_identity_repository::ExternalIdentityRepository
+    for InMemoryExternalIdentityRepository
+{
+    fn find(
+        &self, provider: &str, subject: &str,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Option<ExternalIdentity>, ExternalIdentityError>> + Send + '_>,
+    > {
+        let result = self
+            .identities
+            .lock()
+            .map_err(|e| ExternalIdentityError::FindError(e.to_string()))
+            .map(|v| v.get(&ExternalIdentity::key_of(provider, subject)).cloned());
+        Box::pin(async move { result })
+    }
+
+    fn save(
+        &self, identity: ExternalIdentity,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExternalIdentityError>> + Send + '_>> {
+        let result =
+            self.identities.lock().map_err(|e| ExternalIdentityError::SaveError(e.to_string())).map(|mut v| {
+                v.insert(identity.key(), identity);
+            });
+        Box::pin(async move { result })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, ExternalIdentityError>> + Send + '_>> {
+        let result =
+            self.identities.lock().map_err(|e| ExternalIdentityError::DeleteError(e.to_string())).map(|mut v| {
+                let count = v.len();
+                v.retain(|_, identity| identity.user_id() != user_id);
+                count - v.len()
+            });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::repository::external_identity_repository::ExternalIdentityRepository;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_save_and_find() {
+        let repository = InMemoryExternalIdentityRepository::new();
+        assert_eq!(None, repository.find("google", "1234567890").await.unwrap());
+
+        let saved = ExternalIdentity::new(
+            "google".to_string(),
+            "1234567890".to_string(),
+            UserId::new(),
+            "hoge@email.com".parse().unwrap(),
+            0,
+        );
+        repository.save(saved.clone()).await.unwrap();
+        assert_eq!(Some(saved.clone()), repository.find("google", "1234567890").await.unwrap());
+        assert_eq!(None, repository.find("apple", "1234567890").await.unwrap());
+
+        assert_eq!(0, repository.delete_by_user_id(&UserId::new()).await.unwrap());
+        assert_eq!(1, repository.delete_by_user_id(saved.user_id()).await.unwrap());
+        assert_eq!(None, repository.find("google", "1234567890").await.unwrap());
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use domain::{
+    clock::{Clock, SystemClock},
+    login_attempt::{LoginAttempt, LoginAttemptError},
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// 保存時に有効期限を過ぎた記録を破棄します。プロセスの再起動でデータは失われます
+#[derive(Debug)]
+pub struct InMemoryLoginAttemptRepository {
+    attempts: Mutex<HashMap<String, LoginAttempt>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for InMemoryLoginAttemptRepository {
+    fn default() -> Self {
+        Self { attempts: Mutex::default(), clock: Arc::new(SystemClock) }
+    }
+}
+
+impl InMemoryLoginAttemptRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl domain::repository::login_attempt_repository::LoginAttemptRepository for InMemoryLoginAttemptRepository {
+    fn find(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<LoginAttempt>, LoginAttemptError>> + Send + '_>>
+    {
+        let result = self
+            .attempts
+            .lock()
+            .map_err(|e| LoginAttemptError::FindError(e.to_string()))
+            .map(|v| v.get(key).filter(|attempt| !attempt.is_expired(self.clock.now())).cloned());
+        Box::pin(async move { result })
+    }
+
+    fn save(
+        &self, attempt: LoginAttempt,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>> {
+        let result = self.attempts.lock().map_err(|e| LoginAttemptError::SaveError(e.to_string())).map(|mut v| {
+            let now = self.clock.now();
+            v.retain(|_, attempt| !attempt.is_expired(now));
+            v.insert(attempt.key().to_string(), attempt);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>> {
+        let result = self.attempts.lock().map_err(|e| LoginAttemptError::DeleteError(e.to_string())).map(|mut v| {
+            v.remove(key);
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{clock::FixedClock, repository::login_attempt_repository::LoginAttemptRepository};
+
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn attempt(key: &str, expires_at: i64) -> LoginAttempt {
+        LoginAttempt::new(key.to_string(), vec![NOW], 0, 0, expires_at)
+    }
+
+    #[tokio::test]
+    async fn test_save_find_and_delete() {
+        let repository = InMemoryLoginAttemptRepository::new().with_clock(Arc::new(FixedClock(NOW)));
+        assert_eq!(None, repository.find("key").await.unwrap());
+
+        let saved = attempt("key", NOW + 60);
+        repository.save(saved.clone()).await.unwrap();
+        assert_eq!(Some(saved), repository.find("key").await.unwrap());
+
+        repository.delete("key").await.unwrap();
+        assert_eq!(None, repository.find("key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expired_attempt_is_discarded() {
+        let repository = InMemoryLoginAttemptRepository::new().with_clock(Arc::new(FixedClock(NOW)));
+        repository.save(attempt("expired", NOW - 1)).await.unwrap();
+        assert_eq!(None, repository.find("expired").await.unwrap());
+
+        repository.save(attempt("key", NOW + 60)).await.unwrap();
+        assert!(!repository.attempts.lock().unwrap().contains_key("expired"));
+    }
+}
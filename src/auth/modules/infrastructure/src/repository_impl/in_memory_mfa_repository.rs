@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    mfa::{Mfa, MfaError},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryMfaRepository {
+    settings: Mutex<HashMap<String, Mfa>>,
+}
+
+impl InMemoryMfaRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::mfa_repository::MfaRepository for InMemoryMfaRepository {
+    fn find(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<Mfa>, MfaError>> + Send + '_>> {
+        let result = self
+            .settings
+            .lock()
+            .map_err(|e| MfaError::FindError(e.to_string()))
+            .map(|v| v.get(&user_id.to_string()).cloned());
+        Box::pin(async move { result })
+    }
+
+    fn save(&self, mfa: Mfa) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        let result = self.settings.lock().map_err(|e| MfaError::SaveError(e.to_string())).map(|mut v| {
+            v.insert(mfa.user_id().to_string(), mfa);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn update(
+        &self, mfa: Mfa, expected: &Mfa,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        let result = self.settings.lock().map_err(|e| MfaError::SaveError(e.to_string())).and_then(|mut v| {
+            match v.get(&mfa.user_id().to_string()) {
+                Some(current) if current == expected => {
+                    v.insert(mfa.user_id().to_string(), mfa);
+                    Ok(())
+                }
+                _ => Err(MfaError::Conflict(mfa.user_id().to_string())),
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        let result = self.settings.lock().map_err(|e| MfaError::DeleteError(e.to_string())).map(|mut v| {
+            v.remove(&user_id.to_string());
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::repository::mfa_repository::MfaRepository;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_save_find_and_delete() {
+        let repository = InMemoryMfaRepository::new();
+        let user_id = UserId::new();
+        assert_eq!(None, repository.find(&user_id).await.unwrap());
+
+        let saved = Mfa::enroll(user_id.clone(), "secret".to_string());
+        repository.save(saved.clone()).await.unwrap();
+        assert_eq!(Some(saved), repository.find(&user_id).await.unwrap());
+        assert_eq!(None, repository.find(&UserId::new()).await.unwrap());
+
+        repository.delete(&user_id).await.unwrap();
+        assert_eq!(None, repository.find(&user_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_update_conflict() {
+        let repository = InMemoryMfaRepository::new();
+        let saved = Mfa::enroll(UserId::new(), "secret".to_string()).activate(vec![], 100);
+        repository.save(saved.clone()).await.unwrap();
+
+        let used = saved.use_step(101).unwrap();
+        repository.update(used.clone(), &saved).await.unwrap();
+        assert_eq!(Some(used), repository.find(saved.user_id()).await.unwrap());
+
+        // 先に更新された設定を元にした更新は受け付けません
+        let result = repository.update(saved.use_step(102).unwrap(), &saved).await;
+        assert!(matches!(result, Err(MfaError::Conflict(_))));
+
+        let other = Mfa::enroll(UserId::new(), "secret".to_string());
+        assert!(matches!(repository.update(other.clone(), &other).await, Err(MfaError::Conflict(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    personal_access_token::{PersonalAccessToken, PersonalAccessTokenError},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryPersonalAccessTokenRepository {
+    tokens: Mutex<HashMap<(String, String), PersonalAccessToken>>,
+}
+
+impl InMemoryPersonalAccessTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::personal_access_token_repository::PersonalAccessTokenRepository
+    for InMemoryPersonalAccessTokenRepository
+{
+    fn find(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = Result<Option<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_,
+        >,
+    > {
+        let result = self
+            .tokens
+            .lock()
+            .map_err(|e| PersonalAccessTokenError::FindError(e.to_string()))
+            .map(|v| v.get(&(user_id.to_string(), token_id.to_string())).cloned());
+        Box::pin(async move { result })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_>,
+    > {
+        let result = self.tokens.lock().map_err(|e| PersonalAccessTokenError::FindError(e.to_string())).map(|v| {
+            let mut tokens: Vec<_> = v.values().filter(|v| v.user_id() == user_id).cloned().collect();
+            tokens.sort_by_key(|v| v.created_at());
+            tokens
+        });
+        Box::pin(async move { result })
+    }
+
+    fn save(
+        &self, token: PersonalAccessToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>> {
+        let result = self.tokens.lock().map_err(|e| PersonalAccessTokenError::SaveError(e.to_string())).map(|mut v| {
+            v.insert((token.user_id().to_string(), token.token_id().to_string()), token);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>> {
+        let result =
+            self.tokens.lock().map_err(|e| PersonalAccessTokenError::DeleteError(e.to_string())).and_then(|mut v| {
+                match v.remove(&(user_id.to_string(), token_id.to_string())) {
+                    Some(_) => Ok(()),
+                    None => Err(PersonalAccessTokenError::NotFound(token_id.to_string())),
+                }
+            });
+        Box::pin(async move { result })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, PersonalAccessTokenError>> + Send + '_>> {
+        let result =
+            self.tokens.lock().map_err(|e| PersonalAccessTokenError::DeleteError(e.to_string())).map(|mut v| {
+                let count = v.len();
+                v.retain(|_, v| v.user_id() != user_id);
+                count - v.len()
+            });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{
+        personal_access_token::Scope, repository::personal_access_token_repository::PersonalAccessTokenRepository,
+    };
+
+    use super::*;
+
+    fn token(user_id: &UserId, token_id: &str, created_at: i64) -> PersonalAccessToken {
+        PersonalAccessToken::new(
+            token_id.to_string(),
+            user_id.clone(),
+            "import".to_string(),
+            vec![Scope::SubscriptionsRead],
+            "hash".to_string(),
+            created_at,
+            created_at + 100,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_save_find_and_delete() {
+        let repository = InMemoryPersonalAccessTokenRepository::new();
+        let user_id = UserId::new();
+        let other = UserId::new();
+        repository.save(token(&user_id, "second", 2)).await.unwrap();
+        repository.save(token(&user_id, "first", 1)).await.unwrap();
+        repository.save(token(&other, "other", 0)).await.unwrap();
+
+        assert_eq!(Some(token(&user_id, "first", 1)), repository.find(&user_id, "first").await.unwrap());
+        assert_eq!(None, repository.find(&other, "first").await.unwrap());
+
+        let result = repository.find_by_user_id(&user_id).await.unwrap();
+        assert_eq!(vec!["first", "second"], result.iter().map(|v| v.token_id()).collect::<Vec<_>>());
+
+        repository.delete(&user_id, "first").await.unwrap();
+        assert_eq!(None, repository.find(&user_id, "first").await.unwrap());
+        let result = repository.delete(&user_id, "first").await;
+        assert!(matches!(result, Err(PersonalAccessTokenError::NotFound(_))));
+
+        assert_eq!(1, repository.delete_by_user_id(&user_id).await.unwrap());
+        assert_eq!(0, repository.delete_by_user_id(&user_id).await.unwrap());
+        assert!(repository.find_by_user_id(&user_id).await.unwrap().is_empty());
+        assert_eq!(Some(token(&other, "other", 0)), repository.find(&other, "other").await.unwrap());
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    refresh_token::{RefreshTokenError, RefreshTokenFamily},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryRefreshTokenRepository {
+    families: Mutex<HashMap<String, RefreshTokenFamily>>,
+}
+
+impl InMemoryRefreshTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::refresh_token_repository::RefreshTokenRepository for InMemoryRefreshTokenRepository {
+    fn find_by_family_id(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<RefreshTokenFamily, RefreshTokenError>> + Send + '_>>
+    {
+        let result =
+            self.families.lock().map_err(|e| RefreshTokenError::FindError(e.to_string())).and_then(|v| {
+                v.get(family_id).cloned().ok_or_else(|| RefreshTokenError::NotFound(family_id.to_string()))
+            });
+        Box::pin(async move { result })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<RefreshTokenFamily>, RefreshTokenError>> + Send + '_>,
+    > {
+        let result = self
+            .families
+            .lock()
+            .map_err(|e| RefreshTokenError::FindError(e.to_string()))
+            .map(|v| v.values().filter(|family| family.user_id() == user_id).cloned().collect::<Vec<_>>());
+        Box::pin(async move { result })
+    }
+
+    fn create(
+        &self, family: RefreshTokenFamily,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        let result = self.families.lock().map_err(|e| RefreshTokenError::SaveError(e.to_string())).and_then(|mut v| {
+            match v.contains_key(family.family_id()) {
+                true => Err(RefreshTokenError::SaveError(format!("already exists: {}", family.family_id()))),
+                false => {
+                    v.insert(family.family_id().to_string(), family);
+                    Ok(())
+                }
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn rotate(
+        &self, family: RefreshTokenFamily, expected_hash: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        let result = self.families.lock().map_err(|e| RefreshTokenError::SaveError(e.to_string())).and_then(|mut v| {
+            match v.get(family.family_id()) {
+                Some(current) if current.token_hash() == expected_hash && !current.revoked() => {
+                    v.insert(family.family_id().to_string(), family);
+                    Ok(())
+                }
+                _ => Err(RefreshTokenError::Conflict(family.family_id().to_string())),
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn revoke(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        let result = self.families.lock().map_err(|e| RefreshTokenError::SaveError(e.to_string())).and_then(|mut v| {
+            match v.get(family_id) {
+                Some(current) => {
+                    let revoked = current.revoke();
+                    v.insert(family_id.to_string(), revoked);
+                    Ok(())
+                }
+                None => Err(RefreshTokenError::NotFound(family_id.to_string())),
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, RefreshTokenError>> + Send + '_>> {
+        let result = self.families.lock().map_err(|e| RefreshTokenError::DeleteError(e.to_string())).map(|mut v| {
+            let count = v.len();
+            v.retain(|_, family| family.user_id() != user_id);
+            count - v.len()
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{refresh_token::Device, repository::refresh_token_repository::RefreshTokenRepository};
+
+    use super::*;
+
+    fn family() -> RefreshTokenFamily {
+        RefreshTokenFamily::new(
+            "family".to_string(),
+            UserId::new(),
+            "hash1".to_string(),
+            vec![],
+            "upstream".to_string(),
+            false,
+            Device::default(),
+            0,
+            0,
+            i64::MAX,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_success() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        repository.create(family()).await.unwrap();
+
+        let result = repository.find_by_family_id("family").await;
+        assert_eq!(family().token_hash(), result.unwrap().token_hash());
+        assert!(repository.create(family()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_user_id_success() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        let family = family();
+        repository.create(family.clone()).await.unwrap();
+
+        assert_eq!(vec![family.clone()], repository.find_by_user_id(family.user_id()).await.unwrap());
+        assert!(repository.find_by_user_id(&UserId::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_user_id_success() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        let family = family();
+        repository.create(family.clone()).await.unwrap();
+
+        assert_eq!(0, repository.delete_by_user_id(&UserId::new()).await.unwrap());
+        assert_eq!(1, repository.delete_by_user_id(family.user_id()).await.unwrap());
+        assert!(repository.find_by_user_id(family.user_id()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_not_found() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        let result = repository.find_by_family_id("family").await;
+        assert!(matches!(result, Err(RefreshTokenError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_conflict() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        repository.create(family()).await.unwrap();
+
+        let rotated = family().rotate("hash2".to_string(), "upstream".to_string());
+        assert!(repository.rotate(rotated.clone(), "hash1").await.is_ok());
+
+        let result = repository.rotate(rotated.rotate("hash3".to_string(), "upstream".to_string()), "hash1").await;
+        assert!(matches!(result, Err(RefreshTokenError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_success() {
+        let repository = InMemoryRefreshTokenRepository::new();
+        repository.create(family()).await.unwrap();
+        repository.revoke("family").await.unwrap();
+
+        assert!(repository.find_by_family_id("family").await.unwrap().revoked());
+
+        let rotated = family().rotate("hash2".to_string(), "upstream".to_string());
+        let result = repository.rotate(rotated, "hash1").await;
+        assert!(matches!(result, Err(RefreshTokenError::Conflict(_))));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use domain::{
+    clock::{Clock, SystemClock},
+    revoked_token::{RevokedToken, RevokedTokenError},
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// 保存時に有効期限を過ぎたトークンを破棄します。プロセスの再起動でデータは失われます
+#[derive(Debug)]
+pub struct InMemoryRevokedTokenRepository {
+    tokens: Mutex<HashMap<String, RevokedToken>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for InMemoryRevokedTokenRepository {
+    fn default() -> Self {
+        Self { tokens: Mutex::default(), clock: Arc::new(SystemClock) }
+    }
+}
+
+impl InMemoryRevokedTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl domain::repository::revoked_token_repository::RevokedTokenRepository for InMemoryRevokedTokenRepository {
+    fn save(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RevokedTokenError>> + Send + '_>> {
+        let result = self.tokens.lock().map_err(|e| RevokedTokenError::SaveError(e.to_string())).map(|mut v| {
+            let now = self.clock.now();
+            v.retain(|_, token| !token.is_expired(now));
+            v.insert(token.jti().to_string(), token);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn revoke_once(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>> {
+        let result = self.tokens.lock().map_err(|e| RevokedTokenError::SaveError(e.to_string())).map(|mut v| {
+            let now = self.clock.now();
+            v.retain(|_, token| !token.is_expired(now));
+            match v.contains_key(token.jti()) {
+                true => false,
+                false => {
+                    v.insert(token.jti().to_string(), token);
+                    true
+                }
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn is_revoked(
+        &self, jti: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>> {
+        let result = self
+            .tokens
+            .lock()
+            .map_err(|e| RevokedTokenError::FindError(e.to_string()))
+            .map(|v| v.get(jti).is_some_and(|token| !token.is_expired(self.clock.now())));
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{clock::FixedClock, repository::revoked_token_repository::RevokedTokenRepository};
+
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[tokio::test]
+    async fn test_save_and_is_revoked() {
+        let repository = InMemoryRevokedTokenRepository::new().with_clock(Arc::new(FixedClock(NOW)));
+        assert!(!repository.is_revoked("jti").await.unwrap());
+
+        repository.save(RevokedToken::new("jti".to_string(), NOW + 3600)).await.unwrap();
+        assert!(repository.is_revoked("jti").await.unwrap());
+        assert!(!repository.is_revoked("other").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_once() {
+        let repository = InMemoryRevokedTokenRepository::new().with_clock(Arc::new(FixedClock(NOW)));
+
+        assert!(repository.revoke_once(RevokedToken::new("jti".to_string(), NOW + 300)).await.unwrap());
+        assert!(!repository.revoke_once(RevokedToken::new("jti".to_string(), NOW + 300)).await.unwrap());
+        assert!(repository.is_revoked("jti").await.unwrap());
+
+        // 有効期限を過ぎた記録は無効化されていないものとして扱います
+        repository.save(RevokedToken::new("expired".to_string(), NOW - 1)).await.unwrap();
+        assert!(repository.revoke_once(RevokedToken::new("expired".to_string(), NOW + 300)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_discarded() {
+        let repository = InMemoryRevokedTokenRepository::new().with_clock(Arc::new(FixedClock(NOW)));
+        repository.save(RevokedToken::new("expired".to_string(), NOW - 1)).await.unwrap();
+        assert!(!repository.is_revoked("expired").await.unwrap());
+
+        repository.save(RevokedToken::new("jti".to_string(), NOW + 3600)).await.unwrap();
+        assert!(!repository.tokens.lock().unwrap().contains_key("expired"));
+    }
+}
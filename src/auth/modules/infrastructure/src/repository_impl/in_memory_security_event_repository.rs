@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use domain::{
+    security_event::{SecurityEvent, SecurityEventError, SecurityEventType},
+    user::user_id::UserId,
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemorySecurityEventRepository {
+    events: Mutex<Vec<SecurityEvent>>,
+}
+
+impl InMemorySecurityEventRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(
+        &self, from: i64, to: i64, limit: usize, filter: impl Fn(&SecurityEvent) -> bool,
+    ) -> Result<Vec<SecurityEvent>, SecurityEventError> {
+        self.events.lock().map_err(|e| SecurityEventError::FindError(e.to_string())).map(|v| {
+            let mut events: Vec<_> =
+                v.iter().filter(|v| from <= v.occurred_at() && v.occurred_at() < to && filter(v)).cloned().collect();
+            // 同じ日時のイベントは記録した順の逆に並べます
+            events.reverse();
+            events.sort_by_key(|v| std::cmp::Reverse(v.occurred_at()));
+            events.truncate(limit);
+            events
+        })
+    }
+}
+
+impl domain::repository::security_event_repository::SecurityEventRepository for InMemorySecurityEventRepository {
+    fn save(
+        &self, event: SecurityEvent,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), SecurityEventError>> + Send + '_>> {
+        let result = self.events.lock().map_err(|e| SecurityEventError::SaveError(e.to_string())).map(|mut v| {
+            v.push(event);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId, event_type: Option<SecurityEventType>, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>
+    {
+        let result = self.find(from, to, limit, |v| {
+            v.user_id() == Some(user_id) && event_type.map_or(true, |event_type| v.event_type() == event_type)
+        });
+        Box::pin(async move { result })
+    }
+
+    fn find_by_event_type(
+        &self, event_type: SecurityEventType, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>
+    {
+        let result = self.find(from, to, limit, |v| v.event_type() == event_type);
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::repository::security_event_repository::SecurityEventRepository;
+
+    use super::*;
+
+    fn ids(events: &[SecurityEvent]) -> String {
+        events.iter().map(|v| v.event_id()).collect::<Vec<_>>().join(" ")
+    }
+
+    fn event(event_id: &str, event_type: SecurityEventType, occurred_at: i64) -> SecurityEvent {
+        SecurityEvent::occurred(event_id.to_string(), event_type, occurred_at, "192.0.2.1".to_string(), "".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_save_and_find() {
+        let repository = InMemorySecurityEventRepository::new();
+        let user_id = UserId::new();
+        let events = vec![
+            event("1", SecurityEventType::SignUp, 100).with_user_id(user_id.clone()),
+            event("2", SecurityEventType::LoginFailed, 200).with_email("hoge@email.com".to_string()),
+            event("3", SecurityEventType::LoginSucceeded, 200).with_user_id(user_id.clone()),
+            event("4", SecurityEventType::LoginFailed, 300).with_user_id(UserId::new()),
+            event("5", SecurityEventType::LoginSucceeded, 400).with_user_id(user_id.clone()),
+        ];
+        for event in events {
+            repository.save(event).await.unwrap();
+        }
+
+        let test_cases = vec![
+            (0, 1000, 10, "5 3 1"),
+            (100, 400, 10, "3 1"),
+            (0, 1000, 2, "5 3"),
+            (500, 1000, 10, ""),
+        ];
+        for (from, to, limit, expected) in test_cases {
+            let result = repository.find_by_user_id(&user_id, None, from, to, limit).await.unwrap();
+            assert_eq!(expected, ids(&result), "{} {} {}", from, to, limit);
+        }
+        let result =
+            repository.find_by_user_id(&user_id, Some(SecurityEventType::LoginSucceeded), 0, 1000, 10).await.unwrap();
+        assert_eq!("5 3", ids(&result));
+
+        let result = repository.find_by_event_type(SecurityEventType::LoginFailed, 0, 1000, 10).await.unwrap();
+        assert_eq!("4 2", ids(&result));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::{
+    user::user_id::UserId,
+    user_data::{UserDataError, UserDataItem},
+};
+
+/// ローカル環境とテスト用のインメモリ実装です
+#[derive(Debug, Default)]
+pub struct InMemoryUserDataRepository {
+    target: String,
+    items: Mutex<HashMap<String, Vec<UserDataItem>>>,
+}
+
+impl InMemoryUserDataRepository {
+    pub fn new(target: &str) -> Self {
+        Self { target: target.to_string(), ..Self::default() }
+    }
+
+    /// ユーザーのデータを追加します
+    pub fn insert(&self, user_id: &UserId, items: Vec<UserDataItem>) {
+        self.items.lock().unwrap().entry(user_id.to_string()).or_default().extend(items);
+    }
+
+    pub fn count(&self, user_id: &UserId) -> usize {
+        self.items.lock().unwrap().get(&user_id.to_string()).map(Vec::len).unwrap_or_default()
+    }
+}
+
+impl domain::repository::user_data_repository::UserDataRepository for InMemoryUserDataRepository {
+    fn target(&self) -> &str {
+        &self.target
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<UserDataItem>, UserDataError>> + Send + '_>>
+    {
+        let result = self
+            .items
+            .lock()
+            .map_err(|e| UserDataError::FindError(e.to_string()))
+            .map(|v| v.get(&user_id.to_string()).cloned().unwrap_or_default());
+        Box::pin(async move { result })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, UserDataError>> + Send + '_>> {
+        let result = self
+            .items
+            .lock()
+            .map_err(|e| UserDataError::DeleteError(e.to_string()))
+            .map(|mut v| v.remove(&user_id.to_string()).map(|v| v.len()).unwrap_or_default());
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{repository::user_data_repository::UserDataRepository, user_data::FieldValue};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_find_and_delete_user_data() {
+        let repository = InMemoryUserDataRepository::new("subscribe");
+        let user_id = UserId::new();
+        let other = UserId::new();
+        let item = UserDataItem::from([("subscribe_name".to_string(), FieldValue::String("Netflix".to_string()))]);
+        repository.insert(&user_id, vec![item.clone(); 3]);
+        repository.insert(&other, vec![item.clone()]);
+
+        assert_eq!("subscribe", repository.target());
+        assert_eq!(vec![item; 3], repository.find_by_user_id(&user_id).await.unwrap());
+        assert_eq!(3, repository.delete_by_user_id(&user_id).await.unwrap());
+        assert_eq!(0, repository.delete_by_user_id(&user_id).await.unwrap());
+        assert!(repository.find_by_user_id(&user_id).await.unwrap().is_empty());
+        assert_eq!(1, repository.count(&other));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use domain::user::{email::Email, user_error::UserError, user_id::UserId, User};
+
+/// ローカル環境とテスト用のインメモリ実装です
+///
+/// プロセスの再起動でデータは失われます
+#[derive(Debug, Default)]
+pub struct InMemoryUserRepository {
+    users: Mutex<HashMap<String, User>>,
+}
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl domain::repository::user_repository::UserRepository for InMemoryUserRepository {
+    fn find_by_id(
+        &self, id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>> {
+        let result = self
+            .users
+            .lock()
+            .map_err(|e| UserError::FindByIdError(e.to_string()))
+            .and_then(|v| v.get(&id.to_string()).cloned().ok_or_else(|| UserError::NotFound(id.to_string())));
+        Box::pin(async move { result })
+    }
+
+    fn find_by_email(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<User, UserError>> + Send + '_>> {
+        let result = self.users.lock().map_err(|e| UserError::FindByEmailError(e.to_string())).and_then(|v| {
+            v.values().find(|user| user.email() == email).cloned().ok_or_else(|| UserError::NotFound(email.to_string()))
+        });
+        Box::pin(async move { result })
+    }
+
+    fn exists_by_email(
+        &self, email: &Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, UserError>> + Send + '_>> {
+        let result = self
+            .users
+            .lock()
+            .map_err(|e| UserError::FindByEmailError(e.to_string()))
+            .map(|v| v.values().any(|user| user.email() == email));
+        Box::pin(async move { result })
+    }
+
+    fn create(
+        &self, user: User,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+        let result = self.users.lock().map_err(|e| UserError::CreateUserError(e.to_string())).and_then(|mut v| {
+            let key = user.user_id().to_string();
+            if v.contains_key(&key) {
+                return Err(UserError::CreateUserError(key));
+            }
+            v.insert(key, user);
+            Ok(())
+        });
+        Box::pin(async move { result })
+    }
+
+    fn update(
+        &self, user: User,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+        let result = self.users.lock().map_err(|e| UserError::UpdateUserError(e.to_string())).and_then(|mut v| {
+            let key = user.user_id().to_string();
+            if !v.contains_key(&key) {
+                return Err(UserError::UpdateUserError(key));
+            }
+            v.insert(key, user);
+            Ok(())
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete(
+        &self, id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), UserError>> + Send + '_>> {
+        let result = self.users.lock().map_err(|e| UserError::DeleteUserError(e.to_string())).map(|mut v| {
+            v.remove(&id.to_string());
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{repository::user_repository::UserRepository, user::role::Role};
+
+    use super::*;
+
+    const USER_ID: &str = "usr_550e8400-e29b-41d4-a716-446655440000";
+
+    #[tokio::test]
+    async fn test_create_find_and_update() {
+        let repository = InMemoryUserRepository::new();
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap();
+        assert!(matches!(repository.find_by_id(user.user_id()).await, Err(UserError::NotFound(_))));
+
+        repository.create(user.clone()).await.unwrap();
+        assert!(repository.create(user.clone()).await.is_err());
+        assert_eq!(Role::User, repository.find_by_id(user.user_id()).await.unwrap().role());
+
+        repository.update(user.clone().with_role(Role::Admin)).await.unwrap();
+        assert_eq!(Role::Admin, repository.find_by_id(user.user_id()).await.unwrap().role());
+
+        let other = User::build("usr_123e4567-e89b-12d3-a456-426614174000", "fuga@email.com", "fuga", 1, None).unwrap();
+        assert!(repository.update(other).await.is_err());
+
+        repository.delete(user.user_id()).await.unwrap();
+        assert!(matches!(repository.find_by_id(user.user_id()).await, Err(UserError::NotFound(_))));
+        assert!(repository.delete(user.user_id()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email() {
+        let repository = InMemoryUserRepository::new();
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap();
+        repository.create(user.clone()).await.unwrap();
+
+        assert_eq!(user.user_id(), repository.find_by_email(user.email()).await.unwrap().user_id());
+        assert!(repository.exists_by_email(user.email()).await.unwrap());
+
+        let other: Email = "fuga@email.com".parse().unwrap();
+        assert!(matches!(repository.find_by_email(&other).await, Err(UserError::NotFound(_))));
+        assert!(!repository.exists_by_email(&other).await.unwrap());
+    }
+}
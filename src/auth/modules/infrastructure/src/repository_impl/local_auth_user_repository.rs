@@ -0,0 +1,1167 @@
+use std::sync::{Arc, OnceLock};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::Engine;
+use domain::{
+    auth_user::{AuthUserError, FederatedSignIn, GuestSignUp},
+    clock::{Clock, SystemClock},
+    credential::{Credential, CredentialError, EmailChange, VerificationCode},
+    repository::credential_repository::CredentialRepository,
+    token::Token,
+    user::{email::Email, password::Password, user_id::UserId},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::code_sender::CodeSender;
+
+const ACCESS_TOKEN_TTL: i64 = 60 * 60;
+const REFRESH_TOKEN_TTL: i64 = 30 * 24 * 60 * 60;
+const VERIFICATION_CODE_TTL: i64 = 24 * 60 * 60;
+const RESET_CODE_TTL: i64 = 60 * 60;
+const EMAIL_CHANGE_CODE_TTL: i64 = 60 * 60;
+
+const ACCESS: &str = "access";
+const ID: &str = "id";
+const REFRESH: &str = "refresh";
+
+/// Cognitoを使わずにパスワード認証する`AuthUserRepository`の実装です
+///
+/// パスワードはargon2idでハッシュ化して保存し、Cognitoと同じ形式のJWTを自前の鍵で署名します
+pub struct LocalAuthUserRepository {
+    credentials: Arc<dyn CredentialRepository>,
+    code_sender: Arc<dyn CodeSender>,
+    tokens: LocalTokenIssuer,
+    /// 存在しないユーザーでも照合にかかる時間を揃えるためのハッシュです
+    dummy_hash: OnceLock<String>,
+    clock: Arc<dyn Clock>,
+}
+
+/// 自前の認証で使うJWTを発行、検証します
+pub struct LocalTokenIssuer {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    key_id: String,
+    issuer: String,
+    audience: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalClaims {
+    sub: String,
+    iss: String,
+    client_id: String,
+    token_use: String,
+    username: String,
+    /// IDトークンのみに含めます
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    jti: String,
+    iat: i64,
+    exp: i64,
+}
+
+impl LocalAuthUserRepository {
+    pub fn new(
+        credentials: Arc<dyn CredentialRepository>, code_sender: Arc<dyn CodeSender>, tokens: LocalTokenIssuer,
+    ) -> Self {
+        Self { credentials, code_sender, tokens, dummy_hash: OnceLock::new(), clock: Arc::new(SystemClock) }
+    }
+
+    /// 確認コードとトークンの有効期限に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    async fn find(&self, email: &Email) -> Result<Credential, AuthUserError> {
+        match self.credentials.find_by_email(email).await.map_err(map_credential_error)? {
+            // TTLによる削除は遅延するため、期限切れのゲストユーザーが残っている場合があります
+            credential if credential.is_expired(self.clock.now()) => {
+                Err(AuthUserError::AuthenticationFailed("User does not exist.".to_string()))
+            }
+            credential => Ok(credential),
+        }
+    }
+
+    /// 認証情報を`email`をキーとする認証情報に移し、変更前の認証情報を削除します
+    ///
+    /// 変更前の認証情報を削除できなかった場合は、作成した認証情報を削除して元に戻します
+    async fn move_credential(&self, credential: &Credential, email: Email) -> Result<(), AuthUserError> {
+        let moved = credential.change_email(email, self.clock.now());
+        self.credentials.create(moved.clone()).await.map_err(|e| {
+            let error = map_credential_error(e);
+            tracing::error!("{:?}", error);
+            error
+        })?;
+
+        if let Err(e) = self.credentials.delete(credential.email()).await {
+            if let Err(e) = self.credentials.delete(moved.email()).await {
+                tracing::error!("failed to roll back moved credential. user_id: {}, {:?}", moved.user_id(), e);
+            }
+            return Err(map_credential_error(e));
+        }
+        Ok(())
+    }
+
+    /// 誤った確認コードの入力を記録した認証情報を保存し、返すエラーを作成します
+    ///
+    /// 誤った回数が上限に達してコードを破棄した場合は、コードの再発行を促すエラーを返します
+    async fn reject_code(&self, failed: Credential, discarded: bool) -> AuthUserError {
+        if let Err(e) = self.credentials.update(failed).await {
+            return map_credential_error(e);
+        }
+        match discarded {
+            true => {
+                AuthUserError::AuthenticationFailed("Invalid code provided, please request a code again.".to_string())
+            }
+            false => AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string()),
+        }
+    }
+
+    fn dummy_hash(&self) -> &str {
+        self.dummy_hash.get_or_init(|| hash_password(&uuid::Uuid::new_v4().to_string()).unwrap_or_default())
+    }
+}
+
+impl LocalTokenIssuer {
+    /// RS256の秘密鍵と公開鍵をPEM形式で受け取ります
+    ///
+    /// `key_id`はJWKSの`kid`と一致させてください
+    pub fn from_pem(
+        private_key: &[u8], public_key: &[u8], key_id: String, issuer: String, audience: String,
+    ) -> Result<Self, AuthUserError> {
+        let encoding_key =
+            EncodingKey::from_rsa_pem(private_key).map_err(|e| AuthUserError::InternalServerError(e.to_string()))?;
+        let decoding_key =
+            DecodingKey::from_rsa_pem(public_key).map_err(|e| AuthUserError::InternalServerError(e.to_string()))?;
+        Ok(Self { encoding_key, decoding_key, key_id, issuer, audience })
+    }
+
+    fn issue(&self, credential: &Credential, now: i64) -> Result<Token, AuthUserError> {
+        let access_token = self.sign(credential, ACCESS, now, ACCESS_TOKEN_TTL)?;
+        let id_token = self.sign(credential, ID, now, ACCESS_TOKEN_TTL)?;
+        let refresh_token = self.sign(credential, REFRESH, now, REFRESH_TOKEN_TTL)?;
+        Ok(Token::new(access_token.into(), refresh_token.into(), now, ACCESS_TOKEN_TTL as u64)
+            .with_id_token(Some(id_token.into())))
+    }
+
+    fn sign(&self, credential: &Credential, token_use: &str, now: i64, ttl: i64) -> Result<String, AuthUserError> {
+        let claims = LocalClaims {
+            sub: sub(credential.user_id()),
+            iss: self.issuer.clone(),
+            client_id: self.audience.clone(),
+            token_use: token_use.to_string(),
+            username: credential.email().to_string(),
+            email: (token_use == ID).then(|| credential.email().to_string()),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now,
+            exp: now + ttl,
+        };
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.key_id.clone());
+
+        jsonwebtoken::encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| AuthUserError::InternalServerError(e.to_string()))
+    }
+
+    fn verify(&self, token: &str, token_use: &str) -> Result<LocalClaims, AuthUserError> {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&[
+            "exp", "iss", "sub",
+        ]);
+        validation.validate_aud = false;
+
+        let claims = jsonwebtoken::decode::<LocalClaims>(token, &self.decoding_key, &validation)
+            .map_err(|e| AuthUserError::AuthenticationFailed(e.to_string()))?
+            .claims;
+        if claims.token_use != token_use || claims.client_id != self.audience {
+            return Err(AuthUserError::AuthenticationFailed(format!("Invalid token_use: {}", claims.token_use)));
+        }
+        Ok(claims)
+    }
+}
+
+/// `CredentialError`を`AuthUserError`に変換します
+///
+/// 存在しないユーザーはCognitoと同様に認証失敗として扱います
+fn map_credential_error(e: CredentialError) -> AuthUserError {
+    match e {
+        CredentialError::NotFound(_) => AuthUserError::AuthenticationFailed("User does not exist.".to_string()),
+        CredentialError::AlreadyExists(_) => AuthUserError::UserAlreadyExists,
+        CredentialError::FindError(e) | CredentialError::SaveError(e) | CredentialError::DeleteError(e) => {
+            AuthUserError::InternalServerError(e)
+        }
+    }
+}
+
+/// Cognitoの`sub`と同様に、`UserId`のUUID部分を`sub`にします
+fn sub(user_id: &UserId) -> String {
+    let user_id = user_id.to_string();
+    match user_id.split_once('_') {
+        Some((_, uuid)) => uuid.to_string(),
+        None => user_id,
+    }
+}
+
+fn hash_password(password: &str) -> Result<String, AuthUserError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|v| v.to_string())
+        .map_err(|e| AuthUserError::InternalServerError(e.to_string()))
+}
+
+/// パスワードでログインできないよう、誰も知らないパスワードのハッシュを作成します
+///
+/// パスワードを設定する場合はパスワードの再設定を使います
+fn unusable_password_hash() -> Result<String, AuthUserError> {
+    hash_password(&uuid::Uuid::new_v4().to_string())
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|v| Argon2::default().verify_password(password.as_bytes(), &v).is_ok())
+        .unwrap_or(false)
+}
+
+fn generate_code() -> String {
+    format!("{:06}", rand::rngs::OsRng.gen_range(0..1_000_000))
+}
+
+fn hash_code(code: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(code.as_bytes()))
+}
+
+impl domain::repository::auth_user_repository::AuthUserRepository for LocalAuthUserRepository {
+    fn authenticate(
+        &self, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Token, AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let password = auth.password().expose_secret();
+            let credential = match self.find(auth.email()).await {
+                Ok(v) => v,
+                Err(AuthUserError::AuthenticationFailed(_)) => {
+                    verify_password(password, self.dummy_hash());
+                    return Err(AuthUserError::AuthenticationFailed("Incorrect username or password.".to_string()));
+                }
+                Err(e) => return Err(e),
+            };
+
+            if !verify_password(password, credential.password_hash()) {
+                return Err(AuthUserError::AuthenticationFailed("Incorrect username or password.".to_string()));
+            }
+            if !credential.confirmed() {
+                return Err(AuthUserError::AuthenticationFailed("User is not confirmed.".to_string()));
+            }
+
+            self.tokens.issue(&credential, self.clock.now())
+        })
+    }
+
+    fn sign_up(
+        &self, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<UserId, AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let user_id = UserId::new();
+            let code = generate_code();
+            let credential = Credential::new(
+                user_id.clone(),
+                auth.email().clone(),
+                hash_password(auth.password().expose_secret())?,
+                false,
+                Some(VerificationCode::new(hash_code(&code), self.clock.now() + VERIFICATION_CODE_TTL)),
+                None,
+                0,
+            );
+
+            self.credentials.create(credential).await.map_err(|e| {
+                let error = map_credential_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            self.code_sender.send_verification_code(auth.email(), &code).await?;
+
+            Ok(user_id)
+        })
+    }
+
+    fn verify_code(
+        &self, email: Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+            if credential.confirmed() {
+                return Err(AuthUserError::AuthenticationFailed(
+                    "User cannot be confirmed. Current status is CONFIRMED".to_string(),
+                ));
+            }
+
+            match credential.verification_code() {
+                Some(v) if v.is_expired(self.clock.now()) => Err(AuthUserError::AuthenticationFailed(
+                    "Invalid code provided, please request a code again.".to_string(),
+                )),
+                Some(v) if v.code_hash() == hash_code(&code) => {
+                    self.credentials.update(credential.confirm()).await.map_err(map_credential_error)
+                }
+                Some(_) => {
+                    let failed = credential.fail_verification();
+                    let discarded = failed.verification_code().is_none();
+                    Err(self.reject_code(failed, discarded).await)
+                }
+                None => Err(AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string())),
+            }
+        })
+    }
+
+    fn refresh(
+        &self, email: Email, refresh_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Token, AuthUserError>> + Send + '_>> {
+        let refresh_token = refresh_token.to_string();
+
+        Box::pin(async move {
+            let claims = self.tokens.verify(&refresh_token, REFRESH)?;
+            let credential = self.find(&email).await?;
+
+            // サインアウトより前に発行されたリフレッシュトークンは使用できません
+            if claims.username != credential.email().value()
+                || claims.sub != sub(credential.user_id())
+                || claims.iat < credential.signed_out_at()
+            {
+                return Err(AuthUserError::AuthenticationFailed("Invalid Refresh Token".to_string()));
+            }
+
+            let token = self.tokens.issue(&credential, self.clock.now())?;
+            Ok(Token::new(token.access_token().clone(), refresh_token.into(), token.issued_at(), token.expires_in())
+                .with_id_token(token.id_token().cloned()))
+        })
+    }
+
+    fn forgot_password(
+        &self, email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+            let code = generate_code();
+            let reset_code = VerificationCode::new(hash_code(&code), self.clock.now() + RESET_CODE_TTL);
+
+            self.credentials
+                .update(credential.request_password_reset(reset_code))
+                .await
+                .map_err(map_credential_error)?;
+            self.code_sender.send_password_reset_code(&email, &code).await
+        })
+    }
+
+    fn confirm_forgot_password(
+        &self, email: Email, code: &str, new_password: Password,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+
+            match credential.reset_code() {
+                Some(v) if v.is_expired(self.clock.now()) => Err(AuthUserError::AuthenticationFailed(
+                    "Invalid code provided, please request a code again.".to_string(),
+                )),
+                Some(v) if v.code_hash() == hash_code(&code) => {
+                    let password_hash = hash_password(new_password.expose_secret())?;
+                    self.credentials
+                        .update(credential.reset_password(password_hash, self.clock.now()))
+                        .await
+                        .map_err(map_credential_error)
+                }
+                Some(_) => {
+                    let failed = credential.fail_password_reset();
+                    let discarded = failed.reset_code().is_none();
+                    Err(self.reject_code(failed, discarded).await)
+                }
+                None => Err(AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string())),
+            }
+        })
+    }
+
+    fn sign_out(
+        &self, access_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let access_token = access_token.to_string();
+
+        Box::pin(async move {
+            let claims = self.tokens.verify(&access_token, ACCESS)?;
+            let email = claims
+                .username
+                .parse::<Email>()
+                .map_err(|e| AuthUserError::AuthenticationFailed(format!("{}: {}", e, claims.username)))?;
+            let credential = self.find(&email).await?;
+
+            self.credentials.update(credential.sign_out(self.clock.now())).await.map_err(map_credential_error)
+        })
+    }
+
+    fn federated_sign_in(
+        &self, email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<FederatedSignIn, AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let (credential, created) = match self.credentials.find_by_email(&email).await {
+                Ok(credential) if credential.confirmed() => (credential, false),
+                // 確認前のパスワードは第三者が登録した可能性があるため、推測できないパスワードに置き換えます
+                Ok(credential) => {
+                    let credential = credential.reset_password(unusable_password_hash()?, self.clock.now()).confirm();
+                    self.credentials.update(credential.clone()).await.map_err(map_credential_error)?;
+                    (credential, false)
+                }
+                Err(CredentialError::NotFound(_)) => {
+                    let credential =
+                        Credential::new(UserId::new(), email, unusable_password_hash()?, true, None, None, 0);
+                    self.credentials.create(credential.clone()).await.map_err(|e| {
+                        let error = map_credential_error(e);
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+                    (credential, true)
+                }
+                Err(e) => return Err(map_credential_error(e)),
+            };
+
+            let token = self.tokens.issue(&credential, self.clock.now())?;
+            Ok(FederatedSignIn::new(credential.user_id().clone(), token, created))
+        })
+    }
+
+    fn sign_up_guest(
+        &self, user_id: UserId, email: Email, expires_at: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<GuestSignUp, AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let credential = Credential::guest(user_id.clone(), email, unusable_password_hash()?, expires_at);
+            self.credentials.create(credential.clone()).await.map_err(|e| {
+                let error = map_credential_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+
+            Ok(GuestSignUp::new(user_id, self.tokens.issue(&credential, self.clock.now())?))
+        })
+    }
+
+    fn upgrade_guest(
+        &self, guest_email: Email, auth: domain::auth_user::AuthUser,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let guest = self.find(&guest_email).await?;
+            if guest.expires_at().is_none() {
+                return Err(AuthUserError::AuthenticationFailed("User is not a guest.".to_string()));
+            }
+
+            let code = generate_code();
+            let credential = Credential::new(
+                guest.user_id().clone(),
+                auth.email().clone(),
+                hash_password(auth.password().expose_secret())?,
+                false,
+                Some(VerificationCode::new(hash_code(&code), self.clock.now() + VERIFICATION_CODE_TTL)),
+                None,
+                0,
+            );
+            self.credentials.create(credential).await.map_err(|e| {
+                let error = map_credential_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            // ゲストユーザーとして発行したリフレッシュトークンも、認証情報と一緒に使用できなくなります
+            self.credentials.delete(&guest_email).await.map_err(map_credential_error)?;
+            self.code_sender.send_verification_code(auth.email(), &code).await
+        })
+    }
+
+    /// 認証情報はメールアドレスで探せるため、`user_id`は認証情報の持ち主の確認に使います
+    fn verify_guest_upgrade(
+        &self, user_id: UserId, email: Email, code: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            if self.find(&email).await?.user_id() != &user_id {
+                return Err(AuthUserError::AuthenticationFailed("Incorrect username or password.".to_string()));
+            }
+            self.verify_code(email, &code).await
+        })
+    }
+
+    /// 削除したゲストユーザーの認証情報を作成し直してから、登録したメールアドレスの認証情報を削除します
+    fn revert_guest_upgrade(
+        &self, guest_email: Email, email: Email, expires_at: i64,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+            let guest =
+                Credential::guest(credential.user_id().clone(), guest_email, unusable_password_hash()?, expires_at);
+            self.credentials.create(guest).await.map_err(|e| {
+                let error = map_credential_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            self.credentials.delete(&email).await.map_err(map_credential_error)
+        })
+    }
+
+    fn request_email_change(
+        &self, email: Email, new_email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+            match self.credentials.find_by_email(&new_email).await {
+                Ok(_) => return Err(AuthUserError::UserAlreadyExists),
+                Err(CredentialError::NotFound(_)) => {}
+                Err(e) => return Err(map_credential_error(e)),
+            }
+
+            let code = generate_code();
+            let email_change = EmailChange::new(
+                new_email.clone(),
+                VerificationCode::new(hash_code(&code), self.clock.now() + EMAIL_CHANGE_CODE_TTL),
+            );
+            self.credentials
+                .update(credential.request_email_change(email_change))
+                .await
+                .map_err(map_credential_error)?;
+            self.code_sender.send_verification_code(&new_email, &code).await?;
+            self.code_sender.send_email_change_notice(&email, &new_email).await
+        })
+    }
+
+    fn confirm_email_change(
+        &self, email: Email, code: &str, _access_token: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Email, AuthUserError>> + Send + '_>> {
+        let code = code.to_string();
+
+        Box::pin(async move {
+            let credential = self.find(&email).await?;
+            let new_email = match credential.email_change() {
+                Some(v) if v.code().is_expired(self.clock.now()) => Err(AuthUserError::AuthenticationFailed(
+                    "Invalid code provided, please request a code again.".to_string(),
+                )),
+                Some(v) if v.code().code_hash() == hash_code(&code) => Ok(v.new_email().clone()),
+                Some(_) => {
+                    let failed = credential.fail_email_change();
+                    let discarded = failed.email_change().is_none();
+                    Err(self.reject_code(failed, discarded).await)
+                }
+                None => Err(AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string())),
+            }?;
+
+            self.move_credential(&credential, new_email.clone()).await?;
+            Ok(new_email)
+        })
+    }
+
+    fn revert_email_change(
+        &self, new_email: Email, email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            let credential = self.find(&new_email).await?;
+            self.move_credential(&credential, email).await
+        })
+    }
+
+    fn delete_user(
+        &self, email: Email,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+        Box::pin(async move {
+            self.credentials.delete(&email).await.map_err(|e| {
+                let error = map_credential_error(e);
+                tracing::error!("{:?}", error);
+                error
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Mutex};
+
+    use domain::{
+        auth_user::AuthUser, credential::MAX_CODE_ATTEMPTS, repository::auth_user_repository::AuthUserRepository,
+    };
+
+    use crate::repository_impl::in_memory_credential_repository::InMemoryCredentialRepository;
+
+    use super::*;
+
+    const PRIVATE_KEY: &[u8] = include_bytes!("../../testdata/jwt/private_key.pem");
+    const PUBLIC_KEY: &[u8] = include_bytes!("../../testdata/jwt/public_key.pem");
+    const EMAIL: &str = "hoge@email.com";
+    const PASSWORD: &str = "Password123";
+
+    /// 送信した確認コードを記録します
+    #[derive(Default)]
+    struct RecordingCodeSender {
+        codes: Mutex<Vec<(String, String)>>,
+        reset_codes: Mutex<Vec<(String, String)>>,
+        notices: Mutex<Vec<(String, String)>>,
+    }
+
+    impl RecordingCodeSender {
+        fn last_code(&self) -> String {
+            self.codes.lock().unwrap().last().map(|(_, code)| code.clone()).unwrap()
+        }
+
+        fn last_reset_code(&self) -> String {
+            self.reset_codes.lock().unwrap().last().map(|(_, code)| code.clone()).unwrap()
+        }
+    }
+
+    impl CodeSender for RecordingCodeSender {
+        fn send_verification_code(
+            &self, email: &Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.codes.lock().unwrap().push((email.to_string(), code.to_string()));
+            Box::pin(async { Ok(()) })
+        }
+
+        fn send_password_reset_code(
+            &self, email: &Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.reset_codes.lock().unwrap().push((email.to_string(), code.to_string()));
+            Box::pin(async { Ok(()) })
+        }
+
+        fn send_email_change_notice(
+            &self, email: &Email, new_email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.notices.lock().unwrap().push((email.to_string(), new_email.to_string()));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn issuer() -> LocalTokenIssuer {
+        LocalTokenIssuer::from_pem(
+            PRIVATE_KEY,
+            PUBLIC_KEY,
+            "test-key".to_string(),
+            "http://localhost".to_string(),
+            "client_id".to_string(),
+        )
+        .unwrap()
+    }
+
+    fn repository() -> (LocalAuthUserRepository, Arc<RecordingCodeSender>, Arc<InMemoryCredentialRepository>) {
+        let credentials = Arc::new(InMemoryCredentialRepository::new());
+        let code_sender = Arc::new(RecordingCodeSender::default());
+        (LocalAuthUserRepository::new(credentials.clone(), code_sender.clone(), issuer()), code_sender, credentials)
+    }
+
+    fn auth(password: &str) -> AuthUser {
+        AuthUser::build(EMAIL, password).unwrap()
+    }
+
+    fn email() -> Email {
+        EMAIL.parse().unwrap()
+    }
+
+    async fn signed_up_and_verified() -> LocalAuthUserRepository {
+        signed_up_and_verified_with_sender().await.0
+    }
+
+    async fn signed_up_and_verified_with_sender() -> (LocalAuthUserRepository, Arc<RecordingCodeSender>) {
+        let (repository, code_sender, _) = repository();
+        repository.sign_up(auth(PASSWORD)).await.unwrap();
+        repository.verify_code(email(), &code_sender.last_code()).await.unwrap();
+        (repository, code_sender)
+    }
+
+    fn decode(token: &str) -> LocalClaims {
+        issuer()
+            .verify(token, ACCESS)
+            .or_else(|_| issuer().verify(token, ID))
+            .or_else(|_| issuer().verify(token, REFRESH))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_verify_and_authenticate_success() {
+        let (repository, code_sender, credentials) = repository();
+
+        let user_id = repository.sign_up(auth(PASSWORD)).await.unwrap();
+        let credential = credentials.find_by_email(&email()).await.unwrap();
+        assert_eq!(&user_id, credential.user_id());
+        assert!(!credential.password_hash().contains(PASSWORD));
+        assert!(credential.password_hash().starts_with("$argon2id$"));
+        assert!(!credential.confirmed());
+
+        let code = code_sender.last_code();
+        assert_eq!(6, code.len());
+        assert_ne!(code, credential.verification_code().unwrap().code_hash());
+
+        let result = repository.authenticate(auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        repository.verify_code(email(), &code).await.unwrap();
+
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+        let claims = decode(token.access_token().expose_secret());
+        assert_eq!(format!("usr_{}", claims.sub), user_id.to_string());
+        assert_eq!(ACCESS, claims.token_use);
+        assert_eq!("client_id", claims.client_id);
+        assert_eq!(EMAIL, claims.username);
+        assert_eq!(None, claims.email);
+        assert_eq!(REFRESH, decode(token.refresh_token().expose_secret()).token_use);
+        assert_eq!(claims.exp, token.expires_at());
+
+        let claims = decode(token.id_token().unwrap().expose_secret());
+        assert_eq!(ID, claims.token_use);
+        assert_eq!(Some(EMAIL.to_string()), claims.email);
+        assert_eq!(Some(EMAIL), token.id_token().unwrap().claims().unwrap().email());
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_failed() {
+        let (repository, _, _) = repository();
+        repository.sign_up(auth(PASSWORD)).await.unwrap();
+
+        let result = repository.sign_up(auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_failed() {
+        let (repository, code_sender, _) = repository();
+        repository.sign_up(auth(PASSWORD)).await.unwrap();
+        let code = code_sender.last_code();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        let result = repository.verify_code(email(), wrong).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        let result = repository.verify_code("fuga@email.com".parse().unwrap(), &code).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        repository.verify_code(email(), &code).await.unwrap();
+        let result = repository.verify_code(email(), &code).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    /// 誤ったコードを上限まで入力すると、正しいコードでも確認できなくなることを確認します
+    #[tokio::test]
+    async fn test_verify_code_attempt_limit() {
+        let (repository, code_sender, credentials) = repository();
+        repository.sign_up(auth(PASSWORD)).await.unwrap();
+        let code = code_sender.last_code();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        for i in 1..MAX_CODE_ATTEMPTS {
+            let result = repository.verify_code(email(), wrong).await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+            let credential = credentials.find_by_email(&email()).await.unwrap();
+            assert_eq!(i, credential.verification_code().unwrap().failed_attempts());
+        }
+        let result = repository.verify_code(email(), wrong).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        let result = repository.verify_code(email(), &code).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(!credentials.find_by_email(&email()).await.unwrap().confirmed());
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_expired() {
+        let (repository, _, credentials) = repository();
+        let credential = Credential::new(
+            UserId::new(),
+            email(),
+            hash_password(PASSWORD).unwrap(),
+            false,
+            Some(VerificationCode::new(hash_code("123456"), SystemClock.now() - 1)),
+            None,
+            0,
+        );
+        credentials.create(credential).await.unwrap();
+
+        let result = repository.verify_code(email(), "123456").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_failed() {
+        let repository = signed_up_and_verified().await;
+
+        let test_cases = vec![
+            auth("Password999"),
+            AuthUser::build("fuga@email.com", PASSWORD).unwrap(),
+        ];
+        for auth in test_cases {
+            let result = repository.authenticate(auth).await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_success() {
+        let repository = signed_up_and_verified().await;
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+
+        let result = repository.refresh(email(), token.refresh_token().expose_secret()).await.unwrap();
+        assert_eq!(token.refresh_token().expose_secret(), result.refresh_token().expose_secret());
+        assert_eq!(ACCESS, decode(result.access_token().expose_secret()).token_use);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_failed() {
+        let repository = signed_up_and_verified().await;
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+
+        let test_cases = vec![
+            (email(), "invalid".to_string()),
+            (email(), token.access_token().expose_secret().to_string()),
+            ("fuga@email.com".parse().unwrap(), token.refresh_token().expose_secret().to_string()),
+        ];
+        for (email, refresh_token) in test_cases {
+            let result = repository.refresh(email, &refresh_token).await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_out_invalidates_refresh_token() {
+        let repository = signed_up_and_verified().await;
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+
+        repository.sign_out(token.access_token().expose_secret()).await.unwrap();
+        let credential = repository.credentials.find_by_email(&email()).await.unwrap();
+        assert!(credential.signed_out_at() > 0);
+
+        // 同じ秒に発行されたトークンと区別できるよう、サインアウト時刻を進めます
+        repository.credentials.update(credential.sign_out(credential.signed_out_at() + 1)).await.unwrap();
+        let result = repository.refresh(email(), token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        let result = repository.sign_out(token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_forgot_and_confirm_forgot_password_success() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+
+        repository.forgot_password(email()).await.unwrap();
+        let code = code_sender.last_reset_code();
+        let credential = repository.credentials.find_by_email(&email()).await.unwrap();
+        assert_eq!(hash_code(&code), credential.reset_code().unwrap().code_hash());
+
+        let new_password = Password::from_str("NewPassword456").unwrap();
+        repository.confirm_forgot_password(email(), &code, new_password).await.unwrap();
+
+        let result = repository.authenticate(auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(repository.authenticate(auth("NewPassword456")).await.is_ok());
+
+        // 再設定に使ったコードは再利用できません
+        let result = repository.confirm_forgot_password(email(), &code, Password::from_str("Other789").unwrap()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        // 再設定より前に発行されたリフレッシュトークンは使用できません
+        let credential = repository.credentials.find_by_email(&email()).await.unwrap();
+        repository.credentials.update(credential.sign_out(credential.signed_out_at() + 1)).await.unwrap();
+        let result = repository.refresh(email(), token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_forgot_password_failed() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+
+        let result = repository.forgot_password("fuga@email.com".parse().unwrap()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(code_sender.reset_codes.lock().unwrap().is_empty());
+    }
+
+    /// 誤ったコードを上限まで入力すると、正しいコードでも再設定できなくなることを確認します
+    #[tokio::test]
+    async fn test_confirm_forgot_password_attempt_limit() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let new_password = || Password::from_str("NewPassword456").unwrap();
+        repository.forgot_password(email()).await.unwrap();
+        let code = code_sender.last_reset_code();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        for _ in 0..MAX_CODE_ATTEMPTS {
+            let result = repository.confirm_forgot_password(email(), wrong, new_password()).await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        }
+        let result = repository.confirm_forgot_password(email(), &code, new_password()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+
+        repository.forgot_password(email()).await.unwrap();
+        let code = code_sender.last_reset_code();
+        repository.confirm_forgot_password(email(), &code, new_password()).await.unwrap();
+        assert!(repository.authenticate(auth("NewPassword456")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_forgot_password_failed() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let new_password = || Password::from_str("NewPassword456").unwrap();
+
+        let result = repository.confirm_forgot_password(email(), "123456", new_password()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        repository.forgot_password(email()).await.unwrap();
+        let code = code_sender.last_reset_code();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        let test_cases = vec![
+            (email(), wrong),
+            ("fuga@email.com".parse().unwrap(), code.as_str()),
+        ];
+        for (email, code) in test_cases {
+            let result = repository.confirm_forgot_password(email, code, new_password()).await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        }
+
+        let credential = repository.credentials.find_by_email(&email()).await.unwrap();
+        let expired = credential.request_password_reset(VerificationCode::new(hash_code(&code), SystemClock.now() - 1));
+        repository.credentials.update(expired).await.unwrap();
+        let result = repository.confirm_forgot_password(email(), &code, new_password()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_creates_user() {
+        let (repository, code_sender, credentials) = repository();
+
+        let result = repository.federated_sign_in(email()).await.unwrap();
+        assert!(result.created());
+        assert_eq!(sub(result.user_id()), decode(result.token().access_token().expose_secret()).sub);
+        assert!(code_sender.codes.lock().unwrap().is_empty());
+
+        let credential = credentials.find_by_email(&email()).await.unwrap();
+        assert!(credential.confirmed());
+        assert_eq!(result.user_id(), credential.user_id());
+
+        // 2回目以降は同じユーザーでサインインします
+        let again = repository.federated_sign_in(email()).await.unwrap();
+        assert!(!again.created());
+        assert_eq!(result.user_id(), again.user_id());
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_links_existing_user() {
+        let repository = signed_up_and_verified().await;
+        let user_id = repository.credentials.find_by_email(&email()).await.unwrap().user_id().clone();
+
+        let result = repository.federated_sign_in(email()).await.unwrap();
+        assert!(!result.created());
+        assert_eq!(&user_id, result.user_id());
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_federated_sign_in_replaces_unconfirmed_password() {
+        let (repository, _, credentials) = repository();
+        let user_id = repository.sign_up(auth(PASSWORD)).await.unwrap();
+
+        let result = repository.federated_sign_in(email()).await.unwrap();
+        assert!(!result.created());
+        assert_eq!(&user_id, result.user_id());
+        assert!(credentials.find_by_email(&email()).await.unwrap().confirmed());
+
+        // 確認前に登録されたパスワードではログインできません
+        let result = repository.authenticate(auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_guest_and_upgrade_success() {
+        let (repository, code_sender, credentials) = repository();
+        let user_id = UserId::new();
+        let guest_email = Email::guest(&user_id);
+
+        let result =
+            repository.sign_up_guest(user_id.clone(), guest_email.clone(), SystemClock.now() + 60).await.unwrap();
+        assert_eq!(&user_id, result.user_id());
+        let token = result.into_token();
+        let claims = decode(token.access_token().expose_secret());
+        assert_eq!(format!("usr_{}", claims.sub), user_id.to_string());
+        assert_eq!(guest_email.value(), claims.username);
+        assert!(repository.refresh(guest_email.clone(), token.refresh_token().expose_secret()).await.is_ok());
+
+        repository.upgrade_guest(guest_email.clone(), auth(PASSWORD)).await.unwrap();
+        let credential = credentials.find_by_email(&email()).await.unwrap();
+        assert_eq!(&user_id, credential.user_id());
+        assert_eq!(None, credential.expires_at());
+        assert!(!credential.confirmed());
+        assert!(matches!(credentials.find_by_email(&guest_email).await, Err(CredentialError::NotFound(_))));
+
+        // ゲストユーザーとして発行したリフレッシュトークンは使用できません
+        let result = repository.refresh(email(), token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        // 別のユーザーの認証情報は確認できません
+        let result = repository.verify_guest_upgrade(UserId::new(), email(), &code_sender.last_code()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        repository.verify_guest_upgrade(user_id.clone(), email(), &code_sender.last_code()).await.unwrap();
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+        assert_eq!(format!("usr_{}", decode(token.access_token().expose_secret()).sub), user_id.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_revert_guest_upgrade() {
+        let (repository, _, credentials) = repository();
+        let user_id = UserId::new();
+        let guest_email = Email::guest(&user_id);
+        let expires_at = SystemClock.now() + 60;
+        repository.sign_up_guest(user_id.clone(), guest_email.clone(), expires_at).await.unwrap();
+        repository.upgrade_guest(guest_email.clone(), auth(PASSWORD)).await.unwrap();
+
+        repository.revert_guest_upgrade(guest_email.clone(), email(), expires_at).await.unwrap();
+        assert!(matches!(credentials.find_by_email(&email()).await, Err(CredentialError::NotFound(_))));
+        let credential = credentials.find_by_email(&guest_email).await.unwrap();
+        assert_eq!(&user_id, credential.user_id());
+        assert_eq!(Some(expires_at), credential.expires_at());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_guest_failed() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let sent = code_sender.codes.lock().unwrap().len();
+        let user_id = UserId::new();
+        let guest_email = Email::guest(&user_id);
+        repository.sign_up_guest(user_id.clone(), guest_email.clone(), SystemClock.now() + 60).await.unwrap();
+
+        let result = repository.upgrade_guest(guest_email.clone(), auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+        assert!(repository.credentials.find_by_email(&guest_email).await.is_ok());
+
+        let result = repository.upgrade_guest(email(), AuthUser::build("fuga@email.com", PASSWORD).unwrap()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert_eq!(sent, code_sender.codes.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_change_email_success() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let user_id = repository.credentials.find_by_email(&email()).await.unwrap().user_id().clone();
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+        let new_email: Email = "fuga@email.com".parse().unwrap();
+
+        repository.request_email_change(email(), new_email.clone()).await.unwrap();
+        let (sent_to, code) = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(new_email.to_string(), sent_to);
+        assert_eq!(vec![(EMAIL.to_string(), new_email.to_string())], code_sender.notices.lock().unwrap().clone());
+        // 確認されるまではメールアドレスを変更しません
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+
+        let result = repository.confirm_email_change(email(), &code, "access_token").await.unwrap();
+        assert_eq!(new_email, result);
+        assert!(matches!(repository.credentials.find_by_email(&email()).await, Err(CredentialError::NotFound(_))));
+        let credential = repository.credentials.find_by_email(&new_email).await.unwrap();
+        assert_eq!(&user_id, credential.user_id());
+        assert!(credential.email_change().is_none());
+
+        let result = repository.authenticate(auth(PASSWORD)).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        let result = repository.refresh(new_email.clone(), token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        let token = repository.authenticate(AuthUser::build("fuga@email.com", PASSWORD).unwrap()).await.unwrap();
+        assert_eq!(new_email.value(), decode(token.access_token().expose_secret()).username);
+
+        repository.revert_email_change(new_email.clone(), email()).await.unwrap();
+        assert!(matches!(repository.credentials.find_by_email(&new_email).await, Err(CredentialError::NotFound(_))));
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_change_email_failed() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let new_email: Email = "fuga@email.com".parse().unwrap();
+
+        let result = repository.confirm_email_change(email(), "123456", "access_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        let result = repository.request_email_change(email(), email()).await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+
+        repository.request_email_change(email(), new_email.clone()).await.unwrap();
+        let (_, code) = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        let result = repository.confirm_email_change(email(), wrong, "access_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        let credential = repository.credentials.find_by_email(&email()).await.unwrap();
+        let expired = credential.request_email_change(EmailChange::new(
+            new_email.clone(),
+            VerificationCode::new(hash_code(&code), SystemClock.now() - 1),
+        ));
+        repository.credentials.update(expired).await.unwrap();
+        let result = repository.confirm_email_change(email(), &code, "access_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        // 確認を待っている間に変更後のメールアドレスが登録された場合は変更しません
+        repository.request_email_change(email(), new_email.clone()).await.unwrap();
+        let (_, code) = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        repository.sign_up(AuthUser::build("fuga@email.com", PASSWORD).unwrap()).await.unwrap();
+        let result = repository.confirm_email_change(email(), &code, "access_token").await;
+        assert!(matches!(result, Err(AuthUserError::UserAlreadyExists)));
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+    }
+
+    /// 誤ったコードを上限まで入力すると、正しいコードでも変更できなくなることを確認します
+    #[tokio::test]
+    async fn test_change_email_attempt_limit() {
+        let (repository, code_sender) = signed_up_and_verified_with_sender().await;
+        let new_email: Email = "fuga@email.com".parse().unwrap();
+        repository.request_email_change(email(), new_email.clone()).await.unwrap();
+        let (_, code) = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        for _ in 0..MAX_CODE_ATTEMPTS {
+            let result = repository.confirm_email_change(email(), wrong, "access_token").await;
+            assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        }
+        assert!(repository.credentials.find_by_email(&email()).await.unwrap().email_change().is_none());
+
+        let result = repository.confirm_email_change(email(), &code, "access_token").await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+        assert!(matches!(repository.credentials.find_by_email(&new_email).await, Err(CredentialError::NotFound(_))));
+        assert!(repository.authenticate(auth(PASSWORD)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let repository = signed_up_and_verified().await;
+        let token = repository.authenticate(auth(PASSWORD)).await.unwrap();
+
+        repository.delete_user(email()).await.unwrap();
+        assert!(matches!(repository.authenticate(auth(PASSWORD)).await, Err(AuthUserError::AuthenticationFailed(_))));
+        let result = repository.refresh(email(), token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+
+        // 削除を再開した場合に備え、削除済みのユーザーも成功として扱います
+        assert!(repository.delete_user(email()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_guest_cannot_refresh() {
+        let (repository, _, _) = repository();
+        let user_id = UserId::new();
+        let guest_email = Email::guest(&user_id);
+        let token =
+            repository.sign_up_guest(user_id, guest_email.clone(), SystemClock.now() - 1).await.unwrap().into_token();
+
+        let result = repository.refresh(guest_email, token.refresh_token().expose_secret()).await;
+        assert!(matches!(result, Err(AuthUserError::AuthenticationFailed(_))));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use domain::{repository::breached_password_repository::BreachedPasswordRepository, user::password::PasswordError};
+
+const PREFIX_LENGTH: usize = 5;
+const HASH_LENGTH: usize = 40;
+
+/// ローカルのファイルから読み込んだ漏洩パスワードのリストです
+///
+/// ファイルは1行に1つ、`SHA1の16進数[:出現回数]`の形式で記述します
+/// 空行と`#`で始まる行は無視します。外部のサービスに問い合わせないため、オフラインでも使用できます
+#[derive(Debug, Default)]
+pub struct LocalBreachedPasswordRepository {
+    ranges: HashMap<String, Vec<String>>,
+}
+
+impl LocalBreachedPasswordRepository {
+    /// 漏洩パスワードを1件も持たないリポジトリを作成します
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, PasswordError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| PasswordError::BreachLookupError(format!("failed to read {}: {}", path, e)))?;
+        Self::parse(&content)
+    }
+
+    /// ハッシュを先頭5文字ごとにまとめます。不正な行がある場合は行番号とともにエラーを返します
+    pub fn parse(content: &str) -> Result<Self, PasswordError> {
+        let mut ranges: HashMap<String, Vec<String>> = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let hash = line.split_once(':').map_or(line, |(hash, _)| hash).trim().to_ascii_uppercase();
+            if hash.len() != HASH_LENGTH || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(PasswordError::BreachLookupError(format!("invalid hash at line {}", index + 1)));
+            }
+            let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+            ranges.entry(prefix.to_string()).or_default().push(suffix.to_string());
+        }
+        Ok(Self { ranges })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl BreachedPasswordRepository for LocalBreachedPasswordRepository {
+    fn find_range(
+        &self, prefix: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<String>, PasswordError>> + Send + '_>> {
+        let result = self.ranges.get(&prefix.to_ascii_uppercase()).cloned().unwrap_or_default();
+        Box::pin(async move { Ok(result) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `password`と`Password123`のSHA-1です
+    const CONTENT: &str = "# breached passwords
+5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824
+
+b2e98ad6f6eb8508dd6a14cfa704bad7f05f6fb1
+";
+
+    #[tokio::test]
+    async fn test_find_range() {
+        let repository = LocalBreachedPasswordRepository::parse(CONTENT).unwrap();
+        assert_eq!(2, repository.len());
+
+        let test_cases = vec![
+            ("5BAA6", vec!["1E4C9B93F3F0682250B6CF8331B7EE68FD8".to_string()]),
+            ("b2e98", vec!["AD6F6EB8508DD6A14CFA704BAD7F05F6FB1".to_string()]),
+            ("00000", vec![]),
+        ];
+        for (prefix, expected) in test_cases {
+            assert_eq!(expected, repository.find_range(prefix).await.unwrap(), "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_parse_failed() {
+        let test_cases = vec![
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD",
+            "ZBAA61E4C9B93F3F0682250B6CF8331B7EE68FD8",
+            "password",
+        ];
+        for content in test_cases {
+            let result = LocalBreachedPasswordRepository::parse(content);
+            assert_eq!(
+                Some(PasswordError::BreachLookupError("invalid hash at line 1".to_string())),
+                result.err(),
+                "{}",
+                content
+            );
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use domain::file::FileError;
+
+/// ローカルのファイルシステムのディレクトリにファイルを保存します
+///
+/// キーの`/`をディレクトリの区切りとして扱います。ルートの外に書き込まないよう、`..`などを含むキーは拒否します
+#[derive(Debug)]
+pub struct LocalObjectStorage {
+    root: PathBuf,
+}
+
+impl LocalObjectStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, FileError> {
+        domain::file::validate_key(key)?;
+        Ok(self.root.join(Path::new(key)))
+    }
+}
+
+impl domain::repository::object_storage::ObjectStorage for LocalObjectStorage {
+    fn put(
+        &self, key: &str, content: Vec<u8>, _content_type: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>> {
+        let path = self.path(key);
+
+        Box::pin(async move {
+            let path = path?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| FileError::WriteError(e.to_string()))?;
+            }
+            tokio::fs::write(&path, content).await.map_err(|e| {
+                let error = FileError::WriteError(format!("{}: {}", e, path.display()));
+                tracing::error!("{:?}", error);
+                error
+            })
+        })
+    }
+
+    fn get(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, FileError>> + Send + '_>> {
+        let key = key.to_string();
+        let path = self.path(&key);
+
+        Box::pin(async move {
+            let path = path?;
+            tokio::fs::read(&path).await.map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => FileError::NotFound(key),
+                _ => {
+                    let error = FileError::ReadError(format!("{}: {}", e, path.display()));
+                    tracing::error!("{:?}", error);
+                    error
+                }
+            })
+        })
+    }
+
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>> {
+        let path = self.path(key);
+
+        Box::pin(async move {
+            let path = path?;
+            match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    let error = FileError::DeleteError(format!("{}: {}", e, path.display()));
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// ディレクトリ内のファイルを数えてから、ディレクトリごと削除します
+    fn delete_by_prefix(
+        &self, prefix: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, FileError>> + Send + '_>> {
+        let path = self.path(prefix);
+
+        Box::pin(async move {
+            let path = path?;
+            let error = |e: std::io::Error, path: &Path| {
+                let error = FileError::DeleteError(format!("{}: {}", e, path.display()));
+                tracing::error!("{:?}", error);
+                error
+            };
+
+            let mut count = 0;
+            let mut dirs = vec![path.clone()];
+            while let Some(dir) = dirs.pop() {
+                let mut entries = match tokio::fs::read_dir(&dir).await {
+                    Ok(v) => v,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(error(e, &dir)),
+                };
+                while let Some(entry) = entries.next_entry().await.map_err(|e| error(e, &dir))? {
+                    match entry.file_type().await.map_err(|e| error(e, &entry.path()))?.is_dir() {
+                        true => dirs.push(entry.path()),
+                        false => count += 1,
+                    }
+                }
+            }
+
+            match tokio::fs::remove_dir_all(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(error(e, &path)),
+                _ => Ok(count),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::repository::object_storage::ObjectStorage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_delete() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let storage = LocalObjectStorage::new(&root);
+
+        storage.put("exports/usr_1/export.zip", b"content".to_vec(), "application/zip").await.unwrap();
+        assert!(root.join("exports/usr_1/export.zip").exists());
+        assert_eq!(b"content".to_vec(), storage.get("exports/usr_1/export.zip").await.unwrap());
+
+        storage.put("exports/usr_1/export.zip", b"updated".to_vec(), "application/zip").await.unwrap();
+        assert_eq!(b"updated".to_vec(), storage.get("exports/usr_1/export.zip").await.unwrap());
+
+        storage.delete("exports/usr_1/export.zip").await.unwrap();
+        storage.delete("exports/usr_1/export.zip").await.unwrap();
+        assert!(matches!(storage.get("exports/usr_1/export.zip").await, Err(FileError::NotFound(_))));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_prefix() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let storage = LocalObjectStorage::new(&root);
+        let keys = vec![
+            "icons/usr_1/icon_1/256.png",
+            "icons/usr_1/icon_1/64.png",
+            "icons/usr_1/icon_2/256.png",
+            "icons/usr_10/icon_1/256.png",
+        ];
+        for key in keys {
+            storage.put(key, b"content".to_vec(), "image/png").await.unwrap();
+        }
+
+        assert_eq!(3, storage.delete_by_prefix("icons/usr_1").await.unwrap());
+        assert_eq!(0, storage.delete_by_prefix("icons/usr_1").await.unwrap());
+        assert!(!root.join("icons/usr_1").exists());
+        assert!(root.join("icons/usr_10/icon_1/256.png").exists());
+        assert!(matches!(storage.delete_by_prefix("../icons").await, Err(FileError::InvalidKey(_))));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_key() {
+        let storage = LocalObjectStorage::new(std::env::temp_dir().join(uuid::Uuid::new_v4().to_string()));
+        let test_cases = vec![
+            "",
+            "/etc/passwd",
+            "../secret",
+            "exports/../../secret",
+            "exports//export.zip",
+            "exports\\export.zip",
+        ];
+        for key in test_cases {
+            assert!(matches!(storage.get(key).await, Err(FileError::InvalidKey(_))), "{}", key);
+            assert!(
+                matches!(storage.put(key, vec![], "application/zip").await, Err(FileError::InvalidKey(_))),
+                "{}",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use domain::{
+    clock::{Clock, SystemClock},
+    login_attempt::{LoginAttempt, LoginAttemptError},
+};
+
+#[derive(Debug)]
+pub struct LoginAttemptRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl LoginAttemptRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        LoginAttemptRepositoryImpl { client, table_name, clock: Arc::new(SystemClock) }
+    }
+
+    /// 有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+const KEY: &str = "key";
+const FAILURES: &str = "failures";
+const LOCKOUTS: &str = "lockouts";
+const LOCKED_UNTIL: &str = "locked_until";
+/// DynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+
+impl domain::repository::login_attempt_repository::LoginAttemptRepository for LoginAttemptRepositoryImpl {
+    fn find(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<LoginAttempt>, LoginAttemptError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let key = key.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(KEY, AttributeValue::S(key))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = LoginAttemptError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            // TTLによる削除は遅延するため、期限切れの項目が残っている場合があります
+            match result.item {
+                Some(item) => Ok(Some(LoginAttemptRepositoryImpl::map_to_domain_model(item)?)
+                    .filter(|attempt| !attempt.is_expired(self.clock.now()))),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn save(
+        &self, attempt: LoginAttempt,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        Box::pin(async move {
+            let failures = attempt.failures().iter().map(|v| AttributeValue::N(v.to_string())).collect();
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(KEY, AttributeValue::S(attempt.key().to_string()))
+                .item(FAILURES, AttributeValue::L(failures))
+                .item(LOCKOUTS, AttributeValue::N(attempt.lockouts().to_string()))
+                .item(LOCKED_UNTIL, AttributeValue::N(attempt.locked_until().to_string()))
+                .item(EXPIRES_AT, AttributeValue::N(attempt.expires_at().to_string()))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = LoginAttemptError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoginAttemptError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let key = key.to_string();
+
+        Box::pin(async move {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(KEY, AttributeValue::S(key))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = LoginAttemptError::DeleteError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<LoginAttempt, LoginAttemptError> for LoginAttemptRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<LoginAttempt, LoginAttemptError> {
+        use crate::mapper::{as_i64, as_i64_list, as_string};
+
+        Ok(LoginAttempt::new(
+            as_string(v.get(KEY), ""),
+            as_i64_list(v.get(FAILURES)),
+            as_i64(v.get(LOCKOUTS), 0) as u32,
+            as_i64(v.get(LOCKED_UNTIL), 0),
+            as_i64(v.get(EXPIRES_AT), 0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let item = HashMap::from([
+            (KEY.to_string(), AttributeValue::S("email#hoge@email.com".to_string())),
+            (FAILURES.to_string(), AttributeValue::L(vec![AttributeValue::N("1700000000".to_string())])),
+            (LOCKOUTS.to_string(), AttributeValue::N("2".to_string())),
+            (LOCKED_UNTIL.to_string(), AttributeValue::N("1700000060".to_string())),
+            (EXPIRES_AT.to_string(), AttributeValue::N("1700000960".to_string())),
+        ]);
+
+        let result = LoginAttemptRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!("email#hoge@email.com", result.key());
+        assert_eq!(vec![1700000000], result.failures());
+        assert_eq!(2, result.lockouts());
+        assert_eq!(1700000060, result.locked_until());
+        assert_eq!(1700000960, result.expires_at());
+    }
+}
@@ -0,0 +1,235 @@
+use domain::{
+    mfa::{Mfa, MfaError},
+    user::user_id::UserId,
+};
+
+#[derive(Debug)]
+pub struct MfaRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl MfaRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        MfaRepositoryImpl { client, table_name }
+    }
+
+    fn put_item(&self, mfa: &Mfa) -> aws_sdk_dynamodb::operation::put_item::builders::PutItemFluentBuilder {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let mut builder = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(USER_ID, AttributeValue::S(mfa.user_id().to_string()))
+            .item(SECRET, AttributeValue::S(mfa.secret().to_string()))
+            .item(ENABLED, AttributeValue::Bool(mfa.enabled()))
+            .item(LAST_USED_STEP, AttributeValue::N(mfa.last_used_step().to_string()));
+
+        // DynamoDBは空の文字列セットを保存できないため、リカバリーコードが残っている場合のみ設定します
+        if !mfa.recovery_code_hashes().is_empty() {
+            builder = builder.item(RECOVERY_CODE_HASHES, AttributeValue::Ss(mfa.recovery_code_hashes().to_vec()));
+        }
+        builder
+    }
+}
+
+const USER_ID: &str = "user_id";
+const SECRET: &str = "secret";
+const ENABLED: &str = "enabled";
+const RECOVERY_CODE_HASHES: &str = "recovery_code_hashes";
+const LAST_USED_STEP: &str = "last_used_step";
+
+const SECRET_ATTR: &str = "#secret";
+const ENABLED_ATTR: &str = "#enabled";
+const RECOVERY_CODE_HASHES_ATTR: &str = "#recovery_code_hashes";
+const LAST_USED_STEP_ATTR: &str = "#last_used_step";
+
+const SECRET_VALUE: &str = ":secret";
+const ENABLED_VALUE: &str = ":enabled";
+const RECOVERY_CODE_COUNT_VALUE: &str = ":recovery_code_count";
+const LAST_USED_STEP_VALUE: &str = ":last_used_step";
+
+impl domain::repository::mfa_repository::MfaRepository for MfaRepositoryImpl {
+    fn find(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<Mfa>, MfaError>> + Send + '_>> {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(USER_ID, AttributeValue::S(user_id))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = MfaError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => Ok(Some(MfaRepositoryImpl::map_to_domain_model(item)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn save(&self, mfa: Mfa) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        Box::pin(async move {
+            self.put_item(&mfa).send().await.map_err(|e| {
+                let error = MfaError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    fn update(
+        &self, mfa: Mfa, expected: &Mfa,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let expected = expected.clone();
+
+        Box::pin(async move {
+            let mut builder = self
+                .put_item(&mfa)
+                .expression_attribute_names(SECRET_ATTR, SECRET)
+                .expression_attribute_names(ENABLED_ATTR, ENABLED)
+                .expression_attribute_names(RECOVERY_CODE_HASHES_ATTR, RECOVERY_CODE_HASHES)
+                .expression_attribute_names(LAST_USED_STEP_ATTR, LAST_USED_STEP)
+                .expression_attribute_values(SECRET_VALUE, AttributeValue::S(expected.secret().to_string()))
+                .expression_attribute_values(ENABLED_VALUE, AttributeValue::Bool(expected.enabled()))
+                .expression_attribute_values(
+                    LAST_USED_STEP_VALUE,
+                    AttributeValue::N(expected.last_used_step().to_string()),
+                );
+            let condition = "#secret = :secret AND #enabled = :enabled AND #last_used_step = :last_used_step";
+            // リカバリーコードは減る一方のため、残りの数が同じであれば同じコードが残っています
+            builder = match expected.recovery_code_hashes().len() {
+                0 => builder
+                    .condition_expression(format!("{} AND attribute_not_exists(#recovery_code_hashes)", condition)),
+                v => builder
+                    .condition_expression(format!(
+                        "{} AND size(#recovery_code_hashes) = :recovery_code_count",
+                        condition
+                    ))
+                    .expression_attribute_values(RECOVERY_CODE_COUNT_VALUE, AttributeValue::N(v.to_string())),
+            };
+            let result = builder.send().await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = match e.as_service_error().map(|v| v.is_conditional_check_failed_exception()) {
+                        Some(true) => MfaError::Conflict(mfa.user_id().to_string()),
+                        _ => MfaError::SaveError(crate::dynamodb_error_message(&e)),
+                    };
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn delete(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), MfaError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(USER_ID, AttributeValue::S(user_id))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = MfaError::DeleteError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<Mfa, MfaError> for MfaRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<Mfa, MfaError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_bool, as_i64, as_string, as_string_set};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id = UserId::from_str(&user_id).map_err(|e| MfaError::FindError(format!("{}: {}", e, user_id)))?;
+
+        Ok(Mfa::new(
+            user_id,
+            as_string(v.get(SECRET), ""),
+            as_bool(v.get(ENABLED), false),
+            as_string_set(v.get(RECOVERY_CODE_HASHES)),
+            as_i64(v.get(LAST_USED_STEP), 0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let item = HashMap::from([
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (SECRET.to_string(), AttributeValue::S("encrypted".to_string())),
+            (ENABLED.to_string(), AttributeValue::Bool(true)),
+            (RECOVERY_CODE_HASHES.to_string(), AttributeValue::Ss(vec!["hash1".to_string()])),
+            (LAST_USED_STEP.to_string(), AttributeValue::N("56666666".to_string())),
+        ]);
+
+        let result = MfaRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!("encrypted", result.secret());
+        assert!(result.enabled());
+        assert_eq!(vec!["hash1"], result.recovery_code_hashes());
+        assert_eq!(56666666, result.last_used_step());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_defaults() {
+        let item = HashMap::from([(
+            USER_ID.to_string(),
+            AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string()),
+        )]);
+
+        let result = MfaRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert!(!result.enabled());
+        assert!(result.recovery_code_hashes().is_empty());
+        assert_eq!(0, result.last_used_step());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid_user_id() {
+        let item = HashMap::from([(USER_ID.to_string(), AttributeValue::S("invalid".to_string()))]);
+        let result = MfaRepositoryImpl::map_to_domain_model(item);
+        assert!(matches!(result, Err(MfaError::FindError(_))));
+    }
+}
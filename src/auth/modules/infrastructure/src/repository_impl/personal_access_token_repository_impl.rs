@@ -0,0 +1,301 @@
+use domain::{
+    personal_access_token::{PersonalAccessToken, PersonalAccessTokenError, Scope},
+    user::user_id::UserId,
+};
+
+/// 個人用アクセストークンをユーザーごとに保存します
+///
+/// パーティションキーは`user_id`、ソートキーは`token_id`です
+#[derive(Debug)]
+pub struct PersonalAccessTokenRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl PersonalAccessTokenRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        PersonalAccessTokenRepositoryImpl { client, table_name }
+    }
+}
+
+const USER_ID: &str = "user_id";
+const TOKEN_ID: &str = "token_id";
+const NAME: &str = "name";
+const SCOPES: &str = "scopes";
+const TOKEN_HASH: &str = "token_hash";
+const CREATED_AT: &str = "created_at";
+/// DynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+
+const USER_ID_ATTR: &str = "#user_id";
+const USER_ID_VALUE: &str = ":user_id";
+
+impl domain::repository::personal_access_token_repository::PersonalAccessTokenRepository
+    for PersonalAccessTokenRepositoryImpl
+{
+    fn find(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = Result<Option<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_,
+        >,
+    > {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+        let token_id = token_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(USER_ID, AttributeValue::S(user_id))
+                .key(TOKEN_ID, AttributeValue::S(token_id))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = PersonalAccessTokenError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.item {
+                Some(item) => Ok(Some(PersonalAccessTokenRepositoryImpl::map_to_domain_model(item)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<PersonalAccessToken>, PersonalAccessTokenError>> + Send + '_>,
+    > {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let mut tokens = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .key_condition_expression("#user_id = :user_id")
+                    .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                    .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.clone()))
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = PersonalAccessTokenError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    tokens.push(PersonalAccessTokenRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+
+            tokens.sort_by_key(|v| v.created_at());
+            Ok(tokens)
+        })
+    }
+
+    fn save(
+        &self, token: PersonalAccessToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(USER_ID, AttributeValue::S(token.user_id().to_string()))
+                .item(TOKEN_ID, AttributeValue::S(token.token_id().to_string()))
+                .item(NAME, AttributeValue::S(token.name().to_string()))
+                .item(TOKEN_HASH, AttributeValue::S(token.token_hash().to_string()))
+                .item(CREATED_AT, AttributeValue::N(token.created_at().to_string()))
+                .item(EXPIRES_AT, AttributeValue::N(token.expires_at().to_string()));
+            // DynamoDBは空のセットを保存できません
+            if !token.scopes().is_empty() {
+                request =
+                    request.item(SCOPES, AttributeValue::Ss(token.scopes().iter().map(ToString::to_string).collect()));
+            }
+
+            request.send().await.map_err(|e| {
+                let error = PersonalAccessTokenError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    fn delete(
+        &self, user_id: &UserId, token_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), PersonalAccessTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+        let token_id = token_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(USER_ID, AttributeValue::S(user_id))
+                .key(TOKEN_ID, AttributeValue::S(token_id.clone()))
+                .condition_expression("attribute_exists(token_id)")
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = match e.as_service_error().map(|v| v.is_conditional_check_failed_exception()) {
+                        Some(true) => PersonalAccessTokenError::NotFound(token_id),
+                        _ => PersonalAccessTokenError::DeleteError(crate::dynamodb_error_message(&e)),
+                    };
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, PersonalAccessTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let tokens = self.find_by_user_id(&user_id).await?;
+            for token in &tokens {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key(USER_ID, AttributeValue::S(user_id.to_string()))
+                    .key(TOKEN_ID, AttributeValue::S(token.token_id().to_string()))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = PersonalAccessTokenError::DeleteError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+            }
+            Ok(tokens.len())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<PersonalAccessToken, PersonalAccessTokenError> for PersonalAccessTokenRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<PersonalAccessToken, PersonalAccessTokenError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string, as_string_set};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id = UserId::from_str(&user_id)
+            .map_err(|e| PersonalAccessTokenError::FindError(format!("{}: {}", e, user_id)))?;
+        let mut scopes = as_string_set(v.get(SCOPES))
+            .iter()
+            .map(|v| Scope::from_str(v))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PersonalAccessTokenError::FindError(e.to_string()))?;
+        scopes.sort();
+
+        Ok(PersonalAccessToken::new(
+            as_string(v.get(TOKEN_ID), ""),
+            user_id,
+            as_string(v.get(NAME), ""),
+            scopes,
+            as_string(v.get(TOKEN_HASH), ""),
+            as_i64(v.get(CREATED_AT), 0),
+            as_i64(v.get(EXPIRES_AT), 0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (TOKEN_ID.to_string(), AttributeValue::S("123e4567-e89b-12d3-a456-426614174000".to_string())),
+            (NAME.to_string(), AttributeValue::S("import".to_string())),
+            (
+                SCOPES.to_string(),
+                AttributeValue::Ss(vec![
+                    "subscriptions:write".to_string(),
+                    "subscriptions:read".to_string(),
+                ]),
+            ),
+            (TOKEN_HASH.to_string(), AttributeValue::S("hash".to_string())),
+            (CREATED_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+            (EXPIRES_AT.to_string(), AttributeValue::N("1700086400".to_string())),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = PersonalAccessTokenRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!("123e4567-e89b-12d3-a456-426614174000", result.token_id());
+        assert_eq!("import", result.name());
+        let expected = vec![
+            Scope::SubscriptionsRead,
+            Scope::SubscriptionsWrite,
+        ];
+        assert_eq!(expected, result.scopes());
+        assert_eq!("hash", result.token_hash());
+        assert_eq!(1700000000, result.created_at());
+        assert_eq!(1700086400, result.expires_at());
+
+        let mut item = item();
+        item.remove(SCOPES);
+        let result = PersonalAccessTokenRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert!(result.scopes().is_empty());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid() {
+        let test_cases = vec![
+            (USER_ID.to_string(), AttributeValue::S("invalid".to_string())),
+            (SCOPES.to_string(), AttributeValue::Ss(vec!["invalid".to_string()])),
+        ];
+
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.clone(), value);
+            let result = PersonalAccessTokenRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(PersonalAccessTokenError::FindError(_))), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use domain::{
+    refresh_token::{Device, RefreshTokenError, RefreshTokenFamily},
+    user::user_id::UserId,
+};
+
+#[derive(Debug)]
+pub struct RefreshTokenRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl RefreshTokenRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        RefreshTokenRepositoryImpl { client, table_name }
+    }
+
+    fn put_item(
+        &self, family: &RefreshTokenFamily,
+    ) -> aws_sdk_dynamodb::operation::put_item::builders::PutItemFluentBuilder {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let mut builder = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(FAMILY_ID, AttributeValue::S(family.family_id().to_string()))
+            .item(USER_ID, AttributeValue::S(family.user_id().to_string()))
+            .item(TOKEN_HASH, AttributeValue::S(family.token_hash().to_string()))
+            .item(UPSTREAM, AttributeValue::S(family.upstream().to_string()))
+            .item(REVOKED, AttributeValue::Bool(family.revoked()))
+            .item(DEVICE_LABEL, AttributeValue::S(family.device().label().to_string()))
+            .item(USER_AGENT, AttributeValue::S(family.device().user_agent().to_string()))
+            .item(IP_ADDRESS, AttributeValue::S(family.device().ip().to_string()))
+            .item(CREATED_AT, AttributeValue::N(family.created_at().to_string()))
+            .item(LAST_SEEN_AT, AttributeValue::N(family.last_seen_at().to_string()))
+            .item(EXPIRES_AT, AttributeValue::N(family.expires_at().to_string()));
+
+        // DynamoDBは空の文字列セットを保存できないため、使用済みトークンがある場合のみ設定します
+        if !family.used_hashes().is_empty() {
+            builder = builder.item(USED_HASHES, AttributeValue::Ss(family.used_hashes().to_vec()));
+        }
+        builder
+    }
+}
+
+const FAMILY_ID: &str = "family_id";
+const USER_ID: &str = "user_id";
+const TOKEN_HASH: &str = "token_hash";
+const USED_HASHES: &str = "used_hashes";
+const UPSTREAM: &str = "upstream";
+const REVOKED: &str = "revoked";
+const DEVICE_LABEL: &str = "device_label";
+const USER_AGENT: &str = "user_agent";
+const IP_ADDRESS: &str = "ip_address";
+const CREATED_AT: &str = "created_at";
+const LAST_SEEN_AT: &str = "last_seen_at";
+/// DynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+
+/// `user_id`をパーティションキーとするGSIです。すべての属性を射影します
+const USER_ID_INDEX: &str = "user_id-index";
+
+const USER_ID_ATTR: &str = "#user_id";
+const TOKEN_HASH_ATTR: &str = "#token_hash";
+const REVOKED_ATTR: &str = "#revoked";
+
+const USER_ID_VALUE: &str = ":user_id";
+const EXPECTED_HASH_VALUE: &str = ":expected_hash";
+const REVOKED_VALUE: &str = ":revoked";
+
+impl domain::repository::refresh_token_repository::RefreshTokenRepository for RefreshTokenRepositoryImpl {
+    fn find_by_family_id(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<RefreshTokenFamily, RefreshTokenError>> + Send + '_>>
+    {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let family_id = family_id.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(FAMILY_ID, AttributeValue::S(family_id.clone()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| RefreshTokenError::FindError(crate::dynamodb_error_message(&e)))?;
+
+            match result.item {
+                Some(item) => RefreshTokenRepositoryImpl::map_to_domain_model(item),
+                None => {
+                    let error = RefreshTokenError::NotFound(family_id);
+                    tracing::warn!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<RefreshTokenFamily>, RefreshTokenError>> + Send + '_>,
+    > {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let mut families = vec![];
+            let mut exclusive_start_key = None;
+            loop {
+                let result = self
+                    .client
+                    .query()
+                    .table_name(&self.table_name)
+                    .index_name(USER_ID_INDEX)
+                    .key_condition_expression("#user_id = :user_id")
+                    .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                    .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.clone()))
+                    .set_exclusive_start_key(exclusive_start_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = RefreshTokenError::FindError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                for item in result.items.unwrap_or_default() {
+                    families.push(RefreshTokenRepositoryImpl::map_to_domain_model(item)?);
+                }
+                exclusive_start_key = result.last_evaluated_key;
+                if exclusive_start_key.is_none() {
+                    break;
+                }
+            }
+            Ok(families)
+        })
+    }
+
+    fn create(
+        &self, family: RefreshTokenFamily,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        Box::pin(async move {
+            self.put_item(&family).condition_expression("attribute_not_exists(family_id)").send().await.map_err(
+                |e| {
+                    let error = RefreshTokenError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                },
+            )?;
+            Ok(())
+        })
+    }
+
+    fn rotate(
+        &self, family: RefreshTokenFamily, expected_hash: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let expected_hash = expected_hash.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .put_item(&family)
+                .condition_expression("#token_hash = :expected_hash AND #revoked = :revoked")
+                .expression_attribute_names(TOKEN_HASH_ATTR, TOKEN_HASH)
+                .expression_attribute_names(REVOKED_ATTR, REVOKED)
+                .expression_attribute_values(EXPECTED_HASH_VALUE, AttributeValue::S(expected_hash))
+                .expression_attribute_values(REVOKED_VALUE, AttributeValue::Bool(false))
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = match e.as_service_error().map(|v| v.is_conditional_check_failed_exception()) {
+                        Some(true) => RefreshTokenError::Conflict(family.family_id().to_string()),
+                        _ => RefreshTokenError::SaveError(crate::dynamodb_error_message(&e)),
+                    };
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn revoke(
+        &self, family_id: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RefreshTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let family_id = family_id.to_string();
+
+        Box::pin(async move {
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .key(FAMILY_ID, AttributeValue::S(family_id))
+                .update_expression("SET #revoked = :revoked")
+                .condition_expression("attribute_exists(family_id)")
+                .expression_attribute_names(REVOKED_ATTR, REVOKED)
+                .expression_attribute_values(REVOKED_VALUE, AttributeValue::Bool(true))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = RefreshTokenError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, RefreshTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let user_id = user_id.clone();
+
+        Box::pin(async move {
+            let families = self.find_by_user_id(&user_id).await?;
+            for family in &families {
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .key(FAMILY_ID, AttributeValue::S(family.family_id().to_string()))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = RefreshTokenError::DeleteError(crate::dynamodb_error_message(&e));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+            }
+            Ok(families.len())
+        })
+    }
+}
+
+impl crate::mapper::Mapper<RefreshTokenFamily, RefreshTokenError> for RefreshTokenRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<RefreshTokenFamily, RefreshTokenError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_bool, as_i64, as_string, as_string_set};
+
+        let user_id = as_string(v.get(USER_ID), "");
+        let user_id =
+            UserId::from_str(&user_id).map_err(|e| RefreshTokenError::FindError(format!("{}: {}", e, user_id)))?;
+        // セッションの情報を記録する前に作成した系列は、作成日時を不明として扱います
+        let created_at = as_i64(v.get(CREATED_AT), 0);
+
+        Ok(RefreshTokenFamily::new(
+            as_string(v.get(FAMILY_ID), ""),
+            user_id,
+            as_string(v.get(TOKEN_HASH), ""),
+            as_string_set(v.get(USED_HASHES)),
+            as_string(v.get(UPSTREAM), ""),
+            as_bool(v.get(REVOKED), true),
+            Device::new(
+                as_string(v.get(DEVICE_LABEL), ""),
+                as_string(v.get(USER_AGENT), ""),
+                as_string(v.get(IP_ADDRESS), ""),
+            ),
+            created_at,
+            as_i64(v.get(LAST_SEEN_AT), created_at),
+            as_i64(v.get(EXPIRES_AT), 0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let item = HashMap::from([
+            (FAMILY_ID.to_string(), AttributeValue::S("family".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (TOKEN_HASH.to_string(), AttributeValue::S("hash2".to_string())),
+            (USED_HASHES.to_string(), AttributeValue::Ss(vec!["hash1".to_string()])),
+            (UPSTREAM.to_string(), AttributeValue::S("upstream".to_string())),
+            (REVOKED.to_string(), AttributeValue::Bool(false)),
+            (DEVICE_LABEL.to_string(), AttributeValue::S("Chrome on macOS".to_string())),
+            (USER_AGENT.to_string(), AttributeValue::S("Mozilla/5.0".to_string())),
+            (IP_ADDRESS.to_string(), AttributeValue::S("192.0.2.1".to_string())),
+            (CREATED_AT.to_string(), AttributeValue::N("1690000000".to_string())),
+            (LAST_SEEN_AT.to_string(), AttributeValue::N("1695000000".to_string())),
+            (EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+        ]);
+
+        let result = RefreshTokenRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!("family", result.family_id());
+        assert_eq!("usr_550e8400-e29b-41d4-a716-446655440000", result.user_id().to_string());
+        assert_eq!("hash2", result.token_hash());
+        assert!(result.is_used("hash1"));
+        assert_eq!("upstream", result.upstream());
+        assert!(!result.revoked());
+        assert_eq!("Chrome on macOS", result.device().label());
+        assert_eq!("Mozilla/5.0", result.device().user_agent());
+        assert_eq!("192.0.2.1", result.device().ip());
+        assert_eq!(1690000000, result.created_at());
+        assert_eq!(1695000000, result.last_seen_at());
+        assert_eq!(1700000000, result.expires_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_defaults() {
+        let item = HashMap::from([
+            (FAMILY_ID.to_string(), AttributeValue::S("family".to_string())),
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+        ]);
+
+        let result = RefreshTokenRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert!(result.used_hashes().is_empty());
+        assert!(result.revoked());
+        assert!(result.is_expired(0));
+        assert_eq!(&Device::default(), result.device());
+        assert_eq!(0, result.last_seen_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid_user_id() {
+        let item = HashMap::from([(USER_ID.to_string(), AttributeValue::S("invalid".to_string()))]);
+        let result = RefreshTokenRepositoryImpl::map_to_domain_model(item);
+        assert!(matches!(result, Err(RefreshTokenError::FindError(_))));
+    }
+}
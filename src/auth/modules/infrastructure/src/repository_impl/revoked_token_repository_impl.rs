@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use domain::{
+    clock::{Clock, SystemClock},
+    revoked_token::{RevokedToken, RevokedTokenError},
+};
+
+#[derive(Debug)]
+pub struct RevokedTokenRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl RevokedTokenRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        RevokedTokenRepositoryImpl { client, table_name, clock: Arc::new(SystemClock) }
+    }
+
+    /// 有効期限の判定に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+const JTI: &str = "jti";
+/// DynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+
+impl domain::repository::revoked_token_repository::RevokedTokenRepository for RevokedTokenRepositoryImpl {
+    fn save(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), RevokedTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        Box::pin(async move {
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(JTI, AttributeValue::S(token.jti().to_string()))
+                .item(EXPIRES_AT, AttributeValue::N(token.expires_at().to_string()))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = RevokedTokenError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn revoke_once(
+        &self, token: RevokedToken,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        Box::pin(async move {
+            // TTLによる削除は遅延するため、期限切れの項目は上書きします
+            let result = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(JTI, AttributeValue::S(token.jti().to_string()))
+                .item(EXPIRES_AT, AttributeValue::N(token.expires_at().to_string()))
+                .condition_expression("attribute_not_exists(jti) OR expires_at <= :now")
+                .expression_attribute_values(":now", AttributeValue::N(self.clock.now().to_string()))
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => Ok(true),
+                Err(e) if e.as_service_error().is_some_and(|v| v.is_conditional_check_failed_exception()) => Ok(false),
+                Err(e) => {
+                    let error = RevokedTokenError::SaveError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn is_revoked(
+        &self, jti: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<bool, RevokedTokenError>> + Send + '_>> {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let jti = jti.to_string();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key(JTI, AttributeValue::S(jti))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = RevokedTokenError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            // TTLによる削除は遅延するため、期限切れの項目が残っている場合があります
+            match result.item {
+                Some(item) => Ok(!RevokedTokenRepositoryImpl::map_to_domain_model(item)?.is_expired(self.clock.now())),
+                None => Ok(false),
+            }
+        })
+    }
+}
+
+impl crate::mapper::Mapper<RevokedToken, RevokedTokenError> for RevokedTokenRepositoryImpl {
+    fn map_to_domain_model(
+        v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    ) -> Result<RevokedToken, RevokedTokenError> {
+        use crate::mapper::{as_i64, as_string};
+
+        Ok(RevokedToken::new(as_string(v.get(JTI), ""), as_i64(v.get(EXPIRES_AT), 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let item = HashMap::from([
+            (JTI.to_string(), AttributeValue::S("jti".to_string())),
+            (EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+        ]);
+
+        let result = RevokedTokenRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!("jti", result.jti());
+        assert_eq!(1700000000, result.expires_at());
+    }
+}
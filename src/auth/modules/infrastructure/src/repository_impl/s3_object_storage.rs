@@ -0,0 +1,141 @@
+use aws_sdk_s3::{error::ProvideErrorMetadata, primitives::ByteStream};
+use domain::file::FileError;
+
+/// S3のバケットにファイルを保存します
+///
+/// キーはそのままオブジェクトのキーに使います。ローカルのファイルシステムと同じキーを使えるよう、`..`などを含むキーは拒否します
+#[derive(Debug)]
+pub struct S3ObjectStorage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3ObjectStorage {
+    pub fn new(client: aws_sdk_s3::Client, bucket: String) -> Self {
+        S3ObjectStorage { client, bucket }
+    }
+}
+
+/// S3のエラーからメッセージを取り出します
+fn error_message<E, R>(e: &aws_sdk_s3::error::SdkError<E, R>) -> String
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    match e.message() {
+        Some(s) => s.to_string(),
+        None => e.to_string(),
+    }
+}
+
+impl domain::repository::object_storage::ObjectStorage for S3ObjectStorage {
+    fn put(
+        &self, key: &str, content: Vec<u8>, content_type: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>> {
+        let key = key.to_string();
+        let content_type = content_type.to_string();
+
+        Box::pin(async move {
+            domain::file::validate_key(&key)?;
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .content_type(content_type)
+                .body(ByteStream::from(content))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = FileError::WriteError(format!("{}: {}", error_message(&e), key));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            Ok(())
+        })
+    }
+
+    fn get(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<u8>, FileError>> + Send + '_>> {
+        let key = key.to_string();
+
+        Box::pin(async move {
+            domain::file::validate_key(&key)?;
+            let result = self.client.get_object().bucket(&self.bucket).key(&key).send().await.map_err(|e| {
+                if e.as_service_error().is_some_and(|v| v.is_no_such_key()) {
+                    return FileError::NotFound(key.clone());
+                }
+                let error = FileError::ReadError(format!("{}: {}", error_message(&e), key));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+
+            let content = result.body.collect().await.map_err(|e| {
+                let error = FileError::ReadError(format!("{}: {}", e, key));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(content.into_bytes().to_vec())
+        })
+    }
+
+    fn delete(
+        &self, key: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), FileError>> + Send + '_>> {
+        let key = key.to_string();
+
+        Box::pin(async move {
+            domain::file::validate_key(&key)?;
+            // S3は存在しないキーの削除も成功として返します
+            self.client.delete_object().bucket(&self.bucket).key(&key).send().await.map_err(|e| {
+                let error = FileError::DeleteError(format!("{}: {}", error_message(&e), key));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    /// `ListObjectsV2`で取得したオブジェクトを1件ずつ削除します
+    fn delete_by_prefix(
+        &self, prefix: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, FileError>> + Send + '_>> {
+        let prefix = prefix.to_string();
+
+        Box::pin(async move {
+            domain::file::validate_key(&prefix)?;
+            let mut keys = vec![];
+            let mut continuation_token = None;
+            loop {
+                let result = self
+                    .client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .prefix(format!("{}/", prefix))
+                    .set_continuation_token(continuation_token)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        let error = FileError::DeleteError(format!("{}: {}", error_message(&e), prefix));
+                        tracing::error!("{:?}", error);
+                        error
+                    })?;
+
+                keys.extend(result.contents().iter().filter_map(|v| v.key().map(ToString::to_string)));
+                continuation_token = result.next_continuation_token().map(ToString::to_string);
+                if continuation_token.is_none() {
+                    break;
+                }
+            }
+
+            for key in &keys {
+                self.client.delete_object().bucket(&self.bucket).key(key).send().await.map_err(|e| {
+                    let error = FileError::DeleteError(format!("{}: {}", error_message(&e), key));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+            }
+            Ok(keys.len())
+        })
+    }
+}
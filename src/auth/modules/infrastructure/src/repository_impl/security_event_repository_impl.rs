@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use domain::{
+    security_event::{SecurityEvent, SecurityEventError, SecurityEventType},
+    user::user_id::UserId,
+};
+
+/// 監査ログのイベントを保存します
+///
+/// パーティションキーは`user_id`、ソートキーは発生日時を10桁にゼロ埋めした値と`event_id`を連結した`event_key`です
+/// ユーザーを特定できないイベントは`user_id`を`anonymous`として保存します
+#[derive(Debug)]
+pub struct SecurityEventRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl SecurityEventRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        SecurityEventRepositoryImpl { client, table_name }
+    }
+
+    /// `key`が`value`のイベントのうち、期間内のものを新しい順に`limit`件まで取得します
+    ///
+    /// `event_type`を指定した場合はフィルターで絞り込みます
+    #[allow(clippy::too_many_arguments)]
+    async fn query(
+        &self, index_name: Option<&str>, key: &str, value: String, event_type: Option<SecurityEventType>, from: i64,
+        to: i64, limit: usize,
+    ) -> Result<Vec<SecurityEvent>, SecurityEventError> {
+        use crate::mapper::Mapper;
+
+        let mut events = vec![];
+        let mut exclusive_start_key = None;
+        // 期間が空の場合、`BETWEEN`は下限が上限を超えるとエラーになるため問い合わせません
+        if from >= to || limit == 0 {
+            return Ok(events);
+        }
+        let mut names = HashMap::from([
+            (KEY_ATTR.to_string(), key.to_string()),
+            (EVENT_KEY_ATTR.to_string(), EVENT_KEY.to_string()),
+        ]);
+        let mut values = HashMap::from([
+            (KEY_VALUE.to_string(), AttributeValue::S(value)),
+            (FROM_VALUE.to_string(), AttributeValue::S(key_prefix(from))),
+            (TO_VALUE.to_string(), AttributeValue::S(key_prefix(to))),
+        ]);
+        if let Some(v) = event_type {
+            names.insert(EVENT_TYPE_ATTR.to_string(), EVENT_TYPE.to_string());
+            values.insert(EVENT_TYPE_VALUE.to_string(), AttributeValue::S(v.to_string()));
+        }
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .set_index_name(index_name.map(ToString::to_string))
+                .key_condition_expression("#key = :key AND #event_key BETWEEN :from AND :to")
+                .set_filter_expression(event_type.map(|_| "#event_type = :event_type".to_string()))
+                .set_expression_attribute_names(Some(names.clone()))
+                .set_expression_attribute_values(Some(values.clone()))
+                .scan_index_forward(false)
+                .limit((limit - events.len()).min(i32::MAX as usize) as i32)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error = SecurityEventError::FindError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            for item in result.items.unwrap_or_default() {
+                events.push(SecurityEventRepositoryImpl::map_to_domain_model(item)?);
+            }
+            exclusive_start_key = result.last_evaluated_key;
+            if exclusive_start_key.is_none() || events.len() >= limit {
+                break;
+            }
+        }
+
+        events.truncate(limit);
+        Ok(events)
+    }
+}
+
+const USER_ID: &str = "user_id";
+const EVENT_KEY: &str = "event_key";
+const EVENT_ID: &str = "event_id";
+const EVENT_TYPE: &str = "event_type";
+const OCCURRED_AT: &str = "occurred_at";
+const EMAIL: &str = "email";
+const IP: &str = "ip";
+const USER_AGENT: &str = "user_agent";
+const DETAIL: &str = "detail";
+
+/// ユーザーを特定できないイベントの`user_id`です
+const ANONYMOUS_USER_ID: &str = "anonymous";
+
+/// `event_type`をパーティションキー、`event_key`をソートキーとするGSIです。すべての属性を射影します
+const EVENT_TYPE_INDEX: &str = "event_type-index";
+
+const KEY_ATTR: &str = "#key";
+const KEY_VALUE: &str = ":key";
+const EVENT_KEY_ATTR: &str = "#event_key";
+const FROM_VALUE: &str = ":from";
+const TO_VALUE: &str = ":to";
+const EVENT_TYPE_ATTR: &str = "#event_type";
+const EVENT_TYPE_VALUE: &str = ":event_type";
+
+/// 発生日時が`occurred_at`のイベントの`event_key`はすべてこの値より大きく、次の秒の値より小さくなります
+fn key_prefix(occurred_at: i64) -> String {
+    format!("{:010}", occurred_at.max(0))
+}
+
+fn event_key(event: &SecurityEvent) -> String {
+    format!("{}#{}", key_prefix(event.occurred_at()), event.event_id())
+}
+
+impl domain::repository::security_event_repository::SecurityEventRepository for SecurityEventRepositoryImpl {
+    /// 同じキーのイベントは上書きしません
+    fn save(
+        &self, event: SecurityEvent,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), SecurityEventError>> + Send + '_>> {
+        Box::pin(async move {
+            let user_id = event.user_id().map(ToString::to_string).unwrap_or_else(|| ANONYMOUS_USER_ID.to_string());
+            let mut request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item(USER_ID, AttributeValue::S(user_id))
+                .item(EVENT_KEY, AttributeValue::S(event_key(&event)))
+                .item(EVENT_ID, AttributeValue::S(event.event_id().to_string()))
+                .item(EVENT_TYPE, AttributeValue::S(event.event_type().to_string()))
+                .item(OCCURRED_AT, AttributeValue::N(event.occurred_at().to_string()))
+                .item(IP, AttributeValue::S(event.ip().to_string()))
+                .item(USER_AGENT, AttributeValue::S(event.user_agent().to_string()))
+                .condition_expression("attribute_not_exists(#event_key)")
+                .expression_attribute_names(EVENT_KEY_ATTR, EVENT_KEY);
+            if let Some(v) = event.email() {
+                request = request.item(EMAIL, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = event.detail() {
+                request = request.item(DETAIL, AttributeValue::S(v.to_string()));
+            }
+
+            request.send().await.map_err(|e| {
+                let error = SecurityEventError::SaveError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+            Ok(())
+        })
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId, event_type: Option<SecurityEventType>, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>
+    {
+        let user_id = user_id.to_string();
+
+        Box::pin(async move { self.query(None, USER_ID, user_id, event_type, from, to, limit).await })
+    }
+
+    fn find_by_event_type(
+        &self, event_type: SecurityEventType, from: i64, to: i64, limit: usize,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SecurityEvent>, SecurityEventError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            self.query(Some(EVENT_TYPE_INDEX), EVENT_TYPE, event_type.to_string(), None, from, to, limit).await
+        })
+    }
+}
+
+impl crate::mapper::Mapper<SecurityEvent, SecurityEventError> for SecurityEventRepositoryImpl {
+    fn map_to_domain_model(v: HashMap<String, AttributeValue>) -> Result<SecurityEvent, SecurityEventError> {
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string};
+
+        let user_id = match as_string(v.get(USER_ID), "").as_str() {
+            ANONYMOUS_USER_ID => None,
+            user_id => Some(
+                UserId::from_str(user_id).map_err(|e| SecurityEventError::FindError(format!("{}: {}", e, user_id)))?,
+            ),
+        };
+        let event_type = SecurityEventType::from_str(&as_string(v.get(EVENT_TYPE), ""))
+            .map_err(|e| SecurityEventError::FindError(e.to_string()))?;
+
+        Ok(SecurityEvent::new(
+            as_string(v.get(EVENT_ID), ""),
+            user_id,
+            v.get(EMAIL).map(|_| as_string(v.get(EMAIL), "")),
+            event_type,
+            as_i64(v.get(OCCURRED_AT), 0),
+            as_string(v.get(IP), ""),
+            as_string(v.get(USER_AGENT), ""),
+            v.get(DETAIL).map(|_| as_string(v.get(DETAIL), "")),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapper::Mapper;
+
+    use super::*;
+
+    fn item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (EVENT_KEY.to_string(), AttributeValue::S("1700000000#evt_1".to_string())),
+            (EVENT_ID.to_string(), AttributeValue::S("evt_1".to_string())),
+            (EVENT_TYPE.to_string(), AttributeValue::S("login_succeeded".to_string())),
+            (OCCURRED_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+            (IP.to_string(), AttributeValue::S("192.0.2.1".to_string())),
+            (USER_AGENT.to_string(), AttributeValue::S("Firefox/121.0".to_string())),
+            (DETAIL.to_string(), AttributeValue::S("password".to_string())),
+        ])
+    }
+
+    #[test]
+    fn test_map_to_domain_model() {
+        let result = SecurityEventRepositoryImpl::map_to_domain_model(item()).unwrap();
+        assert_eq!("evt_1", result.event_id());
+        assert_eq!(
+            Some("usr_550e8400-e29b-41d4-a716-446655440000".to_string()),
+            result.user_id().map(|v| v.to_string())
+        );
+        assert_eq!(None, result.email());
+        assert_eq!(SecurityEventType::LoginSucceeded, result.event_type());
+        assert_eq!(1700000000, result.occurred_at());
+        assert_eq!("192.0.2.1", result.ip());
+        assert_eq!("Firefox/121.0", result.user_agent());
+        assert_eq!(Some("password"), result.detail());
+
+        let mut item = item();
+        item.insert(USER_ID.to_string(), AttributeValue::S(ANONYMOUS_USER_ID.to_string()));
+        item.insert(EMAIL.to_string(), AttributeValue::S("hoge@email.com".to_string()));
+        item.remove(DETAIL);
+        let result = SecurityEventRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!(None, result.user_id());
+        assert_eq!(Some("hoge@email.com"), result.email());
+        assert_eq!(None, result.detail());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_invalid() {
+        let test_cases = vec![
+            (USER_ID.to_string(), AttributeValue::S("invalid".to_string())),
+            (EVENT_TYPE.to_string(), AttributeValue::S("login".to_string())),
+        ];
+
+        for (key, value) in test_cases {
+            let mut item = item();
+            item.insert(key.clone(), value);
+            let result = SecurityEventRepositoryImpl::map_to_domain_model(item);
+            assert!(matches!(result, Err(SecurityEventError::FindError(_))), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_event_key() {
+        let event = SecurityEvent::occurred(
+            "evt_1".to_string(),
+            SecurityEventType::LoginFailed,
+            1700000000,
+            "192.0.2.1".to_string(),
+            "".to_string(),
+        );
+        assert_eq!("1700000000#evt_1", event_key(&event));
+        assert!(key_prefix(1700000000) < event_key(&event));
+        assert!(event_key(&event) < key_prefix(1700000001));
+        assert_eq!("0000000100", key_prefix(100));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::{AttributeValue, DeleteRequest, WriteRequest};
+use domain::{
+    user::user_id::UserId,
+    user_data::{UserDataError, UserDataItem},
+};
+
+/// `BatchWriteItem`で一度に削除できる項目の上限です
+const BATCH_SIZE: usize = 25;
+/// 未処理の項目を再送する回数の上限です
+const MAX_RETRIES: usize = 5;
+
+/// パーティションキーが`user_id`のテーブルから、ユーザーのデータを取得、削除します
+///
+/// サブスクリプション、カテゴリー、支払い方法のテーブルで共通して使います
+#[derive(Debug)]
+pub struct UserDataRepositoryImpl {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    /// テーブルのソートキーの属性名です
+    sort_key: String,
+    target: String,
+}
+
+impl UserDataRepositoryImpl {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String, sort_key: &str, target: &str) -> Self {
+        UserDataRepositoryImpl { client, table_name, sort_key: sort_key.to_string(), target: target.to_string() }
+    }
+
+    /// ユーザーの項目を返します。`keys_only`の場合はキーの属性のみを取得します
+    async fn query(
+        &self, user_id: &str, keys_only: bool,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>, UserDataError> {
+        let mut items = vec![];
+        let mut exclusive_start_key = None;
+        loop {
+            let mut query = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("#user_id = :user_id")
+                .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user_id.to_string()))
+                .consistent_read(true)
+                .set_exclusive_start_key(exclusive_start_key);
+            if keys_only {
+                query = query
+                    .projection_expression("#user_id, #sort_key")
+                    .expression_attribute_names(SORT_KEY_ATTR, &self.sort_key);
+            }
+            let result = query.send().await.map_err(|e| {
+                let error = UserDataError::FindError(crate::dynamodb_error_message(&e));
+                tracing::error!("{:?}", error);
+                error
+            })?;
+
+            items.extend(result.items.unwrap_or_default());
+            exclusive_start_key = result.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    async fn delete_batch(&self, keys: Vec<HashMap<String, AttributeValue>>) -> Result<(), UserDataError> {
+        let mut requests = keys
+            .into_iter()
+            .map(|key| {
+                DeleteRequest::builder()
+                    .set_key(Some(key))
+                    .build()
+                    .map(|v| WriteRequest::builder().delete_request(v).build())
+                    .map_err(|e| UserDataError::DeleteError(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for _ in 0..=MAX_RETRIES {
+            let result =
+                self.client.batch_write_item().request_items(&self.table_name, requests).send().await.map_err(|e| {
+                    let error = UserDataError::DeleteError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            requests = result.unprocessed_items.and_then(|mut v| v.remove(&self.table_name)).unwrap_or_default();
+            if requests.is_empty() {
+                return Ok(());
+            }
+        }
+
+        let error = UserDataError::DeleteError(format!(
+            "{} items were not processed. table: {}",
+            requests.len(),
+            self.table_name
+        ));
+        tracing::error!("{:?}", error);
+        Err(error)
+    }
+}
+
+const USER_ID: &str = "user_id";
+
+const USER_ID_ATTR: &str = "#user_id";
+const USER_ID_VALUE: &str = ":user_id";
+const SORT_KEY_ATTR: &str = "#sort_key";
+
+impl domain::repository::user_data_repository::UserDataRepository for UserDataRepositoryImpl {
+    fn target(&self) -> &str {
+        &self.target
+    }
+
+    fn find_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<UserDataItem>, UserDataError>> + Send + '_>>
+    {
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let items = self.query(&user_id, false).await?;
+            Ok(items
+                .iter()
+                .map(|v| v.iter().map(|(k, v)| (k.clone(), crate::mapper::as_field_value(v))).collect())
+                .collect())
+        })
+    }
+
+    fn delete_by_user_id(
+        &self, user_id: &UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize, UserDataError>> + Send + '_>> {
+        let user_id = user_id.to_string();
+
+        Box::pin(async move {
+            let keys = self.query(&user_id, true).await?;
+            let count = keys.len();
+            for chunk in keys.chunks(BATCH_SIZE) {
+                self.delete_batch(chunk.to_vec()).await?;
+            }
+            Ok(count)
+        })
+    }
+}
@@ -15,22 +15,28 @@ const EMAIL: &str = "email";
 const NAME: &str = "name";
 const USER_TYPE: &str = "user_type";
 const PROFILE_ICON_PATH: &str = "profile_icon_path";
+const ROLE: &str = "role";
+/// ゲストユーザーを削除するDynamoDBのTTL属性です
+const EXPIRES_AT: &str = "expires_at";
+const EMAIL_INDEX: &str = "email-index";
 
-const USER_ID_ATTR: &str = "#user_id";
 const EMAIL_ATTR: &str = "#email";
 const NAME_ATTR: &str = "#name";
 const USER_TYPE_ATTR: &str = "#user_type";
 const PROFILE_ICON_PATH_ATTR: &str = "#profile_icon_path";
+const ROLE_ATTR: &str = "#role";
+const EXPIRES_AT_ATTR: &str = "#expires_at";
 
-const USER_ID_VALUE: &str = ":user_id";
 const EMAIL_VALUE: &str = ":email";
 const NAME_VALUE: &str = ":name";
 const USER_TYPE_VALUE: &str = ":user_type";
 const PROFILE_ICON_PATH_VALUE: &str = ":profile_icon_path";
+const ROLE_VALUE: &str = ":role";
+const EXPIRES_AT_VALUE: &str = ":expires_at";
 
 impl domain::repository::user_repository::UserRepository for UserRepositoryImpl {
     fn find_by_id(
-        &self, id: i32,
+        &self, id: &domain::user::user_id::UserId,
     ) -> std::pin::Pin<
         Box<
             dyn std::future::Future<Output = Result<domain::user::User, domain::user::user_error::UserError>>
@@ -41,7 +47,9 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
         use crate::mapper::Mapper;
         use aws_sdk_dynamodb::error::ProvideErrorMetadata;
 
-        let result = Box::pin(async move {
+        let id = id.clone();
+
+        Box::pin(async move {
             let result = self
                 .client
                 .get_item()
@@ -63,13 +71,83 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
                     UserRepositoryImpl::map_to_domain_model(item)
                 }
                 None => {
-                    let error = Err(domain::user::user_error::UserError::FindByIdError(id.to_string()));
+                    let error = Err(domain::user::user_error::UserError::NotFound(id.to_string()));
                     tracing::error!("{:?}", error);
                     error
                 }
             }
-        });
-        result
+        })
+    }
+
+    fn find_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = Result<domain::user::User, domain::user::user_error::UserError>>
+                + Send
+                + '_,
+        >,
+    > {
+        use crate::mapper::Mapper;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let email = email.clone();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(EMAIL_INDEX)
+                .key_condition_expression("#email = :email")
+                .expression_attribute_names(EMAIL_ATTR, EMAIL)
+                .expression_attribute_values(EMAIL_VALUE, AttributeValue::S(email.to_string()))
+                .send()
+                .await
+                .map_err(|e| {
+                    let error =
+                        domain::user::user_error::UserError::FindByEmailError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            match result.items.unwrap_or_default().into_iter().next() {
+                Some(item) => UserRepositoryImpl::map_to_domain_model(item),
+                None => Err(domain::user::user_error::UserError::NotFound(email.to_string())),
+            }
+        })
+    }
+
+    fn exists_by_email(
+        &self, email: &domain::user::email::Email,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<bool, domain::user::user_error::UserError>> + Send + '_>,
+    > {
+        use aws_sdk_dynamodb::types::{AttributeValue, Select};
+
+        let email = email.clone();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(EMAIL_INDEX)
+                .key_condition_expression("#email = :email")
+                .expression_attribute_names(EMAIL_ATTR, EMAIL)
+                .expression_attribute_values(EMAIL_VALUE, AttributeValue::S(email.to_string()))
+                .select(Select::Count)
+                .send()
+                .await
+                .map_err(|e| {
+                    let error =
+                        domain::user::user_error::UserError::FindByEmailError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    error
+                })?;
+
+            Ok(result.count > 0)
+        })
     }
 
     fn create(
@@ -79,8 +157,8 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
         use aws_sdk_dynamodb::error::ProvideErrorMetadata;
         use aws_sdk_dynamodb::types::AttributeValue;
 
-        let result = Box::pin(async move {
-            let result = self
+        Box::pin(async move {
+            let mut result = self
                 .client
                 .put_item()
                 .table_name(&self.table_name)
@@ -88,16 +166,13 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
                 .item(EMAIL, AttributeValue::S(user.email().to_string()))
                 .item(NAME, AttributeValue::S(user.name().to_string()))
                 .item(USER_TYPE, AttributeValue::S(user.user_type().to_string()))
-                .item(
-                    PROFILE_ICON_PATH,
-                    AttributeValue::S({
-                        if let Some(v) = user.profile_icon_path() {
-                            v.to_string()
-                        } else {
-                            "0".to_string()
-                        }
-                    }),
-                );
+                .item(ROLE, AttributeValue::S(user.role().to_string()));
+            if let Some(v) = user.profile_icon_path() {
+                result = result.item(PROFILE_ICON_PATH, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = user.expires_at() {
+                result = result.item(EXPIRES_AT, AttributeValue::N(v.to_string()));
+            }
 
             match result.send().await {
                 Ok(p) => {
@@ -114,8 +189,7 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
                     error
                 }
             }
-        });
-        result
+        })
     }
 
     fn update(
@@ -125,40 +199,38 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
         use aws_sdk_cognitoidentityprovider::error::ProvideErrorMetadata;
         use aws_sdk_dynamodb::types::AttributeValue;
 
-        let result = Box::pin(async move {
-            let result = self.client
+        Box::pin(async move {
+            let mut builder = self
+                .client
                 .update_item()
                 .table_name(&self.table_name)
                 .key(USER_ID, aws_sdk_dynamodb::types::AttributeValue::S(user.user_id().to_string()))
-                .update_expression("SET #email = :email, #name = :name, #user_type = :user_type, #profile_icon_path = :profile_icon_path")
-                .expression_attribute_names(USER_ID_ATTR, USER_ID)
+                .update_expression(update_expression(&user))
                 .expression_attribute_names(EMAIL_ATTR, EMAIL)
                 .expression_attribute_names(NAME_ATTR, NAME)
                 .expression_attribute_names(USER_TYPE_ATTR, USER_TYPE)
                 .expression_attribute_names(PROFILE_ICON_PATH_ATTR, PROFILE_ICON_PATH)
-                .expression_attribute_values(USER_ID_VALUE, AttributeValue::S(user.user_id().to_string()))
+                .expression_attribute_names(ROLE_ATTR, ROLE)
+                .expression_attribute_names(EXPIRES_AT_ATTR, EXPIRES_AT)
                 .expression_attribute_values(EMAIL_VALUE, AttributeValue::S(user.email().to_string()))
                 .expression_attribute_values(NAME_VALUE, AttributeValue::S(user.name().to_string()))
                 .expression_attribute_values(USER_TYPE_VALUE, AttributeValue::S(user.user_type().to_string()))
-                .expression_attribute_values(
-                    PROFILE_ICON_PATH_VALUE,
-                    AttributeValue::S({
-                        if let Some(v) = user.profile_icon_path() {
-                            v.to_string()
-                        } else {
-                            "0".to_string()
-                        }
-                    }),
-                )
-                .send()
-                .await
-                .map_err(|e| {
-                    let msg = match e.message() {
-                        Some(s) => s.to_string(),
-                        None => e.to_string()
-                        };
-                    domain::user::user_error::UserError::UpdateUserError(msg)
-                });
+                .expression_attribute_values(ROLE_VALUE, AttributeValue::S(user.role().to_string()));
+            if let Some(v) = user.profile_icon_path() {
+                builder =
+                    builder.expression_attribute_values(PROFILE_ICON_PATH_VALUE, AttributeValue::S(v.to_string()));
+            }
+            if let Some(v) = user.expires_at() {
+                builder = builder.expression_attribute_values(EXPIRES_AT_VALUE, AttributeValue::N(v.to_string()));
+            }
+
+            let result = builder.send().await.map_err(|e| {
+                let msg = match e.message() {
+                    Some(s) => s.to_string(),
+                    None => e.to_string(),
+                };
+                domain::user::user_error::UserError::UpdateUserError(msg)
+            });
 
             match result {
                 Ok(v) => {
@@ -170,8 +242,58 @@ impl domain::repository::user_repository::UserRepository for UserRepositoryImpl
                     Err(domain::user::user_error::UserError::UpdateUserError(e.to_string()))
                 }
             }
-        });
-        result
+        })
+    }
+
+    fn delete(
+        &self, id: &domain::user::user_id::UserId,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), domain::user::user_error::UserError>> + Send + '_>>
+    {
+        let id = id.clone();
+
+        Box::pin(async move {
+            let result = self
+                .client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(USER_ID, aws_sdk_dynamodb::types::AttributeValue::S(id.to_string()))
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    let error = domain::user::user_error::UserError::DeleteUserError(crate::dynamodb_error_message(&e));
+                    tracing::error!("{:?}", error);
+                    Err(error)
+                }
+            }
+        })
+    }
+}
+
+/// 値がない属性は空の文字列などを保存せず、属性ごと取り除きます
+///
+/// 登録済みのユーザーはTTLで削除されないよう、削除する日時を取り除きます
+fn update_expression(user: &domain::user::User) -> String {
+    let mut set = vec![
+        "#email = :email",
+        "#name = :name",
+        "#user_type = :user_type",
+        "#role = :role",
+    ];
+    let mut remove = vec![];
+    match user.profile_icon_path() {
+        Some(_) => set.push("#profile_icon_path = :profile_icon_path"),
+        None => remove.push(PROFILE_ICON_PATH_ATTR),
+    }
+    match user.expires_at() {
+        Some(_) => set.push("#expires_at = :expires_at"),
+        None => remove.push(EXPIRES_AT_ATTR),
+    }
+    match remove.is_empty() {
+        true => format!("SET {}", set.join(", ")),
+        false => format!("SET {} REMOVE {}", set.join(", "), remove.join(", ")),
     }
 }
 
@@ -179,7 +301,9 @@ impl crate::mapper::Mapper<domain::user::User, domain::user::user_error::UserErr
     fn map_to_domain_model(
         v: std::collections::HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
     ) -> Result<domain::user::User, domain::user::user_error::UserError> {
-        use crate::mapper::as_string;
+        use std::str::FromStr;
+
+        use crate::mapper::{as_i64, as_string};
         use domain::user::user_error::UserError;
         use domain::user::User;
 
@@ -187,7 +311,8 @@ impl crate::mapper::Mapper<domain::user::User, domain::user::user_error::UserErr
         let email = as_string(v.get(EMAIL), "");
         let name = as_string(v.get(NAME), "");
         let user_type = as_string(v.get(USER_TYPE), "");
-        let profile_icon_path = Some(as_string(v.get(PROFILE_ICON_PATH), ""));
+        // アイコンがないユーザーに以前は"0"を保存していたため、属性がない場合と同じく扱います
+        let profile_icon_path = Some(as_string(v.get(PROFILE_ICON_PATH), "")).filter(|v| !v.is_empty() && v != "0");
 
         let user_type = match user_type.as_str() {
             "1" => 1,
@@ -198,7 +323,12 @@ impl crate::mapper::Mapper<domain::user::User, domain::user::user_error::UserErr
                 )))
             }
         };
-        Ok(User::build(&user_id, &email, &name, user_type, profile_icon_path)?)
+        // ロールを導入する前に作成したユーザーは`user`として扱います
+        let role = as_string(v.get(ROLE), "user");
+        let role = domain::user::role::Role::from_str(&role)?;
+        let expires_at = v.get(EXPIRES_AT).map(|_| as_i64(v.get(EXPIRES_AT), 0));
+        User::build(&user_id, &email, &name, user_type, profile_icon_path)
+            .map(|v| v.with_role(role).with_expires_at(expires_at))
     }
 }
 
@@ -225,9 +355,8 @@ mod tests {
             ]),
         ];
 
-        let _test = test_cases
-            .into_iter()
-            .map(|test| match UserRepositoryImpl::map_to_domain_model(test.clone()) {
+        for test in test_cases {
+            match UserRepositoryImpl::map_to_domain_model(test.clone()) {
                 Ok(v) => {
                     assert_eq!(v.user_id().value().to_string(), as_string(test.get(USER_ID), ""));
                     assert_eq!(v.email().to_string(), as_string(test.get(EMAIL), ""));
@@ -237,11 +366,97 @@ mod tests {
                     let icon_path = v.profile_icon_path().clone().unwrap();
                     assert_eq!(icon_path, as_string(test.get(PROFILE_ICON_PATH), ""))
                 }
-                Err(e) => {
-                    println!("{:?}", e);
-                    assert!(false)
-                }
-            })
-            .collect::<()>();
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_to_domain_model_without_icon() {
+        let item = |icon: Option<&str>| {
+            let mut item = HashMap::from([
+                (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+                (EMAIL.to_string(), AttributeValue::S("hoge123@email.com".to_string())),
+                (NAME.to_string(), AttributeValue::S("hoge".to_string())),
+                (USER_TYPE.to_string(), AttributeValue::S("1".to_string())),
+            ]);
+            if let Some(v) = icon {
+                item.insert(PROFILE_ICON_PATH.to_string(), AttributeValue::S(v.to_string()));
+            }
+            item
+        };
+        let test_cases = vec![
+            None,
+            Some(""),
+            Some("0"),
+        ];
+        for icon in test_cases {
+            let result = UserRepositoryImpl::map_to_domain_model(item(icon)).unwrap();
+            assert!(result.profile_icon_path().is_none(), "{:?}", icon);
+        }
+    }
+
+    #[test]
+    fn test_update_expression() {
+        let user =
+            domain::user::User::build("usr_550e8400-e29b-41d4-a716-446655440000", "hoge123@email.com", "hoge", 1, None)
+                .unwrap();
+        let icon = Some("icons/usr_550e8400-e29b-41d4-a716-446655440000/icon_1/256.png".to_string());
+        let test_cases = vec![
+            (
+                user.clone(),
+                "SET #email = :email, #name = :name, #user_type = :user_type, #role = :role REMOVE #profile_icon_path, #expires_at",
+            ),
+            (
+                user.change_profile_icon(icon.clone()),
+                "SET #email = :email, #name = :name, #user_type = :user_type, #role = :role, #profile_icon_path = :profile_icon_path REMOVE #expires_at",
+            ),
+            (
+                user.change_profile_icon(icon).with_expires_at(Some(1700000000)),
+                "SET #email = :email, #name = :name, #user_type = :user_type, #role = :role, #profile_icon_path = :profile_icon_path, #expires_at = :expires_at",
+            ),
+        ];
+        for (user, expected) in test_cases {
+            assert_eq!(expected, update_expression(&user));
+        }
+    }
+
+    #[test]
+    fn test_map_to_domain_model_guest() {
+        let item = HashMap::from([
+            (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+            (
+                EMAIL.to_string(),
+                AttributeValue::S("usr.550e8400-e29b-41d4-a716-446655440000@guest.subtrack.test".to_string()),
+            ),
+            (NAME.to_string(), AttributeValue::S("Guest".to_string())),
+            (USER_TYPE.to_string(), AttributeValue::S("2".to_string())),
+            (EXPIRES_AT.to_string(), AttributeValue::N("1700000000".to_string())),
+        ]);
+
+        let result = UserRepositoryImpl::map_to_domain_model(item).unwrap();
+        assert_eq!(domain::user::role::Role::User, result.role());
+        assert!(result.is_guest());
+        assert!(result.email().is_guest());
+        assert_eq!(Some(1700000000), result.expires_at());
+    }
+
+    #[test]
+    fn test_map_to_domain_model_role() {
+        let item = |role: &str| {
+            HashMap::from([
+                (USER_ID.to_string(), AttributeValue::S("usr_550e8400-e29b-41d4-a716-446655440000".to_string())),
+                (EMAIL.to_string(), AttributeValue::S("hoge123@email.com".to_string())),
+                (NAME.to_string(), AttributeValue::S("hoge".to_string())),
+                (USER_TYPE.to_string(), AttributeValue::S("1".to_string())),
+                (ROLE.to_string(), AttributeValue::S(role.to_string())),
+            ])
+        };
+
+        let result = UserRepositoryImpl::map_to_domain_model(item("admin")).unwrap();
+        assert_eq!(domain::user::role::Role::Admin, result.role());
+
+        let result = UserRepositoryImpl::map_to_domain_model(item("root"));
+        assert!(matches!(result, Err(domain::user::user_error::UserError::RoleError(_))));
     }
 }
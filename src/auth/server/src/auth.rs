@@ -0,0 +1,249 @@
+pub mod authenticated_user;
+pub mod authorization;
+pub mod client_device;
+pub mod client_ip;
+pub mod jwks;
+pub mod jwt_validator;
+pub mod oidc;
+
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+
+use crate::error::ApiError;
+
+const BEARER: &str = "Bearer ";
+
+/// `Authorization: Bearer`ヘッダーからトークンを取り出します
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Result<&str, ApiError> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix(BEARER))
+        .filter(|v| !v.is_empty())
+        .ok_or(ApiError::InvalidToken)
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{Query, State},
+        http::{header::LOCATION, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+
+    use super::{
+        jwks::{JwksCache, JwksSource},
+        jwt_validator::JwtValidator,
+    };
+
+    pub(crate) const JWKS_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/jwt/jwks.json");
+    const PRIVATE_KEY: &[u8] = include_bytes!("../testdata/jwt/private_key.pem");
+
+    pub(crate) const ISSUER: &str = "https://cognito-idp.ap-northeast-1.amazonaws.com/ap-northeast-1_test";
+    pub(crate) const AUDIENCE: &str = "client_id";
+    pub(crate) const SUB: &str = "550e8400-e29b-41d4-a716-446655440000";
+
+    /// 現在時刻から`expires_in`秒後に期限切れになるアクセストークンのクレームです
+    pub(crate) fn claims(expires_in: i64) -> serde_json::Value {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        serde_json::json!({
+            "sub": SUB,
+            "iss": ISSUER,
+            "client_id": AUDIENCE,
+            "token_use": "access",
+            "scope": "aws.cognito.signin.user.admin",
+            "jti": uuid::Uuid::new_v4().to_string(),
+            "iat": now,
+            "exp": now + expires_in,
+        })
+    }
+
+    pub(crate) fn sign(claims: &serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        jsonwebtoken::encode(&header, claims, &EncodingKey::from_rsa_pem(PRIVATE_KEY).unwrap()).unwrap()
+    }
+
+    pub(crate) fn access_token() -> String {
+        sign(&claims(3600))
+    }
+
+    pub(crate) fn validator() -> JwtValidator {
+        JwtValidator::new(JwksCache::new(JwksSource::File(JWKS_FILE.into())), ISSUER.to_string(), AUDIENCE.to_string())
+    }
+
+    pub(crate) const OIDC_CLIENT_ID: &str = "oidc_client_id";
+    pub(crate) const OIDC_REDIRECT_URI: &str = "http://localhost:3000/callback";
+
+    /// 発行した認可コードと、認可リクエストで受け取った値です
+    struct IssuedCode {
+        nonce: String,
+        code_challenge: String,
+        redirect_uri: String,
+    }
+
+    #[derive(Clone)]
+    struct MockState {
+        issuer: String,
+        user: Arc<Mutex<serde_json::Value>>,
+        codes: Arc<Mutex<HashMap<String, IssuedCode>>>,
+    }
+
+    /// ローカルで起動するOpenID ConnectのIDプロバイダーです
+    ///
+    /// 認可エンドポイントは同意画面を省略し、`user`のクレームで認可コードを発行してリダイレクトします
+    /// トークンエンドポイントはPKCEの`code_verifier`を検証し、テスト用の鍵で署名したIDトークンを返します
+    pub(crate) struct MockOidcProvider {
+        pub(crate) issuer: String,
+        user: Arc<Mutex<serde_json::Value>>,
+    }
+
+    impl MockOidcProvider {
+        pub(crate) async fn start(user: serde_json::Value) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            let user = Arc::new(Mutex::new(user));
+            let state = MockState { issuer: issuer.clone(), user: user.clone(), codes: Default::default() };
+
+            let app = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(|| async { std::fs::read_to_string(JWKS_FILE).unwrap() }))
+                .route("/authorize", get(authorize))
+                .route("/token", post(token))
+                .with_state(state);
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            Self { issuer, user }
+        }
+
+        /// 以降に発行するIDトークンのクレームを置き換えます
+        pub(crate) fn set_user(&self, user: serde_json::Value) {
+            *self.user.lock().unwrap() = user;
+        }
+
+        pub(crate) fn settings(&self, name: &str) -> crate::OidcProviderSettings {
+            crate::OidcProviderSettings {
+                name: name.to_string(),
+                issuer: self.issuer.clone(),
+                client_id: OIDC_CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_uri: OIDC_REDIRECT_URI.to_string(),
+                response_mode: None,
+            }
+        }
+    }
+
+    async fn discovery(State(state): State<MockState>) -> Json<serde_json::Value> {
+        Json(serde_json::json!({
+            "issuer": state.issuer,
+            "authorization_endpoint": format!("{}/authorize", state.issuer),
+            "token_endpoint": format!("{}/token", state.issuer),
+            "jwks_uri": format!("{}/jwks", state.issuer),
+        }))
+    }
+
+    async fn authorize(State(state): State<MockState>, Query(query): Query<HashMap<String, String>>) -> Response {
+        let param = |key: &str| query.get(key).cloned().unwrap_or_default();
+        if param("response_type") != "code"
+            || param("client_id") != OIDC_CLIENT_ID
+            || param("code_challenge_method") != "S256"
+        {
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+
+        let code = uuid::Uuid::new_v4().to_string();
+        let issued = IssuedCode {
+            nonce: param("nonce"),
+            code_challenge: param("code_challenge"),
+            redirect_uri: param("redirect_uri"),
+        };
+        state.codes.lock().unwrap().insert(code.clone(), issued);
+
+        let location = reqwest::Url::parse_with_params(
+            &param("redirect_uri"),
+            &[
+                ("code", code.as_str()),
+                ("state", &param("state")),
+            ],
+        )
+        .unwrap();
+        (StatusCode::FOUND, [(LOCATION, location.to_string())]).into_response()
+    }
+
+    async fn token(State(state): State<MockState>, Form(form): Form<HashMap<String, String>>) -> Response {
+        use base64::Engine;
+        use sha2::Digest;
+
+        let param = |key: &str| form.get(key).cloned().unwrap_or_default();
+        // 認可コードは一度だけ使用できます
+        let issued = state.codes.lock().unwrap().remove(&param("code"));
+        let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(sha2::Sha256::digest(param("code_verifier").as_bytes()));
+        let issued = match issued {
+            Some(v)
+                if param("grant_type") == "authorization_code"
+                    && param("client_id") == OIDC_CLIENT_ID
+                    && param("redirect_uri") == v.redirect_uri
+                    && challenge == v.code_challenge =>
+            {
+                v
+            }
+            _ => {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "invalid_grant" }))).into_response()
+            }
+        };
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        let mut claims = serde_json::json!({
+            "iss": state.issuer,
+            "aud": OIDC_CLIENT_ID,
+            "nonce": issued.nonce,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        for (key, value) in state.user.lock().unwrap().as_object().unwrap() {
+            claims[key] = value.clone();
+        }
+
+        Json(serde_json::json!({
+            "access_token": "access_token",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "id_token": sign(&claims),
+        }))
+        .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        let test_cases = vec![
+            (Some("Bearer token"), Some("token")),
+            (Some("Bearer "), None),
+            (Some("bearer token"), None),
+            (Some("token"), None),
+            (None, None),
+        ];
+
+        for (value, expected) in test_cases {
+            let mut headers = HeaderMap::new();
+            if let Some(v) = value {
+                headers.insert(AUTHORIZATION, HeaderValue::from_static(v));
+            }
+            assert_eq!(expected, bearer_token(&headers).ok());
+        }
+    }
+}
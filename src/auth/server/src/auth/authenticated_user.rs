@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use application::services::{PersonalAccessTokenService, PERSONAL_ACCESS_TOKEN_PREFIX};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use domain::{
+    personal_access_token::Scope, repository::revoked_token_repository::RevokedTokenRepository, user::user_id::UserId,
+};
+
+use crate::error::ApiError;
+
+use super::{bearer_token, jwt_validator::AccessClaims, jwt_validator::JwtValidator};
+
+/// 検証済みのアクセストークンまたは個人用アクセストークンから取り出した認証ユーザーです
+///
+/// ハンドラーの引数に指定すると`Authorization: Bearer`ヘッダーを検証します
+/// ログアウトなどで無効化されたトークンは有効期限内でも拒否します
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: UserId,
+    pub authentication: Authentication,
+}
+
+/// 認証に使用したトークンです
+#[derive(Debug, Clone)]
+pub enum Authentication {
+    /// ログインで発行したアクセストークンです。すべての操作を許可します
+    AccessToken(AccessClaims),
+    /// 個人用アクセストークンです。許可したスコープの操作のみ行えます
+    PersonalAccessToken { token_id: String, scopes: Vec<Scope> },
+}
+
+impl AuthenticatedUser {
+    /// ログインで発行したアクセストークンのクレームを返します
+    ///
+    /// ログアウトやトークンの発行など、アカウントを操作するハンドラーで呼び出します
+    /// 個人用アクセストークンの場合は`ApiError::Forbidden`を返します
+    pub fn session(&self) -> Result<&AccessClaims, ApiError> {
+        match &self.authentication {
+            Authentication::AccessToken(claims) => Ok(claims),
+            Authentication::PersonalAccessToken { token_id, .. } => {
+                tracing::warn!(
+                    "personal access token is not allowed. user_id: {}, token_id: {}",
+                    self.user_id,
+                    token_id
+                );
+                Err(ApiError::Forbidden)
+            }
+        }
+    }
+
+    /// 個人用アクセストークンが`scope`を許可していない場合、`ApiError::Forbidden`を返します
+    pub fn ensure_scope(&self, scope: Scope) -> Result<(), ApiError> {
+        match &self.authentication {
+            Authentication::AccessToken(_) => Ok(()),
+            Authentication::PersonalAccessToken { scopes, .. } if scopes.contains(&scope) => Ok(()),
+            Authentication::PersonalAccessToken { token_id, .. } => {
+                tracing::warn!(
+                    "insufficient scope. user_id: {}, token_id: {}, required: {}",
+                    self.user_id,
+                    token_id,
+                    scope
+                );
+                Err(ApiError::Forbidden)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    S: Send + Sync,
+    Arc<JwtValidator>: FromRef<S>,
+    Arc<dyn RevokedTokenRepository>: FromRef<S>,
+    Arc<dyn PersonalAccessTokenService>: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
+
+        let token = bearer_token(&parts.headers)?;
+        let user = match token.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX) {
+            true => {
+                let token = Arc::<dyn PersonalAccessTokenService>::from_ref(state).authenticate(token).await?;
+                AuthenticatedUser {
+                    user_id: token.user_id().clone(),
+                    authentication: Authentication::PersonalAccessToken {
+                        token_id: token.token_id().to_string(),
+                        scopes: token.scopes().to_vec(),
+                    },
+                }
+            }
+            false => {
+                let claims = Arc::<JwtValidator>::from_ref(state).validate(token).await?;
+                let sub = uuid::Uuid::parse_str(&claims.sub).map_err(|_| {
+                    tracing::warn!("sub is not uuid: {}", claims.sub);
+                    ApiError::InvalidToken
+                })?;
+                if let Some(jti) = &claims.jti {
+                    if Arc::<dyn RevokedTokenRepository>::from_ref(state).is_revoked(jti).await? {
+                        tracing::info!("revoked token. jti: {}", jti);
+                        return Err(ApiError::InvalidToken);
+                    }
+                }
+                AuthenticatedUser { user_id: UserId::from(sub), authentication: Authentication::AccessToken(claims) }
+            }
+        };
+
+        parts.extensions.insert(user.clone());
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use application::services::personal_access_token_service::PersonalAccessTokenServiceImpl;
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use domain::revoked_token::RevokedToken;
+    use infrastructure::repository_impl::{
+        in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+        in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+    };
+    use tower::ServiceExt;
+
+    use crate::auth::testing::{claims, sign, validator, SUB};
+
+    use super::*;
+
+    const REVOKED_JTI: &str = "revoked";
+
+    #[derive(Clone)]
+    struct TestState {
+        jwt_validator: Arc<JwtValidator>,
+        revoked_token_repository: Arc<dyn RevokedTokenRepository>,
+        personal_access_token_service: Arc<dyn PersonalAccessTokenService>,
+    }
+
+    impl FromRef<TestState> for Arc<JwtValidator> {
+        fn from_ref(input: &TestState) -> Self {
+            input.jwt_validator.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<dyn RevokedTokenRepository> {
+        fn from_ref(input: &TestState) -> Self {
+            input.revoked_token_repository.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<dyn PersonalAccessTokenService> {
+        fn from_ref(input: &TestState) -> Self {
+            input.personal_access_token_service.clone()
+        }
+    }
+
+    fn personal_access_token_service() -> Arc<dyn PersonalAccessTokenService> {
+        Arc::new(PersonalAccessTokenServiceImpl::new(
+            Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+            std::time::Duration::from_secs(60 * 60),
+        ))
+    }
+
+    async fn handler(user: AuthenticatedUser) -> String {
+        user.user_id.to_string()
+    }
+
+    async fn send(authorization: Option<String>) -> (StatusCode, String) {
+        send_with(personal_access_token_service(), authorization).await
+    }
+
+    async fn send_with(
+        personal_access_token_service: Arc<dyn PersonalAccessTokenService>, authorization: Option<String>,
+    ) -> (StatusCode, String) {
+        let revoked_token_repository = InMemoryRevokedTokenRepository::new();
+        revoked_token_repository.save(RevokedToken::new(REVOKED_JTI.to_string(), i64::MAX)).await.unwrap();
+        let state = TestState {
+            jwt_validator: Arc::new(validator()),
+            revoked_token_repository: Arc::new(revoked_token_repository),
+            personal_access_token_service,
+        };
+        let app = Router::new().route("/", get(handler)).with_state(state);
+        let mut request = Request::get("/");
+        if let Some(v) = authorization {
+            request = request.header(AUTHORIZATION, v);
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_success() {
+        let (status, body) = send(Some(format!("Bearer {}", sign(&claims(3600))))).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(format!("usr_{}", SUB), body);
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_failed() {
+        let mut not_uuid = claims(3600);
+        not_uuid["sub"] = serde_json::json!("not-uuid");
+        let mut revoked = claims(3600);
+        revoked["jti"] = serde_json::json!(REVOKED_JTI);
+
+        let test_cases = vec![
+            (None, "AUTH_003"),
+            (Some("Basic dXNlcjpwYXNz".to_string()), "AUTH_003"),
+            (Some(format!("Bearer {}", sign(&claims(-3600)))), "AUTH_002"),
+            (Some(format!("Bearer {}", sign(&not_uuid))), "AUTH_003"),
+            (Some(format!("Bearer {}", sign(&revoked))), "AUTH_003"),
+            (Some(format!("Bearer {}invalid", PERSONAL_ACCESS_TOKEN_PREFIX)), "AUTH_003"),
+        ];
+
+        for (authorization, expected) in test_cases {
+            let (status, body) = send(authorization).await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status);
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(expected, body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_personal_access_token() {
+        let service = personal_access_token_service();
+        let user_id = UserId::new();
+        let issued = service
+            .issue(&user_id, "import", vec![Scope::SubscriptionsRead], std::time::Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let (status, body) = send_with(service.clone(), Some(format!("Bearer {}", issued.value))).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(user_id.to_string(), body);
+
+        service.revoke(&user_id, issued.token.token_id()).await.unwrap();
+        let (status, _) = send_with(service, Some(format!("Bearer {}", issued.value))).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+    }
+
+    #[test]
+    fn test_session_and_ensure_scope() {
+        let session = AuthenticatedUser {
+            user_id: UserId::new(),
+            authentication: Authentication::AccessToken(serde_json::from_value(claims(3600)).unwrap()),
+        };
+        assert!(session.session().is_ok());
+        assert!(session.ensure_scope(Scope::SubscriptionsWrite).is_ok());
+
+        let personal_access_token = AuthenticatedUser {
+            user_id: UserId::new(),
+            authentication: Authentication::PersonalAccessToken {
+                token_id: "token".to_string(),
+                scopes: vec![Scope::SubscriptionsRead],
+            },
+        };
+        assert!(matches!(personal_access_token.session(), Err(ApiError::Forbidden)));
+        assert!(personal_access_token.ensure_scope(Scope::SubscriptionsRead).is_ok());
+        assert!(matches!(personal_access_token.ensure_scope(Scope::SubscriptionsWrite), Err(ApiError::Forbidden)));
+    }
+}
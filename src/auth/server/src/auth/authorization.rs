@@ -0,0 +1,275 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use application::services::{PersonalAccessTokenService, UserService};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use domain::{
+    repository::revoked_token_repository::RevokedTokenRepository,
+    user::{
+        role::{Access, Permission},
+        user_error::UserError,
+        user_id::UserId,
+        User,
+    },
+};
+
+use crate::error::ApiError;
+
+use super::{authenticated_user::AuthenticatedUser, jwt_validator::JwtValidator};
+
+/// ハンドラーが要求する権限です
+///
+/// `Authorized<P>`の型引数に指定します
+pub trait RequiredPermission: Send + Sync + 'static {
+    const PERMISSION: Permission;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOwnResources;
+
+impl RequiredPermission for ReadOwnResources {
+    const PERMISSION: Permission = Permission::ReadOwnResources;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WriteOwnResources;
+
+impl RequiredPermission for WriteOwnResources {
+    const PERMISSION: Permission = Permission::WriteOwnResources;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReadAnyResources;
+
+impl RequiredPermission for ReadAnyResources {
+    const PERMISSION: Permission = Permission::ReadAnyResources;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WriteAnyResources;
+
+impl RequiredPermission for WriteAnyResources {
+    const PERMISSION: Permission = Permission::WriteAnyResources;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ManageRoles;
+
+impl RequiredPermission for ManageRoles {
+    const PERMISSION: Permission = Permission::ManageRoles;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReadSecurityEvents;
+
+impl RequiredPermission for ReadSecurityEvents {
+    const PERMISSION: Permission = Permission::ReadSecurityEvents;
+}
+
+/// 権限を確認した認証ユーザーです
+///
+/// ハンドラーの引数に`Authorized<ManageRoles>`のように指定すると、アクセストークンを検証したうえで
+/// ユーザーテーブルのロールが`P`の権限を持たない場合に`ApiError::Forbidden`で拒否します
+#[derive(Debug, Clone)]
+pub struct Authorized<P> {
+    pub user: User,
+    pub authenticated: AuthenticatedUser,
+    permission: PhantomData<P>,
+}
+
+impl<P> Authorized<P> {
+    pub fn user_id(&self) -> &UserId {
+        self.user.user_id()
+    }
+
+    /// `owner`のリソースに`access`できるか確認します
+    ///
+    /// サブスクリプション、カテゴリー、支払い方法など、ユーザーが所有するリソースを扱うハンドラーで呼び出します
+    pub fn ensure_access(&self, owner: &UserId, access: Access) -> Result<(), ApiError> {
+        if self.user.role().can_access(self.user_id(), owner, access) {
+            return Ok(());
+        }
+        tracing::warn!(
+            "access denied. user_id: {}, role: {}, owner: {}, access: {:?}",
+            self.user_id(),
+            self.user.role(),
+            owner,
+            access
+        );
+        Err(ApiError::Forbidden)
+    }
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for Authorized<P>
+where
+    S: Send + Sync,
+    P: RequiredPermission,
+    Arc<JwtValidator>: FromRef<S>,
+    Arc<dyn RevokedTokenRepository>: FromRef<S>,
+    Arc<dyn PersonalAccessTokenService>: FromRef<S>,
+    Arc<dyn UserService>: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let authenticated = AuthenticatedUser::from_request_parts(parts, state).await?;
+        let user = Arc::<dyn UserService>::from_ref(state).find_by_id(&authenticated.user_id).await.map_err(|e| {
+            match e.downcast_ref::<UserError>() {
+                // トークンは有効でも、ユーザーが削除済みの場合はセッションを無効として扱います
+                Some(UserError::NotFound(_)) => {
+                    tracing::warn!("user not found. user_id: {}", authenticated.user_id);
+                    ApiError::InvalidToken
+                }
+                _ => ApiError::from(e),
+            }
+        })?;
+
+        if !user.role().has(P::PERMISSION) {
+            tracing::warn!(
+                "permission denied. user_id: {}, role: {}, required: {:?}",
+                user.user_id(),
+                user.role(),
+                P::PERMISSION
+            );
+            return Err(ApiError::Forbidden);
+        }
+
+        Ok(Authorized { user, authenticated, permission: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use application::services::{
+        personal_access_token_service::PersonalAccessTokenServiceImpl, user_service::UserServiceImpl,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use domain::user::role::Role;
+    use infrastructure::repository_impl::{
+        in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+        in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+        in_memory_user_repository::InMemoryUserRepository,
+    };
+    use tower::ServiceExt;
+
+    use crate::auth::{
+        authenticated_user::Authentication,
+        testing::{access_token, validator, SUB},
+    };
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestState {
+        jwt_validator: Arc<JwtValidator>,
+        revoked_token_repository: Arc<dyn RevokedTokenRepository>,
+        personal_access_token_service: Arc<dyn PersonalAccessTokenService>,
+        user_service: Arc<dyn UserService>,
+    }
+
+    impl FromRef<TestState> for Arc<JwtValidator> {
+        fn from_ref(input: &TestState) -> Self {
+            input.jwt_validator.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<dyn RevokedTokenRepository> {
+        fn from_ref(input: &TestState) -> Self {
+            input.revoked_token_repository.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<dyn PersonalAccessTokenService> {
+        fn from_ref(input: &TestState) -> Self {
+            input.personal_access_token_service.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<dyn UserService> {
+        fn from_ref(input: &TestState) -> Self {
+            input.user_service.clone()
+        }
+    }
+
+    async fn read(user: Authorized<ReadOwnResources>) -> String {
+        user.user_id().to_string()
+    }
+
+    async fn manage(user: Authorized<ManageRoles>) -> String {
+        user.user_id().to_string()
+    }
+
+    /// `role`のユーザーを登録し、`uri`にアクセストークンを付けてリクエストします
+    async fn send(role: Option<Role>, uri: &str) -> StatusCode {
+        let user_service = UserServiceImpl::new(Arc::new(InMemoryUserRepository::new()), std::time::Duration::ZERO);
+        if let Some(role) = role {
+            let user = User::build(&format!("usr_{}", SUB), "hoge@email.com", "hoge", 1, None).unwrap();
+            user_service.create_user(user.with_role(role)).await.unwrap();
+        }
+        let state = TestState {
+            jwt_validator: Arc::new(validator()),
+            revoked_token_repository: Arc::new(InMemoryRevokedTokenRepository::new()),
+            personal_access_token_service: Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            user_service: Arc::new(user_service),
+        };
+        let app = Router::new().route("/read", get(read)).route("/manage", get(manage)).with_state(state);
+        let request =
+            Request::get(uri).header(AUTHORIZATION, format!("Bearer {}", access_token())).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_authorized() {
+        let test_cases = vec![
+            (Some(Role::User), "/read", StatusCode::OK),
+            (Some(Role::User), "/manage", StatusCode::FORBIDDEN),
+            (Some(Role::Support), "/manage", StatusCode::FORBIDDEN),
+            (Some(Role::Admin), "/manage", StatusCode::OK),
+            (None, "/read", StatusCode::UNAUTHORIZED),
+        ];
+        for (role, uri, expected) in test_cases {
+            assert_eq!(expected, send(role, uri).await, "{:?} {}", role, uri);
+        }
+    }
+
+    #[test]
+    fn test_ensure_access() {
+        let owner = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+        let other = UserId::new();
+        let authorized = |role: Role| Authorized::<ReadOwnResources> {
+            user: User::build(&owner.to_string(), "hoge@email.com", "hoge", 1, None).unwrap().with_role(role),
+            authenticated: AuthenticatedUser {
+                user_id: owner.clone(),
+                authentication: Authentication::AccessToken(
+                    serde_json::from_value(crate::auth::testing::claims(3600)).unwrap(),
+                ),
+            },
+            permission: PhantomData,
+        };
+
+        let test_cases = vec![
+            (Role::User, &owner, Access::Write, true),
+            (Role::User, &other, Access::Read, false),
+            (Role::Support, &other, Access::Read, true),
+            (Role::Support, &other, Access::Write, false),
+            (Role::Admin, &other, Access::Write, true),
+        ];
+        for (role, target, access, expected) in test_cases {
+            assert_eq!(expected, authorized(role).ensure_access(target, access).is_ok());
+        }
+    }
+}
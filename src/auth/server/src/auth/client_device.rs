@@ -0,0 +1,124 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use domain::refresh_token::Device;
+
+use super::client_ip::{ClientIp, TrustedProxyHops};
+
+/// 保存する`User-Agent`の最大文字数です
+const USER_AGENT_MAX_CHARS: usize = 256;
+const UNKNOWN_DEVICE: &str = "Unknown device";
+
+/// ログインしたクライアントの端末です
+///
+/// 端末の名前は`User-Agent`のブラウザーとOSから`Chrome on macOS`のように作成します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientDevice(pub Device);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientDevice
+where
+    S: Send + Sync,
+    TrustedProxyHops: FromRef<S>,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+        let user_agent: String = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .unwrap_or_default()
+            .chars()
+            .take(USER_AGENT_MAX_CHARS)
+            .collect();
+
+        Ok(Self(Device::new(device_label(&user_agent), user_agent, ip)))
+    }
+}
+
+/// 判定できない場合は`Unknown device`を返します
+fn device_label(user_agent: &str) -> String {
+    // Chrome系のブラウザーは`Chrome`と`Safari`も含むため、固有の名前を先に判定します
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("FxiOS/", "Firefox"),
+        ("CriOS/", "Chrome"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name);
+    // iOSとAndroidは`Mac OS X`と`Linux`も含むため、先に判定します
+    let os = [
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Android", "Android"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .into_iter()
+    .find(|(token, _)| user_agent.contains(token))
+    .map(|(_, name)| name);
+
+    match (browser, os) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => UNKNOWN_DEVICE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    #[test]
+    fn test_device_label() {
+        let test_cases = vec![
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                "Chrome on macOS",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0",
+                "Edge on Windows",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+                "Safari on iOS",
+            ),
+            ("Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0", "Firefox on Linux"),
+            ("Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36", "Chrome on Android"),
+            ("SubTrack/1.0 (iPhone; iOS 17.1)", "iOS"),
+            ("curl/8.4.0", UNKNOWN_DEVICE),
+            ("", UNKNOWN_DEVICE),
+        ];
+        for (user_agent, expected) in test_cases {
+            assert_eq!(expected, device_label(user_agent), "{}", user_agent);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_device() {
+        let request = Request::get("/")
+            .header(USER_AGENT, format!("Firefox/121.0 (Windows) {}", "a".repeat(USER_AGENT_MAX_CHARS)))
+            .header("x-forwarded-for", "192.0.2.1");
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+
+        let ClientDevice(device) = ClientDevice::from_request_parts(&mut parts, &TrustedProxyHops(1)).await.unwrap();
+        assert_eq!("Firefox on Windows", device.label());
+        assert_eq!(USER_AGENT_MAX_CHARS, device.user_agent().chars().count());
+        assert_eq!("192.0.2.1", device.ip());
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const UNKNOWN: &str = "unknown";
+
+/// 接続元との間にある信頼できるプロキシの段数です
+///
+/// 既定値の0ではクライアントが自由に設定できる`X-Forwarded-For`を使用せず、接続元のアドレスのみを使用します
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrustedProxyHops(pub usize);
+
+/// リクエストの接続元IPアドレスです
+///
+/// `TrustedProxyHops`が1以上の場合、プロキシが`X-Forwarded-For`の末尾から追加した値のうち、最も外側の値を使用します
+/// 値がない場合やIPアドレスでない場合は接続元のアドレスを使用します
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIp(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    TrustedProxyHops: FromRef<S>,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let TrustedProxyHops(hops) = TrustedProxyHops::from_ref(state);
+        let forwarded = match hops {
+            0 => None,
+            _ => parts
+                .headers
+                .get(X_FORWARDED_FOR)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').nth(hops - 1))
+                .and_then(|v| IpAddr::from_str(v.trim()).ok()),
+        };
+
+        let ip = match forwarded {
+            Some(v) => v.to_string(),
+            None => parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|v| v.0.ip().to_string())
+                .unwrap_or_else(|| UNKNOWN.to_string()),
+        };
+        Ok(Self(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    async fn client_ip(hops: usize, forwarded: Option<&str>, connect_info: Option<&str>) -> String {
+        let mut request = Request::get("/");
+        if let Some(v) = forwarded {
+            request = request.header(X_FORWARDED_FOR, v);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        if let Some(v) = connect_info {
+            parts.extensions.insert(ConnectInfo(v.parse::<SocketAddr>().unwrap()));
+        }
+
+        let ClientIp(ip) = ClientIp::from_request_parts(&mut parts, &TrustedProxyHops(hops)).await.unwrap();
+        ip
+    }
+
+    #[tokio::test]
+    async fn test_client_ip() {
+        let long = "1".repeat(1000);
+        let test_cases = vec![
+            (0, Some("203.0.113.1, 192.0.2.1"), Some("10.0.0.1:80"), "10.0.0.1"),
+            (0, Some("192.0.2.1"), None, UNKNOWN),
+            (1, Some("203.0.113.1, 192.0.2.1"), Some("10.0.0.1:80"), "192.0.2.1"),
+            (2, Some("203.0.113.1, 192.0.2.1"), Some("10.0.0.1:80"), "203.0.113.1"),
+            (1, Some("192.0.2.1"), None, "192.0.2.1"),
+            (1, Some("2001:DB8::1"), None, "2001:db8::1"),
+            (3, Some("203.0.113.1, 192.0.2.1"), Some("10.0.0.1:80"), "10.0.0.1"),
+            (1, Some(" "), Some("10.0.0.1:80"), "10.0.0.1"),
+            (1, Some("203.0.113.1, not-an-ip"), Some("10.0.0.1:80"), "10.0.0.1"),
+            (1, Some(long.as_str()), Some("10.0.0.1:80"), "10.0.0.1"),
+            (1, None, Some("[2001:db8::1]:80"), "2001:db8::1"),
+            (1, None, None, UNKNOWN),
+        ];
+        for (hops, forwarded, connect_info, expected) in test_cases {
+            assert_eq!(expected, client_ip(hops, forwarded, connect_info).await, "{} {:?}", hops, forwarded);
+        }
+    }
+}
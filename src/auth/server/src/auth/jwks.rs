@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{jwk::JwkSet, DecodingKey};
+use tokio::sync::RwLock;
+
+/// JWKSの取得元です
+///
+/// 本番ではCognitoの`/.well-known/jwks.json`を、テストではローカルファイルを使用します
+#[derive(Debug, Clone)]
+pub enum JwksSource {
+    Url(String),
+    File(PathBuf),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwksError {
+    #[error("Failed to fetch jwks: {0}")]
+    FetchFailed(String),
+
+    #[error("Invalid jwks: {0}")]
+    InvalidJwks(String),
+
+    #[error("Key not found: {0}")]
+    KeyNotFound(String),
+}
+
+struct CachedKeys {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Instant,
+}
+
+/// JWKSの公開鍵をキャッシュします
+///
+/// キャッシュが`ttl`を過ぎた場合、または未知の`kid`を受け取った場合に再取得します
+/// 未知の`kid`による再取得は`min_refresh_interval`に一度までに制限します
+pub struct JwksCache {
+    source: JwksSource,
+    ttl: Duration,
+    min_refresh_interval: Duration,
+    http: reqwest::Client,
+    cache: RwLock<Option<CachedKeys>>,
+}
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+impl JwksCache {
+    pub fn new(source: JwksSource) -> Self {
+        Self::with_ttl(source, DEFAULT_TTL, DEFAULT_MIN_REFRESH_INTERVAL)
+    }
+
+    pub fn with_ttl(source: JwksSource, ttl: Duration, min_refresh_interval: Duration) -> Self {
+        Self { source, ttl, min_refresh_interval, http: reqwest::Client::new(), cache: RwLock::new(None) }
+    }
+
+    /// `kid`に対応する公開鍵を返します
+    pub async fn key(&self, kid: &str) -> Result<DecodingKey, JwksError> {
+        {
+            let cache = self.cache.read().await;
+            if let Some(cached) = cache.as_ref() {
+                let fresh = cached.fetched_at.elapsed() < self.ttl;
+                match cached.keys.get(kid) {
+                    Some(key) if fresh => return Ok(key.clone()),
+                    None if fresh && cached.fetched_at.elapsed() < self.min_refresh_interval => {
+                        return Err(JwksError::KeyNotFound(kid.to_string()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        // 他のタスクが先に再取得している場合はそれを使用します
+        if let Some(cached) = cache.as_ref() {
+            if cached.fetched_at.elapsed() < self.min_refresh_interval {
+                return cached.keys.get(kid).cloned().ok_or_else(|| JwksError::KeyNotFound(kid.to_string()));
+            }
+        }
+
+        let keys = self.fetch().await?;
+        tracing::info!("jwks refreshed. keys: {}", keys.len());
+        let key = keys.get(kid).cloned();
+        *cache = Some(CachedKeys { keys, fetched_at: Instant::now() });
+
+        key.ok_or_else(|| JwksError::KeyNotFound(kid.to_string()))
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, DecodingKey>, JwksError> {
+        let jwks: JwkSet = match &self.source {
+            JwksSource::Url(url) => self
+                .http
+                .get(url)
+                .send()
+                .await
+                .and_then(|v| v.error_for_status())
+                .map_err(|e| JwksError::FetchFailed(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| JwksError::InvalidJwks(e.to_string()))?,
+            JwksSource::File(path) => {
+                let body = tokio::fs::read(path).await.map_err(|e| JwksError::FetchFailed(e.to_string()))?;
+                serde_json::from_slice(&body).map_err(|e| JwksError::InvalidJwks(e.to_string()))?
+            }
+        };
+
+        jwks.keys
+            .iter()
+            .filter_map(|jwk| jwk.common.key_id.clone().map(|kid| (kid, jwk)))
+            .map(|(kid, jwk)| {
+                DecodingKey::from_jwk(jwk).map(|key| (kid, key)).map_err(|e| JwksError::InvalidJwks(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::testing::JWKS_FILE;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_key_from_file_success() {
+        let cache = JwksCache::new(JwksSource::File(JWKS_FILE.into()));
+        assert!(cache.key("test-key").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_key_not_found() {
+        let cache = JwksCache::new(JwksSource::File(JWKS_FILE.into()));
+        let result = cache.key("unknown").await;
+        assert!(matches!(result, Err(JwksError::KeyNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_key_uses_cache() {
+        let dir = std::env::temp_dir().join(format!("jwks_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jwks.json");
+        std::fs::copy(JWKS_FILE, &path).unwrap();
+
+        let cache = JwksCache::new(JwksSource::File(path.clone()));
+        assert!(cache.key("test-key").await.is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(cache.key("test-key").await.is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_key_fetch_failed() {
+        let cache = JwksCache::new(JwksSource::File("not_found.json".into()));
+        let result = cache.key("test-key").await;
+        assert!(matches!(result, Err(JwksError::FetchFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_key_from_url_success() {
+        let app = axum::Router::new().route(
+            "/.well-known/jwks.json",
+            axum::routing::get(|| async { std::fs::read_to_string(JWKS_FILE).unwrap() }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let cache = JwksCache::new(JwksSource::Url(format!("http://{}/.well-known/jwks.json", addr)));
+        assert!(cache.key("test-key").await.is_ok());
+    }
+}
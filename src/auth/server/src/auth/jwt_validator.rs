@@ -0,0 +1,160 @@
+use jsonwebtoken::{errors::ErrorKind, Algorithm, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+use super::jwks::JwksCache;
+
+const ACCESS_TOKEN_USE: &str = "access";
+
+/// Cognitoが発行するアクセストークンのクレームです
+///
+/// Cognitoのアクセストークンは`aud`の代わりに`client_id`を持つため、どちらも受け付けます
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub iss: String,
+    pub exp: u64,
+    pub token_use: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+/// RS256で署名されたアクセストークンをJWKSで検証します
+pub struct JwtValidator {
+    jwks: JwksCache,
+    issuer: String,
+    audience: String,
+}
+
+impl JwtValidator {
+    pub fn new(jwks: JwksCache, issuer: String, audience: String) -> Self {
+        Self { jwks, issuer, audience }
+    }
+
+    /// 署名、`exp`、`iss`、`aud`(`client_id`)、`token_use`を検証します
+    ///
+    /// 期限切れの場合は`ApiError::TokenExpired`、それ以外の不正は`ApiError::InvalidToken`を返します
+    pub async fn validate(&self, token: &str) -> Result<AccessClaims, ApiError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| {
+            tracing::warn!("invalid token header: {}", e);
+            ApiError::InvalidToken
+        })?;
+        if header.alg != Algorithm::RS256 {
+            tracing::warn!("unexpected algorithm: {:?}", header.alg);
+            return Err(ApiError::InvalidToken);
+        }
+        let kid = header.kid.ok_or_else(|| {
+            tracing::warn!("token has no kid");
+            ApiError::InvalidToken
+        })?;
+
+        let key = self.jwks.key(&kid).await.map_err(|e| {
+            tracing::warn!("{}", e);
+            ApiError::InvalidToken
+        })?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&[
+            "exp", "iss", "sub",
+        ]);
+        validation.validate_aud = false;
+
+        let claims = jsonwebtoken::decode::<AccessClaims>(token, &key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => ApiError::TokenExpired,
+                _ => {
+                    tracing::warn!("invalid token: {}", e);
+                    ApiError::InvalidToken
+                }
+            })?
+            .claims;
+
+        if claims.token_use != ACCESS_TOKEN_USE {
+            tracing::warn!("unexpected token_use: {}", claims.token_use);
+            return Err(ApiError::InvalidToken);
+        }
+        if claims.aud.as_deref().or(claims.client_id.as_deref()) != Some(self.audience.as_str()) {
+            tracing::warn!("unexpected audience. aud: {:?}, client_id: {:?}", claims.aud, claims.client_id);
+            return Err(ApiError::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::auth::testing::{claims, sign, validator, AUDIENCE};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate_success() {
+        let result = validator().validate(&sign(&claims(3600))).await;
+        assert!(result.is_ok());
+        assert_eq!(crate::auth::testing::SUB, result.unwrap().sub);
+    }
+
+    #[tokio::test]
+    async fn test_validate_with_aud_success() {
+        let mut claims = claims(3600);
+        claims["client_id"] = serde_json::Value::Null;
+        claims["aud"] = json!(AUDIENCE);
+
+        let result = validator().validate(&sign(&claims)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_expired() {
+        let result = validator().validate(&sign(&claims(-3600))).await;
+        assert!(matches!(result, Err(ApiError::TokenExpired)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_invalid() {
+        let test_cases = vec![
+            ("iss", json!("https://example.com")),
+            ("client_id", json!("other_client")),
+            ("token_use", json!("id")),
+            ("sub", serde_json::Value::Null),
+        ];
+
+        for (key, value) in test_cases {
+            let mut claims = claims(3600);
+            claims[key] = value;
+            let result = validator().validate(&sign(&claims)).await;
+            assert!(matches!(result, Err(ApiError::InvalidToken)), "{}", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_tampered() {
+        let token = sign(&claims(3600));
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let mut other = claims(3600);
+        other["sub"] = json!("123e4567-e89b-12d3-a456-426614174000");
+        let other = sign(&other);
+        parts[1] = other.split('.').nth(1).unwrap();
+
+        let test_cases = vec![
+            parts.join("."),
+            "invalid".to_string(),
+            "".to_string(),
+        ];
+        for token in test_cases {
+            let result = validator().validate(&token).await;
+            assert!(matches!(result, Err(ApiError::InvalidToken)));
+        }
+    }
+}
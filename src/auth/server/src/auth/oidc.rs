@@ -0,0 +1,478 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use base64::Engine;
+use domain::clock::{Clock, SystemClock};
+use jsonwebtoken::{Algorithm, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::OidcProviderSettings;
+
+use super::jwks::{JwksCache, JwksSource};
+
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+const SCOPE: &str = "openid email profile";
+const STATE_KEY_CONTEXT: &[u8] = b"subtrack.oidc.state";
+const NONCE_LENGTH: usize = 12;
+const CODE_VERIFIER_BYTES: usize = 32;
+const OIDC_NONCE_BYTES: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("Unknown provider: {0}")]
+    UnknownProvider(String),
+
+    #[error("Invalid state")]
+    InvalidState,
+
+    #[error("State expired")]
+    StateExpired,
+
+    #[error("Authorization code rejected: {0}")]
+    CodeRejected(String),
+
+    #[error("Invalid id token: {0}")]
+    InvalidIdToken(String),
+
+    #[error("Email is not verified: {0}")]
+    EmailNotVerified(String),
+
+    #[error("Provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
+    #[error("Failed to encrypt state: {0}")]
+    CryptoError(String),
+}
+
+/// 検証済みのIDトークンのクレームです
+///
+/// Appleは`email_verified`を文字列で返すため、真偽値と文字列のどちらも受け付けます
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+/// IDプロバイダーのディスカバリードキュメントのうち、使用する項目です
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+struct ProviderMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks: JwksCache,
+}
+
+/// 認可コードフローとPKCEでIDプロバイダーからIDトークンを受け取るクライアントです
+///
+/// エンドポイントと公開鍵は初回の使用時にディスカバリーで取得します
+pub struct OidcClient {
+    settings: OidcProviderSettings,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+impl OidcClient {
+    pub fn new(settings: OidcProviderSettings) -> Self {
+        Self { settings, http: reqwest::Client::new(), metadata: OnceCell::new() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!("{}{}", self.settings.issuer.trim_end_matches('/'), DISCOVERY_PATH);
+                let discovery: Discovery = self
+                    .http
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|v| v.error_for_status())
+                    .map_err(|e| OidcError::ProviderUnavailable(e.to_string()))?
+                    .json()
+                    .await
+                    .map_err(|e| OidcError::ProviderUnavailable(e.to_string()))?;
+
+                // 別の発行者を名乗るドキュメントは、なりすましとみなして使用しません
+                if discovery.issuer != self.settings.issuer {
+                    return Err(OidcError::ProviderUnavailable(format!("issuer mismatch: {}", discovery.issuer)));
+                }
+
+                tracing::info!("oidc discovery loaded. provider: {}", self.settings.name);
+                Ok(ProviderMetadata {
+                    authorization_endpoint: discovery.authorization_endpoint,
+                    token_endpoint: discovery.token_endpoint,
+                    jwks: JwksCache::new(JwksSource::Url(discovery.jwks_uri)),
+                })
+            })
+            .await
+    }
+
+    /// 認可エンドポイントのURLを作成します
+    pub async fn authorization_url(&self, state: &str, nonce: &str, code_challenge: &str) -> Result<String, OidcError> {
+        let metadata = self.metadata().await?;
+        let mut url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.settings.client_id),
+                ("redirect_uri", &self.settings.redirect_uri),
+                ("scope", SCOPE),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| OidcError::ProviderUnavailable(e.to_string()))?;
+        if let Some(response_mode) = &self.settings.response_mode {
+            url.query_pairs_mut().append_pair("response_mode", response_mode);
+        }
+
+        Ok(url.to_string())
+    }
+
+    /// 認可コードをトークンエンドポイントでIDトークンに交換します
+    ///
+    /// 認可コードやPKCEの`code_verifier`が拒否された場合は`OidcError::CodeRejected`を返します
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<String, OidcError> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            id_token: String,
+        }
+
+        let metadata = self.metadata().await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.settings.redirect_uri),
+            ("client_id", &self.settings.client_id),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = &self.settings.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| OidcError::ProviderUnavailable(e.to_string()))?;
+        let status = response.status();
+        if status.is_client_error() {
+            return Err(OidcError::CodeRejected(response.text().await.unwrap_or_else(|_| status.to_string())));
+        }
+        if !status.is_success() {
+            return Err(OidcError::ProviderUnavailable(status.to_string()));
+        }
+
+        response
+            .json::<TokenResponse>()
+            .await
+            .map(|v| v.id_token)
+            .map_err(|e| OidcError::ProviderUnavailable(e.to_string()))
+    }
+
+    /// IDトークンの署名、`iss`、`aud`、`exp`と、認可リクエストで送った`nonce`を検証します
+    pub async fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims, OidcError> {
+        let header = jsonwebtoken::decode_header(id_token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+        if header.alg != Algorithm::RS256 {
+            return Err(OidcError::InvalidIdToken(format!("unexpected algorithm: {:?}", header.alg)));
+        }
+        let kid = header.kid.ok_or_else(|| OidcError::InvalidIdToken("token has no kid".to_string()))?;
+        let key = self.metadata().await?.jwks.key(&kid).await.map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.settings.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+        validation.set_required_spec_claims(&[
+            "exp", "iss", "sub", "aud",
+        ]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| OidcError::InvalidIdToken(e.to_string()))?
+            .claims;
+
+        // 別の認可リクエストで発行されたIDトークンを差し込まれないよう、nonceの一致を確認します
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError::InvalidIdToken("nonce mismatch".to_string()));
+        }
+        Ok(claims)
+    }
+}
+
+/// 認可リクエストの開始時に作成し、`state`に暗号化して持たせる値です
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingAuthorization {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+    expires_at: i64,
+}
+
+/// 認可リクエストの開始時に返す値です
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+/// 設定されたIDプロバイダーで認可コードフローを開始、完了します
+///
+/// サーバーに状態を持たないよう、`nonce`とPKCEの`code_verifier`は暗号化して`state`に含めます
+/// `state`は改ざんできず、有効期限と発行したプロバイダーを確認してから使用します
+pub struct OidcProviders {
+    clients: HashMap<String, OidcClient>,
+    cipher: Aes256Gcm,
+    state_ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl OidcProviders {
+    pub fn new(clients: Vec<OidcClient>, state_key: &[u8], state_ttl: Duration) -> Self {
+        let key = Sha256::new().chain_update(STATE_KEY_CONTEXT).chain_update(state_key).finalize();
+        Self {
+            clients: clients.into_iter().map(|v| (v.name().to_string(), v)).collect(),
+            cipher: Aes256Gcm::new(&key),
+            state_ttl,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// `state`の有効期限に使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    fn client(&self, provider: &str) -> Result<&OidcClient, OidcError> {
+        self.clients.get(provider).ok_or_else(|| OidcError::UnknownProvider(provider.to_string()))
+    }
+
+    /// `nonce`とPKCEの`code_verifier`を生成し、認可エンドポイントのURLと`state`を返します
+    pub async fn authorize(&self, provider: &str) -> Result<AuthorizationRequest, OidcError> {
+        let client = self.client(provider)?;
+        let pending = PendingAuthorization {
+            provider: provider.to_string(),
+            nonce: random_token(OIDC_NONCE_BYTES),
+            code_verifier: random_token(CODE_VERIFIER_BYTES),
+            expires_at: self.clock.now() + self.state_ttl.as_secs() as i64,
+        };
+        let state = self.seal(&pending)?;
+        let authorization_url =
+            client.authorization_url(&state, &pending.nonce, &code_challenge(&pending.code_verifier)).await?;
+
+        Ok(AuthorizationRequest { authorization_url, state })
+    }
+
+    /// `state`を確認して認可コードをIDトークンに交換し、検証したクレームを返します
+    pub async fn callback(&self, provider: &str, code: &str, state: &str) -> Result<IdTokenClaims, OidcError> {
+        let client = self.client(provider)?;
+        let pending = self.open(state)?;
+        if pending.provider != provider {
+            return Err(OidcError::InvalidState);
+        }
+        if pending.expires_at <= self.clock.now() {
+            return Err(OidcError::StateExpired);
+        }
+
+        let id_token = client.exchange_code(code, &pending.code_verifier).await?;
+        client.validate_id_token(&id_token, &pending.nonce).await
+    }
+
+    fn seal(&self, pending: &PendingAuthorization) -> Result<String, OidcError> {
+        let plaintext = serde_json::to_vec(pending).map_err(|e| OidcError::CryptoError(e.to_string()))?;
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|e| OidcError::CryptoError(e.to_string()))?;
+
+        Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(
+            [
+                nonce.as_slice(),
+                &ciphertext,
+            ]
+            .concat(),
+        ))
+    }
+
+    fn open(&self, state: &str) -> Result<PendingAuthorization, OidcError> {
+        let bytes =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(state).map_err(|_| OidcError::InvalidState)?;
+        if bytes.len() < NONCE_LENGTH {
+            return Err(OidcError::InvalidState);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+        let plaintext =
+            self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| OidcError::InvalidState)?;
+
+        serde_json::from_slice(&plaintext).map_err(|_| OidcError::InvalidState)
+    }
+}
+
+/// PKCEの`code_challenge`(S256)です
+fn code_challenge(code_verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+fn random_token(length: usize) -> String {
+    let mut bytes = vec![0u8; length];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(v) => v,
+        Value::String(v) => v == "true",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::clock::FixedClock;
+    use serde_json::json;
+
+    use crate::auth::testing::MockOidcProvider;
+
+    use super::*;
+
+    const STATE_KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+    const NOW: i64 = 1_700_000_000;
+
+    fn providers(provider: &MockOidcProvider, state_ttl: Duration) -> OidcProviders {
+        OidcProviders::new(vec![OidcClient::new(provider.settings("google"))], STATE_KEY, state_ttl)
+            .with_clock(Arc::new(FixedClock(NOW)))
+    }
+
+    fn pending(expires_at: i64) -> PendingAuthorization {
+        PendingAuthorization {
+            provider: "google".to_string(),
+            nonce: "nonce".to_string(),
+            code_verifier: "code_verifier".to_string(),
+            expires_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let provider = MockOidcProvider::start(json!({ "sub": "1234567890" })).await;
+        let providers = providers(&provider, Duration::from_secs(600));
+
+        let request = providers.authorize("google").await.unwrap();
+        let url = reqwest::Url::parse(&request.authorization_url).unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert!(request.authorization_url.starts_with(&format!("{}/authorize?", provider.issuer)));
+        assert_eq!("code", query["response_type"]);
+        assert_eq!(SCOPE, query["scope"]);
+        assert_eq!("S256", query["code_challenge_method"]);
+        assert_eq!(request.state, query["state"]);
+        assert!(!query.contains_key("response_mode"));
+
+        // `state`にはnonceとPKCEのcode_verifierが含まれます
+        let pending = providers.open(&request.state).unwrap();
+        assert_eq!(pending.nonce, query["nonce"]);
+        assert_eq!(code_challenge(&pending.code_verifier), query["code_challenge"]);
+        assert_eq!(43, pending.code_verifier.len());
+        assert_eq!(NOW + 600, pending.expires_at);
+
+        let result = providers.authorize("unknown").await;
+        assert!(matches!(result, Err(OidcError::UnknownProvider(_))));
+    }
+
+    #[tokio::test]
+    async fn test_open_state() {
+        let provider = MockOidcProvider::start(json!({})).await;
+        let providers = providers(&provider, Duration::from_secs(600));
+        let state = providers.seal(&pending(NOW + 600)).unwrap();
+        assert_eq!("code_verifier", providers.open(&state).unwrap().code_verifier);
+
+        let other = OidcProviders::new(vec![], b"fedcba9876543210fedcba9876543210", Duration::from_secs(600));
+        let mut tampered = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&state).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        let test_cases = vec![
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(tampered),
+            other.seal(&pending(NOW + 600)).unwrap(),
+            "invalid!".to_string(),
+            "".to_string(),
+        ];
+        for state in test_cases {
+            assert!(matches!(providers.open(&state), Err(OidcError::InvalidState)), "{}", state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_callback_state_expired() {
+        let provider = MockOidcProvider::start(json!({})).await;
+        let providers = providers(&provider, Duration::from_secs(600));
+        let state = providers.seal(&pending(NOW - 1)).unwrap();
+
+        let result = providers.callback("google", "code", &state).await;
+        assert!(matches!(result, Err(OidcError::StateExpired)));
+    }
+
+    #[tokio::test]
+    async fn test_authorization_url_response_mode() {
+        let provider = MockOidcProvider::start(json!({})).await;
+        let settings =
+            OidcProviderSettings { response_mode: Some("form_post".to_string()), ..provider.settings("apple") };
+
+        let url = OidcClient::new(settings).authorization_url("state", "nonce", "challenge").await.unwrap();
+        assert!(url.ends_with("&response_mode=form_post"));
+    }
+
+    #[tokio::test]
+    async fn test_discovery_issuer_mismatch() {
+        let provider = MockOidcProvider::start(json!({})).await;
+        let settings = OidcProviderSettings { issuer: format!("{}/", provider.issuer), ..provider.settings("google") };
+
+        let result = OidcClient::new(settings).authorization_url("state", "nonce", "challenge").await;
+        assert!(matches!(result, Err(OidcError::ProviderUnavailable(_))));
+    }
+
+    #[test]
+    fn test_id_token_claims_email_verified() {
+        let test_cases = vec![
+            (json!({ "sub": "1", "email_verified": true }), true),
+            (json!({ "sub": "1", "email_verified": "true" }), true),
+            (json!({ "sub": "1", "email_verified": false }), false),
+            (json!({ "sub": "1", "email_verified": "false" }), false),
+            (json!({ "sub": "1" }), false),
+        ];
+        for (claims, expected) in test_cases {
+            let claims: IdTokenClaims = serde_json::from_value(claims).unwrap();
+            assert_eq!(expected, claims.email_verified);
+        }
+    }
+}
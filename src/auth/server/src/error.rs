@@ -0,0 +1,707 @@
+use axum::{
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use domain::{
+    account_deletion::AccountDeletionError, auth_user::AuthUserError, data_export::DataExportError,
+    external_identity::ExternalIdentityError, file::FileError, login_attempt::LoginAttemptError, mfa::MfaError,
+    personal_access_token::PersonalAccessTokenError, profile_icon::ProfileIconError, refresh_token::RefreshTokenError,
+    revoked_token::RevokedTokenError, security_event::SecurityEventError, token::TokenError,
+    user::password::PasswordError, user::user_error::UserError, user_data::UserDataError,
+};
+use serde::Serialize;
+
+use crate::auth::oidc::OidcError;
+
+/// APIのエラーです
+///
+/// `docs/station3/error_spec`のエラーコードに対応します
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
+    #[error("Token expired")]
+    TokenExpired,
+
+    #[error("Invalid token")]
+    InvalidToken,
+
+    #[error("Email already exists")]
+    EmailAlreadyExists,
+
+    /// ロックが解除されるまでの秒数を持ちます
+    #[error("Account locked: retry after {0} seconds")]
+    AccountLocked(u64),
+
+    #[error("Invalid MFA code")]
+    InvalidMfaCode,
+
+    #[error("MFA already enabled")]
+    MfaAlreadyEnabled,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    /// 再度リクエストできるまでの秒数を持ちます
+    #[error("Too many requests: retry after {0} seconds")]
+    TooManyRequests(u64),
+
+    #[error("Invalid email format")]
+    InvalidEmailFormat,
+
+    /// 満たしていないパスワードポリシーの規則の識別子を持ちます
+    #[error("Password too weak: {0:?}")]
+    PasswordTooWeak(Vec<String>),
+
+    #[error("Required field missing")]
+    RequiredFieldMissing,
+
+    #[error("Invalid date format")]
+    InvalidDateFormat,
+
+    /// 対応していない形式や、読み込めない画像です
+    #[error("Invalid image")]
+    InvalidImage,
+
+    #[error("Image too large")]
+    ImageTooLarge,
+
+    #[error("Resource not found")]
+    NotFound,
+
+    #[error("Account deletion already requested")]
+    DeletionAlreadyRequested,
+
+    /// 猶予期間が過ぎ、削除を開始した依頼です
+    #[error("Account deletion cannot be cancelled")]
+    DeletionNotCancellable,
+
+    #[error("Data export already requested")]
+    ExportAlreadyRequested,
+
+    /// 完了していないエクスポートのダウンロード用トークンは発行できません
+    #[error("Data export is not ready")]
+    ExportNotReady,
+
+    #[error("Internal server error: {0}")]
+    InternalServerError(String),
+
+    /// 設定している認証基盤では使用できない機能です
+    #[error("Not supported: {0}")]
+    NotSupported(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidCredentials => "AUTH_001",
+            ApiError::TokenExpired => "AUTH_002",
+            ApiError::InvalidToken => "AUTH_003",
+            ApiError::EmailAlreadyExists => "AUTH_004",
+            ApiError::AccountLocked(_) => "AUTH_005",
+            ApiError::InvalidMfaCode => "AUTH_006",
+            ApiError::MfaAlreadyEnabled => "AUTH_007",
+            ApiError::Forbidden => "AUTH_008",
+            ApiError::InvalidEmailFormat => "VAL_001",
+            ApiError::PasswordTooWeak(_) => "VAL_002",
+            ApiError::RequiredFieldMissing => "VAL_003",
+            ApiError::InvalidDateFormat => "VAL_004",
+            ApiError::InvalidImage => "VAL_005",
+            ApiError::ImageTooLarge => "VAL_006",
+            ApiError::NotFound => "RES_001",
+            ApiError::DeletionAlreadyRequested => "RES_002",
+            ApiError::DeletionNotCancellable => "RES_003",
+            ApiError::ExportAlreadyRequested => "RES_004",
+            ApiError::ExportNotReady => "RES_005",
+            ApiError::TooManyRequests(_) => "RATE_001",
+            ApiError::InternalServerError(_) => "SRV_001",
+            ApiError::DatabaseError(_) => "SRV_002",
+            ApiError::NotSupported(_) => "SRV_003",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidCredentials
+            | ApiError::TokenExpired
+            | ApiError::InvalidToken
+            | ApiError::InvalidMfaCode => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::EmailAlreadyExists
+            | ApiError::MfaAlreadyEnabled
+            | ApiError::DeletionAlreadyRequested
+            | ApiError::DeletionNotCancellable
+            | ApiError::ExportAlreadyRequested
+            | ApiError::ExportNotReady => StatusCode::CONFLICT,
+            ApiError::AccountLocked(_) | ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::InvalidEmailFormat
+            | ApiError::PasswordTooWeak(_)
+            | ApiError::RequiredFieldMissing
+            | ApiError::InvalidDateFormat
+            | ApiError::InvalidImage => StatusCode::BAD_REQUEST,
+            ApiError::ImageTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::InternalServerError(_) | ApiError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::NotSupported(_) => StatusCode::NOT_IMPLEMENTED,
+        }
+    }
+
+    /// ユーザー表示用のメッセージです
+    pub fn message(&self) -> &'static str {
+        match self {
+            ApiError::InvalidCredentials => "メールアドレスまたはパスワードが正しくありません",
+            ApiError::TokenExpired => "セッションの有効期限が切れました。再度ログインしてください",
+            ApiError::InvalidToken => "認証エラーが発生しました。再度ログインしてください",
+            ApiError::EmailAlreadyExists => "このメールアドレスは既に登録されています",
+            ApiError::AccountLocked(_) => "ログインの試行回数が上限に達しました。しばらくしてから再度お試しください",
+            ApiError::InvalidMfaCode => "認証コードが正しくありません",
+            ApiError::MfaAlreadyEnabled => "2段階認証は既に有効です",
+            ApiError::Forbidden => "この操作を行う権限がありません",
+            ApiError::InvalidEmailFormat => "正しいメールアドレスの形式で入力してください",
+            ApiError::PasswordTooWeak(_) => "パスワードが要件を満たしていません。詳細を確認して設定してください",
+            ApiError::RequiredFieldMissing => "必須項目を入力してください",
+            ApiError::InvalidDateFormat => "正しい日付形式で入力してください",
+            ApiError::InvalidImage => "画像を読み込めません。PNG、JPEG、WebPの画像を選択してください",
+            ApiError::ImageTooLarge => "画像のサイズが大きすぎます。5MB以下の画像を選択してください",
+            ApiError::NotFound => "指定されたデータが見つかりません",
+            ApiError::DeletionAlreadyRequested => "アカウントの削除は既に申請されています",
+            ApiError::DeletionNotCancellable => "アカウントの削除を開始したため、キャンセルできません",
+            ApiError::ExportAlreadyRequested => "データのエクスポートは既に依頼されています",
+            ApiError::ExportNotReady => "データのエクスポートが完了していません",
+            ApiError::TooManyRequests(_) => "リクエストが集中しています。しばらくしてから再度お試しください",
+            ApiError::InternalServerError(_) => "システムエラーが発生しました。時間をおいて再度お試しください",
+            ApiError::DatabaseError(_) => "システムメンテナンス中です。しばらくお待ちください",
+            ApiError::NotSupported(_) => "この機能は現在ご利用いただけません",
+        }
+    }
+
+    /// エラー仕様書のエラーレベルに従ってログを出力します
+    fn log(&self) {
+        let code = self.code();
+        match self {
+            ApiError::InvalidToken | ApiError::InternalServerError(_) | ApiError::DatabaseError(_) => {
+                tracing::error!(code, "{}", self)
+            }
+            ApiError::InvalidCredentials
+            | ApiError::InvalidMfaCode
+            | ApiError::AccountLocked(_)
+            | ApiError::Forbidden
+            | ApiError::TooManyRequests(_)
+            | ApiError::NotFound
+            | ApiError::NotSupported(_) => {
+                tracing::warn!(code, "{}", self)
+            }
+            _ => tracing::info!(code, "{}", self),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.log();
+        let details = match &self {
+            ApiError::PasswordTooWeak(v) => v.clone(),
+            _ => vec![],
+        };
+        let body = ErrorResponse { error: ErrorBody { code: self.code(), message: self.message(), details } };
+        match self {
+            ApiError::AccountLocked(retry_after) | ApiError::TooManyRequests(retry_after) => {
+                (self.status(), [(RETRY_AFTER, retry_after.to_string())], Json(body)).into_response()
+            }
+            _ => (self.status(), Json(body)).into_response(),
+        }
+    }
+}
+
+impl From<AuthUserError> for ApiError {
+    fn from(value: AuthUserError) -> Self {
+        match value {
+            AuthUserError::EmailError(_) => ApiError::InvalidEmailFormat,
+            AuthUserError::PasswordError(e) => e.into(),
+            AuthUserError::InvalidPassword => ApiError::PasswordTooWeak(vec![]),
+            AuthUserError::AuthenticationFailed(_) => ApiError::InvalidCredentials,
+            AuthUserError::UserAlreadyExists => ApiError::EmailAlreadyExists,
+            AuthUserError::AccountLocked(v) => ApiError::AccountLocked(v),
+            AuthUserError::TokenMissing => ApiError::InternalServerError(value.to_string()),
+            AuthUserError::InternalServerError(e) => ApiError::InternalServerError(e),
+            AuthUserError::Unsupported(e) => ApiError::NotSupported(e),
+        }
+    }
+}
+
+impl From<PasswordError> for ApiError {
+    fn from(value: PasswordError) -> Self {
+        match value {
+            PasswordError::PolicyViolation(v) => ApiError::PasswordTooWeak(v.iter().map(ToString::to_string).collect()),
+            PasswordError::Empty => ApiError::RequiredFieldMissing,
+            PasswordError::InvalidCharacterClass(_) | PasswordError::BreachLookupError(_) => {
+                ApiError::InternalServerError(value.to_string())
+            }
+        }
+    }
+}
+
+impl From<UserError> for ApiError {
+    fn from(value: UserError) -> Self {
+        match value {
+            UserError::EmailError(_) => ApiError::InvalidEmailFormat,
+            UserError::NameError(_) => ApiError::RequiredFieldMissing,
+            UserError::NotFound(_) => ApiError::NotFound,
+            UserError::NotGuest(_) => ApiError::EmailAlreadyExists,
+            UserError::EntityIdError(_) | UserError::UserTypeError(_) | UserError::RoleError(_) => {
+                ApiError::InternalServerError(value.to_string())
+            }
+            UserError::FindByIdError(e)
+            | UserError::FindByEmailError(e)
+            | UserError::CreateUserError(e)
+            | UserError::UpdateUserError(e)
+            | UserError::DeleteUserError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<RefreshTokenError> for ApiError {
+    fn from(value: RefreshTokenError) -> Self {
+        match value {
+            RefreshTokenError::Expired => ApiError::TokenExpired,
+            RefreshTokenError::InvalidToken
+            | RefreshTokenError::Revoked(_)
+            | RefreshTokenError::ReuseDetected(_)
+            | RefreshTokenError::Conflict(_)
+            | RefreshTokenError::NotFound(_) => ApiError::InvalidToken,
+            RefreshTokenError::SessionNotFound(_) => ApiError::NotFound,
+            RefreshTokenError::FindError(e) | RefreshTokenError::SaveError(e) | RefreshTokenError::DeleteError(e) => {
+                ApiError::DatabaseError(e)
+            }
+            RefreshTokenError::CryptoError(e) => ApiError::InternalServerError(e),
+        }
+    }
+}
+
+impl From<RevokedTokenError> for ApiError {
+    fn from(value: RevokedTokenError) -> Self {
+        match value {
+            RevokedTokenError::FindError(e) | RevokedTokenError::SaveError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<TokenError> for ApiError {
+    fn from(value: TokenError) -> Self {
+        match value {
+            TokenError::InvalidClaims => ApiError::InvalidToken,
+        }
+    }
+}
+
+impl From<LoginAttemptError> for ApiError {
+    fn from(value: LoginAttemptError) -> Self {
+        match value {
+            LoginAttemptError::FindError(e) | LoginAttemptError::SaveError(e) | LoginAttemptError::DeleteError(e) => {
+                ApiError::DatabaseError(e)
+            }
+        }
+    }
+}
+
+impl From<MfaError> for ApiError {
+    fn from(value: MfaError) -> Self {
+        match value {
+            // 同時の使用が続いてコードを受け付けられなかった場合も、コードの誤りとして扱います
+            MfaError::InvalidCode | MfaError::Conflict(_) => ApiError::InvalidMfaCode,
+            MfaError::AlreadyEnabled(_) => ApiError::MfaAlreadyEnabled,
+            MfaError::NotEnrolled(_) => ApiError::NotFound,
+            MfaError::InvalidChallenge => ApiError::InvalidToken,
+            MfaError::ChallengeExpired => ApiError::TokenExpired,
+            MfaError::FindError(e) | MfaError::SaveError(e) | MfaError::DeleteError(e) => ApiError::DatabaseError(e),
+            MfaError::CryptoError(e) => ApiError::InternalServerError(e),
+        }
+    }
+}
+
+impl From<OidcError> for ApiError {
+    fn from(value: OidcError) -> Self {
+        match value {
+            OidcError::UnknownProvider(_) => ApiError::NotFound,
+            OidcError::InvalidState => ApiError::InvalidToken,
+            OidcError::StateExpired => ApiError::TokenExpired,
+            OidcError::CodeRejected(_) | OidcError::InvalidIdToken(_) | OidcError::EmailNotVerified(_) => {
+                tracing::warn!("{}", value);
+                ApiError::InvalidCredentials
+            }
+            OidcError::ProviderUnavailable(e) | OidcError::CryptoError(e) => ApiError::InternalServerError(e),
+        }
+    }
+}
+
+impl From<ExternalIdentityError> for ApiError {
+    fn from(value: ExternalIdentityError) -> Self {
+        match value {
+            ExternalIdentityError::FindError(e)
+            | ExternalIdentityError::SaveError(e)
+            | ExternalIdentityError::DeleteError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<PersonalAccessTokenError> for ApiError {
+    fn from(value: PersonalAccessTokenError) -> Self {
+        match value {
+            PersonalAccessTokenError::InvalidToken => ApiError::InvalidToken,
+            PersonalAccessTokenError::Expired => ApiError::TokenExpired,
+            PersonalAccessTokenError::NotFound(_) => ApiError::NotFound,
+            PersonalAccessTokenError::InvalidName(_)
+            | PersonalAccessTokenError::InvalidScope(_)
+            | PersonalAccessTokenError::InvalidExpiry(_) => ApiError::RequiredFieldMissing,
+            PersonalAccessTokenError::FindError(e)
+            | PersonalAccessTokenError::SaveError(e)
+            | PersonalAccessTokenError::DeleteError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<AccountDeletionError> for ApiError {
+    fn from(value: AccountDeletionError) -> Self {
+        match value {
+            AccountDeletionError::NotFound(_) => ApiError::NotFound,
+            AccountDeletionError::AlreadyRequested(_) => ApiError::DeletionAlreadyRequested,
+            AccountDeletionError::NotCancellable(_) => ApiError::DeletionNotCancellable,
+            AccountDeletionError::InvalidStatus(_) | AccountDeletionError::NotClaimable(_) => {
+                ApiError::InternalServerError(value.to_string())
+            }
+            AccountDeletionError::FindError(e)
+            | AccountDeletionError::SaveError(e)
+            | AccountDeletionError::DeleteError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<DataExportError> for ApiError {
+    fn from(value: DataExportError) -> Self {
+        match value {
+            DataExportError::NotFound(_) => ApiError::NotFound,
+            DataExportError::AlreadyRequested(_) => ApiError::ExportAlreadyRequested,
+            DataExportError::NotReady(_) => ApiError::ExportNotReady,
+            DataExportError::InvalidToken => ApiError::InvalidToken,
+            DataExportError::Expired => ApiError::TokenExpired,
+            DataExportError::InvalidStatus(_) | DataExportError::ArchiveError(_) => {
+                ApiError::InternalServerError(value.to_string())
+            }
+            DataExportError::FindError(e) | DataExportError::SaveError(e) | DataExportError::DeleteError(e) => {
+                ApiError::DatabaseError(e)
+            }
+        }
+    }
+}
+
+impl From<UserDataError> for ApiError {
+    fn from(value: UserDataError) -> Self {
+        match value {
+            UserDataError::FindError(e) | UserDataError::DeleteError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<FileError> for ApiError {
+    fn from(value: FileError) -> Self {
+        match value {
+            FileError::NotFound(_) => ApiError::NotFound,
+            _ => ApiError::InternalServerError(value.to_string()),
+        }
+    }
+}
+
+impl From<ProfileIconError> for ApiError {
+    fn from(value: ProfileIconError) -> Self {
+        match value {
+            ProfileIconError::TooLarge(_) => ApiError::ImageTooLarge,
+            ProfileIconError::UnsupportedFormat | ProfileIconError::InvalidImage(_) => ApiError::InvalidImage,
+        }
+    }
+}
+
+impl From<SecurityEventError> for ApiError {
+    fn from(value: SecurityEventError) -> Self {
+        match value {
+            SecurityEventError::InvalidEventType(_) | SecurityEventError::FilterRequired => {
+                ApiError::RequiredFieldMissing
+            }
+            SecurityEventError::InvalidTimeRange(_) => ApiError::InvalidDateFormat,
+            SecurityEventError::FindError(e) | SecurityEventError::SaveError(e) => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(value: anyhow::Error) -> Self {
+        let value = match value.downcast::<UserError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<AuthUserError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<PasswordError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<LoginAttemptError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<MfaError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<PersonalAccessTokenError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<AccountDeletionError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<DataExportError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<UserDataError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<FileError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<SecurityEventError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let value = match value.downcast::<ProfileIconError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        match value.downcast::<RefreshTokenError>() {
+            Ok(e) => e.into(),
+            Err(e) => ApiError::InternalServerError(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::user::password::{CharacterClass, PasswordViolation};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_into_response_envelope() {
+        let response = ApiError::EmailAlreadyExists.into_response();
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("AUTH_004", body["error"]["code"]);
+        assert_eq!("このメールアドレスは既に登録されています", body["error"]["message"]);
+    }
+
+    #[tokio::test]
+    async fn test_into_response_forbidden() {
+        let response = ApiError::Forbidden.into_response();
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("AUTH_008", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_into_response_details() {
+        let error = PasswordError::PolicyViolation(vec![
+            PasswordViolation::TooShort(8),
+            PasswordViolation::MissingCharacterClass(CharacterClass::Digit),
+        ]);
+        let response = ApiError::from(error).into_response();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("VAL_002", body["error"]["code"]);
+        let expected = vec![
+            "too_short",
+            "missing_digit",
+        ];
+        assert_eq!(serde_json::json!(expected), body["error"]["details"]);
+
+        let response = ApiError::NotFound.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"].get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_into_response_retry_after() {
+        let test_cases = vec![
+            (ApiError::AccountLocked(60), "60"),
+            (ApiError::TooManyRequests(5), "5"),
+        ];
+        for (error, expected) in test_cases {
+            let response = error.into_response();
+            assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+            assert_eq!(expected, response.headers()[RETRY_AFTER]);
+        }
+    }
+
+    #[test]
+    fn test_from_auth_user_error() {
+        let test_cases = vec![
+            (AuthUserError::AuthenticationFailed("".to_string()), "AUTH_001"),
+            (AuthUserError::UserAlreadyExists, "AUTH_004"),
+            (AuthUserError::AccountLocked(60), "AUTH_005"),
+            (AuthUserError::InvalidPassword, "VAL_002"),
+            (AuthUserError::TokenMissing, "SRV_001"),
+            (AuthUserError::InternalServerError("".to_string()), "SRV_001"),
+            (AuthUserError::Unsupported("".to_string()), "SRV_003"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(error).code());
+        }
+    }
+
+    #[test]
+    fn test_from_anyhow_error() {
+        let result = ApiError::from(anyhow::Error::from(UserError::NotFound("usr_".to_string())));
+        assert_eq!("RES_001", result.code());
+
+        let result = ApiError::from(anyhow::Error::from(UserError::FindByIdError("usr_".to_string())));
+        assert_eq!("SRV_002", result.code());
+
+        let result = ApiError::from(anyhow::Error::from(UserError::NotGuest("usr_".to_string())));
+        assert_eq!("AUTH_004", result.code());
+
+        let test_cases = vec![
+            (RefreshTokenError::Expired, "AUTH_002"),
+            (RefreshTokenError::InvalidToken, "AUTH_003"),
+            (RefreshTokenError::ReuseDetected("".to_string()), "AUTH_003"),
+            (RefreshTokenError::Revoked("".to_string()), "AUTH_003"),
+            (RefreshTokenError::SessionNotFound("".to_string()), "RES_001"),
+            (RefreshTokenError::SaveError("".to_string()), "SRV_002"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(anyhow::Error::from(error)).code());
+        }
+
+        let result = ApiError::from(anyhow::Error::from(AuthUserError::AccountLocked(60)));
+        assert_eq!("AUTH_005", result.code());
+
+        let result = ApiError::from(anyhow::Error::from(LoginAttemptError::SaveError("".to_string())));
+        assert_eq!("SRV_002", result.code());
+
+        let test_cases = vec![
+            (MfaError::InvalidCode, "AUTH_006"),
+            (MfaError::Conflict("".to_string()), "AUTH_006"),
+            (MfaError::AlreadyEnabled("".to_string()), "AUTH_007"),
+            (MfaError::NotEnrolled("".to_string()), "RES_001"),
+            (MfaError::InvalidChallenge, "AUTH_003"),
+            (MfaError::ChallengeExpired, "AUTH_002"),
+            (MfaError::SaveError("".to_string()), "SRV_002"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(anyhow::Error::from(error)).code());
+        }
+
+        let test_cases = vec![
+            (PersonalAccessTokenError::InvalidToken, "AUTH_003"),
+            (PersonalAccessTokenError::Expired, "AUTH_002"),
+            (PersonalAccessTokenError::NotFound("".to_string()), "RES_001"),
+            (PersonalAccessTokenError::InvalidScope("".to_string()), "VAL_003"),
+            (PersonalAccessTokenError::InvalidExpiry(0), "VAL_003"),
+            (PersonalAccessTokenError::DeleteError("".to_string()), "SRV_002"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(anyhow::Error::from(error)).code());
+        }
+
+        let test_cases = vec![
+            (PasswordError::PolicyViolation(vec![PasswordViolation::Breached]), "VAL_002"),
+            (PasswordError::Empty, "VAL_003"),
+            (PasswordError::BreachLookupError("".to_string()), "SRV_001"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(anyhow::Error::from(error)).code());
+        }
+
+        let test_cases = vec![
+            (AccountDeletionError::NotFound("".to_string()), "RES_001"),
+            (AccountDeletionError::AlreadyRequested("".to_string()), "RES_002"),
+            (AccountDeletionError::NotCancellable("".to_string()), "RES_003"),
+            (AccountDeletionError::DeleteError("".to_string()), "SRV_002"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(anyhow::Error::from(error)).code());
+        }
+
+        let test_cases = vec![
+            (anyhow::Error::from(DataExportError::NotFound("".to_string())), "RES_001"),
+            (anyhow::Error::from(DataExportError::AlreadyRequested("".to_string())), "RES_004"),
+            (anyhow::Error::from(DataExportError::NotReady("".to_string())), "RES_005"),
+            (anyhow::Error::from(DataExportError::InvalidToken), "AUTH_003"),
+            (anyhow::Error::from(DataExportError::Expired), "AUTH_002"),
+            (anyhow::Error::from(DataExportError::ArchiveError("".to_string())), "SRV_001"),
+            (anyhow::Error::from(UserDataError::FindError("".to_string())), "SRV_002"),
+            (anyhow::Error::from(FileError::NotFound("".to_string())), "RES_001"),
+            (anyhow::Error::from(FileError::ReadError("".to_string())), "SRV_001"),
+            (anyhow::Error::from(SecurityEventError::InvalidEventType("".to_string())), "VAL_003"),
+            (anyhow::Error::from(SecurityEventError::FilterRequired), "VAL_003"),
+            (anyhow::Error::from(SecurityEventError::InvalidTimeRange("".to_string())), "VAL_004"),
+            (anyhow::Error::from(SecurityEventError::FindError("".to_string())), "SRV_002"),
+            (anyhow::Error::from(ProfileIconError::UnsupportedFormat), "VAL_005"),
+            (anyhow::Error::from(ProfileIconError::InvalidImage("".to_string())), "VAL_005"),
+            (anyhow::Error::from(ProfileIconError::TooLarge(0)), "VAL_006"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(error).code());
+        }
+
+        let result = ApiError::from(anyhow::anyhow!("unknown"));
+        assert_eq!("SRV_001", result.code());
+    }
+
+    #[test]
+    fn test_from_oidc_error() {
+        let test_cases = vec![
+            (OidcError::UnknownProvider("".to_string()), "RES_001"),
+            (OidcError::InvalidState, "AUTH_003"),
+            (OidcError::StateExpired, "AUTH_002"),
+            (OidcError::CodeRejected("".to_string()), "AUTH_001"),
+            (OidcError::InvalidIdToken("".to_string()), "AUTH_001"),
+            (OidcError::EmailNotVerified("".to_string()), "AUTH_001"),
+            (OidcError::ProviderUnavailable("".to_string()), "SRV_001"),
+        ];
+        for (error, expected) in test_cases {
+            assert_eq!(expected, ApiError::from(error).code());
+        }
+
+        let result = ApiError::from(ExternalIdentityError::SaveError("".to_string()));
+        assert_eq!("SRV_002", result.code());
+    }
+}
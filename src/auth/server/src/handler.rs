@@ -0,0 +1,7 @@
+pub mod account_deletion_handler;
+pub mod auth_handler;
+pub mod data_export_handler;
+pub mod personal_access_token_handler;
+pub mod security_event_handler;
+pub mod session_handler;
+pub mod user_handler;
@@ -0,0 +1,364 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use domain::{
+    account_deletion::{AccountDeletion, DeletedItems},
+    security_event::SecurityEventType,
+};
+use serde::Serialize;
+
+use crate::{
+    auth::{authenticated_user::AuthenticatedUser, client_device::ClientDevice},
+    error::ApiError,
+    handler::security_event_handler::{record, security_event},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct DeletedItemsResponse {
+    target: String,
+    count: usize,
+}
+
+/// 削除の依頼の状態です。完了した依頼は削除証明として使います
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    deletion_id: String,
+    user_id: String,
+    status: String,
+    requested_at: i64,
+    scheduled_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed_at: Option<i64>,
+    deleted: Vec<DeletedItemsResponse>,
+}
+
+impl From<&DeletedItems> for DeletedItemsResponse {
+    fn from(value: &DeletedItems) -> Self {
+        Self { target: value.target().to_string(), count: value.count() }
+    }
+}
+
+impl From<&AccountDeletion> for AccountDeletionResponse {
+    fn from(value: &AccountDeletion) -> Self {
+        Self {
+            deletion_id: value.deletion_id().to_string(),
+            user_id: value.user_id().to_string(),
+            status: value.status().to_string(),
+            requested_at: value.requested_at(),
+            scheduled_at: value.scheduled_at(),
+            completed_at: value.completed_at(),
+            deleted: value.deleted().iter().map(DeletedItemsResponse::from).collect(),
+        }
+    }
+}
+
+/// POST /auth/account/deletion
+///
+/// 猶予期間が過ぎた後にアカウントを削除する依頼を作成します
+pub async fn request_deletion(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+) -> Result<(StatusCode, Json<AccountDeletionResponse>), ApiError> {
+    user.session()?;
+    let found = state.user_service.find_by_id(&user.user_id).await?;
+    let deletion = state.account_deletion_service.request(&user.user_id, found.email()).await?;
+    let event =
+        security_event(&state, SecurityEventType::AccountDeletionRequested, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail(deletion.deletion_id())).await;
+
+    Ok((StatusCode::ACCEPTED, Json(AccountDeletionResponse::from(&deletion))))
+}
+
+/// GET /auth/account/deletion
+///
+/// 完了もキャンセルもしていない依頼を返します
+pub async fn find_deletion(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<Json<AccountDeletionResponse>, ApiError> {
+    user.session()?;
+    let deletion = state.account_deletion_service.find_active(&user.user_id).await?.ok_or(ApiError::NotFound)?;
+
+    Ok(Json(AccountDeletionResponse::from(&deletion)))
+}
+
+/// DELETE /auth/account/deletion
+///
+/// 猶予期間中の依頼をキャンセルします
+pub async fn cancel_deletion(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+) -> Result<Json<AccountDeletionResponse>, ApiError> {
+    user.session()?;
+    let deletion = state.account_deletion_service.cancel(&user.user_id).await?;
+    let event =
+        security_event(&state, SecurityEventType::AccountDeletionCancelled, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail(deletion.deletion_id())).await;
+
+    Ok(Json(AccountDeletionResponse::from(&deletion)))
+}
+
+/// GET /auth/account/deletion/:deletion_id
+///
+/// 完了した依頼の削除証明を返します
+/// 削除後はログインできないため認証を求めません。推測できない`deletion_id`を知っている場合のみ取得できます
+pub async fn find_receipt(
+    State(state): State<AppState>, Path(deletion_id): Path<String>,
+) -> Result<Json<AccountDeletionResponse>, ApiError> {
+    let deletion = state.account_deletion_service.find_receipt(&deletion_id).await?;
+
+    Ok(Json(AccountDeletionResponse::from(&deletion)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, AccountDeletionService,
+        UserService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request},
+        Router,
+    };
+    use domain::{
+        login_attempt::LockoutPolicy,
+        repository::user_repository::UserRepository,
+        user::{password::PasswordPolicy, user_id::UserId, User},
+        user_data::UserDataItem,
+    };
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_data_repository::InMemoryUserDataRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{access_token, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    struct TestApp {
+        router: Router,
+        service: Arc<dyn AccountDeletionService>,
+        user_repository: Arc<InMemoryUserRepository>,
+        subscribe_repository: Arc<InMemoryUserDataRepository>,
+    }
+
+    /// `SUB`のユーザーがサブスクリプションを3件登録した状態です
+    async fn app(grace_period: std::time::Duration) -> TestApp {
+        let user_id = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user_service = UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO);
+        let user = User::build(&user_id.to_string(), "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.create_user(user).await.unwrap();
+        let subscribe_repository = Arc::new(InMemoryUserDataRepository::new("subscribe"));
+        subscribe_repository.insert(&user_id, vec![UserDataItem::new(); 3]);
+
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let service: Arc<dyn AccountDeletionService> = Arc::new(AccountDeletionServiceImpl::new(
+            Arc::new(InMemoryAccountDeletionRepository::new()),
+            auth_user_repository.clone(),
+            Arc::new(InMemoryRefreshTokenRepository::new()),
+            Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+            Arc::new(InMemoryMfaRepository::new()),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(InMemoryDataExportRepository::new()),
+            vec![subscribe_repository.clone()],
+            Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            user_repository.clone(),
+            grace_period,
+        ));
+        let state = AppState::new(
+            auth_user_repository,
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            service.clone(),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            )),
+        );
+        let router = crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())));
+        TestApp { router, service, user_repository, subscribe_repository }
+    }
+
+    async fn send(app: &Router, request: axum::http::request::Builder) -> (StatusCode, Value) {
+        let request = request.header(AUTHORIZATION, format!("Bearer {}", access_token()));
+        let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_request_and_cancel_deletion() {
+        let app = app(std::time::Duration::from_secs(60 * 60)).await;
+
+        let (status, _) = send(&app.router, Request::get("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        let (status, body) = send(&app.router, Request::post("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::ACCEPTED, status);
+        assert_eq!("scheduled", body["status"]);
+        assert_eq!(format!("usr_{}", SUB), body["user_id"]);
+        assert_eq!(60 * 60, body["scheduled_at"].as_i64().unwrap() - body["requested_at"].as_i64().unwrap());
+        let deletion_id = body["deletion_id"].clone();
+
+        let (status, body) = send(&app.router, Request::post("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("RES_002", body["error"]["code"]);
+
+        let (status, body) = send(&app.router, Request::get("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(deletion_id, body["deletion_id"]);
+
+        let (status, body) = send(&app.router, Request::delete("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("cancelled", body["status"]);
+
+        let test_cases = vec![
+            Request::get("/auth/account/deletion"),
+            Request::delete("/auth/account/deletion"),
+        ];
+        for request in test_cases {
+            let (status, _) = send(&app.router, request).await;
+            assert_eq!(StatusCode::NOT_FOUND, status);
+        }
+        assert_eq!(0, app.service.run_due().await.unwrap());
+        assert_eq!(3, app.subscribe_repository.count(&UserId::from_str(&format!("usr_{}", SUB)).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_deletion_receipt() {
+        let app = app(std::time::Duration::ZERO).await;
+        let user_id = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+
+        let (_, body) = send(&app.router, Request::post("/auth/account/deletion")).await;
+        let uri = format!("/auth/account/deletion/{}", body["deletion_id"].as_str().unwrap());
+
+        let (status, body) = send(&app.router, Request::delete("/auth/account/deletion")).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("RES_003", body["error"]["code"]);
+
+        let (status, _) = send(&app.router, Request::get(&uri)).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        assert_eq!(1, app.service.run_due().await.unwrap());
+        assert_eq!(0, app.subscribe_repository.count(&user_id));
+        assert!(app.user_repository.find_by_id(&user_id).await.is_err());
+
+        let request = Request::get(&uri).body(Body::empty()).unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("completed", body["status"]);
+        assert!(body["completed_at"].is_i64());
+        assert!(body.get("email").is_none());
+        let expected = vec![
+            ("auth", 1),
+            ("session", 0),
+            ("personal_access_token", 0),
+            ("mfa", 0),
+            ("external_identity", 0),
+            ("data_export", 0),
+            ("subscribe", 3),
+            ("profile_icon", 0),
+            ("export_file", 0),
+            ("user", 1),
+        ];
+        let deleted: Vec<_> = body["deleted"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| (v["target"].as_str().unwrap(), v["count"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(expected, deleted);
+
+        let (status, _) = send(&app.router, Request::get("/auth/account/deletion/unknown")).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+}
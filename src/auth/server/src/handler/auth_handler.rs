@@ -0,0 +1,2444 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use domain::{
+    auth_user::{AuthUser, AuthUserError},
+    external_identity::ExternalIdentity,
+    mfa::MfaError,
+    refresh_token::{Device, RefreshTokenError},
+    revoked_token::RevokedToken,
+    security_event::SecurityEventType,
+    token::Token,
+    user::{email::Email, name::Name, user_error::UserError, user_id::UserId, user_type::UserType, User},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{
+        authenticated_user::AuthenticatedUser,
+        bearer_token,
+        client_device::ClientDevice,
+        client_ip::ClientIp,
+        oidc::{IdTokenClaims, OidcError},
+    },
+    error::ApiError,
+    handler::security_event_handler::{record, security_event},
+    state::AppState,
+};
+
+/// ゲストユーザーの名前です
+const GUEST_NAME: &str = "Guest";
+
+#[derive(Deserialize)]
+pub struct SignUpRequest {
+    email: String,
+    password: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignUpResponse {
+    user_id: String,
+    token: Option<String>,
+    email: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyRequest {
+    email: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyResponse {
+    message: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    email: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    /// `access_token`と同じ値です。以前のクライアントとの互換性のために返します
+    token: String,
+    #[serde(flatten)]
+    credentials: Token,
+    refresh_token: String,
+    user: UserResponse,
+}
+
+/// 多要素認証が有効なユーザーのログインでは、トークンの代わりにチャレンジトークンを返します
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    mfa_required: bool,
+    challenge_token: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Authenticated(LoginResponse),
+    MfaRequired(MfaRequiredResponse),
+}
+
+#[derive(Deserialize)]
+pub struct MfaLoginRequest {
+    challenge_token: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollResponse {
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct MfaCodeRequest {
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaActivateResponse {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaDisableResponse {
+    message: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorizeResponse {
+    authorization_url: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallbackRequest {
+    code: String,
+    state: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    user_id: String,
+    email: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    message: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshTokenResponse {
+    #[serde(flatten)]
+    token: Token,
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    email: String,
+    code: String,
+    new_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordResponse {
+    message: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeEmailResponse {
+    message: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeRequest {
+    code: String,
+}
+
+impl From<&User> for UserResponse {
+    fn from(value: &User) -> Self {
+        Self { user_id: value.user_id().to_string(), email: value.email().to_string(), name: value.name().to_string() }
+    }
+}
+
+/// POST /auth/signup
+///
+/// 認証基盤にユーザーを登録し、ユーザーテーブルにも作成します
+/// メールアドレスの確認が必要な場合、`token`は`null`になります
+/// パスワードが満たしていない規則は、すべてエラーの`details`で返します
+/// ユーザーテーブルに登録済みのメールアドレスは、認証基盤に問い合わせずに拒否します
+pub async fn sign_up(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice,
+    payload: Result<Json<SignUpRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<SignUpResponse>), ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let name = Name::from_str(&request.name).map_err(UserError::from)?;
+    let email = Email::from_str(&request.email).map_err(AuthUserError::from)?;
+    let password = state.password_service.validate(&request.password, &email, Some(&name)).await?;
+    let auth_user = AuthUser::new(email, password);
+
+    let result = match state.user_service.exists_by_email(auth_user.email()).await? {
+        true => Err(AuthUserError::UserAlreadyExists),
+        false => state.auth_user_repository.sign_up(auth_user.clone()).await,
+    };
+    let user_id = match result {
+        Ok(user_id) => user_id,
+        Err(AuthUserError::UserAlreadyExists) => {
+            let event = security_event(&state, SecurityEventType::SignUpRejected, &device);
+            record(&state, event.with_email(auth_user.email().to_string())).await;
+            return Err(AuthUserError::UserAlreadyExists.into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let user =
+        User::build(&user_id.to_string(), &request.email, &name.to_string(), UserType::Registerd as usize, None)?;
+    // ユーザーテーブルに作成できない場合は、同じメールアドレスで登録し直せるよう認証基盤のユーザーを削除します
+    if let Err(e) = state.user_service.create_user(user.clone()).await {
+        if let Err(e) = state.auth_user_repository.delete_user(auth_user.email().clone()).await {
+            tracing::error!("failed to delete signed up user. user_id: {}, {:?}", user.user_id(), e);
+        }
+        return Err(e.into());
+    }
+    let event = security_event(&state, SecurityEventType::SignUp, &device);
+    record(&state, event.with_user_id(user.user_id().clone()).with_email(user.email().to_string())).await;
+
+    let token = match state.auth_user_repository.authenticate(auth_user).await {
+        Ok(token) => Some(token.access_token().expose_secret().to_string()),
+        Err(AuthUserError::AuthenticationFailed(e)) => {
+            tracing::info!("sign up requires verification: {}", e);
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let response = SignUpResponse {
+        user_id: user.user_id().to_string(),
+        token,
+        email: user.email().to_string(),
+        name: user.name().to_string(),
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// POST /auth/verify
+///
+/// サインアップ時に送信された確認コードでメールアドレスを確認します
+/// `POST /auth/guest/upgrade`で登録したゲストユーザーは、確認後に登録済みのユーザーになります
+pub async fn verify(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice,
+    payload: Result<Json<VerifyRequest>, JsonRejection>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let email = Email::from_str(&request.email).map_err(UserError::from)?;
+
+    let guest = match state.user_service.find_by_email(&email).await {
+        Ok(user) => Some(user).filter(|v| v.is_guest()),
+        Err(e) => match e.downcast_ref::<UserError>() {
+            Some(UserError::NotFound(_)) => None,
+            _ => return Err(e.into()),
+        },
+    };
+    match &guest {
+        Some(guest) => {
+            state
+                .auth_user_repository
+                .verify_guest_upgrade(guest.user_id().clone(), email.clone(), &request.code)
+                .await?
+        }
+        None => state.auth_user_repository.verify_code(email.clone(), &request.code).await?,
+    }
+    let mut event = security_event(&state, SecurityEventType::EmailVerified, &device).with_email(email.to_string());
+    if let Some(guest) = guest {
+        state.user_service.update(guest.complete_registration()).await?;
+        tracing::info!("guest user registered. user_id: {}", guest.user_id());
+        event = event.with_user_id(guest.user_id().clone());
+    }
+    record(&state, event).await;
+    Ok(Json(VerifyResponse { message: "Email verified successfully" }))
+}
+
+/// POST /auth/login
+///
+/// 認証の失敗をメールアドレスと接続元ごとに数え、上限に達した場合は一定時間ログインを拒否します
+/// 多要素認証が有効な場合は`POST /auth/login/mfa`に渡すチャレンジトークンを返します
+pub async fn login(
+    State(state): State<AppState>, ClientIp(ip): ClientIp, ClientDevice(device): ClientDevice,
+    payload: Result<Json<LoginRequest>, JsonRejection>,
+) -> Result<Json<LoginResult>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let auth_user = AuthUser::credentials(&request.email, &request.password)?;
+    let email = auth_user.email().clone();
+    state.login_attempt_service.check(&email, &ip).await?;
+
+    let token = match state.auth_user_repository.authenticate(auth_user).await {
+        Ok(token) => token,
+        Err(AuthUserError::AuthenticationFailed(e)) => {
+            state.login_attempt_service.record_failure(&email, &ip).await?;
+            let mut event = security_event(&state, SecurityEventType::LoginFailed, &device)
+                .with_email(email.to_string())
+                .with_detail("password");
+            // 登録済みのアカウントであれば、本人が`GET /me/security-events`で確認できるようユーザーIDを含めます
+            if let Ok(user) = state.user_service.find_by_email(&email).await {
+                event = event.with_user_id(user.user_id().clone());
+            }
+            record(&state, event).await;
+            return Err(AuthUserError::AuthenticationFailed(e).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let user_id = user_id_from_token(&token)?;
+    if state.mfa_service.is_enabled(&user_id).await? {
+        let challenge_token = state.mfa_service.issue_challenge(&user_id, &token)?;
+        return Ok(Json(LoginResult::MfaRequired(MfaRequiredResponse { mfa_required: true, challenge_token })));
+    }
+
+    state.login_attempt_service.clear(&email).await?;
+    let user = state.user_service.find_by_id(&user_id).await?;
+    Ok(Json(LoginResult::Authenticated(complete_login(&state, &user, token, device, "password").await?)))
+}
+
+/// POST /auth/login/mfa
+///
+/// チャレンジトークンと、認証アプリのコードまたはリカバリーコードを確認してログインを完了します
+/// 誤ったコードはパスワードの失敗と同じく数え、上限に達した場合は一定時間ログインを拒否します
+pub async fn login_mfa(
+    State(state): State<AppState>, ClientIp(ip): ClientIp, ClientDevice(device): ClientDevice,
+    payload: Result<Json<MfaLoginRequest>, JsonRejection>,
+) -> Result<Json<LoginResponse>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let challenge = state.mfa_service.open_challenge(&request.challenge_token)?;
+    if state.revoked_token_repository.is_revoked(&challenge.nonce).await? {
+        return Err(MfaError::InvalidChallenge.into());
+    }
+    let user = state.user_service.find_by_id(&challenge.user_id).await?;
+    state.login_attempt_service.check(user.email(), &ip).await?;
+
+    verify_mfa_code(&state, &user, &device, &request.code).await?;
+    // 同じチャレンジトークンで何度もログインできないよう、使用済みとして記録します
+    let consumed = RevokedToken::new(challenge.nonce.clone(), challenge.expires_at);
+    if !state.revoked_token_repository.revoke_once(consumed).await? {
+        return Err(MfaError::InvalidChallenge.into());
+    }
+    state.login_attempt_service.clear(user.email()).await?;
+    Ok(Json(complete_login(&state, &user, challenge.token, device, "mfa").await?))
+}
+
+/// POST /auth/mfa/enroll
+///
+/// シークレットを生成し、認証アプリに登録するための`otpauth://`形式のURIを返します
+/// `POST /auth/mfa/activate`でコードを確認するまでは、ログインに多要素認証を求めません
+pub async fn enroll_mfa(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<Json<MfaEnrollResponse>, ApiError> {
+    user.session()?;
+    let user = state.user_service.find_by_id(&user.user_id).await?;
+    let enrollment = state.mfa_service.enroll(user.user_id(), user.email()).await?;
+
+    Ok(Json(MfaEnrollResponse { secret: enrollment.secret, otpauth_uri: enrollment.otpauth_uri }))
+}
+
+/// POST /auth/mfa/activate
+///
+/// 認証アプリのコードを確認して多要素認証を有効にし、リカバリーコードを返します
+/// リカバリーコードを表示できるのはこのレスポンスのみです
+pub async fn activate_mfa(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    payload: Result<Json<MfaCodeRequest>, JsonRejection>,
+) -> Result<Json<MfaActivateResponse>, ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let recovery_codes = state.mfa_service.activate(&user.user_id, &request.code).await?;
+    record(&state, security_event(&state, SecurityEventType::MfaEnabled, &device).with_user_id(user.user_id.clone()))
+        .await;
+
+    Ok(Json(MfaActivateResponse { recovery_codes }))
+}
+
+/// POST /auth/mfa/disable
+///
+/// アクセストークンを盗まれた場合に無効化されないよう、認証アプリのコードまたはリカバリーコードを求めます
+pub async fn disable_mfa(
+    State(state): State<AppState>, ClientIp(ip): ClientIp, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    payload: Result<Json<MfaCodeRequest>, JsonRejection>,
+) -> Result<Json<MfaDisableResponse>, ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let user = state.user_service.find_by_id(&user.user_id).await?;
+    state.login_attempt_service.check(user.email(), &ip).await?;
+
+    match state.mfa_service.disable(user.user_id(), &request.code).await.map_err(ApiError::from) {
+        Ok(_) => {}
+        Err(ApiError::InvalidMfaCode) => {
+            state.login_attempt_service.record_failure(user.email(), &ip).await?;
+            return Err(ApiError::InvalidMfaCode);
+        }
+        Err(e) => return Err(e),
+    }
+    record(
+        &state,
+        security_event(&state, SecurityEventType::MfaDisabled, &device).with_user_id(user.user_id().clone()),
+    )
+    .await;
+    Ok(Json(MfaDisableResponse { message: "MFA disabled successfully" }))
+}
+
+/// 誤ったコードをメールアドレスと接続元ごとのログインの失敗として記録します
+async fn verify_mfa_code(state: &AppState, user: &User, device: &Device, code: &str) -> Result<(), ApiError> {
+    match state.mfa_service.verify(user.user_id(), code).await.map_err(ApiError::from) {
+        Ok(_) => Ok(()),
+        Err(ApiError::InvalidMfaCode) => {
+            state.login_attempt_service.record_failure(user.email(), device.ip()).await?;
+            let event = security_event(state, SecurityEventType::LoginFailed, device);
+            record(state, event.with_user_id(user.user_id().clone()).with_detail("mfa")).await;
+            Err(ApiError::InvalidMfaCode)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// リフレッシュトークンを発行し、ログインのレスポンスを作成します
+///
+/// リフレッシュトークンの系列は`device`のセッションとして記録します
+/// 監査ログには`method`をログインの方法として記録します
+async fn complete_login(
+    state: &AppState, user: &User, token: Token, device: Device, method: &str,
+) -> Result<LoginResponse, ApiError> {
+    let event = security_event(state, SecurityEventType::LoginSucceeded, &device);
+    let refresh_token =
+        state.refresh_token_service.issue(user.user_id(), token.refresh_token().expose_secret(), device).await?;
+    record(state, event.with_user_id(user.user_id().clone()).with_detail(method)).await;
+
+    Ok(LoginResponse {
+        token: token.access_token().expose_secret().to_string(),
+        credentials: token,
+        refresh_token,
+        user: UserResponse::from(user),
+    })
+}
+
+/// POST /auth/guest
+///
+/// メールアドレスを登録せずに使えるゲストユーザーを作成し、ログインします
+/// `POST /auth/guest/upgrade`で登録されないまま`GUEST_TTL_SECONDS`が過ぎたゲストユーザーは削除されます
+pub async fn guest(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice,
+) -> Result<(StatusCode, Json<LoginResponse>), ApiError> {
+    let name = Name::from_str(GUEST_NAME).map_err(UserError::from)?;
+    let guest = state.user_service.new_guest(name);
+    let expires_at =
+        guest.expires_at().ok_or_else(|| ApiError::InternalServerError("guest user has no expiry".to_string()))?;
+
+    let result =
+        state.auth_user_repository.sign_up_guest(guest.user_id().clone(), guest.email().clone(), expires_at).await?;
+    // Cognitoはゲストユーザーを作成する際に`UserId`を割り当てます
+    let user = guest.with_user_id(result.user_id().clone());
+    if let Err(e) = state.user_service.create_user(user.clone()).await {
+        if let Err(e) = state.auth_user_repository.delete_user(user.email().clone()).await {
+            tracing::error!("failed to delete guest user. user_id: {}, {:?}", user.user_id(), e);
+        }
+        return Err(e.into());
+    }
+    tracing::info!("guest user created. user_id: {}", user.user_id());
+    Ok((StatusCode::CREATED, Json(complete_login(&state, &user, result.into_token(), device, "guest").await?)))
+}
+
+/// POST /auth/guest/upgrade
+///
+/// ゲストユーザーにメールアドレスとパスワードを登録し、同じ`UserId`のまま登録済みのユーザーにします
+/// サインアップと同様にメールアドレスの確認が必要な場合、`token`は`null`になり、確認されるまではゲストユーザーのままです
+/// ゲストユーザーとして発行したリフレッシュトークンは使用できなくなるため、確認後に再度ログインします
+/// ユーザーテーブルの更新に失敗した場合は、認証基盤の変更を取り消します
+pub async fn upgrade_guest(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    payload: Result<Json<SignUpRequest>, JsonRejection>,
+) -> Result<Json<SignUpResponse>, ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let name = Name::from_str(&request.name).map_err(UserError::from)?;
+    let email = Email::from_str(&request.email).map_err(AuthUserError::from)?;
+    let password = state.password_service.validate(&request.password, &email, Some(&name)).await?;
+    let auth_user = AuthUser::new(email, password);
+    let guest = state.user_service.find_by_id(&user.user_id).await?;
+    // 登録を要求済みのゲストユーザーは、削除する日時を持ちません
+    let expires_at = match guest.expires_at() {
+        Some(expires_at) if guest.is_guest() => expires_at,
+        _ => return Err(UserError::NotGuest(guest.user_id().to_string()).into()),
+    };
+    if state.user_service.exists_by_email(auth_user.email()).await? {
+        return Err(AuthUserError::UserAlreadyExists.into());
+    }
+
+    state.auth_user_repository.upgrade_guest(guest.email().clone(), auth_user.clone()).await?;
+    let user = guest.register(auth_user.email().clone(), name);
+    if let Err(e) = state.user_service.update(user.clone()).await {
+        let reverted =
+            state.auth_user_repository.revert_guest_upgrade(guest.email().clone(), user.email().clone(), expires_at);
+        if let Err(e) = reverted.await {
+            tracing::error!("failed to revert guest upgrade. user_id: {}, {:?}", user.user_id(), e);
+        }
+        return Err(e.into());
+    }
+    tracing::info!("guest user upgrade requested. user_id: {}", user.user_id());
+    let event = security_event(&state, SecurityEventType::SignUp, &device).with_user_id(user.user_id().clone());
+    record(&state, event.with_email(user.email().to_string()).with_detail("guest_upgrade")).await;
+
+    let token = match state.auth_user_repository.authenticate(auth_user).await {
+        // 確認せずにログインできる認証基盤では、この時点で登録済みのユーザーにします
+        Ok(token) => {
+            state.user_service.update(user.complete_registration()).await?;
+            tracing::info!("guest user registered. user_id: {}", user.user_id());
+            Some(token.access_token().expose_secret().to_string())
+        }
+        Err(AuthUserError::AuthenticationFailed(e)) => {
+            tracing::info!("guest upgrade requires verification: {}", e);
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let response = SignUpResponse {
+        user_id: user.user_id().to_string(),
+        token,
+        email: user.email().to_string(),
+        name: user.name().to_string(),
+    };
+    Ok(Json(response))
+}
+
+/// POST /auth/logout
+///
+/// アクセストークンを有効期限まで無効化し、認証基盤上のセッションも終了します
+/// アクセストークンはセッションのIDを持たないため、リフレッシュトークンが指定された場合はそのセッションも無効化します
+pub async fn logout(
+    State(state): State<AppState>, headers: HeaderMap, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<Json<LogoutResponse>, ApiError> {
+    let claims = user.session()?;
+    match &claims.jti {
+        Some(jti) => state.revoked_token_repository.save(RevokedToken::new(jti.clone(), claims.exp as i64)).await?,
+        None => tracing::warn!("access token has no jti. user_id: {}", user.user_id),
+    }
+    if let Some(Json(request)) = payload {
+        revoke_session_by_token(&state, &user.user_id, &request.refresh_token).await?;
+    }
+
+    match state.auth_user_repository.sign_out(bearer_token(&headers)?).await {
+        Ok(_) => {}
+        // 認証基盤側で既に無効化されている場合もログアウト済みとして扱います
+        Err(AuthUserError::AuthenticationFailed(e)) => tracing::warn!("sign out failed: {}", e),
+        Err(e) => return Err(e.into()),
+    }
+
+    tracing::info!("logout user_id: {}", user.user_id);
+    let event = security_event(&state, SecurityEventType::TokenRevoked, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail("logout")).await;
+    Ok(Json(LogoutResponse { message: "Logged out successfully" }))
+}
+
+/// `refresh_token`のセッションを無効化します
+///
+/// 無効化済みや他のユーザーのリフレッシュトークンは、ログアウトを妨げないよう無視します
+async fn revoke_session_by_token(state: &AppState, user_id: &UserId, refresh_token: &str) -> Result<(), ApiError> {
+    let result = match state.refresh_token_service.verify(refresh_token).await {
+        Ok(verified) => state.refresh_token_service.revoke_session(user_id, &verified.session_id).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => match e.downcast_ref::<RefreshTokenError>() {
+            Some(
+                error @ (RefreshTokenError::InvalidToken
+                | RefreshTokenError::Expired
+                | RefreshTokenError::Revoked(_)
+                | RefreshTokenError::ReuseDetected(_)
+                | RefreshTokenError::NotFound(_)
+                | RefreshTokenError::SessionNotFound(_)),
+            ) => {
+                tracing::warn!("session is not revoked on logout. user_id: {}, {:?}", user_id, error);
+                Ok(())
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+/// POST /auth/refresh-token
+///
+/// リフレッシュトークンをローテーションし、新しいアクセストークンとリフレッシュトークンを返します
+/// 認証基盤でのトークンの更新に成功した場合のみ、提示されたリフレッシュトークンを使用済みにします
+/// 使用済みのリフレッシュトークンが提示された場合は、同じ系列のトークンをすべて無効化します
+pub async fn refresh_token(
+    State(state): State<AppState>, ClientIp(ip): ClientIp, ClientDevice(device): ClientDevice,
+    payload: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Result<Json<RefreshTokenResponse>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let verified = match state.refresh_token_service.verify(&request.refresh_token).await.map_err(ApiError::from) {
+        Ok(verified) => verified,
+        Err(ApiError::InvalidToken) => {
+            record(
+                &state,
+                security_event(&state, SecurityEventType::InvalidToken, &device).with_detail("refresh_token"),
+            )
+            .await;
+            return Err(ApiError::InvalidToken);
+        }
+        Err(e) => return Err(e),
+    };
+    let user = state.user_service.find_by_id(&verified.user_id).await?;
+
+    // 認証基盤での更新に失敗した場合に同じトークンで再試行できるよう、ローテーションは最後に保存します
+    let token =
+        state.auth_user_repository.refresh(user.email().clone(), &verified.upstream_refresh_token).await.map_err(
+            |e| match e {
+                AuthUserError::AuthenticationFailed(_) => ApiError::InvalidToken,
+                e => e.into(),
+            },
+        )?;
+    let rotation =
+        state.refresh_token_service.rotate(&request.refresh_token, &ip, token.refresh_token().expose_secret()).await?;
+
+    let response = RefreshTokenResponse { token, refresh_token: rotation.refresh_token };
+    Ok(Json(response))
+}
+
+/// POST /auth/password/forgot
+///
+/// パスワード再設定用のコードを送信します
+/// メールアドレスの登録有無を推測されないよう、送信に失敗した場合も同じレスポンスを返します
+pub async fn forgot_password(
+    State(state): State<AppState>, payload: Result<Json<ForgotPasswordRequest>, JsonRejection>,
+) -> Result<Json<PasswordResponse>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let email = Email::from_str(&request.email).map_err(UserError::from)?;
+
+    if let Err(e) = state.auth_user_repository.forgot_password(email).await {
+        tracing::warn!("forgot password failed: {:?}", e);
+    }
+    Ok(Json(PasswordResponse { message: "If the email is registered, a reset code has been sent" }))
+}
+
+/// POST /auth/password/reset
+///
+/// 再設定用のコードを確認し、パスワードを置き換えてすべての端末のリフレッシュトークンを無効化します
+/// 未登録のメールアドレスと誤ったコードは区別せず、どちらも`AUTH_003`を返します
+/// 新しいパスワードはサインアップと同じパスワードポリシーで検証します
+pub async fn reset_password(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice,
+    payload: Result<Json<ResetPasswordRequest>, JsonRejection>,
+) -> Result<Json<PasswordResponse>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let email = Email::from_str(&request.email).map_err(UserError::from)?;
+    let new_password = state.password_service.validate(&request.new_password, &email, None).await?;
+
+    state.auth_user_repository.confirm_forgot_password(email.clone(), &request.code, new_password).await.map_err(
+        |e| match e {
+            AuthUserError::AuthenticationFailed(_) => ApiError::InvalidToken,
+            e => e.into(),
+        },
+    )?;
+    // 認証基盤はリフレッシュトークンを無効化しないため、パスワードを知っていた第三者のセッションもここで無効化します
+    let user = state.user_service.find_by_email(&email).await?;
+    state.refresh_token_service.revoke_all_sessions(user.user_id()).await?;
+
+    let event = security_event(&state, SecurityEventType::PasswordReset, &device).with_user_id(user.user_id().clone());
+    record(&state, event.with_email(email.to_string())).await;
+    Ok(Json(PasswordResponse { message: "Password reset successfully" }))
+}
+
+/// POST /auth/email/change
+///
+/// 変更後のメールアドレスに確認コードを送信し、変更前のメールアドレスには変更が要求されたことを通知します
+/// `POST /auth/email/change/confirm`で確認されるまで、メールアドレスは変更しません
+/// アカウントの削除を依頼している間は変更できません
+pub async fn change_email(
+    State(state): State<AppState>, user: AuthenticatedUser, payload: Result<Json<ChangeEmailRequest>, JsonRejection>,
+) -> Result<Json<ChangeEmailResponse>, ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let new_email = Email::from_str(&request.email).map_err(UserError::from)?;
+    let user = state.user_service.find_by_id(&user.user_id).await?;
+    // ゲストユーザーは`POST /auth/guest/upgrade`でメールアドレスを登録します
+    if user.is_guest() {
+        return Err(ApiError::Forbidden);
+    }
+    ensure_no_deletion(&state, user.user_id()).await?;
+
+    state.auth_user_repository.request_email_change(user.email().clone(), new_email).await?;
+    tracing::info!("email change requested. user_id: {}", user.user_id());
+    Ok(Json(ChangeEmailResponse { message: "A verification code has been sent to the new email address" }))
+}
+
+/// POST /auth/email/change/confirm
+///
+/// 確認コードを確認し、認証基盤とユーザーテーブルのメールアドレスを変更します
+/// ユーザーテーブルの更新に失敗した場合は、認証基盤の変更を取り消します
+/// 変更前に発行したリフレッシュトークンは使用できなくなるため、変更後のメールアドレスで再度ログインします
+pub async fn confirm_email_change(
+    State(state): State<AppState>, headers: HeaderMap, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    payload: Result<Json<ConfirmEmailChangeRequest>, JsonRejection>,
+) -> Result<Json<UserResponse>, ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let user = state.user_service.find_by_id(&user.user_id).await?;
+    ensure_no_deletion(&state, user.user_id()).await?;
+
+    let new_email = state
+        .auth_user_repository
+        .confirm_email_change(user.email().clone(), &request.code, bearer_token(&headers)?)
+        .await
+        .map_err(|e| match e {
+            AuthUserError::AuthenticationFailed(_) => ApiError::InvalidToken,
+            e => e.into(),
+        })?;
+    let changed = user.change_email(new_email.clone());
+    if let Err(e) = state.user_service.update(changed.clone()).await {
+        if let Err(e) = state.auth_user_repository.revert_email_change(new_email, user.email().clone()).await {
+            tracing::error!("failed to revert email change. user_id: {}, {:?}", user.user_id(), e);
+        }
+        return Err(e.into());
+    }
+
+    tracing::info!("email changed. user_id: {}", user.user_id());
+    let event = security_event(&state, SecurityEventType::EmailChanged, &device).with_user_id(user.user_id().clone());
+    record(&state, event.with_email(changed.email().to_string())).await;
+    Ok(Json(UserResponse::from(&changed)))
+}
+
+/// アカウントの削除を依頼している場合は`DeletionAlreadyRequested`を返します
+async fn ensure_no_deletion(state: &AppState, user_id: &UserId) -> Result<(), ApiError> {
+    match state.account_deletion_service.find_active(user_id).await? {
+        Some(_) => Err(ApiError::DeletionAlreadyRequested),
+        None => Ok(()),
+    }
+}
+
+/// GET /auth/oidc/:provider/authorize
+///
+/// IDプロバイダーの認可エンドポイントのURLと`state`を返します
+/// クライアントは`state`を保存し、リダイレクトで戻った`state`と一致することを確認してからコールバックを呼び出します
+pub async fn oidc_authorize(
+    State(state): State<AppState>, Path(provider): Path<String>,
+) -> Result<Json<OidcAuthorizeResponse>, ApiError> {
+    let request = state.oidc_providers.authorize(&provider).await?;
+
+    Ok(Json(OidcAuthorizeResponse { authorization_url: request.authorization_url, state: request.state }))
+}
+
+/// POST /auth/oidc/:provider/callback
+///
+/// 認可コードをIDトークンに交換してログインします
+/// 初回のログインでは、確認済みのメールアドレスが一致するユーザーに紐付け、いなければユーザーを作成します
+/// 多要素認証が有効な場合は`POST /auth/login`と同じくチャレンジトークンを返します
+pub async fn oidc_callback(
+    State(state): State<AppState>, Path(provider): Path<String>, ClientDevice(device): ClientDevice,
+    payload: Result<Json<OidcCallbackRequest>, JsonRejection>,
+) -> Result<Json<LoginResult>, ApiError> {
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let claims = state.oidc_providers.callback(&provider, &request.code, &request.state).await?;
+
+    let (user, token) = match state.external_identity_repository.find(&provider, &claims.sub).await? {
+        Some(identity) => {
+            let user = state.user_service.find_by_id(identity.user_id()).await?;
+            let sign_in = state.auth_user_repository.federated_sign_in(user.email().clone()).await?;
+            if sign_in.user_id() != user.user_id() {
+                tracing::error!("external identity is linked to another user. user_id: {}", user.user_id());
+                return Err(ApiError::InvalidCredentials);
+            }
+            (user, sign_in.into_token())
+        }
+        None => link_external_identity(&state, &provider, &claims).await?,
+    };
+
+    if state.mfa_service.is_enabled(user.user_id()).await? {
+        let challenge_token = state.mfa_service.issue_challenge(user.user_id(), &token)?;
+        return Ok(Json(LoginResult::MfaRequired(MfaRequiredResponse { mfa_required: true, challenge_token })));
+    }
+    Ok(Json(LoginResult::Authenticated(complete_login(&state, &user, token, device, "oidc").await?)))
+}
+
+/// IDプロバイダーのアカウントを、確認済みのメールアドレスでユーザーに紐付けます
+///
+/// 未確認のメールアドレスで紐付けると他人のアカウントを乗っ取れるため拒否します
+async fn link_external_identity(
+    state: &AppState, provider: &str, claims: &IdTokenClaims,
+) -> Result<(User, Token), ApiError> {
+    let email = match (&claims.email, claims.email_verified) {
+        (Some(email), true) => Email::from_str(email).map_err(UserError::from)?,
+        _ => return Err(OidcError::EmailNotVerified(claims.sub.clone()).into()),
+    };
+
+    let sign_in = state.auth_user_repository.federated_sign_in(email.clone()).await?;
+    let user = match sign_in.created() {
+        true => {
+            let name = federated_name(claims.name.as_deref(), &email)?;
+            let user = User::build(
+                &sign_in.user_id().to_string(),
+                email.value(),
+                &name.to_string(),
+                UserType::Registerd as usize,
+                None,
+            )?;
+            state.user_service.create_user(user.clone()).await?;
+            user
+        }
+        false => state.user_service.find_by_id(sign_in.user_id()).await?,
+    };
+
+    let identity = ExternalIdentity::new(
+        provider.to_string(),
+        claims.sub.clone(),
+        user.user_id().clone(),
+        email,
+        state.clock.now(),
+    );
+    state.external_identity_repository.save(identity).await?;
+    tracing::info!("external identity linked. provider: {}, user_id: {}", provider, user.user_id());
+
+    Ok((user, sign_in.into_token()))
+}
+
+/// IDトークンの`name`、なければメールアドレスの`@`より前を、ユーザー名の上限に収まるよう切り詰めます
+fn federated_name(name: Option<&str>, email: &Email) -> Result<Name, ApiError> {
+    let name = name
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| email.value().split('@').next().unwrap_or_default());
+
+    Ok(Name::truncate(name).map_err(UserError::from)?)
+}
+
+/// アクセストークンのクレームの`sub`を`UserId`に変換します
+///
+/// 署名は検証しないため、認証基盤から直接受け取ったトークンにのみ使用してください
+fn user_id_from_token(token: &Token) -> Result<UserId, ApiError> {
+    let claims = token.claims()?;
+    let sub = uuid::Uuid::parse_str(claims.sub()).map_err(|_| ApiError::InvalidToken)?;
+    Ok(UserId::from(sub))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl,
+        data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl,
+        mfa_service::{self, MfaServiceImpl},
+        password_service::PasswordServiceImpl,
+        personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl,
+        refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl,
+        UserService,
+    };
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header::AUTHORIZATION, Request},
+        Router,
+    };
+    use base64::Engine;
+    use domain::{
+        auth_user::{FederatedSignIn, GuestSignUp},
+        clock::{Clock, SystemClock},
+        login_attempt::LockoutPolicy,
+        repository::auth_user_repository::AuthUserRepository,
+        user::{
+            email::Email,
+            password::{Password, PasswordPolicy},
+        },
+    };
+    use infrastructure::{
+        code_sender::CodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::{OidcClient, OidcProviders},
+            testing::MockOidcProvider,
+        },
+        rate_limit::{Limit, RateLimiter, RouteLimits},
+        RateLimitSettings,
+    };
+
+    const SUB: &str = "550e8400-e29b-41d4-a716-446655440000";
+    /// `Summer2024`のSHA-1です
+    const BREACHED_PASSWORDS: &str = "6EA164759ADCCDF0B63C3E6A8A52792691F4C37B:120";
+    const USER_ID: &str = "usr_550e8400-e29b-41d4-a716-446655440000";
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    fn jwt(sub: &str) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let header = engine.encode(json!({ "alg": "none" }).to_string());
+        let payload = engine.encode(json!({ "sub": sub, "token_use": "access" }).to_string());
+        format!("{}.{}.signature", header, payload)
+    }
+
+    fn token(refresh_token: &str) -> Token {
+        Token::new(jwt(SUB).into(), refresh_token.to_string().into(), SystemClock.now(), 3600)
+    }
+
+    #[derive(Default)]
+    struct FakeAuthUserRepository {
+        users: Mutex<HashMap<String, String>>,
+        signed_out: Arc<Mutex<Vec<String>>>,
+        unconfirmed: bool,
+        /// 認証基盤でのトークンの更新を失敗させる回数です
+        refresh_failures: Mutex<usize>,
+    }
+
+    impl AuthUserRepository for FakeAuthUserRepository {
+        fn authenticate(
+            &self, auth: AuthUser,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Token, AuthUserError>> + Send + '_>> {
+            let registered = self.users.lock().unwrap().get(auth.email().value()).cloned();
+            let result = match registered {
+                Some(password) if password == auth.password().expose_secret() && !self.unconfirmed => {
+                    Ok(token("refresh_token"))
+                }
+                _ => Err(AuthUserError::AuthenticationFailed("Incorrect username or password.".to_string())),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn sign_up(
+            &self, auth: AuthUser,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<UserId, AuthUserError>> + Send + '_>> {
+            let mut users = self.users.lock().unwrap();
+            let result = match users.contains_key(auth.email().value()) {
+                true => Err(AuthUserError::UserAlreadyExists),
+                false => {
+                    users.insert(auth.email().to_string(), auth.password().expose_secret().to_string());
+                    Ok(UserId::from_str(USER_ID).unwrap())
+                }
+            };
+            Box::pin(async move { result })
+        }
+
+        fn verify_code(
+            &self, _email: Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            let result = match code {
+                "123456" => Ok(()),
+                _ => Err(AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string())),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn refresh(
+            &self, _email: Email, refresh_token: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Token, AuthUserError>> + Send + '_>> {
+            let mut refresh_failures = self.refresh_failures.lock().unwrap();
+            if *refresh_failures > 0 {
+                *refresh_failures -= 1;
+                return Box::pin(async { Err(AuthUserError::InternalServerError("Service unavailable".to_string())) });
+            }
+            // 認証基盤側でもリフレッシュトークンがローテーションされるケースを再現します
+            let result = match refresh_token {
+                "refresh_token" | "rotated_refresh_token" => Ok(token("rotated_refresh_token")),
+                _ => Err(AuthUserError::AuthenticationFailed("Invalid Refresh Token".to_string())),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn forgot_password(
+            &self, email: Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            let result = match self.users.lock().unwrap().contains_key(email.value()) {
+                true => Ok(()),
+                false => {
+                    Err(AuthUserError::AuthenticationFailed("Username/client id combination not found.".to_string()))
+                }
+            };
+            Box::pin(async move { result })
+        }
+
+        fn confirm_forgot_password(
+            &self, email: Email, code: &str, new_password: Password,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            let mut users = self.users.lock().unwrap();
+            let result = match (users.get_mut(email.value()), code) {
+                (Some(password), "123456") => {
+                    *password = new_password.expose_secret().to_string();
+                    Ok(())
+                }
+                _ => Err(AuthUserError::AuthenticationFailed("Invalid verification code provided.".to_string())),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn sign_out(
+            &self, access_token: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.signed_out.lock().unwrap().push(access_token.to_string());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn federated_sign_in(
+            &self, email: Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<FederatedSignIn, AuthUserError>> + Send + '_>>
+        {
+            let mut users = self.users.lock().unwrap();
+            let created = !users.contains_key(email.value());
+            if created {
+                users.insert(email.to_string(), String::new());
+            }
+            let token = token("refresh_token");
+            let result = Ok(FederatedSignIn::new(UserId::from_str(USER_ID).unwrap(), token, created));
+            Box::pin(async move { result })
+        }
+
+        fn sign_up_guest(
+            &self, user_id: UserId, email: Email, _expires_at: i64,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<GuestSignUp, AuthUserError>> + Send + '_>>
+        {
+            self.users.lock().unwrap().insert(email.to_string(), String::new());
+            Box::pin(async { Ok(GuestSignUp::new(user_id, token("refresh_token"))) })
+        }
+
+        fn upgrade_guest(
+            &self, guest_email: Email, auth: AuthUser,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            let mut users = self.users.lock().unwrap();
+            let result = match users.contains_key(auth.email().value()) {
+                true => Err(AuthUserError::UserAlreadyExists),
+                false => {
+                    users.remove(guest_email.value());
+                    users.insert(auth.email().to_string(), auth.password().expose_secret().to_string());
+                    Ok(())
+                }
+            };
+            Box::pin(async move { result })
+        }
+
+        fn verify_guest_upgrade(
+            &self, _user_id: UserId, email: Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.verify_code(email, code)
+        }
+
+        fn revert_guest_upgrade(
+            &self, guest_email: Email, email: Email, _expires_at: i64,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            let mut users = self.users.lock().unwrap();
+            users.remove(email.value());
+            users.insert(guest_email.to_string(), String::new());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn request_email_change(
+            &self, _email: Email, _new_email: Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            Box::pin(async { Err(AuthUserError::InternalServerError("Email change is not supported".to_string())) })
+        }
+
+        fn confirm_email_change(
+            &self, _email: Email, _code: &str, _access_token: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Email, AuthUserError>> + Send + '_>> {
+            Box::pin(async { Err(AuthUserError::InternalServerError("Email change is not supported".to_string())) })
+        }
+
+        fn revert_email_change(
+            &self, _new_email: Email, _email: Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            Box::pin(async { Err(AuthUserError::InternalServerError("Email change is not supported".to_string())) })
+        }
+
+        fn delete_user(
+            &self, _email: Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeUserService {
+        users: Arc<Mutex<HashMap<String, User>>>,
+        /// ユーザーテーブルへの作成に失敗するケースを再現します
+        fail_create: bool,
+        /// ユーザーテーブルの更新に失敗するケースを再現します
+        fail_update: bool,
+    }
+
+    impl UserService for FakeUserService {
+        fn find_by_id(
+            &self, user_id: &UserId,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+            let result = self
+                .users
+                .lock()
+                .unwrap()
+                .get(&user_id.to_string())
+                .cloned()
+                .ok_or_else(|| UserError::NotFound(user_id.to_string()).into());
+            Box::pin(async move { result })
+        }
+
+        fn find_by_email(
+            &self, email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+            let result = self
+                .users
+                .lock()
+                .unwrap()
+                .values()
+                .find(|v| v.email() == email)
+                .cloned()
+                .ok_or_else(|| UserError::NotFound(email.to_string()).into());
+            Box::pin(async move { result })
+        }
+
+        fn exists_by_email(
+            &self, email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<bool>> + Send + '_>> {
+            let result = self.users.lock().unwrap().values().any(|v| v.email() == email);
+            Box::pin(async move { Ok(result) })
+        }
+
+        fn create_user(
+            &self, user: User,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+            if self.fail_create {
+                let error = UserError::CreateUserError(user.user_id().to_string());
+                return Box::pin(async move { Err(error.into()) });
+            }
+            self.users.lock().unwrap().insert(user.user_id().to_string(), user);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn update(
+            &self, user: User,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + '_>> {
+            if self.fail_update {
+                let error = UserError::UpdateUserError(user.user_id().to_string());
+                return Box::pin(async move { Err(error.into()) });
+            }
+            self.users.lock().unwrap().insert(user.user_id().to_string(), user);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn new_guest(&self, name: Name) -> User {
+            User::guest(UserId::new(), name, SystemClock.now() + 60 * 60)
+        }
+
+        fn change_role(
+            &self, user_id: &UserId, role: domain::user::role::Role,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<User>> + Send + '_>> {
+            let result = self
+                .users
+                .lock()
+                .unwrap()
+                .get_mut(&user_id.to_string())
+                .map(|v| {
+                    *v = v.clone().with_role(role);
+                    v.clone()
+                })
+                .ok_or_else(|| UserError::NotFound(user_id.to_string()).into());
+            Box::pin(async move { result })
+        }
+    }
+
+    fn app(auth_user_repository: impl AuthUserRepository) -> Router {
+        app_with_rate_limit(auth_user_repository, RateLimitSettings::default())
+    }
+
+    fn app_with_rate_limit(auth_user_repository: impl AuthUserRepository, rate_limit: RateLimitSettings) -> Router {
+        app_with(auth_user_repository, FakeUserService::default(), rate_limit, vec![])
+    }
+
+    /// `google`と`apple`のどちらも`provider`を使うアプリケーションです
+    fn app_with_oidc(auth_user_repository: impl AuthUserRepository, provider: &MockOidcProvider) -> Router {
+        let clients = vec![
+            OidcClient::new(provider.settings("google")),
+            OidcClient::new(provider.settings("apple")),
+        ];
+        app_with(auth_user_repository, FakeUserService::default(), RateLimitSettings::default(), clients)
+    }
+
+    fn app_with(
+        auth_user_repository: impl AuthUserRepository, user_service: FakeUserService, rate_limit: RateLimitSettings,
+        oidc_clients: Vec<OidcClient>,
+    ) -> Router {
+        let auth_user_repository = Arc::new(auth_user_repository);
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LockoutPolicy { threshold: 10, ..LOCKOUT_POLICY },
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                oidc_clients,
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::parse(BREACHED_PASSWORDS).unwrap()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                Arc::new(InMemoryUserRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            )),
+        );
+        crate::router::router(state, Arc::new(RateLimiter::new(rate_limit)))
+    }
+
+    async fn send(app: &Router, uri: &str, body: Value, authorization: Option<&str>) -> (StatusCode, Value) {
+        let mut request = Request::post(uri).header("content-type", "application/json");
+        if let Some(v) = authorization {
+            request = request.header(AUTHORIZATION, v);
+        }
+        let response = app.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn sign_up_body() -> Value {
+        json!({ "email": "hoge@email.com", "password": "Password123", "name": "hoge" })
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_success() {
+        let app = app(FakeAuthUserRepository::default());
+
+        let (status, body) = send(&app, "/auth/signup", sign_up_body(), None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(USER_ID, body["user_id"]);
+        assert_eq!(jwt(SUB), body["token"]);
+        assert_eq!("hoge@email.com", body["email"]);
+        assert_eq!("hoge", body["name"]);
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_requires_verification() {
+        let app = app(FakeAuthUserRepository { unconfirmed: true, ..Default::default() });
+
+        let (status, body) = send(&app, "/auth/signup", sign_up_body(), None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert!(body["token"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_failed() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        let test_cases = vec![
+            (sign_up_body(), StatusCode::CONFLICT, "AUTH_004"),
+            (json!({ "email": "hoge", "password": "Password123", "name": "hoge" }), StatusCode::BAD_REQUEST, "VAL_001"),
+            (
+                json!({ "email": "fuga@email.com", "password": "pass", "name": "fuga" }),
+                StatusCode::BAD_REQUEST,
+                "VAL_002",
+            ),
+            (json!({ "email": "fuga@email.com", "password": "Password123" }), StatusCode::BAD_REQUEST, "VAL_003"),
+        ];
+
+        for (request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/signup", request, None).await;
+            assert_eq!(expected_status, status);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_existing_user() {
+        let user_service = FakeUserService::default();
+        let user = User::build(USER_ID, "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.users.lock().unwrap().insert(USER_ID.to_string(), user);
+        let app = app_with(FakeAuthUserRepository::default(), user_service, RateLimitSettings::default(), vec![]);
+
+        let (status, body) = send(&app, "/auth/signup", sign_up_body(), None).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("AUTH_004", body["error"]["code"]);
+
+        // 認証基盤には登録しません
+        let credentials = json!({ "email": "hoge@email.com", "password": "Password123" });
+        let (status, body) = send(&app, "/auth/login", credentials, None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_001", body["error"]["code"]);
+    }
+
+    /// メールアドレスの大文字と小文字の違いで、別のアカウントを登録できません
+    #[tokio::test]
+    async fn test_sign_up_email_case_insensitive() {
+        let (app, _) = local_app();
+        let request = |email: &str| json!({ "email": email, "password": "Password123", "name": "hoge" });
+
+        let (status, body) = send(&app, "/auth/signup", request("Foo@x.com"), None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!("foo@x.com", body["email"]);
+
+        let test_cases = vec![
+            "foo@x.com",
+            "FOO@X.COM",
+        ];
+        for email in test_cases {
+            let (status, body) = send(&app, "/auth/signup", request(email), None).await;
+            assert_eq!(StatusCode::CONFLICT, status, "{}", email);
+            assert_eq!("AUTH_004", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_password_policy() {
+        let app = app(FakeAuthUserRepository::default());
+
+        let test_cases = vec![
+            (
+                "pass",
+                vec![
+                    "too_short",
+                    "missing_digit",
+                ],
+            ),
+            ("Summer2024", vec!["breached"]),
+            ("fugafuga123", vec!["contains_email"]),
+            ("Hanako2024", vec!["contains_name"]),
+        ];
+        for (password, expected) in test_cases {
+            let request = json!({ "email": "fugafuga@email.com", "password": password, "name": "hanako" });
+            let (status, body) = send(&app, "/auth/signup", request, None).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status, "{}", password);
+            assert_eq!("VAL_002", body["error"]["code"]);
+            assert_eq!(json!(expected), body["error"]["details"], "{}", password);
+        }
+
+        let request = json!({ "email": "fugafuga@email.com", "password": "パスワード2024", "name": "hanako" });
+        let (status, _) = send(&app, "/auth/signup", request, None).await;
+        assert_eq!(StatusCode::CREATED, status);
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let app = app(FakeAuthUserRepository::default());
+
+        let test_cases = vec![
+            (json!({ "email": "hoge@email.com", "code": "123456" }), StatusCode::OK, None),
+            (json!({ "email": "hoge@email.com", "code": "000000" }), StatusCode::UNAUTHORIZED, Some("AUTH_001")),
+            (json!({ "email": "hoge", "code": "123456" }), StatusCode::BAD_REQUEST, Some("VAL_001")),
+            (json!({ "email": "hoge@email.com" }), StatusCode::BAD_REQUEST, Some("VAL_003")),
+        ];
+        for (request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/verify", request, None).await;
+            assert_eq!(expected_status, status);
+            if let Some(code) = expected_code {
+                assert_eq!(code, body["error"]["code"]);
+            }
+        }
+    }
+
+    /// 送信した確認コードを記録します
+    #[derive(Default)]
+    struct RecordingCodeSender {
+        codes: Mutex<Vec<String>>,
+    }
+
+    impl CodeSender for RecordingCodeSender {
+        fn send_verification_code(
+            &self, _email: &Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.codes.lock().unwrap().push(code.to_string());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn send_password_reset_code(
+            &self, email: &Email, code: &str,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            self.send_verification_code(email, code)
+        }
+
+        fn send_email_change_notice(
+            &self, _email: &Email, _new_email: &Email,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AuthUserError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// 自前の認証を使うアプリケーションと、確認コードの送信先です
+    fn local_app() -> (Router, Arc<RecordingCodeSender>) {
+        local_app_with(FakeUserService::default())
+    }
+
+    fn local_app_with(user_service: FakeUserService) -> (Router, Arc<RecordingCodeSender>) {
+        let code_sender = Arc::new(RecordingCodeSender::default());
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            crate::auth::testing::ISSUER.to_string(),
+            crate::auth::testing::AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let app = app_with(
+            LocalAuthUserRepository::new(
+                Arc::new(InMemoryCredentialRepository::new()),
+                code_sender.clone(),
+                token_issuer,
+            ),
+            user_service,
+            RateLimitSettings::default(),
+            vec![],
+        );
+        (app, code_sender)
+    }
+
+    /// Cognitoを使わずにサインアップから確認、ログイン、ログアウトまでを通します
+    #[tokio::test]
+    async fn test_local_backend_flow() {
+        let (app, code_sender) = local_app();
+        let credentials = json!({ "email": "hoge@email.com", "password": "Password123" });
+
+        let (status, body) = send(&app, "/auth/signup", sign_up_body(), None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert!(body["token"].is_null());
+        let user_id = body["user_id"].clone();
+
+        let (status, _) = send(&app, "/auth/login", credentials.clone(), None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        let (status, _) = send(&app, "/auth/verify", json!({ "email": "hoge@email.com", "code": code }), None).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (status, body) = send(&app, "/auth/login", credentials, None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(user_id, body["user"]["user_id"]);
+        let access_token = body["token"].as_str().unwrap().to_string();
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_ne!(refresh_token, body["refresh_token"]);
+
+        let authorization = format!("Bearer {}", access_token);
+        let (status, _) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (status, _) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let (status, _) = send(&app, "/auth/password/forgot", json!({ "email": "hoge@email.com" }), None).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        let reset = json!({ "email": "hoge@email.com", "code": code, "new_password": "NewPassword456" });
+        let (status, _) = send(&app, "/auth/password/reset", reset, None).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (status, _) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let (status, _) =
+            send(&app, "/auth/login", json!({ "email": "hoge@email.com", "password": "NewPassword456" }), None).await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    /// ゲストユーザーとしてログインし、同じ`UserId`のまま登録済みのユーザーにします
+    #[tokio::test]
+    async fn test_guest_upgrade_flow() {
+        let users = Arc::new(Mutex::new(HashMap::new()));
+        let (app, code_sender) = local_app_with(FakeUserService { users: users.clone(), ..Default::default() });
+
+        let (status, body) = send(&app, "/auth/guest", Value::Null, None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!("Guest", body["user"]["name"]);
+        assert!(body["user"]["email"].as_str().unwrap().ends_with("@guest.subtrack.test"));
+        let user_id = body["user"]["user_id"].clone();
+        let authorization = format!("Bearer {}", body["token"].as_str().unwrap());
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::OK, status);
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = send(&app, "/auth/guest/upgrade", sign_up_body(), Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(user_id, body["user_id"]);
+        assert_eq!("hoge@email.com", body["email"]);
+        assert!(body["token"].is_null());
+        // メールアドレスが確認されるまではゲストユーザーのままです
+        let is_guest =
+            |users: &Mutex<HashMap<String, User>>| users.lock().unwrap()[user_id.as_str().unwrap()].is_guest();
+        assert!(is_guest(&users));
+
+        // 登録を要求したユーザーは再度登録できません
+        let (status, body) = send(&app, "/auth/guest/upgrade", sign_up_body(), Some(&authorization)).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("AUTH_004", body["error"]["code"]);
+
+        let (status, _) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        let (status, _) = send(&app, "/auth/verify", json!({ "email": "hoge@email.com", "code": code }), None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(!is_guest(&users));
+
+        let credentials = json!({ "email": "hoge@email.com", "password": "Password123" });
+        let (status, body) = send(&app, "/auth/login", credentials, None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(user_id, body["user"]["user_id"]);
+        assert_eq!("hoge", body["user"]["name"]);
+    }
+
+    /// ユーザーテーブルへの作成に失敗した場合は、認証基盤のユーザーを削除して登録し直せるようにします
+    #[tokio::test]
+    async fn test_sign_up_rollback() {
+        let (app, _) = local_app_with(FakeUserService { fail_create: true, ..Default::default() });
+
+        for _ in 0..2 {
+            let (status, body) = send(&app, "/auth/signup", sign_up_body(), None).await;
+            assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status);
+            assert_eq!("SRV_002", body["error"]["code"]);
+        }
+    }
+
+    /// ユーザーテーブルの更新に失敗した場合は、認証基盤をゲストユーザーに戻します
+    #[tokio::test]
+    async fn test_upgrade_guest_rollback() {
+        let (app, _) = local_app_with(FakeUserService { fail_update: true, ..Default::default() });
+        let (_, body) = send(&app, "/auth/guest", Value::Null, None).await;
+        let authorization = format!("Bearer {}", body["token"].as_str().unwrap());
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = send(&app, "/auth/guest/upgrade", sign_up_body(), Some(&authorization)).await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status);
+        assert_eq!("SRV_002", body["error"]["code"]);
+
+        let (status, _) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::OK, status);
+        let credentials = json!({ "email": "hoge@email.com", "password": "Password123" });
+        let (status, _) = send(&app, "/auth/login", credentials, None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_guest_failed() {
+        let (local, _) = local_app();
+        send(&local, "/auth/signup", sign_up_body(), None).await;
+
+        let (_, body) = send(&local, "/auth/guest", Value::Null, None).await;
+        let authorization = format!("Bearer {}", body["token"].as_str().unwrap());
+
+        let test_cases = vec![
+            (sign_up_body(), None, StatusCode::UNAUTHORIZED, "AUTH_003"),
+            (sign_up_body(), Some(authorization.as_str()), StatusCode::CONFLICT, "AUTH_004"),
+            (json!({ "email": "fuga@email.com" }), Some(authorization.as_str()), StatusCode::BAD_REQUEST, "VAL_003"),
+        ];
+        for (request, authorization, status, code) in test_cases {
+            let (result, body) = send(&local, "/auth/guest/upgrade", request, authorization).await;
+            assert_eq!(status, result);
+            assert_eq!(code, body["error"]["code"]);
+        }
+
+        // ゲストユーザーでなければ登録できません
+        let registered = app(FakeAuthUserRepository::default());
+        send(&registered, "/auth/signup", sign_up_body(), None).await;
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let request = json!({ "email": "fuga@email.com", "password": "Password123", "name": "fuga" });
+        let (status, body) = send(&registered, "/auth/guest/upgrade", request, Some(&authorization)).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("AUTH_004", body["error"]["code"]);
+    }
+
+    /// ログイン済みのユーザーのアクセストークンです
+    async fn local_login(app: &Router, code_sender: &RecordingCodeSender) -> String {
+        send(app, "/auth/signup", sign_up_body(), None).await;
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+        send(app, "/auth/verify", json!({ "email": "hoge@email.com", "code": code }), None).await;
+
+        let credentials = json!({ "email": "hoge@email.com", "password": "Password123" });
+        let (_, body) = send(app, "/auth/login", credentials, None).await;
+        format!("Bearer {}", body["token"].as_str().unwrap())
+    }
+
+    /// 登録済みのアカウントへのログイン失敗は、本人のセキュリティイベントとして確認できます
+    #[tokio::test]
+    async fn test_login_failed_recorded_for_user() {
+        let (app, code_sender) = local_app();
+        let authorization = local_login(&app, &code_sender).await;
+
+        let test_cases = vec![
+            json!({ "email": "hoge@email.com", "password": "WrongPassword123" }),
+            json!({ "email": "fuga@email.com", "password": "WrongPassword123" }),
+        ];
+        for credentials in test_cases {
+            let (status, _) = send(&app, "/auth/login", credentials, None).await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status);
+        }
+
+        let request = Request::get("/me/security-events?event_type=login_failed")
+            .header(AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let events = body["events"].as_array().unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!("hoge@email.com", events[0]["email"]);
+        assert_eq!("password", events[0]["detail"]);
+    }
+
+    #[tokio::test]
+    async fn test_change_email_flow() {
+        let (app, code_sender) = local_app();
+        let authorization = local_login(&app, &code_sender).await;
+
+        let request = json!({ "email": "fuga@email.com" });
+        let (status, _) = send(&app, "/auth/email/change", request, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+
+        let (status, body) =
+            send(&app, "/auth/email/change/confirm", json!({ "code": "wrong" }), Some(&authorization)).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let (status, body) =
+            send(&app, "/auth/email/change/confirm", json!({ "code": code }), Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("fuga@email.com", body["email"]);
+
+        let test_cases = vec![
+            ("hoge@email.com", StatusCode::UNAUTHORIZED),
+            ("fuga@email.com", StatusCode::OK),
+        ];
+        for (email, expected) in test_cases {
+            let credentials = json!({ "email": email, "password": "Password123" });
+            let (status, body) = send(&app, "/auth/login", credentials, None).await;
+            assert_eq!(expected, status, "{}", email);
+            if status == StatusCode::OK {
+                assert_eq!("fuga@email.com", body["user"]["email"]);
+            }
+        }
+    }
+
+    /// ユーザーテーブルの更新に失敗した場合は、認証基盤のメールアドレスを元に戻します
+    #[tokio::test]
+    async fn test_confirm_email_change_rollback() {
+        let (app, code_sender) = local_app_with(FakeUserService { fail_update: true, ..Default::default() });
+        let authorization = local_login(&app, &code_sender).await;
+
+        let request = json!({ "email": "fuga@email.com" });
+        send(&app, "/auth/email/change", request, Some(&authorization)).await;
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+
+        let (status, body) =
+            send(&app, "/auth/email/change/confirm", json!({ "code": code }), Some(&authorization)).await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status);
+        assert_eq!("SRV_002", body["error"]["code"]);
+
+        let test_cases = vec![
+            ("hoge@email.com", StatusCode::OK),
+            ("fuga@email.com", StatusCode::UNAUTHORIZED),
+        ];
+        for (email, expected) in test_cases {
+            let credentials = json!({ "email": email, "password": "Password123" });
+            let (status, _) = send(&app, "/auth/login", credentials, None).await;
+            assert_eq!(expected, status, "{}", email);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_change_email_failed() {
+        let (app, code_sender) = local_app();
+        let authorization = local_login(&app, &code_sender).await;
+
+        let test_cases = vec![
+            (json!({ "email": "fuga@email.com" }), None, StatusCode::UNAUTHORIZED, "AUTH_003"),
+            (json!({ "email": "hoge@email.com" }), Some(authorization.as_str()), StatusCode::CONFLICT, "AUTH_004"),
+            (json!({ "email": "fuga" }), Some(authorization.as_str()), StatusCode::BAD_REQUEST, "VAL_001"),
+            (json!({}), Some(authorization.as_str()), StatusCode::BAD_REQUEST, "VAL_003"),
+        ];
+        for (request, authorization, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/email/change", request, authorization).await;
+            assert_eq!(expected_status, status);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+
+        // 変更を要求していなければ確認できません
+        let (status, body) =
+            send(&app, "/auth/email/change/confirm", json!({ "code": "123456" }), Some(&authorization)).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        // ゲストユーザーはメールアドレスを変更できません
+        let (_, body) = send(&app, "/auth/guest", Value::Null, None).await;
+        let guest = format!("Bearer {}", body["token"].as_str().unwrap());
+        let (status, body) = send(&app, "/auth/email/change", json!({ "email": "piyo@email.com" }), Some(&guest)).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        assert_eq!("AUTH_008", body["error"]["code"]);
+    }
+
+    /// アカウントの削除を依頼している間はメールアドレスを変更できません
+    #[tokio::test]
+    async fn test_change_email_deletion_requested() {
+        let (app, code_sender) = local_app();
+        let authorization = local_login(&app, &code_sender).await;
+        send(&app, "/auth/email/change", json!({ "email": "fuga@email.com" }), Some(&authorization)).await;
+        let code = code_sender.codes.lock().unwrap().last().cloned().unwrap();
+
+        let (status, _) = send(&app, "/auth/account/deletion", json!({}), Some(&authorization)).await;
+        assert_eq!(StatusCode::ACCEPTED, status);
+
+        let test_cases = vec![
+            ("/auth/email/change", json!({ "email": "piyo@email.com" })),
+            ("/auth/email/change/confirm", json!({ "code": code })),
+        ];
+        for (uri, request) in test_cases {
+            let (status, body) = send(&app, uri, request, Some(&authorization)).await;
+            assert_eq!(StatusCode::CONFLICT, status, "{}", uri);
+            assert_eq!("RES_002", body["error"]["code"], "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_success() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        let (status, body) =
+            send(&app, "/auth/login", json!({ "email": "hoge@email.com", "password": "Password123" }), None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(jwt(SUB), body["token"]);
+        assert_eq!(jwt(SUB), body["access_token"]);
+        assert!(body.get("id_token").is_none());
+        assert_eq!("Bearer", body["token_type"]);
+        assert_eq!(3600, body["expires_in"]);
+        assert!(body["refresh_token"].is_string());
+        assert_ne!("refresh_token", body["refresh_token"]);
+        assert_eq!(USER_ID, body["user"]["user_id"]);
+        assert_eq!("hoge@email.com", body["user"]["email"]);
+        assert_eq!("hoge", body["user"]["name"]);
+    }
+
+    #[tokio::test]
+    async fn test_login_failed() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        let (status, body) =
+            send(&app, "/auth/login", json!({ "email": "hoge@email.com", "password": "Password999" }), None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_001", body["error"]["code"]);
+    }
+
+    async fn login_with(app: &Router, password: &str) -> (StatusCode, Value) {
+        send(app, "/auth/login", json!({ "email": "hoge@email.com", "password": password }), None).await
+    }
+
+    #[tokio::test]
+    async fn test_login_lockout() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        for _ in 0..LOCKOUT_POLICY.threshold {
+            let (status, body) = login_with(&app, "Password999").await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status);
+            assert_eq!("AUTH_001", body["error"]["code"]);
+        }
+
+        // ロック中は正しいパスワードでもログインできません
+        let request = Request::post("/auth/login")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "email": "hoge@email.com", "password": "Password123" }).to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        let retry_after: u64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+        assert!(0 < retry_after && retry_after <= 30);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("AUTH_005", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_login_success_clears_failures() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        for _ in 0..LOCKOUT_POLICY.threshold - 1 {
+            login_with(&app, "Password999").await;
+        }
+        let (status, _) = login_with(&app, "Password123").await;
+        assert_eq!(StatusCode::OK, status);
+
+        for _ in 0..LOCKOUT_POLICY.threshold - 1 {
+            login_with(&app, "Password999").await;
+        }
+        let (status, _) = login_with(&app, "Password123").await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limit = |capacity| Limit { capacity, period: std::time::Duration::from_secs(60) };
+        let rate_limit = RateLimitSettings {
+            auth: RouteLimits { ip: limit(3), user: limit(100) },
+            api: RouteLimits { ip: limit(100), user: limit(100) },
+        };
+        let app = app_with_rate_limit(FakeAuthUserRepository::default(), rate_limit);
+
+        let refresh = |ip: &str, forwarded: &str| {
+            Request::post("/auth/refresh-token")
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", forwarded)
+                .extension(ConnectInfo(SocketAddr::new(ip.parse().unwrap(), 443)))
+                .body(Body::from(json!({ "refresh_token": "invalid" }).to_string()))
+                .unwrap()
+        };
+        // プロキシを設定していないため、偽装した`X-Forwarded-For`を変えても同じ接続元として制限します
+        for i in 0..3 {
+            let response = app.clone().oneshot(refresh("192.0.2.1", &format!("203.0.113.{}", i))).await.unwrap();
+            assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        }
+
+        let response = app.clone().oneshot(refresh("192.0.2.1", "203.0.113.10")).await.unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!("20", response.headers()["retry-after"]);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("RATE_001", body["error"]["code"]);
+
+        let response = app.clone().oneshot(refresh("198.51.100.1", "203.0.113.10")).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_by_user() {
+        let limit = |capacity| Limit { capacity, period: std::time::Duration::from_secs(60) };
+        let rate_limit = RateLimitSettings {
+            auth: RouteLimits { ip: limit(100), user: limit(1) },
+            api: RouteLimits { ip: limit(100), user: limit(100) },
+        };
+        let app = app_with_rate_limit(FakeAuthUserRepository::default(), rate_limit);
+
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let (status, _) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let (status, body) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, status);
+        assert_eq!("RATE_001", body["error"]["code"]);
+
+        // 無効なトークンは接続元IPアドレスの制限のみを適用し、ハンドラーで拒否します
+        let (status, body) = send(&app, "/auth/logout", Value::Null, Some("Bearer invalid")).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        let repository = FakeAuthUserRepository::default();
+        let signed_out = repository.signed_out.clone();
+        let app = app(repository);
+
+        let access_token = crate::auth::testing::access_token();
+        let authorization = format!("Bearer {}", access_token);
+        let (status, body) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("Logged out successfully", body["message"]);
+        assert_eq!(vec![access_token], *signed_out.lock().unwrap());
+
+        let (status, body) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let (status, _) = send(&app, "/auth/logout", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    /// リフレッシュトークンを指定した場合は、そのセッションも無効化します
+    #[tokio::test]
+    async fn test_logout_revokes_session() {
+        let app = app(FakeAuthUserRepository::default());
+        let refresh_token = login(&app).await;
+        let other_refresh_token = login(&app).await;
+
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let request = json!({ "refresh_token": refresh_token });
+        let (status, _) = send(&app, "/auth/logout", request, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (status, body) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+        let (status, _) = refresh(&app, &other_refresh_token).await;
+        assert_eq!(StatusCode::OK, status);
+
+        // 無効化済みのリフレッシュトークンを指定してもログアウトできます
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let request = json!({ "refresh_token": refresh_token });
+        let (status, _) = send(&app, "/auth/logout", request, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn test_logout_failed() {
+        let repository = FakeAuthUserRepository::default();
+        let signed_out = repository.signed_out.clone();
+        let app = app(repository);
+
+        let (status, body) = send(&app, "/auth/logout", Value::Null, Some(&format!("Bearer {}", jwt(SUB)))).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let (status, body) = send(&app, "/auth/logout", Value::Null, None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+        assert!(signed_out.lock().unwrap().is_empty());
+    }
+
+    async fn login(app: &Router) -> String {
+        send(app, "/auth/signup", sign_up_body(), None).await;
+        let (_, body) =
+            send(app, "/auth/login", json!({ "email": "hoge@email.com", "password": "Password123" }), None).await;
+        body["refresh_token"].as_str().unwrap().to_string()
+    }
+
+    async fn refresh(app: &Router, refresh_token: &str) -> (StatusCode, Value) {
+        send(app, "/auth/refresh-token", json!({ "refresh_token": refresh_token }), None).await
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_rotation() {
+        let app = app(FakeAuthUserRepository::default());
+        let first = login(&app).await;
+
+        let (status, body) = refresh(&app, &first).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(jwt(SUB), body["access_token"]);
+        assert_eq!("Bearer", body["token_type"]);
+        assert_eq!(3600, body["expires_in"]);
+        let second = body["refresh_token"].as_str().unwrap().to_string();
+        assert_ne!("rotated_refresh_token", second);
+        assert_ne!(first, second);
+
+        let (status, body) = refresh(&app, &second).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_ne!(second, body["refresh_token"]);
+    }
+
+    /// 盗まれたリフレッシュトークンが使用された後、正規ユーザーが同じトークンを使用するケースです
+    #[tokio::test]
+    async fn test_refresh_token_reuse_revokes_family() {
+        let app = app(FakeAuthUserRepository::default());
+        let stolen = login(&app).await;
+
+        let (status, body) = refresh(&app, &stolen).await;
+        assert_eq!(StatusCode::OK, status);
+        let attacker = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, body) = refresh(&app, &stolen).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let (status, body) = refresh(&app, &attacker).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let other = login(&app).await;
+        let (status, _) = refresh(&app, &other).await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    /// 認証基盤での更新に失敗した後に同じトークンで再試行しても、再利用とみなされないことを確認します
+    #[tokio::test]
+    async fn test_refresh_token_retry_after_upstream_failure() {
+        let app = app(FakeAuthUserRepository { refresh_failures: Mutex::new(1), ..Default::default() });
+        let token = login(&app).await;
+
+        let (status, _) = refresh(&app, &token).await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status);
+
+        let (status, body) = refresh(&app, &token).await;
+        assert_eq!(StatusCode::OK, status);
+        let rotated = body["refresh_token"].as_str().unwrap().to_string();
+
+        let (status, _) = refresh(&app, &rotated).await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_failed() {
+        let app = app(FakeAuthUserRepository::default());
+
+        let (status, body) = refresh(&app, "invalid").await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let (status, body) = send(&app, "/auth/refresh-token", json!({}), None).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_eq!("VAL_003", body["error"]["code"]);
+    }
+
+    /// 登録済みかどうかをレスポンスから判別できないことを確認します
+    #[tokio::test]
+    async fn test_forgot_password_same_response() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        let registered = send(&app, "/auth/password/forgot", json!({ "email": "hoge@email.com" }), None).await;
+        let unregistered = send(&app, "/auth/password/forgot", json!({ "email": "fuga@email.com" }), None).await;
+        assert_eq!(StatusCode::OK, registered.0);
+        assert_eq!(registered, unregistered);
+
+        let test_cases = vec![
+            (json!({ "email": "hoge" }), "VAL_001"),
+            (json!({}), "VAL_003"),
+        ];
+        for (request, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/password/forgot", request, None).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reset_password() {
+        let app = app(FakeAuthUserRepository::default());
+        let refresh_token = login(&app).await;
+
+        let reset = |email: &str, code: &str, new_password: &str| json!({ "email": email, "code": code, "new_password": new_password });
+        let test_cases = vec![
+            (reset("hoge@email.com", "000000", "NewPassword456"), StatusCode::UNAUTHORIZED, Some("AUTH_003")),
+            (reset("fuga@email.com", "123456", "NewPassword456"), StatusCode::UNAUTHORIZED, Some("AUTH_003")),
+            (reset("hoge@email.com", "123456", "pass"), StatusCode::BAD_REQUEST, Some("VAL_002")),
+            (reset("hoge", "123456", "NewPassword456"), StatusCode::BAD_REQUEST, Some("VAL_001")),
+            (json!({ "email": "hoge@email.com", "code": "123456" }), StatusCode::BAD_REQUEST, Some("VAL_003")),
+            (reset("hoge@email.com", "123456", "NewPassword456"), StatusCode::OK, None),
+        ];
+        for (request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/password/reset", request, None).await;
+            assert_eq!(expected_status, status);
+            if let Some(code) = expected_code {
+                assert_eq!(code, body["error"]["code"]);
+            }
+        }
+
+        let (status, _) =
+            send(&app, "/auth/login", json!({ "email": "hoge@email.com", "password": "NewPassword456" }), None).await;
+        assert_eq!(StatusCode::OK, status);
+
+        // 再設定前に発行したリフレッシュトークンは使用できません
+        let (status, body) = refresh(&app, &refresh_token).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+    }
+
+    /// 登録したシークレットから現在の前後のステップのコードを計算します
+    fn totp(secret: &str, offset: i64) -> String {
+        let secret = data_encoding::BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+        let now = SystemClock.now();
+        mfa_service::totp(&secret, now / mfa_service::PERIOD + offset)
+    }
+
+    /// 多要素認証を有効にし、シークレットとリカバリーコードを返します
+    async fn enable_mfa(app: &Router) -> (String, Vec<String>) {
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+        let (status, body) = send(app, "/auth/mfa/enroll", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        let secret = body["secret"].as_str().unwrap().to_string();
+        assert!(body["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/SubTrack:hoge%40email%2Ecom?"));
+
+        let (status, body) =
+            send(app, "/auth/mfa/activate", json!({ "code": totp(&secret, 0) }), Some(&authorization)).await;
+        assert_eq!(StatusCode::OK, status);
+        let recovery_codes = serde_json::from_value(body["recovery_codes"].clone()).unwrap();
+        (secret, recovery_codes)
+    }
+
+    async fn challenge(app: &Router) -> String {
+        let (status, body) = login_with(app, "Password123").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["mfa_required"]);
+        assert!(body["token"].is_null());
+        body["challenge_token"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_login_with_mfa() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+        let (secret, recovery_codes) = enable_mfa(&app).await;
+
+        let challenge_token = challenge(&app).await;
+        let other_challenge_token = challenge(&app).await;
+        let login_mfa = |code: String| json!({ "challenge_token": challenge_token, "code": code });
+        let login_other = |code: String| json!({ "challenge_token": other_challenge_token, "code": code });
+        let test_cases = vec![
+            (login_mfa("000000".to_string()), StatusCode::UNAUTHORIZED, Some("AUTH_006")),
+            // 有効化に使用したコードは再利用できません
+            (login_mfa(totp(&secret, 0)), StatusCode::UNAUTHORIZED, Some("AUTH_006")),
+            (
+                json!({ "challenge_token": "invalid", "code": totp(&secret, 1) }),
+                StatusCode::UNAUTHORIZED,
+                Some("AUTH_003"),
+            ),
+            (json!({ "challenge_token": challenge_token }), StatusCode::BAD_REQUEST, Some("VAL_003")),
+            (login_mfa(totp(&secret, 1)), StatusCode::OK, None),
+            // 使用済みのチャレンジトークンではログインできません
+            (login_mfa(recovery_codes[0].clone()), StatusCode::UNAUTHORIZED, Some("AUTH_003")),
+            (login_other(recovery_codes[0].clone()), StatusCode::OK, None),
+            (login_other(recovery_codes[0].clone()), StatusCode::UNAUTHORIZED, Some("AUTH_003")),
+        ];
+        for (request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/login/mfa", request, None).await;
+            assert_eq!(expected_status, status);
+            match expected_code {
+                Some(code) => assert_eq!(code, body["error"]["code"]),
+                None => {
+                    assert_eq!(jwt(SUB), body["token"]);
+                    assert_eq!(USER_ID, body["user"]["user_id"]);
+                    assert_eq!(StatusCode::OK, refresh(&app, body["refresh_token"].as_str().unwrap()).await.0);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_with_mfa_lockout() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+        let (secret, _) = enable_mfa(&app).await;
+
+        let challenge_token = challenge(&app).await;
+        for _ in 0..LOCKOUT_POLICY.threshold {
+            let request = json!({ "challenge_token": challenge_token, "code": "000000" });
+            let (status, _) = send(&app, "/auth/login/mfa", request, None).await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status);
+        }
+
+        let request = json!({ "challenge_token": challenge_token, "code": totp(&secret, 1) });
+        let (status, body) = send(&app, "/auth/login/mfa", request, None).await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, status);
+        assert_eq!("AUTH_005", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_mfa_enroll_and_disable() {
+        let app = app(FakeAuthUserRepository::default());
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+        let authorization = format!("Bearer {}", crate::auth::testing::access_token());
+
+        let (status, body) = send(&app, "/auth/mfa/activate", json!({ "code": "000000" }), Some(&authorization)).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        assert_eq!("RES_001", body["error"]["code"]);
+
+        let (secret, _) = enable_mfa(&app).await;
+        let (status, body) = send(&app, "/auth/mfa/enroll", Value::Null, Some(&authorization)).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("AUTH_007", body["error"]["code"]);
+
+        let test_cases = vec![
+            (json!({ "code": "000000" }), Some(&authorization), StatusCode::UNAUTHORIZED, Some("AUTH_006")),
+            (json!({ "code": totp(&secret, 1) }), None, StatusCode::UNAUTHORIZED, Some("AUTH_003")),
+            (json!({}), Some(&authorization), StatusCode::BAD_REQUEST, Some("VAL_003")),
+            (json!({ "code": totp(&secret, 1) }), Some(&authorization), StatusCode::OK, None),
+        ];
+        for (request, authorization, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, "/auth/mfa/disable", request, authorization.map(|v| v.as_str())).await;
+            assert_eq!(expected_status, status);
+            if let Some(code) = expected_code {
+                assert_eq!(code, body["error"]["code"]);
+            }
+        }
+
+        let (status, body) = login_with(&app, "Password123").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(jwt(SUB), body["token"]);
+        assert!(body["mfa_required"].is_null());
+    }
+
+    fn oidc_user(email_verified: Value) -> Value {
+        json!({ "sub": "1234567890", "email": "hoge@email.com", "email_verified": email_verified, "name": "Hoge Fuga" })
+    }
+
+    /// 認可URLを取得し、IDプロバイダーからリダイレクトされた認可コードと`state`を返します
+    async fn oidc_authorize(app: &Router, provider: &str) -> (String, String) {
+        let request = Request::get(format!("/auth/oidc/{}/authorize", provider)).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        let http = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+        let response = http.get(body["authorization_url"].as_str().unwrap()).send().await.unwrap();
+        assert_eq!(StatusCode::FOUND, response.status());
+        let location = reqwest::Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+        assert!(location.as_str().starts_with(crate::auth::testing::OIDC_REDIRECT_URI));
+
+        let query: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(body["state"], query["state"]);
+        (query["code"].clone(), query["state"].clone())
+    }
+
+    async fn oidc_login(app: &Router, provider: &str) -> (StatusCode, Value) {
+        let (code, state) = oidc_authorize(app, provider).await;
+        let uri = format!("/auth/oidc/{}/callback", provider);
+        send(app, &uri, json!({ "code": code, "state": state }), None).await
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_creates_user() {
+        let provider = MockOidcProvider::start(oidc_user(json!(true))).await;
+        let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+
+        let (status, body) = oidc_login(&app, "google").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(jwt(SUB), body["token"]);
+        assert_eq!(USER_ID, body["user"]["user_id"]);
+        assert_eq!("hoge@email.com", body["user"]["email"]);
+        assert_eq!("Hoge Fuga", body["user"]["name"]);
+        assert_eq!(StatusCode::OK, refresh(&app, body["refresh_token"].as_str().unwrap()).await.0);
+
+        // 紐付け後はメールアドレスが変わっても同じユーザーでログインします
+        provider.set_user(json!({ "sub": "1234567890", "email": "fuga@email.com", "email_verified": false }));
+        let (status, body) = oidc_login(&app, "google").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(USER_ID, body["user"]["user_id"]);
+        assert_eq!("hoge@email.com", body["user"]["email"]);
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_links_existing_user() {
+        let provider = MockOidcProvider::start(oidc_user(json!("true"))).await;
+        let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+
+        let (status, body) = oidc_login(&app, "apple").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(USER_ID, body["user"]["user_id"]);
+        assert_eq!("hoge", body["user"]["name"]);
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_requires_verified_email() {
+        let test_cases = vec![
+            oidc_user(json!(false)),
+            oidc_user(json!("false")),
+            json!({ "sub": "1234567890", "email": "hoge@email.com" }),
+            json!({ "sub": "1234567890", "email_verified": true }),
+        ];
+
+        for user in test_cases {
+            let provider = MockOidcProvider::start(user.clone()).await;
+            let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+
+            let (status, body) = oidc_login(&app, "google").await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status, "{}", user);
+            assert_eq!("AUTH_001", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oidc_callback_failed() {
+        let provider = MockOidcProvider::start(oidc_user(json!(true))).await;
+        let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+        let callback = |provider: &str| format!("/auth/oidc/{}/callback", provider);
+
+        let request = Request::get("/auth/oidc/unknown/authorize").body(Body::empty()).unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, app.clone().oneshot(request).await.unwrap().status());
+
+        let (code, state) = oidc_authorize(&app, "google").await;
+        let test_cases = vec![
+            (callback("unknown"), json!({ "code": code, "state": state }), StatusCode::NOT_FOUND, "RES_001"),
+            // 別のプロバイダーで発行した`state`は使用できません
+            (callback("apple"), json!({ "code": code, "state": state }), StatusCode::UNAUTHORIZED, "AUTH_003"),
+            (callback("google"), json!({ "code": code, "state": "invalid" }), StatusCode::UNAUTHORIZED, "AUTH_003"),
+            (callback("google"), json!({ "code": "invalid", "state": state }), StatusCode::UNAUTHORIZED, "AUTH_001"),
+            (callback("google"), json!({ "code": code }), StatusCode::BAD_REQUEST, "VAL_003"),
+        ];
+        for (uri, request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app, &uri, request, None).await;
+            assert_eq!(expected_status, status, "{}", uri);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+
+        // 誤った認可コードで交換を試みても、正しい認可コードは使用できます
+        let (status, _) = send(&app, &callback("google"), json!({ "code": code, "state": state }), None).await;
+        assert_eq!(StatusCode::OK, status);
+        // 使用済みの認可コードは再利用できません
+        let (status, body) = send(&app, &callback("google"), json!({ "code": code, "state": state }), None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_001", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_oidc_callback_invalid_id_token() {
+        let test_cases = vec![
+            ("nonce", json!("other")),
+            ("aud", json!("other_client")),
+            ("iss", json!("https://example.com")),
+            ("exp", json!(0)),
+        ];
+
+        for (key, value) in test_cases {
+            let mut user = oidc_user(json!(true));
+            user[key] = value;
+            let provider = MockOidcProvider::start(user).await;
+            let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+
+            let (status, body) = oidc_login(&app, "google").await;
+            assert_eq!(StatusCode::UNAUTHORIZED, status, "{}", key);
+            assert_eq!("AUTH_001", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_with_mfa() {
+        let provider = MockOidcProvider::start(oidc_user(json!(true))).await;
+        let app = app_with_oidc(FakeAuthUserRepository::default(), &provider);
+        send(&app, "/auth/signup", sign_up_body(), None).await;
+        let (secret, _) = enable_mfa(&app).await;
+
+        let (status, body) = oidc_login(&app, "google").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["mfa_required"]);
+        assert!(body["token"].is_null());
+
+        let request = json!({ "challenge_token": body["challenge_token"], "code": totp(&secret, 1) });
+        let (status, body) = send(&app, "/auth/login/mfa", request, None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(USER_ID, body["user"]["user_id"]);
+    }
+
+    #[test]
+    fn test_federated_name() {
+        let email = Email::from_str("hoge@email.com").unwrap();
+        let test_cases = vec![
+            (Some("Hoge Fuga"), "Hoge Fuga"),
+            (Some("  "), "hoge"),
+            (None, "hoge"),
+            (Some("abcdefghijklmnopqrstuvwxyz"), "abcdefghijklmnopqrst"),
+            (Some("あいうえおかきくけこ"), "あいうえおかきくけこ"),
+            (Some("あいうえおかきくけこさしすせそたちつてとなにぬねの"), "あいうえおかきくけこさしすせそたちつてと"),
+        ];
+        for (name, expected) in test_cases {
+            assert_eq!(expected, federated_name(name, &email).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_user_id_from_token() {
+        assert_eq!(USER_ID, user_id_from_token(&token("refresh_token")).unwrap().to_string());
+
+        let test_cases = vec![
+            "".to_string(),
+            "invalid".to_string(),
+            jwt("invalid"),
+            "a.!!!.c".to_string(),
+        ];
+        for value in test_cases {
+            let token = Token::new(value.into(), "refresh_token".to_string().into(), SystemClock.now(), 3600);
+            assert!(matches!(user_id_from_token(&token), Err(ApiError::InvalidToken)));
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use domain::data_export::{DataExport, ExportedItems};
+use serde::Serialize;
+
+use crate::{auth::authenticated_user::AuthenticatedUser, error::ApiError, state::AppState};
+
+#[derive(Debug, Serialize)]
+pub struct ExportedItemsResponse {
+    target: String,
+    count: usize,
+}
+
+/// エクスポートの状態です。`exported`の数と`total`で進捗を表します
+#[derive(Debug, Serialize)]
+pub struct DataExportResponse {
+    export_id: String,
+    status: String,
+    requested_at: i64,
+    total: usize,
+    exported: Vec<ExportedItemsResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataExportListResponse {
+    exports: Vec<DataExportResponse>,
+}
+
+/// トークン本体を表示できるのは発行時のレスポンスのみです
+#[derive(Debug, Serialize)]
+pub struct DownloadTokenResponse {
+    token: String,
+    expires_at: i64,
+}
+
+impl From<&ExportedItems> for ExportedItemsResponse {
+    fn from(value: &ExportedItems) -> Self {
+        Self { target: value.target().to_string(), count: value.count() }
+    }
+}
+
+impl From<&DataExport> for DataExportResponse {
+    fn from(value: &DataExport) -> Self {
+        Self {
+            export_id: value.export_id().to_string(),
+            status: value.status().to_string(),
+            requested_at: value.requested_at(),
+            total: value.total(),
+            exported: value.exported().iter().map(ExportedItemsResponse::from).collect(),
+            completed_at: value.completed_at(),
+        }
+    }
+}
+
+/// POST /auth/account/exports
+///
+/// データのエクスポートを依頼します
+pub async fn request_export(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<(StatusCode, Json<DataExportResponse>), ApiError> {
+    user.session()?;
+    let export = state.data_export_service.request(&user.user_id).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(DataExportResponse::from(&export))))
+}
+
+/// GET /auth/account/exports
+///
+/// エクスポートを依頼日時の新しい順に返します
+pub async fn list_exports(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<Json<DataExportListResponse>, ApiError> {
+    user.session()?;
+    let exports = state.data_export_service.list(&user.user_id).await?;
+
+    Ok(Json(DataExportListResponse { exports: exports.iter().map(DataExportResponse::from).collect() }))
+}
+
+/// GET /auth/account/exports/:export_id
+pub async fn find_export(
+    State(state): State<AppState>, user: AuthenticatedUser, Path(export_id): Path<String>,
+) -> Result<Json<DataExportResponse>, ApiError> {
+    user.session()?;
+    let export = state.data_export_service.find(&user.user_id, &export_id).await?;
+
+    Ok(Json(DataExportResponse::from(&export)))
+}
+
+/// POST /auth/account/exports/:export_id/download-token
+///
+/// 完了したエクスポートのダウンロード用トークンを発行します
+pub async fn issue_download_token(
+    State(state): State<AppState>, user: AuthenticatedUser, Path(export_id): Path<String>,
+) -> Result<(StatusCode, Json<DownloadTokenResponse>), ApiError> {
+    user.session()?;
+    let issued = state.data_export_service.issue_download_token(&user.user_id, &export_id).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(DownloadTokenResponse { token: issued.value.expose_secret().to_string(), expires_at: issued.expires_at }),
+    ))
+}
+
+/// GET /auth/account/exports/download/:token
+///
+/// ZIPファイルを返します
+/// ブラウザのリンクから取得できるよう認証を求めず、期限付きのダウンロード用トークンで検証します
+pub async fn download_export(State(state): State<AppState>, Path(token): Path<String>) -> Result<Response, ApiError> {
+    let file = state.data_export_service.download(&token).await?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip".to_string()),
+            (CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file.file_name)),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        file.content,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, DataExportService,
+        UserService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request},
+        Router,
+    };
+    use domain::{
+        login_attempt::LockoutPolicy,
+        repository::data_export_repository::DataExportRepository,
+        user::{password::PasswordPolicy, user_id::UserId, User},
+        user_data::UserDataItem,
+    };
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_data_repository::InMemoryUserDataRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{access_token, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    struct TestApp {
+        router: Router,
+        service: Arc<dyn DataExportService>,
+        repository: Arc<InMemoryDataExportRepository>,
+        root: std::path::PathBuf,
+    }
+
+    impl Drop for TestApp {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// `SUB`のユーザーがサブスクリプションを2件登録した状態です
+    async fn app() -> TestApp {
+        let user_id = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user_service = UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO);
+        let user = User::build(&user_id.to_string(), "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.create_user(user).await.unwrap();
+        let subscribe_repository = Arc::new(InMemoryUserDataRepository::new("subscribe"));
+        subscribe_repository.insert(&user_id, vec![UserDataItem::new(); 2]);
+
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let repository = Arc::new(InMemoryDataExportRepository::new());
+        let service: Arc<dyn DataExportService> = Arc::new(DataExportServiceImpl::new(
+            repository.clone(),
+            user_repository.clone(),
+            vec![subscribe_repository.clone()],
+            Arc::new(LocalObjectStorage::new(&root)),
+            std::time::Duration::from_secs(60),
+        ));
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                user_repository,
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            service.clone(),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(&root)),
+            )),
+        );
+        let router = crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())));
+        TestApp { router, service, repository, root }
+    }
+
+    async fn send(app: &Router, request: axum::http::request::Builder) -> (StatusCode, Value) {
+        let request = request.header(AUTHORIZATION, format!("Bearer {}", access_token()));
+        let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_export_and_download() {
+        let app = app().await;
+
+        let (status, body) = send(&app.router, Request::post("/auth/account/exports")).await;
+        assert_eq!(StatusCode::ACCEPTED, status);
+        assert_eq!("pending", body["status"]);
+        assert!(body.get("completed_at").is_none());
+        let export_id = body["export_id"].as_str().unwrap().to_string();
+        let uri = format!("/auth/account/exports/{}", export_id);
+
+        let (status, body) = send(&app.router, Request::post("/auth/account/exports")).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("RES_004", body["error"]["code"]);
+
+        let (status, body) = send(&app.router, Request::post(format!("{}/download-token", uri))).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        assert_eq!("RES_005", body["error"]["code"]);
+
+        assert_eq!(1, app.service.run_pending().await.unwrap());
+
+        let (status, body) = send(&app.router, Request::get(&uri)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("completed", body["status"]);
+        assert_eq!(2, body["total"]);
+        let expected = vec![
+            ("profile", 1),
+            ("subscribe", 2),
+        ];
+        let exported: Vec<_> = body["exported"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| (v["target"].as_str().unwrap(), v["count"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(expected, exported);
+
+        let (status, body) = send(&app.router, Request::get("/auth/account/exports")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(export_id, body["exports"][0]["export_id"]);
+
+        let (status, body) = send(&app.router, Request::post(format!("{}/download-token", uri))).await;
+        assert_eq!(StatusCode::CREATED, status);
+        let token = body["token"].as_str().unwrap();
+
+        let request = Request::get(format!("/auth/account/exports/download/{}", token)).body(Body::empty()).unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("application/zip", response.headers()[CONTENT_TYPE]);
+        assert_eq!(
+            format!("attachment; filename=\"subtrack-export-{}.zip\"", export_id),
+            response.headers()[CONTENT_DISPOSITION]
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.starts_with(b"PK"));
+    }
+
+    #[tokio::test]
+    async fn test_download_failed() {
+        let app = app().await;
+        let other = DataExport::request("exp_other".to_string(), UserId::new(), 0);
+        app.repository.save(other.start(1).complete("exports/other.zip".to_string(), 1)).await.unwrap();
+
+        let test_cases = vec![
+            (Request::get("/auth/account/exports/exp_other"), StatusCode::NOT_FOUND, "RES_001"),
+            (Request::post("/auth/account/exports/exp_other/download-token"), StatusCode::NOT_FOUND, "RES_001"),
+            (Request::get("/auth/account/exports/download/exp_other.invalid"), StatusCode::UNAUTHORIZED, "AUTH_003"),
+            (Request::get("/auth/account/exports/download/invalid"), StatusCode::UNAUTHORIZED, "AUTH_003"),
+        ];
+        for (request, expected_status, expected_code) in test_cases {
+            let (status, body) = send(&app.router, request).await;
+            assert_eq!(expected_status, status);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    Json,
+};
+use domain::{
+    personal_access_token::{PersonalAccessToken, Scope},
+    security_event::SecurityEventType,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{authenticated_user::AuthenticatedUser, client_device::ClientDevice},
+    error::ApiError,
+    handler::security_event_handler::{record, security_event},
+    state::AppState,
+};
+
+/// 有効期間を指定しない場合の日数です
+const DEFAULT_EXPIRES_IN_DAYS: u64 = 30;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Deserialize)]
+pub struct IssueTokenRequest {
+    name: String,
+    scopes: Vec<String>,
+    expires_in_days: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    token_id: String,
+    name: String,
+    scopes: Vec<String>,
+    created_at: i64,
+    expires_at: i64,
+}
+
+/// トークン本体を表示できるのは発行時のレスポンスのみです
+#[derive(Debug, Serialize)]
+pub struct IssueTokenResponse {
+    #[serde(flatten)]
+    metadata: TokenResponse,
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenListResponse {
+    tokens: Vec<TokenResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeTokenResponse {
+    message: &'static str,
+}
+
+impl From<&PersonalAccessToken> for TokenResponse {
+    fn from(value: &PersonalAccessToken) -> Self {
+        Self {
+            token_id: value.token_id().to_string(),
+            name: value.name().to_string(),
+            scopes: value.scopes().iter().map(ToString::to_string).collect(),
+            created_at: value.created_at(),
+            expires_at: value.expires_at(),
+        }
+    }
+}
+
+/// POST /auth/tokens
+///
+/// 指定したスコープの個人用アクセストークンを発行します
+/// トークンの発行と管理はアクセストークンでのみ行えます
+pub async fn issue_token(
+    State(state): State<AppState>, user: AuthenticatedUser, payload: Result<Json<IssueTokenRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<IssueTokenResponse>), ApiError> {
+    user.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let scopes = request.scopes.iter().map(|v| Scope::from_str(v)).collect::<Result<Vec<_>, _>>()?;
+    let days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRES_IN_DAYS);
+    let ttl = std::time::Duration::from_secs(days.saturating_mul(SECONDS_PER_DAY));
+
+    let issued = state.personal_access_token_service.issue(&user.user_id, &request.name, scopes, ttl).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(IssueTokenResponse { metadata: TokenResponse::from(&issued.token), token: issued.value }),
+    ))
+}
+
+/// GET /auth/tokens
+///
+/// 有効な個人用アクセストークンを発行日時の昇順で返します
+pub async fn list_tokens(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<Json<TokenListResponse>, ApiError> {
+    user.session()?;
+    let tokens = state.personal_access_token_service.list(&user.user_id).await?;
+
+    Ok(Json(TokenListResponse { tokens: tokens.iter().map(TokenResponse::from).collect() }))
+}
+
+/// DELETE /auth/tokens/:token_id
+///
+/// 個人用アクセストークンを失効させます
+pub async fn revoke_token(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    Path(token_id): Path<String>,
+) -> Result<Json<RevokeTokenResponse>, ApiError> {
+    user.session()?;
+    state.personal_access_token_service.revoke(&user.user_id, &token_id).await?;
+    let event = security_event(&state, SecurityEventType::TokenRevoked, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail("personal_access_token")).await;
+
+    Ok(Json(RevokeTokenResponse { message: "Token revoked successfully" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, UserService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request},
+        Router,
+    };
+    use domain::{login_attempt::LockoutPolicy, user::password::PasswordPolicy, user::User};
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{access_token, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    /// `SUB`のユーザーを登録したアプリケーションです
+    async fn app() -> Router {
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user_service = UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO);
+        let user = User::build(&format!("usr_{}", SUB), "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.create_user(user).await.unwrap();
+
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(90 * SECONDS_PER_DAY),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                user_repository,
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            )),
+        );
+        crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())))
+    }
+
+    async fn send(
+        app: &Router, request: axum::http::request::Builder, token: &str, body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request =
+            request.header(AUTHORIZATION, format!("Bearer {}", token)).header("content-type", "application/json");
+        let body = body.map(|v| Body::from(v.to_string())).unwrap_or_else(Body::empty);
+        let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn issue(app: &Router, body: Value) -> (StatusCode, Value) {
+        send(app, Request::post("/auth/tokens"), &access_token(), Some(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_personal_access_token_flow() {
+        let app = app().await;
+
+        let body = json!({ "name": "report", "scopes": ["users:read", "subscriptions:read", "users:read"] });
+        let (status, issued) = issue(&app, body).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!("report", issued["name"]);
+        let expected = vec![
+            "subscriptions:read",
+            "users:read",
+        ];
+        assert_eq!(json!(expected), issued["scopes"]);
+        let expires_in = issued["expires_at"].as_i64().unwrap() - issued["created_at"].as_i64().unwrap();
+        assert_eq!((DEFAULT_EXPIRES_IN_DAYS * SECONDS_PER_DAY) as i64, expires_in);
+        let token = issued["token"].as_str().unwrap().to_string();
+        assert!(token.starts_with("pat_"));
+
+        let (status, body) = send(&app, Request::get("/auth/tokens"), &access_token(), None).await;
+        assert_eq!(StatusCode::OK, status);
+        let tokens = body["tokens"].as_array().unwrap();
+        assert_eq!(1, tokens.len());
+        assert_eq!(issued["token_id"], tokens[0]["token_id"]);
+        assert!(tokens[0].get("token").is_none());
+
+        let (status, body) = send(&app, Request::get(format!("/users/usr_{}", SUB)), &token, None).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(format!("usr_{}", SUB), body["user_id"]);
+
+        // 個人用アクセストークンではトークンを管理できません
+        let (status, body) = send(&app, Request::get("/auth/tokens"), &token, None).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        assert_eq!("AUTH_008", body["error"]["code"]);
+
+        let uri = format!("/auth/tokens/{}", issued["token_id"].as_str().unwrap());
+        let (status, _) = send(&app, Request::delete(&uri), &access_token(), None).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (status, body) = send(&app, Request::get(format!("/users/usr_{}", SUB)), &token, None).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!("AUTH_003", body["error"]["code"]);
+
+        let (status, body) = send(&app, Request::delete(&uri), &access_token(), None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        assert_eq!("RES_001", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_personal_access_token_without_scope() {
+        let app = app().await;
+        let (_, issued) = issue(&app, json!({ "name": "import", "scopes": ["subscriptions:write"] })).await;
+
+        let token = issued["token"].as_str().unwrap();
+        let (status, body) = send(&app, Request::get(format!("/users/usr_{}", SUB)), token, None).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        assert_eq!("AUTH_008", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_issue_token_failed() {
+        let app = app().await;
+        let test_cases = vec![
+            json!({ "name": "import", "scopes": ["root"] }),
+            json!({ "name": "import", "scopes": [] }),
+            json!({ "name": " ", "scopes": ["users:read"] }),
+            json!({ "name": "import", "scopes": ["users:read"], "expires_in_days": 0 }),
+            json!({ "name": "import", "scopes": ["users:read"], "expires_in_days": 91 }),
+            json!({ "name": "import" }),
+        ];
+        for body in test_cases {
+            let (status, response) = issue(&app, body.clone()).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status, "{}", body);
+            assert_eq!("VAL_003", response["error"]["code"], "{}", body);
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use std::str::FromStr;
+
+use application::services::SecurityEventQuery;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    Json,
+};
+use domain::{
+    refresh_token::Device,
+    security_event::{SecurityEvent, SecurityEventType},
+    user::user_id::UserId,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{
+        authenticated_user::AuthenticatedUser,
+        authorization::{Authorized, ReadSecurityEvents},
+    },
+    error::ApiError,
+    state::AppState,
+};
+
+/// 期間はUNIX時間の秒で指定します
+#[derive(Deserialize)]
+pub struct SecurityEventListParams {
+    event_type: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct AdminSecurityEventListParams {
+    user_id: Option<String>,
+    event_type: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityEventResponse {
+    event_id: String,
+    event_type: String,
+    user_id: Option<String>,
+    email: Option<String>,
+    occurred_at: i64,
+    ip_address: String,
+    user_agent: String,
+    detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityEventListResponse {
+    events: Vec<SecurityEventResponse>,
+}
+
+impl From<&SecurityEvent> for SecurityEventResponse {
+    fn from(value: &SecurityEvent) -> Self {
+        Self {
+            event_id: value.event_id().to_string(),
+            event_type: value.event_type().to_string(),
+            user_id: value.user_id().map(ToString::to_string),
+            email: value.email().map(ToString::to_string),
+            occurred_at: value.occurred_at(),
+            ip_address: value.ip().to_string(),
+            user_agent: value.user_agent().to_string(),
+            detail: value.detail().map(ToString::to_string),
+        }
+    }
+}
+
+fn search_query(
+    user_id: Option<UserId>, event_type: Option<&str>, from: Option<&str>, to: Option<&str>, limit: Option<usize>,
+) -> Result<SecurityEventQuery, ApiError> {
+    let event_type = event_type.map(SecurityEventType::from_str).transpose()?;
+    let from = from.map(parse_time).transpose()?;
+    let to = to.map(parse_time).transpose()?;
+
+    Ok(SecurityEventQuery { user_id, event_type, from, to, limit })
+}
+
+fn parse_time(value: &str) -> Result<i64, ApiError> {
+    i64::from_str(value).map_err(|_| ApiError::InvalidDateFormat)
+}
+
+/// GET /me/security-events
+///
+/// 自分の監査ログを発生日時の新しい順に返します
+/// `event_type`で種類を、`from`と`to`で期間を絞り込めます
+pub async fn list_my_events(
+    State(state): State<AppState>, user: AuthenticatedUser,
+    params: Result<Query<SecurityEventListParams>, QueryRejection>,
+) -> Result<Json<SecurityEventListResponse>, ApiError> {
+    user.session()?;
+    let Query(params) = params.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let query = search_query(
+        Some(user.user_id.clone()),
+        params.event_type.as_deref(),
+        params.from.as_deref(),
+        params.to.as_deref(),
+        params.limit,
+    )?;
+
+    let events = state.security_event_service.search(query).await?;
+    Ok(Json(SecurityEventListResponse { events: events.iter().map(SecurityEventResponse::from).collect() }))
+}
+
+/// GET /admin/security-events
+///
+/// すべてのユーザーの監査ログを検索します。`admin`のロールのみ実行できます
+/// `user_id`と`event_type`の少なくとも一方を指定します。ユーザーを特定できないイベントは`event_type`で検索します
+/// 個人用アクセストークンでは実行できません
+pub async fn list_events(
+    State(state): State<AppState>, user: Authorized<ReadSecurityEvents>,
+    params: Result<Query<AdminSecurityEventListParams>, QueryRejection>,
+) -> Result<Json<SecurityEventListResponse>, ApiError> {
+    user.authenticated.session()?;
+    let Query(params) = params.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let user_id =
+        params.user_id.as_deref().map(UserId::from_str).transpose().map_err(|_| ApiError::RequiredFieldMissing)?;
+    let query = search_query(
+        user_id,
+        params.event_type.as_deref(),
+        params.from.as_deref(),
+        params.to.as_deref(),
+        params.limit,
+    )?;
+
+    let events = state.security_event_service.search(query).await?;
+    Ok(Json(SecurityEventListResponse { events: events.iter().map(SecurityEventResponse::from).collect() }))
+}
+
+/// リクエストした端末で発生したイベントを作成します
+pub(crate) fn security_event(state: &AppState, event_type: SecurityEventType, device: &Device) -> SecurityEvent {
+    SecurityEvent::occurred(
+        uuid::Uuid::new_v4().to_string(),
+        event_type,
+        state.clock.now(),
+        device.ip().to_string(),
+        device.user_agent().to_string(),
+    )
+}
+
+/// 監査ログにイベントを記録します
+///
+/// 記録に失敗しても元の操作は失敗させず、イベントの内容をログに出力します
+pub(crate) async fn record(state: &AppState, event: SecurityEvent) {
+    if let Err(e) = state.security_event_service.record(event.clone()).await {
+        tracing::error!("failed to record security event. event: {:?}, {:?}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, UserService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        Router,
+    };
+    use domain::{
+        login_attempt::LockoutPolicy,
+        repository::security_event_repository::SecurityEventRepository,
+        user::{password::PasswordPolicy, role::Role, User},
+    };
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{access_token, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const OTHER_SUB: &str = "123e4567-e89b-12d3-a456-426614174000";
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    fn event(event_id: &str, event_type: SecurityEventType, occurred_at: i64) -> SecurityEvent {
+        SecurityEvent::occurred(
+            event_id.to_string(),
+            event_type,
+            occurred_at,
+            "192.0.2.1".to_string(),
+            "Mozilla/5.0".to_string(),
+        )
+    }
+
+    /// `SUB`のユーザーを`role`で登録し、`SUB`と`OTHER_SUB`のユーザーと、ユーザーを特定できないイベントを記録したアプリケーションです
+    async fn app(role: Role) -> Router {
+        let user_id = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+        let other_id = UserId::from_str(&format!("usr_{}", OTHER_SUB)).unwrap();
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user_service = UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO);
+        let actor = User::build(&user_id.to_string(), "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.create_user(actor.with_role(role)).await.unwrap();
+
+        let security_event_repository = Arc::new(InMemorySecurityEventRepository::new());
+        let events = vec![
+            event("1", SecurityEventType::SignUp, 100).with_user_id(user_id.clone()),
+            event("2", SecurityEventType::LoginFailed, 200).with_email("hoge@email.com".to_string()),
+            event("3", SecurityEventType::LoginSucceeded, 300).with_user_id(user_id.clone()).with_detail("password"),
+            event("4", SecurityEventType::LoginFailed, 400).with_user_id(other_id.clone()).with_detail("mfa"),
+            event("5", SecurityEventType::MfaEnabled, 500).with_user_id(user_id),
+        ];
+        for event in events {
+            security_event_repository.save(event).await.unwrap();
+        }
+
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                user_repository,
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(security_event_repository)),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            )),
+        )
+        .with_trusted_proxy_hops(1);
+        crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())))
+    }
+
+    async fn send(app: &Router, request: axum::http::request::Builder) -> (StatusCode, Value) {
+        let request = request
+            .header(AUTHORIZATION, format!("Bearer {}", access_token()))
+            .header("user-agent", "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0")
+            .header("x-forwarded-for", "198.51.100.7");
+        let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn ids(body: &Value) -> String {
+        let events = body["events"].as_array().unwrap();
+        events.iter().map(|v| v["event_id"].as_str().unwrap()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[tokio::test]
+    async fn test_list_my_events() {
+        let app = app(Role::User).await;
+
+        let test_cases = vec![
+            ("/me/security-events", "5 3 1"),
+            ("/me/security-events?event_type=login_succeeded", "3"),
+            ("/me/security-events?event_type=login_failed", ""),
+            ("/me/security-events?from=100&to=500", "3 1"),
+            ("/me/security-events?limit=1", "5"),
+            // `user_id`を指定しても自分のイベントのみ返します
+            ("/me/security-events?user_id=usr_123e4567-e89b-12d3-a456-426614174000", "5 3 1"),
+        ];
+        for (uri, expected) in test_cases {
+            let (status, body) = send(&app, Request::get(uri)).await;
+            assert_eq!(StatusCode::OK, status, "{}", uri);
+            assert_eq!(expected, ids(&body), "{}", uri);
+        }
+
+        let (_, body) = send(&app, Request::get("/me/security-events?event_type=login_succeeded")).await;
+        let event = &body["events"][0];
+        assert_eq!("login_succeeded", event["event_type"]);
+        assert_eq!(format!("usr_{}", SUB), event["user_id"]);
+        assert_eq!(300, event["occurred_at"]);
+        assert_eq!("192.0.2.1", event["ip_address"]);
+        assert_eq!("Mozilla/5.0", event["user_agent"]);
+        assert_eq!("password", event["detail"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_my_events_failed() {
+        let app = app(Role::User).await;
+
+        let test_cases = vec![
+            ("/me/security-events?event_type=login", "VAL_003"),
+            ("/me/security-events?limit=many", "VAL_003"),
+            ("/me/security-events?from=2024-01-01", "VAL_004"),
+            ("/me/security-events?from=500&to=100", "VAL_004"),
+            ("/me/security-events?from=-1", "VAL_004"),
+        ];
+        for (uri, expected) in test_cases {
+            let (status, body) = send(&app, Request::get(uri)).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status, "{}", uri);
+            assert_eq!(expected, body["error"]["code"], "{}", uri);
+        }
+
+        let request = Request::get("/me/security-events");
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_list_events() {
+        let app = app(Role::Admin).await;
+
+        let test_cases = vec![
+            ("/admin/security-events?event_type=login_failed", "4 2"),
+            ("/admin/security-events?event_type=login_failed&from=100&to=300", "2"),
+            ("/admin/security-events?user_id=usr_123e4567-e89b-12d3-a456-426614174000", "4"),
+            ("/admin/security-events?user_id=usr_550e8400-e29b-41d4-a716-446655440000&event_type=sign_up", "1"),
+        ];
+        for (uri, expected) in test_cases {
+            let (status, body) = send(&app, Request::get(uri)).await;
+            assert_eq!(StatusCode::OK, status, "{}", uri);
+            assert_eq!(expected, ids(&body), "{}", uri);
+        }
+
+        let (_, body) =
+            send(&app, Request::get("/admin/security-events?event_type=login_failed&from=200&to=300")).await;
+        assert_eq!(Value::Null, body["events"][0]["user_id"]);
+        assert_eq!("hoge@email.com", body["events"][0]["email"]);
+
+        let test_cases = vec![
+            ("/admin/security-events", "VAL_003"),
+            ("/admin/security-events?user_id=invalid", "VAL_003"),
+            ("/admin/security-events?event_type=login_failed&to=now", "VAL_004"),
+        ];
+        for (uri, expected) in test_cases {
+            let (status, body) = send(&app, Request::get(uri)).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status, "{}", uri);
+            assert_eq!(expected, body["error"]["code"], "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_events_forbidden() {
+        let test_cases = vec![
+            Role::User,
+            Role::Support,
+        ];
+        for role in test_cases {
+            let app = app(role).await;
+            let (status, body) = send(&app, Request::get("/admin/security-events?event_type=login_failed")).await;
+            assert_eq!(StatusCode::FORBIDDEN, status, "{}", role);
+            assert_eq!("AUTH_008", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_with_client_device() {
+        let app = app(Role::User).await;
+
+        let (status, _) = send(&app, Request::delete("/auth/sessions")).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let (_, body) = send(&app, Request::get("/me/security-events?event_type=token_revoked")).await;
+        let events = body["events"].as_array().unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!("all_sessions", events[0]["detail"]);
+        assert_eq!("198.51.100.7", events[0]["ip_address"]);
+        assert_eq!("Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0", events[0]["user_agent"]);
+    }
+}
@@ -0,0 +1,315 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use domain::{refresh_token::RefreshTokenFamily, security_event::SecurityEventType};
+use serde::Serialize;
+
+use crate::{
+    auth::{authenticated_user::AuthenticatedUser, client_device::ClientDevice},
+    error::ApiError,
+    handler::security_event_handler::{record, security_event},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    session_id: String,
+    device_label: String,
+    user_agent: String,
+    ip_address: String,
+    created_at: i64,
+    last_seen_at: i64,
+    expires_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionResponse {
+    message: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeAllSessionsResponse {
+    message: &'static str,
+    revoked: usize,
+}
+
+impl From<&RefreshTokenFamily> for SessionResponse {
+    fn from(value: &RefreshTokenFamily) -> Self {
+        Self {
+            session_id: value.family_id().to_string(),
+            device_label: value.device().label().to_string(),
+            user_agent: value.device().user_agent().to_string(),
+            ip_address: value.device().ip().to_string(),
+            created_at: value.created_at(),
+            last_seen_at: value.last_seen_at(),
+            expires_at: value.expires_at(),
+        }
+    }
+}
+
+/// GET /auth/sessions
+///
+/// ログイン中のセッションを最終利用日時の新しい順に返します
+pub async fn list_sessions(
+    State(state): State<AppState>, user: AuthenticatedUser,
+) -> Result<Json<SessionListResponse>, ApiError> {
+    user.session()?;
+    let sessions = state.refresh_token_service.list_sessions(&user.user_id).await?;
+
+    Ok(Json(SessionListResponse { sessions: sessions.iter().map(SessionResponse::from).collect() }))
+}
+
+/// DELETE /auth/sessions/:session_id
+///
+/// セッションのリフレッシュトークンを無効化し、その端末をログアウトさせます
+pub async fn revoke_session(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+    Path(session_id): Path<String>,
+) -> Result<Json<RevokeSessionResponse>, ApiError> {
+    user.session()?;
+    state.refresh_token_service.revoke_session(&user.user_id, &session_id).await?;
+    let event = security_event(&state, SecurityEventType::TokenRevoked, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail("session")).await;
+
+    Ok(Json(RevokeSessionResponse { message: "Session revoked successfully" }))
+}
+
+/// DELETE /auth/sessions
+///
+/// すべての端末のリフレッシュトークンを無効化します
+/// 発行済みのアクセストークンは有効期限まで使用できます
+pub async fn revoke_all_sessions(
+    State(state): State<AppState>, ClientDevice(device): ClientDevice, user: AuthenticatedUser,
+) -> Result<Json<RevokeAllSessionsResponse>, ApiError> {
+    user.session()?;
+    let revoked = state.refresh_token_service.revoke_all_sessions(&user.user_id).await?;
+    let event = security_event(&state, SecurityEventType::TokenRevoked, &device).with_user_id(user.user_id.clone());
+    record(&state, event.with_detail("all_sessions")).await;
+
+    Ok(Json(RevokeAllSessionsResponse { message: "Signed out of all sessions", revoked }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, RefreshTokenService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        Router,
+    };
+    use domain::{
+        login_attempt::LockoutPolicy,
+        refresh_token::Device,
+        user::{password::PasswordPolicy, user_id::UserId},
+    };
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{access_token, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    fn app(refresh_token_service: Arc<dyn RefreshTokenService>) -> Router {
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO)),
+            refresh_token_service,
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                user_repository,
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(
+                Arc::new(InMemoryUserRepository::new()),
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+            )),
+        );
+        crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())))
+    }
+
+    /// `SUB`のユーザーが2台の端末で、他のユーザーが1台の端末でログインした状態です
+    async fn app_with_sessions() -> (Router, Arc<dyn RefreshTokenService>, Vec<String>) {
+        let service: Arc<dyn RefreshTokenService> = Arc::new(RefreshTokenServiceImpl::new(
+            Arc::new(InMemoryRefreshTokenRepository::new()),
+            std::time::Duration::from_secs(60 * 60),
+        ));
+        let user_id = UserId::from_str(&format!("usr_{}", SUB)).unwrap();
+        let device = |label: &str| Device::new(label.to_string(), "Mozilla/5.0".to_string(), "192.0.2.1".to_string());
+
+        let tokens = vec![
+            service.issue(&user_id, "upstream", device("Chrome on macOS")).await.unwrap(),
+            service.issue(&user_id, "upstream", device("Safari on iOS")).await.unwrap(),
+            service.issue(&UserId::new(), "upstream", device("Firefox on Linux")).await.unwrap(),
+        ];
+        (app(service.clone()), service, tokens)
+    }
+
+    async fn send(app: &Router, request: axum::http::request::Builder) -> (StatusCode, Value) {
+        let request = request.header(AUTHORIZATION, format!("Bearer {}", access_token()));
+        let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn session_id(token: &str) -> &str {
+        token.split_once('.').unwrap().0
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let (app, _, tokens) = app_with_sessions().await;
+
+        let (status, body) = send(&app, Request::get("/auth/sessions")).await;
+        assert_eq!(StatusCode::OK, status);
+        let sessions = body["sessions"].as_array().unwrap();
+        assert_eq!(2, sessions.len());
+        let mut labels: Vec<_> = sessions.iter().map(|v| v["device_label"].as_str().unwrap()).collect();
+        labels.sort();
+        let expected = vec![
+            "Chrome on macOS",
+            "Safari on iOS",
+        ];
+        assert_eq!(expected, labels);
+        assert!(sessions.iter().all(|v| v["session_id"] != session_id(&tokens[2])));
+        assert_eq!("192.0.2.1", sessions[0]["ip_address"]);
+        assert_eq!("Mozilla/5.0", sessions[0]["user_agent"]);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_session() {
+        let (app, service, tokens) = app_with_sessions().await;
+
+        let uri = format!("/auth/sessions/{}", session_id(&tokens[0]));
+        let (status, _) = send(&app, Request::delete(&uri)).await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(service.rotate(&tokens[0], "192.0.2.1", "upstream").await.is_err());
+        assert!(service.rotate(&tokens[1], "192.0.2.1", "upstream").await.is_ok());
+
+        let test_cases = vec![
+            uri,
+            format!("/auth/sessions/{}", session_id(&tokens[2])),
+            "/auth/sessions/unknown".to_string(),
+        ];
+        for uri in test_cases {
+            let (status, body) = send(&app, Request::delete(&uri)).await;
+            assert_eq!(StatusCode::NOT_FOUND, status, "{}", uri);
+            assert_eq!("RES_001", body["error"]["code"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let (app, service, tokens) = app_with_sessions().await;
+
+        let (status, body) = send(&app, Request::delete("/auth/sessions")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(2, body["revoked"]);
+        assert!(service.rotate(&tokens[0], "192.0.2.1", "upstream").await.is_err());
+        assert!(service.rotate(&tokens[1], "192.0.2.1", "upstream").await.is_err());
+        assert!(service.rotate(&tokens[2], "192.0.2.1", "upstream").await.is_ok());
+
+        let (_, body) = send(&app, Request::get("/auth/sessions")).await;
+        assert!(body["sessions"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+use std::str::FromStr;
+
+use axum::{
+    body::Bytes,
+    extract::{
+        rejection::{BytesRejection, JsonRejection},
+        Path, State,
+    },
+    http::StatusCode,
+    Json,
+};
+use domain::{
+    personal_access_token::Scope,
+    user::{
+        role::{Access, Role},
+        user_id::UserId,
+        User,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{
+        authenticated_user::AuthenticatedUser,
+        authorization::{Authorized, ManageRoles, ReadOwnResources},
+    },
+    error::ApiError,
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    user_id: String,
+    email: String,
+    name: String,
+    role: String,
+    /// アイコンがない場合は出力しません
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile_icon_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeRoleRequest {
+    role: String,
+}
+
+impl From<&User> for UserResponse {
+    fn from(value: &User) -> Self {
+        Self {
+            user_id: value.user_id().to_string(),
+            email: value.email().to_string(),
+            name: value.name().to_string(),
+            role: value.role().to_string(),
+            profile_icon_path: value.profile_icon_path().clone(),
+        }
+    }
+}
+
+/// パスの`user_id`が不正な場合は存在しないユーザーとして扱います
+fn parse_user_id(user_id: &str) -> Result<UserId, ApiError> {
+    UserId::from_str(user_id).map_err(|_| ApiError::NotFound)
+}
+
+/// GET /users/:user_id
+///
+/// 自分のユーザー情報を返します
+/// `support`と`admin`のロールは他のユーザーの情報も参照できます
+/// 個人用アクセストークンの場合は`users:read`のスコープが必要です
+pub async fn find_user(
+    State(state): State<AppState>, user: Authorized<ReadOwnResources>, Path(user_id): Path<String>,
+) -> Result<Json<UserResponse>, ApiError> {
+    user.authenticated.ensure_scope(Scope::UsersRead)?;
+    let user_id = parse_user_id(&user_id)?;
+    user.ensure_access(&user_id, Access::Read)?;
+
+    let target = state.user_service.find_by_id(&user_id).await?;
+    Ok(Json(UserResponse::from(&target)))
+}
+
+/// PUT /users/:user_id/role
+///
+/// ユーザーのロールを変更します。`admin`のロールのみ実行できます
+/// 管理者がいなくなることを防ぐため、自分のロールは変更できません
+/// 個人用アクセストークンでは実行できません
+pub async fn change_role(
+    State(state): State<AppState>, user: Authorized<ManageRoles>, Path(user_id): Path<String>,
+    payload: Result<Json<ChangeRoleRequest>, JsonRejection>,
+) -> Result<Json<UserResponse>, ApiError> {
+    user.authenticated.session()?;
+    let Json(request) = payload.map_err(|_| ApiError::RequiredFieldMissing)?;
+    let role = Role::from_str(&request.role).map_err(|_| ApiError::RequiredFieldMissing)?;
+    let user_id = parse_user_id(&user_id)?;
+    if &user_id == user.user_id() {
+        tracing::warn!("admin cannot change own role. user_id: {}", user_id);
+        return Err(ApiError::Forbidden);
+    }
+
+    let target = state.user_service.change_role(&user_id, role).await?;
+    tracing::info!("role changed. user_id: {}, role: {}, changed_by: {}", user_id, role, user.user_id());
+    Ok(Json(UserResponse::from(&target)))
+}
+
+/// PUT /users/me/icon
+///
+/// リクエストボディの画像をプロフィールアイコンとして保存し、更新したユーザー情報を返します
+/// 形式は`Content-Type`ではなく内容から判定します。PNG、JPEG、WebPに対応します
+/// 個人用アクセストークンでは実行できません
+pub async fn upload_icon(
+    State(state): State<AppState>, user: AuthenticatedUser, body: Result<Bytes, BytesRejection>,
+) -> Result<Json<UserResponse>, ApiError> {
+    user.session()?;
+    let body = body.map_err(|e| match e.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => ApiError::ImageTooLarge,
+        _ => ApiError::RequiredFieldMissing,
+    })?;
+    if body.is_empty() {
+        return Err(ApiError::RequiredFieldMissing);
+    }
+
+    let target = state.profile_icon_service.upload(&user.user_id, body.to_vec()).await?;
+    Ok(Json(UserResponse::from(&target)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use application::services::{
+        account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+        login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl,
+        password_service::PasswordServiceImpl, personal_access_token_service::PersonalAccessTokenServiceImpl,
+        profile_icon_service::ProfileIconServiceImpl, refresh_token_service::RefreshTokenServiceImpl,
+        security_event_service::SecurityEventServiceImpl, user_service::UserServiceImpl, UserService,
+    };
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+        Router,
+    };
+    use base64::Engine;
+    use domain::{login_attempt::LockoutPolicy, profile_icon::ProfileIcon, user::password::PasswordPolicy};
+    use infrastructure::{
+        code_sender::LogCodeSender,
+        repository_impl::{
+            in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+            in_memory_credential_repository::InMemoryCredentialRepository,
+            in_memory_data_export_repository::InMemoryDataExportRepository,
+            in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+            in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+            in_memory_mfa_repository::InMemoryMfaRepository,
+            in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+            in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+            in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+            in_memory_security_event_repository::InMemorySecurityEventRepository,
+            in_memory_user_repository::InMemoryUserRepository,
+            local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+            local_breached_password_repository::LocalBreachedPasswordRepository,
+            local_object_storage::LocalObjectStorage,
+        },
+    };
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        auth::{
+            oidc::OidcProviders,
+            testing::{claims, sign, AUDIENCE, ISSUER, SUB},
+        },
+        rate_limit::RateLimiter,
+        RateLimitSettings,
+    };
+
+    const OTHER_SUB: &str = "123e4567-e89b-12d3-a456-426614174000";
+    const LOCKOUT_POLICY: LockoutPolicy =
+        LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 };
+
+    /// `SUB`のユーザーを`role`で、`OTHER_SUB`のユーザーを`user`のロールで登録したアプリケーションです
+    async fn app(role: Role) -> Router {
+        app_with_storage(role, std::env::temp_dir()).await
+    }
+
+    /// プロフィールアイコンを`root`のディレクトリに保存するアプリケーションです
+    async fn app_with_storage(role: Role, root: std::path::PathBuf) -> Router {
+        let user_repository = Arc::new(InMemoryUserRepository::new());
+        let user_service = UserServiceImpl::new(user_repository.clone(), std::time::Duration::ZERO);
+        let actor = User::build(&format!("usr_{}", SUB), "hoge@email.com", "hoge", 1, None).unwrap();
+        user_service.create_user(actor.with_role(role)).await.unwrap();
+        let other = User::build(&format!("usr_{}", OTHER_SUB), "fuga@email.com", "fuga", 1, None).unwrap();
+        user_service.create_user(other).await.unwrap();
+
+        let token_issuer = LocalTokenIssuer::from_pem(
+            include_bytes!("../../testdata/jwt/private_key.pem"),
+            include_bytes!("../../testdata/jwt/public_key.pem"),
+            "test-key".to_string(),
+            ISSUER.to_string(),
+            AUDIENCE.to_string(),
+        )
+        .unwrap();
+        let auth_user_repository = Arc::new(LocalAuthUserRepository::new(
+            Arc::new(InMemoryCredentialRepository::new()),
+            Arc::new(LogCodeSender),
+            token_issuer,
+        ));
+        let state = AppState::new(
+            auth_user_repository.clone(),
+            Arc::new(user_service),
+            Arc::new(RefreshTokenServiceImpl::new(
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(crate::auth::testing::validator()),
+            Arc::new(InMemoryRevokedTokenRepository::new()),
+            Arc::new(LoginAttemptServiceImpl::new(
+                Arc::new(InMemoryLoginAttemptRepository::new()),
+                LOCKOUT_POLICY,
+                LOCKOUT_POLICY,
+            )),
+            Arc::new(MfaServiceImpl::new(
+                Arc::new(InMemoryMfaRepository::new()),
+                "SubTrack".to_string(),
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(300),
+            )),
+            Arc::new(OidcProviders::new(
+                vec![],
+                b"0123456789abcdef0123456789abcdef",
+                std::time::Duration::from_secs(600),
+            )),
+            Arc::new(InMemoryExternalIdentityRepository::new()),
+            Arc::new(PersonalAccessTokenServiceImpl::new(
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(PasswordServiceImpl::new(
+                PasswordPolicy::default(),
+                Arc::new(LocalBreachedPasswordRepository::new()),
+            )),
+            Arc::new(AccountDeletionServiceImpl::new(
+                Arc::new(InMemoryAccountDeletionRepository::new()),
+                auth_user_repository,
+                Arc::new(InMemoryRefreshTokenRepository::new()),
+                Arc::new(InMemoryPersonalAccessTokenRepository::new()),
+                Arc::new(InMemoryMfaRepository::new()),
+                Arc::new(InMemoryExternalIdentityRepository::new()),
+                Arc::new(InMemoryDataExportRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                user_repository.clone(),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(DataExportServiceImpl::new(
+                Arc::new(InMemoryDataExportRepository::new()),
+                Arc::new(InMemoryUserRepository::new()),
+                vec![],
+                Arc::new(LocalObjectStorage::new(std::env::temp_dir())),
+                std::time::Duration::from_secs(60 * 60),
+            )),
+            Arc::new(SecurityEventServiceImpl::new(Arc::new(InMemorySecurityEventRepository::new()))),
+            Arc::new(ProfileIconServiceImpl::new(user_repository, Arc::new(LocalObjectStorage::new(root)))),
+        );
+        crate::router::router(state, Arc::new(RateLimiter::new(RateLimitSettings::default())))
+    }
+
+    async fn send(app: &Router, request: axum::http::request::Builder, body: Option<Value>) -> (StatusCode, Value) {
+        let request = request
+            .header(AUTHORIZATION, format!("Bearer {}", sign(&claims(3600))))
+            .header("content-type", "application/json");
+        let body = body.map(|v| Body::from(v.to_string())).unwrap_or_else(Body::empty);
+        let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn find_user(app: &Router, sub: &str) -> (StatusCode, Value) {
+        send(app, Request::get(format!("/users/usr_{}", sub)), None).await
+    }
+
+    async fn change_role(app: &Router, sub: &str, role: &str) -> (StatusCode, Value) {
+        send(app, Request::put(format!("/users/usr_{}/role", sub)), Some(json!({ "role": role }))).await
+    }
+
+    #[tokio::test]
+    async fn test_find_user() {
+        let test_cases = vec![
+            (Role::User, SUB, StatusCode::OK),
+            (Role::User, OTHER_SUB, StatusCode::FORBIDDEN),
+            (Role::Support, OTHER_SUB, StatusCode::OK),
+            (Role::Admin, OTHER_SUB, StatusCode::OK),
+        ];
+        for (role, sub, expected) in test_cases {
+            let (status, body) = find_user(&app(role).await, sub).await;
+            assert_eq!(expected, status, "{:?} {}", role, sub);
+            if status == StatusCode::OK {
+                assert_eq!(format!("usr_{}", sub), body["user_id"]);
+            } else {
+                assert_eq!("AUTH_008", body["error"]["code"]);
+            }
+        }
+
+        let app = app(Role::User).await;
+        let (status, body) = send(&app, Request::get("/users/not-user-id"), None).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        assert_eq!("RES_001", body["error"]["code"]);
+    }
+
+    #[tokio::test]
+    async fn test_change_role() {
+        let app = app(Role::Admin).await;
+        let (status, body) = change_role(&app, OTHER_SUB, "support").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("support", body["role"]);
+        assert_eq!("support", find_user(&app, OTHER_SUB).await.1["role"]);
+    }
+
+    #[tokio::test]
+    async fn test_change_role_failed() {
+        let test_cases = vec![
+            (Role::User, OTHER_SUB, "admin", StatusCode::FORBIDDEN, "AUTH_008"),
+            (Role::Support, OTHER_SUB, "admin", StatusCode::FORBIDDEN, "AUTH_008"),
+            (Role::Admin, SUB, "user", StatusCode::FORBIDDEN, "AUTH_008"),
+            (Role::Admin, OTHER_SUB, "root", StatusCode::BAD_REQUEST, "VAL_003"),
+            (Role::Admin, "00000000-0000-0000-0000-000000000000", "support", StatusCode::NOT_FOUND, "RES_001"),
+        ];
+        for (role, sub, value, expected_status, expected_code) in test_cases {
+            let (status, body) = change_role(&app(role).await, sub, value).await;
+            assert_eq!(expected_status, status, "{:?} {} {}", role, sub, value);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+    }
+
+    /// 1x1の透過PNGです
+    const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    async fn upload_icon(app: &Router, body: Vec<u8>) -> (StatusCode, Value) {
+        let request = Request::put("/users/me/icon")
+            .header(AUTHORIZATION, format!("Bearer {}", sign(&claims(3600))))
+            .header("content-type", "image/png");
+        let response = app.clone().oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_upload_icon() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let app = app_with_storage(Role::User, root.clone()).await;
+        assert!(find_user(&app, SUB).await.1.get("profile_icon_path").is_none());
+
+        let png = base64::engine::general_purpose::STANDARD.decode(PNG).unwrap();
+        let (status, body) = upload_icon(&app, png).await;
+        assert_eq!(StatusCode::OK, status);
+        let path = body["profile_icon_path"].as_str().unwrap().to_string();
+        assert!(path.starts_with(&format!("icons/usr_{}/", SUB)), "{}", path);
+        assert!(root.join(&path).exists());
+        assert_eq!(path, find_user(&app, SUB).await.1["profile_icon_path"]);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_icon_failed() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let app = app_with_storage(Role::User, root.clone()).await;
+        let test_cases = vec![
+            (vec![], StatusCode::BAD_REQUEST, "VAL_003"),
+            (b"GIF89a".to_vec(), StatusCode::BAD_REQUEST, "VAL_005"),
+            (b"\x89PNG\r\n\x1a\n".to_vec(), StatusCode::BAD_REQUEST, "VAL_005"),
+            (vec![0; ProfileIcon::MAX_BYTES + 1], StatusCode::PAYLOAD_TOO_LARGE, "VAL_006"),
+        ];
+        for (body, expected_status, expected_code) in test_cases {
+            let (status, body) = upload_icon(&app, body).await;
+            assert_eq!(expected_status, status);
+            assert_eq!(expected_code, body["error"]["code"]);
+        }
+        assert!(!root.exists());
+    }
+}
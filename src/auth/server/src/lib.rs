@@ -1,15 +1,201 @@
+pub mod auth;
+pub mod error;
+pub mod handler;
+pub mod rate_limit;
+pub mod redact;
+pub mod router;
+pub mod state;
+
+use domain::{
+    login_attempt::LockoutPolicy,
+    user::password::{CharacterClass, PasswordPolicy},
+};
+use rate_limit::{Limit, RouteLimits};
+use redact::RedactSecrets;
 use thiserror::Error;
-use tracing_subscriber::{filter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+use tracing_subscriber::{
+    filter,
+    fmt::format::{format, JsonFields},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
 
 #[derive(Debug)]
 pub struct ApiSettings {
     pub host: String,
     pub port: String,
+    /// ロードバランサーなど、`X-Forwarded-For`に接続元を追加する信頼できるプロキシの段数です
+    pub trusted_proxy_hops: usize,
 }
 
 #[derive(Debug)]
 pub struct AwsSettings {
-    auth: String,
+    pub auth: String,
+    /// 未設定の場合、`allow_in_memory_stores`が有効な時のみリフレッシュトークンをインメモリで管理します
+    pub refresh_token: Option<String>,
+    /// 未設定の場合、`allow_in_memory_stores`が有効な時のみ無効化したアクセストークンをインメモリで管理します
+    pub revoked_token: Option<String>,
+    /// 未設定の場合、`allow_in_memory_stores`が有効な時のみログインの失敗をインメモリで管理します
+    pub login_attempt: Option<String>,
+    /// 未設定の場合、`allow_in_memory_stores`が有効な時のみ多要素認証の設定をインメモリで管理します
+    pub mfa: Option<String>,
+    /// 未設定の場合、IDプロバイダーのアカウントとの紐付けはインメモリで管理します
+    pub external_identity: Option<String>,
+    /// 未設定の場合、`allow_in_memory_stores`が有効な時のみ個人用アクセストークンをインメモリで管理します
+    pub personal_access_token: Option<String>,
+    /// 未設定の場合、アカウントの削除の依頼はインメモリで管理します
+    pub account_deletion: Option<String>,
+    /// 未設定の場合、データのエクスポートはインメモリで管理します
+    pub data_export: Option<String>,
+    /// 未設定の場合、監査ログはインメモリで管理します
+    pub security_event: Option<String>,
+    /// アカウントの削除で消去し、データのエクスポートに含めるサブスクリプションのテーブルです。未設定の場合は扱いません
+    pub subscribe: Option<String>,
+    /// アカウントの削除で消去し、データのエクスポートに含めるカテゴリーのテーブルです。未設定の場合は扱いません
+    pub category: Option<String>,
+    /// アカウントの削除で消去し、データのエクスポートに含める支払い方法のテーブルです。未設定の場合は扱いません
+    pub payment_method: Option<String>,
+    /// アカウントの削除で消去し、データのエクスポートに含める支払い履歴のテーブルです。未設定の場合は扱いません
+    pub payment_history: Option<String>,
+    /// アカウントの削除で消去し、データのエクスポートに含める通知設定のテーブルです。未設定の場合は扱いません
+    pub notification_setting: Option<String>,
+    /// トークンと多要素認証のテーブルが未設定の場合に、インメモリで管理して起動することを許可します
+    ///
+    /// 再起動やインスタンス間で無効化などの状態が失われるため、開発環境でのみ有効にします
+    pub allow_in_memory_stores: bool,
+}
+
+#[derive(Debug)]
+pub struct CognitoSettings {
+    pub client_id: String,
+    pub client_secret: String,
+    /// アカウントの削除とメールアドレスの変更に使います
+    pub user_pool_id: String,
+    /// パスワードを使わずにトークンを発行するカスタム認証フローで、チャレンジに回答する値です
+    ///
+    /// ソーシャルログインを使う場合は必須です
+    pub custom_auth_secret: Option<String>,
+}
+
+/// ユーザー認証に使う基盤です
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthBackend {
+    Cognito,
+    /// Cognitoに接続できないローカル環境とCI用の自前の認証です
+    Local,
+}
+
+#[derive(Debug)]
+pub struct LocalAuthSettings {
+    /// 未設定の場合、認証情報はインメモリで管理します
+    pub credential_table: Option<String>,
+    pub private_key_file: String,
+    pub public_key_file: String,
+    /// `JWKS_FILE`の`kid`と一致させます
+    pub key_id: String,
+    /// 確認コードをログに出力することを許可します。開発環境でのみ`true`にします
+    pub log_codes: bool,
+}
+
+/// ログイン失敗によるロックの設定です
+///
+/// 未設定の項目は既定値を使用します
+#[derive(Debug, PartialEq, Eq)]
+pub struct LoginAttemptSettings {
+    pub email_policy: LockoutPolicy,
+    pub ip_policy: LockoutPolicy,
+}
+
+/// パスワードポリシーと漏洩パスワードのリストの設定です
+///
+/// 未設定の項目は`PasswordPolicy::default`の値を使用します
+#[derive(Debug, PartialEq, Eq)]
+pub struct PasswordSettings {
+    pub policy: PasswordPolicy,
+    /// `SHA1の16進数[:出現回数]`を1行ずつ記述したファイルです。未設定の場合は照合しません
+    pub breached_password_file: Option<String>,
+}
+
+/// ゲストユーザーの設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct GuestSettings {
+    /// 登録されなかったゲストユーザーを削除するまでの期間です
+    pub ttl: std::time::Duration,
+}
+
+/// アカウントの削除の設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccountDeletionSettings {
+    /// 依頼してから削除を開始するまでの、キャンセルできる期間です
+    pub grace_period: std::time::Duration,
+    /// 猶予期間が過ぎた依頼を確認する間隔です
+    pub job_interval: std::time::Duration,
+}
+
+/// データのエクスポートの設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct DataExportSettings {
+    /// ダウンロード用トークンの有効期間です
+    pub download_ttl: std::time::Duration,
+    /// 依頼されたエクスポートを確認する間隔です
+    pub job_interval: std::time::Duration,
+}
+
+/// プロフィールアイコンとエクスポートしたファイルの保存先の設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct StorageSettings {
+    /// 未設定の場合、`dir`のローカルのディレクトリに保存します
+    pub bucket: Option<String>,
+    pub dir: String,
+}
+
+/// 多要素認証の設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct MfaSettings {
+    /// 認証アプリに表示されるサービス名です
+    pub issuer: String,
+    /// シークレットとチャレンジトークンの暗号化に使う鍵です。Base64で32バイトを指定します
+    pub encryption_key: Option<Vec<u8>>,
+}
+
+/// OpenID ConnectのIDプロバイダーの設定です
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcProviderSettings {
+    /// `/auth/oidc/:provider`の`:provider`に使う名前です
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    /// 公開クライアントとして登録した場合は未設定にします
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    /// Appleのように`form_post`を求めるプロバイダーで指定します
+    pub response_mode: Option<String>,
+}
+
+/// ソーシャルログインの設定です
+#[derive(Debug, PartialEq, Eq)]
+pub struct OidcSettings {
+    pub providers: Vec<OidcProviderSettings>,
+    /// 認可リクエストの`state`の暗号化に使う鍵です。Base64で32バイトを指定します
+    pub state_key: Option<Vec<u8>>,
+}
+
+/// ルートグループごとのレートリミットです
+///
+/// 未設定の項目は`Default`の値を使用します
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitSettings {
+    pub auth: RouteLimits,
+    pub api: RouteLimits,
+}
+
+#[derive(Debug)]
+pub struct JwtSettings {
+    pub jwks_url: Option<String>,
+    pub jwks_file: Option<String>,
+    pub issuer: String,
+    pub audience: String,
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -27,8 +213,9 @@ impl ApiSettings {
             .map_err(|_| SettingsError::InvalidLoadConfig("HOST".to_string()))?;
         let port = std::env::var("PORT")
             .map_err(|_| SettingsError::InvalidLoadConfig("PORT".to_string()))?;
+        let trusted_proxy_hops = env_or("TRUSTED_PROXY_HOPS", 0)?;
 
-        Ok(Self { host, port })
+        Ok(Self { host, port, trusted_proxy_hops })
     }
 }
 
@@ -36,8 +223,294 @@ impl AwsSettings {
     pub fn build() -> Result<Self, SettingsError> {
         let auth = std::env::var("AUTH_TABLE")
             .map_err(|_| SettingsError::InvalidLoadConfig("AUTH_TABLE".to_string()))?;
+        let refresh_token = std::env::var("REFRESH_TOKEN_TABLE").ok();
+        let revoked_token = std::env::var("REVOKED_TOKEN_TABLE").ok();
+        let login_attempt = std::env::var("LOGIN_ATTEMPT_TABLE").ok();
+        let mfa = std::env::var("MFA_TABLE").ok();
+        let external_identity = std::env::var("EXTERNAL_IDENTITY_TABLE").ok();
+        let personal_access_token = std::env::var("PERSONAL_ACCESS_TOKEN_TABLE").ok();
+        let account_deletion = std::env::var("ACCOUNT_DELETION_TABLE").ok();
+        let data_export = std::env::var("DATA_EXPORT_TABLE").ok();
+        let security_event = std::env::var("SECURITY_EVENT_TABLE").ok();
+        let subscribe = std::env::var("SUBSCRIBE_TABLE").ok();
+        let category = std::env::var("CATEGORY_TABLE").ok();
+        let payment_method = std::env::var("PAYMENT_METHOD_TABLE").ok();
+        let payment_history = std::env::var("PAYMENT_HISTORY_TABLE").ok();
+        let notification_setting = std::env::var("NOTIFICATION_SETTING_TABLE").ok();
+        let allow_in_memory_stores = env_or("ALLOW_IN_MEMORY_STORES", false)?;
+
+        Ok(Self {
+            auth,
+            refresh_token,
+            revoked_token,
+            login_attempt,
+            mfa,
+            external_identity,
+            personal_access_token,
+            account_deletion,
+            data_export,
+            security_event,
+            subscribe,
+            category,
+            payment_method,
+            payment_history,
+            notification_setting,
+            allow_in_memory_stores,
+        })
+    }
+}
+
+impl CognitoSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let client_id = std::env::var("COGNITO_CLIENT_ID")
+            .map_err(|_| SettingsError::InvalidLoadConfig("COGNITO_CLIENT_ID".to_string()))?;
+        let client_secret = std::env::var("COGNITO_CLIENT_SECRET")
+            .map_err(|_| SettingsError::InvalidLoadConfig("COGNITO_CLIENT_SECRET".to_string()))?;
+        let user_pool_id = std::env::var("COGNITO_USER_POOL_ID")
+            .map_err(|_| SettingsError::InvalidLoadConfig("COGNITO_USER_POOL_ID".to_string()))?;
+        let custom_auth_secret = std::env::var("COGNITO_CUSTOM_AUTH_SECRET").ok();
 
-        Ok(Self { auth })
+        Ok(Self { client_id, client_secret, user_pool_id, custom_auth_secret })
+    }
+}
+
+impl AuthBackend {
+    /// `AUTH_BACKEND`が未設定の場合はCognitoを使用します
+    pub fn build() -> Result<Self, SettingsError> {
+        match std::env::var("AUTH_BACKEND").as_deref() {
+            Err(_) | Ok("cognito") => Ok(Self::Cognito),
+            Ok("local") => Ok(Self::Local),
+            Ok(_) => Err(SettingsError::InvalidLoadConfig("AUTH_BACKEND".to_string())),
+        }
+    }
+}
+
+impl LocalAuthSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let credential_table = std::env::var("LOCAL_AUTH_TABLE").ok();
+        let private_key_file = std::env::var("LOCAL_JWT_PRIVATE_KEY_FILE")
+            .map_err(|_| SettingsError::InvalidLoadConfig("LOCAL_JWT_PRIVATE_KEY_FILE".to_string()))?;
+        let public_key_file = std::env::var("LOCAL_JWT_PUBLIC_KEY_FILE")
+            .map_err(|_| SettingsError::InvalidLoadConfig("LOCAL_JWT_PUBLIC_KEY_FILE".to_string()))?;
+        let key_id = std::env::var("LOCAL_JWT_KEY_ID")
+            .map_err(|_| SettingsError::InvalidLoadConfig("LOCAL_JWT_KEY_ID".to_string()))?;
+        let log_codes = env_or("LOCAL_AUTH_LOG_CODES", false)?;
+
+        Ok(Self { credential_table, private_key_file, public_key_file, key_id, log_codes })
+    }
+}
+
+impl LoginAttemptSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let threshold = env_or("LOGIN_LOCKOUT_THRESHOLD", 5)?;
+        let ip_threshold = env_or("LOGIN_LOCKOUT_IP_THRESHOLD", 20)?;
+        let window = env_or("LOGIN_LOCKOUT_WINDOW_SECONDS", 15 * 60)?;
+        let base_lockout = env_or("LOGIN_LOCKOUT_BASE_SECONDS", 60)?;
+        let max_lockout = env_or("LOGIN_LOCKOUT_MAX_SECONDS", 60 * 60)?;
+
+        let email_policy = LockoutPolicy { threshold, window, base_lockout, max_lockout };
+        let ip_policy = LockoutPolicy { threshold: ip_threshold, ..email_policy };
+        Ok(Self { email_policy, ip_policy })
+    }
+}
+
+impl PasswordSettings {
+    /// `PASSWORD_REQUIRED_CLASSES`は`lowercase`、`uppercase`、`letter`、`digit`、`symbol`をカンマ区切りで指定します
+    pub fn build() -> Result<Self, SettingsError> {
+        let default = PasswordPolicy::default();
+        let min_length = env_or("PASSWORD_MIN_LENGTH", default.min_length)?;
+        let max_length = env_or("PASSWORD_MAX_LENGTH", default.max_length)?;
+        if min_length > max_length {
+            return Err(SettingsError::InvalidLoadConfig("PASSWORD_MAX_LENGTH".to_string()));
+        }
+        let required_classes = match std::env::var("PASSWORD_REQUIRED_CLASSES") {
+            Ok(v) => v
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<CharacterClass>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| SettingsError::InvalidLoadConfig("PASSWORD_REQUIRED_CLASSES".to_string()))?,
+            Err(_) => default.required_classes,
+        };
+        let max_repeated = env_or("PASSWORD_MAX_REPEATED", 0)?;
+        let reject_personal_info = env_or("PASSWORD_REJECT_PERSONAL_INFO", default.reject_personal_info)?;
+
+        let policy = PasswordPolicy {
+            min_length,
+            max_length,
+            required_classes,
+            max_repeated: (max_repeated > 0).then_some(max_repeated),
+            reject_personal_info,
+        };
+        Ok(Self { policy, breached_password_file: std::env::var("BREACHED_PASSWORD_FILE").ok() })
+    }
+}
+
+impl GuestSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let ttl = env_or("GUEST_TTL_SECONDS", 30 * 24 * 60 * 60)?;
+
+        Ok(Self { ttl: std::time::Duration::from_secs(ttl) })
+    }
+}
+
+impl AccountDeletionSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let grace_period = env_or("ACCOUNT_DELETION_GRACE_PERIOD_SECONDS", 14 * 24 * 60 * 60)?;
+        let job_interval = env_or("ACCOUNT_DELETION_JOB_INTERVAL_SECONDS", 10 * 60)?;
+        if job_interval == 0 {
+            return Err(SettingsError::InvalidLoadConfig("ACCOUNT_DELETION_JOB_INTERVAL_SECONDS".to_string()));
+        }
+
+        Ok(Self {
+            grace_period: std::time::Duration::from_secs(grace_period),
+            job_interval: std::time::Duration::from_secs(job_interval),
+        })
+    }
+}
+
+impl DataExportSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let download_ttl = env_or("DATA_EXPORT_DOWNLOAD_TTL_SECONDS", 15 * 60)?;
+        if download_ttl == 0 {
+            return Err(SettingsError::InvalidLoadConfig("DATA_EXPORT_DOWNLOAD_TTL_SECONDS".to_string()));
+        }
+        let job_interval = env_or("DATA_EXPORT_JOB_INTERVAL_SECONDS", 60)?;
+        if job_interval == 0 {
+            return Err(SettingsError::InvalidLoadConfig("DATA_EXPORT_JOB_INTERVAL_SECONDS".to_string()));
+        }
+
+        Ok(Self {
+            download_ttl: std::time::Duration::from_secs(download_ttl),
+            job_interval: std::time::Duration::from_secs(job_interval),
+        })
+    }
+}
+
+impl StorageSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let bucket = std::env::var("STORAGE_BUCKET").ok();
+        let dir = std::env::var("STORAGE_DIR").unwrap_or_else(|_| "storage".to_string());
+
+        Ok(Self { bucket, dir })
+    }
+}
+
+impl MfaSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let issuer = std::env::var("MFA_ISSUER").unwrap_or_else(|_| "SubTrack".to_string());
+        let encryption_key = env_key("MFA_ENCRYPTION_KEY")?;
+
+        Ok(Self { issuer, encryption_key })
+    }
+}
+
+impl OidcProviderSettings {
+    /// `OIDC_<NAME>_ISSUER`などプロバイダーの名前を大文字にした環境変数を読み込みます
+    pub fn build(name: &str) -> Result<Self, SettingsError> {
+        let prefix = format!("OIDC_{}_", name.to_uppercase());
+        let var = |key: &str| {
+            let key = format!("{}{}", prefix, key);
+            std::env::var(&key).map_err(|_| SettingsError::InvalidLoadConfig(key))
+        };
+
+        Ok(Self {
+            name: name.to_lowercase(),
+            issuer: var("ISSUER")?,
+            client_id: var("CLIENT_ID")?,
+            client_secret: var("CLIENT_SECRET").ok(),
+            redirect_uri: var("REDIRECT_URI")?,
+            response_mode: var("RESPONSE_MODE").ok(),
+        })
+    }
+}
+
+impl OidcSettings {
+    /// `OIDC_PROVIDERS`にカンマ区切りで指定したプロバイダーを読み込みます。未設定の場合は無効です
+    pub fn build() -> Result<Self, SettingsError> {
+        let providers = std::env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(OidcProviderSettings::build)
+            .collect::<Result<Vec<_>, _>>()?;
+        let state_key = env_key("OIDC_STATE_KEY")?;
+
+        Ok(Self { providers, state_key })
+    }
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        let per_minute = |capacity| Limit { capacity, period: std::time::Duration::from_secs(60) };
+        Self {
+            auth: RouteLimits { ip: per_minute(20), user: per_minute(30) },
+            api: RouteLimits { ip: per_minute(300), user: per_minute(600) },
+        }
+    }
+}
+
+impl RateLimitSettings {
+    pub fn build() -> Result<Self, SettingsError> {
+        let default = Self::default();
+        let auth = RouteLimits {
+            ip: env_or("RATE_LIMIT_AUTH_IP", default.auth.ip)?,
+            user: env_or("RATE_LIMIT_AUTH_USER", default.auth.user)?,
+        };
+        let api = RouteLimits {
+            ip: env_or("RATE_LIMIT_IP", default.api.ip)?,
+            user: env_or("RATE_LIMIT_USER", default.api.user)?,
+        };
+
+        Ok(Self { auth, api })
+    }
+}
+
+/// 環境変数を`T`に変換します。未設定の場合は`default`を返します
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> Result<T, SettingsError> {
+    match std::env::var(key) {
+        Ok(v) => v.parse().map_err(|_| SettingsError::InvalidLoadConfig(key.to_string())),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Base64で指定した32バイトの鍵を読み込みます。未設定の場合は`None`を返します
+fn env_key(key: &str) -> Result<Option<Vec<u8>>, SettingsError> {
+    use base64::Engine;
+
+    match std::env::var(key) {
+        Ok(v) => match base64::engine::general_purpose::STANDARD.decode(v) {
+            Ok(value) if value.len() == 32 => Ok(Some(value)),
+            _ => Err(SettingsError::InvalidLoadConfig(key.to_string())),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+impl JwtSettings {
+    /// `JWKS_URL`が未設定の場合は`JWKS_FILE`を使用します
+    pub fn build() -> Result<Self, SettingsError> {
+        let jwks_url = std::env::var("JWKS_URL").ok();
+        let jwks_file = std::env::var("JWKS_FILE").ok();
+        if jwks_url.is_none() && jwks_file.is_none() {
+            return Err(SettingsError::InvalidLoadConfig("JWKS_URL".to_string()));
+        }
+        let issuer =
+            std::env::var("JWT_ISSUER").map_err(|_| SettingsError::InvalidLoadConfig("JWT_ISSUER".to_string()))?;
+        let audience =
+            std::env::var("JWT_AUDIENCE").map_err(|_| SettingsError::InvalidLoadConfig("JWT_AUDIENCE".to_string()))?;
+
+        Ok(Self { jwks_url, jwks_file, issuer, audience })
+    }
+
+    pub fn jwks_source(&self) -> auth::jwks::JwksSource {
+        match (&self.jwks_url, &self.jwks_file) {
+            (Some(url), _) => auth::jwks::JwksSource::Url(url.clone()),
+            (None, Some(file)) => auth::jwks::JwksSource::File(file.into()),
+            (None, None) => unreachable!("JwtSettings::build requires JWKS_URL or JWKS_FILE"),
+        }
     }
 }
 
@@ -48,38 +521,102 @@ pub fn set_up_tracing_subscriber() {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::fmt::layer()
-                .json()
-                .with_target(true)
+                .fmt_fields(JsonFields::new())
+                .event_format(RedactSecrets::new(format().json().with_target(true)))
                 .with_ansi(false)
                 .with_filter(filter)
-                .with_filter(filter::filter_fn(|metadata| {
-                    if metadata.target().contains(CREDENTIALS) {
-                        false
-                    } else {
-                        true
-                    }
-                })),
+                .with_filter(filter::filter_fn(|metadata| !metadata.target().contains(CREDENTIALS))),
         )
         .init();
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Mutex, MutexGuard};
+
     use super::*;
 
     const HOST: &str = "1.1.1.1";
     const PORT: &str = "7878";
     const AUTH_TABLE: &str = "payment";
+    const COGNITO_CLIENT_ID: &str = "client_id";
+    const COGNITO_CLIENT_SECRET: &str = "client_secret";
+
+    /// 環境変数はプロセス全体で共有されるため、テスト間で排他制御します
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
 
-    fn clear_env() {
+    fn clear_env() -> MutexGuard<'static, ()> {
+        let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         std::env::remove_var("HOST");
         std::env::remove_var("PORT");
+        std::env::remove_var("TRUSTED_PROXY_HOPS");
         std::env::remove_var("AUTH_TABLE");
+        std::env::remove_var("REFRESH_TOKEN_TABLE");
+        std::env::remove_var("REVOKED_TOKEN_TABLE");
+        std::env::remove_var("LOGIN_ATTEMPT_TABLE");
+        std::env::remove_var("MFA_TABLE");
+        std::env::remove_var("EXTERNAL_IDENTITY_TABLE");
+        std::env::remove_var("PERSONAL_ACCESS_TOKEN_TABLE");
+        std::env::remove_var("ACCOUNT_DELETION_TABLE");
+        std::env::remove_var("SUBSCRIBE_TABLE");
+        std::env::remove_var("CATEGORY_TABLE");
+        std::env::remove_var("PAYMENT_METHOD_TABLE");
+        std::env::remove_var("PAYMENT_HISTORY_TABLE");
+        std::env::remove_var("NOTIFICATION_SETTING_TABLE");
+        std::env::remove_var("DATA_EXPORT_TABLE");
+        std::env::remove_var("ALLOW_IN_MEMORY_STORES");
+        std::env::remove_var("SECURITY_EVENT_TABLE");
+        std::env::remove_var("ACCOUNT_DELETION_GRACE_PERIOD_SECONDS");
+        std::env::remove_var("ACCOUNT_DELETION_JOB_INTERVAL_SECONDS");
+        std::env::remove_var("DATA_EXPORT_DOWNLOAD_TTL_SECONDS");
+        std::env::remove_var("DATA_EXPORT_JOB_INTERVAL_SECONDS");
+        std::env::remove_var("STORAGE_BUCKET");
+        std::env::remove_var("STORAGE_DIR");
+        std::env::remove_var("MFA_ISSUER");
+        std::env::remove_var("MFA_ENCRYPTION_KEY");
+        std::env::remove_var("GUEST_TTL_SECONDS");
+        std::env::remove_var("PASSWORD_MIN_LENGTH");
+        std::env::remove_var("PASSWORD_MAX_LENGTH");
+        std::env::remove_var("PASSWORD_REQUIRED_CLASSES");
+        std::env::remove_var("PASSWORD_MAX_REPEATED");
+        std::env::remove_var("PASSWORD_REJECT_PERSONAL_INFO");
+        std::env::remove_var("BREACHED_PASSWORD_FILE");
+        std::env::remove_var("OIDC_PROVIDERS");
+        std::env::remove_var("OIDC_STATE_KEY");
+        for name in ["GOOGLE", "APPLE"] {
+            for key in ["ISSUER", "CLIENT_ID", "CLIENT_SECRET", "REDIRECT_URI", "RESPONSE_MODE"] {
+                std::env::remove_var(format!("OIDC_{}_{}", name, key));
+            }
+        }
+        std::env::remove_var("LOGIN_LOCKOUT_THRESHOLD");
+        std::env::remove_var("LOGIN_LOCKOUT_IP_THRESHOLD");
+        std::env::remove_var("LOGIN_LOCKOUT_WINDOW_SECONDS");
+        std::env::remove_var("LOGIN_LOCKOUT_BASE_SECONDS");
+        std::env::remove_var("LOGIN_LOCKOUT_MAX_SECONDS");
+        std::env::remove_var("RATE_LIMIT_AUTH_IP");
+        std::env::remove_var("RATE_LIMIT_AUTH_USER");
+        std::env::remove_var("RATE_LIMIT_IP");
+        std::env::remove_var("RATE_LIMIT_USER");
+        std::env::remove_var("COGNITO_CLIENT_ID");
+        std::env::remove_var("COGNITO_CLIENT_SECRET");
+        std::env::remove_var("COGNITO_USER_POOL_ID");
+        std::env::remove_var("COGNITO_CUSTOM_AUTH_SECRET");
+        std::env::remove_var("JWKS_URL");
+        std::env::remove_var("JWKS_FILE");
+        std::env::remove_var("JWT_ISSUER");
+        std::env::remove_var("JWT_AUDIENCE");
+        std::env::remove_var("AUTH_BACKEND");
+        std::env::remove_var("LOCAL_AUTH_TABLE");
+        std::env::remove_var("LOCAL_JWT_PRIVATE_KEY_FILE");
+        std::env::remove_var("LOCAL_JWT_PUBLIC_KEY_FILE");
+        std::env::remove_var("LOCAL_JWT_KEY_ID");
+        std::env::remove_var("LOCAL_AUTH_LOG_CODES");
+        guard
     }
 
     #[test]
     fn test_api_settings_build_success() {
-        clear_env();
+        let _guard = clear_env();
         std::env::set_var("HOST", HOST);
         std::env::set_var("PORT", PORT);
 
@@ -88,12 +625,27 @@ mod tests {
         assert!(&result.is_ok());
         let result = result.unwrap();
         assert_eq!(&result.host, HOST);
-        assert_eq!(&result.port, PORT)
+        assert_eq!(&result.port, PORT);
+        assert_eq!(0, result.trusted_proxy_hops);
+
+        std::env::set_var("TRUSTED_PROXY_HOPS", "1");
+        assert_eq!(1, ApiSettings::build().unwrap().trusted_proxy_hops);
+    }
+
+    #[test]
+    fn test_api_settings_build_trusted_proxy_hops_failed() {
+        let _guard = clear_env();
+        std::env::set_var("HOST", HOST);
+        std::env::set_var("PORT", PORT);
+        std::env::set_var("TRUSTED_PROXY_HOPS", "-1");
+        let result = ApiSettings::build();
+
+        assert_eq!(SettingsError::InvalidLoadConfig("TRUSTED_PROXY_HOPS".to_string()), result.unwrap_err());
     }
 
     #[test]
     fn test_api_settings_build_host_failed() {
-        clear_env();
+        let _guard = clear_env();
         let result = ApiSettings::build();
 
         assert!(result.is_err());
@@ -105,7 +657,7 @@ mod tests {
 
     #[test]
     fn test_api_settings_build_port_failed() {
-        clear_env();
+        let _guard = clear_env();
         std::env::set_var("HOST", HOST);
         let result = ApiSettings::build();
 
@@ -118,18 +670,71 @@ mod tests {
 
     #[test]
     fn aws_settings_build_payment_success() {
-        clear_env();
+        let _guard = clear_env();
         std::env::set_var("AUTH_TABLE", AUTH_TABLE);
         let result = AwsSettings::build();
 
         assert!(result.is_ok());
         let result = result.unwrap();
-        assert_eq!(&result.auth, AUTH_TABLE)
+        assert_eq!(&result.auth, AUTH_TABLE);
+        assert_eq!(None, result.refresh_token);
+        assert_eq!(None, result.revoked_token);
+        assert_eq!(None, result.login_attempt);
+        assert_eq!(None, result.mfa);
+        assert_eq!(None, result.external_identity);
+        assert_eq!(None, result.personal_access_token);
+        assert_eq!(None, result.account_deletion);
+        assert_eq!(None, result.subscribe);
+        assert_eq!(None, result.category);
+        assert_eq!(None, result.payment_method);
+        assert_eq!(None, result.data_export);
+        assert_eq!(None, result.security_event);
+        assert_eq!(None, result.payment_history);
+        assert_eq!(None, result.notification_setting);
+        assert!(!result.allow_in_memory_stores);
+
+        std::env::set_var("REFRESH_TOKEN_TABLE", "refresh_token");
+        std::env::set_var("REVOKED_TOKEN_TABLE", "revoked_token");
+        std::env::set_var("LOGIN_ATTEMPT_TABLE", "login_attempt");
+        std::env::set_var("MFA_TABLE", "mfa");
+        std::env::set_var("EXTERNAL_IDENTITY_TABLE", "external_identity");
+        std::env::set_var("PERSONAL_ACCESS_TOKEN_TABLE", "personal_access_token");
+        std::env::set_var("ACCOUNT_DELETION_TABLE", "account_deletion");
+        std::env::set_var("SUBSCRIBE_TABLE", "subscribe");
+        std::env::set_var("CATEGORY_TABLE", "category");
+        std::env::set_var("PAYMENT_METHOD_TABLE", "payment_method");
+        std::env::set_var("DATA_EXPORT_TABLE", "data_export");
+        std::env::set_var("SECURITY_EVENT_TABLE", "security_event");
+        std::env::set_var("PAYMENT_HISTORY_TABLE", "payment_history");
+        std::env::set_var("NOTIFICATION_SETTING_TABLE", "notification_setting");
+        let result = AwsSettings::build().unwrap();
+        assert_eq!(Some("refresh_token".to_string()), result.refresh_token);
+        assert_eq!(Some("revoked_token".to_string()), result.revoked_token);
+        assert_eq!(Some("login_attempt".to_string()), result.login_attempt);
+        assert_eq!(Some("mfa".to_string()), result.mfa);
+        assert_eq!(Some("external_identity".to_string()), result.external_identity);
+        assert_eq!(Some("personal_access_token".to_string()), result.personal_access_token);
+        assert_eq!(Some("account_deletion".to_string()), result.account_deletion);
+        assert_eq!(Some("subscribe".to_string()), result.subscribe);
+        assert_eq!(Some("category".to_string()), result.category);
+        assert_eq!(Some("payment_method".to_string()), result.payment_method);
+        assert_eq!(Some("data_export".to_string()), result.data_export);
+        assert_eq!(Some("security_event".to_string()), result.security_event);
+        assert_eq!(Some("payment_history".to_string()), result.payment_history);
+        assert_eq!(Some("notification_setting".to_string()), result.notification_setting);
+
+        std::env::set_var("ALLOW_IN_MEMORY_STORES", "true");
+        assert!(AwsSettings::build().unwrap().allow_in_memory_stores);
+        std::env::set_var("ALLOW_IN_MEMORY_STORES", "yes");
+        assert_eq!(
+            SettingsError::InvalidLoadConfig("ALLOW_IN_MEMORY_STORES".to_string()),
+            AwsSettings::build().unwrap_err()
+        )
     }
 
     #[test]
     fn aws_settings_build_payment_failed() {
-        clear_env();
+        let _guard = clear_env();
         let result = AwsSettings::build();
 
         assert!(result.is_err());
@@ -138,4 +743,399 @@ mod tests {
             result.unwrap_err()
         )
     }
+
+    #[test]
+    fn cognito_settings_build_success() {
+        let _guard = clear_env();
+        std::env::set_var("COGNITO_CLIENT_ID", COGNITO_CLIENT_ID);
+        std::env::set_var("COGNITO_CLIENT_SECRET", COGNITO_CLIENT_SECRET);
+        std::env::set_var("COGNITO_USER_POOL_ID", "ap-northeast-1_example");
+        let result = CognitoSettings::build();
+
+        assert!(result.is_ok());
+        let result = result.unwrap();
+        assert_eq!(&result.client_id, COGNITO_CLIENT_ID);
+        assert_eq!(&result.client_secret, COGNITO_CLIENT_SECRET);
+        assert_eq!("ap-northeast-1_example", result.user_pool_id);
+        assert_eq!(None, result.custom_auth_secret);
+
+        std::env::set_var("COGNITO_CUSTOM_AUTH_SECRET", "custom_auth_secret");
+        let result = CognitoSettings::build().unwrap();
+        assert_eq!(Some("custom_auth_secret".to_string()), result.custom_auth_secret);
+    }
+
+    #[test]
+    fn cognito_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("COGNITO_CLIENT_ID", COGNITO_CLIENT_ID);
+        let result = CognitoSettings::build();
+
+        assert!(result.is_err());
+        assert_eq!(
+            SettingsError::InvalidLoadConfig("COGNITO_CLIENT_SECRET".to_string()),
+            result.unwrap_err()
+        );
+
+        // ユーザーを削除できないため、ユーザープールのIDは必須です
+        std::env::set_var("COGNITO_CLIENT_SECRET", COGNITO_CLIENT_SECRET);
+        let result = CognitoSettings::build();
+        assert_eq!(
+            SettingsError::InvalidLoadConfig("COGNITO_USER_POOL_ID".to_string()),
+            result.unwrap_err()
+        )
+    }
+
+    #[test]
+    fn jwt_settings_build_success() {
+        let _guard = clear_env();
+        std::env::set_var("JWKS_FILE", "jwks.json");
+        std::env::set_var("JWT_ISSUER", "issuer");
+        std::env::set_var("JWT_AUDIENCE", "audience");
+        let result = JwtSettings::build();
+
+        assert!(result.is_ok());
+        let result = result.unwrap();
+        assert!(matches!(result.jwks_source(), auth::jwks::JwksSource::File(_)));
+        assert_eq!(&result.issuer, "issuer");
+        assert_eq!(&result.audience, "audience");
+
+        std::env::set_var("JWKS_URL", "https://example.com/.well-known/jwks.json");
+        let result = JwtSettings::build().unwrap();
+        assert!(matches!(result.jwks_source(), auth::jwks::JwksSource::Url(_)));
+    }
+
+    #[test]
+    fn jwt_settings_build_failed() {
+        let _guard = clear_env();
+        let result = JwtSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("JWKS_URL".to_string()), result.unwrap_err());
+
+        std::env::set_var("JWKS_URL", "https://example.com/.well-known/jwks.json");
+        let result = JwtSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("JWT_ISSUER".to_string()), result.unwrap_err());
+    }
+
+    #[test]
+    fn auth_backend_build() {
+        let _guard = clear_env();
+        assert_eq!(AuthBackend::Cognito, AuthBackend::build().unwrap());
+
+        let test_cases = vec![
+            ("cognito", Ok(AuthBackend::Cognito)),
+            ("local", Ok(AuthBackend::Local)),
+            ("unknown", Err(SettingsError::InvalidLoadConfig("AUTH_BACKEND".to_string()))),
+        ];
+        for (value, expected) in test_cases {
+            std::env::set_var("AUTH_BACKEND", value);
+            assert_eq!(expected, AuthBackend::build());
+        }
+    }
+
+    #[test]
+    fn local_auth_settings_build_success() {
+        let _guard = clear_env();
+        std::env::set_var("LOCAL_JWT_PRIVATE_KEY_FILE", "private_key.pem");
+        std::env::set_var("LOCAL_JWT_PUBLIC_KEY_FILE", "public_key.pem");
+        std::env::set_var("LOCAL_JWT_KEY_ID", "local");
+        let result = LocalAuthSettings::build().unwrap();
+
+        assert_eq!(None, result.credential_table);
+        assert_eq!("private_key.pem", &result.private_key_file);
+        assert_eq!("public_key.pem", &result.public_key_file);
+        assert_eq!("local", &result.key_id);
+        assert!(!result.log_codes);
+
+        std::env::set_var("LOCAL_AUTH_TABLE", "credential");
+        std::env::set_var("LOCAL_AUTH_LOG_CODES", "true");
+        let result = LocalAuthSettings::build().unwrap();
+        assert_eq!(Some("credential".to_string()), result.credential_table);
+        assert!(result.log_codes);
+    }
+
+    #[test]
+    fn local_auth_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("LOCAL_JWT_PRIVATE_KEY_FILE", "private_key.pem");
+        let result = LocalAuthSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("LOCAL_JWT_PUBLIC_KEY_FILE".to_string()), result.unwrap_err());
+
+        std::env::set_var("LOCAL_JWT_PUBLIC_KEY_FILE", "public_key.pem");
+        std::env::set_var("LOCAL_JWT_KEY_ID", "local");
+        std::env::set_var("LOCAL_AUTH_LOG_CODES", "yes");
+        let result = LocalAuthSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("LOCAL_AUTH_LOG_CODES".to_string()), result.unwrap_err());
+    }
+
+    #[test]
+    fn login_attempt_settings_build_success() {
+        let _guard = clear_env();
+        let result = LoginAttemptSettings::build().unwrap();
+        assert_eq!(
+            LockoutPolicy { threshold: 5, window: 900, base_lockout: 60, max_lockout: 3600 },
+            result.email_policy
+        );
+        assert_eq!(20, result.ip_policy.threshold);
+
+        std::env::set_var("LOGIN_LOCKOUT_THRESHOLD", "3");
+        std::env::set_var("LOGIN_LOCKOUT_IP_THRESHOLD", "10");
+        std::env::set_var("LOGIN_LOCKOUT_WINDOW_SECONDS", "60");
+        std::env::set_var("LOGIN_LOCKOUT_BASE_SECONDS", "30");
+        std::env::set_var("LOGIN_LOCKOUT_MAX_SECONDS", "300");
+        let result = LoginAttemptSettings::build().unwrap();
+        assert_eq!(LockoutPolicy { threshold: 3, window: 60, base_lockout: 30, max_lockout: 300 }, result.email_policy);
+        assert_eq!(LockoutPolicy { threshold: 10, window: 60, base_lockout: 30, max_lockout: 300 }, result.ip_policy);
+    }
+
+    #[test]
+    fn login_attempt_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("LOGIN_LOCKOUT_THRESHOLD", "five");
+        let result = LoginAttemptSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("LOGIN_LOCKOUT_THRESHOLD".to_string()), result.unwrap_err());
+    }
+
+    #[test]
+    fn password_settings_build_success() {
+        let _guard = clear_env();
+        let result = PasswordSettings::build().unwrap();
+        assert_eq!(PasswordSettings { policy: PasswordPolicy::default(), breached_password_file: None }, result);
+
+        std::env::set_var("PASSWORD_MIN_LENGTH", "12");
+        std::env::set_var("PASSWORD_MAX_LENGTH", "64");
+        std::env::set_var("PASSWORD_REQUIRED_CLASSES", "lowercase, uppercase,digit,symbol");
+        std::env::set_var("PASSWORD_MAX_REPEATED", "3");
+        std::env::set_var("PASSWORD_REJECT_PERSONAL_INFO", "false");
+        std::env::set_var("BREACHED_PASSWORD_FILE", "breached_passwords.txt");
+        let result = PasswordSettings::build().unwrap();
+        let expected = PasswordPolicy {
+            min_length: 12,
+            max_length: 64,
+            required_classes: vec![
+                CharacterClass::Lowercase,
+                CharacterClass::Uppercase,
+                CharacterClass::Digit,
+                CharacterClass::Symbol,
+            ],
+            max_repeated: Some(3),
+            reject_personal_info: false,
+        };
+        assert_eq!(expected, result.policy);
+        assert_eq!(Some("breached_passwords.txt".to_string()), result.breached_password_file);
+
+        std::env::set_var("PASSWORD_REQUIRED_CLASSES", "");
+        std::env::set_var("PASSWORD_MAX_REPEATED", "0");
+        let result = PasswordSettings::build().unwrap();
+        assert!(result.policy.required_classes.is_empty());
+        assert_eq!(None, result.policy.max_repeated);
+    }
+
+    #[test]
+    fn password_settings_build_failed() {
+        let test_cases = vec![
+            ("PASSWORD_MIN_LENGTH", "eight", "PASSWORD_MIN_LENGTH"),
+            ("PASSWORD_MIN_LENGTH", "200", "PASSWORD_MAX_LENGTH"),
+            ("PASSWORD_REQUIRED_CLASSES", "digit,emoji", "PASSWORD_REQUIRED_CLASSES"),
+            ("PASSWORD_REJECT_PERSONAL_INFO", "yes", "PASSWORD_REJECT_PERSONAL_INFO"),
+        ];
+        for (key, value, expected) in test_cases {
+            let _guard = clear_env();
+            std::env::set_var(key, value);
+            let result = PasswordSettings::build();
+            assert_eq!(SettingsError::InvalidLoadConfig(expected.to_string()), result.unwrap_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn guest_settings_build_success() {
+        let _guard = clear_env();
+        let result = GuestSettings::build().unwrap();
+        assert_eq!(std::time::Duration::from_secs(30 * 24 * 60 * 60), result.ttl);
+
+        std::env::set_var("GUEST_TTL_SECONDS", "3600");
+        let result = GuestSettings::build().unwrap();
+        assert_eq!(std::time::Duration::from_secs(3600), result.ttl);
+    }
+
+    #[test]
+    fn guest_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("GUEST_TTL_SECONDS", "-1");
+        let result = GuestSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("GUEST_TTL_SECONDS".to_string()), result.unwrap_err());
+    }
+
+    #[test]
+    fn account_deletion_settings_build_success() {
+        let _guard = clear_env();
+        let result = AccountDeletionSettings::build().unwrap();
+        assert_eq!(std::time::Duration::from_secs(14 * 24 * 60 * 60), result.grace_period);
+        assert_eq!(std::time::Duration::from_secs(10 * 60), result.job_interval);
+
+        std::env::set_var("ACCOUNT_DELETION_GRACE_PERIOD_SECONDS", "0");
+        std::env::set_var("ACCOUNT_DELETION_JOB_INTERVAL_SECONDS", "60");
+        let result = AccountDeletionSettings::build().unwrap();
+        assert_eq!(std::time::Duration::ZERO, result.grace_period);
+        assert_eq!(std::time::Duration::from_secs(60), result.job_interval);
+    }
+
+    #[test]
+    fn account_deletion_settings_build_failed() {
+        let test_cases = vec![
+            ("ACCOUNT_DELETION_GRACE_PERIOD_SECONDS", "-1"),
+            ("ACCOUNT_DELETION_JOB_INTERVAL_SECONDS", "0"),
+        ];
+        for (key, value) in test_cases {
+            let _guard = clear_env();
+            std::env::set_var(key, value);
+            let result = AccountDeletionSettings::build();
+            assert_eq!(SettingsError::InvalidLoadConfig(key.to_string()), result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn data_export_settings_build_success() {
+        let _guard = clear_env();
+        let result = DataExportSettings::build().unwrap();
+        assert_eq!(std::time::Duration::from_secs(15 * 60), result.download_ttl);
+        assert_eq!(std::time::Duration::from_secs(60), result.job_interval);
+
+        std::env::set_var("DATA_EXPORT_DOWNLOAD_TTL_SECONDS", "300");
+        std::env::set_var("DATA_EXPORT_JOB_INTERVAL_SECONDS", "10");
+        let result = DataExportSettings::build().unwrap();
+        assert_eq!(std::time::Duration::from_secs(300), result.download_ttl);
+        assert_eq!(std::time::Duration::from_secs(10), result.job_interval);
+    }
+
+    #[test]
+    fn data_export_settings_build_failed() {
+        let test_cases = vec![
+            ("DATA_EXPORT_DOWNLOAD_TTL_SECONDS", "0"),
+            ("DATA_EXPORT_DOWNLOAD_TTL_SECONDS", "-1"),
+            ("DATA_EXPORT_JOB_INTERVAL_SECONDS", "0"),
+        ];
+        for (key, value) in test_cases {
+            let _guard = clear_env();
+            std::env::set_var(key, value);
+            let result = DataExportSettings::build();
+            assert_eq!(SettingsError::InvalidLoadConfig(key.to_string()), result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn storage_settings_build_success() {
+        let _guard = clear_env();
+        let result = StorageSettings::build().unwrap();
+        assert_eq!(StorageSettings { bucket: None, dir: "storage".to_string() }, result);
+
+        std::env::set_var("STORAGE_BUCKET", "subtrack-storage");
+        std::env::set_var("STORAGE_DIR", "/var/lib/subtrack");
+        let result = StorageSettings::build().unwrap();
+        assert_eq!(
+            StorageSettings { bucket: Some("subtrack-storage".to_string()), dir: "/var/lib/subtrack".to_string() },
+            result
+        );
+    }
+
+    #[test]
+    fn mfa_settings_build_success() {
+        let _guard = clear_env();
+        let result = MfaSettings::build().unwrap();
+        assert_eq!(MfaSettings { issuer: "SubTrack".to_string(), encryption_key: None }, result);
+
+        std::env::set_var("MFA_ISSUER", "SubTrack Dev");
+        std::env::set_var("MFA_ENCRYPTION_KEY", "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=");
+        let result = MfaSettings::build().unwrap();
+        assert_eq!("SubTrack Dev", &result.issuer);
+        assert_eq!(Some(b"0123456789abcdef0123456789abcdef".to_vec()), result.encryption_key);
+    }
+
+    #[test]
+    fn mfa_settings_build_failed() {
+        let _guard = clear_env();
+        let test_cases = vec![
+            "invalid!", "c2hvcnQ=",
+        ];
+        for value in test_cases {
+            std::env::set_var("MFA_ENCRYPTION_KEY", value);
+            let result = MfaSettings::build();
+            assert_eq!(SettingsError::InvalidLoadConfig("MFA_ENCRYPTION_KEY".to_string()), result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn oidc_settings_build_success() {
+        let _guard = clear_env();
+        let result = OidcSettings::build().unwrap();
+        assert_eq!(OidcSettings { providers: vec![], state_key: None }, result);
+
+        std::env::set_var("OIDC_PROVIDERS", "google, apple");
+        std::env::set_var("OIDC_STATE_KEY", "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=");
+        for name in ["GOOGLE", "APPLE"] {
+            std::env::set_var(format!("OIDC_{}_ISSUER", name), format!("https://{}.example.com", name.to_lowercase()));
+            std::env::set_var(format!("OIDC_{}_CLIENT_ID", name), "client_id");
+            std::env::set_var(format!("OIDC_{}_REDIRECT_URI", name), "https://app.example.com/callback");
+        }
+        std::env::set_var("OIDC_GOOGLE_CLIENT_SECRET", "client_secret");
+        std::env::set_var("OIDC_APPLE_RESPONSE_MODE", "form_post");
+
+        let result = OidcSettings::build().unwrap();
+        assert_eq!(Some(b"0123456789abcdef0123456789abcdef".to_vec()), result.state_key);
+        assert_eq!(
+            vec![
+                OidcProviderSettings {
+                    name: "google".to_string(),
+                    issuer: "https://google.example.com".to_string(),
+                    client_id: "client_id".to_string(),
+                    client_secret: Some("client_secret".to_string()),
+                    redirect_uri: "https://app.example.com/callback".to_string(),
+                    response_mode: None,
+                },
+                OidcProviderSettings {
+                    name: "apple".to_string(),
+                    issuer: "https://apple.example.com".to_string(),
+                    client_id: "client_id".to_string(),
+                    client_secret: None,
+                    redirect_uri: "https://app.example.com/callback".to_string(),
+                    response_mode: Some("form_post".to_string()),
+                },
+            ],
+            result.providers
+        );
+    }
+
+    #[test]
+    fn oidc_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("OIDC_PROVIDERS", "google");
+        std::env::set_var("OIDC_GOOGLE_ISSUER", "https://accounts.google.com");
+        std::env::set_var("OIDC_GOOGLE_REDIRECT_URI", "https://app.example.com/callback");
+        let result = OidcSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("OIDC_GOOGLE_CLIENT_ID".to_string()), result.unwrap_err());
+
+        std::env::set_var("OIDC_GOOGLE_CLIENT_ID", "client_id");
+        std::env::set_var("OIDC_STATE_KEY", "c2hvcnQ=");
+        let result = OidcSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("OIDC_STATE_KEY".to_string()), result.unwrap_err());
+    }
+
+    #[test]
+    fn rate_limit_settings_build_success() {
+        let _guard = clear_env();
+        assert_eq!(RateLimitSettings::default(), RateLimitSettings::build().unwrap());
+
+        std::env::set_var("RATE_LIMIT_AUTH_IP", "5/60");
+        std::env::set_var("RATE_LIMIT_USER", "100/10");
+        let result = RateLimitSettings::build().unwrap();
+        assert_eq!(Limit { capacity: 5, period: std::time::Duration::from_secs(60) }, result.auth.ip);
+        assert_eq!(Limit { capacity: 100, period: std::time::Duration::from_secs(10) }, result.api.user);
+        assert_eq!(RateLimitSettings::default().auth.user, result.auth.user);
+    }
+
+    #[test]
+    fn rate_limit_settings_build_failed() {
+        let _guard = clear_env();
+        std::env::set_var("RATE_LIMIT_IP", "0/60");
+        let result = RateLimitSettings::build();
+        assert_eq!(SettingsError::InvalidLoadConfig("RATE_LIMIT_IP".to_string()), result.unwrap_err());
+    }
 }
@@ -1,12 +1,87 @@
 use std::{
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
     str::FromStr,
+    sync::Arc,
 };
 
 use anyhow::Ok;
+use application::services::{
+    account_deletion_service::AccountDeletionServiceImpl, data_export_service::DataExportServiceImpl,
+    login_attempt_service::LoginAttemptServiceImpl, mfa_service::MfaServiceImpl, password_service::PasswordServiceImpl,
+    personal_access_token_service::PersonalAccessTokenServiceImpl, profile_icon_service::ProfileIconServiceImpl,
+    refresh_token_service::RefreshTokenServiceImpl, security_event_service::SecurityEventServiceImpl,
+    user_service::UserServiceImpl, AccountDeletionService, DataExportService, LoginAttemptService, MfaService,
+    PasswordService, PersonalAccessTokenService, RefreshTokenService,
+};
+use domain::{
+    clock::{Clock, SystemClock},
+    repository::{
+        account_deletion_repository::AccountDeletionRepository, auth_user_repository::AuthUserRepository,
+        credential_repository::CredentialRepository, data_export_repository::DataExportRepository,
+        external_identity_repository::ExternalIdentityRepository, login_attempt_repository::LoginAttemptRepository,
+        mfa_repository::MfaRepository, object_storage::ObjectStorage,
+        personal_access_token_repository::PersonalAccessTokenRepository,
+        refresh_token_repository::RefreshTokenRepository, revoked_token_repository::RevokedTokenRepository,
+        security_event_repository::SecurityEventRepository, user_data_repository::UserDataRepository,
+    },
+};
 use dotenv::dotenv;
-use server::{set_up_tracing_subscriber, ApiSettings};
-use tracing::error;
+use infrastructure::{
+    code_sender::LogCodeSender,
+    repository_impl::{
+        account_deletion_repository_impl::AccountDeletionRepositoryImpl,
+        cognito_auth_user_repository::CognitoAuthUserRepository,
+        credential_repository_impl::CredentialRepositoryImpl,
+        data_export_repository_impl::DataExportRepositoryImpl,
+        external_identity_repository_impl::ExternalIdentityRepositoryImpl,
+        in_memory_account_deletion_repository::InMemoryAccountDeletionRepository,
+        in_memory_credential_repository::InMemoryCredentialRepository,
+        in_memory_data_export_repository::InMemoryDataExportRepository,
+        in_memory_external_identity_repository::InMemoryExternalIdentityRepository,
+        in_memory_login_attempt_repository::InMemoryLoginAttemptRepository,
+        in_memory_mfa_repository::InMemoryMfaRepository,
+        in_memory_personal_access_token_repository::InMemoryPersonalAccessTokenRepository,
+        in_memory_refresh_token_repository::InMemoryRefreshTokenRepository,
+        in_memory_revoked_token_repository::InMemoryRevokedTokenRepository,
+        in_memory_security_event_repository::InMemorySecurityEventRepository,
+        local_auth_user_repository::{LocalAuthUserRepository, LocalTokenIssuer},
+        local_breached_password_repository::LocalBreachedPasswordRepository,
+        local_object_storage::LocalObjectStorage,
+        login_attempt_repository_impl::LoginAttemptRepositoryImpl,
+        mfa_repository_impl::MfaRepositoryImpl,
+        personal_access_token_repository_impl::PersonalAccessTokenRepositoryImpl,
+        refresh_token_repository_impl::RefreshTokenRepositoryImpl,
+        revoked_token_repository_impl::RevokedTokenRepositoryImpl,
+        s3_object_storage::S3ObjectStorage,
+        security_event_repository_impl::SecurityEventRepositoryImpl,
+        user_data_repository_impl::UserDataRepositoryImpl,
+        user_repository_impl::UserRepositoryImpl,
+    },
+};
+use server::{
+    auth::{
+        jwks::JwksCache,
+        jwt_validator::JwtValidator,
+        oidc::{OidcClient, OidcProviders},
+    },
+    rate_limit::RateLimiter,
+    router::router,
+    set_up_tracing_subscriber,
+    state::AppState,
+    AccountDeletionSettings, ApiSettings, AuthBackend, AwsSettings, CognitoSettings, DataExportSettings, GuestSettings,
+    JwtSettings, LocalAuthSettings, LoginAttemptSettings, MfaSettings, OidcSettings, PasswordSettings,
+    RateLimitSettings, SettingsError, StorageSettings,
+};
+use tracing::{error, info, warn};
+
+/// リフレッシュトークン系列の有効期間です
+const REFRESH_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(30 * 24 * 60 * 60);
+/// パスワード認証後、多要素認証のコードを入力するまでの猶予です
+const MFA_CHALLENGE_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// ソーシャルログインの認可リクエストから、コールバックを受け取るまでの猶予です
+const OIDC_STATE_TTL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+/// 個人用アクセストークンに指定できる最長の有効期間です
+const PERSONAL_ACCESS_TOKEN_MAX_TTL: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -17,8 +92,394 @@ async fn main() -> anyhow::Result<()> {
         error!("{:?}", e);
         e
     })?;
+    let aws = AwsSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let auth_backend = AuthBackend::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let jwt = JwtSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let login_attempt = LoginAttemptSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let rate_limit = RateLimitSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let mfa = MfaSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let oidc = OidcSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let guest = GuestSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let password = PasswordSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let account_deletion = AccountDeletionSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let data_export = DataExportSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let storage = StorageSettings::build().map_err(|e| {
+        error!("{:?}", e);
+        e
+    })?;
+    let oidc_enabled = !oidc.providers.is_empty();
 
     let socket_addr_v4 = SocketAddrV4::new(Ipv4Addr::from_str(&api.host)?, api.port.parse()?);
 
+    let config = aws_config::load_from_env().await;
+    let dynamodb_client = aws_sdk_dynamodb::Client::new(&config);
+    // 有効期限の判定などで使う時刻は、すべてこの`Clock`から取得します
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
+    // 無効化や使い回しの検知が再起動やインスタンス間で失われるため、インメモリで管理するには明示的な許可を必須にします
+    if !aws.allow_in_memory_stores {
+        for (key, table_name) in [
+            ("REFRESH_TOKEN_TABLE", &aws.refresh_token),
+            ("REVOKED_TOKEN_TABLE", &aws.revoked_token),
+            ("LOGIN_ATTEMPT_TABLE", &aws.login_attempt),
+            ("MFA_TABLE", &aws.mfa),
+            ("PERSONAL_ACCESS_TOKEN_TABLE", &aws.personal_access_token),
+        ] {
+            if table_name.is_none() {
+                let e = SettingsError::InvalidLoadConfig(key.to_string());
+                error!("{} is not set. set ALLOW_IN_MEMORY_STORES=true only in development. {:?}", key, e);
+                return Err(e.into());
+            }
+        }
+    }
+    let refresh_token_repository: Arc<dyn RefreshTokenRepository> = match aws.refresh_token {
+        Some(table_name) => Arc::new(RefreshTokenRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("REFRESH_TOKEN_TABLE is not set. refresh tokens are stored in memory");
+            Arc::new(InMemoryRefreshTokenRepository::new())
+        }
+    };
+    let revoked_token_repository: Arc<dyn RevokedTokenRepository> = match aws.revoked_token {
+        Some(table_name) => {
+            Arc::new(RevokedTokenRepositoryImpl::new(dynamodb_client.clone(), table_name).with_clock(clock.clone()))
+        }
+        None => {
+            warn!("REVOKED_TOKEN_TABLE is not set. revoked tokens are stored in memory");
+            Arc::new(InMemoryRevokedTokenRepository::new().with_clock(clock.clone()))
+        }
+    };
+    let login_attempt_repository: Arc<dyn LoginAttemptRepository> = match aws.login_attempt {
+        Some(table_name) => {
+            Arc::new(LoginAttemptRepositoryImpl::new(dynamodb_client.clone(), table_name).with_clock(clock.clone()))
+        }
+        None => {
+            warn!("LOGIN_ATTEMPT_TABLE is not set. login attempts are stored in memory");
+            Arc::new(InMemoryLoginAttemptRepository::new().with_clock(clock.clone()))
+        }
+    };
+    // 保存済みのシークレットを復号できなくなるため、永続化する場合は鍵の指定を必須にします
+    let mfa_encryption_key = match (mfa.encryption_key, aws.mfa.is_some()) {
+        (Some(key), _) => key,
+        (None, true) => {
+            let e = SettingsError::InvalidLoadConfig("MFA_ENCRYPTION_KEY".to_string());
+            error!("{:?}", e);
+            return Err(e.into());
+        }
+        (None, false) => {
+            warn!("MFA_ENCRYPTION_KEY is not set. a random key is used");
+            let mut key = vec![0u8; 32];
+            rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut key);
+            key
+        }
+    };
+    let mfa_repository: Arc<dyn MfaRepository> = match aws.mfa {
+        Some(table_name) => Arc::new(MfaRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("MFA_TABLE is not set. mfa settings are stored in memory");
+            Arc::new(InMemoryMfaRepository::new())
+        }
+    };
+    let external_identity_repository: Arc<dyn ExternalIdentityRepository> = match aws.external_identity {
+        Some(table_name) => Arc::new(ExternalIdentityRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("EXTERNAL_IDENTITY_TABLE is not set. external identities are stored in memory");
+            Arc::new(InMemoryExternalIdentityRepository::new())
+        }
+    };
+    let personal_access_token_repository: Arc<dyn PersonalAccessTokenRepository> = match aws.personal_access_token {
+        Some(table_name) => Arc::new(PersonalAccessTokenRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("PERSONAL_ACCESS_TOKEN_TABLE is not set. personal access tokens are stored in memory");
+            Arc::new(InMemoryPersonalAccessTokenRepository::new())
+        }
+    };
+    let oidc_state_key = match oidc.state_key {
+        Some(key) => key,
+        None => {
+            if !oidc.providers.is_empty() {
+                warn!("OIDC_STATE_KEY is not set. a random key is used and states are not shared between instances");
+            }
+            let mut key = vec![0u8; 32];
+            rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut key);
+            key
+        }
+    };
+    let oidc_providers = Arc::new(
+        OidcProviders::new(oidc.providers.into_iter().map(OidcClient::new).collect(), &oidc_state_key, OIDC_STATE_TTL)
+            .with_clock(clock.clone()),
+    );
+    let auth_user_repository: Arc<dyn AuthUserRepository> = match auth_backend {
+        AuthBackend::Cognito => {
+            let cognito = CognitoSettings::build().map_err(|e| {
+                error!("{:?}", e);
+                e
+            })?;
+            // Cognitoでパスワードを使わずにトークンを発行するには、カスタム認証フローが必要です
+            if oidc_enabled && cognito.custom_auth_secret.is_none() {
+                let e = SettingsError::InvalidLoadConfig("COGNITO_CUSTOM_AUTH_SECRET".to_string());
+                error!("OIDC_PROVIDERS with AUTH_BACKEND=cognito requires COGNITO_CUSTOM_AUTH_SECRET. {:?}", e);
+                return Err(e.into());
+            }
+            let cognito_client = aws_sdk_cognitoidentityprovider::Client::new(&config);
+            Arc::new(
+                CognitoAuthUserRepository::new(cognito_client, cognito.client_id, cognito.client_secret)
+                    .with_user_pool_id(Some(cognito.user_pool_id))
+                    .with_custom_auth_secret(cognito.custom_auth_secret),
+            )
+        }
+        AuthBackend::Local => {
+            let local = LocalAuthSettings::build().map_err(|e| {
+                error!("{:?}", e);
+                e
+            })?;
+            // メールを送信する`CodeSender`はまだないため、確認コードをログに出力することを明示的に許可した場合のみ起動します
+            if !local.log_codes {
+                let e = SettingsError::InvalidLoadConfig("LOCAL_AUTH_LOG_CODES".to_string());
+                error!("AUTH_BACKEND=local writes verification codes to the log. set LOCAL_AUTH_LOG_CODES=true only in development. {:?}", e);
+                return Err(e.into());
+            }
+            warn!("AUTH_BACKEND is local. verification codes are written to the log");
+            let credential_repository: Arc<dyn CredentialRepository> = match local.credential_table {
+                Some(table_name) => Arc::new(CredentialRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+                None => {
+                    warn!("LOCAL_AUTH_TABLE is not set. credentials are stored in memory");
+                    Arc::new(InMemoryCredentialRepository::new())
+                }
+            };
+            let token_issuer = LocalTokenIssuer::from_pem(
+                &std::fs::read(&local.private_key_file)?,
+                &std::fs::read(&local.public_key_file)?,
+                local.key_id,
+                jwt.issuer.clone(),
+                jwt.audience.clone(),
+            )?;
+            Arc::new(
+                LocalAuthUserRepository::new(credential_repository, Arc::new(LogCodeSender), token_issuer)
+                    .with_clock(clock.clone()),
+            )
+        }
+    };
+    let account_deletion_repository: Arc<dyn AccountDeletionRepository> = match aws.account_deletion {
+        Some(table_name) => Arc::new(AccountDeletionRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("ACCOUNT_DELETION_TABLE is not set. account deletions are stored in memory");
+            Arc::new(InMemoryAccountDeletionRepository::new())
+        }
+    };
+    let data_export_repository: Arc<dyn DataExportRepository> = match aws.data_export {
+        Some(table_name) => Arc::new(DataExportRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("DATA_EXPORT_TABLE is not set. data exports are stored in memory");
+            Arc::new(InMemoryDataExportRepository::new())
+        }
+    };
+    let security_event_repository: Arc<dyn SecurityEventRepository> = match aws.security_event {
+        Some(table_name) => Arc::new(SecurityEventRepositoryImpl::new(dynamodb_client.clone(), table_name)),
+        None => {
+            warn!("SECURITY_EVENT_TABLE is not set. security events are stored in memory");
+            Arc::new(InMemorySecurityEventRepository::new())
+        }
+    };
+    let object_storage: Arc<dyn ObjectStorage> = match storage.bucket {
+        Some(bucket) => Arc::new(S3ObjectStorage::new(aws_sdk_s3::Client::new(&config), bucket)),
+        None => {
+            warn!("STORAGE_BUCKET is not set. files are stored in {}", storage.dir);
+            Arc::new(LocalObjectStorage::new(storage.dir))
+        }
+    };
+    let user_data_tables = [
+        ("SUBSCRIBE_TABLE", aws.subscribe, "subscribe_id", "subscribe"),
+        ("CATEGORY_TABLE", aws.category, "category_id", "category"),
+        ("PAYMENT_METHOD_TABLE", aws.payment_method, "payment_method_key", "payment_method"),
+        ("PAYMENT_HISTORY_TABLE", aws.payment_history, "payment_id", "payment_history"),
+        ("NOTIFICATION_SETTING_TABLE", aws.notification_setting, "notification_setting_id", "notification_setting"),
+    ];
+    let mut user_data_repositories: Vec<Arc<dyn UserDataRepository>> = vec![];
+    for (key, table_name, sort_key, target) in user_data_tables {
+        match table_name {
+            Some(table_name) => user_data_repositories.push(Arc::new(UserDataRepositoryImpl::new(
+                dynamodb_client.clone(),
+                table_name,
+                sort_key,
+                target,
+            ))),
+            None => warn!("{} is not set. {} items are not deleted or exported with accounts", key, target),
+        }
+    }
+    let user_repository = Arc::new(UserRepositoryImpl::new(dynamodb_client, aws.auth));
+    let user_service = Arc::new(UserServiceImpl::new(user_repository.clone(), guest.ttl).with_clock(clock.clone()));
+    let data_export_service: Arc<dyn DataExportService> = Arc::new(
+        DataExportServiceImpl::new(
+            data_export_repository.clone(),
+            user_repository.clone(),
+            user_data_repositories.clone(),
+            object_storage.clone(),
+            data_export.download_ttl,
+        )
+        .with_clock(clock.clone()),
+    );
+    tokio::spawn(run_data_exports(data_export_service.clone(), data_export.job_interval));
+    let account_deletion_service: Arc<dyn AccountDeletionService> = Arc::new(
+        AccountDeletionServiceImpl::new(
+            account_deletion_repository,
+            auth_user_repository.clone(),
+            refresh_token_repository.clone(),
+            personal_access_token_repository.clone(),
+            mfa_repository.clone(),
+            external_identity_repository.clone(),
+            data_export_repository,
+            user_data_repositories,
+            object_storage.clone(),
+            user_repository.clone(),
+            account_deletion.grace_period,
+        )
+        .with_clock(clock.clone()),
+    );
+    tokio::spawn(run_account_deletions(account_deletion_service.clone(), account_deletion.job_interval));
+    let refresh_token_service: Arc<dyn RefreshTokenService> =
+        Arc::new(RefreshTokenServiceImpl::new(refresh_token_repository, REFRESH_TOKEN_TTL).with_clock(clock.clone()));
+    let login_attempt_service: Arc<dyn LoginAttemptService> = Arc::new(
+        LoginAttemptServiceImpl::new(login_attempt_repository, login_attempt.email_policy, login_attempt.ip_policy)
+            .with_clock(clock.clone()),
+    );
+    let mfa_service: Arc<dyn MfaService> = Arc::new(
+        MfaServiceImpl::new(mfa_repository, mfa.issuer, &mfa_encryption_key, MFA_CHALLENGE_TTL)
+            .with_clock(clock.clone()),
+    );
+    let personal_access_token_service: Arc<dyn PersonalAccessTokenService> = Arc::new(
+        PersonalAccessTokenServiceImpl::new(personal_access_token_repository, PERSONAL_ACCESS_TOKEN_MAX_TTL)
+            .with_clock(clock.clone()),
+    );
+    let breached_password_repository = match password.breached_password_file {
+        Some(path) => {
+            let repository = LocalBreachedPasswordRepository::from_file(&path).map_err(|e| {
+                error!("{:?}", e);
+                e
+            })?;
+            info!("loaded {} breached password hashes from {}", repository.len(), path);
+            repository
+        }
+        None => {
+            warn!("BREACHED_PASSWORD_FILE is not set. passwords are not checked against breached passwords");
+            LocalBreachedPasswordRepository::new()
+        }
+    };
+    let password_service: Arc<dyn PasswordService> =
+        Arc::new(PasswordServiceImpl::new(password.policy, Arc::new(breached_password_repository)));
+    let jwt_validator = Arc::new(JwtValidator::new(JwksCache::new(jwt.jwks_source()), jwt.issuer, jwt.audience));
+    let state = AppState::new(
+        auth_user_repository,
+        user_service,
+        refresh_token_service,
+        jwt_validator,
+        revoked_token_repository,
+        login_attempt_service,
+        mfa_service,
+        oidc_providers,
+        external_identity_repository,
+        personal_access_token_service,
+        password_service,
+        account_deletion_service,
+        data_export_service,
+        Arc::new(SecurityEventServiceImpl::new(security_event_repository).with_clock(clock.clone())),
+        Arc::new(ProfileIconServiceImpl::new(user_repository, object_storage)),
+    )
+    .with_trusted_proxy_hops(api.trusted_proxy_hops)
+    .with_clock(clock);
+    if api.trusted_proxy_hops == 0 {
+        warn!("TRUSTED_PROXY_HOPS is not set. X-Forwarded-For is ignored for client IP addresses");
+    }
+
+    let listener = tokio::net::TcpListener::bind(socket_addr_v4).await?;
+    info!("listening on {}", socket_addr_v4);
+    let rate_limiter = Arc::new(RateLimiter::new(rate_limit));
+    axum::serve(listener, router(state, rate_limiter).into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
     Ok(())
 }
+
+/// 猶予期間が過ぎたアカウントの削除を`interval`ごとに開始または再開します
+///
+/// 削除の進捗は保存しているため、中断したサーバーとは別のインスタンスでも再開できます
+async fn run_account_deletions(service: Arc<dyn AccountDeletionService>, interval: std::time::Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        match service.run_due().await {
+            Result::Ok(0) => {}
+            Result::Ok(v) => info!("{} accounts deleted", v),
+            Err(e) => error!("failed to run account deletions. {:?}", e),
+        }
+    }
+}
+
+/// 依頼されたデータのエクスポートを`interval`ごとに実行します
+///
+/// 中断したエクスポートは最初から作り直します
+async fn run_data_exports(service: Arc<dyn DataExportService>, interval: std::time::Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        match service.run_pending().await {
+            Result::Ok(0) => {}
+            Result::Ok(v) => info!("{} data exports completed", v),
+            Err(e) => error!("failed to run data exports. {:?}", e),
+        }
+    }
+}
+
+/// SIGTERMまたはCtrl+Cを受け取るまで待機します
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("failed to install Ctrl+C handler");
+    };
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("shutdown signal received");
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Body,
+    extract::{FromRef, FromRequestParts},
+    http::{header::AUTHORIZATION, Request},
+    response::{IntoResponse, Response},
+};
+use domain::user::user_id::UserId;
+use tower::{Layer, Service};
+
+use crate::{
+    auth::{
+        authenticated_user::AuthenticatedUser,
+        client_ip::{ClientIp, TrustedProxyHops},
+    },
+    error::ApiError,
+    RateLimitSettings,
+};
+
+/// 保持するバケット数の上限です
+///
+/// 上限に達した場合は満杯のバケットを破棄し、それでも足りない場合は最後に使われてから最も時間が経ったバケットを破棄します
+const MAX_BUCKETS: usize = 10_000;
+/// 満杯でないバケットを破棄する場合に、まとめて破棄する数です。リクエストごとに並べ替えないよう余裕を残します
+const EVICTION_BATCH: usize = MAX_BUCKETS / 10;
+
+/// トークンバケットの容量と、容量分のトークンが補充されるまでの時間です
+///
+/// 環境変数では`容量/秒数`の形式で指定します。`60/60`は1分あたり60リクエストです
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl Limit {
+    fn refill_per_second(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+impl std::str::FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (capacity, period) = s.split_once('/').ok_or_else(|| format!("invalid limit: {}", s))?;
+        let capacity: u32 = capacity.trim().parse().map_err(|_| format!("invalid capacity: {}", s))?;
+        let period: u64 = period.trim().parse().map_err(|_| format!("invalid period: {}", s))?;
+        if capacity == 0 || period == 0 {
+            return Err(format!("limit must be positive: {}", s));
+        }
+        Ok(Self { capacity, period: Duration::from_secs(period) })
+    }
+}
+
+/// 同じ制限を適用するルートのまとまりです
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// `/auth/*`です。総当たり攻撃を防ぐため、他より厳しく制限します
+    Auth,
+    Api,
+}
+
+impl RouteGroup {
+    pub fn from_path(path: &str) -> Self {
+        match path == "/auth" || path.starts_with("/auth/") {
+            true => Self::Auth,
+            false => Self::Api,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::Api => "api",
+        }
+    }
+}
+
+/// ルートグループごとの接続元IPアドレスと認証ユーザーの制限です
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteLimits {
+    pub ip: Limit,
+    pub user: Limit,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &Limit, now: Instant) -> Self {
+        Self { tokens: limit.capacity as f64, updated_at: now }
+    }
+
+    fn is_full(&self, limit: &Limit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens + elapsed * limit.refill_per_second() >= limit.capacity as f64
+    }
+
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_second()).min(limit.capacity as f64);
+        self.updated_at = now;
+    }
+
+    /// 1リクエスト分のトークンが補充されるまでの秒数です
+    fn retry_after(&self, limit: &Limit) -> u64 {
+        ((1.0 - self.tokens) / limit.refill_per_second()).ceil().max(1.0) as u64
+    }
+}
+
+/// 接続元IPアドレスと認証ユーザーごとのトークンバケットです
+///
+/// プロセスごとに管理するため、複数台で動かす場合の上限は台数倍になります
+#[derive(Debug)]
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<String, (Bucket, Limit)>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self { settings, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// 該当するすべてのバケットにトークンが残っている場合のみ消費します
+    ///
+    /// 制限を超えた場合は、リクエストできるようになるまでの秒数を返します
+    pub fn check(&self, ip: &str, user_id: Option<&UserId>, group: RouteGroup, now: Instant) -> Result<(), u64> {
+        let limits = self.limits(group);
+        let mut keys = vec![(ip_key(group, ip), limits.ip)];
+        if let Some(user_id) = user_id {
+            keys.push((format!("{}#user#{}", group.name(), user_id), limits.user));
+        }
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() + keys.len() > MAX_BUCKETS {
+            evict(&mut buckets, keys.len(), now);
+        }
+
+        let mut retry_after = None;
+        for (key, limit) in &keys {
+            let (bucket, _) = buckets.entry(key.clone()).or_insert_with(|| (Bucket::full(limit, now), *limit));
+            bucket.refill(limit, now);
+            if bucket.tokens < 1.0 {
+                retry_after = retry_after.max(Some(bucket.retry_after(limit)));
+            }
+        }
+        if let Some(v) = retry_after {
+            tracing::warn!(
+                "rate limited. keys: {:?}, retry_after: {}",
+                keys.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+                v
+            );
+            return Err(v);
+        }
+
+        for (key, _) in &keys {
+            if let Some((bucket, _)) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// 接続元IPアドレスのバケットにトークンが残っているかを、消費せずに確認します
+    ///
+    /// 認証ユーザーのトークンを検証する前に呼び、制限を超えた接続元のリクエストでは署名を検証しないようにします
+    pub fn check_ip(&self, ip: &str, group: RouteGroup, now: Instant) -> Result<(), u64> {
+        let limit = self.limits(group).ip;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        match buckets.get_mut(&ip_key(group, ip)) {
+            Some((bucket, _)) => {
+                bucket.refill(&limit, now);
+                match bucket.tokens < 1.0 {
+                    true => Err(bucket.retry_after(&limit)),
+                    false => Ok(()),
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn limits(&self, group: RouteGroup) -> RouteLimits {
+        match group {
+            RouteGroup::Auth => self.settings.auth,
+            RouteGroup::Api => self.settings.api,
+        }
+    }
+}
+
+fn ip_key(group: RouteGroup, ip: &str) -> String {
+    format!("{}#ip#{}", group.name(), ip)
+}
+
+/// `additional`個のバケットを追加できるよう、バケット数を`MAX_BUCKETS`未満に減らします
+///
+/// 偽装した接続元から大量にリクエストされても、使用中のバケットでメモリを使い切らないようにします
+fn evict(buckets: &mut HashMap<String, (Bucket, Limit)>, additional: usize, now: Instant) {
+    buckets.retain(|_, (bucket, limit)| !bucket.is_full(limit, now));
+    if buckets.len() + additional <= MAX_BUCKETS {
+        return;
+    }
+
+    // 同じ時刻に使われたバケットがあっても`count`個だけ破棄するよう、キーを選んで削除します
+    let count = EVICTION_BATCH.max(buckets.len() + additional - MAX_BUCKETS).min(buckets.len());
+    let mut updated_at = buckets.iter().map(|(key, (bucket, _))| (bucket.updated_at, key.clone())).collect::<Vec<_>>();
+    updated_at.select_nth_unstable(count - 1);
+    for (_, key) in &updated_at[..count] {
+        buckets.remove(key);
+    }
+    tracing::warn!("rate limit buckets evicted. remaining: {}", buckets.len());
+}
+
+/// ルーターに`RateLimiter`を適用するレイヤーです
+///
+/// `Authorization`ヘッダーのトークンが有効な場合は認証ユーザーの制限も適用します
+/// 無効なトークンはここでは拒否せず、接続元IPアドレスの制限のみを適用します
+#[derive(Clone)]
+pub struct RateLimitLayer<S> {
+    limiter: Arc<RateLimiter>,
+    state: S,
+}
+
+impl<S> RateLimitLayer<S> {
+    pub fn new(limiter: Arc<RateLimiter>, state: S) -> Self {
+        Self { limiter, state }
+    }
+}
+
+impl<I, S: Clone> Layer<I> for RateLimitLayer<S> {
+    type Service = RateLimit<I, S>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        RateLimit { inner, limiter: self.limiter.clone(), state: self.state.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<I, S> {
+    inner: I,
+    limiter: Arc<RateLimiter>,
+    state: S,
+}
+
+impl<I, S> Service<Request<Body>> for RateLimit<I, S>
+where
+    I: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    I::Future: Send + 'static,
+    S: Clone + Send + Sync + 'static,
+    AuthenticatedUser: FromRequestParts<S>,
+    TrustedProxyHops: FromRef<S>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // poll_readyで準備済みのサービスを使い、次の呼び出しには複製を残します
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let Ok(ClientIp(ip)) = ClientIp::from_request_parts(&mut parts, &state).await;
+            let group = RouteGroup::from_path(parts.uri.path());
+            if let Err(retry_after) = limiter.check_ip(&ip, group, Instant::now()) {
+                return Ok(ApiError::TooManyRequests(retry_after).into_response());
+            }
+            let user = match parts.headers.contains_key(AUTHORIZATION) {
+                true => AuthenticatedUser::from_request_parts(&mut parts, &state).await.ok(),
+                false => None,
+            };
+
+            if let Err(retry_after) = limiter.check(&ip, user.as_ref().map(|v| &v.user_id), group, Instant::now()) {
+                return Ok(ApiError::TooManyRequests(retry_after).into_response());
+            }
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const IP: &str = "192.0.2.1";
+
+    fn limit(capacity: u32, period: u64) -> Limit {
+        Limit { capacity, period: Duration::from_secs(period) }
+    }
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitSettings {
+            auth: RouteLimits { ip: limit(2, 10), user: limit(2, 10) },
+            api: RouteLimits { ip: limit(5, 10), user: limit(3, 10) },
+        })
+    }
+
+    #[test]
+    fn test_limit_from_str() {
+        assert_eq!(Ok(limit(60, 60)), Limit::from_str("60/60"));
+        assert_eq!(Ok(limit(10, 1)), Limit::from_str(" 10 / 1 "));
+
+        let test_cases = vec![
+            "", "60", "a/60", "60/b", "0/60", "60/0", "-1/60",
+        ];
+        for value in test_cases {
+            assert!(Limit::from_str(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_route_group_from_path() {
+        let test_cases = vec![
+            ("/auth/login", RouteGroup::Auth),
+            ("/auth", RouteGroup::Auth),
+            ("/authors", RouteGroup::Api),
+            ("/users/me", RouteGroup::Api),
+        ];
+        for (path, expected) in test_cases {
+            assert_eq!(expected, RouteGroup::from_path(path));
+        }
+    }
+
+    #[test]
+    fn test_check_by_ip_and_route_group() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            assert_eq!(Ok(()), limiter.check(IP, None, RouteGroup::Auth, now));
+        }
+        assert_eq!(Err(5), limiter.check(IP, None, RouteGroup::Auth, now));
+
+        // ルートグループと接続元ごとに別のバケットを使います
+        assert_eq!(Ok(()), limiter.check(IP, None, RouteGroup::Api, now));
+        assert_eq!(Ok(()), limiter.check("198.51.100.1", None, RouteGroup::Auth, now));
+
+        // 5秒で1リクエスト分補充されます
+        assert_eq!(Err(1), limiter.check(IP, None, RouteGroup::Auth, now + Duration::from_secs(4)));
+        assert_eq!(Ok(()), limiter.check(IP, None, RouteGroup::Auth, now + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_check_by_user() {
+        let limiter = limiter();
+        let now = Instant::now();
+        let user_id = UserId::new();
+
+        for i in 0..3 {
+            assert_eq!(Ok(()), limiter.check(&format!("192.0.2.{}", i), Some(&user_id), RouteGroup::Api, now));
+        }
+        assert!(limiter.check("192.0.2.10", Some(&user_id), RouteGroup::Api, now).is_err());
+        assert_eq!(Ok(()), limiter.check("192.0.2.10", Some(&UserId::new()), RouteGroup::Api, now));
+
+        // 拒否したリクエストでは接続元のトークンを消費しません
+        let ip = "198.51.100.1";
+        for _ in 0..4 {
+            let _ = limiter.check(ip, Some(&user_id), RouteGroup::Api, now);
+        }
+        for _ in 0..5 {
+            assert_eq!(Ok(()), limiter.check(ip, None, RouteGroup::Api, now));
+        }
+    }
+
+    #[test]
+    fn test_check_ip() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        // 確認だけではトークンを消費しません
+        assert_eq!(Ok(()), limiter.check_ip(IP, RouteGroup::Auth, now));
+        for _ in 0..2 {
+            assert_eq!(Ok(()), limiter.check_ip(IP, RouteGroup::Auth, now));
+            assert_eq!(Ok(()), limiter.check(IP, None, RouteGroup::Auth, now));
+        }
+        assert_eq!(Err(5), limiter.check_ip(IP, RouteGroup::Auth, now));
+        assert_eq!(Ok(()), limiter.check_ip(IP, RouteGroup::Api, now));
+        assert_eq!(Ok(()), limiter.check_ip(IP, RouteGroup::Auth, now + Duration::from_secs(5)));
+    }
+
+    /// 同じ時刻に使われたバケットでも、破棄するのは`EVICTION_BATCH`個のみです
+    #[test]
+    fn test_eviction_batch_with_same_updated_at() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for i in 0..MAX_BUCKETS + 1 {
+            limiter.check(&i.to_string(), None, RouteGroup::Api, now).unwrap();
+        }
+        assert_eq!(MAX_BUCKETS - EVICTION_BATCH + 1, limiter.buckets.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_full_buckets_are_discarded() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for i in 0..MAX_BUCKETS {
+            limiter.check(&i.to_string(), None, RouteGroup::Api, now).unwrap();
+        }
+        limiter.check(IP, None, RouteGroup::Api, now + Duration::from_secs(10)).unwrap();
+        assert_eq!(1, limiter.buckets.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_oldest_buckets_are_evicted() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        // 使用中のバケットは満杯にならないため、古いものから破棄します
+        for i in 0..MAX_BUCKETS * 2 {
+            limiter.check(&i.to_string(), None, RouteGroup::Api, now + Duration::from_micros(i as u64)).unwrap();
+            assert!(limiter.buckets.lock().unwrap().len() <= MAX_BUCKETS);
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() > MAX_BUCKETS - EVICTION_BATCH);
+        assert!(!buckets.contains_key("api#ip#0"));
+        assert!(buckets.contains_key(&format!("api#ip#{}", MAX_BUCKETS * 2 - 1)));
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// 値を伏せるフィールド名です。大文字と小文字を区別しません
+const SECRET_FIELDS: [&str; 14] = [
+    "token",
+    "jwt",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "upstream_refresh_token",
+    "authorization",
+    "cookie",
+    "client_secret",
+    "code_verifier",
+    "verification_code",
+    "recovery_code",
+    "otp",
+    "api_key",
+];
+
+/// 名前に含まれている場合に値を伏せる語です
+const SECRET_FIELD_PARTS: [&str; 2] = [
+    "password", "secret",
+];
+
+/// JSON形式のログから、秘密の値を持つフィールドを伏せる`FormatEvent`です
+///
+/// イベントとスパンのフィールドを名前で判定します
+/// 型で値を伏せられない`String`などのフィールドが、誤ってログに出力されるのを防ぎます
+#[derive(Debug, Clone)]
+pub struct RedactSecrets<F> {
+    inner: F,
+}
+
+impl<F> RedactSecrets<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for RedactSecrets<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        let mut buffer = String::new();
+        self.inner.format_event(ctx, Writer::new(&mut buffer), event)?;
+        writer.write_str(&redact_line(&buffer))
+    }
+}
+
+/// JSONとして解釈できない行はそのまま返します
+fn redact_line(line: &str) -> String {
+    match serde_json::from_str::<Value>(line.trim_end()) {
+        Ok(mut value) => {
+            redact(&mut value);
+            format!("{}\n", value)
+        }
+        Err(_) => line.to_string(),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match is_secret_field(key) {
+                    true => *value = Value::String(REDACTED.to_string()),
+                    false => redact(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRET_FIELDS.contains(&name.as_str()) || SECRET_FIELD_PARTS.iter().any(|v| name.contains(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::{fmt::format::JsonFields, layer::SubscriberExt};
+
+    use super::*;
+
+    #[test]
+    fn test_is_secret_field() {
+        let test_cases = vec![
+            ("password", true),
+            ("new_password", true),
+            ("Authorization", true),
+            ("refresh_token", true),
+            ("mfa_secret", true),
+            ("code", false),
+            ("token_id", false),
+            ("user_id", false),
+            ("message", false),
+        ];
+        for (name, expected) in test_cases {
+            assert_eq!(expected, is_secret_field(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_redact_line() {
+        let line = r#"{"fields":{"message":"login","password":"Password123","code":"AUTH_001"},"spans":[{"refresh_token":"abc","name":"request"}]}"#;
+
+        let result: Value = serde_json::from_str(&redact_line(line)).unwrap();
+        assert_eq!(REDACTED, result["fields"]["password"]);
+        assert_eq!("login", result["fields"]["message"]);
+        assert_eq!("AUTH_001", result["fields"]["code"]);
+        assert_eq!(REDACTED, result["spans"][0]["refresh_token"]);
+        assert_eq!("request", result["spans"][0]["name"]);
+
+        assert_eq!("plain text\n", redact_line("plain text\n"));
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_redact_secrets_layer() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(RedactSecrets::new(tracing_subscriber::fmt::format().json()))
+                .with_writer(move || writer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(password = "Password123", user_id = "usr_1", "sign in");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("Password123"));
+        let result: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(REDACTED, result["fields"]["password"]);
+        assert_eq!("usr_1", result["fields"]["user_id"]);
+    }
+}
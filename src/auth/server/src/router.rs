@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
+    Router,
+};
+use domain::profile_icon::ProfileIcon;
+
+use crate::{
+    handler::{
+        account_deletion_handler, auth_handler, data_export_handler, personal_access_token_handler,
+        security_event_handler, session_handler, user_handler,
+    },
+    rate_limit::{RateLimitLayer, RateLimiter},
+    state::AppState,
+};
+
+pub fn router(state: AppState, rate_limiter: Arc<RateLimiter>) -> Router {
+    Router::new()
+        .nest("/auth", auth_router())
+        .nest("/users", user_router())
+        .nest("/me", me_router())
+        .nest("/admin", admin_router())
+        .layer(RateLimitLayer::new(rate_limiter, state.clone()))
+        .with_state(state)
+}
+
+fn auth_router() -> Router<AppState> {
+    Router::new()
+        .route("/signup", post(auth_handler::sign_up))
+        .route("/verify", post(auth_handler::verify))
+        .route("/login", post(auth_handler::login))
+        .route("/login/mfa", post(auth_handler::login_mfa))
+        .route("/guest", post(auth_handler::guest))
+        .route("/guest/upgrade", post(auth_handler::upgrade_guest))
+        .route("/logout", post(auth_handler::logout))
+        .route("/refresh-token", post(auth_handler::refresh_token))
+        .route("/password/forgot", post(auth_handler::forgot_password))
+        .route("/password/reset", post(auth_handler::reset_password))
+        .route("/email/change", post(auth_handler::change_email))
+        .route("/email/change/confirm", post(auth_handler::confirm_email_change))
+        .route("/mfa/enroll", post(auth_handler::enroll_mfa))
+        .route("/mfa/activate", post(auth_handler::activate_mfa))
+        .route("/mfa/disable", post(auth_handler::disable_mfa))
+        .route("/oidc/:provider/authorize", get(auth_handler::oidc_authorize))
+        .route("/oidc/:provider/callback", post(auth_handler::oidc_callback))
+        .route(
+            "/tokens",
+            post(personal_access_token_handler::issue_token).get(personal_access_token_handler::list_tokens),
+        )
+        .route("/tokens/:token_id", delete(personal_access_token_handler::revoke_token))
+        .route("/sessions", get(session_handler::list_sessions).delete(session_handler::revoke_all_sessions))
+        .route("/sessions/:session_id", delete(session_handler::revoke_session))
+        .route(
+            "/account/deletion",
+            post(account_deletion_handler::request_deletion)
+                .get(account_deletion_handler::find_deletion)
+                .delete(account_deletion_handler::cancel_deletion),
+        )
+        .route("/account/deletion/:deletion_id", get(account_deletion_handler::find_receipt))
+        .route("/account/exports", post(data_export_handler::request_export).get(data_export_handler::list_exports))
+        .route("/account/exports/:export_id", get(data_export_handler::find_export))
+        .route("/account/exports/:export_id/download-token", post(data_export_handler::issue_download_token))
+        .route("/account/exports/download/:token", get(data_export_handler::download_export))
+}
+
+fn user_router() -> Router<AppState> {
+    Router::new()
+        .route("/me/icon", put(user_handler::upload_icon).layer(DefaultBodyLimit::max(ProfileIcon::MAX_BYTES)))
+        .route("/:user_id", get(user_handler::find_user))
+        .route("/:user_id/role", put(user_handler::change_role))
+}
+
+fn me_router() -> Router<AppState> {
+    Router::new().route("/security-events", get(security_event_handler::list_my_events))
+}
+
+fn admin_router() -> Router<AppState> {
+    Router::new().route("/security-events", get(security_event_handler::list_events))
+}
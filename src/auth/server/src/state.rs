@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use application::services::{
+    AccountDeletionService, DataExportService, LoginAttemptService, MfaService, PasswordService,
+    PersonalAccessTokenService, ProfileIconService, RefreshTokenService, SecurityEventService, UserService,
+};
+use axum::extract::FromRef;
+use domain::{
+    clock::{Clock, SystemClock},
+    repository::{
+        auth_user_repository::AuthUserRepository, external_identity_repository::ExternalIdentityRepository,
+        revoked_token_repository::RevokedTokenRepository,
+    },
+};
+
+use crate::auth::{client_ip::TrustedProxyHops, jwt_validator::JwtValidator, oidc::OidcProviders};
+
+/// ハンドラー間で共有する状態です
+#[derive(Clone)]
+pub struct AppState {
+    pub(crate) auth_user_repository: Arc<dyn AuthUserRepository>,
+    pub(crate) user_service: Arc<dyn UserService>,
+    pub(crate) refresh_token_service: Arc<dyn RefreshTokenService>,
+    pub(crate) jwt_validator: Arc<JwtValidator>,
+    pub(crate) revoked_token_repository: Arc<dyn RevokedTokenRepository>,
+    pub(crate) login_attempt_service: Arc<dyn LoginAttemptService>,
+    pub(crate) mfa_service: Arc<dyn MfaService>,
+    pub(crate) oidc_providers: Arc<OidcProviders>,
+    pub(crate) external_identity_repository: Arc<dyn ExternalIdentityRepository>,
+    pub(crate) personal_access_token_service: Arc<dyn PersonalAccessTokenService>,
+    pub(crate) password_service: Arc<dyn PasswordService>,
+    pub(crate) account_deletion_service: Arc<dyn AccountDeletionService>,
+    pub(crate) data_export_service: Arc<dyn DataExportService>,
+    pub(crate) security_event_service: Arc<dyn SecurityEventService>,
+    pub(crate) profile_icon_service: Arc<dyn ProfileIconService>,
+    pub(crate) trusted_proxy_hops: TrustedProxyHops,
+    pub(crate) clock: Arc<dyn Clock>,
+}
+
+impl AppState {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        auth_user_repository: Arc<dyn AuthUserRepository>, user_service: Arc<dyn UserService>,
+        refresh_token_service: Arc<dyn RefreshTokenService>, jwt_validator: Arc<JwtValidator>,
+        revoked_token_repository: Arc<dyn RevokedTokenRepository>, login_attempt_service: Arc<dyn LoginAttemptService>,
+        mfa_service: Arc<dyn MfaService>, oidc_providers: Arc<OidcProviders>,
+        external_identity_repository: Arc<dyn ExternalIdentityRepository>,
+        personal_access_token_service: Arc<dyn PersonalAccessTokenService>, password_service: Arc<dyn PasswordService>,
+        account_deletion_service: Arc<dyn AccountDeletionService>, data_export_service: Arc<dyn DataExportService>,
+        security_event_service: Arc<dyn SecurityEventService>, profile_icon_service: Arc<dyn ProfileIconService>,
+    ) -> Self {
+        Self {
+            auth_user_repository,
+            user_service,
+            refresh_token_service,
+            jwt_validator,
+            revoked_token_repository,
+            login_attempt_service,
+            mfa_service,
+            oidc_providers,
+            external_identity_repository,
+            personal_access_token_service,
+            password_service,
+            account_deletion_service,
+            data_export_service,
+            security_event_service,
+            profile_icon_service,
+            trusted_proxy_hops: TrustedProxyHops::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// `X-Forwarded-For`から接続元IPアドレスを取り出す、信頼できるプロキシの段数を設定した値を返します
+    pub fn with_trusted_proxy_hops(self, hops: usize) -> Self {
+        Self { trusted_proxy_hops: TrustedProxyHops(hops), ..self }
+    }
+
+    /// イベントの発生日時などハンドラーで使う時刻の取得元を差し替えた値を返します
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+}
+
+impl FromRef<AppState> for Arc<JwtValidator> {
+    fn from_ref(input: &AppState) -> Self {
+        input.jwt_validator.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn RevokedTokenRepository> {
+    fn from_ref(input: &AppState) -> Self {
+        input.revoked_token_repository.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn UserService> {
+    fn from_ref(input: &AppState) -> Self {
+        input.user_service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn PersonalAccessTokenService> {
+    fn from_ref(input: &AppState) -> Self {
+        input.personal_access_token_service.clone()
+    }
+}
+
+impl FromRef<AppState> for TrustedProxyHops {
+    fn from_ref(input: &AppState) -> Self {
+        input.trusted_proxy_hops
+    }
+}